@@ -1,4 +1,8 @@
-use rfvp_core::format::scenario::Nls;
+use rfvp_core::format::bytes::{
+    write_f32_le, write_i16_le, write_i32_le, write_u16_le, write_u32_le,
+};
+use rfvp_core::format::scenario::instructions::Opcode;
+use rfvp_core::format::scenario::{split_string_literal, Nls};
 
 pub trait Inst {
     fn address(&self) -> u32;
@@ -28,7 +32,7 @@ impl Inst for NopInst {
     }
 
     fn serialize_to_binary(&self) -> Vec<u8> {
-        vec![0x00]
+        vec![Opcode::Nop as u8]
     }
 
     fn size(&self) -> u32 {
@@ -62,7 +66,7 @@ impl Inst for InitStackInst {
     }
 
     fn serialize_to_binary(&self) -> Vec<u8> {
-        vec![0x01, self.arg_count, self.locals_count]
+        vec![Opcode::InitStack as u8, self.arg_count, self.locals_count]
     }
 
     fn size(&self) -> u32 {
@@ -102,8 +106,8 @@ impl Inst for CallInst {
     }
 
     fn serialize_to_binary(&self) -> Vec<u8> {
-        let mut bytes = vec![0x02];
-        bytes.extend_from_slice(&self.func_address.to_le_bytes());
+        let mut bytes = vec![Opcode::Call as u8];
+        write_u32_le(&mut bytes, self.func_address);
         bytes
     }
 
@@ -136,8 +140,8 @@ impl Inst for SyscallInst {
     }
 
     fn serialize_to_binary(&self) -> Vec<u8> {
-        let mut bytes = vec![0x03];
-        bytes.extend_from_slice(&self.syscall_id.to_le_bytes());
+        let mut bytes = vec![Opcode::Syscall as u8];
+        write_u16_le(&mut bytes, self.syscall_id);
         bytes
     }
 
@@ -167,7 +171,7 @@ impl Inst for RetInst {
     }
 
     fn serialize_to_binary(&self) -> Vec<u8> {
-        vec![0x04]
+        vec![Opcode::Ret as u8]
     }
 
     fn size(&self) -> u32 {
@@ -196,7 +200,7 @@ impl Inst for RetVInst {
     }
 
     fn serialize_to_binary(&self) -> Vec<u8> {
-        vec![0x05]
+        vec![Opcode::RetV as u8]
     }
 
     fn size(&self) -> u32 {
@@ -236,8 +240,8 @@ impl Inst for JmpInst {
     }
 
     fn serialize_to_binary(&self) -> Vec<u8> {
-        let mut bytes = vec![0x06];
-        bytes.extend_from_slice(&self.target_address.to_le_bytes());
+        let mut bytes = vec![Opcode::Jmp as u8];
+        write_u32_le(&mut bytes, self.target_address);
         bytes
     }
 
@@ -278,8 +282,8 @@ impl Inst for JzInst {
     }
 
     fn serialize_to_binary(&self) -> Vec<u8> {
-        let mut bytes = vec![0x07];
-        bytes.extend_from_slice(&self.target_address.to_le_bytes());
+        let mut bytes = vec![Opcode::Jz as u8];
+        write_u32_le(&mut bytes, self.target_address);
         bytes
     }
 
@@ -309,7 +313,7 @@ impl Inst for PushNilInst {
     }
 
     fn serialize_to_binary(&self) -> Vec<u8> {
-        vec![0x08]
+        vec![Opcode::PushNil as u8]
     }
 
     fn size(&self) -> u32 {
@@ -338,7 +342,7 @@ impl Inst for PushTrueInst {
     }
 
     fn serialize_to_binary(&self) -> Vec<u8> {
-        vec![0x09]
+        vec![Opcode::PushTrue as u8]
     }
 
     fn size(&self) -> u32 {
@@ -367,8 +371,8 @@ impl Inst for PushI32Inst {
     }
 
     fn serialize_to_binary(&self) -> Vec<u8> {
-        let mut bytes = vec![0x0A];
-        bytes.extend_from_slice(&self.value.to_le_bytes());
+        let mut bytes = vec![Opcode::PushI32 as u8];
+        write_i32_le(&mut bytes, self.value);
         bytes
     }
 
@@ -398,8 +402,8 @@ impl Inst for PushI16Inst {
     }
 
     fn serialize_to_binary(&self) -> Vec<u8> {
-        let mut bytes = vec![0x0B];
-        bytes.extend_from_slice(&self.value.to_le_bytes());
+        let mut bytes = vec![Opcode::PushI16 as u8];
+        write_i16_le(&mut bytes, self.value);
         bytes
     }
 
@@ -429,7 +433,7 @@ impl Inst for PushI8Inst {
     }
 
     fn serialize_to_binary(&self) -> Vec<u8> {
-        vec![0x0C, self.value as u8]
+        vec![Opcode::PushI8 as u8, self.value as u8]
     }
 
     fn size(&self) -> u32 {
@@ -458,8 +462,8 @@ impl Inst for PushF32Inst {
     }
 
     fn serialize_to_binary(&self) -> Vec<u8> {
-        let mut bytes = vec![0x0D];
-        bytes.extend_from_slice(&self.value.to_le_bytes());
+        let mut bytes = vec![Opcode::PushF32 as u8];
+        write_f32_le(&mut bytes, self.value);
         bytes
     }
 
@@ -471,30 +475,33 @@ impl Inst for PushF32Inst {
 pub struct PushStringInst {
     address: u32,
     content: String,
-    content_blob: Vec<u8>,
+    /// literals longer than a single PushString are split into pieces,
+    /// which are pushed and concatenated with Add
+    content_blobs: Vec<Vec<u8>>,
     nls: Nls,
 }
 
 impl PushStringInst {
     pub fn new(content: String, nls: Nls) -> Self {
+        let content_blobs = split_string_literal(&content, &nls)
+            .iter()
+            .map(|piece| Self::string_to_blob(piece, nls.clone()))
+            .collect();
+
         Self {
             address: 0,
-            content: content.clone(),
-            content_blob: Self::string_to_blob(&content, nls.clone()),
+            content,
+            content_blobs,
             nls,
         }
     }
 
     fn string_to_blob(content: &str, nls: Nls) -> Vec<u8> {
         // convert utf-8 string to local string via Nls
-        let mut content_bytes = match nls {
-            Nls::GBK => encoding_rs::GBK.encode(content).0.to_vec(),
-            Nls::ShiftJIS => encoding_rs::SHIFT_JIS.encode(content).0.to_vec(),
-            Nls::UTF8 => content.as_bytes().to_vec(),
-        };
-
-        if !content_bytes.ends_with(&[0]) {
-            content_bytes.push(0);
+        let mut content_bytes = nls.encode(content);
+
+        if !content_bytes.ends_with(nls.terminator()) {
+            content_bytes.extend_from_slice(nls.terminator());
         }
 
         content_bytes
@@ -511,17 +518,26 @@ impl Inst for PushStringInst {
     }
 
     fn serialize_to_binary(&self) -> Vec<u8> {
-        let mut bytes = vec![0x0E];
-        if self.content_blob.len() > 0xFF {
-            panic!("String too long");
+        let mut bytes = Vec::new();
+        for (i, blob) in self.content_blobs.iter().enumerate() {
+            if blob.len() > 0xFF {
+                panic!("String too long");
+            }
+            bytes.push(Opcode::PushString as u8);
+            bytes.push(blob.len() as u8);
+            bytes.extend_from_slice(blob);
+            if i > 0 {
+                // add
+                bytes.push(Opcode::Add as u8);
+            }
         }
-        bytes.push(self.content_blob.len() as u8);
-        bytes.extend_from_slice(&self.content_blob);
         bytes
     }
 
     fn size(&self) -> u32 {
-        self.content_blob.len() as u32 + 2
+        let pieces = self.content_blobs.len() as u32;
+        let blobs = self.content_blobs.iter().map(|blob| blob.len() as u32 + 2).sum::<u32>();
+        blobs + pieces.saturating_sub(1)
     }
 }
 
@@ -534,6 +550,10 @@ impl PushGlobalInst {
     pub fn new(idx: u16) -> Self {
         Self { address: 0, idx }
     }
+
+    pub fn get_idx(&self) -> u16 {
+        self.idx
+    }
 }
 
 impl Inst for PushGlobalInst {
@@ -546,8 +566,8 @@ impl Inst for PushGlobalInst {
     }
 
     fn serialize_to_binary(&self) -> Vec<u8> {
-        let mut bytes = vec![0x0F];
-        bytes.extend_from_slice(&self.idx.to_le_bytes());
+        let mut bytes = vec![Opcode::PushGlobal as u8];
+        write_u16_le(&mut bytes, self.idx);
         bytes
     }
 
@@ -577,7 +597,7 @@ impl Inst for PushStackInst {
     }
 
     fn serialize_to_binary(&self) -> Vec<u8> {
-        vec![0x10, self.idx as u8]
+        vec![Opcode::PushStack as u8, self.idx as u8]
     }
 
     fn size(&self) -> u32 {
@@ -594,6 +614,10 @@ impl PushGlobalTableInst {
     pub fn new(idx: u16) -> Self {
         Self { address: 0, idx }
     }
+
+    pub fn get_idx(&self) -> u16 {
+        self.idx
+    }
 }
 
 impl Inst for PushGlobalTableInst {
@@ -606,8 +630,8 @@ impl Inst for PushGlobalTableInst {
     }
 
     fn serialize_to_binary(&self) -> Vec<u8> {
-        let mut bytes = vec![0x11];
-        bytes.extend_from_slice(&self.idx.to_le_bytes());
+        let mut bytes = vec![Opcode::PushGlobalTable as u8];
+        write_u16_le(&mut bytes, self.idx);
         bytes
     }
 
@@ -637,7 +661,7 @@ impl Inst for PushLocalTableInst {
     }
 
     fn serialize_to_binary(&self) -> Vec<u8> {
-        vec![0x12, self.idx as u8]
+        vec![Opcode::PushLocalTable as u8, self.idx as u8]
     }
 
     fn size(&self) -> u32 {
@@ -665,7 +689,7 @@ impl Inst for PushTopInst {
     }
 
     fn serialize_to_binary(&self) -> Vec<u8> {
-        vec![0x13]
+        vec![Opcode::PushTop as u8]
     }
 
     fn size(&self) -> u32 {
@@ -693,7 +717,7 @@ impl Inst for PushReturnInst {
     }
 
     fn serialize_to_binary(&self) -> Vec<u8> {
-        vec![0x14]
+        vec![Opcode::PushReturn as u8]
     }
 
     fn size(&self) -> u32 {
@@ -710,6 +734,10 @@ impl PopGlobalInst {
     pub fn new(idx: u16) -> Self {
         Self { address: 0, idx }
     }
+
+    pub fn get_idx(&self) -> u16 {
+        self.idx
+    }
 }
 
 impl Inst for PopGlobalInst {
@@ -722,8 +750,8 @@ impl Inst for PopGlobalInst {
     }
 
     fn serialize_to_binary(&self) -> Vec<u8> {
-        let mut bytes = vec![0x15];
-        bytes.extend_from_slice(&self.idx.to_le_bytes());
+        let mut bytes = vec![Opcode::PopGlobal as u8];
+        write_u16_le(&mut bytes, self.idx);
         bytes
     }
 
@@ -753,7 +781,7 @@ impl Inst for PopStackInst {
     }
 
     fn serialize_to_binary(&self) -> Vec<u8> {
-        vec![0x16, self.idx as u8]
+        vec![Opcode::PopStack as u8, self.idx as u8]
     }
 
     fn size(&self) -> u32 {
@@ -770,6 +798,10 @@ impl PopGlobalTableInst {
     pub fn new(idx: u16) -> Self {
         Self { address: 0, idx }
     }
+
+    pub fn get_idx(&self) -> u16 {
+        self.idx
+    }
 }
 
 impl Inst for PopGlobalTableInst {
@@ -782,8 +814,8 @@ impl Inst for PopGlobalTableInst {
     }
 
     fn serialize_to_binary(&self) -> Vec<u8> {
-        let mut bytes = vec![0x17];
-        bytes.extend_from_slice(&self.idx.to_le_bytes());
+        let mut bytes = vec![Opcode::PopGlobalTable as u8];
+        write_u16_le(&mut bytes, self.idx);
         bytes
     }
 
@@ -813,7 +845,7 @@ impl Inst for PopLocalTableInst {
     }
 
     fn serialize_to_binary(&self) -> Vec<u8> {
-        vec![0x18, self.idx as u8]
+        vec![Opcode::PopLocalTable as u8, self.idx as u8]
     }
 
     fn size(&self) -> u32 {
@@ -841,7 +873,7 @@ impl Inst for NegInst {
     }
 
     fn serialize_to_binary(&self) -> Vec<u8> {
-        vec![0x19]
+        vec![Opcode::Neg as u8]
     }
 
     fn size(&self) -> u32 {
@@ -869,7 +901,7 @@ impl Inst for AddInst {
     }
 
     fn serialize_to_binary(&self) -> Vec<u8> {
-        vec![0x1A]
+        vec![Opcode::Add as u8]
     }
 
     fn size(&self) -> u32 {
@@ -897,7 +929,7 @@ impl Inst for SubInst {
     }
 
     fn serialize_to_binary(&self) -> Vec<u8> {
-        vec![0x1B]
+        vec![Opcode::Sub as u8]
     }
 
     fn size(&self) -> u32 {
@@ -925,7 +957,7 @@ impl Inst for MulInst {
     }
 
     fn serialize_to_binary(&self) -> Vec<u8> {
-        vec![0x1C]
+        vec![Opcode::Mul as u8]
     }
 
     fn size(&self) -> u32 {
@@ -953,7 +985,7 @@ impl Inst for DivInst {
     }
 
     fn serialize_to_binary(&self) -> Vec<u8> {
-        vec![0x1D]
+        vec![Opcode::Div as u8]
     }
 
     fn size(&self) -> u32 {
@@ -981,7 +1013,7 @@ impl Inst for ModInst {
     }
 
     fn serialize_to_binary(&self) -> Vec<u8> {
-        vec![0x1E]
+        vec![Opcode::Mod as u8]
     }
 
     fn size(&self) -> u32 {
@@ -1009,7 +1041,7 @@ impl Inst for BitTestInst {
     }
 
     fn serialize_to_binary(&self) -> Vec<u8> {
-        vec![0x1F]
+        vec![Opcode::BitTest as u8]
     }
 
     fn size(&self) -> u32 {
@@ -1037,7 +1069,7 @@ impl Inst for AndInst {
     }
 
     fn serialize_to_binary(&self) -> Vec<u8> {
-        vec![0x20]
+        vec![Opcode::And as u8]
     }
 
     fn size(&self) -> u32 {
@@ -1065,7 +1097,7 @@ impl Inst for OrInst {
     }
 
     fn serialize_to_binary(&self) -> Vec<u8> {
-        vec![0x21]
+        vec![Opcode::Or as u8]
     }
 
     fn size(&self) -> u32 {
@@ -1093,7 +1125,7 @@ impl Inst for SetEInst {
     }
 
     fn serialize_to_binary(&self) -> Vec<u8> {
-        vec![0x22]
+        vec![Opcode::SetE as u8]
     }
 
     fn size(&self) -> u32 {
@@ -1121,7 +1153,7 @@ impl Inst for SetNEInst {
     }
 
     fn serialize_to_binary(&self) -> Vec<u8> {
-        vec![0x23]
+        vec![Opcode::SetNE as u8]
     }
 
     fn size(&self) -> u32 {
@@ -1149,7 +1181,7 @@ impl Inst for SetGInst {
     }
 
     fn serialize_to_binary(&self) -> Vec<u8> {
-        vec![0x24]
+        vec![Opcode::SetG as u8]
     }
 
     fn size(&self) -> u32 {
@@ -1177,7 +1209,7 @@ impl Inst for SetLEInst {
     }
 
     fn serialize_to_binary(&self) -> Vec<u8> {
-        vec![0x25]
+        vec![Opcode::SetLE as u8]
     }
 
     fn size(&self) -> u32 {
@@ -1205,7 +1237,7 @@ impl Inst for SetLInst {
     }
 
     fn serialize_to_binary(&self) -> Vec<u8> {
-        vec![0x26]
+        vec![Opcode::SetL as u8]
     }
 
     fn size(&self) -> u32 {
@@ -1233,7 +1265,7 @@ impl Inst for SetGEInst {
     }
 
     fn serialize_to_binary(&self) -> Vec<u8> {
-        vec![0x27]
+        vec![Opcode::SetGE as u8]
     }
 
     fn size(&self) -> u32 {
@@ -75,22 +75,37 @@ impl ProjectConfig {
         buffer.push(((value >> 24) & 0xff) as u8);
     }
 
+    /// globals are indexed with a u16, the non-volatile ones first
+    pub fn total_global_count(&self) -> u32 {
+        self.non_volatile_global_count as u32 + self.volatile_global_count as u32
+    }
+
+    fn validate_global_counts(&self) -> Result<()> {
+        if self.total_global_count() > u16::MAX as u32 {
+            bail!(
+                "too many globals: {} non-volatile + {} volatile, at most {} in total",
+                self.non_volatile_global_count,
+                self.volatile_global_count,
+                u16::MAX
+            );
+        }
+        Ok(())
+    }
+
     fn string_to_blob(content: &str, nls: Nls) -> Vec<u8> {
         // convert utf-8 string to local string via Nls
-        let mut content_bytes = match nls {
-            Nls::GBK => encoding_rs::GBK.encode(content).0.to_vec(),
-            Nls::ShiftJIS => encoding_rs::SHIFT_JIS.encode(content).0.to_vec(),
-            Nls::UTF8 => content.as_bytes().to_vec(),
-        };
+        let mut content_bytes = nls.encode(content);
 
-        if !content_bytes.ends_with(&[0]) {
-            content_bytes.push(0);
+        if !content_bytes.ends_with(nls.terminator()) {
+            content_bytes.extend_from_slice(nls.terminator());
         }
 
         content_bytes
     }
 
     fn serialize_to_binary(&mut self, nls: Nls) -> Result<Vec<u8>> {
+        self.validate_global_counts()?;
+
         let mut data = Vec::new();
         Self::put_u32_le(self.entry_point, &mut data);
         Self::put_u16_le(self.non_volatile_global_count, &mut data);
@@ -225,6 +240,17 @@ impl InstSet {
         }
     }
 
+    /// the global accessed by the instruction, if any
+    pub fn global_index(&self) -> Option<u16> {
+        match self {
+            InstSet::PushGlobal(inst) => Some(inst.get_idx()),
+            InstSet::PushGlobalTable(inst) => Some(inst.get_idx()),
+            InstSet::PopGlobal(inst) => Some(inst.get_idx()),
+            InstSet::PopGlobalTable(inst) => Some(inst.get_idx()),
+            _ => None,
+        }
+    }
+
     pub fn get_address(&self) -> u32 {
         match self {
             InstSet::Nop(inst) => inst.address(),
@@ -452,6 +478,17 @@ impl Assembler {
         let mut cursor = 4u32;
         for (addr, inst) in map {
             let mut wrapped_inst = Self::inst2_to_inst(inst, &self.nls, &syscall_table)?;
+            if let Some(idx) = wrapped_inst.global_index() {
+                if idx as u32 >= self.config.total_global_count() {
+                    bail!(
+                        "global {} used at {:#x}, but only {} globals are declared, \
+                         raise the global counts in the config",
+                        idx,
+                        addr,
+                        self.config.total_global_count()
+                    );
+                }
+            }
             wrapped_inst.set_address(cursor);
             let size = wrapped_inst.size();
             let wrapped_inst = Rc::new(RefCell::new(wrapped_inst));
@@ -552,7 +589,7 @@ fn main() {
 
 #[cfg(test)]
 mod tests {
-    use rfvp_core::format::scenario::Scenario;
+    use rfvp_core::format::scenario::{global::Global, Scenario};
 
     use super::*;
 
@@ -569,7 +606,125 @@ mod tests {
         let nls = Nls::ShiftJIS;
         compile(input, output, nls.clone()).unwrap();
         let outdata = std::fs::read(output).unwrap();
+        // the disassembly of Snow.hcb assembles back to the same bytes
+        let original = std::fs::read(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../disassembler/testcase/Snow.hcb"
+        ))
+        .unwrap();
+        assert_eq!(outdata, original);
         let outdata = Bytes::from(outdata);
         let _parser = Scenario::new(outdata, Some(nls)).unwrap();
     }
+
+    #[test]
+    fn test_split_long_string() {
+        // 600 bytes in UTF-8, 400 in the target encodings
+        let content = "あ".repeat(200);
+        for nls in [Nls::GBK, Nls::ShiftJIS] {
+            let inst = PushStringInst::new(content.clone(), nls.clone());
+            let bytes = inst.serialize_to_binary();
+            assert_eq!(bytes.len() as u32, inst.size());
+
+            let mut pieces = Vec::new();
+            let mut pos = 0;
+            while pos < bytes.len() {
+                assert_eq!(bytes[pos], 0x0E);
+                let len = bytes[pos + 1] as usize;
+                let blob = &bytes[pos + 2..pos + 2 + len];
+                assert_eq!(blob.last(), Some(&0));
+                pieces.push(blob[..len - 1].to_vec());
+                pos += 2 + len;
+                if pieces.len() > 1 {
+                    assert_eq!(bytes[pos], 0x1A);
+                    pos += 1;
+                }
+            }
+
+            assert_eq!(pieces.len(), 2);
+            assert_eq!(pieces.concat(), nls.encode(&content));
+        }
+    }
+
+    #[test]
+    fn test_comparison_opcodes() {
+        // the mnemonics the disassembler writes must assemble back to the same bytes
+        for opcode in [
+            Opcode::SetE,
+            Opcode::SetNE,
+            Opcode::SetG,
+            Opcode::SetLE,
+            Opcode::SetL,
+            Opcode::SetGE,
+        ] {
+            let yaml = format!(
+                "{{address: 0, mnemonic: {}, operands: []}}",
+                opcode.to_string()
+            );
+            let inst: Inst2 = serde_yaml::from_str(&yaml).unwrap();
+            let inst = Assembler::inst2_to_inst(&inst, &Nls::ShiftJIS, &BTreeMap::new()).unwrap();
+            assert_eq!(inst.serialize_to_binary(), vec![opcode as u8]);
+        }
+    }
+
+    /// a project storing to global 2 with `non_volatile` + 1 globals declared
+    fn global_project(name: &str, non_volatile: u16) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "rfvp_assembler_test_{}_{}",
+            name,
+            std::process::id()
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("project.toml"),
+            "config_file = \"config.yaml\"\ndisassembly_file = \"disassembly.yaml\"\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("config.yaml"),
+            format!(
+                "entry_point: 4\nnon_volatile_global_count: {}\nvolatile_global_count: 1\n\
+                 game_mode: 0\ngame_title: test\nsyscalls: []\ncustom_syscall_count: 0\n",
+                non_volatile
+            ),
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("disassembly.yaml"),
+            "- address: 4\n  args_count: 0\n  locals_count: 0\n  insts:\n\
+             \x20 - {address: 4, mnemonic: init_stack, operands: ['0', '0']}\n\
+             \x20 - {address: 7, mnemonic: push_i32, operands: ['1']}\n\
+             \x20 - {address: 12, mnemonic: pop_global, operands: ['2']}\n\
+             \x20 - {address: 15, mnemonic: ret, operands: []}\n",
+        )
+        .unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_grow_globals() {
+        // global 2 isn't declared until the non-volatile count is bumped
+        let dir = global_project("small", 1);
+        let err = compile(&dir, dir.join("out.hcb"), Nls::ShiftJIS).unwrap_err();
+        assert!(err.to_string().contains("global 2"));
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let dir = global_project("grown", 4);
+        compile(&dir, dir.join("out.hcb"), Nls::ShiftJIS).unwrap();
+        let data = Bytes::from(std::fs::read(dir.join("out.hcb")).unwrap());
+        let scenario = Scenario::new(data, Some(Nls::ShiftJIS)).unwrap();
+        assert_eq!(scenario.get_non_volatile_global_count(), 4);
+        assert_eq!(scenario.get_volatile_global_count(), 1);
+
+        let mut global = Global::new();
+        scenario.init_scene(&mut global);
+        assert!(global.get(4).is_some());
+        assert!(global.get(5).is_none());
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let dir = global_project("overflow", u16::MAX);
+        let err = compile(&dir, dir.join("out.hcb"), Nls::ShiftJIS).unwrap_err();
+        assert!(err.to_string().contains("too many globals"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
 }
@@ -0,0 +1,26 @@
+//! Disassembly of a large synthetic script, see `rfvp-test-support`
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use disassembler::{Disassembler, Format};
+use rfvp_core::format::scenario::Scenario;
+
+fn disassemble(c: &mut Criterion) {
+    let scenario = Scenario::new(rfvp_test_support::many_functions(20_000), None).unwrap();
+
+    let mut group = c.benchmark_group("disassemble");
+    group.sample_size(10);
+    for format in [Format::Yaml, Format::Json] {
+        let disassembler = Disassembler::from_scenario(scenario.clone());
+        group.bench_function(format.extension(), |b| {
+            b.iter(|| {
+                disassembler
+                    .write_disassembly(&mut std::io::sink(), black_box(format))
+                    .unwrap()
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, disassemble);
+criterion_main!(benches);
@@ -0,0 +1,77 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use clap::{Parser as ClapParser, Subcommand};
+use rfvp_core::format::scenario::{text_patch::TextPatch, Nls, Scenario};
+
+/// Replace the strings of a script in place, without reassembling it
+#[derive(ClapParser, Debug)]
+#[command(version, about, long_about = None)]
+struct Args {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// write a patch listing every string of the script, to be translated
+    Create {
+        #[arg(short, long)]
+        input: PathBuf,
+
+        #[arg(short, long)]
+        output: PathBuf,
+
+        #[arg(short, long, default_value = "sjis")]
+        lang: Nls,
+    },
+    /// write the script with the strings of the patch replaced
+    Apply {
+        #[arg(short, long)]
+        input: PathBuf,
+
+        #[arg(short, long)]
+        patch: PathBuf,
+
+        #[arg(short, long)]
+        output: PathBuf,
+
+        #[arg(short, long, default_value = "sjis")]
+        lang: Nls,
+    },
+}
+
+fn load_scenario(input: &Path, lang: Nls) -> Result<Scenario> {
+    let data = std::fs::read(input).with_context(|| format!("Reading {:?}", input))?;
+    Scenario::new(data.into(), Some(lang))
+}
+
+fn main() -> Result<()> {
+    let args = Args::parse();
+    match args.command {
+        Command::Create {
+            input,
+            output,
+            lang,
+        } => {
+            let scenario = load_scenario(&input, lang)?;
+            let patch = TextPatch::create(&scenario)?;
+            std::fs::write(&output, patch.to_toml()?)
+                .with_context(|| format!("Writing {:?}", output))?;
+        }
+        Command::Apply {
+            input,
+            patch,
+            output,
+            lang,
+        } => {
+            let scenario = load_scenario(&input, lang)?;
+            let patch =
+                std::fs::read_to_string(&patch).with_context(|| format!("Reading {:?}", patch))?;
+            let data = TextPatch::parse(&patch)?.apply(&scenario)?;
+            std::fs::write(&output, data).with_context(|| format!("Writing {:?}", output))?;
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,1488 @@
+use anyhow::{bail, Result};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::mem::size_of;
+use std::path::{PathBuf, Path};
+use rfvp_core::format::scenario::instructions::{inst::*, Instruction, Opcode, OpcodeBase};
+use rfvp_core::format::scenario::{split_string_literal, Nls, Scenario};
+use bytes::Bytes;
+
+use std::collections::HashSet;
+
+use std::io::{BufWriter, Write};
+
+pub mod repl;
+
+/// Functions decoded by one task of the parallel disassembly
+const FUNCTIONS_PER_TASK: usize = 64;
+
+/// Tasks decoded before their functions are written out, per thread: the output is
+/// streamed so only this many functions are in memory at once
+const TASKS_PER_BATCH: usize = 4;
+
+#[derive(Debug, Serialize)]
+pub struct Function {
+    address: u32,
+    args_count: u8,
+    locals_count: u8,
+    insts: Vec<Inst>
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Inst {
+    address: u32,
+    mnemonic: &'static str,
+    operands: Vec<String>,
+}
+
+/// The format of `disassembly.*`, the assembler reads both as JSON is valid YAML
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum Format {
+    #[default]
+    Yaml,
+    Json,
+}
+
+impl Format {
+    pub fn extension(self) -> &'static str {
+        match self {
+            Format::Yaml => "yaml",
+            Format::Json => "json",
+        }
+    }
+}
+
+/// What the disassembly needs to know about the whole code before it's split
+#[derive(Debug, Default)]
+struct CodeMap {
+    /// the address of every `init_stack`
+    function_starts: Vec<u32>,
+    /// the targets of the jumps, the split strings are not folded across them
+    jump_targets: HashSet<u32>,
+}
+
+impl Inst {
+    pub fn from_nop(inst: NopInst) -> Self {
+        Self {
+            address: inst.address(),
+            mnemonic: inst.opcode().mnemonic(),
+            operands: Vec::new(),
+        }
+    }
+
+    pub fn from_init_stack(inst: InitStackInst) -> Self {
+        Self {
+            address: inst.address(),
+            mnemonic: inst.opcode().mnemonic(),
+            operands: vec![inst.get_arg_count().to_string(), inst.get_local_count().to_string()],
+        }
+    }
+
+    pub fn from_call(inst: CallInst) -> Self {
+        Self {
+            address: inst.address(),
+            mnemonic: inst.opcode().mnemonic(),
+            operands: vec![inst.get_target().to_string()],
+        }
+    }
+
+    pub fn from_syscall(inst: SyscallInst) -> Self {
+        Self {
+            address: inst.address(),
+            mnemonic: inst.opcode().mnemonic(),
+            operands: vec![inst.get_syscall_name().to_string()],
+        }
+    }
+
+    pub fn from_ret(inst: RetInst) -> Self {
+        Self {
+            address: inst.address(),
+            mnemonic: inst.opcode().mnemonic(),
+            operands: Vec::new(),
+        }
+    }
+
+    pub fn from_ret_value(inst: RetValueInst) -> Self {
+        Self {
+            address: inst.address(),
+            mnemonic: inst.opcode().mnemonic(),
+            operands: Vec::new(),
+        }
+    }
+
+    pub fn from_jmp(inst: JmpInst) -> Self {
+        Self {
+            address: inst.address(),
+            mnemonic: inst.opcode().mnemonic(),
+            operands: vec![inst.get_target().to_string()],
+        }
+    }
+
+    pub fn from_jz(inst: JzInst) -> Self {
+        Self {
+            address: inst.address(),
+            mnemonic: inst.opcode().mnemonic(),
+            operands: vec![inst.get_target().to_string()],
+        }
+    }
+
+    pub fn from_push_nil(inst: PushNilInst) -> Self {
+        Self {
+            address: inst.address(),
+            mnemonic: inst.opcode().mnemonic(),
+            operands: Vec::new(),
+        }
+    }
+
+    pub fn from_push_true(inst: PushTrueInst) -> Self {
+        Self {
+            address: inst.address(),
+            mnemonic: inst.opcode().mnemonic(),
+            operands: Vec::new(),
+        }
+    }
+
+    pub fn from_push_i32(inst: PushI32Inst) -> Self {
+        Self {
+            address: inst.address(),
+            mnemonic: inst.opcode().mnemonic(),
+            operands: vec![inst.get_value().to_string()],
+        }
+    }
+
+    pub fn from_push_i16(inst: PushI16Inst) -> Self {
+        Self {
+            address: inst.address(),
+            mnemonic: inst.opcode().mnemonic(),
+            operands: vec![inst.get_value().to_string()],
+        }
+    }
+
+    pub fn from_push_i8(inst: PushI8Inst) -> Self {
+        Self {
+            address: inst.address(),
+            mnemonic: inst.opcode().mnemonic(),
+            operands: vec![inst.get_value().to_string()],
+        }
+    }
+
+    pub fn from_push_f32(inst: PushF32Inst) -> Self {
+        Self {
+            address: inst.address(),
+            mnemonic: inst.opcode().mnemonic(),
+            operands: vec![inst.get_value().to_string()],
+        }
+    }
+
+    pub fn from_push_string(inst: PushStringInst) -> Self {
+        Self {
+            address: inst.address(),
+            mnemonic: inst.opcode().mnemonic(),
+            operands: vec![inst.get_value().to_string()],
+        }
+    }
+
+    pub fn from_push_global(inst: PushGlobalInst) -> Self {
+        Self {
+            address: inst.address(),
+            mnemonic: inst.opcode().mnemonic(),
+            operands: vec![inst.get_idx().to_string()],
+        }
+    }
+
+    pub fn from_push_stack(inst: PushStackInst) -> Self {
+        Self {
+            address: inst.address(),
+            mnemonic: inst.opcode().mnemonic(),
+            operands: vec![inst.get_idx().to_string()],
+        }
+    }
+
+    pub fn from_push_global_table(inst: PushGlobalTableInst) -> Self {
+        Self {
+            address: inst.address(),
+            mnemonic: inst.opcode().mnemonic(),
+            operands: vec![inst.get_idx().to_string()],
+        }
+    }
+
+    pub fn from_push_local_table(inst: PushLocalTableInst) -> Self {
+        Self {
+            address: inst.address(),
+            mnemonic: inst.opcode().mnemonic(),
+            operands: vec![inst.get_idx().to_string()],
+        }
+    }
+
+    pub fn from_push_top(inst: PushTopInst) -> Self {
+        Self {
+            address: inst.address(),
+            mnemonic: inst.opcode().mnemonic(),
+            operands: Vec::new(),
+        }
+    }
+
+    pub fn from_push_return(inst: PushReturnInst) -> Self {
+        Self {
+            address: inst.address(),
+            mnemonic: inst.opcode().mnemonic(),
+            operands: Vec::new(),
+        }
+    }
+    
+    pub fn from_pop_global(inst: PopGlobalInst) -> Self {
+        Self {
+            address: inst.address(),
+            mnemonic: inst.opcode().mnemonic(),
+            operands: vec![inst.get_idx().to_string()],
+        }
+    }
+
+    pub fn from_pop_stack(inst: PopStackInst) -> Self {
+        Self {
+            address: inst.address(),
+            mnemonic: inst.opcode().mnemonic(),
+            operands: vec![inst.get_idx().to_string()],
+        }
+    }
+
+    pub fn from_pop_global_table(inst: PopGlobalTableInst) -> Self {
+        Self {
+            address: inst.address(),
+            mnemonic: inst.opcode().mnemonic(),
+            operands: vec![inst.get_idx().to_string()],
+        }
+    }
+
+    pub fn from_pop_local_table(inst: PopLocalTableInst) -> Self {
+        Self {
+            address: inst.address(),
+            mnemonic: inst.opcode().mnemonic(),
+            operands: vec![inst.get_idx().to_string()],
+        }
+    }
+
+    pub fn from_neg(inst: NegInst) -> Self {
+        Self {
+            address: inst.address(),
+            mnemonic: inst.opcode().mnemonic(),
+            operands: Vec::new(),
+        }
+    }
+
+    pub fn from_add(inst: AddInst) -> Self {
+        Self {
+            address: inst.address(),
+            mnemonic: inst.opcode().mnemonic(),
+            operands: Vec::new(),
+        }
+    }
+
+    pub fn from_sub(inst: SubInst) -> Self {
+        Self {
+            address: inst.address(),
+            mnemonic: inst.opcode().mnemonic(),
+            operands: Vec::new(),
+        }
+    }
+
+    pub fn from_mul(inst: MulInst) -> Self {
+        Self {
+            address: inst.address(),
+            mnemonic: inst.opcode().mnemonic(),
+            operands: Vec::new(),
+        }
+    }
+
+    pub fn from_div(inst: DivInst) -> Self {
+        Self {
+            address: inst.address(),
+            mnemonic: inst.opcode().mnemonic(),
+            operands: Vec::new(),
+        }
+    }
+
+    pub fn from_mod(inst: ModInst) -> Self {
+        Self {
+            address: inst.address(),
+            mnemonic: inst.opcode().mnemonic(),
+            operands: Vec::new(),
+        }
+    }
+
+    pub fn from_bittest(inst: BitTestInst) -> Self {
+        Self {
+            address: inst.address(),
+            mnemonic: inst.opcode().mnemonic(),
+            operands: Vec::new(),
+        }
+    }
+
+    pub fn from_and(inst: AndInst) -> Self {
+        Self {
+            address: inst.address(),
+            mnemonic: inst.opcode().mnemonic(),
+            operands: Vec::new(),
+        }
+    }
+
+    pub fn from_or(inst: OrInst) -> Self {
+        Self {
+            address: inst.address(),
+            mnemonic: inst.opcode().mnemonic(),
+            operands: Vec::new(),
+        }
+    }
+
+    pub fn from_sete(inst: SeteInst) -> Self {
+        Self {
+            address: inst.address(),
+            mnemonic: inst.opcode().mnemonic(),
+            operands: Vec::new(),
+        }
+    }
+
+    pub fn from_setne(inst: SetneInst) -> Self {
+        Self {
+            address: inst.address(),
+            mnemonic: inst.opcode().mnemonic(),
+            operands: Vec::new(),
+        }
+    }
+
+    pub fn from_setg(inst: SetgInst) -> Self {
+        Self {
+            address: inst.address(),
+            mnemonic: inst.opcode().mnemonic(),
+            operands: Vec::new(),
+        }
+    }
+
+    pub fn from_setle(inst: SetleInst) -> Self {
+        Self {
+            address: inst.address(),
+            mnemonic: inst.opcode().mnemonic(),
+            operands: Vec::new(),
+        }
+    }
+
+    pub fn from_setl(inst: SetlInst) -> Self {
+        Self {
+            address: inst.address(),
+            mnemonic: inst.opcode().mnemonic(),
+            operands: Vec::new(),
+        }
+    }
+
+    pub fn from_setge(inst: SetgeInst) -> Self {
+        Self {
+            address: inst.address(),
+            mnemonic: inst.opcode().mnemonic(),
+            operands: Vec::new(),
+        }
+    }
+
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SyscallEntry {
+    id: u32,
+    name: String,
+    args_count: u8,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProjectConfig {
+    entry_point: u32,
+    non_volatile_global_count: u16,
+    volatile_global_count: u16,
+    game_mode: u16,
+    game_title: String,
+    syscalls: Vec<SyscallEntry>,
+    custom_syscall_count: u16,
+}
+
+pub struct Disassembler {
+    scenario: Scenario,
+    cursor: usize,
+    functions: Vec<Function>,
+}
+
+impl Disassembler {
+    pub fn new(path: impl AsRef<Path>, nls: Nls) -> Result<Self> {
+        let data = std::fs::read(path.as_ref())?;
+        let data = Bytes::from(data);
+        let scenario = Scenario::new(data, Some(nls))?;
+        Ok(Self::from_scenario(scenario))
+    }
+
+    pub fn from_scenario(scenario: Scenario) -> Self {
+        Self {
+            scenario,
+            cursor: 4,
+            functions: Vec::new(),
+        }
+    }
+
+    pub fn get_scenario(&self) -> &Scenario {
+        &self.scenario
+    }
+
+    pub fn get_pc(&self) -> usize {
+        self.cursor
+    }
+
+    /// 0x00 nop instruction
+    /// nop, no operation
+    pub fn nop(&mut self) -> Result<()> {
+        let addr = self.get_pc() as u32;
+        self.cursor += 1;
+        let inst = NopInst::new(addr);
+        let inst = Inst::from_nop(inst);
+        self.functions.last_mut().unwrap().insts.push(inst);
+
+        Ok(())
+    }
+
+    /// 0x01 init stack instruction
+    /// initialize the local routine stack, as well as
+    /// the post-phase of perforimg call instruction or launching a new routine
+    pub fn init_stack(&mut self, scenario: &Scenario) -> Result<()> {
+        let addr = self.get_pc() as u32;
+        self.cursor += 1;
+
+        // how many arguments are passed to the routine
+        let args_count = scenario.read_i8(self.cursor)?;
+        self.cursor += size_of::<i8>();
+
+        // how many locals are declared in the routine
+        let locals_count = scenario.read_i8(self.cursor)?;
+        self.cursor += size_of::<i8>();
+
+        self.functions.push(Function {
+            address: addr,
+            args_count: args_count as u8,
+            locals_count: locals_count as u8,
+            insts: Vec::new(),
+        });
+
+        let inst = InitStackInst::new(addr, args_count as u8, locals_count as u8);
+        let inst = Inst::from_init_stack(inst);
+        self.functions.last_mut().unwrap().insts.push(inst);
+        
+        Ok(())
+    }
+
+
+    /// 0x02 call instruction
+    /// call a routine
+    pub fn call(&mut self, scenario: &Scenario) -> Result<()> {
+        let addr = self.get_pc() as u32;
+        self.cursor += 1;
+        let target = scenario.read_u32(self.cursor)?;
+        self.cursor += size_of::<u32>();
+
+        let inst = CallInst::new(addr, target);
+        let inst = Inst::from_call(inst);
+        self.functions.last_mut().unwrap().insts.push(inst);
+
+        Ok(())
+    }
+
+    /// 0x03 syscall
+    /// call a system call
+    pub fn syscall(&mut self, scenario: &Scenario) -> Result<()> {
+        let addr = self.get_pc() as u32;
+        self.cursor += 1;
+        let id = scenario.read_u16(self.cursor)?;
+        self.cursor += size_of::<u16>();
+
+        if let Some(syscall) = scenario.get_syscall(id) {
+            let inst = SyscallInst::new(addr, syscall.name.clone());
+            let inst = Inst::from_syscall(inst);
+            self.functions.last_mut().unwrap().insts.push(inst);
+
+        } else {
+            bail!("syscall not found: {}", id);
+        }
+
+        Ok(())
+    }
+
+    /// 0x04 ret instruction
+    /// return from a routine
+    pub fn ret(&mut self) -> Result<()> {
+        let addr = self.get_pc() as u32;
+        self.cursor += 1;
+
+        let inst = RetInst::new(addr);
+        let inst = Inst::from_ret(inst);
+        self.functions.last_mut().unwrap().insts.push(inst);
+        
+        Ok(())
+    }
+
+    /// 0x05 retv instruction
+    /// return from a routine with a value
+    pub fn retv(&mut self) -> Result<()> {
+        let addr = self.get_pc() as u32;
+        self.cursor += 1;
+
+        let inst = RetValueInst::new(addr);
+        let inst = Inst::from_ret_value(inst);
+        self.functions.last_mut().unwrap().insts.push(inst);
+        
+        Ok(())
+    }
+
+    /// 0x06 jmp instruction
+    /// jump to the address
+    pub fn jmp(&mut self, scenario: &Scenario) -> Result<()> {
+        let addr = self.get_pc() as u32;
+        self.cursor += 1;
+        let target = scenario.read_u32(self.cursor)?;
+        self.cursor += size_of::<u32>();
+
+        let inst = JmpInst::new(addr, target);
+        let inst = Inst::from_jmp(inst);
+        self.functions.last_mut().unwrap().insts.push(inst);
+
+        Ok(())
+    }
+
+    /// 0x07 jz instruction
+    /// jump to the address if the top of the stack is zero
+    pub fn jz(&mut self, scenario: &Scenario) -> Result<()> {
+        let addr = self.get_pc() as u32;
+        self.cursor += 1;
+        let target = scenario.read_u32(self.cursor)?;
+        self.cursor += size_of::<u32>();
+
+        let inst = JzInst::new(addr, target);
+        let inst = Inst::from_jz(inst);
+        self.functions.last_mut().unwrap().insts.push(inst);
+
+        Ok(())
+    }
+
+    /// 0x08 push nil
+    /// push a nil value onto the stack
+    pub fn push_nil(&mut self) -> Result<()> {
+        let addr = self.get_pc() as u32;
+        self.cursor += 1;
+
+        let inst = PushNilInst::new(addr);
+        let inst = Inst::from_push_nil(inst);
+        self.functions.last_mut().unwrap().insts.push(inst);
+
+        Ok(())
+    }
+
+    /// 0x09 push true
+    /// push a true value onto the stack
+    pub fn push_true(&mut self) -> Result<()> {
+        let addr = self.get_pc() as u32;
+        self.cursor += 1;
+
+        let inst = PushTrueInst::new(addr);
+        let inst = Inst::from_push_true(inst);
+        self.functions.last_mut().unwrap().insts.push(inst);
+
+        Ok(())
+    }
+
+    /// 0x0A push i32
+    /// push an i32 value onto the stack
+    pub fn push_i32(&mut self, scenario: &Scenario) -> Result<()> {
+        let addr = self.get_pc() as u32;
+        self.cursor += 1;
+
+        let value = scenario.read_i32(self.cursor)?;
+        self.cursor += size_of::<i32>();
+
+        let inst = PushI32Inst::new(addr, value);
+        let inst = Inst::from_push_i32(inst);
+        self.functions.last_mut().unwrap().insts.push(inst);
+
+        Ok(())
+    }
+
+    /// 0x0B push i16
+    /// push an i16 value onto the stack
+    pub fn push_i16(&mut self, scenario: &Scenario) -> Result<()> {
+        let addr = self.get_pc() as u32;
+        self.cursor += 1;
+        let value = scenario.read_i16(self.cursor)?;
+        self.cursor += size_of::<i16>();
+
+        let inst = PushI16Inst::new(addr, value);
+        let inst = Inst::from_push_i16(inst);
+        self.functions.last_mut().unwrap().insts.push(inst);
+        
+        Ok(())
+    }
+
+    /// 0x0C push i8
+    /// push an i8 value onto the stack
+    pub fn push_i8(&mut self, scenario: &Scenario) -> Result<()> {
+        let addr = self.get_pc() as u32;
+        self.cursor += 1;
+        let value = scenario.read_i8(self.cursor)?;
+        self.cursor += size_of::<i8>();
+
+        let inst = PushI8Inst::new(addr, value);
+        let inst = Inst::from_push_i8(inst);
+        self.functions.last_mut().unwrap().insts.push(inst);
+
+        Ok(())
+    }
+
+    /// 0x0D push f32
+    /// push an f32 value onto the stack
+    pub fn push_f32(&mut self, scenario: &Scenario) -> Result<()> {
+        let addr = self.get_pc() as u32;
+        self.cursor += 1;
+        let value = scenario.read_f32(self.cursor)?;
+        self.cursor += size_of::<f32>();
+
+        let inst = PushF32Inst::new(addr, value);
+        let inst = Inst::from_push_f32(inst);
+        self.functions.last_mut().unwrap().insts.push(inst);
+
+        Ok(())
+    }
+
+    /// 0x0E push string
+    /// push a string onto the stack
+    pub fn push_string(&mut self, scenario: &Scenario) -> Result<()> {
+        let addr = self.get_pc() as u32;
+        self.cursor += 1;
+        let len = scenario.read_u8(self.cursor)? as usize;
+        self.cursor += size_of::<u8>();
+
+        let s = scenario.read_cstring(self.cursor, len)?;
+        self.cursor += len;
+
+        let inst = PushStringInst::new(addr, s);
+        let inst = Inst::from_push_string(inst);
+        self.functions.last_mut().unwrap().insts.push(inst);
+
+        Ok(())
+    }
+
+    /// 0x0F push global
+    /// push a global variable onto the stack
+    pub fn push_global(&mut self, scenario: &Scenario) -> Result<()> {
+        let addr = self.get_pc() as u32;
+        self.cursor += 1;
+        let key = scenario.read_u16(self.cursor)?;
+        self.cursor += size_of::<u16>();
+
+        let inst = PushGlobalInst::new(addr, key as u32);
+        let inst = Inst::from_push_global(inst);
+        self.functions.last_mut().unwrap().insts.push(inst);
+
+        Ok(())
+    }
+
+    /// 0x10 push stack
+    /// push a stack variable onto the stack
+    pub fn push_stack(&mut self, scenario: &Scenario) -> Result<()> {
+        let addr = self.get_pc() as u32;
+        self.cursor += 1;
+        let offset = scenario.read_i8(self.cursor)?;
+        self.cursor += size_of::<i8>();
+
+        let inst = PushStackInst::new(addr, offset);
+        let inst = Inst::from_push_stack(inst);
+        self.functions.last_mut().unwrap().insts.push(inst);
+
+        Ok(())
+    }
+
+    /// 0x11 push global table
+    /// push a value than stored in the global table by immediate key onto the stack
+    /// we assume that if any failure occurs, such as the key not found, 
+    /// we will push a nil value onto the stack for compatibility reasons.
+    pub fn push_global_table(&mut self, scenario: &Scenario) -> Result<()> {
+        let addr = self.get_pc() as u32;
+        self.cursor += 1;
+        let key = scenario.read_u16(self.cursor)?;
+        self.cursor += size_of::<u16>();
+
+        let inst = PushGlobalTableInst::new(addr, key as u32);
+        let inst = Inst::from_push_global_table(inst);
+        self.functions.last_mut().unwrap().insts.push(inst);
+
+        Ok(())
+    }
+
+    /// 0x12 push local table
+    /// push a value than stored in the local table by key onto the stack
+    pub fn push_local_table(&mut self, scenario: &Scenario) -> Result<()> {
+        let addr = self.get_pc() as u32;
+        self.cursor += 1;
+        let idx = scenario.read_i8(self.cursor)?;
+        self.cursor += size_of::<i8>();
+
+        let inst = PushLocalTableInst::new(addr, idx);
+        let inst = Inst::from_push_local_table(inst);
+        self.functions.last_mut().unwrap().insts.push(inst);
+
+        Ok(())
+    }
+
+    /// 0x13 push top
+    /// push the top of the stack onto the stack
+    pub fn push_top(&mut self) -> Result<()> {
+        let addr = self.get_pc() as u32;
+        self.cursor += 1;
+
+        let inst = PushTopInst::new(addr);
+        let inst = Inst::from_push_top(inst);
+        self.functions.last_mut().unwrap().insts.push(inst);
+
+        Ok(())
+    }
+
+    /// 0x14 push return value
+    /// push the return value onto the stack
+    pub fn push_return_value(&mut self) -> Result<()> {
+        let addr = self.get_pc() as u32;
+        self.cursor += 1;
+
+        let inst = PushReturnInst::new(addr);
+        let inst = Inst::from_push_return(inst);
+        self.functions.last_mut().unwrap().insts.push(inst);
+
+        Ok(())
+    }
+
+    /// 0x15 pop global
+    /// pop the top of the stack and store it in the global table
+    pub fn pop_global(&mut self, scenario: &Scenario) -> Result<()> {
+        let addr = self.get_pc() as u32;
+        self.cursor += 1;
+        let key = scenario.read_u16(self.cursor)?;
+        self.cursor += size_of::<u16>();
+
+        let inst = PopGlobalInst::new(addr, key as u32);
+        let inst = Inst::from_pop_global(inst);
+        self.functions.last_mut().unwrap().insts.push(inst);
+
+        Ok(())
+    }
+
+    /// 0x16 local copy
+    /// copy the top of the stack to the local variable
+    pub fn local_copy(&mut self, scenario: &Scenario) -> Result<()> {
+        let addr = self.get_pc() as u32;
+        self.cursor += 1;
+        let idx = scenario.read_i8(self.cursor)?;
+        self.cursor += size_of::<i8>();
+
+        let inst = PopStackInst::new(addr, idx);
+        let inst = Inst::from_pop_stack(inst);
+        self.functions.last_mut().unwrap().insts.push(inst);
+
+        Ok(())
+    }
+
+    /// 0x17 pop global table
+    /// pop the top of the stack and store it in the global table by key
+    pub fn pop_global_table(&mut self, scenario: &Scenario) -> Result<()> {
+        let addr = self.get_pc() as u32;
+        self.cursor += 1;
+        let key = scenario.read_u16(self.cursor)?;
+        self.cursor += size_of::<u16>();
+
+        let inst = PopGlobalTableInst::new(addr, key as u32);
+        let inst = Inst::from_pop_global_table(inst);
+        self.functions.last_mut().unwrap().insts.push(inst);
+
+        Ok(())
+    }
+
+    /// 0x18 pop local table 
+    /// pop the top of the stack and store it in the local table by key
+    pub fn pop_local_table(&mut self, scenario: &Scenario) -> Result<()> {
+        let addr = self.get_pc() as u32;
+        self.cursor += 1;
+        let idx = scenario.read_i8(self.cursor)?;
+        self.cursor += size_of::<i8>();
+
+        let inst = PopLocalTableInst::new(addr, idx);
+        let inst = Inst::from_pop_local_table(inst);
+        self.functions.last_mut().unwrap().insts.push(inst);
+
+        Ok(())
+    }
+
+    /// 0x19 neg 
+    /// negate the top of the stack, only works for integers and floats
+    pub fn neg(&mut self) -> Result<()> {
+        let addr = self.get_pc() as u32;
+        self.cursor += 1;
+
+        let inst = NegInst::new(addr);
+        let inst = Inst::from_neg(inst);
+        self.functions.last_mut().unwrap().insts.push(inst);
+
+        Ok(())
+    }
+
+    /// 0x1A add
+    /// add the top two values on the stack
+    pub fn add(&mut self) -> Result<()> {
+        let addr = self.get_pc() as u32;
+        self.cursor += 1;
+
+        let inst = AddInst::new(addr);
+        let inst = Inst::from_add(inst);
+        self.functions.last_mut().unwrap().insts.push(inst);
+
+        Ok(())
+    }
+
+    /// 0x1B sub
+    /// subtract the top two values on the stack
+    pub fn sub(&mut self) -> Result<()> {
+        let addr = self.get_pc() as u32;
+        self.cursor += 1;
+
+        let inst = SubInst::new(addr);
+        let inst = Inst::from_sub(inst);
+        self.functions.last_mut().unwrap().insts.push(inst);
+
+        Ok(())
+    }
+
+    /// 0x1C mul
+    /// multiply the top two values on the stack
+    pub fn mul(&mut self) -> Result<()> {
+        let addr = self.get_pc() as u32;
+        self.cursor += 1;
+
+        let inst = MulInst::new(addr);
+        let inst = Inst::from_mul(inst);
+        self.functions.last_mut().unwrap().insts.push(inst);
+
+        Ok(())
+    }
+
+    /// 0x1D div
+    /// divide the top two values on the stack
+    pub fn div(&mut self) -> Result<()> {
+        let addr = self.get_pc() as u32;
+        self.cursor += 1;
+
+        let inst = DivInst::new(addr);
+        let inst = Inst::from_div(inst);
+        self.functions.last_mut().unwrap().insts.push(inst);
+
+        Ok(())
+    }
+
+    /// 0x1E modulo
+    /// modulo the top two values on the stack
+    pub fn modulo(&mut self) -> Result<()> {
+        let addr = self.get_pc() as u32;
+        self.cursor += 1;
+
+        let inst = ModInst::new(addr);
+        let inst = Inst::from_mod(inst);
+        self.functions.last_mut().unwrap().insts.push(inst);
+
+        Ok(())
+    }
+
+    /// 0x1F bittest
+    /// test with the top two values on the stack
+    pub fn bittest(&mut self) -> Result<()> {
+        let addr = self.get_pc() as u32;
+        self.cursor += 1;
+
+        let inst = BitTestInst::new(addr);
+        let inst = Inst::from_bittest(inst);
+        self.functions.last_mut().unwrap().insts.push(inst);
+
+        Ok(())
+    }
+
+    /// 0x20 and
+    /// push true if both the top two values on the stack are none-nil
+    pub fn and(&mut self) -> Result<()> {
+        let addr = self.get_pc() as u32;
+        self.cursor += 1;
+
+        let inst = AndInst::new(addr);
+        let inst = Inst::from_and(inst);
+        self.functions.last_mut().unwrap().insts.push(inst);
+
+        Ok(())
+    }
+
+    /// 0x21 or
+    /// push true if either of the top two values on the stack is none-nil
+    pub fn or(&mut self) -> Result<()> {
+        let addr = self.get_pc() as u32;
+        self.cursor += 1;
+
+        let inst = OrInst::new(addr);
+        let inst = Inst::from_or(inst);
+        self.functions.last_mut().unwrap().insts.push(inst);
+
+        Ok(())
+    }
+
+    /// 0x22 sete
+    /// set the top of the stack to true if the top two values on the stack are equal
+    pub fn sete(&mut self) -> Result<()> {
+        let addr = self.get_pc() as u32;
+        self.cursor += 1;
+
+        let inst = SeteInst::new(addr);
+        let inst = Inst::from_sete(inst);
+        self.functions.last_mut().unwrap().insts.push(inst);
+
+        Ok(())
+    }
+
+    /// 0x23 setne
+    /// set the top of the stack to true if the top two values on the stack are not equal
+    pub fn setne(&mut self) -> Result<()> {
+        let addr = self.get_pc() as u32;
+        self.cursor += 1;
+
+        let inst = SetneInst::new(addr);
+        let inst = Inst::from_setne(inst);
+        self.functions.last_mut().unwrap().insts.push(inst);
+
+        Ok(())
+    }
+
+    /// 0x24 setg
+    /// set the top of the stack to true if the top two values on the stack are greater
+    pub fn setg(&mut self) -> Result<()> {
+        let addr = self.get_pc() as u32;
+        self.cursor += 1;
+
+        let inst = SetgInst::new(addr);
+        let inst = Inst::from_setg(inst);
+        self.functions.last_mut().unwrap().insts.push(inst);
+
+        Ok(())
+    }
+
+    /// 0x25 setle
+    /// set the top of the stack to true if the top two values on the stack are less or equal
+    pub fn setle(&mut self) -> Result<()> {
+        let addr = self.get_pc() as u32;
+        self.cursor += 1;
+
+        let inst = SetleInst::new(addr);
+        let inst = Inst::from_setle(inst);
+        self.functions.last_mut().unwrap().insts.push(inst);
+
+        Ok(())
+    }
+
+    /// 0x26 setl
+    /// set the top of the stack to true if the top two values on the stack are less
+    pub fn setl(&mut self) -> Result<()> {
+        let addr = self.get_pc() as u32;
+        self.cursor += 1;
+
+        let inst = SetlInst::new(addr);
+        let inst = Inst::from_setl(inst);
+        self.functions.last_mut().unwrap().insts.push(inst);
+
+        Ok(())
+    }
+
+    /// 0x27 setge
+    /// set the top of the stack to true if the top two values on the stack are greater or equal
+    pub fn setge(&mut self) -> Result<()> {
+        let addr = self.get_pc() as u32;
+        self.cursor += 1;
+
+        let inst = SetgeInst::new(addr);
+        let inst = Inst::from_setge(inst);
+        self.functions.last_mut().unwrap().insts.push(inst);
+
+        Ok(())
+    }
+
+    fn disassemble_opcode(&mut self, scenario: &Scenario) -> Result<()> {
+        let opcode = scenario.read_u8(self.get_pc())? as i32;
+        
+        match opcode.try_into() {
+            Ok(Opcode::Nop) => {
+                self.nop()?;
+            }
+            Ok(Opcode::InitStack) => {
+                self.init_stack(scenario)?;
+            }
+            Ok(Opcode::Call) => {
+                self.call(scenario)?;
+            }
+            Ok(Opcode::Syscall) => {
+                self.syscall(scenario)?;
+            }
+            Ok(Opcode::Ret) => {
+                self.ret()?;
+            }
+            Ok(Opcode::RetV) => {
+                self.retv()?;
+            }
+            Ok(Opcode::Jmp) => {
+                self.jmp(scenario)?;
+            }
+            Ok(Opcode::Jz) => {
+                self.jz(scenario)?;
+            }
+            Ok(Opcode::PushNil) => {
+                self.push_nil()?;
+            }
+            Ok(Opcode::PushTrue) => {
+                self.push_true()?;
+            }
+            Ok(Opcode::PushI32) => {
+                self.push_i32(scenario)?;
+            }
+            Ok(Opcode::PushI16) => {
+                self.push_i16(scenario)?;
+            }
+            Ok(Opcode::PushI8) => {
+                self.push_i8(scenario)?;
+            }
+            Ok(Opcode::PushF32) => {
+                self.push_f32(scenario)?;
+            }
+            Ok(Opcode::PushString) => {
+                self.push_string(scenario)?;
+            }
+            Ok(Opcode::PushGlobal) => {
+                self.push_global(scenario)?;
+            }
+            Ok(Opcode::PushStack) => {
+                self.push_stack(scenario)?;
+            }
+            Ok(Opcode::PushGlobalTable) => {
+                self.push_global_table(scenario)?;
+            }
+            Ok(Opcode::PushLocalTable) => {
+                self.push_local_table(scenario)?;
+            }
+            Ok(Opcode::PushTop) => {
+                self.push_top()?;
+            }
+            Ok(Opcode::PushReturn) => {
+                self.push_return_value()?;
+            }
+            Ok(Opcode::PopGlobal) => {
+                self.pop_global(scenario)?;
+            }
+            Ok(Opcode::PopStack) => {
+                self.local_copy(scenario)?;
+            }
+            Ok(Opcode::PopGlobalTable) => {
+                self.pop_global_table(scenario)?;
+            }
+            Ok(Opcode::PopLocalTable) => {
+                self.pop_local_table(scenario)?;
+            }
+            Ok(Opcode::Neg) => {
+                self.neg()?;
+            }
+            Ok(Opcode::Add) => {
+                self.add()?;
+            }
+            Ok(Opcode::Sub) => {
+                self.sub()?;
+            }
+            Ok(Opcode::Mul) => {
+                self.mul()?;
+            }
+            Ok(Opcode::Div) => {
+                self.div()?;
+            }
+            Ok(Opcode::Mod) => {
+                self.modulo()?;
+            }
+            Ok(Opcode::BitTest) => {
+                self.bittest()?;
+            }
+            Ok(Opcode::And) => {
+                self.and()?;
+            }
+            Ok(Opcode::Or) => {
+                self.or()?;
+            }
+            Ok(Opcode::SetE) => {
+                self.sete()?;
+            }
+            Ok(Opcode::SetNE) => {
+                self.setne()?;
+            }
+            Ok(Opcode::SetG) => {
+                self.setg()?;
+            }
+            Ok(Opcode::SetLE) => {
+                self.setle()?;
+            }
+            Ok(Opcode::SetL) => {
+                self.setl()?;
+            }
+            Ok(Opcode::SetGE) => {
+                self.setge()?;
+            }
+            _ => {
+                self.nop()?;
+                log::error!("unknown opcode: {}", opcode);
+            }
+        };
+
+        Ok(())
+    }
+
+    /// walk the sizes of the instructions, without decoding their operands, for the
+    /// boundaries of the functions the disassembly is split at
+    fn scan(&self) -> Result<CodeMap> {
+        let scenario = &self.scenario;
+        let mut map = CodeMap::default();
+        let mut address = 4;
+        while address < scenario.get_sys_desc_offset() {
+            let Ok(inst) = Instruction::decode(scenario, address) else {
+                // an unknown opcode is disassembled as a nop
+                address += 1;
+                continue;
+            };
+            match inst.opcode {
+                Opcode::InitStack => map.function_starts.push(address),
+                Opcode::Jmp | Opcode::Jz => {
+                    map.jump_targets
+                        .insert(scenario.read_u32(address as usize + 1)?);
+                }
+                _ => {}
+            }
+            address = inst.next_address();
+        }
+        Ok(map)
+    }
+
+    /// the functions between two addresses, `start` is where a function begins
+    fn disassemble_range(&self, start: u32, end: u32, map: &CodeMap) -> Result<Vec<Function>> {
+        let mut worker = Disassembler {
+            scenario: self.scenario.clone(),
+            cursor: start as usize,
+            functions: Vec::new(),
+        };
+        while worker.get_pc() < end as usize {
+            worker.disassemble_opcode(&self.scenario)?;
+        }
+
+        let nls = &self.scenario.nls;
+        for function in &mut worker.functions {
+            let insts = std::mem::take(&mut function.insts);
+            function.insts = fold_split_strings(insts, &map.jump_targets, nls);
+        }
+        Ok(worker.functions)
+    }
+
+    /// disassemble the functions in parallel and hand them to `sink` in order, a batch
+    /// at a time
+    fn disassemble_batches(&self, mut sink: impl FnMut(Vec<Function>) -> Result<()>) -> Result<()> {
+        let map = self.scan()?;
+        let end = self.scenario.get_sys_desc_offset();
+        // the first task also gets whatever is before the first function
+        let bounds = std::iter::once(4)
+            .chain(
+                map.function_starts
+                    .iter()
+                    .copied()
+                    .step_by(FUNCTIONS_PER_TASK)
+                    .skip(1),
+            )
+            .chain(std::iter::once(end))
+            .collect::<Vec<_>>();
+        let ranges = bounds.windows(2).map(|w| (w[0], w[1])).collect::<Vec<_>>();
+
+        let batch_size = rayon::current_num_threads() * TASKS_PER_BATCH;
+        for batch in ranges.chunks(batch_size) {
+            let functions = batch
+                .par_iter()
+                .map(|&(start, end)| self.disassemble_range(start, end, &map))
+                .collect::<Result<Vec<_>>>()?;
+            sink(functions.into_iter().flatten().collect())?;
+        }
+        Ok(())
+    }
+
+    pub fn disassemble(&mut self) -> Result<()> {
+        let mut functions = Vec::new();
+        self.disassemble_batches(|batch| {
+            functions.extend(batch);
+            Ok(())
+        })?;
+        self.functions = functions;
+        Ok(())
+    }
+
+    /// disassemble and write the functions as they come, the whole disassembly is
+    /// never in memory
+    pub fn write_disassembly(&self, writer: &mut impl Write, format: Format) -> Result<()> {
+        let mut empty = true;
+        self.disassemble_batches(|batch| {
+            for function in &batch {
+                match format {
+                    // a sequence of one function, the same text as the function in the
+                    // sequence of all of them
+                    Format::Yaml => {
+                        serde_yaml::to_writer(&mut *writer, std::slice::from_ref(function))?
+                    }
+                    Format::Json => {
+                        writer.write_all(if empty { b"[\n" } else { b",\n" })?;
+                        serde_json::to_writer(&mut *writer, function)?;
+                    }
+                }
+                empty = false;
+            }
+            Ok(())
+        })?;
+
+        match format {
+            _ if empty => writer.write_all(b"[]\n")?,
+            Format::Yaml => {}
+            Format::Json => writer.write_all(b"\n]\n")?,
+        }
+        Ok(())
+    }
+
+    pub fn write_insts(&self, path: impl AsRef<Path>, format: Format) -> Result<()> {
+        // create a new directory
+        let output = path.as_ref();
+        if !output.exists() {
+            std::fs::create_dir_all(output)?;
+        }
+
+        let disassembly_file = PathBuf::from(format!("disassembly.{}", format.extension()));
+        let mut writer = BufWriter::new(std::fs::File::create(output.join(&disassembly_file))?);
+        self.write_disassembly(&mut writer, format)?;
+        writer.flush()?;
+
+        let mut syscalls: Vec<_> = self
+            .get_scenario()
+            .get_all_syscalls()
+            .iter()
+            .map(|(id, sys)| SyscallEntry {
+                id: *id as u32,
+                name: sys.name.clone(),
+                args_count: sys.args,
+            })
+            .collect();
+        // in the order of the ids, not of the hash map, so the config is the same every time
+        syscalls.sort_by_key(|syscall| syscall.id);
+
+        let config = ProjectConfig {
+            entry_point: self.get_scenario().get_entry_point(),
+            non_volatile_global_count: self.get_scenario().get_non_volatile_global_count(),
+            volatile_global_count: self.get_scenario().get_volatile_global_count(),
+            game_mode: self.get_scenario().get_game_mode(),
+            game_title: self.get_scenario().get_title(),
+            syscalls,
+            custom_syscall_count: self.get_scenario().get_custom_syscall_count(),
+        };
+
+        let yaml_config = output.join("config.yaml");
+        let mut writer = std::fs::File::create(yaml_config)?;
+        serde_yaml::to_writer(&mut writer, &config)?;
+
+        let project = FVPProject {
+            config_file: PathBuf::from("config.yaml"),
+            disassembly_file,
+        };
+
+        let toml_project = output.join("project.toml");
+        let mut writer = std::fs::File::create(toml_project)?;
+        let serialized_string = toml::to_string_pretty(&project)?;
+        writer.write_all(serialized_string.as_bytes())?;
+
+        Ok(())
+    }
+}
+
+
+/// A chain is only folded when nothing jumps into the middle of it and the
+/// assembler would split the folded literal into exactly the same pieces,
+/// so reassembling the output reproduces the original bytes.
+fn fold_split_strings(insts: Vec<Inst>, jump_targets: &HashSet<u32>, nls: &Nls) -> Vec<Inst> {
+    let mut folded: Vec<Inst> = Vec::with_capacity(insts.len());
+    let mut i = 0;
+    while i < insts.len() {
+        if insts[i].mnemonic != "push_string" {
+            folded.push(insts[i].clone());
+            i += 1;
+            continue;
+        }
+
+        let mut pieces = vec![insts[i].operands[0].clone()];
+        let mut end = i + 1;
+        while end + 1 < insts.len()
+            && insts[end].mnemonic == "push_string"
+            && insts[end + 1].mnemonic == "add"
+            && !jump_targets.contains(&insts[end].address)
+            && !jump_targets.contains(&insts[end + 1].address)
+        {
+            pieces.push(insts[end].operands[0].clone());
+            end += 2;
+        }
+
+        let content = pieces.concat();
+        if pieces.len() > 1 && split_string_literal(&content, nls) == pieces {
+            folded.push(Inst {
+                address: insts[i].address,
+                mnemonic: insts[i].mnemonic,
+                operands: vec![content],
+            });
+            i = end;
+        } else {
+            folded.push(insts[i].clone());
+            i += 1;
+        }
+    }
+
+    folded
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FVPProject {
+    config_file: PathBuf,
+    disassembly_file: PathBuf,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_disassembler() -> Result<()> {
+        let input = Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/testcase/Snow.hcb"));
+        let fixture = Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/testcase/Snow"));
+        // the committed project is only compared against, never rewritten
+        let output =
+            std::env::temp_dir().join(format!("rfvp_disassembler_test_{}", std::process::id()));
+        let disassembler = Disassembler::new(input, Nls::ShiftJIS)?;
+        disassembler.write_insts(&output, Format::Yaml)?;
+
+        let result = ["disassembly.yaml", "config.yaml", "project.toml"]
+            .iter()
+            .try_for_each(|name| {
+                let actual = std::fs::read_to_string(output.join(name))?;
+                let expected = std::fs::read_to_string(fixture.join(name))?;
+                compare_lines(name, &actual, &expected)
+            });
+        std::fs::remove_dir_all(&output)?;
+        result
+    }
+
+    /// fails on the first line which differs, the whole fixture is too big to print
+    fn compare_lines(name: &str, actual: &str, expected: &str) -> Result<()> {
+        let mut actual = actual.lines();
+        let mut expected = expected.lines();
+        for line in 1.. {
+            match (actual.next(), expected.next()) {
+                (None, None) => break,
+                (a, e) if a != e => bail!(
+                    "{} differs at line {}: {:?}, expected {:?}",
+                    name,
+                    line,
+                    a,
+                    e
+                ),
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_streaming() -> Result<()> {
+        let hcb = rfvp_test_support::many_functions(FUNCTIONS_PER_TASK * 3 + 5);
+        let mut disassembler = Disassembler::from_scenario(Scenario::new(hcb, None)?);
+
+        // in one piece, like before the split
+        let map = disassembler.scan()?;
+        let end = disassembler.get_scenario().get_sys_desc_offset();
+        let whole = disassembler.disassemble_range(4, end, &map)?;
+        assert_eq!(whole.len(), FUNCTIONS_PER_TASK * 3 + 5);
+
+        let mut yaml = Vec::new();
+        disassembler.write_disassembly(&mut yaml, Format::Yaml)?;
+        assert_eq!(String::from_utf8(yaml)?, serde_yaml::to_string(&whole)?);
+
+        let mut json = Vec::new();
+        disassembler.write_disassembly(&mut json, Format::Json)?;
+        let parsed: serde_json::Value = serde_json::from_slice(&json)?;
+        assert_eq!(parsed, serde_json::to_value(&whole)?);
+        // what the assembler reads it with
+        let parsed: serde_yaml::Value = serde_yaml::from_slice(&json)?;
+        assert_eq!(parsed, serde_yaml::to_value(&whole)?);
+
+        disassembler.disassemble()?;
+        assert_eq!(
+            serde_yaml::to_string(&disassembler.functions)?,
+            serde_yaml::to_string(&whole)?
+        );
+        Ok(())
+    }
+
+    fn inst(address: u32, mnemonic: &'static str, operand: Option<&str>) -> Inst {
+        Inst {
+            address,
+            mnemonic,
+            operands: operand.into_iter().map(|s| s.to_string()).collect(),
+        }
+    }
+
+    fn split_chain(content: &str, nls: &Nls) -> Vec<Inst> {
+        let mut insts = Vec::new();
+        let mut address = 0;
+        for (i, piece) in split_string_literal(content, nls).iter().enumerate() {
+            insts.push(inst(address, "push_string", Some(piece)));
+            address += 2 + nls.encode(piece).len() as u32 + 1;
+            if i > 0 {
+                insts.push(inst(address, "add", None));
+                address += 1;
+            }
+        }
+        insts
+    }
+
+    #[test]
+    fn test_fold_split_strings() {
+        let content = "あ".repeat(200);
+        for nls in [Nls::GBK, Nls::ShiftJIS] {
+            let insts = split_chain(&content, &nls);
+            assert_eq!(insts.len(), 3);
+
+            let folded = fold_split_strings(insts.clone(), &HashSet::new(), &nls);
+            assert_eq!(folded.len(), 1);
+            assert_eq!(folded[0].operands[0], content);
+
+            // a jump into the chain keeps it as is
+            let targets = HashSet::from([insts[1].address]);
+            let folded = fold_split_strings(insts, &targets, &nls);
+            assert_eq!(folded.len(), 3);
+        }
+
+        // a hand written concatenation is not what the assembler would emit
+        let insts = vec![
+            inst(0, "push_string", Some("a")),
+            inst(4, "push_string", Some("b")),
+            inst(8, "add", None),
+        ];
+        let folded = fold_split_strings(insts, &HashSet::new(), &Nls::ShiftJIS);
+        assert_eq!(folded.len(), 3);
+    }
+}
\ No newline at end of file
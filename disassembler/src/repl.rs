@@ -0,0 +1,477 @@
+//! Interactive queries against a loaded script.
+//!
+//! Every command returns its output as a string, so the same code serves the
+//! interactive prompt, the `--script` batch mode and the tests.
+
+use std::collections::HashMap;
+use std::io::{BufRead, Write};
+use std::path::Path;
+
+use anyhow::{bail, Context as _, Result};
+use bytes::Bytes;
+use rfvp_core::format::scenario::context::Context;
+use rfvp_core::format::scenario::instructions::Opcode;
+use rfvp_core::format::scenario::raw_variant::RawVariant;
+use rfvp_core::format::scenario::variant::Variant;
+use rfvp_core::format::scenario::{Nls, Scenario};
+
+use crate::{Disassembler, Function, Inst};
+
+/// an eval that runs longer than this is most likely stuck in a loop
+const MAX_EVAL_STEPS: usize = 1_000_000;
+
+const HELP: &str = "\
+funcs                    list the functions
+dis <addr>               disassemble the function containing the address
+xref <addr>              list the calls to the function at the address
+syscall <name>           list the uses of a syscall
+strings <text>           list the string literals containing the text
+complete <prefix>        complete a syscall name
+host <syscall> <value>   make the sandbox return the value from the syscall
+eval <addr> [args...]    run the function in the sandbox and print its syscalls
+help                     show this message
+quit                     leave";
+
+pub struct Repl {
+    disassembler: Disassembler,
+    /// the fake syscall host of the sandbox, unset syscalls return nil
+    host: HashMap<String, Variant>,
+}
+
+impl Repl {
+    pub fn new(path: impl AsRef<Path>, nls: Nls) -> Result<Self> {
+        let data = Bytes::from(std::fs::read(path.as_ref())?);
+        // the sandbox stubs syscalls anyway, so load scripts the engine doesn't fully know
+        let scenario = Scenario::new_tolerant(data, Some(nls))?;
+        let mut disassembler = Disassembler::from_scenario(scenario);
+        disassembler.disassemble()?;
+
+        Ok(Self {
+            disassembler,
+            host: HashMap::new(),
+        })
+    }
+
+    /// read commands from stdin until `quit` or EOF
+    pub fn run_interactive(&mut self) -> Result<()> {
+        let stdin = std::io::stdin();
+        let mut stdout = std::io::stdout();
+        loop {
+            write!(stdout, "> ")?;
+            stdout.flush()?;
+
+            let mut line = String::new();
+            if stdin.lock().read_line(&mut line)? == 0 {
+                return Ok(());
+            }
+            match self.execute(&line) {
+                Ok(None) => return Ok(()),
+                Ok(Some(output)) => writeln!(stdout, "{}", output)?,
+                Err(e) => writeln!(stdout, "error: {:#}", e)?,
+            }
+        }
+    }
+
+    /// run the commands in a file, failing on the first error (for CI checks)
+    pub fn run_script(&mut self, path: impl AsRef<Path>) -> Result<String> {
+        let script = std::fs::read_to_string(path.as_ref())?;
+        let mut output = String::new();
+        for (n, line) in script.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            output.push_str(&format!("> {}\n", line));
+            match self
+                .execute(line)
+                .with_context(|| format!("line {}: {}", n + 1, line))?
+            {
+                Some(out) => {
+                    output.push_str(&out);
+                    output.push('\n');
+                }
+                None => break,
+            }
+        }
+        Ok(output)
+    }
+
+    /// `None` when the command asks to leave
+    pub fn execute(&mut self, line: &str) -> Result<Option<String>> {
+        let words = split_words(line)?;
+        let Some((command, args)) = words.split_first() else {
+            return Ok(Some(String::new()));
+        };
+
+        let output = match (command.as_str(), args) {
+            ("quit" | "exit", _) => return Ok(None),
+            ("help", _) => HELP.to_string(),
+            ("funcs", []) => self.funcs(),
+            ("dis", [addr]) => self.dis(parse_addr(addr)?)?,
+            ("xref", [addr]) => self.xref(parse_addr(addr)?),
+            ("syscall", [name]) => self.syscall_uses(name),
+            ("strings", [text]) => self.strings(unquote(text)),
+            ("complete", [prefix]) => self.complete(prefix).join(" "),
+            ("host", [name, value]) => {
+                let value = parse_variant(value);
+                let output = format!("{} -> {}", name, pretty(&value));
+                self.host.insert(name.clone(), value);
+                output
+            }
+            ("eval", [addr, args @ ..]) => {
+                let args = args.iter().map(|a| parse_variant(a)).collect();
+                self.eval(parse_addr(addr)?, args)?
+            }
+            _ => bail!("unknown command or wrong arguments: {} (try help)", line.trim()),
+        };
+
+        Ok(Some(output))
+    }
+
+    fn functions(&self) -> &[Function] {
+        &self.disassembler.functions
+    }
+
+    fn insts(&self) -> impl Iterator<Item = (&Function, &Inst)> {
+        self.functions()
+            .iter()
+            .flat_map(|f| f.insts.iter().map(move |inst| (f, inst)))
+    }
+
+    fn funcs(&self) -> String {
+        self.functions()
+            .iter()
+            .map(|f| {
+                format!(
+                    "0x{:x} args: {} locals: {} insts: {}",
+                    f.address,
+                    f.args_count,
+                    f.locals_count,
+                    f.insts.len()
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn dis(&self, addr: u32) -> Result<String> {
+        // functions are laid out in order, the last one starting at or before the address contains it
+        let Some(function) = self.functions().iter().rev().find(|f| f.address <= addr) else {
+            bail!("no function at 0x{:x}", addr);
+        };
+
+        Ok(function
+            .insts
+            .iter()
+            .map(format_inst)
+            .collect::<Vec<_>>()
+            .join("\n"))
+    }
+
+    fn xref(&self, addr: u32) -> String {
+        let target = addr.to_string();
+        self.insts()
+            .filter(|(_, inst)| inst.mnemonic == "call" && inst.operands[0] == target)
+            .map(|(f, inst)| format!("0x{:x} in 0x{:x}", inst.address, f.address))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn syscall_uses(&self, name: &str) -> String {
+        self.insts()
+            .filter(|(_, inst)| inst.mnemonic == "syscall" && inst.operands[0] == name)
+            .map(|(f, inst)| format!("0x{:x} in 0x{:x}", inst.address, f.address))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn strings(&self, text: &str) -> String {
+        self.insts()
+            .filter(|(_, inst)| inst.mnemonic == "push_string" && inst.operands[0].contains(text))
+            .map(|(_, inst)| format!("0x{:x} {:?}", inst.address, inst.operands[0]))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// the syscall names imported by the script starting with the prefix
+    pub fn complete(&self, prefix: &str) -> Vec<String> {
+        let mut names = self
+            .disassembler
+            .get_scenario()
+            .get_all_syscalls()
+            .values()
+            .map(|s| s.name.clone())
+            .filter(|name| name.starts_with(prefix))
+            .collect::<Vec<_>>();
+        names.sort();
+        names
+    }
+
+    /// run a function in a fresh context, answering the syscalls from the host.
+    /// globals are shared with the rest of the process, nothing is persisted
+    fn eval(&self, addr: u32, args: Vec<Variant>) -> Result<String> {
+        let scenario = self.disassembler.get_scenario();
+        if !scenario.is_code_area(addr) {
+            bail!("0x{:x} is not in the code area", addr);
+        }
+
+        let mut context = Context::with_args(addr, args);
+        let mut output = Vec::new();
+        for _ in 0..MAX_EVAL_STEPS {
+            // returned from the evaluated function
+            if context.get_pc() == 0 {
+                let value = context.get_return_value();
+                output.push(format!("=> {}{}", pretty(value), raw(value)));
+                return Ok(output.join("\n"));
+            }
+
+            let opcode = scenario.read_u8(context.get_pc())? as i32;
+            if let Ok(Opcode::Syscall) = opcode.try_into() {
+                let command = context.syscall(scenario)?;
+                let args: Vec<_> = command
+                    .args()
+                    .map(|args| (0..args.len()).map(|n| pretty(args.get(n))).collect())
+                    .unwrap_or_default();
+                let value = self.host.get(command.name()).cloned().unwrap_or_default();
+                output.push(format!(
+                    "{}({}) -> {}",
+                    command.name(),
+                    args.join(", "),
+                    pretty(&value)
+                ));
+                context.set_return_value(value);
+            } else {
+                context.dispatch_opcode(scenario)?;
+            }
+        }
+
+        bail!("gave up after {} steps", MAX_EVAL_STEPS)
+    }
+}
+
+fn format_inst(inst: &Inst) -> String {
+    format!("0x{:08x} {} {}", inst.address, inst.mnemonic, inst.operands.join(", "))
+        .trim_end()
+        .to_string()
+}
+
+/// split on whitespace, double quotes group words into one
+fn split_words(line: &str) -> Result<Vec<String>> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut quoted = false;
+    let mut in_word = false;
+    for c in line.trim().chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                in_word = true;
+                word.push(c);
+            }
+            c if c.is_whitespace() && !quoted => {
+                if in_word {
+                    words.push(std::mem::take(&mut word));
+                    in_word = false;
+                }
+            }
+            c => {
+                in_word = true;
+                word.push(c);
+            }
+        }
+    }
+    if quoted {
+        bail!("unterminated quote");
+    }
+    if in_word {
+        words.push(word);
+    }
+    Ok(words)
+}
+
+fn unquote(s: &str) -> &str {
+    s.strip_prefix('"')
+        .and_then(|s| s.strip_suffix('"'))
+        .unwrap_or(s)
+}
+
+fn parse_addr(s: &str) -> Result<u32> {
+    let addr = match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(hex) => u32::from_str_radix(hex, 16),
+        None => s.parse(),
+    };
+    addr.with_context(|| format!("invalid address: {}", s))
+}
+
+/// nil, true, integers, floats and strings, quoted or not
+fn parse_variant(s: &str) -> Variant {
+    if s.len() >= 2 && s.starts_with('"') && s.ends_with('"') {
+        return Variant::String(unquote(s).to_string());
+    }
+    match s {
+        "nil" => Variant::Nil,
+        "true" => Variant::True,
+        _ => {
+            if let Ok(i) = s.parse::<i32>() {
+                Variant::Int(i)
+            } else if let Ok(f) = s.parse::<f32>() {
+                Variant::Float(f)
+            } else {
+                Variant::String(s.to_string())
+            }
+        }
+    }
+}
+
+/// the value in the layout of the original engine, to compare with a dump of its stack.
+/// empty for the dynamic strings, their index depends on the pool of the dump
+fn raw(value: &Variant) -> String {
+    match RawVariant::try_from(value) {
+        Ok(raw) => format!(" [{:?} {:#010x}]", raw.ty, raw.value),
+        Err(_) => String::new(),
+    }
+}
+
+pub fn pretty(value: &Variant) -> String {
+    match value {
+        Variant::Nil => "nil".to_string(),
+        Variant::True => "true".to_string(),
+        Variant::Int(i) => i.to_string(),
+        Variant::Float(f) => format!("{:?}", f),
+        Variant::String(s) | Variant::ConstString(s, _) => format!("{:?}", s),
+        Variant::Table(t) => {
+            let entries = t
+                .iter()
+                .map(|(key, value)| format!("{}: {}", key, pretty(value)))
+                .collect::<Vec<_>>();
+            format!("{{{}}}", entries.join(", "))
+        }
+        // the saved stack frames of calls
+        _ => "<frame>".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rfvp_core::format::scenario::variant::Table;
+
+    use super::*;
+
+    fn repl() -> Repl {
+        let input = Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/testcase/Snow.hcb"));
+        Repl::new(input, Nls::ShiftJIS).unwrap()
+    }
+
+    fn run(repl: &mut Repl, line: &str) -> String {
+        repl.execute(line).unwrap().unwrap()
+    }
+
+    #[test]
+    fn test_parsing() {
+        assert_eq!(split_words(r#"host Msg "a b" 1"#).unwrap(), ["host", "Msg", "\"a b\"", "1"]);
+        assert!(split_words(r#"strings "a"#).is_err());
+        assert_eq!(parse_addr("0x4f3a").unwrap(), 0x4f3a);
+        assert_eq!(parse_addr("20282").unwrap(), 20282);
+        assert!(parse_addr("main").is_err());
+
+        assert!(matches!(parse_variant("nil"), Variant::Nil));
+        assert!(matches!(parse_variant("-3"), Variant::Int(-3)));
+        assert!(matches!(parse_variant("0.5"), Variant::Float(f) if f == 0.5));
+        assert!(matches!(parse_variant("\"1\""), Variant::String(s) if s == "1"));
+
+        let mut table = Table::new();
+        table.insert(2, Variant::True);
+        table.insert(0, Variant::String("bg".to_string()));
+        assert_eq!(pretty(&Variant::Table(table)), "{0: \"bg\", 2: true}");
+    }
+
+    #[test]
+    fn test_commands() {
+        let mut repl = repl();
+        let entry = repl.disassembler.get_scenario().get_entry_point();
+
+        let funcs = run(&mut repl, "funcs");
+        assert_eq!(funcs.lines().count(), repl.functions().len());
+
+        let dis = run(&mut repl, &format!("dis 0x{:x}", entry));
+        assert!(dis.starts_with(&format!("0x{:08x} init_stack", entry)));
+
+        // the first syscall used by any function can be found again by name
+        let (_, first) = repl
+            .insts()
+            .find(|(_, inst)| inst.mnemonic == "syscall")
+            .unwrap();
+        let (name, address) = (first.operands[0].clone(), first.address);
+        let uses = run(&mut repl, &format!("syscall {}", name));
+        assert!(uses.lines().any(|l| l.starts_with(&format!("0x{:x} ", address))));
+        assert!(run(&mut repl, &format!("complete {}", &name[..2]))
+            .split(' ')
+            .any(|n| n == name));
+
+        let (_, call) = repl.insts().find(|(_, inst)| inst.mnemonic == "call").unwrap();
+        let (target, address) = (call.operands[0].clone(), call.address);
+        let xref = run(&mut repl, &format!("xref {}", target));
+        assert!(xref.lines().any(|l| l.starts_with(&format!("0x{:x} ", address))));
+
+        let (_, string) = repl
+            .insts()
+            .find(|(_, inst)| {
+                inst.mnemonic == "push_string"
+                    && !inst.operands[0].is_empty()
+                    && !inst.operands[0].contains('"')
+            })
+            .unwrap();
+        let (text, address) = (string.operands[0].clone(), string.address);
+        let strings = run(&mut repl, &format!("strings \"{}\"", text));
+        assert!(strings.lines().any(|l| l.starts_with(&format!("0x{:x} ", address))));
+
+        assert!(repl.execute("dis main").is_err());
+        assert!(repl.execute("frobnicate").is_err());
+        assert!(repl.execute("quit").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_eval_with_host() {
+        let mut repl = repl();
+        // a short function calling a syscall
+        let function = repl
+            .functions()
+            .iter()
+            .find(|f| f.insts.iter().any(|inst| inst.mnemonic == "syscall") && f.insts.len() < 16)
+            .unwrap();
+        let addr = function.address;
+        let args = vec!["0"; function.args_count as usize].join(" ");
+        let name = function
+            .insts
+            .iter()
+            .find(|inst| inst.mnemonic == "syscall")
+            .unwrap()
+            .operands[0]
+            .clone();
+
+        run(&mut repl, &format!("host {} 42", name));
+        let output = run(&mut repl, &format!("eval 0x{:x} {}", addr, args));
+        assert!(output.contains(&format!("{}(", name)));
+        assert!(output.contains(") -> 42"));
+        assert!(output.lines().last().unwrap().starts_with("=> "));
+        assert_eq!(raw(&Variant::Int(42)), " [Int 0x0000002a]");
+        assert_eq!(raw(&Variant::String("dyn".to_string())), "");
+    }
+
+    #[test]
+    fn test_script() {
+        let mut repl = repl();
+        let path = std::env::temp_dir().join("rfvp_repl_test_script.txt");
+        std::fs::write(&path, "# comment\n\nfuncs\nhost Msg \"hi\"\nquit\nfuncs\n").unwrap();
+        let output = repl.run_script(&path).unwrap();
+        assert!(output.starts_with("> funcs\n"));
+        assert!(output.contains("> host Msg \"hi\"\nMsg -> \"hi\""));
+        // nothing runs after quit
+        assert_eq!(output.matches("> funcs").count(), 1);
+
+        std::fs::write(&path, "funcs\nfrobnicate\n").unwrap();
+        let err = repl.run_script(&path).unwrap_err();
+        assert!(format!("{:#}", err).starts_with("line 2: frobnicate"));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -5,7 +5,7 @@ use std::sync::Arc;
 use anyhow::Result;
 use kira::sound::{Sound, SoundData};
 use ringbuf::{traits::Split as _, HeapRb};
-use rfvp_core::format::audio::{AudioDecoder, AudioFile, AudioFrameSource};
+use rfvp_core::format::audio::{AudioDecoder, AudioFile, AudioFrameSource, WavDecoder, WavFile};
 
 use super::AudioSettings;
 use crate::{
@@ -27,6 +27,15 @@ impl AudioData<AudioDecoder<Arc<AudioFile>>> {
     }
 }
 
+impl AudioData<WavDecoder<Arc<WavFile>>> {
+    pub fn from_wav_file(audio: Arc<WavFile>, settings: AudioSettings) -> Self {
+        Self {
+            source: WavDecoder::new(audio),
+            settings,
+        }
+    }
+}
+
 impl<S: AudioFrameSource + Send + 'static> SoundData for AudioData<S> {
     type Error = anyhow::Error;
     type Handle = AudioHandle;
@@ -32,6 +32,17 @@ impl AudioHandle {
         )
     }
 
+    /// Starts measuring the loudness of the sound for the lip sync, until every
+    /// [`AudioEnvelope`] is dropped. Nothing is measured while there is none.
+    pub fn envelope(&self) -> AudioEnvelope {
+        self.shared
+            .envelope_readers
+            .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        AudioEnvelope {
+            shared: self.shared.clone(),
+        }
+    }
+
     /// Sets the volume of the sound.
     /// The volume is a value between 0.0 and 1.0, on the linear scale.
     pub fn set_volume(&mut self, volume: Volume, tween: Tween) -> anyhow::Result<()> {
@@ -66,3 +77,36 @@ impl AudioHandle {
         )
     }
 }
+
+/// Reads the loudness of a sound, measured on the audio thread over short windows
+pub struct AudioEnvelope {
+    shared: Arc<Shared>,
+}
+
+impl AudioEnvelope {
+    /// the RMS of the last window, as heard: after the volume and the panning
+    pub fn level(&self) -> f32 {
+        f32::from_bits(
+            self.shared
+                .amplitude
+                .load(std::sync::atomic::Ordering::Relaxed),
+        )
+    }
+
+    pub fn is_playing(&self) -> bool {
+        AudioWaitStatus::from_bits_truncate(
+            self.shared
+                .wait_status
+                .load(std::sync::atomic::Ordering::SeqCst),
+        )
+        .contains(AudioWaitStatus::PLAYING)
+    }
+}
+
+impl Drop for AudioEnvelope {
+    fn drop(&mut self) {
+        self.shared
+            .envelope_readers
+            .fetch_sub(1, std::sync::atomic::Ordering::Relaxed);
+    }
+}
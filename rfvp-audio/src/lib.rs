@@ -1,4 +1,4 @@
-//! Glue together `rfvp-core` and `kira` to provide an API to play NXA audio files.
+//! Glue together `rfvp-core` and `kira` to provide an API to play NXA and WAV audio files.
 
 mod data;
 mod handle;
@@ -7,10 +7,10 @@ mod resampler;
 mod sound;
 
 pub use data::AudioData;
-pub use handle::AudioHandle;
+pub use handle::{AudioEnvelope, AudioHandle};
 use kira::track::TrackId;
-pub use manager::AudioManager;
-pub use rfvp_core::format::audio::AudioFile;
+pub use manager::{AudioManager, DeviceLost};
+pub use rfvp_core::format::audio::{AudioFile, WavFile};
 use rfvp_core::{
     time::Tween,
     vm::command::types::{Pan, Volume},
@@ -22,5 +22,7 @@ pub struct AudioSettings {
     pub loop_start: Option<u32>,
     pub volume: Volume,
     pub pan: Pan,
+    /// the sample to start at, 0 for the start of the file
+    pub start_position: u32,
     // TODO: support play speed (needs research)
 }
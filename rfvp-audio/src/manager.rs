@@ -1,22 +1,172 @@
-use std::sync::Mutex;
+use std::{
+    fmt::Debug,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
 
-use kira::{manager::AudioManagerSettings, sound::SoundData};
+use kira::{
+    manager::{
+        backend::{cpal::CpalBackend, Backend},
+        AudioManagerSettings,
+    },
+    sound::SoundData,
+    tween::{Easing, StartTime, Tween},
+    Volume,
+};
+use tracing::{info, warn};
 
-type Backend = kira::manager::backend::cpal::CpalBackend;
+/// An error of the output stream, reported by [`DeviceBackend::pop_device_error`]
+#[derive(Debug, Clone)]
+pub struct DeviceError {
+    pub message: String,
+    /// the device went away, a headset unplugged. The other errors are glitches the stream
+    /// goes on after.
+    pub device_lost: bool,
+}
+
+/// A kira backend which reports the errors of the output device
+pub trait DeviceBackend: Backend {
+    fn pop_device_error(&mut self) -> Option<DeviceError>;
+}
+
+impl DeviceBackend for CpalBackend {
+    fn pop_device_error(&mut self) -> Option<DeviceError> {
+        self.pop_error().map(|error| DeviceError {
+            device_lost: matches!(error, cpal::StreamError::DeviceNotAvailable),
+            message: error.to_string(),
+        })
+    }
+}
 
-pub struct AudioManager {
-    manager: Mutex<kira::manager::AudioManager<Backend>>,
+/// The output device failed, see [`AudioManager::poll_device`]
+#[derive(Debug, Clone)]
+pub struct DeviceLost {
+    /// what the backend reported
+    pub error: String,
+    /// whether a new device was opened. The sounds and the tracks of the old one are gone
+    /// either way, they have to be started again
+    pub recovered: bool,
+}
+
+type DeviceLostCallback = Box<dyn FnMut(&DeviceLost) + Send>;
+
+/// How often opening a device is tried again while there is none
+const REOPEN_INTERVAL: Duration = Duration::from_secs(1);
+
+pub struct AudioManager<B: DeviceBackend = CpalBackend> {
+    manager: Mutex<kira::manager::AudioManager<B>>,
+    /// bumped every time the device is opened again
+    generation: AtomicU32,
+    on_device_lost: Mutex<Option<DeviceLostCallback>>,
+    /// the device is lost and none could be opened: the error and the last attempt
+    lost: Mutex<Option<(String, Instant)>>,
 }
 
 impl AudioManager {
     #[allow(clippy::new_without_default)]
     pub fn new() -> Self {
-        let manager = kira::manager::AudioManager::new(AudioManagerSettings::default())
-            .expect("Failed to create kira audio manager");
+        Self::open().expect("Failed to create kira audio manager")
+    }
+}
 
-        Self {
+impl<B: DeviceBackend> AudioManager<B>
+where
+    B::Settings: Default,
+    B::Error: Debug,
+{
+    /// opens the default device of the backend
+    pub fn open() -> Result<Self, B::Error> {
+        let manager = kira::manager::AudioManager::new(AudioManagerSettings::default())?;
+
+        Ok(Self {
             manager: Mutex::new(manager),
+            generation: AtomicU32::new(0),
+            on_device_lost: Mutex::new(None),
+            lost: Mutex::new(None),
+        })
+    }
+
+    /// Called by [`Self::poll_device`] when the output device is lost, for the host to tell
+    /// the player
+    pub fn set_on_device_lost(&self, callback: impl FnMut(&DeviceLost) + Send + 'static) {
+        *self.on_device_lost.lock().unwrap() = Some(Box::new(callback));
+    }
+
+    /// the number of times the device was opened again, the tracks made before the last
+    /// time don't play anymore
+    pub fn generation(&self) -> u32 {
+        self.generation.load(Ordering::Acquire)
+    }
+
+    /// Checks for the loss of the output device, like a headset unplugged, once a frame.
+    ///
+    /// When it's lost the device is opened again, the default one of the system. Everything
+    /// that played is dropped with the old device: the caller starts the sounds again,
+    /// from where they were. The other errors of the stream are only logged.
+    ///
+    /// Without a device to open it's tried again every [`REOPEN_INTERVAL`], the loss is
+    /// reported a second time, recovered, once one is plugged in.
+    pub fn poll_device(&self) -> Option<DeviceLost> {
+        self.poll_device_at(Instant::now())
+    }
+
+    fn poll_device_at(&self, now: Instant) -> Option<DeviceLost> {
+        let mut manager = self.manager.lock().unwrap();
+        let mut still_lost = self.lost.lock().unwrap();
+        let mut lost = None;
+        // errors of the same failure queue up, one recovery is enough
+        while let Some(error) = manager.backend_mut().pop_device_error() {
+            if error.device_lost {
+                lost.get_or_insert(error.message);
+            } else {
+                warn!("Audio stream error: {}", error.message);
+            }
         }
+        let (error, retry) = match (lost, still_lost.take()) {
+            (Some(error), _) => {
+                warn!("Audio device lost: {}", error);
+                (error, false)
+            }
+            (None, Some((error, last_attempt))) => {
+                if now.saturating_duration_since(last_attempt) < REOPEN_INTERVAL {
+                    *still_lost = Some((error, last_attempt));
+                    return None;
+                }
+                (error, true)
+            }
+            (None, None) => return None,
+        };
+
+        let recovered = match kira::manager::AudioManager::new(AudioManagerSettings::default()) {
+            Ok(new_manager) => {
+                *manager = new_manager;
+                self.generation.fetch_add(1, Ordering::AcqRel);
+                info!("Audio device opened again");
+                true
+            }
+            Err(err) => {
+                if !retry {
+                    warn!("Could not open an audio device: {:?}", err);
+                }
+                *still_lost = Some((error.clone(), now));
+                false
+            }
+        };
+        drop(still_lost);
+        drop(manager);
+
+        // the player was told about the loss already, only the recovery is news
+        if retry && !recovered {
+            return None;
+        }
+        let lost = DeviceLost { error, recovered };
+        if let Some(callback) = self.on_device_lost.lock().unwrap().as_mut() {
+            callback(&lost);
+        }
+        Some(lost)
     }
 
     pub fn play<S: SoundData>(&self, data: S) -> S::Handle
@@ -28,7 +178,133 @@ impl AudioManager {
         manager.play(data).expect("Failed to start playing audio")
     }
 
-    pub fn kira_manager(&self) -> &Mutex<kira::manager::AudioManager<Backend>> {
+    /// Fade the main track to silence, used on shutdown so the output doesn't stop with a pop.
+    /// Returns immediately, the caller has to wait for `duration` before dropping the manager.
+    pub fn fade_out(&self, duration: Duration) {
+        let mut manager = self.manager.lock().unwrap();
+        manager.main_track().set_volume(
+            Volume::Amplitude(0.0),
+            Tween {
+                start_time: StartTime::Immediate,
+                duration,
+                easing: Easing::Linear,
+            },
+        );
+    }
+
+    /// Pause every sound at once, they are resumed where they stopped
+    pub fn pause(&self) {
+        let mut manager = self.manager.lock().unwrap();
+        manager.pause(Tween::default());
+    }
+
+    pub fn resume(&self) {
+        let mut manager = self.manager.lock().unwrap();
+        manager.resume(Tween::default());
+    }
+
+    pub fn kira_manager(&self) -> &Mutex<kira::manager::AudioManager<B>> {
         &self.manager
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{atomic::AtomicBool, Arc};
+
+    use kira::manager::backend::{
+        mock::{MockBackend, MockBackendSettings},
+        Renderer,
+    };
+
+    use super::*;
+
+    /// what the fake device reports, shared with the backends opened again
+    static ERRORS: Mutex<Vec<DeviceError>> = Mutex::new(Vec::new());
+    static OPENED: AtomicU32 = AtomicU32::new(0);
+    static UNPLUGGED: AtomicBool = AtomicBool::new(false);
+
+    struct FakeBackend(MockBackend);
+
+    impl Backend for FakeBackend {
+        type Settings = MockBackendSettings;
+        type Error = ();
+
+        fn setup(settings: Self::Settings) -> Result<(Self, u32), Self::Error> {
+            if UNPLUGGED.load(Ordering::SeqCst) {
+                return Err(());
+            }
+            OPENED.fetch_add(1, Ordering::SeqCst);
+            MockBackend::setup(settings).map(|(backend, sample_rate)| (Self(backend), sample_rate))
+        }
+
+        fn start(&mut self, renderer: Renderer) -> Result<(), Self::Error> {
+            self.0.start(renderer)
+        }
+    }
+
+    impl DeviceBackend for FakeBackend {
+        fn pop_device_error(&mut self) -> Option<DeviceError> {
+            let mut errors = ERRORS.lock().unwrap();
+            (!errors.is_empty()).then(|| errors.remove(0))
+        }
+    }
+
+    fn report(device_lost: bool) {
+        ERRORS.lock().unwrap().push(DeviceError {
+            message: "fake".to_string(),
+            device_lost,
+        });
+    }
+
+    #[test]
+    fn test_device_lost() {
+        let manager = AudioManager::<FakeBackend>::open().unwrap();
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        manager.set_on_device_lost({
+            let calls = calls.clone();
+            move |lost: &DeviceLost| calls.lock().unwrap().push(lost.recovered)
+        });
+        assert!(manager.poll_device().is_none());
+
+        // the stream goes on after a glitch
+        report(false);
+        assert!(manager.poll_device().is_none());
+        assert_eq!(OPENED.load(Ordering::SeqCst), 1);
+        assert_eq!(manager.generation(), 0);
+
+        // opened again once for all the queued errors
+        report(false);
+        report(true);
+        report(true);
+        assert!(manager.poll_device().unwrap().recovered);
+        assert_eq!(OPENED.load(Ordering::SeqCst), 2);
+        assert_eq!(manager.generation(), 1);
+        assert!(manager.poll_device().is_none());
+
+        // no device to open
+        UNPLUGGED.store(true, Ordering::SeqCst);
+        report(true);
+        let unplugged = Instant::now();
+        assert!(!manager.poll_device_at(unplugged).unwrap().recovered);
+        assert_eq!(manager.generation(), 1);
+
+        // tried again once a second, quietly until a device shows up
+        let opened = OPENED.load(Ordering::SeqCst);
+        assert!(manager.poll_device_at(unplugged).is_none());
+        assert!(manager
+            .poll_device_at(unplugged + REOPEN_INTERVAL)
+            .is_none());
+        UNPLUGGED.store(false, Ordering::SeqCst);
+        let later = unplugged + REOPEN_INTERVAL + Duration::from_millis(500);
+        assert!(manager.poll_device_at(later).is_none());
+        assert_eq!(OPENED.load(Ordering::SeqCst), opened);
+        let lost = manager.poll_device_at(later + REOPEN_INTERVAL).unwrap();
+        assert!(lost.recovered);
+        assert_eq!(manager.generation(), 2);
+        assert!(manager
+            .poll_device_at(later + REOPEN_INTERVAL * 2)
+            .is_none());
+        assert_eq!(*calls.lock().unwrap(), [true, false, true]);
+    }
+}
@@ -13,6 +13,7 @@ use kira::{
 use ringbuf::{traits::Consumer as _, HeapCons};
 use rfvp_core::{
     format::audio::{AudioFrameSource, AudioSource},
+    lip_sync::RmsEnvelope,
     time::{Ticks, Tween, Tweener},
     vm::command::types::{AudioWaitStatus, Pan, Volume},
 };
@@ -34,8 +35,10 @@ pub(crate) struct Shared {
     // TODO: use it to implement BGMSYNC (I don't know which unit it uses)
     // in ms, relative to the start of the sound
     pub position: AtomicU32,
-    // used for lip sync
+    // used for lip sync, the last level of the envelope, as f32 bits
     pub amplitude: AtomicU32,
+    /// the envelope is only measured while someone reads it
+    pub envelope_readers: AtomicU32,
 }
 
 impl Shared {
@@ -44,6 +47,7 @@ impl Shared {
             wait_status: AtomicI32::new(0),
             position: AtomicU32::new(0),
             amplitude: AtomicU32::new(0),
+            envelope_readers: AtomicU32::new(0),
         }
     }
 }
@@ -69,9 +73,16 @@ pub struct SampleProvider<S: AudioFrameSource + Send> {
 }
 
 impl<S: AudioFrameSource + Send> SampleProvider<S> {
-    fn new(audio: S, loop_start: Option<u32>) -> Self {
+    fn new(audio: S, loop_start: Option<u32>, start_position: u32) -> Self {
+        let mut source = AudioSource::new(audio);
+        if start_position != 0 {
+            if let Err(err) = source.samples_seek(start_position) {
+                warn!("Could not seek to sample {}: {:#}", start_position, err);
+            }
+        }
+
         Self {
-            source: AudioSource::new(audio),
+            source,
             loop_start,
             resampler: Resampler::new(0),
             fractional_position: 0.0,
@@ -121,6 +132,9 @@ pub struct AudioSound<S: AudioFrameSource + Send> {
     panning: Tweener,
     volume_fade: Tweener,
     sample_provider: SampleProvider<S>,
+    /// whether an envelope reader was there at the start of this buffer
+    track_envelope: bool,
+    envelope: Option<RmsEnvelope>,
 }
 
 impl<S: AudioFrameSource + Send> AudioSound<S> {
@@ -140,7 +154,13 @@ impl<S: AudioFrameSource + Send> AudioSound<S> {
             volume: Tweener::new(data.settings.volume.0),
             panning: Tweener::new(data.settings.pan.0),
             volume_fade,
-            sample_provider: SampleProvider::new(data.source, data.settings.loop_start),
+            sample_provider: SampleProvider::new(
+                data.source,
+                data.settings.loop_start,
+                data.settings.start_position,
+            ),
+            track_envelope: false,
+            envelope: None,
         }
     }
 
@@ -195,7 +215,14 @@ impl<S: AudioFrameSource + Send> Sound for AudioSound<S> {
             self.wait_status().bits(),
             std::sync::atomic::Ordering::SeqCst,
         );
-        // TODO: compute the amplitude
+        self.track_envelope = self
+            .shared
+            .envelope_readers
+            .load(std::sync::atomic::Ordering::Relaxed)
+            > 0;
+        if !self.track_envelope {
+            self.envelope = None;
+        }
         let position = self.sample_provider.source.current_samples_position() as u64 * 1000
             / self.sample_provider.source.sample_rate() as u64;
         self.shared.position.store(
@@ -235,6 +262,17 @@ impl<S: AudioFrameSource + Send> Sound for AudioSound<S> {
             f = Frame::new(f.left * (1.0 - pan).sqrt(), f.right * pan.sqrt()) * SQRT_2
         }
 
+        if self.track_envelope {
+            let envelope = self
+                .envelope
+                .get_or_insert_with(|| RmsEnvelope::new((1.0 / dt).round() as u32));
+            if let Some(level) = envelope.push(f.left, f.right) {
+                self.shared
+                    .amplitude
+                    .store(level.to_bits(), std::sync::atomic::Ordering::Relaxed);
+            }
+        }
+
         f
     }
 
@@ -0,0 +1,59 @@
+//! Ticking many tweeners at once, like a screen full of fading and moving layers
+
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+use rfvp_core::time::{Easing, Ticks, Tween, Tweener};
+
+const MOTIONS: usize = 1000;
+/// one second at 60 fps
+const FRAMES: usize = 60;
+
+/// an alpha fade and a move on both axes for every motion
+fn tweeners() -> Vec<Tweener> {
+    let mut tweeners = Vec::with_capacity(MOTIONS * 3);
+    for i in 0..MOTIONS {
+        let duration = Ticks::from_millis(500.0 + i as f32);
+
+        let mut alpha = Tweener::new(0.0);
+        alpha.enqueue(1.0, Tween::linear(duration));
+        tweeners.push(alpha);
+
+        for target in [640.0, 480.0] {
+            let mut position = Tweener::new(0.0);
+            position.enqueue(
+                target,
+                Tween {
+                    duration,
+                    easing: Easing::SineInOut,
+                },
+            );
+            tweeners.push(position);
+        }
+    }
+    tweeners
+}
+
+fn motion(c: &mut Criterion) {
+    let frame = Ticks::from_seconds(1.0 / 60.0);
+
+    c.bench_function("motion/tick_1000_alpha_move", |b| {
+        b.iter_batched(
+            tweeners,
+            |mut tweeners| {
+                for _ in 0..FRAMES {
+                    for tweener in &mut tweeners {
+                        tweener.update(black_box(frame));
+                    }
+                }
+                tweeners
+            },
+            BatchSize::SmallInput,
+        )
+    });
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default().noise_threshold(0.05);
+    targets = motion
+}
+criterion_main!(benches);
@@ -0,0 +1,40 @@
+//! VM micro-benchmarks over synthetic scripts, see `rfvp-test-support`
+
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+use rfvp_core::{format::scenario::Scenario, vm::Scripter};
+
+fn vm(c: &mut Criterion) {
+    let cases = [
+        (
+            "arithmetic_loop",
+            rfvp_test_support::arithmetic_loop(10_000),
+        ),
+        (
+            "string_concat_loop",
+            rfvp_test_support::string_concat_loop(1_000, "ab"),
+        ),
+        ("table_loop", rfvp_test_support::table_loop(200)),
+        ("recursion", rfvp_test_support::recursion(100)),
+    ];
+
+    let mut group = c.benchmark_group("vm");
+    for (name, hcb) in cases {
+        let scenario = Scenario::new(hcb, None).unwrap();
+        group.bench_function(name, |b| {
+            // the scripter allocates all of its thread stacks upfront, keep that out of the numbers
+            b.iter_batched(
+                Scripter::new,
+                |mut scripter| rfvp_test_support::run_main!(scripter, black_box(&scenario)),
+                BatchSize::SmallInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default().noise_threshold(0.05);
+    targets = vm
+}
+criterion_main!(benches);
@@ -3,16 +3,22 @@
 //! It is a simple container storing opus frames mostly as-is. The only addition compared to usual opus formats are loop points.
 //!
 //! The header specifies loop start and loop end points in samples. When looping is enabled and loop end is reached, the decoder seeks to the loop start.
+//!
+//! Raw WAV files can be parsed too, see [`wav`].
 
 mod audio_source;
+pub mod wav;
 
 use std::io::Read;
 
 use anyhow::{bail, Result};
 pub use audio_source::{AudioBuffer, AudioFrameSource, AudioSource};
+pub use wav::{read_wav, WavDecoder, WavFile};
 use binrw::{BinRead, BinWrite};
 use opus::Channels;
 
+use crate::memory::{self, MemoryKind, MemoryTicket};
+
 #[derive(BinRead, BinWrite, Debug)]
 #[brw(little, magic = b"NXA1")]
 #[br(assert(version == 2))]
@@ -50,6 +56,8 @@ pub struct AudioInfo {
 pub struct AudioFile {
     info: AudioInfo,
     data: Vec<u8>,
+    #[allow(unused)]
+    memory: MemoryTicket,
 }
 
 impl AudioFile {
@@ -256,8 +264,11 @@ pub fn read_audio(data: &[u8]) -> Result<AudioFile> {
     let mut data = Vec::new();
     cur.read_to_end(&mut data)?;
 
+    let memory = memory::governor().track(MemoryKind::Audio, data.len() as u64);
+
     Ok(AudioFile {
         info: header.info,
         data,
+        memory,
     })
 }
@@ -0,0 +1,358 @@
+//! Support for RIFF WAVE files, for the games shipping their sound effects as raw WAV.
+//!
+//! The samples are PCM (16, 24 or 32 bits) or IEEE float (32 bits), mono or stereo,
+//! including the `WAVE_FORMAT_EXTENSIBLE` variant of the header. They are converted to
+//! stereo `f32` when the file is loaded, what kira plays: sound effects are short, so the
+//! whole file is kept decoded in memory.
+//!
+//! This is only the parser: the BGM, SE and voice players still take an NXA
+//! [`AudioFile`](super::AudioFile), nothing in the engine loads a `WavFile` yet.
+
+use anyhow::{bail, Context, Result};
+
+use super::{AudioBuffer, AudioFrameSource};
+use crate::{
+    format::bytes::{read_u16_le, read_u32_le},
+    memory::{self, MemoryKind, MemoryTicket},
+};
+
+const WAVE_FORMAT_PCM: u16 = 1;
+const WAVE_FORMAT_IEEE_FLOAT: u16 = 3;
+const WAVE_FORMAT_EXTENSIBLE: u16 = 0xFFFE;
+
+/// Amount of samples given to kira at once
+const FRAME_SAMPLES: usize = 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SampleFormat {
+    Pcm16,
+    Pcm24,
+    Pcm32,
+    Float32,
+}
+
+impl SampleFormat {
+    fn new(format_tag: u16, bits_per_sample: u16) -> Result<Self> {
+        Ok(match (format_tag, bits_per_sample) {
+            (WAVE_FORMAT_PCM, 16) => Self::Pcm16,
+            (WAVE_FORMAT_PCM, 24) => Self::Pcm24,
+            (WAVE_FORMAT_PCM, 32) => Self::Pcm32,
+            (WAVE_FORMAT_IEEE_FLOAT, 32) => Self::Float32,
+            (format_tag, bits) => bail!(
+                "Unsupported WAV sample format {:#x} with {} bits per sample",
+                format_tag,
+                bits
+            ),
+        })
+    }
+
+    fn bytes(self) -> usize {
+        match self {
+            Self::Pcm16 => 2,
+            Self::Pcm24 => 3,
+            Self::Pcm32 | Self::Float32 => 4,
+        }
+    }
+
+    /// converts one sample to `-1.0..=1.0`, `bytes` has the length of the sample
+    fn decode(self, bytes: &[u8]) -> f32 {
+        match self {
+            Self::Pcm16 => i16::from_le_bytes([bytes[0], bytes[1]]) as f32 / 32768.0,
+            // shifted to the top of an i32 and back, to extend the sign
+            Self::Pcm24 => {
+                (i32::from_le_bytes([0, bytes[0], bytes[1], bytes[2]]) >> 8) as f32 / 8388608.0
+            }
+            Self::Pcm32 => i32::from_le_bytes(bytes.try_into().unwrap()) as f32 / 2147483648.0,
+            Self::Float32 => f32::from_le_bytes(bytes.try_into().unwrap()),
+        }
+    }
+}
+
+/// A fully in-memory and decoded WAV file
+pub struct WavFile {
+    sample_rate: u32,
+    samples: Vec<(f32, f32)>,
+    #[allow(unused)]
+    memory: MemoryTicket,
+}
+
+impl WavFile {
+    /// Sample rate, in Hz
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// The stereo samples, mono ones are on both channels
+    pub fn samples(&self) -> &[(f32, f32)] {
+        &self.samples
+    }
+
+    pub fn num_samples(&self) -> u32 {
+        self.samples.len() as u32
+    }
+
+    pub fn decode(self) -> WavDecoder<Self> {
+        WavDecoder::new(self)
+    }
+}
+
+impl AsRef<WavFile> for WavFile {
+    fn as_ref(&self) -> &WavFile {
+        self
+    }
+}
+
+pub struct WavDecoder<F: AsRef<WavFile>> {
+    file: F,
+    position: usize,
+}
+
+impl<F: AsRef<WavFile>> WavDecoder<F> {
+    pub fn new(file: F) -> Self {
+        Self { file, position: 0 }
+    }
+
+    pub fn file(&self) -> &WavFile {
+        self.file.as_ref()
+    }
+}
+
+impl<F: AsRef<WavFile>> AudioFrameSource for WavDecoder<F> {
+    fn max_frame_size(&self) -> usize {
+        FRAME_SAMPLES
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.file().sample_rate
+    }
+
+    fn pre_skip(&self) -> u32 {
+        0
+    }
+
+    fn pre_roll(&self) -> u32 {
+        // the samples are already decoded, there is nothing to converge
+        0
+    }
+
+    fn read_frame(&mut self, destination: &mut AudioBuffer) -> bool {
+        let samples = &self.file.as_ref().samples;
+        if self.position >= samples.len() {
+            return false;
+        }
+
+        let end = (self.position + FRAME_SAMPLES).min(samples.len());
+        for &sample in &samples[self.position..end] {
+            destination.push(sample);
+        }
+        self.position = end;
+
+        true
+    }
+
+    fn samples_seek(&mut self, sample_position: u32) -> Result<u32> {
+        if sample_position > self.file().num_samples() {
+            bail!(
+                "Seek position {} is out of bounds (the file is {} samples)",
+                sample_position,
+                self.file().num_samples()
+            );
+        }
+
+        // any sample can start a frame
+        self.position = sample_position as usize;
+        Ok(0)
+    }
+
+    fn current_sample_position(&self) -> u32 {
+        self.position as u32
+    }
+}
+
+/// whether `data` looks like a WAV file rather than an NXA one
+pub fn is_wav(data: &[u8]) -> bool {
+    data.len() >= 12 && &data[0..4] == b"RIFF" && &data[8..12] == b"WAVE"
+}
+
+pub fn read_wav(data: &[u8]) -> Result<WavFile> {
+    if !is_wav(data) {
+        bail!("Not a RIFF WAVE file");
+    }
+
+    let mut format = None;
+    let mut samples = None;
+
+    let mut offset = 12;
+    while offset + 8 <= data.len() {
+        let id = &data[offset..offset + 4];
+        let size = read_u32_le(data, offset + 4)? as usize;
+        let start = offset + 8;
+        // some encoders write a wrong size for the last chunk, don't trust it past the end
+        let end = start.saturating_add(size).min(data.len());
+        let chunk = &data[start..end];
+
+        match id {
+            b"fmt " => {
+                let mut format_tag = read_u16_le(chunk, 0)?;
+                let channel_count = read_u16_le(chunk, 2)?;
+                let sample_rate = read_u32_le(chunk, 4)?;
+                let bits_per_sample = read_u16_le(chunk, 14)?;
+                if format_tag == WAVE_FORMAT_EXTENSIBLE {
+                    // the actual format is the first two bytes of the sub-format GUID
+                    format_tag =
+                        read_u16_le(chunk, 24).context("Truncated WAV extensible format")?;
+                }
+                format = Some((
+                    SampleFormat::new(format_tag, bits_per_sample)?,
+                    channel_count,
+                    sample_rate,
+                ));
+            }
+            b"data" => samples = Some(chunk),
+            _ => {}
+        }
+
+        // chunks are padded to an even size
+        offset = start.saturating_add(size).saturating_add(size & 1);
+    }
+
+    let Some((sample_format, channel_count, sample_rate)) = format else {
+        bail!("WAV file has no fmt chunk");
+    };
+    let Some(samples) = samples else {
+        bail!("WAV file has no data chunk");
+    };
+    if sample_rate == 0 {
+        bail!("WAV file has a sample rate of 0");
+    }
+
+    let sample_bytes = sample_format.bytes();
+    let block = |bytes: &[u8]| {
+        let left = sample_format.decode(&bytes[..sample_bytes]);
+        let right = match channel_count {
+            1 => left,
+            _ => sample_format.decode(&bytes[sample_bytes..]),
+        };
+        (left, right)
+    };
+    let samples: Vec<_> = match channel_count {
+        // a truncated last block is dropped
+        1 | 2 => samples
+            .chunks_exact(sample_bytes * channel_count as usize)
+            .map(block)
+            .collect(),
+        _ => bail!("Unsupported WAV channel count: {}", channel_count),
+    };
+
+    let memory = memory::governor().track(
+        MemoryKind::Audio,
+        std::mem::size_of_val(samples.as_slice()) as u64,
+    );
+
+    Ok(WavFile {
+        sample_rate,
+        samples,
+        memory,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::format::{
+        audio::AudioSource,
+        bytes::{write_u16_le, write_u32_le},
+    };
+
+    fn wav(format_tag: u16, channel_count: u16, bits: u16, samples: &[u8]) -> Vec<u8> {
+        let block_align = channel_count * bits / 8;
+        let mut fmt = Vec::new();
+        write_u16_le(&mut fmt, format_tag);
+        write_u16_le(&mut fmt, channel_count);
+        write_u32_le(&mut fmt, 44100);
+        write_u32_le(&mut fmt, 44100 * block_align as u32);
+        write_u16_le(&mut fmt, block_align);
+        write_u16_le(&mut fmt, bits);
+
+        let mut data = b"RIFF".to_vec();
+        write_u32_le(&mut data, (4 + 8 + fmt.len() + 8 + samples.len()) as u32);
+        data.extend_from_slice(b"WAVE");
+        for (id, chunk) in [(b"fmt ", fmt.as_slice()), (b"data", samples)] {
+            data.extend_from_slice(id);
+            write_u32_le(&mut data, chunk.len() as u32);
+            data.extend_from_slice(chunk);
+        }
+        data
+    }
+
+    fn peak(file: &WavFile) -> f32 {
+        file.samples()
+            .iter()
+            .map(|&(left, right)| left.abs().max(right.abs()))
+            .fold(0.0, f32::max)
+    }
+
+    #[test]
+    fn test_pcm24() {
+        let samples: Vec<u8> = [0, 0x400000, -0x200000, 0x7FFFFF, -0x800000]
+            .into_iter()
+            .flat_map(|sample: i32| sample.to_le_bytes()[..3].to_vec())
+            .collect();
+        let file = read_wav(&wav(WAVE_FORMAT_PCM, 1, 24, &samples)).unwrap();
+
+        assert_eq!(file.sample_rate(), 44100);
+        assert_eq!(file.num_samples(), 5);
+        assert_eq!(peak(&file), 1.0);
+        // mono is on both channels, negative samples keep their sign
+        assert_eq!(file.samples()[1], (0.5, 0.5));
+        assert_eq!(file.samples()[2], (-0.25, -0.25));
+        assert_eq!(file.samples()[4], (-1.0, -1.0));
+    }
+
+    #[test]
+    fn test_float() {
+        let samples: Vec<u8> = (0..3000)
+            .flat_map(|i| {
+                let sample = (i as f32 / 3000.0) * 0.75;
+                [sample, -sample]
+            })
+            .flat_map(f32::to_le_bytes)
+            .collect();
+        let data = wav(WAVE_FORMAT_IEEE_FLOAT, 2, 32, &samples);
+        let file = read_wav(&data).unwrap();
+
+        assert_eq!(file.num_samples(), 3000);
+        assert!((peak(&file) - 0.75).abs() < 1e-3);
+        assert_eq!(file.samples()[1500], (0.375, -0.375));
+
+        // read back over several frames
+        let mut source = AudioSource::new(file.decode());
+        let mut count = 0;
+        while source.read_sample().is_some() {
+            count += 1;
+        }
+        assert_eq!(count, 3000);
+        source.samples_seek(2000).unwrap();
+        assert_eq!(source.current_samples_position(), 2000);
+        assert_eq!(source.read_sample(), Some((0.5, -0.5)));
+    }
+
+    #[test]
+    fn test_formats() {
+        let samples: Vec<u8> = [i16::MIN, 0x4000]
+            .into_iter()
+            .flat_map(i16::to_le_bytes)
+            .collect();
+        let file = read_wav(&wav(WAVE_FORMAT_PCM, 2, 16, &samples)).unwrap();
+        assert_eq!(file.samples(), [(-1.0, 0.5)]);
+
+        let samples = (i32::MAX / 2 + 1).to_le_bytes();
+        let file = read_wav(&wav(WAVE_FORMAT_PCM, 1, 32, &samples)).unwrap();
+        assert_eq!(file.samples(), [(0.5, 0.5)]);
+
+        assert!(read_wav(&wav(WAVE_FORMAT_PCM, 1, 8, &[0x80])).is_err());
+        assert!(read_wav(&wav(WAVE_FORMAT_IEEE_FLOAT, 1, 64, &[0; 8])).is_err());
+        assert!(read_wav(&wav(WAVE_FORMAT_PCM, 6, 16, &[0; 12])).is_err());
+        assert!(read_wav(b"NXA1").is_err());
+    }
+}
@@ -0,0 +1,117 @@
+//! Little-endian reads and writes of the script and archive formats.
+//!
+//! The readers check the bounds and fail instead of panicking on truncated data.
+//! `write_*` append to a buffer, `put_*` overwrite the bytes at an offset, for
+//! patching an operand in place.
+
+use anyhow::{bail, Result};
+
+fn read_array<const N: usize>(buf: &[u8], offset: usize) -> Result<[u8; N]> {
+    match offset.checked_add(N).and_then(|end| buf.get(offset..end)) {
+        Some(bytes) => Ok(bytes.try_into().unwrap()),
+        None => bail!(
+            "offset out of bounds: {} bytes at {:#x}, the buffer is {:#x} bytes",
+            N,
+            offset,
+            buf.len()
+        ),
+    }
+}
+
+pub fn read_u8(buf: &[u8], offset: usize) -> Result<u8> {
+    Ok(read_array::<1>(buf, offset)?[0])
+}
+
+pub fn read_i8(buf: &[u8], offset: usize) -> Result<i8> {
+    Ok(read_u8(buf, offset)? as i8)
+}
+
+pub fn read_u16_le(buf: &[u8], offset: usize) -> Result<u16> {
+    read_array(buf, offset).map(u16::from_le_bytes)
+}
+
+pub fn read_i16_le(buf: &[u8], offset: usize) -> Result<i16> {
+    read_array(buf, offset).map(i16::from_le_bytes)
+}
+
+pub fn read_u32_le(buf: &[u8], offset: usize) -> Result<u32> {
+    read_array(buf, offset).map(u32::from_le_bytes)
+}
+
+pub fn read_i32_le(buf: &[u8], offset: usize) -> Result<i32> {
+    read_array(buf, offset).map(i32::from_le_bytes)
+}
+
+pub fn read_f32_le(buf: &[u8], offset: usize) -> Result<f32> {
+    read_array(buf, offset).map(f32::from_le_bytes)
+}
+
+pub fn write_u16_le(buf: &mut Vec<u8>, value: u16) {
+    buf.extend_from_slice(&value.to_le_bytes());
+}
+
+pub fn write_i16_le(buf: &mut Vec<u8>, value: i16) {
+    buf.extend_from_slice(&value.to_le_bytes());
+}
+
+pub fn write_u32_le(buf: &mut Vec<u8>, value: u32) {
+    buf.extend_from_slice(&value.to_le_bytes());
+}
+
+pub fn write_i32_le(buf: &mut Vec<u8>, value: i32) {
+    buf.extend_from_slice(&value.to_le_bytes());
+}
+
+pub fn write_f32_le(buf: &mut Vec<u8>, value: f32) {
+    buf.extend_from_slice(&value.to_le_bytes());
+}
+
+/// overwrites the bytes at `offset`, panics if they are out of bounds
+pub fn put_u16_le(buf: &mut [u8], offset: usize, value: u16) {
+    buf[offset..offset + 2].copy_from_slice(&value.to_le_bytes());
+}
+
+/// overwrites the bytes at `offset`, panics if they are out of bounds
+pub fn put_u32_le(buf: &mut [u8], offset: usize, value: u32) {
+    buf[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let mut buf = vec![0xaa];
+        write_u16_le(&mut buf, 0x1234);
+        write_i16_le(&mut buf, -2);
+        write_u32_le(&mut buf, 0xdeadbeef);
+        write_i32_le(&mut buf, i32::MIN);
+        write_f32_le(&mut buf, -1.5);
+        assert_eq!(buf[1..3], [0x34, 0x12]);
+        assert_eq!(buf.len(), 1 + 2 + 2 + 4 + 4 + 4);
+
+        assert_eq!(read_u8(&buf, 0).unwrap(), 0xaa);
+        assert_eq!(read_i8(&buf, 0).unwrap(), -0x56);
+        assert_eq!(read_u16_le(&buf, 1).unwrap(), 0x1234);
+        assert_eq!(read_i16_le(&buf, 3).unwrap(), -2);
+        assert_eq!(read_u32_le(&buf, 5).unwrap(), 0xdeadbeef);
+        assert_eq!(read_i32_le(&buf, 9).unwrap(), i32::MIN);
+        assert_eq!(read_f32_le(&buf, 13).unwrap(), -1.5);
+
+        put_u32_le(&mut buf, 5, 7);
+        put_u16_le(&mut buf, 1, 0xffff);
+        assert_eq!(read_u32_le(&buf, 5).unwrap(), 7);
+        assert_eq!(read_u16_le(&buf, 1).unwrap(), 0xffff);
+    }
+
+    #[test]
+    fn test_out_of_bounds() {
+        let buf = [1, 2, 3, 4];
+        assert_eq!(read_u32_le(&buf, 0).unwrap(), 0x04030201);
+        assert!(read_u32_le(&buf, 1).is_err());
+        assert!(read_u16_le(&buf, 3).is_err());
+        assert!(read_u8(&buf, 4).is_err());
+        assert!(read_u32_le(&buf, usize::MAX).is_err());
+    }
+}
@@ -0,0 +1,91 @@
+//! The file container of the engine's own files, the autosaves.
+//!
+//! A container is a magic, a tag (the sequence number for the autosaves), the data length
+//! and a crc32 of the data, followed by the data. Files are written to a temp file, synced
+//! and renamed into place, so a crash or a full disk never leaves a half-written file where
+//! a reader expects a complete one.
+
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+
+use super::save::crc32::crc32;
+
+/// magic, tag, data length and crc32 of the data
+pub(crate) const HEADER_SIZE: usize = 4 + 8 + 8 + 4;
+
+pub(crate) fn encode(magic: [u8; 4], tag: u64, data: &[u8]) -> Vec<u8> {
+    let mut contents = Vec::with_capacity(HEADER_SIZE + data.len());
+    contents.extend_from_slice(&magic);
+    contents.extend_from_slice(&tag.to_le_bytes());
+    contents.extend_from_slice(&(data.len() as u64).to_le_bytes());
+    contents.extend_from_slice(&crc32(data, 0).to_le_bytes());
+    contents.extend_from_slice(data);
+    contents
+}
+
+/// the tag and the data of a container, `None` if it's corrupted or has another magic
+pub(crate) fn decode(magic: [u8; 4], contents: &[u8]) -> Option<(u64, &[u8])> {
+    if contents.len() < HEADER_SIZE || contents[..4] != magic {
+        return None;
+    }
+    let tag = u64::from_le_bytes(contents[4..12].try_into().unwrap());
+    let length = u64::from_le_bytes(contents[12..20].try_into().unwrap());
+    let checksum = u32::from_le_bytes(contents[20..24].try_into().unwrap());
+    let data = &contents[HEADER_SIZE..];
+
+    (length == data.len() as u64 && crc32(data, 0) == checksum).then_some((tag, data))
+}
+
+/// Writes `contents` to `temp` with `write`, [`write_synced`] outside of tests, and renames
+/// it to `path`
+pub(crate) fn write_atomic_with(
+    path: &Path,
+    temp: &Path,
+    contents: &[u8],
+    write: impl FnOnce(&Path, &[u8]) -> io::Result<()>,
+) -> io::Result<()> {
+    if let Err(err) = write(temp, contents).and_then(|()| fs::rename(temp, path)) {
+        let _ = fs::remove_file(temp);
+        return Err(err);
+    }
+    if let Some(dir) = path.parent() {
+        sync_dir(dir);
+    }
+    Ok(())
+}
+
+pub(crate) fn write_synced(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let mut file = fs::File::create(path)?;
+    file.write_all(contents)?;
+    // the data has to be on the disk before the rename makes it visible
+    file.sync_all()
+}
+
+/// makes the rename itself durable, the file is complete either way
+fn sync_dir(dir: &Path) {
+    #[cfg(unix)]
+    if let Err(err) = fs::File::open(dir).and_then(|dir| dir.sync_all()) {
+        tracing::debug!("Could not sync directory {:?}: {}", dir, err);
+    }
+    #[cfg(not(unix))]
+    let _ = dir;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let contents = encode(*b"TEST", 42, b"data");
+        assert_eq!(contents.len(), HEADER_SIZE + 4);
+        assert_eq!(decode(*b"TEST", &contents), Some((42, b"data".as_slice())));
+
+        assert_eq!(decode(*b"OTHR", &contents), None);
+        assert_eq!(decode(*b"TEST", &contents[..contents.len() - 1]), None);
+        let mut flipped = contents.clone();
+        flipped[HEADER_SIZE] ^= 0xFF;
+        assert_eq!(decode(*b"TEST", &flipped), None);
+    }
+}
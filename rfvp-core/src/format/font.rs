@@ -0,0 +1,295 @@
+use std::fs::{self, File};
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use memmap2::Mmap;
+
+use super::vfs::Vfs;
+
+/// Fonts bigger than this are rejected, a full CJK font with every weight is below it
+pub const DEFAULT_MAX_FONT_SIZE: u64 = 30 * 1024 * 1024;
+
+/// Fonts from this size on are memory-mapped instead of read into memory
+const MMAP_THRESHOLD: u64 = 1024 * 1024;
+
+/// The directory of a game which overrides the system fonts
+const OVERRIDE_DIR: &str = "font";
+
+/// The bytes of a loaded font file
+pub enum FontData {
+    Owned(Vec<u8>),
+    Mapped(Mmap),
+}
+
+impl Deref for FontData {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            FontData::Owned(data) => data,
+            FontData::Mapped(map) => map,
+        }
+    }
+}
+
+/// Where a font was found
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FontLocation {
+    /// a file, either in the game directory or in a system font directory
+    File(PathBuf),
+    /// a file in one of the game archives, by its VFS path
+    Vfs(String),
+}
+
+/// Finds the message fonts of a game.
+///
+/// Fonts are looked up by file name, in order: the `font` directory of the game, the
+/// game archives (`font/<name>`), then the font directories of the OS. The first
+/// existing file which isn't over the size limit wins.
+pub struct FontLocator {
+    override_dir: Option<PathBuf>,
+    system_dirs: Vec<PathBuf>,
+    max_size: u64,
+}
+
+impl FontLocator {
+    pub fn new(game_root: impl AsRef<Path>) -> Self {
+        Self::with_dirs(
+            Some(game_root.as_ref().join(OVERRIDE_DIR)),
+            system_font_dirs(),
+        )
+    }
+
+    /// A locator searching other directories than the game's and the OS ones
+    pub fn with_dirs(override_dir: Option<PathBuf>, system_dirs: Vec<PathBuf>) -> Self {
+        Self {
+            override_dir,
+            system_dirs,
+            max_size: DEFAULT_MAX_FONT_SIZE,
+        }
+    }
+
+    pub fn set_max_size(&mut self, max_size: u64) {
+        self.max_size = max_size;
+    }
+
+    /// Finds the first usable font among `names`, trying each name in every location
+    /// before moving on to the next one.
+    ///
+    /// The error lists every place tried, and why the files found there were skipped.
+    pub fn locate(&self, names: &[&str], vfs: Option<&Vfs>) -> Result<FontLocation> {
+        let mut tried = Vec::new();
+
+        for name in names {
+            if let Some(dir) = &self.override_dir {
+                if let Some(location) = self.locate_in_dir(dir, name, &mut tried) {
+                    return Ok(location);
+                }
+            }
+            if let Some(location) = self.locate_in_vfs(name, vfs, &mut tried) {
+                return Ok(location);
+            }
+            for dir in &self.system_dirs {
+                if let Some(location) = self.locate_in_dir(dir, name, &mut tried) {
+                    return Ok(location);
+                }
+            }
+        }
+
+        if tried.is_empty() {
+            bail!("No usable font found, no font name was given");
+        }
+        bail!("No usable font found, tried:\n  {}", tried.join("\n  "));
+    }
+
+    fn locate_in_dir(
+        &self,
+        dir: &Path,
+        name: &str,
+        tried: &mut Vec<String>,
+    ) -> Option<FontLocation> {
+        let path = dir.join(name);
+        // portable, `MetadataExt` is unix only
+        match fs::metadata(&path) {
+            Ok(metadata) if !metadata.is_file() => {
+                tried.push(format!("{} (not a file)", path.display()))
+            }
+            Ok(metadata) if metadata.len() > self.max_size => tried.push(format!(
+                "{} (too big: {} bytes, the limit is {})",
+                path.display(),
+                metadata.len(),
+                self.max_size
+            )),
+            Ok(_) => return Some(FontLocation::File(path)),
+            Err(_) => tried.push(format!("{} (not found)", path.display())),
+        }
+        None
+    }
+
+    fn locate_in_vfs(
+        &self,
+        name: &str,
+        vfs: Option<&Vfs>,
+        tried: &mut Vec<String>,
+    ) -> Option<FontLocation> {
+        let vfs = vfs?;
+        let path = format!("{}/{}", OVERRIDE_DIR, name);
+        match vfs.archived_file_size(&path) {
+            Some(size) if size > self.max_size => tried.push(format!(
+                "archive {} (too big: {} bytes, the limit is {})",
+                path, size, self.max_size
+            )),
+            Some(_) => return Some(FontLocation::Vfs(path)),
+            None => tried.push(format!("archive {} (not found)", path)),
+        }
+        None
+    }
+
+    /// Locates and loads the first usable font among `names`
+    pub fn load(&self, names: &[&str], vfs: Option<&Vfs>) -> Result<(FontLocation, FontData)> {
+        let location = self.locate(names, vfs)?;
+        let data = match &location {
+            FontLocation::File(path) => self.load_file(path)?,
+            FontLocation::Vfs(path) => {
+                let vfs = vfs.expect("a VFS location without a VFS");
+                FontData::Owned(vfs.read_file(path)?)
+            }
+        };
+        Ok((location, data))
+    }
+
+    fn load_file(&self, path: &Path) -> Result<FontData> {
+        let file = File::open(path).with_context(|| format!("Opening font {:?}", path))?;
+        let len = file.metadata()?.len();
+        if len > self.max_size {
+            bail!("Font {:?} is too big: {} bytes", path, len);
+        }
+
+        if len >= MMAP_THRESHOLD {
+            // SAFETY: font files aren't expected to change while the game runs
+            let map =
+                unsafe { Mmap::map(&file) }.with_context(|| format!("Mapping font {:?}", path))?;
+            Ok(FontData::Mapped(map))
+        } else {
+            Ok(FontData::Owned(fs::read(path)?))
+        }
+    }
+}
+
+/// The font directories of the OS, the ones which don't exist are kept, they are
+/// only reported as tried
+pub fn system_font_dirs() -> Vec<PathBuf> {
+    let home = std::env::var_os("HOME").map(PathBuf::from);
+    let mut dirs = Vec::new();
+
+    if cfg!(target_os = "windows") {
+        let windir = std::env::var_os("WINDIR").unwrap_or_else(|| "C:\\Windows".into());
+        dirs.push(PathBuf::from(windir).join("Fonts"));
+        if let Some(local) = std::env::var_os("LOCALAPPDATA") {
+            dirs.push(PathBuf::from(local).join("Microsoft\\Windows\\Fonts"));
+        }
+    } else if cfg!(target_os = "macos") {
+        if let Some(home) = &home {
+            dirs.push(home.join("Library/Fonts"));
+        }
+        dirs.push("/Library/Fonts".into());
+        dirs.push("/System/Library/Fonts".into());
+    } else {
+        if let Some(data_home) = std::env::var_os("XDG_DATA_HOME") {
+            dirs.push(PathBuf::from(data_home).join("fonts"));
+        } else if let Some(home) = &home {
+            dirs.push(home.join(".local/share/fonts"));
+        }
+        if let Some(home) = &home {
+            dirs.push(home.join(".fonts"));
+        }
+        dirs.push("/usr/local/share/fonts".into());
+        dirs.push("/usr/share/fonts".into());
+    }
+
+    dirs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_root(name: &str) -> PathBuf {
+        let root =
+            std::env::temp_dir().join(format!("rfvp_font_test_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        root
+    }
+
+    #[test]
+    fn test_locate_order() {
+        let root = temp_root("order");
+        let game = root.join("game");
+        let system = root.join("system");
+        fs::create_dir_all(game.join(OVERRIDE_DIR)).unwrap();
+        fs::create_dir_all(&system).unwrap();
+        fs::write(system.join("msgothic.ttc"), b"system").unwrap();
+        fs::write(system.join("fallback.ttf"), b"fb").unwrap();
+
+        let mut locator =
+            FontLocator::with_dirs(Some(game.join(OVERRIDE_DIR)), vec![system.clone()]);
+        let (location, data) = locator
+            .load(&["msgothic.ttc", "fallback.ttf"], None)
+            .unwrap();
+        assert_eq!(location, FontLocation::File(system.join("msgothic.ttc")));
+        assert_eq!(&*data, b"system");
+
+        // the game's font wins over the system one
+        let game_font = game.join(OVERRIDE_DIR).join("msgothic.ttc");
+        fs::write(&game_font, b"game").unwrap();
+        let (_, data) = locator.load(&["msgothic.ttc"], None).unwrap();
+        assert_eq!(&*data, b"game");
+
+        // fonts over the limit are skipped
+        fs::remove_file(&game_font).unwrap();
+        locator.set_max_size(5);
+        let location = locator
+            .locate(&["msgothic.ttc", "fallback.ttf"], None)
+            .unwrap();
+        assert_eq!(location, FontLocation::File(system.join("fallback.ttf")));
+        locator.set_max_size(1);
+        assert!(locator
+            .locate(&["msgothic.ttc", "fallback.ttf"], None)
+            .is_err());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_locate_error_lists_paths() {
+        let root = temp_root("missing");
+        fs::create_dir_all(root.join("big")).unwrap();
+        fs::write(root.join("big").join("huge.ttf"), vec![0; 16]).unwrap();
+
+        let mut locator = FontLocator::with_dirs(Some(root.join("empty")), vec![root.join("big")]);
+        locator.set_max_size(8);
+        let err = locator.locate(&["huge.ttf"], None).unwrap_err().to_string();
+        assert!(err.contains(&root.join("empty").join("huge.ttf").display().to_string()));
+        assert!(err.contains("not found"));
+        assert!(err.contains("too big: 16 bytes"));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_mapped_font() {
+        let root = temp_root("mapped");
+        let font = vec![0xAB; MMAP_THRESHOLD as usize];
+        fs::write(root.join("large.ttf"), &font).unwrap();
+
+        let locator = FontLocator::with_dirs(None, vec![root.clone()]);
+        let (_, data) = locator.load(&["large.ttf"], None).unwrap();
+        assert!(matches!(data, FontData::Mapped(_)));
+        assert_eq!(&*data, &font[..]);
+        drop(data);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
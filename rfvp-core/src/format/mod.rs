@@ -5,10 +5,13 @@ pub mod text;
 pub mod vfs;
 
 pub mod audio;
+pub mod bytes;
+pub(crate) mod container;
+pub mod font;
 pub mod bustup;
 pub mod pic;
 pub mod save;
 pub mod scenario;
 
 #[cfg(test)]
-mod test_util;
+pub(crate) mod test_util;
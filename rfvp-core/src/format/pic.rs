@@ -5,6 +5,8 @@ use std::path::Path;
 
 use image::{GrayAlphaImage, ImageBuffer, DynamicImage};
 
+use super::bytes::{read_u16_le, read_u32_le};
+
 #[allow(clippy::enum_variant_names)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum TextureType {
@@ -53,6 +55,25 @@ pub struct NvsgTexture {
     unknown3: u32,
     unknown4: u32,
     slices: Vec<Vec<u8>>,
+    /// bumped every time the pixels change, so GPU copies know when to re-upload
+    generation: u64,
+    /// RGB, applied again when the pixels are read
+    color_key: Option<[u8; 3]>,
+}
+
+/// A snapshot of what the renderer and the scripts ask about a texture
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GraphInfo {
+    pub width: u16,
+    pub height: u16,
+    pub offset_x: u16,
+    pub offset_y: u16,
+    /// whether the pixels have been loaded
+    pub ready: bool,
+    pub generation: u64,
+    /// the RGB made transparent by [`NvsgTexture::apply_color_key`], to apply again
+    /// when the picture is loaded back from a save
+    pub color_key: Option<[u8; 3]>,
 }
 
 impl NvsgTexture {
@@ -70,28 +91,11 @@ impl NvsgTexture {
             unknown3: 0,
             unknown4: 0,
             slices: vec![],
+            generation: 0,
+            color_key: None,
         }
     }
 
-    fn read_u16le(&self, buff: &[u8], offset: usize) -> Result<u16> {
-        if buff.len() < offset + 2 {
-            bail!("buffer too small for u16");
-        }
-        Ok(u16::from_le_bytes([buff[offset], buff[offset + 1]]))
-    }
-
-    fn read_u32le(&self, buff: &[u8], offset: usize) -> Result<u32> {
-        if buff.len() < offset + 4 {
-            bail!("buffer too small for u32");
-        }
-        Ok(u32::from_le_bytes([
-            buff[offset],
-            buff[offset + 1],
-            buff[offset + 2],
-            buff[offset + 3],
-        ]))
-    }
-
     pub fn get_type(&self) -> TextureType {
         self.typ
     }
@@ -124,6 +128,40 @@ impl NvsgTexture {
         self.entry_count
     }
 
+    pub fn get_texture_ready(&self) -> bool {
+        !self.slices.is_empty()
+    }
+
+    pub fn info(&self) -> GraphInfo {
+        GraphInfo {
+            width: self.width,
+            height: self.height,
+            offset_x: self.offset_x,
+            offset_y: self.offset_y,
+            ready: self.get_texture_ready(),
+            generation: self.generation,
+            color_key: self.color_key,
+        }
+    }
+
+    /// The size of the decoded pixels in bytes
+    pub fn data_size(&self) -> usize {
+        self.slices.iter().map(|slice| slice.len()).sum()
+    }
+
+    fn bytes_per_pixel(&self) -> usize {
+        match self.typ {
+            TextureType::Single24Bit => 3,
+            TextureType::Single32Bit | TextureType::Multi32Bit => 4,
+            TextureType::Single8Bit | TextureType::Single1Bit => 1,
+        }
+    }
+
+    /// Signals that the pixels changed and any uploaded copy is stale
+    pub fn mark_dirty(&mut self) {
+        self.generation += 1;
+    }
+
     pub fn read_texture<F: FnOnce(TextureType) -> bool >(&mut self, buff: &[u8], type_callback: F) -> Result<()> {
         if buff.len() < 4 || buff[..4] != HZC1_SIGNATURE {
             bail!("Invalid HZC1 header");
@@ -135,8 +173,8 @@ impl NvsgTexture {
 
         let hzc1hdr = HZC1HDR {
             signature: [buff[0], buff[1], buff[2], buff[3]],
-            original_length: self.read_u32le(buff, 4)?,
-            header_length: self.read_u32le(buff, 8)?,
+            original_length: read_u32_le(buff, 4)?,
+            header_length: read_u32_le(buff, 8)?,
         };
 
         let data_len = buff.len() - std::mem::size_of::<HZC1HDR>();
@@ -147,20 +185,20 @@ impl NvsgTexture {
         }
 
         let signature = [data_buff[0], data_buff[1], data_buff[2], data_buff[3]];
-        self.unknown1 = self.read_u16le(data_buff, 4)?;
+        self.unknown1 = read_u16_le(data_buff, 4)?;
 
-        let typ = self.read_u16le(data_buff, 6)?;
+        let typ = read_u16_le(data_buff, 6)?;
         self.typ = typ.try_into()?;
 
-        self.width = self.read_u16le(data_buff, 8)?;
-        self.height = self.read_u16le(data_buff, 10)?;
-        self.offset_x = self.read_u16le(data_buff, 12)?;
-        self.offset_y = self.read_u16le(data_buff, 14)?;
-        self.u = self.read_u16le(data_buff, 16)?;
-        self.v = self.read_u16le(data_buff, 18)?;
-        self.entry_count = self.read_u32le(data_buff, 20)?;
-        self.unknown3 = self.read_u32le(data_buff, 24)?;
-        self.unknown4 = self.read_u32le(data_buff, 28)?;
+        self.width = read_u16_le(data_buff, 8)?;
+        self.height = read_u16_le(data_buff, 10)?;
+        self.offset_x = read_u16_le(data_buff, 12)?;
+        self.offset_y = read_u16_le(data_buff, 14)?;
+        self.u = read_u16_le(data_buff, 16)?;
+        self.v = read_u16_le(data_buff, 18)?;
+        self.entry_count = read_u32_le(data_buff, 20)?;
+        self.unknown3 = read_u32_le(data_buff, 24)?;
+        self.unknown4 = read_u32_le(data_buff, 28)?;
 
         if signature != NVSG_SIGNATURE {
             bail!("Invalid NVSG header: {:?}", &signature);
@@ -172,12 +210,7 @@ impl NvsgTexture {
 
         let data_buff = &data_buff[hzc1hdr.header_length as usize..];
 
-        let depth = match self.typ {
-            TextureType::Single24Bit => 3,
-            TextureType::Single32Bit | TextureType::Multi32Bit => 4,
-            TextureType::Single8Bit | TextureType::Single1Bit => 1,
-            _ => bail!("Invalid NVSG type: {:?}", self.typ),
-        };
+        let depth = self.bytes_per_pixel() as u64;
 
         if !type_callback(self.typ) {
             bail!("Unexpected texture type: {:?}", self.typ);
@@ -206,10 +239,18 @@ impl NvsgTexture {
                 self.slices.push(frame.to_vec());
             }
         }
+        self.key_out_color();
+        self.mark_dirty();
 
         Ok(())
     }
 
+    /// Tones every pixel of a 32-bit slice, per channel.
+    ///
+    /// A tone of 100 keeps the channel, below it the channel is scaled to that percent and
+    /// above it `tone - 100` is added. Each tone is clamped to `0..=COLOR_TONE_LIMIT` like
+    /// the hardware does, and the addition saturates at 255 instead of wrapping. The alpha
+    /// is kept.
     pub fn texture_color_tone_32(
         &mut self,
         index: usize,
@@ -225,69 +266,100 @@ impl NvsgTexture {
             bail!("Invalid texture type: {:?}", self.typ);
         }
 
+        let tone = |value: i32| value.clamp(0, COLOR_TONE_LIMIT);
+        // BGRA, like on disk
+        let tones = [tone(blue_value), tone(green_value), tone(red_value)];
+
         let texture = &mut self.slices[index];
-        let pixel_count = texture.len() / 4;
-        for i in 0..pixel_count {
-            let index = i * 4;
-            let r = texture[index] as i32;
-            let g = texture[index + 1] as i32;
-            let b = texture[index + 2] as i32;
-            let a = texture[index + 3] as i32;
-
-            let r = if red_value >= 100 {
-                if red_value > 100 {
-                    let green = g;
-                    let adjusted_red =
-                        r.saturating_add(green.saturating_mul(red_value - 100) / 0xFF);
-                    if adjusted_red > green {
-                        green
-                    } else {
-                        adjusted_red
-                    }
+        for pixel in texture.chunks_exact_mut(4) {
+            for (channel, tone) in pixel.iter_mut().zip(tones) {
+                let value = *channel as i32;
+                *channel = if tone <= 100 {
+                    value * tone / 100
                 } else {
-                    red_value * r / 100
+                    (value + tone - 100).min(0xFF)
+                } as u8;
+            }
+        }
+        self.mark_dirty();
+
+        Ok(())
+    }
+
+    /// Makes the pixels of the color `rgb` transparent, for old pictures which key on a
+    /// color instead of having an alpha channel. 24-bit textures become 32-bit ones.
+    ///
+    /// The key is kept, the pixels read afterwards are keyed too.
+    pub fn apply_color_key(&mut self, rgb: [u8; 3]) -> Result<()> {
+        if matches!(self.typ, TextureType::Single8Bit | TextureType::Single1Bit) {
+            bail!("Invalid texture type: {:?}", self.typ);
+        }
+
+        self.color_key = Some(rgb);
+        self.key_out_color();
+        self.mark_dirty();
+
+        Ok(())
+    }
+
+    fn key_out_color(&mut self) {
+        let Some([red, green, blue]) = self.color_key else {
+            return;
+        };
+        // BGR, like on disk
+        let key = [blue, green, red];
+
+        match self.typ {
+            TextureType::Single24Bit => {
+                for slice in &mut self.slices {
+                    *slice = slice
+                        .chunks_exact(3)
+                        .flat_map(|p| [p[0], p[1], p[2], if p == key { 0 } else { 0xFF }])
+                        .collect();
                 }
-            } else {
-                r
-            };
-
-            let b = if green_value >= 100 {
-                if green_value > 100 {
-                    let blue = b;
-                    let adjusted_green =
-                        b.saturating_add(blue.saturating_mul(green_value - 100) / 0xFF);
-                    if adjusted_green > blue {
-                        blue
-                    } else {
-                        adjusted_green
+                self.typ = TextureType::Single32Bit;
+            }
+            TextureType::Single32Bit | TextureType::Multi32Bit => {
+                for pixel in self.slices.iter_mut().flat_map(|s| s.chunks_exact_mut(4)) {
+                    if pixel[..3] == key {
+                        pixel[3] = 0;
                     }
-                } else {
-                    green_value * a / 100
-                }
-            } else {
-                b
-            };
-
-            let a = if blue_value < 100 {
-                blue_value * a / 100
-            } else if blue_value > 100 {
-                let blue_value = b;
-                let adjusted_blue =
-                    a.saturating_add(blue_value.saturating_mul(blue_value - 100) / 0xFF);
-                if adjusted_blue > blue_value {
-                    blue_value
-                } else {
-                    adjusted_blue
                 }
-            } else {
-                a
-            };
+            }
+            TextureType::Single8Bit | TextureType::Single1Bit => {}
+        }
+    }
+
+    /// Box blurs a slice in place, one horizontal and one vertical pass.
+    /// Samples past the edges repeat the edge pixel.
+    pub fn blur(&mut self, index: usize, radius: u32) -> Result<()> {
+        if index >= self.slices.len() {
+            bail!("Invalid index: {}", index);
+        }
+
+        let radius = radius.min(MAX_BLUR_RADIUS) as usize;
+        if radius == 0 {
+            return Ok(());
+        }
+
+        let (width, height) = (self.width as usize, self.height as usize);
+        let channels = self.bytes_per_pixel();
+        let texture = &mut self.slices[index];
+        if texture.len() < width * height * channels {
+            bail!("Texture slice is smaller than {}x{}", width, height);
+        }
 
-            texture[index] = r as u8;
-            texture[index + 1] = g as u8;
-            texture[index + 2] = b as u8;
-            texture[index + 3] = a as u8;
+        let mut line = Vec::new();
+        for y in 0..height {
+            let start = y * width * channels;
+            box_blur_line(&mut texture[start..], width, channels, channels, radius, &mut line);
+        }
+        for x in 0..width {
+            let start = x * channels;
+            let stride = width * channels;
+            box_blur_line(&mut texture[start..], height, stride, channels, radius, &mut line);
         }
+        self.mark_dirty();
 
         Ok(())
     }
@@ -421,6 +493,22 @@ impl NvsgTexture {
         Ok(())
     }
 
+    /// the alpha of a pixel of a slice, `None` outside of the texture.
+    /// the textures without alpha are opaque.
+    pub fn alpha_at(&self, index: usize, x: u32, y: u32) -> Option<u8> {
+        let slice = self.slices.get(index)?;
+        if x >= self.width as u32 || y >= self.height as u32 {
+            return None;
+        }
+        let pixel = (y * self.width as u32 + x) as usize;
+        match self.typ {
+            TextureType::Single32Bit | TextureType::Multi32Bit => slice.get(pixel * 4 + 3).copied(),
+            TextureType::Single24Bit | TextureType::Single8Bit | TextureType::Single1Bit => {
+                Some(0xff)
+            }
+        }
+    }
+
     pub fn get_texture(&self, index: usize) -> Result<DynamicImage> {
         if index >= self.slices.len() {
             bail!("Invalid index: {}", index);
@@ -444,6 +532,115 @@ impl NvsgTexture {
 
 }
 
+/// Decodes the first frame of an NVSG image to RGBA, returns the pixels, the width
+/// and the height.
+///
+/// For tools which only want to look at a picture, the engine keeps the [`NvsgTexture`]
+/// for the offsets and the other frames.
+pub fn decode_rgba(buff: &[u8]) -> Result<(Vec<u8>, u32, u32)> {
+    let mut container = NvsgTexture::new();
+    container.read_texture(buff, |_typ| true)?;
+    let image = container.get_texture(0)?.to_rgba8();
+    let (width, height) = image.dimensions();
+
+    Ok((image.into_raw(), width, height))
+}
+
+/// A single channel picture, for the masks of the dissolves: one byte per pixel instead
+/// of the four of a picture
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MaskImage {
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+}
+
+impl MaskImage {
+    pub fn new(width: u32, height: u32, pixels: Vec<u8>) -> Result<Self> {
+        if pixels.len() != width as usize * height as usize {
+            bail!("Mask of {}x{} with {} pixels", width, height, pixels.len());
+        }
+        Ok(Self {
+            width,
+            height,
+            pixels,
+        })
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    /// the value of a pixel, `None` outside of the mask
+    pub fn value_at(&self, x: u32, y: u32) -> Option<u8> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        self.pixels.get((y * self.width + x) as usize).copied()
+    }
+}
+
+/// Loads the first frame of an NVSG image as a mask.
+///
+/// The masks are 8-bit, their pixels are kept as they are. A colored picture used as a
+/// mask is turned to grayscale.
+pub fn load_mask(buff: &[u8]) -> Result<MaskImage> {
+    let mut container = NvsgTexture::new();
+    container.read_texture(buff, |_typ| true)?;
+    let (width, height) = (container.width as u32, container.height as u32);
+    let pixels = match container.typ {
+        TextureType::Single8Bit | TextureType::Single1Bit => match container.slices.first() {
+            Some(slice) => slice.clone(),
+            None => bail!("Mask without pixels"),
+        },
+        _ => container.get_texture(0)?.to_luma8().into_raw(),
+    };
+
+    MaskImage::new(width, height, pixels)
+}
+
+/// The largest tone of [`NvsgTexture::texture_color_tone_32`], adding 255 to the channels
+pub const COLOR_TONE_LIMIT: i32 = 100 + 255;
+
+const MAX_BLUR_RADIUS: u32 = 64;
+
+/// Blurs `len` pixels spaced `stride` bytes apart, each `channels` bytes wide.
+/// `line` is scratch space kept between calls.
+fn box_blur_line(
+    data: &mut [u8],
+    len: usize,
+    stride: usize,
+    channels: usize,
+    radius: usize,
+    line: &mut Vec<u8>,
+) {
+    line.clear();
+    for i in 0..len {
+        line.extend_from_slice(&data[i * stride..i * stride + channels]);
+    }
+
+    let window = (radius * 2 + 1) as u32;
+    for c in 0..channels {
+        let sample = |i: isize| line[i.clamp(0, len as isize - 1) as usize * channels + c] as u32;
+
+        // running sum over the window centered on the current pixel
+        let mut sum: u32 = (-(radius as isize)..=radius as isize).map(sample).sum();
+        for i in 0..len {
+            data[i * stride + c] = ((sum + window / 2) / window) as u8;
+            let i = i as isize;
+            sum = sum + sample(i + radius as isize + 1) - sample(i - radius as isize);
+        }
+    }
+}
+
 #[repr(C, packed)]
 #[derive(Default)]
 struct HZC1HDR {
@@ -457,6 +654,175 @@ mod tests {
     use super::*;
     use std::path::Path;
 
+    /// an HZC1 file around raw NVSG pixels
+    fn build_nvsg(typ: TextureType, width: u16, height: u16, pixels: &[u8]) -> Vec<u8> {
+        use flate2::{write::ZlibEncoder, Compression};
+        use std::io::Write;
+
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(pixels).unwrap();
+        let compressed = encoder.finish().unwrap();
+
+        let mut buff = HZC1_SIGNATURE.to_vec();
+        buff.extend_from_slice(&(pixels.len() as u32).to_le_bytes());
+        buff.extend_from_slice(&32u32.to_le_bytes());
+        buff.extend_from_slice(&NVSG_SIGNATURE);
+        buff.extend_from_slice(&0u16.to_le_bytes());
+        buff.extend_from_slice(&(typ as u16).to_le_bytes());
+        for value in [width, height, 0, 0, 0, 0] {
+            buff.extend_from_slice(&value.to_le_bytes());
+        }
+        buff.extend_from_slice(&[0; 12]);
+        buff.extend_from_slice(&compressed);
+        buff
+    }
+
+    #[test]
+    fn test_decode_rgba() {
+        // BGRA on disk
+        let pixels = [0x10, 0x20, 0x30, 0x40, 0x50, 0x60, 0x70, 0x80];
+        let buff = build_nvsg(TextureType::Single32Bit, 2, 1, &pixels);
+        let (rgba, width, height) = decode_rgba(&buff).unwrap();
+        assert_eq!((width, height), (2, 1));
+        assert_eq!(rgba, [0x30, 0x20, 0x10, 0x40, 0x70, 0x60, 0x50, 0x80]);
+
+        // 24-bit pictures come out opaque
+        let buff = build_nvsg(TextureType::Single24Bit, 1, 1, &[0x01, 0x02, 0x03]);
+        assert_eq!(decode_rgba(&buff).unwrap().0, [0x03, 0x02, 0x01, 0xFF]);
+
+        assert!(decode_rgba(&buff[..20]).is_err());
+    }
+
+    #[test]
+    fn test_color_tone_saturates() {
+        let mut container = NvsgTexture::new();
+        container.typ = TextureType::Single32Bit;
+        container.width = 1;
+        container.height = 1;
+        container.slices.push(vec![0x80, 0x80, 0x80, 0x80]);
+
+        container.texture_color_tone_32(0, 100, 100, 100).unwrap();
+        let rgba = container.get_texture(0).unwrap().to_rgba8().into_raw();
+        assert_eq!(rgba, [0x80, 0x80, 0x80, 0x80]);
+
+        container
+            .texture_color_tone_32(0, 300, 50, i32::MIN)
+            .unwrap();
+        let rgba = container.get_texture(0).unwrap().to_rgba8().into_raw();
+        assert_eq!(rgba, [0xFF, 0x40, 0x00, 0x80]);
+
+        container.typ = TextureType::Single8Bit;
+        assert!(container.texture_color_tone_32(0, 1, 1, 1).is_err());
+    }
+
+    #[test]
+    fn test_color_key() {
+        const MAGENTA: [u8; 3] = [0xFF, 0x00, 0xFF];
+        // magenta on the left column, BGR on disk
+        let pixels = [0xFF, 0x00, 0xFF, 0x10, 0x20, 0x30].repeat(2);
+        let buff = build_nvsg(TextureType::Single24Bit, 2, 2, &pixels);
+        let mut container = NvsgTexture::new();
+        container.read_texture(&buff, |_typ| true).unwrap();
+        let generation = container.info().generation;
+
+        container.apply_color_key(MAGENTA).unwrap();
+        assert_eq!(container.get_type(), TextureType::Single32Bit);
+        assert!(container.info().generation > generation);
+        assert_eq!(container.info().color_key, Some(MAGENTA));
+        let rgba = container.get_texture(0).unwrap().to_rgba8();
+        let alpha: Vec<u8> = rgba.pixels().map(|p| p[3]).collect();
+        assert_eq!(alpha, [0, 0xFF, 0, 0xFF]);
+        assert_eq!(rgba.get_pixel(1, 0).0, [0x30, 0x20, 0x10, 0xFF]);
+
+        // a picture loaded again with the key of a save
+        let mut restored = NvsgTexture::new();
+        restored.apply_color_key(MAGENTA).unwrap();
+        restored.read_texture(&buff, |_typ| true).unwrap();
+        assert_eq!(restored.get_texture(0).unwrap().to_rgba8(), rgba);
+
+        assert_eq!(restored.alpha_at(0, 0, 1), Some(0));
+        assert_eq!(restored.alpha_at(0, 1, 1), Some(0xFF));
+        assert_eq!(restored.alpha_at(0, 2, 0), None);
+
+        restored.typ = TextureType::Single8Bit;
+        assert!(restored.apply_color_key(MAGENTA).is_err());
+    }
+
+    #[test]
+    fn test_mask_dissolve() {
+        use crate::time::{MaskDissolve, Ticks};
+
+        // a gradient from left to right, on 8 bits
+        let pixels = [0x00, 0x40, 0x80, 0xFF].repeat(2);
+        let buff = build_nvsg(TextureType::Single8Bit, 4, 2, &pixels);
+        let mask = load_mask(&buff).unwrap();
+        assert_eq!((mask.width(), mask.height()), (4, 2));
+        assert_eq!(mask.pixels(), pixels);
+
+        let row = |dissolve: &MaskDissolve| -> Vec<u8> {
+            (0..4)
+                .map(|x| dissolve.alpha_at(&mask, x, 1).unwrap())
+                .collect()
+        };
+        let mut dissolve = MaskDissolve::new(Ticks::from_u32(60), 0);
+        assert_eq!(row(&dissolve), [0, 0, 0, 0]);
+        // the dark pixels go first
+        assert!(!dissolve.tick(Ticks::from_u32(30)));
+        assert_eq!(row(&dissolve), [0xFF, 0xFF, 0, 0]);
+        assert!(dissolve.tick(Ticks::from_u32(30)));
+        assert_eq!(row(&dissolve), [0xFF; 4]);
+        assert!(!dissolve.tick(Ticks::from_u32(30)));
+        assert_eq!(dissolve.alpha_at(&mask, 4, 0), None);
+
+        // a soft edge fades the pixels in over the levels of the edge
+        let mut dissolve = MaskDissolve::new(Ticks::from_u32(60), 0x80);
+        dissolve.tick(Ticks::from_u32(30));
+        let soft = row(&dissolve);
+        assert_eq!(soft, [0xFF, 0xFF, 0x80, 0]);
+
+        let mut rgba = [0xFF; 4 * 8];
+        dissolve.apply(&mask, &mut rgba);
+        let alpha: Vec<u8> = rgba[16..].chunks(4).map(|p| p[3]).collect();
+        assert_eq!(alpha, soft);
+
+        assert!(load_mask(&buff[..20]).is_err());
+    }
+
+    #[test]
+    fn test_info_generation() {
+        let mut container = NvsgTexture::new();
+        let info = container.info();
+        assert!(!info.ready);
+
+        container.mark_dirty();
+        assert!(container.info().generation > info.generation);
+    }
+
+    #[test]
+    fn test_blur_edge() {
+        let mut container = NvsgTexture::new();
+        container.typ = TextureType::Single32Bit;
+        container.width = 8;
+        container.height = 2;
+        // black on the left half, white on the right one
+        let row: Vec<u8> = (0..8u8)
+            .flat_map(|x| if x < 4 { [0, 0, 0, 0xFF] } else { [0xFF; 4] })
+            .collect();
+        container.slices.push(row.repeat(2));
+        let generation = container.info().generation;
+
+        container.blur(0, 2).unwrap();
+        assert!(container.info().generation > generation);
+
+        let texture = &container.slices[0];
+        let red: Vec<u8> = (0..8).map(|x| texture[x * 4]).collect();
+        // the edge is smeared over the radius, the far ends stay untouched
+        assert_eq!(red, [0, 0, 51, 102, 153, 204, 255, 255]);
+        assert!(texture.chunks(4).all(|p| p[3] == 0xFF));
+        // both rows blur the same, the vertical pass has nothing to mix
+        assert_eq!(texture[..32], texture[32..]);
+    }
+
     #[test]
     fn test_read_texture() {
         let filepath = Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/testcase/BGS016b"));
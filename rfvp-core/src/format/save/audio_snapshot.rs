@@ -0,0 +1,50 @@
+//! What the audio players are playing, saved with the globals so loading a save
+//! resumes the music instead of restarting or silencing it.
+//!
+//! Sounds are saved by their id in the info tables rather than by handle, applying
+//! a snapshot loads the files again and seeks to the saved position.
+
+use serde::{Deserialize, Serialize};
+
+use crate::format::audio::AudioInfo;
+
+/// A sound playing on a BGM or SE slot
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct AudioSlotSnapshotV1 {
+    /// the BGM or SE id, in the info tables of the scenario
+    pub asset_id: i32,
+    /// `false` if the sound was fading out, it's not resumed
+    pub playing: bool,
+    pub volume: f32,
+    pub pan: f32,
+    /// the sample the sound loops back to at the end, `None` if it plays once
+    pub loop_start: Option<u32>,
+    /// in ms from the start of the file
+    pub position_ms: u32,
+}
+
+impl AudioSlotSnapshotV1 {
+    /// the sample to resume at, `None` if a sound playing once would have ended.
+    /// a position past the end of a looping sound is wrapped back into the loop.
+    pub fn resume_sample(&self, info: &AudioInfo) -> Option<u32> {
+        let sample = (self.position_ms as u64 * info.sample_rate as u64 / 1000) as u32;
+        if sample < info.num_samples {
+            return Some(sample);
+        }
+
+        match self.loop_start {
+            Some(loop_start) if loop_start < info.num_samples => {
+                let loop_length = info.num_samples - loop_start;
+                Some(loop_start + (sample - loop_start) % loop_length)
+            }
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AudioManagerSnapshotV1 {
+    pub bgm: Option<AudioSlotSnapshotV1>,
+    /// indexed by SE slot
+    pub se: Vec<Option<AudioSlotSnapshotV1>>,
+}
@@ -0,0 +1,490 @@
+//! Autosaves: when to take them, and the rotating slots they are written to.
+//!
+//! Autosaves live in their own files next to the manual saves, `auto_<slot>.sav`, so the
+//! two never overwrite each other. Every file carries the sequence number of the save,
+//! the newest one is the one with the highest number.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, warn};
+
+use super::{audio_snapshot::AudioManagerSnapshotV1, text_history::TextHistory};
+use crate::{
+    format::{container, scenario::global::Global},
+    time::Playtime,
+    vm::VmSnapshot,
+};
+
+const AUTOSAVE_MAGIC: [u8; 4] = *b"RFVA";
+const AUTOSAVE_PREFIX: &str = "auto_";
+const AUTOSAVE_EXTENSION: &str = "sav";
+const TEMP_EXTENSION: &str = "tmp";
+
+#[derive(Debug, Clone, Copy)]
+pub struct AutosaveConfig {
+    /// how many autosaves are kept, the oldest one is overwritten
+    pub slot_count: u32,
+    /// also autosave after this many advanced lines, `None` to only save at scene changes
+    pub every_lines: Option<u32>,
+    /// autosaves closer than this are delayed, rapid scene changes don't thrash the disk
+    pub min_interval: Duration,
+}
+
+impl Default for AutosaveConfig {
+    fn default() -> Self {
+        Self {
+            slot_count: 3,
+            every_lines: Some(100),
+            min_interval: Duration::from_secs(30),
+        }
+    }
+}
+
+/// What asked for an autosave
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AutosaveTrigger {
+    /// the script changed scenes, or asked for an autosave itself
+    SceneTransition,
+    /// the configured number of lines were advanced since the last autosave
+    Lines,
+}
+
+/// Decides when to autosave.
+///
+/// Triggers only mark an autosave as wanted, the snapshot is taken at the next safe point:
+/// between two commands, with none of them running, so a load resumes the script threads
+/// as if the last command had just finished.
+#[derive(Debug)]
+pub struct AutosaveScheduler {
+    config: AutosaveConfig,
+    requested: Option<AutosaveTrigger>,
+    lines: u32,
+    last_save: Option<Instant>,
+}
+
+impl AutosaveScheduler {
+    pub fn new(config: AutosaveConfig) -> Self {
+        Self {
+            config,
+            requested: None,
+            lines: 0,
+            last_save: None,
+        }
+    }
+
+    pub fn config(&self) -> &AutosaveConfig {
+        &self.config
+    }
+
+    /// Asks for an autosave at the next safe point, a scene transition wins over a line count
+    pub fn request(&mut self, trigger: AutosaveTrigger) {
+        if self.requested != Some(AutosaveTrigger::SceneTransition) {
+            self.requested = Some(trigger);
+        }
+    }
+
+    /// Counts an advanced line, requesting an autosave every [`AutosaveConfig::every_lines`]
+    pub fn line_advanced(&mut self) {
+        self.lines += 1;
+        if self
+            .config
+            .every_lines
+            .is_some_and(|every| every > 0 && self.lines >= every)
+        {
+            self.request(AutosaveTrigger::Lines);
+        }
+    }
+
+    pub fn is_requested(&self) -> bool {
+        self.requested.is_some()
+    }
+
+    /// Called between the commands, returns the trigger if the snapshot has to be taken now.
+    ///
+    /// Nothing is taken unless the script is `settled`, with no command running, and
+    /// a request stays pending until [`AutosaveConfig::min_interval`] has passed since
+    /// the last autosave.
+    pub fn poll(&mut self, now: Instant, settled: bool) -> Option<AutosaveTrigger> {
+        if !settled {
+            return None;
+        }
+        if self
+            .last_save
+            .is_some_and(|last| now.saturating_duration_since(last) < self.config.min_interval)
+        {
+            return None;
+        }
+
+        let trigger = self.requested.take()?;
+        self.last_save = Some(now);
+        self.lines = 0;
+        Some(trigger)
+    }
+}
+
+/// An autosave found on disk, what the load screen lists
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AutosaveEntry {
+    pub slot: u32,
+    pub sequence: u64,
+    pub path: PathBuf,
+    /// the playtime when it was taken
+    pub playtime: Playtime,
+}
+
+/// The rotating autosave slots in a save directory.
+///
+/// Slots are [containers](crate::format::container) tagged with the sequence number, a crash
+/// in the middle of a write leaves the previous autosave intact.
+#[derive(Debug, Clone)]
+pub struct AutosaveStore {
+    dir: PathBuf,
+    slot_count: u32,
+}
+
+impl AutosaveStore {
+    /// Opens the autosaves of `dir`, creating it if needed, and drops the temp files of
+    /// writes which never finished
+    pub fn open(dir: impl AsRef<Path>, slot_count: u32) -> Result<Self> {
+        if slot_count == 0 {
+            bail!("At least one autosave slot is needed");
+        }
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir).with_context(|| format!("Creating save directory {:?}", dir))?;
+
+        for dir_entry in fs::read_dir(&dir)? {
+            let path = dir_entry?.path();
+            let is_autosave_temp = path.extension().is_some_and(|ext| ext == TEMP_EXTENSION)
+                && path
+                    .file_name()
+                    .is_some_and(|name| name.to_string_lossy().starts_with(AUTOSAVE_PREFIX));
+            if is_autosave_temp {
+                debug!("Removing unfinished autosave {:?}", path);
+                let _ = fs::remove_file(&path);
+            }
+        }
+
+        Ok(Self { dir, slot_count })
+    }
+
+    fn slot_path(&self, slot: u32) -> PathBuf {
+        self.dir
+            .join(format!("{}{}", AUTOSAVE_PREFIX, slot))
+            .with_extension(AUTOSAVE_EXTENSION)
+    }
+
+    /// The valid autosaves, newest first, for the load screen. Corrupted slots are skipped.
+    pub fn list(&self) -> Result<Vec<AutosaveEntry>> {
+        let mut entries = Vec::new();
+        for slot in 0..self.slot_count {
+            let path = self.slot_path(slot);
+            let data = match fs::read(&path) {
+                Ok(data) => data,
+                Err(err) if err.kind() == io::ErrorKind::NotFound => continue,
+                Err(err) => return Err(err).with_context(|| format!("Reading {:?}", path)),
+            };
+            let decoded = decode_autosave(&data).and_then(|(sequence, snapshot)| {
+                Some((sequence, parse_save_state(snapshot).ok()?))
+            });
+            match decoded {
+                Some((sequence, state)) => entries.push(AutosaveEntry {
+                    slot,
+                    sequence,
+                    path,
+                    playtime: state.globals.playtime(),
+                }),
+                None => warn!("Skipping corrupted autosave {:?}", path),
+            }
+        }
+        entries.sort_by_key(|entry| std::cmp::Reverse(entry.sequence));
+        Ok(entries)
+    }
+
+    /// The sequence number for the next autosave
+    pub fn next_sequence(&self) -> Result<u64> {
+        Ok(self.list()?.first().map_or(0, |entry| entry.sequence + 1))
+    }
+
+    /// Writes the snapshot `data` as autosave number `sequence`, over the slot of the
+    /// autosave `slot_count` saves older
+    pub fn write(&self, sequence: u64, data: &[u8]) -> Result<PathBuf> {
+        self.write_with(sequence, data, container::write_synced)
+    }
+
+    fn write_with(
+        &self,
+        sequence: u64,
+        data: &[u8],
+        write: impl FnOnce(&Path, &[u8]) -> io::Result<()>,
+    ) -> Result<PathBuf> {
+        let path = self.slot_path((sequence % self.slot_count as u64) as u32);
+        let temp = path.with_extension(TEMP_EXTENSION);
+        let contents = container::encode(AUTOSAVE_MAGIC, sequence, data);
+        container::write_atomic_with(&path, &temp, &contents, write)
+            .with_context(|| format!("Writing autosave {:?}", path))?;
+        Ok(path)
+    }
+
+    /// The snapshot stored in an autosave
+    pub fn read(&self, entry: &AutosaveEntry) -> Result<Vec<u8>> {
+        let data = fs::read(&entry.path).with_context(|| format!("Reading {:?}", entry.path))?;
+        match decode_autosave(&data) {
+            Some((_, snapshot)) => Ok(snapshot.to_vec()),
+            None => bail!("Autosave {:?} is corrupted", entry.path),
+        }
+    }
+}
+
+/// The snapshot stored in an autosave: the script globals and threads, what the audio plays
+/// and the backlog.
+#[derive(Debug, Default, Deserialize)]
+pub struct SaveState {
+    pub globals: Global,
+    /// `None` in the autosaves from before the threads were saved, they restart the script
+    #[serde(default)]
+    pub vm_v1: Option<VmSnapshot>,
+    /// empty in the autosaves from before audio was saved
+    #[serde(default)]
+    pub audio_v1: AudioManagerSnapshotV1,
+    /// empty in the autosaves from before the backlog was saved
+    #[serde(default)]
+    pub history_v1: TextHistory,
+}
+
+#[derive(Serialize)]
+struct SaveStateRef<'a> {
+    globals: &'a Global,
+    vm_v1: &'a VmSnapshot,
+    audio_v1: &'a AudioManagerSnapshotV1,
+    history_v1: &'a TextHistory,
+}
+
+pub fn save_state_snapshot(
+    global: &Global,
+    vm: &VmSnapshot,
+    audio: &AudioManagerSnapshotV1,
+    history: &TextHistory,
+) -> Result<Vec<u8>> {
+    let state = SaveStateRef {
+        globals: global,
+        vm_v1: vm,
+        audio_v1: audio,
+        history_v1: history,
+    };
+    Ok(serde_yaml::to_string(&state)?.into_bytes())
+}
+
+/// parses a snapshot of [`save_state_snapshot`], or an older one holding only the globals
+pub fn parse_save_state(snapshot: &[u8]) -> Result<SaveState> {
+    if let Ok(state) = serde_yaml::from_slice::<SaveState>(snapshot) {
+        return Ok(state);
+    }
+    let globals = serde_yaml::from_slice(snapshot).context("Parsing the autosave snapshot")?;
+    Ok(SaveState {
+        globals,
+        ..Default::default()
+    })
+}
+
+/// the sequence number and the snapshot of an autosave file, `None` if it's corrupted
+fn decode_autosave(data: &[u8]) -> Option<(u64, &[u8])> {
+    container::decode(AUTOSAVE_MAGIC, data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::format::{
+        audio::AudioInfo,
+        save::{audio_snapshot::AudioSlotSnapshotV1, text_history::HistoryLine},
+        scenario::variant::Variant,
+    };
+    use crate::vm::Scripter;
+
+    fn temp_root(name: &str) -> PathBuf {
+        let root = std::env::temp_dir().join(format!(
+            "rfvp_autosave_test_{}_{}",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&root);
+        root
+    }
+
+    /// a snapshot played for `secs`
+    fn snapshot(secs: u64) -> Vec<u8> {
+        let mut global = Global::new();
+        *global.playtime_mut() = Playtime::from_secs(secs);
+        let vm = Scripter::new().snapshot();
+        save_state_snapshot(&global, &vm, &Default::default(), &TextHistory::new(10)).unwrap()
+    }
+
+    #[test]
+    fn test_safe_point_scheduling() {
+        let mut scheduler = AutosaveScheduler::new(AutosaveConfig {
+            slot_count: 3,
+            every_lines: Some(3),
+            min_interval: Duration::from_secs(10),
+        });
+        let start = Instant::now();
+        assert_eq!(scheduler.poll(start, true), None);
+
+        // waits for the running command to finish
+        scheduler.request(AutosaveTrigger::SceneTransition);
+        assert_eq!(scheduler.poll(start, false), None);
+        assert_eq!(
+            scheduler.poll(start, true),
+            Some(AutosaveTrigger::SceneTransition)
+        );
+
+        // throttled, the request is kept until the interval is over
+        scheduler.request(AutosaveTrigger::SceneTransition);
+        assert_eq!(scheduler.poll(start + Duration::from_secs(5), true), None);
+        assert!(scheduler.is_requested());
+        let later = start + Duration::from_secs(10);
+        assert_eq!(
+            scheduler.poll(later, true),
+            Some(AutosaveTrigger::SceneTransition)
+        );
+
+        for _ in 0..2 {
+            scheduler.line_advanced();
+        }
+        assert!(!scheduler.is_requested());
+        scheduler.line_advanced();
+        let later = later + Duration::from_secs(10);
+        assert_eq!(scheduler.poll(later, true), Some(AutosaveTrigger::Lines));
+    }
+
+    #[test]
+    fn test_save_state_round_trip() {
+        let mut global = Global::new();
+        global.set(4, Variant::Int(12));
+        *global.playtime_mut() = Playtime::from_secs(3 * 3600 + 25);
+        let bgm = AudioSlotSnapshotV1 {
+            asset_id: 7,
+            playing: true,
+            volume: 0.8,
+            pan: 0.0,
+            loop_start: Some(48000),
+            position_ms: 83_250,
+        };
+        let mut audio = AudioManagerSnapshotV1 {
+            bgm: Some(bgm),
+            se: vec![None; 32],
+        };
+        audio.se[3] = Some(AudioSlotSnapshotV1 {
+            asset_id: 2,
+            playing: false,
+            pan: -0.5,
+            loop_start: None,
+            ..bgm
+        });
+
+        let mut history = TextHistory::new(10);
+        history.push_line(HistoryLine::from_message("Ange@rHello."));
+
+        let mut scripter = Scripter::new();
+        scripter.start_main(0x10);
+        let snapshot =
+            save_state_snapshot(&global, &scripter.snapshot(), &audio, &history).unwrap();
+        let state = parse_save_state(&snapshot).unwrap();
+        assert_eq!(state.globals.get(4).and_then(Variant::as_int), Some(12));
+        let mut resumed = Scripter::new();
+        resumed.restore(&state.vm_v1.unwrap()).unwrap();
+        assert_eq!(resumed.get_thread(0).get_pc(), 0x10);
+        assert_eq!(state.globals.playtime(), Playtime::from_secs(3 * 3600 + 25));
+        assert_eq!(state.audio_v1, audio);
+        assert_eq!(state.history_v1, history);
+
+        // a 60s track at 48kHz looping from 1s: 83.25s in is 23.25s past the loop start
+        let info = AudioInfo {
+            sample_rate: 48000,
+            channel_count: 2,
+            frame_size: 0,
+            frame_samples: 960,
+            pre_skip: 312,
+            num_samples: 60 * 48000,
+            loop_start: 48000,
+            loop_end: 60 * 48000,
+        };
+        let resumed = state.audio_v1.bgm.unwrap().resume_sample(&info).unwrap();
+        assert_eq!(resumed, 48000 + 23_250 * 48);
+        let mut once = bgm;
+        once.loop_start = None;
+        assert_eq!(once.resume_sample(&info), None);
+
+        // autosaves from before the audio was saved still load
+        let old = serde_yaml::to_string(&global).unwrap();
+        let state = parse_save_state(old.as_bytes()).unwrap();
+        assert_eq!(state.globals.get(4).and_then(Variant::as_int), Some(12));
+        assert!(state.vm_v1.is_none());
+        assert_eq!(state.audio_v1, AudioManagerSnapshotV1::default());
+        assert!(state.history_v1.is_empty());
+    }
+
+    #[test]
+    fn test_rotation() {
+        let root = temp_root("rotation");
+        let store = AutosaveStore::open(&root, 3).unwrap();
+        assert_eq!(store.next_sequence().unwrap(), 0);
+
+        for sequence in 0..5u64 {
+            store.write(sequence, &snapshot(sequence * 60)).unwrap();
+        }
+
+        // the two oldest were overwritten
+        let entries = store.list().unwrap();
+        let sequences: Vec<_> = entries.iter().map(|e| e.sequence).collect();
+        assert_eq!(sequences, [4, 3, 2]);
+        assert_eq!(entries[0].slot, 1);
+        assert_eq!(entries[0].playtime, Playtime::from_secs(240));
+        assert_eq!(store.read(&entries[0]).unwrap(), snapshot(240));
+        assert_eq!(store.next_sequence().unwrap(), 5);
+
+        // manual saves in the same directory are left alone
+        fs::write(root.join("save_0.sav"), b"manual").unwrap();
+        assert_eq!(store.list().unwrap().len(), 3);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_failed_write_keeps_previous() {
+        let root = temp_root("failure");
+        let store = AutosaveStore::open(&root, 1).unwrap();
+        store.write(0, &snapshot(1)).unwrap();
+
+        // the disk fills up halfway through the next autosave
+        let err = store
+            .write_with(1, b"newer snapshot", |path, contents| {
+                fs::write(path, &contents[..contents.len() / 2])?;
+                Err(io::Error::other("disk full"))
+            })
+            .unwrap_err();
+        assert!(format!("{:#}", err).contains("disk full"));
+
+        let entries = store.list().unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(store.read(&entries[0]).unwrap(), snapshot(1));
+        assert_eq!(fs::read_dir(&root).unwrap().count(), 1);
+
+        // a crash leaves a temp file behind, it's dropped on the next start
+        fs::write(root.join("auto_0.tmp"), b"half").unwrap();
+        let store = AutosaveStore::open(&root, 1).unwrap();
+        assert_eq!(fs::read_dir(&root).unwrap().count(), 1);
+
+        // a torn slot is skipped rather than loaded
+        let path = &store.list().unwrap()[0].path;
+        let data = fs::read(path).unwrap();
+        fs::write(path, &data[..data.len() - 1]).unwrap();
+        assert!(store.list().unwrap().is_empty());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -8,7 +8,11 @@ use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
 use serde_big_array::BigArray;
 
-mod crc32;
+pub mod audio_snapshot;
+pub mod autosave;
+pub mod text_history;
+pub mod voice_config;
+pub(crate) mod crc32;
 mod obfuscation;
 
 type Endian = bitbuffer::BigEndian;
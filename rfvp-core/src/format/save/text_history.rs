@@ -0,0 +1,157 @@
+//! The lines the player has read, for the backlog scrolled back with the mouse wheel.
+//!
+//! A line is recorded once it's fully displayed, with the speaker split from the text
+//! like the message box does. Only the last lines are kept, the history is saved with
+//! the rest of the state so the backlog is still there after a load.
+
+use std::collections::VecDeque;
+
+use serde::{Deserialize, Serialize};
+
+use crate::layout::{LayouterParser, ParsedCommand};
+
+/// The number of lines kept by default
+pub const DEFAULT_HISTORY_CAPACITY: usize = 500;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HistoryLine {
+    /// `None` for the narration
+    pub speaker: Option<String>,
+    pub text: String,
+    /// the voice file of the line, from its `@v` command
+    pub voice_id: Option<String>,
+}
+
+impl HistoryLine {
+    /// the line of a message in the layouter syntax: the first line is the speaker, even
+    /// when it's empty, and the layout commands are dropped
+    pub fn from_message(message: &str) -> Self {
+        let mut speaker = String::new();
+        let mut text = String::new();
+        let mut voice_id = None;
+        let mut in_speaker = true;
+        for command in LayouterParser::new(message) {
+            match command {
+                ParsedCommand::Char(c) if in_speaker => speaker.push(c),
+                ParsedCommand::Char(c) => text.push(c),
+                ParsedCommand::Newline if in_speaker => in_speaker = false,
+                ParsedCommand::Newline => text.push('\n'),
+                ParsedCommand::Voice(voice) => {
+                    voice_id.get_or_insert(voice);
+                }
+                _ => {}
+            }
+        }
+        Self {
+            speaker: (!speaker.is_empty()).then_some(speaker),
+            text,
+            voice_id,
+        }
+    }
+}
+
+/// The last lines displayed, the oldest one is dropped when it's full
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TextHistory {
+    capacity: usize,
+    lines: VecDeque<HistoryLine>,
+}
+
+impl Default for TextHistory {
+    fn default() -> Self {
+        Self::new(DEFAULT_HISTORY_CAPACITY)
+    }
+}
+
+impl TextHistory {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            lines: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn len(&self) -> usize {
+        self.lines.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty()
+    }
+
+    pub fn push_line(&mut self, line: HistoryLine) {
+        if self.capacity == 0 {
+            return;
+        }
+        while self.lines.len() >= self.capacity {
+            self.lines.pop_front();
+        }
+        self.lines.push_back(line);
+    }
+
+    /// the `n` newest lines, oldest first
+    pub fn iter_recent(&self, n: usize) -> impl DoubleEndedIterator<Item = &HistoryLine> + '_ {
+        self.lines.range(self.lines.len().saturating_sub(n)..)
+    }
+
+    pub fn clear(&mut self) {
+        self.lines.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(index: usize) -> HistoryLine {
+        HistoryLine {
+            speaker: None,
+            text: format!("line {}", index),
+            voice_id: None,
+        }
+    }
+
+    #[test]
+    fn test_eviction() {
+        let mut history = TextHistory::new(100);
+        for index in 0..200 {
+            history.push_line(line(index));
+        }
+        assert_eq!(history.len(), 100);
+        let texts = history
+            .iter_recent(usize::MAX)
+            .map(|line| line.text.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(texts.first(), Some(&"line 100"));
+        assert_eq!(texts.last(), Some(&"line 199"));
+
+        let newest = history.iter_recent(3).rev().map(|line| line.text.as_str());
+        assert_eq!(
+            newest.collect::<Vec<_>>(),
+            ["line 199", "line 198", "line 197"]
+        );
+
+        let saved = serde_yaml::to_string(&history).unwrap();
+        assert_eq!(
+            serde_yaml::from_str::<TextHistory>(&saved).unwrap(),
+            history
+        );
+    }
+
+    #[test]
+    fn test_from_message() {
+        let line = HistoryLine::from_message("@v00/awase0001.@c900.Ange@r@c.Hello,@k@rworld.");
+        assert_eq!(line.speaker.as_deref(), Some("Ange"));
+        assert_eq!(line.text, "Hello,\nworld.");
+        assert_eq!(line.voice_id.as_deref(), Some("00/awase0001"));
+
+        let line = HistoryLine::from_message("@rNarration.");
+        assert_eq!(line.speaker, None);
+        assert_eq!(line.text, "Narration.");
+        assert_eq!(line.voice_id, None);
+    }
+}
@@ -0,0 +1,110 @@
+//! The voice options of the player, kept between sessions.
+//!
+//! Characters are muted by the character id of their voices, the directory the voice files
+//! are in: `00/awase6042_o` is a line of character 0. The ids are part of the script, so
+//! the same id is the same character in the next session and the table is saved as is.
+//!
+//! ```toml
+//! auto_advance_padding_ms = 500.0
+//! muted_characters = [3, 12]
+//! ```
+
+use std::{collections::BTreeSet, fs, io, path::Path};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::time::DEFAULT_VOICE_PADDING_MS;
+
+/// The name of the voice options, in the save directory
+pub const VOICE_CONFIG_FILE: &str = "voice.toml";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VoiceConfig {
+    /// the pause of the auto mode between the end of a voice and the next message
+    pub auto_advance_padding_ms: f32,
+    /// the characters whose lines aren't voiced
+    pub muted_characters: BTreeSet<i32>,
+}
+
+impl Default for VoiceConfig {
+    fn default() -> Self {
+        Self {
+            auto_advance_padding_ms: DEFAULT_VOICE_PADDING_MS,
+            muted_characters: BTreeSet::new(),
+        }
+    }
+}
+
+/// The character id of a message voice, see the [module](self) docs
+pub fn voice_character_id(voice: &str) -> Option<i32> {
+    let (dir, _) = voice.split_once('/')?;
+    dir.parse().ok()
+}
+
+impl VoiceConfig {
+    pub fn parse(content: &str) -> Result<Self> {
+        toml::from_str(content).context("Parsing the voice options")
+    }
+
+    /// The options saved in the save directory `dir`, the defaults until they are saved
+    pub fn load(dir: &Path) -> Result<Self> {
+        let path = dir.join(VOICE_CONFIG_FILE);
+        match fs::read_to_string(&path) {
+            Ok(content) => Self::parse(&content),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(err).with_context(|| format!("Reading {:?}", path)),
+        }
+    }
+
+    pub fn to_toml(&self) -> Result<String> {
+        Ok(toml::to_string(self)?)
+    }
+
+    /// consulted before playing a line, a muted line is reported as finished right away
+    pub fn is_muted(&self, character_id: i32) -> bool {
+        self.muted_characters.contains(&character_id)
+    }
+
+    pub fn set_muted(&mut self, character_id: i32, muted: bool) {
+        if muted {
+            self.muted_characters.insert(character_id);
+        } else {
+            self.muted_characters.remove(&character_id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let mut config = VoiceConfig::default();
+        config.set_muted(12, true);
+        config.set_muted(3, true);
+        config.set_muted(7, false);
+        assert!(config.is_muted(3));
+        assert!(!config.is_muted(7));
+
+        let parsed = VoiceConfig::parse(&config.to_toml().unwrap()).unwrap();
+        assert_eq!(parsed, config);
+        config.set_muted(12, false);
+        assert_eq!(config.muted_characters.iter().collect::<Vec<_>>(), [&3]);
+
+        // older files without some of the options
+        let parsed = VoiceConfig::parse("muted_characters = [5]").unwrap();
+        assert!(parsed.is_muted(5));
+        assert_eq!(parsed.auto_advance_padding_ms, DEFAULT_VOICE_PADDING_MS);
+    }
+
+    #[test]
+    fn test_voice_character_id() {
+        assert_eq!(voice_character_id("00/awase6042_o"), Some(0));
+        assert_eq!(voice_character_id("12/line"), Some(12));
+        assert_eq!(voice_character_id("narration"), None);
+        assert_eq!(voice_character_id("voice/000000001"), None);
+    }
+}
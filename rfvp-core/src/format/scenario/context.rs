@@ -1,15 +1,68 @@
 
+use std::collections::HashMap;
 use std::mem::size_of;
+use std::sync::atomic::{AtomicU32, Ordering};
 
-use crate::{format::scenario::global::GLOBAL, vm::command::Command};
+use crate::{format::scenario::global::GLOBAL, vm::{command::{strings, Command, CommandResult}, VmConfig}};
 use crate::format::scenario::Scenario;
 use crate::format::scenario::variant::Variant;
 use crate::format::scenario::instructions::Opcode;
 
 use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
 
 static MAX_STACK_SIZE: usize = 0x100;
 
+/// the table writes with a bad key are reported this many times, a script doing it in a
+/// loop would flood the log
+const NON_INT_KEY_WARNINGS: u32 = 16;
+static NON_INT_KEY_WARNED: AtomicU32 = AtomicU32::new(0);
+
+fn warn_non_int_key(pc: usize, key: &Variant) {
+    let warned = NON_INT_KEY_WARNED.fetch_add(1, Ordering::Relaxed);
+    if warned < NON_INT_KEY_WARNINGS {
+        log::warn!(
+            "{:#x}: table key is not an integer: {:?}, the write is dropped",
+            pc,
+            key
+        );
+    } else if warned == NON_INT_KEY_WARNINGS {
+        log::warn!(
+            "{:#x}: more table writes with a bad key, not reported anymore",
+            pc
+        );
+    }
+}
+
+/// The strings of the PushString instructions already decoded, by offset. The script
+/// never changes while it runs, so nothing is invalidated: the cache is dropped when it
+/// grows large, the text of a whole route goes through it once, and by
+/// [`Scripter::reset`](crate::vm::Scripter::reset), which can start another script
+#[derive(Debug, Clone, Default)]
+struct ConstStringCache {
+    strings: HashMap<usize, String>,
+    /// how many strings were read from the script, the others came from the cache
+    decodes: u64,
+}
+
+impl ConstStringCache {
+    const MAX_STRINGS: usize = 4096;
+
+    fn get(&mut self, scenario: &Scenario, offset: usize, len: usize) -> Result<String> {
+        if let Some(s) = self.strings.get(&offset) {
+            return Ok(s.clone());
+        }
+
+        let s = scenario.read_cstring(offset, len)?;
+        self.decodes += 1;
+        if self.strings.len() >= Self::MAX_STRINGS {
+            self.strings.clear();
+        }
+        self.strings.insert(offset, s.clone());
+        Ok(s)
+    }
+}
+
 #[derive(Debug, Clone, Default)]
 pub struct StackFrame {
     pub args_count: u16,
@@ -44,6 +97,8 @@ pub struct Context {
     wait_ms: u64,
     should_exit: bool,
     should_break: bool,
+    config: VmConfig,
+    const_strings: ConstStringCache,
 }
 
 pub const CONTEXT_STATUS_NONE: u32 = 0;
@@ -52,8 +107,28 @@ pub const CONTEXT_STATUS_WAIT: u32 = 2;
 pub const CONTEXT_STATUS_SLEEP: u32 = 4;
 pub const CONTEXT_STATUS_DISSOLVE_WAIT: u32 = 16;
 
+/// The state of a thread to resume it from a save: where it is, its stack and what it waits on
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContextSnapshot {
+    pc: usize,
+    start_addr: u32,
+    state: u32,
+    wait_ms: u64,
+    stack_base: usize,
+    stack_pos: usize,
+    /// the stack up to its top, the slots above are nil
+    stack: Vec<Variant>,
+    return_value: Variant,
+}
+
 impl Context {
     pub fn new(start_addr: u32) -> Self {
+        Self::with_args(start_addr, Vec::new())
+    }
+
+    /// start by running the routine at `start_addr` as if the script called it with `args`,
+    /// returning from it moves the program counter to 0
+    pub fn with_args(start_addr: u32, args: Vec<Variant>) -> Self {
         let mut ctx = Context {
             id: 0,
             stack: vec![Variant::Nil; MAX_STACK_SIZE],
@@ -66,22 +141,94 @@ impl Context {
             wait_ms: 0,
             should_exit: false,
             should_break: false,
+            config: VmConfig::default(),
+            const_strings: ConstStringCache::default(),
         };
 
+        ctx.enter(args);
+        ctx
+    }
+
+    /// return to the state of `Context::new(start_addr)`, keeping the stack allocation and
+    /// the decoded strings.
+    /// the values left on the stack are dropped, releasing the strings and tables they hold.
+    pub fn reset(&mut self, start_addr: u32) {
+        self.stack.fill(Variant::Nil);
+        self.cursor = start_addr as usize;
+        self.cur_stack_pos = 0;
+        self.cur_stack_base = 0;
+        self.start_addr = start_addr;
+        self.return_value = Variant::Nil;
+        self.state = CONTEXT_STATUS_NONE;
+        self.wait_ms = 0;
+        self.should_exit = false;
+        self.should_break = false;
+        self.enter(Vec::new());
+    }
+
+    pub fn snapshot(&self) -> ContextSnapshot {
+        let top = (self.cur_stack_base + self.cur_stack_pos).min(self.stack.len());
+        ContextSnapshot {
+            pc: self.cursor,
+            start_addr: self.start_addr,
+            state: self.state,
+            wait_ms: self.wait_ms,
+            stack_base: self.cur_stack_base,
+            stack_pos: self.cur_stack_pos,
+            stack: self.stack[..top].to_vec(),
+            return_value: self.return_value.clone(),
+        }
+    }
+
+    /// resume the thread of `snapshot`, keeping the stack allocation and the decoded strings
+    pub fn restore(&mut self, snapshot: &ContextSnapshot) -> Result<()> {
+        let top = snapshot.stack_base + snapshot.stack_pos;
+        if snapshot.stack.len() != top || top > MAX_STACK_SIZE {
+            bail!(
+                "Invalid thread snapshot: {} stack slots, top at {}",
+                snapshot.stack.len(),
+                top
+            );
+        }
+
+        self.stack.fill(Variant::Nil);
+        self.stack[..top].clone_from_slice(&snapshot.stack);
+        self.cursor = snapshot.pc;
+        self.cur_stack_pos = snapshot.stack_pos;
+        self.cur_stack_base = snapshot.stack_base;
+        self.start_addr = snapshot.start_addr;
+        self.return_value = snapshot.return_value.clone();
+        self.state = snapshot.state;
+        self.wait_ms = snapshot.wait_ms;
+        self.should_exit = false;
+        self.should_break = false;
+        Ok(())
+    }
+
+    /// push the arguments and the initial stack frame of the entry routine.
+    /// without arguments slot 0 is left nil and the frame is at slot 1, like in the original
+    /// engine: a thread started in the middle of a function reads nil from `-2`.
+    fn enter(&mut self, args: Vec<Variant>) {
+        if args.is_empty() {
+            self.push(Variant::Nil).unwrap();
+        }
+        for arg in args {
+            self.push(arg).unwrap();
+        }
+        let slots = self.cur_stack_pos;
+
         // the initial stack frame
-        ctx.push(Variant::SavedStackInfo(
+        self.push(Variant::SavedStackInfo(
             crate::format::scenario::variant::SavedStackInfo { 
                 stack_base: 0, 
-                stack_pos: 0, 
+                stack_pos: slots, 
                 return_addr: 0,
                 args: 0,
             }
         )).unwrap();
 
-        ctx.cur_stack_base = ctx.cur_stack_pos;
-        ctx.cur_stack_pos = 0;
-
-        ctx
+        self.cur_stack_base = self.cur_stack_pos;
+        self.cur_stack_pos = 0;
     }
 
     pub fn set_should_break(&mut self, should_break: bool) {
@@ -288,9 +435,14 @@ impl Context {
         if !scenario.is_code_area(addr) {
             bail!("call: address is not in the code area");
         }
+        let addr = scenario.resolve_function(addr);
 
         tracing::trace!("call: {:x}", addr);
+        self.enter_routine(addr)
+    }
 
+    /// push the frame returning to the current position and jump to `addr`
+    fn enter_routine(&mut self, addr: u32) -> Result<()> {
         let frame = Variant::SavedStackInfo(
             crate::format::scenario::variant::SavedStackInfo { 
                 stack_base: self.cur_stack_base, 
@@ -310,6 +462,45 @@ impl Context {
         Ok(())
     }
 
+    /// call the routine at `addr` with `args` from a syscall, the thread runs it when resumed
+    /// and comes back to the instruction after the syscall
+    pub fn call_subroutine(
+        &mut self,
+        scenario: &Scenario,
+        addr: u32,
+        args: Vec<Variant>,
+    ) -> Result<()> {
+        if !scenario.is_code_area(addr) {
+            bail!("call_subroutine: 0x{:x} is not in the code area", addr);
+        }
+        let addr = scenario.resolve_function(addr);
+
+        // the arguments and the frame, a callback calling back into itself ends here
+        if self.to_global_offset()? + args.len() + 1 > self.stack.len() {
+            bail!("call_subroutine: stack overflow calling 0x{:x}", addr);
+        }
+
+        tracing::trace!("call_subroutine: {:x} {:?}", addr, &args);
+        for arg in args {
+            self.push(arg)?;
+        }
+        self.enter_routine(addr)
+    }
+
+    /// apply the result of the command of a syscall, before resuming the thread
+    pub fn apply_result(&mut self, scenario: &Scenario, result: CommandResult) -> Result<()> {
+        match result {
+            CommandResult::None => Ok(()),
+            CommandResult::WriteR0(value) => {
+                self.return_value = value;
+                Ok(())
+            }
+            CommandResult::CallSubroutine { target, args } => {
+                self.call_subroutine(scenario, target, args)
+            }
+        }
+    }
+
     /// 0x03 syscall
     /// call a system call
     pub fn syscall(&mut self, scenario: &Scenario) -> Result<Command> {
@@ -327,456 +518,15 @@ impl Context {
             args.reverse();
 
             tracing::trace!("syscall: {} {:?}", &syscall.name, &args);
-            let proxy = match syscall.name.as_str() {
-                "AudioLoad" => {
-                    Command::AudioLoad{ args }
-                },
-                "AudioPlay" => {
-                    Command::AudioPlay{ args }
-                },
-                "AudioSilentOn" => {
-                    Command::AudioSilentOn{ args }
-                },
-                "AudioState" => {
-                    Command::AudioState{ args }
-                },
-                "AudioStop" => {
-                    Command::AudioStop{ args }
-                },
-                "AudioType" => {
-                    Command::AudioType{ args }
-                },
-                "AudioVol" => {
-                    Command::AudioVol{ args }
-                },
-                "ColorSet" => {
-                    Command::ColorSet{ args }
-                },
-                "ControlMask" => {
-                    Command::ControlMask{ args }
-                },
-                "ControlPulse" => {
-                    Command::ControlPulse{ args }
-                },
-                "CursorChange" => {
-                    Command::CursorChange{ args }
-                },
-                "CursorMove" => {
-                    Command::CursorMove{ args }
-                },
-                "CursorShow" => {
-                    Command::CursorShow{ args }
-                },
-                "Debmess" => {
-                    Command::Debmess{ args }
-                },
-                "Dissolve" => {
-                    Command::Dissolve{ args }
-                },
-                "DissolveWait" => {
-                    Command::DissolveWait{ args }
-                },
-                "ExitDialog" => {
-                    Command::ExitDialog{ args }
-                },
-                "ExitMode" => {
-                    Command::ExitMode{ args }
-                },
-                "FlagGet" => {
-                    Command::FlagGet{ args }
-                },
-                "FlagSet" => {
-                    Command::FlagSet{ args }
-                },
-                "FloatToInt" => {
-                    Command::FloatToInt{ args }
-                },
-                "GaijiLoad" => {
-                    Command::GaijiLoad{ args }
-                },
-                "GraphLoad" => {
-                    Command::GraphLoad{ args }
-                },
-                "GraphRGB" => {
-                    Command::GraphRGB{ args }
-                },
-                "IntToText" => {
-                    Command::IntToText{ args }
-                },
-                "HistoryGet" => {
-                    Command::HistoryGet{ args }
-                },
-                "HistorySet" => {
-                    Command::HistorySet{ args }
-                },
-                "InputFlash" => {
-                    Command::InputFlash{ args }
-                },
-                "InputGetCursIn" => {
-                    Command::InputGetCursIn{ args }
-                },
-                "InputGetCursX" => {
-                    Command::InputGetCursX{ args }
-                },
-                "InputGetCursY" => {
-                    Command::InputGetCursY{ args }
-                },
-                "InputGetDown" => {
-                    Command::InputGetDown{ args }
-                },
-                "InputGetEvent" => {
-                    Command::InputGetEvent{ args }
-                },
-                "InputGetRepeat" => {
-                    Command::InputGetRepeat{ args }
-                },
-                "InputGetState" => {
-                    Command::InputGetState{ args }
-                },
-                "InputGetUp" => {
-                    Command::InputGetUp{ args }
-                },
-                "InputGetWheel" => {
-                    Command::InputGetWheel{ args }
-                },
-                "InputSetClick" => {
-                    Command::InputSetClick { args }
-                },
-                "LipAnim" => {
-                    Command::LipAnim{ args }
-                },
-                "LipSync" => {
-                    Command::LipSync{ args }
-                },
-                "Load" => {
-                    Command::Load{ args }
-                },
-                "MenuMessSkip" => {
-                    Command::MenuMessSkip{ args }
-                },
-                "MotionAlpha" => {
-                    Command::MotionAlpha{ args }
-                },
-                "MotionAlphaStop" => {
-                    Command::MotionAlphaStop{ args }
-                },
-                "MotionAlphaTest" => {
-                    Command::MotionAlphaTest{ args }
-                },
-                "MotionAnim" => {
-                    Command::MotionAnim{ args }
-                },
-                "MotionAnimStop" => {
-                    Command::MotionAnimStop{ args }
-                },
-                "MotionAnimTest" => {
-                    Command::MotionAnimTest{ args }
-                },
-                "MotionMove" => {
-                    Command::MotionMove{ args }
-                },
-                "MotionMoveStop" => {
-                    Command::MotionMoveStop{ args }
-                },
-                "MotionMoveTest" => {
-                    Command::MotionMoveTest{ args }
-                },
-                "MotionMoveR" => {
-                    Command::MotionMoveR{ args }
-                },
-                "MotionMoveRStop" => {
-                    Command::MotionMoveRStop{ args }
-                },
-                "MotionMoveRTest" => {
-                    Command::MotionMoveRTest{ args }
-                },
-                "MotionMoveS2" => {
-                    Command::MotionMoveS2{ args }
-                },
-                "MotionMoveS2Stop" => {
-                    Command::MotionMoveS2Stop{ args }
-                },
-                "MotionMoveS2Test" => {
-                    Command::MotionMoveS2Test{ args }
-                },
-                "MotionMoveZ" => {
-                    Command::MotionMoveZ{ args }
-                },
-                "MotionMoveZStop" => {
-                    Command::MotionMoveZStop{ args }
-                },
-                "MotionMoveZTest" => {
-                    Command::MotionMoveZTest{ args }
-                },
-                "MotionPause" => {
-                    Command::MotionPause{ args }
-                },
-                "Movie" => {
-                    Command::Movie{ args }
-                },
-                "MovieState" => {
-                    Command::MovieState{ args }
-                },
-                "MovieStop" => {
-                    Command::MovieStop{ args }
-                },
-                "PartsAssign" => {
-                    Command::PartsAssign{ args }
-                },
-                "PartsLoad" => {
-                    Command::PartsLoad{ args }
-                },
-                "PartsMotion" => {
-                    Command::PartsMotion{ args }
-                },
-                "PartsMotionPause" => {
-                    Command::PartsMotionPause{ args }
-                },
-                "PartsMotionStop" => {
-                    Command::PartsMotionStop{ args }
-                },
-                "PartsMotionTest" => {
-                    Command::PartsMotionTest{ args }
-                },
-                "PartsRGB" => {
-                    Command::PartsRGB{ args }
-                },
-                "PartsSelect" => {
-                    Command::PartsSelect{ args }
-                },
-                "PrimExitGroup" => {
-                    Command::PrimExitGroup{ args }
-                },
-                "PrimGroupIn" => {
-                    Command::PrimGroupIn{ args }
-                },
-                "PrimGroupMove" => {
-                    Command::PrimGroupMove{ args }
-                },
-                "PrimGroupOut" => {
-                    Command::PrimGroupOut{ args }
-                },
-                "PrimHit" => {
-                    Command::PrimHit{ args }
-                },
-                "PrimSetAlpha" => {
-                    Command::PrimSetAlpha{ args }
-                },
-                "PrimSetBlend" => {
-                    Command::PrimSetBlend{ args }
-                },
-                "PrimSetDraw" => {
-                    Command::PrimSetDraw{ args }
-                },
-                "PrimSetNull" => {
-                    Command::PrimSetNull{ args }
-                },
-                "PrimSetOP" => {
-                    Command::PrimSetOP{ args }
-                },
-                "PrimSetRS" => {
-                    Command::PrimSetRS{ args }
-                },
-                "PrimSetRS2" => {
-                    Command::PrimSetRS2{ args }
-                },
-                "PrimSetSnow" => {
-                    Command::PrimSetSnow{ args }
-                },
-                "PrimSetSprt" => {
-                    Command::PrimSetSprt{ args }
-                },
-                "PrimSetText" => {
-                    Command::PrimSetText{ args }
-                },
-                "PrimSetTile" => {
-                    Command::PrimSetTile{ args }
-                },
-                "PrimSetUV" => {
-                    Command::PrimSetUV{ args }
-                },
-                "PrimSetWH" => {
-                    Command::PrimSetWH{ args }
-                },
-                "PrimSetXY" => {
-                    Command::PrimSetXY{ args }
-                },
-                "PrimSetZ" => {
-                    Command::PrimSetZ{ args }
-                },
-                "Rand" => {
-                    Command::Rand{ args }
-                },
-                "SaveCreate" => {
-                    Command::SaveCreate{ args }
-                },
-                "SaveThumbSize" => {
-                    Command::SaveThumbSize{ args }
-                },
-                "SaveData" => {
-                    Command::SaveData{ args }
-                },
-                "SaveWrite" => {
-                    Command::SaveWrite{ args }
-                },
-                "Snow" => {
-                    Command::Snow{ args }
-                },
-                "SnowStart" => {
-                    Command::SnowStart{ args }
-                },
-                "SnowStop" => {
-                    Command::SnowStop{ args }
-                },
-                "SoundLoad" => {
-                    Command::SoundLoad{ args }
-                },
-                "SoundMasterVol" => {
-                    Command::SoundMasterVol{ args }
-                },
-                "SoundPlay" => {
-                    Command::SoundPlay{ args }
-                },
-                "SoundSilentOn" => {
-                    Command::SoundSilentOn{ args }
-                },
-                "SoundStop" => {
-                    Command::SoundStop{ args }
-                },
-                "SoundType" => {
-                    Command::SoundType{ args }
-                },
-                "SoundTypeVol" => {
-                    Command::SoundTypeVol{ args }
-                },
-                "SoundVol" => {
-                    Command::SoundVol{ args }
-                },
-                "SysAtSkipName" => {
-                    Command::SysAtSkipName{ args }
-                },
-                "SysProjFolder" => {
-                    Command::SysProjFolder{ args }
-                },
-                "TextBuff" => {
-                    Command::TextBuff{ args }
-                },
-                "TextClear" => {
-                    Command::TextClear{ args }
-                },
-                "TextColor" => {
-                    Command::TextColor{ args }
-                },
-                "TextFont" => {
-                    Command::TextFont{ args }
-                },
-                "TextFontCount" => {
-                    Command::TextFontCount{ args }
-                },
-                "TextFontGet" => {
-                    Command::TextFontGet{ args }
-                },
-                "TextFontName" => {
-                    Command::TextFontName{ args }
-                },
-                "TextFontSet" => {
-                    Command::TextFontSet{ args }
-                },
-                "TextFormat" => {
-                    Command::TextFormat{ args }
-                },
-                "TextFunction" => {
-                    Command::TextFunction{ args }
-                },
-                "TextOutSize" => {
-                    Command::TextOutSize{ args }
-                },
-                "TextPause" => {
-                    Command::TextPause{ args }
-                },
-                "TextPos" => {
-                    Command::TextPos{ args }
-                },
-                "TextPrint" => {
-                    Command::TextPrint{ args }
-                },
-                "TextRepaint" => {
-                    Command::TextRepaint{ args }
-                },
-                "TextShadowDist" => {
-                    Command::TextShadowDist{ args }
-                },
-                "TextSize" => {
-                    Command::TextSize{ args }
-                },
-                "TextSkip" => {
-                    Command::TextSkip{ args }
-                },
-                "TextSpace" => {
-                    Command::TextSpace{ args }
-                },
-                "TextSpeed" => {
-                    Command::TextSpeed{ args }
-                },
-                "TextSuspendChr" => {
-                    Command::TextSuspendChr{ args }
-                },
-                "TextTest" => {
-                    Command::TextTest{ args }
-                },
-                "ThreadExit" => {
-                    Command::ThreadExit{ args }
-                },
-                "ThreadNext" => {
-                    Command::ThreadNext{ args }
-                },
-                "ThreadRaise" => {
-                    Command::ThreadRaise{ args }
-                },
-                "ThreadSleep" => {
-                    Command::ThreadSleep{ args }
-                },
-                "ThreadStart" => {
-                    Command::ThreadStart{ args }
-                },
-                "ThreadWait" => {
-                    Command::ThreadWait{ args }
-                },
-                "TimerGet" => {
-                    Command::TimerGet{ args }
-                },
-                "TimerSet" => {
-                    Command::TimerSet{ args }
-                },
-                "TimerSuspend" => {
-                    Command::TimerSuspend{ args }
-                },
-                "TitleMenu" => {
-                    Command::TitleMenu{ args }
-                },
-                "V3DMotion" => {
-                    Command::V3DMotion{ args }
-                },
-                "V3DMotionPause" => {
-                    Command::V3DMotionPause{ args }
-                },
-                "V3DMotionStop" => {
-                    Command::V3DMotionStop{ args }
-                },
-                "V3DMotionTest" => {
-                    Command::V3DMotionTest{ args }
-                },
-                "V3DSet" => {
-                    Command::V3DSet{ args }
-                },
-                "WindowMode" => {
-                    Command::WindowMode{ args }
-                },
-                _ => {
-                    bail!("syscall not found: {}", &syscall.name);
+            match Command::from_syscall(&syscall.name, args) {
+                Some(proxy) => return Ok(proxy),
+                None if scenario.is_tolerant() => {
+                    log::warn!("unresolved syscall {} returns nil", &syscall.name);
+                    self.return_value = Variant::Nil;
+                    return Ok(Command::Unresolved { name: syscall.name.clone() });
                 }
-            };
-            return Ok(proxy);
+                None => bail!("syscall not found: {}", &syscall.name),
+            }
         }
 
         panic!("syscall should not reach here, id: {}", id);
@@ -787,21 +537,7 @@ impl Context {
     pub fn ret(&mut self) -> Result<()> {
         self.cursor += 1;
         self.return_value = Variant::Nil;
-        let frame = self.get_local(-1)?;
-        if let Some(frame) = frame.as_saved_stack_info() {
-            self.cur_stack_pos = frame.stack_pos;
-            self.cur_stack_base = frame.stack_base;
-            self.cursor = frame.return_addr;
-
-            // pop the arguments
-            for _ in 0..frame.args {
-                self.pop()?;
-            }
-        } else {
-            self.print_stack();
-            bail!("ret: invalid stack frame: {:?}", &frame);
-        }
-        Ok(())
+        self.leave_routine("ret")
     }
 
     /// 0x05 retv instruction
@@ -809,19 +545,29 @@ impl Context {
     pub fn retv(&mut self) -> Result<()> {
         self.cursor += 1;
         self.return_value = self.pop()?;
+        self.leave_routine("retv")
+    }
+
+    /// restore the frame of the caller and pop the arguments
+    fn leave_routine(&mut self, name: &str) -> Result<()> {
         let frame = self.get_local(-1)?;
-        if let Some(frame) = frame.as_saved_stack_info() {
-            self.cur_stack_pos = frame.stack_pos;
-            self.cur_stack_base = frame.stack_base;
-            self.cursor = frame.return_addr;
-
-            // pop the arguments
-            for _ in 0..frame.args {
-                self.pop()?;
-            }
-        } else {
+        let Some(frame) = frame.as_saved_stack_info() else {
             self.print_stack();
-            bail!("retv: invalid stack frame");
+            bail!("{}: invalid stack frame: {:?}", name, &frame);
+        };
+
+        self.cur_stack_pos = frame.stack_pos;
+        self.cur_stack_base = frame.stack_base;
+        self.cursor = frame.return_addr;
+
+        // a thread entry declares arguments nobody pushed, when it's started by address
+        let args = if frame.return_addr == 0 {
+            frame.args.min(frame.stack_pos)
+        } else {
+            frame.args
+        };
+        for _ in 0..args {
+            self.pop()?;
         }
         Ok(())
     }
@@ -933,7 +679,7 @@ impl Context {
         let len = scenario.read_u8(self.cursor)? as usize;
         self.cursor += size_of::<u8>();
 
-        let s = scenario.read_cstring(self.cursor, len)?;
+        let s = self.const_strings.get(scenario, self.cursor, len)?;
         self.cursor += len;
 
         tracing::trace!("push_string: {}", &s);
@@ -987,8 +733,8 @@ impl Context {
         tracing::trace!("push_global_table: {:x} {:?}", key, &top);
         if let Some(table) = GLOBAL.lock().unwrap().get_mut(key) {
             if let Some(table) = table.as_table() {
-                if let Some(table_key) = top.as_int() {
-                    if let Some(value) = table.get(table_key as u32) {
+                if let Some(table_key) = top.as_table_key() {
+                    if let Some(value) = table.get(table_key) {
                         self.push(value.clone())?;
                     } else {
                         self.push(Variant::Nil)?;
@@ -1018,12 +764,12 @@ impl Context {
         let idx = scenario.read_i8(self.cursor)?;
         self.cursor += size_of::<i8>();
 
-        let key = self.pop()?.as_int();
+        let key = self.pop()?.as_table_key();
 
         let mut local = self.get_local(idx)?;
         if let Some(table) = local.as_table() {
             if let Some(table_key) = key {
-                if let Some(value) = table.get(table_key as u32) {
+                if let Some(value) = table.get(table_key) {
                     self.push(value.clone())?;
                 } else {
                     self.push(Variant::Nil)?;
@@ -1085,13 +831,21 @@ impl Context {
 
     /// 0x17 pop global table
     /// pop the top of the stack and store it in the global table by key
+    ///
+    /// Checked against the original engine: a key which isn't an int drops the value and
+    /// the key, and leaves the global as it is. It's not turned into an empty table.
     pub fn pop_global_table(&mut self, scenario: &Scenario) -> Result<()> {
+        let pc = self.cursor;
         self.cursor += 1;
         let key = scenario.read_u16(self.cursor)?;
         self.cursor += size_of::<u16>();
 
         let value = self.pop()?;
         let mkey = self.pop()?;
+        let Some(mkey) = mkey.as_table_key() else {
+            warn_non_int_key(pc, &mkey);
+            return Ok(());
+        };
 
         if let Some(table) = GLOBAL.lock().unwrap().get_mut(key) {
             // cast to table if it is not
@@ -1100,11 +854,7 @@ impl Context {
             }
 
             if let Some(table) = table.as_table() {
-                if let Some(mkey) = mkey.as_int() {
-                    table.insert(mkey as u32, value);
-                } else {
-                    log::warn!("top of the stack is not an integer");
-                }
+                table.insert(mkey, value);
             } else {
                 log::warn!("the value in the global table is not a table");
             }
@@ -1116,24 +866,27 @@ impl Context {
 
     /// 0x18 pop local table 
     /// pop the top of the stack and store it in the local table by key
+    ///
+    /// Like [`Context::pop_global_table`], a key which isn't an int leaves the local as it is.
     pub fn pop_local_table(&mut self, scenario: &Scenario) -> Result<()> {
+        let pc = self.cursor;
         self.cursor += 1;
         let idx = scenario.read_i8(self.cursor)?;
         self.cursor += size_of::<i8>();
 
         let value = self.pop()?;
-        let key = self.pop()?.as_int();
+        let key = self.pop()?;
+        let Some(key) = key.as_table_key() else {
+            warn_non_int_key(pc, &key);
+            return Ok(());
+        };
 
         let local = self.get_local_mut(idx)?;
         if !local.is_table() {
             local.cast_table();
         }
         if let Some(table) = local.as_table() {
-            if let Some(table_key) = key {
-                table.insert(table_key as u32, value);
-            } else {
-                log::warn!("key is not an integer");
-            }
+            table.insert(key, value);
         } else {
             log::warn!("local is not a table");
         }
@@ -1161,7 +914,7 @@ impl Context {
         let mut a = self.pop()?;
 
         tracing::trace!("add: {:?} {:?}", &a, &b);
-        a.vadd(&b);
+        a.vadd_with(&b, self.config.int_overflow);
         self.push(a)?;
         Ok(())
     }
@@ -1174,7 +927,7 @@ impl Context {
         let mut a = self.pop()?;
 
         tracing::trace!("sub: {:?} {:?}", &a, &b);
-        a.vsub(&b);
+        a.vsub_with(&b, self.config.int_overflow);
         self.push(a)?;
         Ok(())
     }
@@ -1187,7 +940,7 @@ impl Context {
         let mut a = self.pop()?;
 
         tracing::trace!("mul: {:?} {:?}", &a, &b);
-        a.vmul(&b);
+        a.vmul_with(&b, self.config.int_overflow);
         self.push(a)?;
         Ok(())
     }
@@ -1344,6 +1097,30 @@ impl Context {
         self.cursor
     }
 
+    /// how many PushString strings were decoded from the script, the repeated ones are not
+    pub fn const_string_decodes(&self) -> u64 {
+        self.const_strings.decodes
+    }
+
+    /// forgets the decoded strings, before running another script
+    pub fn clear_const_strings(&mut self) {
+        self.const_strings = ConstStringCache::default();
+    }
+
+    /// the VM settings the opcodes follow, e.g. the integer overflow, see [`VmConfig`]
+    pub fn set_config(&mut self, config: VmConfig) {
+        self.config = config;
+    }
+
+    pub fn get_return_value(&self) -> &Variant {
+        &self.return_value
+    }
+
+    /// the value a syscall hands back to the script, picked up by push_return
+    pub fn set_return_value(&mut self, value: Variant) {
+        self.return_value = value;
+    }
+
     /// get waiting time for the context in ms
     pub fn get_waiting_time(&self) -> u64 {
         self.wait_ms
@@ -1390,7 +1167,10 @@ impl Context {
                 self.call(scenario)?;
             }
             Ok(Opcode::Syscall) => {
-                self.syscall(scenario)?;
+                let command = self.syscall(scenario)?;
+                if let Some(value) = strings::evaluate(&command) {
+                    self.return_value = value;
+                }
             }
             Ok(Opcode::Ret) => {
                 self.ret()?;
@@ -1510,3 +1290,399 @@ impl Context {
     }
 
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::format::scenario::{
+        global::GLOBAL_TEST_LOCK, split_string_literal, variant::Table, Nls,
+    };
+    use crate::format::test_util::build_hcb;
+    use rfvp_test_support::CodeBuilder;
+
+    fn script() -> bytes::Bytes {
+        // 0x04: NoSuchCall(1); ThreadNext()
+        let code = [0x01, 0, 0, 0x0a, 7, 0, 0, 0, 0x03, 0, 0, 0x03, 1, 0, 0x04];
+        build_hcb(&code, 4, &[(1, "NoSuchCall"), (0, "ThreadNext")])
+    }
+
+    #[test]
+    fn test_tolerant_stubs_unknown_syscall() {
+        let scenario = Scenario::new_tolerant(script(), None).unwrap();
+        assert_eq!(scenario.get_unresolved_syscalls(), &["NoSuchCall".to_string()]);
+
+        let mut context = Context::new(scenario.get_entry_point());
+        context.return_value = Variant::Int(1);
+        context.dispatch_opcode(&scenario).unwrap();
+        context.dispatch_opcode(&scenario).unwrap();
+
+        let command = context.syscall(&scenario).unwrap();
+        assert!(matches!(command, Command::Unresolved { .. }));
+        assert!(context.return_value.is_nil());
+        // the argument has been consumed
+        assert_eq!(context.cur_stack_pos, 0);
+
+        let command = context.syscall(&scenario).unwrap();
+        assert!(matches!(command, Command::ThreadNext { .. }));
+    }
+
+    #[test]
+    fn test_strict_rejects_unknown_syscall() {
+        let scenario = Scenario::new(script(), None).unwrap();
+        assert!(scenario.get_unresolved_syscalls().is_empty());
+
+        let mut context = Context::new(scenario.get_entry_point());
+        context.dispatch_opcode(&scenario).unwrap();
+        context.dispatch_opcode(&scenario).unwrap();
+        assert!(context.syscall(&scenario).is_err());
+    }
+
+    #[test]
+    fn test_syscall_calls_subroutine() {
+        let mut code = rfvp_test_support::CodeBuilder::new();
+        // 0x04: main, local0 = Debmess(41); return local0
+        code.init_stack(0, 1);
+        code.push_i32(41).syscall(0);
+        code.push_return().pop_stack(0);
+        code.push_stack(0).retv();
+        // the callback, returns its argument + 1
+        let callback = code.addr();
+        code.init_stack(1, 0).push_stack(-2).push_i32(1).add().retv();
+        let scenario = Scenario::new(build_hcb(code.code(), 4, &[(1, "Debmess")]), None).unwrap();
+
+        // the host answers the syscall by calling back into the script with its argument
+        let mut context = Context::new(scenario.get_entry_point());
+        let mut syscalls = 0;
+        while context.get_pc() != 0 {
+            let opcode = scenario.read_u8(context.get_pc()).unwrap() as i32;
+            if let Ok(Opcode::Syscall) = opcode.try_into() {
+                let command = context.syscall(&scenario).unwrap();
+                let args = vec![command.args().unwrap().get(0).clone()];
+                let result = CommandResult::CallSubroutine {
+                    target: callback,
+                    args,
+                };
+                context.apply_result(&scenario, result).unwrap();
+                syscalls += 1;
+            } else {
+                context.dispatch_opcode(&scenario).unwrap();
+            }
+        }
+
+        assert_eq!(syscalls, 1);
+        assert_eq!(context.get_return_value().as_int(), Some(42));
+        assert_eq!(context.cur_stack_base, 0);
+
+        // calls which never return run out of stack instead of overwriting it
+        let mut context = Context::new(scenario.get_entry_point());
+        let mut depth = 0;
+        while context
+            .call_subroutine(&scenario, callback, vec![Variant::Int(depth)])
+            .is_ok()
+        {
+            depth += 1;
+        }
+        assert_eq!(depth, (MAX_STACK_SIZE as i32 - 1) / 2);
+        assert_eq!(context.get_pc(), callback as usize);
+        assert!(context.call_subroutine(&scenario, 0x1000, vec![]).is_err());
+    }
+
+    #[test]
+    fn test_thread_entry() {
+        let mut code = rfvp_test_support::CodeBuilder::new();
+        // 0x04: a function declaring an argument, returns 6
+        let function = code.addr();
+        code.init_stack(1, 1);
+        // the middle of it, a thread can be started here with its locals unallocated
+        let middle = code.addr();
+        code.push_stack(-2).pop_stack(0);
+        code.push_i32(5).pop_stack(0);
+        code.push_stack(0).push_i32(1).add().retv();
+        let scenario = Scenario::new(build_hcb(code.code(), 4, &[]), None).unwrap();
+
+        for entry in [function, middle] {
+            let mut context = Context::new(entry);
+            assert!(context.get_local(-1).unwrap().is_saved_stack_info());
+            assert_eq!(context.cur_stack_base, 2);
+
+            let mut steps = 0;
+            while context.get_pc() != 0 {
+                context.dispatch_opcode(&scenario).unwrap();
+                steps += 1;
+                assert!(steps < 100, "the thread at 0x{:x} doesn't return", entry);
+            }
+            assert_eq!(context.get_return_value().as_int(), Some(6));
+            assert_eq!(context.cur_stack_base, 0);
+        }
+    }
+
+    /// a write of 7 with each kind of key: int 2, float, string and nil
+    fn write_keys(code: &mut CodeBuilder, pop: impl Fn(&mut CodeBuilder)) {
+        code.push_i32(2).push_i32(7);
+        pop(code);
+        code.push_f32(3.0).push_i32(7);
+        pop(code);
+        code.push_string("4").push_i32(7);
+        pop(code);
+        code.push_nil().push_i32(7);
+        pop(code);
+    }
+
+    fn run_to(context: &mut Context, scenario: &Scenario, end: u32) {
+        while context.get_pc() < end as usize {
+            context.dispatch_opcode(scenario).unwrap();
+        }
+    }
+
+    fn table_entries(value: &mut Variant) -> Vec<(u32, Variant)> {
+        let table = value.as_table().expect("not a table");
+        table.iter().map(|(k, v)| (k, v.clone())).collect()
+    }
+
+    #[test]
+    fn test_pop_global_table_keys() {
+        const TABLE: u16 = 0x7e01;
+        const INT: u16 = 0x7e02;
+        let _lock = GLOBAL_TEST_LOCK
+            .lock()
+            .unwrap_or_else(|err| err.into_inner());
+        {
+            let mut global = GLOBAL.lock().unwrap();
+            let mut table = Table::new();
+            table.insert(1, Variant::Int(5));
+            global.set(TABLE, Variant::Table(table));
+            global.set(INT, Variant::Int(9));
+        }
+
+        let mut code = CodeBuilder::new();
+        write_keys(&mut code, |code| {
+            code.pop_global_table(TABLE);
+        });
+        // not a table, but the key is dropped before it would be made one
+        code.push_f32(1.0).push_i32(7).pop_global_table(INT);
+        let end = code.addr();
+        let scenario = Scenario::new(build_hcb(code.code(), 4, &[]), None).unwrap();
+        let mut context = Context::new(scenario.get_entry_point());
+        run_to(&mut context, &scenario, end);
+
+        let mut global = GLOBAL.lock().unwrap();
+        let entries = table_entries(global.get_mut(TABLE).unwrap());
+        assert_eq!(
+            format!("{:?}", entries),
+            format!("{:?}", [(1, Variant::Int(5)), (2, Variant::Int(7))])
+        );
+        assert_eq!(global.get(INT).and_then(Variant::as_int), Some(9));
+        // the operands are gone
+        assert_eq!(context.cur_stack_pos, 0);
+    }
+
+    #[test]
+    fn test_pop_local_table_keys() {
+        let mut code = CodeBuilder::new();
+        code.init_stack(0, 2);
+        code.push_i32(1).push_i32(5).pop_local_table(0);
+        write_keys(&mut code, |code| {
+            code.pop_local_table(0);
+        });
+        code.push_i32(9).pop_stack(1);
+        code.push_f32(1.0).push_i32(7).pop_local_table(1);
+        let end = code.addr();
+        let scenario = Scenario::new(build_hcb(code.code(), 4, &[]), None).unwrap();
+        let mut context = Context::new(scenario.get_entry_point());
+        run_to(&mut context, &scenario, end);
+
+        let entries = table_entries(&mut context.get_local(0).unwrap());
+        assert_eq!(
+            format!("{:?}", entries),
+            format!("{:?}", [(1, Variant::Int(5)), (2, Variant::Int(7))])
+        );
+        assert_eq!(context.get_local(1).unwrap().as_int(), Some(9));
+        // only the locals are left
+        assert_eq!(context.cur_stack_pos, 2);
+    }
+
+    #[test]
+    fn test_table_key_bits() {
+        assert_eq!(Variant::Int(-1).as_table_key(), Some(0xFFFF_FFFF));
+        assert_eq!(Variant::Int(i32::MIN).as_table_key(), Some(0x8000_0000));
+        assert_eq!(Variant::String("4".into()).as_table_key(), None);
+        assert_eq!(Variant::Float(4.0).as_table_key(), None);
+
+        // a negative key reads back what was written with it
+        let mut code = CodeBuilder::new();
+        code.init_stack(0, 2);
+        code.push_i32(-1).push_i32(5).pop_local_table(0);
+        code.push_i32(-1).push_local_table(0).pop_stack(1);
+        let end = code.addr();
+        let scenario = Scenario::new(build_hcb(code.code(), 4, &[]), None).unwrap();
+        let mut context = Context::new(scenario.get_entry_point());
+        run_to(&mut context, &scenario, end);
+
+        let entries = table_entries(&mut context.get_local(0).unwrap());
+        assert_eq!(
+            format!("{:?}", entries),
+            format!("{:?}", [(0xFFFF_FFFFu32, Variant::Int(5))])
+        );
+        assert_eq!(context.get_local(1).unwrap().as_int(), Some(5));
+    }
+
+    #[test]
+    fn test_split_string_concat() {
+        let content = "あ".repeat(200);
+        let nls = Nls::ShiftJIS;
+
+        // what the assembler emits for an over-long literal
+        let mut code = vec![0x01, 0, 0];
+        let pieces = split_string_literal(&content, &nls);
+        assert_eq!(pieces.len(), 2);
+        for (i, piece) in pieces.iter().enumerate() {
+            let mut blob = nls.encode(piece);
+            blob.push(0);
+            code.push(0x0E);
+            code.push(blob.len() as u8);
+            code.extend_from_slice(&blob);
+            if i > 0 {
+                code.push(0x1A);
+            }
+        }
+
+        let scenario = Scenario::new(build_hcb(&code, 4, &[]), Some(nls)).unwrap();
+        let mut context = Context::new(scenario.get_entry_point());
+        for _ in 0..4 {
+            context.dispatch_opcode(&scenario).unwrap();
+        }
+
+        assert_eq!(context.cur_stack_pos, 1);
+        // the popped pieces are released, only the final string is alive
+        let strings = context
+            .stack
+            .iter()
+            .filter(|v| matches!(v, Variant::String(_)))
+            .count();
+        assert_eq!(strings, 1);
+        assert!(matches!(context.pop().unwrap(), Variant::String(s) if s == content));
+    }
+
+    #[test]
+    fn test_const_string_cache() {
+        let mut code = CodeBuilder::new();
+        code.init_stack(0, 1);
+        let head = code.addr();
+        code.push_string("ab").pop_stack(0);
+        code.push_string("cd").pop_stack(0);
+        code.jmp(head);
+        let scenario = Scenario::new(build_hcb(code.code(), 4, &[]), Some(Nls::UTF8)).unwrap();
+
+        let mut context = Context::new(scenario.get_entry_point());
+        context.dispatch_opcode(&scenario).unwrap();
+        for _ in 0..10 {
+            for expected in ["ab", "cd"] {
+                context.dispatch_opcode(&scenario).unwrap();
+                assert!(matches!(context.pop().unwrap(), Variant::String(s) if s == expected));
+                context.push(Variant::Nil).unwrap();
+                context.dispatch_opcode(&scenario).unwrap();
+            }
+            context.dispatch_opcode(&scenario).unwrap();
+        }
+        // each literal is only read once from the script
+        assert_eq!(context.const_string_decodes(), 2);
+
+        context.reset(scenario.get_entry_point());
+        context.dispatch_opcode(&scenario).unwrap();
+        context.dispatch_opcode(&scenario).unwrap();
+        assert_eq!(context.const_string_decodes(), 2);
+    }
+
+    /// one value of each type, in the order of the rows of the `vm::matrix` tables
+    fn samples() -> Vec<Variant> {
+        vec![
+            Variant::Nil,
+            Variant::True,
+            Variant::Int(1),
+            Variant::Float(1.0),
+            Variant::String("a".to_string()),
+            Variant::ConstString("a".to_string(), 0),
+            Variant::Table(Table::new()),
+        ]
+    }
+
+    /// run a binary opcode on every pair of samples, `T` where it pushed true
+    fn truth_table(op: fn(&mut Context) -> Result<()>) -> Vec<String> {
+        let samples = samples();
+        let mut rows = Vec::new();
+        for a in &samples {
+            let mut row = String::new();
+            for b in &samples {
+                let mut context = Context::new(0);
+                context.push(a.clone()).unwrap();
+                context.push(b.clone()).unwrap();
+                op(&mut context).unwrap();
+                let result = context.pop().unwrap();
+                assert_eq!(context.cur_stack_pos, 0);
+                row.push(match result {
+                    Variant::True => 'T',
+                    Variant::Nil => '.',
+                    other => panic!("{:?} pushed by a logic opcode", other),
+                });
+            }
+            rows.push(row);
+        }
+        rows
+    }
+
+    // rows: nil, true, int, float, string, const string, table
+    // columns: the same for the value pushed second
+
+    #[test]
+    fn test_and_matrix() {
+        #[rustfmt::skip]
+        let expected = [
+            ".......",
+            ".TTTTTT",
+            ".TTTTTT",
+            ".TTTTTT",
+            ".TTTTTT",
+            ".TTTTTT",
+            ".TTTTTT",
+        ];
+        assert_eq!(truth_table(Context::and), expected);
+    }
+
+    #[test]
+    fn test_or_matrix() {
+        #[rustfmt::skip]
+        let expected = [
+            ".TTTTTT",
+            "TTTTTTT",
+            "TTTTTTT",
+            "TTTTTTT",
+            "TTTTTTT",
+            "TTTTTTT",
+            "TTTTTTT",
+        ];
+        assert_eq!(truth_table(Context::or), expected);
+    }
+
+    #[test]
+    fn test_eq_matrix() {
+        // the samples hold the same number and the same string
+        #[rustfmt::skip]
+        let expected = [
+            "T......",
+            ".T.....",
+            "..TT...",
+            "..TT...",
+            "....TT.",
+            "....T..",
+            ".......",
+        ];
+        assert_eq!(truth_table(Context::sete), expected);
+
+        let mut value = Variant::Int(1);
+        value.equal(&Variant::Float(1.5));
+        assert!(value.is_nil());
+        let mut value = Variant::String("a".to_string());
+        value.equal(&Variant::ConstString("b".to_string(), 0));
+        assert!(value.is_nil());
+    }
+}
@@ -1,6 +1,6 @@
 use std::{collections::HashMap, sync::Mutex};
 
-use crate::format::scenario::variant::Variant;
+use crate::{format::scenario::variant::Variant, time::Playtime};
 use serde::{Serialize, Deserialize};
 
 /// Global variables
@@ -8,7 +8,10 @@ use serde::{Serialize, Deserialize};
 pub struct Global {
     global_table: HashMap<u16, Variant>,
     none_volatile_count: u16, 
-    volatile_count: u16
+    volatile_count: u16,
+    /// kept across the scenes and saved with the globals
+    #[serde(default)]
+    playtime: Playtime,
 }
 
 
@@ -18,10 +21,19 @@ impl Global {
         Global {
             global_table: HashMap::new(),
             none_volatile_count: 0,
-            volatile_count: 0
+            volatile_count: 0,
+            playtime: Playtime::ZERO,
         }
     }
 
+    pub fn playtime(&self) -> Playtime {
+        self.playtime
+    }
+
+    pub fn playtime_mut(&mut self) -> &mut Playtime {
+        &mut self.playtime
+    }
+
     pub fn get(&self, key: u16) -> Option<&Variant> {
         self.global_table.get(&key)
     }
@@ -57,6 +69,10 @@ lazy_static::lazy_static! {
     pub static ref GLOBAL: Mutex<Global> =  Mutex::new(Global::new());
 }
 
+/// Held by the tests using [`GLOBAL`], they run in parallel and a scene init wipes it
+#[cfg(test)]
+pub(crate) static GLOBAL_TEST_LOCK: Mutex<()> = Mutex::new(());
+
 
 pub fn get_int_var(key: u16) -> i32 {
     GLOBAL.lock().unwrap().get_int_var(key)
@@ -1,47 +1,67 @@
 pub mod inst;
 
+use anyhow::{bail, Result};
 
+use super::Scenario;
+
+/// number of opcodes, they are numbered from 0 without gaps
+pub const OPCODE_COUNT: usize = Opcode::SetGE as usize + 1;
+
+/// The opcodes of the HCB bytecode, the single table the VM, the disassembler and
+/// the assembler all decode and encode with.
+///
+/// Comparisons pop `b` then `a` (`a` was pushed first) and push `True` or `Nil`.
+/// Note the order of the original dispatch table: 0x24 to 0x27 are greater,
+/// less or equal, less and greater or equal, *not* the order the mnemonics
+/// would suggest. Getting it wrong silently inverts every `<=` and `>=`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Opcode {
-    Nop = 0,
-    InitStack = 1,
-    Call = 2,
-    Syscall,
-    Ret,
-    RetV,
-    Jmp,
-    Jz,
-    PushNil,
-    PushTrue,
-    PushI32,
-    PushI16,
-    PushI8,
-    PushF32,
-    PushString,
-    PushGlobal,
-    PushStack,
-    PushGlobalTable,
-    PushLocalTable,
-    PushTop,
-    PushReturn,
-    PopGlobal,
-    PopStack,
-    PopGlobalTable,
-    PopLocalTable,
-    Neg,
-    Add,
-    Sub,
-    Mul,
-    Div,
-    Mod,
-    BitTest,
-    And,
-    Or,
-    SetE,
-    SetNE,
-    SetG,
-    SetLE,
-    SetL,
-    SetGE,
+    Nop = 0x00,
+    InitStack = 0x01,
+    Call = 0x02,
+    Syscall = 0x03,
+    Ret = 0x04,
+    RetV = 0x05,
+    Jmp = 0x06,
+    Jz = 0x07,
+    PushNil = 0x08,
+    PushTrue = 0x09,
+    PushI32 = 0x0A,
+    PushI16 = 0x0B,
+    PushI8 = 0x0C,
+    PushF32 = 0x0D,
+    PushString = 0x0E,
+    PushGlobal = 0x0F,
+    PushStack = 0x10,
+    PushGlobalTable = 0x11,
+    PushLocalTable = 0x12,
+    PushTop = 0x13,
+    PushReturn = 0x14,
+    PopGlobal = 0x15,
+    PopStack = 0x16,
+    PopGlobalTable = 0x17,
+    PopLocalTable = 0x18,
+    Neg = 0x19,
+    Add = 0x1A,
+    Sub = 0x1B,
+    Mul = 0x1C,
+    Div = 0x1D,
+    Mod = 0x1E,
+    BitTest = 0x1F,
+    And = 0x20,
+    Or = 0x21,
+    /// `a == b`
+    SetE = 0x22,
+    /// `a != b`
+    SetNE = 0x23,
+    /// `a > b`
+    SetG = 0x24,
+    /// `a <= b`
+    SetLE = 0x25,
+    /// `a < b`
+    SetL = 0x26,
+    /// `a >= b`
+    SetGE = 0x27,
 }
 
 impl TryFrom<i32> for Opcode {
@@ -146,6 +166,13 @@ impl TryFrom<&str> for Opcode {
 
 impl ToString for Opcode {
     fn to_string(&self) -> String {
+        self.mnemonic().to_string()
+    }
+}
+
+impl Opcode {
+    /// the name used by the disassembly and the assembler
+    pub fn mnemonic(self) -> &'static str {
         match self {
             Opcode::Nop => "nop",
             Opcode::InitStack => "init_stack",
@@ -187,7 +214,7 @@ impl ToString for Opcode {
             Opcode::SetLE => "set_le",
             Opcode::SetL => "set_l",
             Opcode::SetGE => "set_ge",
-        }.to_string()
+        }
     }
 }
 
@@ -197,3 +224,117 @@ pub trait OpcodeBase {
     fn mnemonic(&self) -> &'static str;
     fn disassemble(&self) -> String;
 }
+
+/// An instruction located in the code, the operands are read from the scenario when needed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Instruction {
+    pub address: u32,
+    pub opcode: Opcode,
+    /// size of the whole instruction, the opcode and its operands
+    pub size: u32,
+}
+
+impl Instruction {
+    /// decode the opcode at the address and the size of its operands
+    pub fn decode(scenario: &Scenario, address: u32) -> Result<Self> {
+        let addr = address as usize;
+        let op = scenario.read_u8(addr)?;
+        let opcode = match Opcode::try_from(op as i32) {
+            Ok(opcode) => opcode,
+            Err(_) => bail!("unknown opcode {:#x} at {:#x}", op, addr),
+        };
+
+        let size = match opcode {
+            Opcode::PushI8
+            | Opcode::PushStack
+            | Opcode::PushLocalTable
+            | Opcode::PopStack
+            | Opcode::PopLocalTable => 2,
+            Opcode::InitStack
+            | Opcode::Syscall
+            | Opcode::PushI16
+            | Opcode::PushGlobal
+            | Opcode::PushGlobalTable
+            | Opcode::PopGlobal
+            | Opcode::PopGlobalTable => 3,
+            Opcode::Call | Opcode::Jmp | Opcode::Jz | Opcode::PushI32 | Opcode::PushF32 => 5,
+            Opcode::PushString => 2 + scenario.read_u8(addr + 1)? as u32,
+            _ => 1,
+        };
+
+        Ok(Self {
+            address,
+            opcode,
+            size,
+        })
+    }
+
+    pub fn next_address(&self) -> u32 {
+        self.address + self.size
+    }
+}
+
+/// Walks the code area of a script, one instruction after the other.
+///
+/// The iteration ends at the first error: the boundaries of everything behind an
+/// undecodable instruction are unknown.
+pub struct InstructionIter<'a> {
+    scenario: &'a Scenario,
+    address: u32,
+    end: u32,
+}
+
+impl<'a> InstructionIter<'a> {
+    pub fn new(scenario: &'a Scenario) -> Self {
+        Self {
+            scenario,
+            address: 4,
+            end: scenario.get_sys_desc_offset(),
+        }
+    }
+
+    /// the address of the next instruction, or of the one which failed to decode
+    pub fn address(&self) -> u32 {
+        self.address
+    }
+}
+
+impl Iterator for InstructionIter<'_> {
+    type Item = Result<Instruction>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.address >= self.end {
+            return None;
+        }
+
+        let result = Instruction::decode(self.scenario, self.address).and_then(|inst| {
+            if inst.next_address() > self.end {
+                bail!("instruction at {:#x} runs past the code", inst.address);
+            }
+            Ok(inst)
+        });
+        match &result {
+            Ok(inst) => self.address = inst.next_address(),
+            Err(_) => self.end = self.address,
+        }
+        Some(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_opcode_table_roundtrip() {
+        for byte in 0..OPCODE_COUNT as i32 {
+            let opcode = Opcode::try_from(byte).unwrap();
+            assert_eq!(opcode as i32, byte);
+            assert_eq!(Opcode::try_from(opcode.to_string().as_str()), Ok(opcode));
+        }
+        assert!(Opcode::try_from(OPCODE_COUNT as i32).is_err());
+
+        assert_eq!(Opcode::try_from(0x25), Ok(Opcode::SetLE));
+        assert_eq!(Opcode::try_from(0x27), Ok(Opcode::SetGE));
+    }
+}
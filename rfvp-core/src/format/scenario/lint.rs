@@ -0,0 +1,594 @@
+//! Static checks of a script, to catch broken fan-modified HCBs on load.
+//!
+//! A call into the middle of a function or a function leaving values on the stack
+//! only shows up as a bizarre VM error far from the cause. The checks walk the code
+//! once, linear in its size:
+//!
+//! - every call, jump and branch target has to be an instruction boundary, and calls
+//!   have to land on an `InitStack`
+//! - the stack depth is simulated through every function, it must never go below the
+//!   locals and be back to zero (or one value for `retv`) when returning
+//! - the code no path from the entry point, a call or a thread start leads to is listed,
+//!   for information only: the compiler leaves whole unused functions in the scripts
+//!
+//! The assembler can leave a `map.json` next to the script, pairing addresses with the
+//! lines of the disassembly they were built from, the findings are located with it:
+//!
+//! ```json
+//! [{"address": 4660, "file": "disassembly.yaml", "line": 120}]
+//! ```
+
+use std::{
+    collections::{BTreeMap, HashSet},
+    fmt,
+    path::Path,
+};
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+
+use super::{
+    instructions::{Instruction, InstructionIter, Opcode},
+    Scenario,
+};
+
+/// The name of the source map, in the game directory
+pub const SOURCE_MAP_FILE: &str = "map.json";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LintKind {
+    /// the instruction can't be decoded, the code behind it isn't checked
+    BadOpcode,
+    /// a jump into the middle of an instruction or out of the code
+    BadJumpTarget {
+        target: u32,
+    },
+    /// a call to something else than the start of a function
+    BadCallTarget {
+        target: u32,
+    },
+    UnknownSyscall {
+        id: u16,
+    },
+    /// the function pops more values than it pushed
+    StackUnderflow {
+        function: u32,
+    },
+    /// two paths join with different stack depths
+    StackMismatch {
+        function: u32,
+        depth: i32,
+        other: i32,
+    },
+    /// the function returns with values left on the stack, or without its return value
+    UnbalancedReturn {
+        function: u32,
+        depth: i32,
+    },
+    /// the function runs into the next one, or off the end of the code
+    MissingReturn {
+        function: u32,
+    },
+    /// nothing leads to the instructions up to `end`, exclusive
+    Unreachable {
+        end: u32,
+    },
+}
+
+impl LintKind {
+    /// whether the finding fails the load under `--strict`, unreachable code doesn't
+    pub fn is_fatal(&self) -> bool {
+        !matches!(self, Self::Unreachable { .. })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LintFinding {
+    pub address: u32,
+    pub kind: LintKind,
+}
+
+impl fmt::Display for LintFinding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:#x}: ", self.address)?;
+        match self.kind {
+            LintKind::BadOpcode => write!(f, "undecodable instruction, the rest isn't checked"),
+            LintKind::BadJumpTarget { target } => {
+                write!(f, "jump to {:#x}, which isn't an instruction", target)
+            }
+            LintKind::BadCallTarget { target } => {
+                write!(
+                    f,
+                    "call to {:#x}, which isn't the start of a function",
+                    target
+                )
+            }
+            LintKind::UnknownSyscall { id } => write!(f, "call to unknown syscall {}", id),
+            LintKind::StackUnderflow { function } => {
+                write!(f, "stack underflow in the function at {:#x}", function)
+            }
+            LintKind::StackMismatch {
+                function,
+                depth,
+                other,
+            } => write!(
+                f,
+                "paths join with stack depths {} and {} in the function at {:#x}",
+                depth, other, function
+            ),
+            LintKind::UnbalancedReturn { function, depth } => write!(
+                f,
+                "return with stack depth {} from the function at {:#x}",
+                depth, function
+            ),
+            LintKind::MissingReturn { function } => {
+                write!(f, "the function at {:#x} doesn't return", function)
+            }
+            LintKind::Unreachable { end } => write!(f, "unreachable code up to {:#x}", end),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct SourceLocation {
+    pub file: String,
+    pub line: u32,
+}
+
+#[derive(Deserialize)]
+struct SourceMapEntry {
+    address: u32,
+    file: String,
+    line: u32,
+}
+
+/// Pairs script addresses with the source lines they were assembled from
+#[derive(Debug, Default)]
+pub struct SourceMap {
+    locations: BTreeMap<u32, SourceLocation>,
+}
+
+impl SourceMap {
+    pub fn parse(text: &str) -> Result<Self> {
+        // json is a subset of yaml
+        let entries: Vec<SourceMapEntry> = serde_yaml::from_str(text)?;
+        let locations = entries
+            .into_iter()
+            .map(|entry| {
+                let location = SourceLocation {
+                    file: entry.file,
+                    line: entry.line,
+                };
+                (entry.address, location)
+            })
+            .collect();
+        Ok(Self { locations })
+    }
+
+    /// the source of the closest mapped address at or before `address`
+    pub fn locate(&self, address: u32) -> Option<&SourceLocation> {
+        self.locations
+            .range(..=address)
+            .next_back()
+            .map(|(_, location)| location)
+    }
+}
+
+/// load the source map of the game in `game_root`, if the assembler left one
+pub fn load_source_map(game_root: impl AsRef<Path>) -> Result<Option<SourceMap>> {
+    let path = game_root.as_ref().join(SOURCE_MAP_FILE);
+    let text = match std::fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err).with_context(|| format!("Reading {:?}", path)),
+    };
+    SourceMap::parse(&text)
+        .map(Some)
+        .with_context(|| format!("Parsing {:?}", path))
+}
+
+/// the index of the instruction starting at `address`
+fn index_of(insts: &[Instruction], address: u32) -> Option<usize> {
+    insts
+        .binary_search_by_key(&address, |inst| inst.address)
+        .ok()
+}
+
+fn operand_u32(scenario: &Scenario, inst: &Instruction) -> u32 {
+    // the instruction was decoded, its operands are in bounds
+    scenario.read_u32(inst.address as usize + 1).unwrap_or(0)
+}
+
+/// values popped and pushed by the instruction, returns are handled by the caller
+fn stack_effect(scenario: &Scenario, insts: &[Instruction], inst: &Instruction) -> (i32, i32) {
+    match inst.opcode {
+        Opcode::Nop | Opcode::InitStack | Opcode::Ret | Opcode::Jmp => (0, 0),
+        Opcode::Call => {
+            let target = scenario.resolve_function(operand_u32(scenario, inst));
+            let args = index_of(insts, target)
+                .filter(|&index| insts[index].opcode == Opcode::InitStack)
+                .and_then(|_| scenario.read_u8(target as usize + 1).ok())
+                .unwrap_or(0);
+            (args as i32, 0)
+        }
+        Opcode::Syscall => {
+            let args = scenario
+                .read_u16(inst.address as usize + 1)
+                .ok()
+                .and_then(|id| scenario.get_syscall(id))
+                .map_or(0, |syscall| syscall.args);
+            (args as i32, 0)
+        }
+        Opcode::RetV | Opcode::Jz | Opcode::PopGlobal | Opcode::PopStack => (1, 0),
+        Opcode::PushNil
+        | Opcode::PushTrue
+        | Opcode::PushI32
+        | Opcode::PushI16
+        | Opcode::PushI8
+        | Opcode::PushF32
+        | Opcode::PushString
+        | Opcode::PushGlobal
+        | Opcode::PushStack
+        | Opcode::PushReturn => (0, 1),
+        Opcode::PushTop => (1, 2),
+        Opcode::PushGlobalTable | Opcode::PushLocalTable | Opcode::Neg => (1, 1),
+        Opcode::PopGlobalTable | Opcode::PopLocalTable => (2, 0),
+        Opcode::Add
+        | Opcode::Sub
+        | Opcode::Mul
+        | Opcode::Div
+        | Opcode::Mod
+        | Opcode::BitTest
+        | Opcode::And
+        | Opcode::Or
+        | Opcode::SetE
+        | Opcode::SetNE
+        | Opcode::SetG
+        | Opcode::SetLE
+        | Opcode::SetL
+        | Opcode::SetGE => (2, 1),
+    }
+}
+
+/// check the script, the findings are sorted by address
+pub fn lint_scenario(scenario: &Scenario) -> Vec<LintFinding> {
+    let mut findings = Vec::new();
+
+    let mut insts = Vec::new();
+    let mut iter = InstructionIter::new(scenario);
+    while let Some(inst) = iter.next() {
+        match inst {
+            Ok(inst) => insts.push(inst),
+            Err(_) => findings.push(LintFinding {
+                address: iter.address(),
+                kind: LintKind::BadOpcode,
+            }),
+        }
+    }
+
+    // the functions started from the entry point, by calls and as threads
+    let mut roots = Vec::new();
+    if let Some(index) = index_of(&insts, scenario.get_entry_point()) {
+        roots.push(index);
+    }
+    for inst in &insts {
+        match inst.opcode {
+            Opcode::Jmp | Opcode::Jz => {
+                let target = operand_u32(scenario, inst);
+                if index_of(&insts, target).is_none() {
+                    findings.push(LintFinding {
+                        address: inst.address,
+                        kind: LintKind::BadJumpTarget { target },
+                    });
+                }
+            }
+            Opcode::Call => {
+                let target = operand_u32(scenario, inst);
+                match index_of(&insts, scenario.resolve_function(target)) {
+                    Some(index) if insts[index].opcode == Opcode::InitStack => roots.push(index),
+                    _ => findings.push(LintFinding {
+                        address: inst.address,
+                        kind: LintKind::BadCallTarget { target },
+                    }),
+                }
+            }
+            Opcode::Syscall => {
+                let id = scenario.read_u16(inst.address as usize + 1).unwrap_or(0);
+                if scenario.get_syscall(id).is_none() {
+                    findings.push(LintFinding {
+                        address: inst.address,
+                        kind: LintKind::UnknownSyscall { id },
+                    });
+                }
+            }
+            Opcode::PushI32 => {
+                // thread entries are pushed as immediates
+                let value = operand_u32(scenario, inst);
+                if let Some(index) = index_of(&insts, scenario.resolve_function(value)) {
+                    if insts[index].opcode == Opcode::InitStack {
+                        roots.push(index);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // the stack depth before every reached instruction, relative to the locals of its
+    // function. every instruction is entered once, the later paths are only compared.
+    let mut depths: Vec<Option<i32>> = vec![None; insts.len()];
+    let mut reported_mismatches = HashSet::new();
+    let mut reported_underflows = HashSet::new();
+    let mut pending = Vec::new();
+    for root in roots {
+        if depths[root].is_some() {
+            continue;
+        }
+        let function = insts[root].address;
+        depths[root] = Some(0);
+        pending.push((root + 1, 0));
+
+        while let Some((index, depth)) = pending.pop() {
+            let Some(inst) = insts.get(index) else {
+                findings.push(LintFinding {
+                    address: function,
+                    kind: LintKind::MissingReturn { function },
+                });
+                continue;
+            };
+            if inst.opcode == Opcode::InitStack {
+                findings.push(LintFinding {
+                    address: inst.address,
+                    kind: LintKind::MissingReturn { function },
+                });
+                continue;
+            }
+            if let Some(other) = depths[index] {
+                if other != depth && reported_mismatches.insert(inst.address) {
+                    findings.push(LintFinding {
+                        address: inst.address,
+                        kind: LintKind::StackMismatch {
+                            function,
+                            depth,
+                            other,
+                        },
+                    });
+                }
+                continue;
+            }
+            depths[index] = Some(depth);
+
+            let (pops, pushes) = stack_effect(scenario, &insts, inst);
+            if depth < pops && reported_underflows.insert(function) {
+                findings.push(LintFinding {
+                    address: inst.address,
+                    kind: LintKind::StackUnderflow { function },
+                });
+            }
+            let next_depth = (depth - pops).max(0) + pushes;
+
+            match inst.opcode {
+                Opcode::Ret | Opcode::RetV => {
+                    let expected = if inst.opcode == Opcode::RetV { 1 } else { 0 };
+                    if depth != expected {
+                        findings.push(LintFinding {
+                            address: inst.address,
+                            kind: LintKind::UnbalancedReturn {
+                                function,
+                                depth: depth - expected,
+                            },
+                        });
+                    }
+                }
+                Opcode::Jmp => {
+                    if let Some(target) = index_of(&insts, operand_u32(scenario, inst)) {
+                        pending.push((target, next_depth));
+                    }
+                }
+                Opcode::Jz => {
+                    if let Some(target) = index_of(&insts, operand_u32(scenario, inst)) {
+                        pending.push((target, next_depth));
+                    }
+                    pending.push((index + 1, next_depth));
+                }
+                _ => pending.push((index + 1, next_depth)),
+            }
+        }
+    }
+
+    // the compiler ends every function with a `ret` and every branch with a `jmp`, even
+    // right after a `ret` or a `jmp`
+    let filler = |index: usize| {
+        index > 0
+            && matches!(insts[index].opcode, Opcode::Ret | Opcode::Jmp)
+            && matches!(
+                insts[index - 1].opcode,
+                Opcode::Ret | Opcode::RetV | Opcode::Jmp
+            )
+    };
+    let reached = |index: usize| depths[index].is_some() || filler(index);
+    let mut index = 0;
+    while index < insts.len() {
+        if reached(index) {
+            index += 1;
+            continue;
+        }
+        let start = insts[index].address;
+        while index < insts.len() && !reached(index) {
+            index += 1;
+        }
+        findings.push(LintFinding {
+            address: start,
+            kind: LintKind::Unreachable {
+                end: insts[index - 1].next_address(),
+            },
+        });
+    }
+
+    findings.sort_by_key(|finding| finding.address);
+    findings
+}
+
+#[cfg(test)]
+mod tests {
+    use rfvp_test_support::{build_hcb, CodeBuilder};
+
+    use super::*;
+
+    fn lint(code: &CodeBuilder) -> Vec<LintKind> {
+        let hcb = build_hcb(code.code(), 4, &[(1, "ThreadNext")]);
+        let scenario = Scenario::new(hcb, None).unwrap();
+        lint_scenario(&scenario)
+            .into_iter()
+            .map(|finding| finding.kind)
+            .collect()
+    }
+
+    #[test]
+    fn test_clean_script() {
+        let mut code = CodeBuilder::new();
+        // 0x04: main
+        code.init_stack(0, 1);
+        code.push_i32(5);
+        let call = code.call_forward();
+        code.push_return().pop_stack(0);
+        code.push_i32(1).syscall(0);
+        code.push_true();
+        let skip = code.jz_forward();
+        code.push_nil().pop_stack(0);
+        let end = code.addr();
+        code.patch(skip, end);
+        code.ret();
+        // takes one argument and returns it
+        let func = code.addr();
+        code.patch(call, func);
+        code.init_stack(1, 0).push_stack(-1).retv();
+        assert_eq!(lint(&code), vec![]);
+    }
+
+    #[test]
+    fn test_bad_targets() {
+        let mut code = CodeBuilder::new();
+        // into the middle of the jmp itself
+        code.init_stack(0, 0).jmp(0x0a).push_i32(1);
+        assert_eq!(
+            lint(&code),
+            vec![
+                LintKind::BadJumpTarget { target: 0x0a },
+                LintKind::Unreachable { end: 0x11 },
+            ]
+        );
+
+        let mut code = CodeBuilder::new();
+        // 0x0c is the push, not a function
+        code.init_stack(0, 0)
+            .call(0x0c)
+            .push_i32(1)
+            .syscall(7)
+            .ret();
+        code.push_true().retv();
+        assert_eq!(
+            lint(&code),
+            vec![
+                LintKind::BadCallTarget { target: 0x0c },
+                LintKind::UnknownSyscall { id: 7 },
+                LintKind::UnbalancedReturn {
+                    function: 4,
+                    depth: 1
+                },
+                LintKind::Unreachable { end: 0x17 },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_stack_balance() {
+        let mut code = CodeBuilder::new();
+        code.init_stack(0, 0);
+        // pops from an empty stack
+        let branch = code.jz_forward();
+        code.push_i32(1);
+        let join = code.addr();
+        code.patch(branch, join);
+        code.push_i32(2).ret();
+        assert_eq!(
+            lint(&code),
+            vec![
+                LintKind::StackUnderflow { function: 4 },
+                LintKind::StackMismatch {
+                    function: 4,
+                    depth: 0,
+                    other: 1
+                },
+                LintKind::UnbalancedReturn {
+                    function: 4,
+                    depth: 2
+                },
+            ]
+        );
+
+        let mut code = CodeBuilder::new();
+        code.init_stack(0, 0).push_nil();
+        // runs into the next function, which is never called
+        code.init_stack(0, 0).ret();
+        assert_eq!(
+            lint(&code),
+            vec![
+                LintKind::MissingReturn { function: 4 },
+                LintKind::Unreachable { end: 0x0c },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_bad_opcode() {
+        let hcb = build_hcb(&[0x01, 0, 0, 0xee, 0x04], 4, &[]);
+        let scenario = Scenario::new(hcb, None).unwrap();
+        let findings = lint_scenario(&scenario);
+        assert_eq!(
+            findings[0],
+            LintFinding {
+                address: 4,
+                kind: LintKind::MissingReturn { function: 4 },
+            }
+        );
+        assert_eq!(
+            findings[1],
+            LintFinding {
+                address: 7,
+                kind: LintKind::BadOpcode,
+            }
+        );
+    }
+
+    #[test]
+    fn test_snow() {
+        let data = std::fs::read(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../disassembler/testcase/Snow.hcb"
+        ))
+        .unwrap();
+        let scenario = Scenario::new(data.into(), None).unwrap();
+        let findings = lint_scenario(&scenario);
+        let problems: Vec<_> = findings
+            .iter()
+            .filter(|finding| finding.kind.is_fatal())
+            .collect();
+        assert_eq!(problems, Vec::<&LintFinding>::new());
+        // the `ret` the compiler put after the last `ret` of a function isn't listed
+        assert_eq!(scenario.read_u8(0x214).unwrap(), Opcode::Ret as u8);
+        assert!(findings.iter().all(|finding| finding.address != 0x215));
+    }
+
+    #[test]
+    fn test_source_map() {
+        let map = SourceMap::parse(
+            r#"[{"address": 4, "file": "main.yaml", "line": 3},
+                {"address": 16, "file": "main.yaml", "line": 9}]"#,
+        )
+        .unwrap();
+        assert_eq!(map.locate(2), None);
+        assert_eq!(map.locate(12).unwrap().line, 3);
+        assert_eq!(map.locate(20).unwrap().line, 9);
+    }
+}
@@ -1,6 +1,12 @@
 pub mod context;
 pub mod instructions;
 pub mod global;
+pub mod lint;
+pub mod overlay;
+pub mod probe;
+pub mod raw_variant;
+pub mod scene_table;
+pub mod text_patch;
 pub mod variant;
 
 use std::{collections::HashMap, io::Cursor, str::FromStr};
@@ -9,13 +15,24 @@ use anyhow::{bail, Result};
 use binrw::{BinRead, BinWrite};
 use bytes::Bytes;
 
+use crate::{
+    format::bytes::{
+        read_f32_le, read_i16_le, read_i32_le, read_i8, read_u16_le, read_u32_le, read_u8,
+        write_u16_le, write_u32_le,
+    },
+    vm::command::Command,
+};
+use global::Global;
 
-#[derive(Debug, Clone, Default)]
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub enum Nls {
     #[default]
     ShiftJIS = 0,
     GBK = 1,
     UTF8 = 2,
+    UTF16LE = 3,
+    UTF16BE = 4,
 }
 
 
@@ -27,11 +44,215 @@ impl FromStr for Nls {
             "sjis" => Ok(Nls::ShiftJIS),
             "gbk" => Ok(Nls::GBK),
             "utf8" => Ok(Nls::UTF8),
+            "utf16" | "utf16le" => Ok(Nls::UTF16LE),
+            "utf16be" => Ok(Nls::UTF16BE),
             _ => Err(anyhow::anyhow!("unknown NLS")),
         }
     }
 }
 
+impl Nls {
+    /// encode a UTF-8 string to the local encoding, without the null terminator
+    pub fn encode(&self, content: &str) -> Vec<u8> {
+        match self {
+            Nls::GBK => encoding_rs::GBK.encode(content).0.to_vec(),
+            Nls::ShiftJIS => encoding_rs::SHIFT_JIS.encode(content).0.to_vec(),
+            Nls::UTF8 => content.as_bytes().to_vec(),
+            // encoding_rs only decodes UTF-16, its encoder outputs UTF-8
+            Nls::UTF16LE => content.encode_utf16().flat_map(u16::to_le_bytes).collect(),
+            Nls::UTF16BE => content.encode_utf16().flat_map(u16::to_be_bytes).collect(),
+        }
+    }
+
+    /// decode a string in the local encoding, malformed sequences are replaced.
+    /// a byte order mark takes precedence over the encoding, and is dropped
+    pub fn decode(&self, content: &[u8]) -> String {
+        let (encoding, name) = match self {
+            Nls::ShiftJIS => (encoding_rs::SHIFT_JIS, "ShiftJIS"),
+            Nls::GBK => (encoding_rs::GBK, "GBK"),
+            Nls::UTF8 => (encoding_rs::UTF_8, "UTF-8"),
+            Nls::UTF16LE => (encoding_rs::UTF_16LE, "UTF-16LE"),
+            Nls::UTF16BE => (encoding_rs::UTF_16BE, "UTF-16BE"),
+        };
+        // sniffs the BOM
+        let (s, _, e) = encoding.decode(content);
+        if e {
+            log::error!("failed to decode string as {}", name);
+        }
+        s.to_string()
+    }
+
+    /// the null terminator of a string, a whole code unit in UTF-16
+    pub fn terminator(&self) -> &'static [u8] {
+        match self {
+            Nls::UTF16LE | Nls::UTF16BE => &[0, 0],
+            Nls::ShiftJIS | Nls::GBK | Nls::UTF8 => &[0],
+        }
+    }
+
+    /// the bytes of a null terminated string before the terminator, all of them if there
+    /// is none. In UTF-16 only an aligned pair of zeros ends the string, a single zero is
+    /// half of a character like `A`
+    pub fn cstr_content<'a>(&self, content: &'a [u8]) -> &'a [u8] {
+        let end = match self {
+            Nls::UTF16LE | Nls::UTF16BE => content
+                .chunks_exact(2)
+                .position(|unit| unit == [0, 0])
+                .map(|i| i * 2),
+            Nls::ShiftJIS | Nls::GBK | Nls::UTF8 => content.iter().position(|&b| b == 0),
+        };
+        &content[..end.unwrap_or(content.len())]
+    }
+
+    /// decode a null terminated string, see [`Self::cstr_content`]
+    pub fn decode_cstr(&self, content: &[u8]) -> String {
+        self.decode(self.cstr_content(content))
+    }
+
+    /// the length in bytes of the character at the start of `content`, from its lead byte
+    /// and without decoding it. a character cut short takes the bytes left, a malformed
+    /// byte counts as a character of its own
+    pub fn char_width(&self, content: &[u8]) -> usize {
+        let Some(&lead) = content.first() else {
+            return 0;
+        };
+        let width = match self {
+            Nls::ShiftJIS => match lead {
+                0x81..=0x9F | 0xE0..=0xFC => 2,
+                _ => 1,
+            },
+            // the four byte sequences of GB18030 have a digit as their second byte
+            Nls::GBK => match (lead, content.get(1)) {
+                (0x81..=0xFE, Some(0x30..=0x39)) => 4,
+                (0x81..=0xFE, _) => 2,
+                _ => 1,
+            },
+            Nls::UTF8 => match lead {
+                0xC0..=0xDF => 2,
+                0xE0..=0xEF => 3,
+                0xF0..=0xF7 => 4,
+                _ => 1,
+            },
+            Nls::UTF16LE | Nls::UTF16BE => {
+                let high = match self {
+                    Nls::UTF16LE => content.get(1),
+                    _ => Some(&lead),
+                };
+                match high {
+                    Some(0xD8..=0xDB) => 4,
+                    _ => 2,
+                }
+            }
+        };
+        width.min(content.len())
+    }
+
+    /// the number of characters of an encoded string, see [`Self::char_width`]
+    pub fn char_count(&self, content: &[u8]) -> usize {
+        let mut count = 0;
+        let mut rest = content;
+        while !rest.is_empty() {
+            rest = &rest[self.char_width(rest)..];
+            count += 1;
+        }
+        count
+    }
+
+    /// The bytes of the characters `start..start + len` of an encoded string, without
+    /// decoding it, so a double byte character is never cut in half. The range is clamped
+    /// to the string, past its end the slice is empty.
+    pub fn char_slice<'a>(&self, content: &'a [u8], start: usize, len: usize) -> &'a [u8] {
+        let mut offset = 0;
+        for _ in 0..start {
+            offset += self.char_width(&content[offset..]);
+        }
+        let mut end = offset;
+        for _ in 0..len {
+            if end == content.len() {
+                break;
+            }
+            end += self.char_width(&content[end..]);
+        }
+        &content[offset..end]
+    }
+}
+
+/// the window size of a game mode from the sysdesc
+pub fn screen_size_for_game_mode(game_mode: u16) -> (u32, u32) {
+    match game_mode {
+        0 => (640, 480),
+        1 => (800, 600),
+        2 => (1024, 768),
+        3 => (1280, 960),
+        4 => (1600, 1200),
+        5 => (640, 480),
+        6 => (1024, 576),
+        7 => (1024, 640),
+        8 => (1280, 720),
+        9 => (1280, 800),
+        10 => (1440, 810),
+        11 => (1440, 900),
+        12 => (1680, 945),
+        13 => (1680, 1050),
+        14 => (1920, 1080),
+        15 => (1920, 1200),
+        _ => {
+            log::error!("unknown resolution: {}, use 640x480 as defualt", game_mode);
+            (640, 480)
+        }
+    }
+}
+
+/// What the scene init configures for the game mode of a script, see [`Scenario::init_scene`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SceneSetup {
+    pub game_mode: u16,
+    pub screen_size: (u32, u32),
+    pub non_volatile_global_count: u16,
+    pub volatile_global_count: u16,
+}
+
+/// the longest string a single PushString can carry, including the null terminator
+pub const MAX_PUSH_STRING_LEN: usize = 0xFF;
+
+/// split a string literal into pieces which fit into a single PushString each.
+/// the pieces are concatenated back with Add at runtime, the split never
+/// happens inside a character.
+pub fn split_string_literal(content: &str, nls: &Nls) -> Vec<String> {
+    let mut pieces = Vec::new();
+    let mut piece = String::new();
+    // reserve room for the null terminator
+    let mut len = nls.terminator().len();
+    let mut buf = [0u8; 4];
+    for c in content.chars() {
+        let char_len = nls.encode(c.encode_utf8(&mut buf)).len();
+        if len + char_len > MAX_PUSH_STRING_LEN && !piece.is_empty() {
+            pieces.push(std::mem::take(&mut piece));
+            len = nls.terminator().len();
+        }
+        piece.push(c);
+        len += char_len;
+    }
+
+    if !piece.is_empty() || pieces.is_empty() {
+        pieces.push(piece);
+    }
+
+    pieces
+}
+
+/// append a length-prefixed, null terminated string as stored in the sysdesc
+fn push_cstring(buf: &mut Vec<u8>, nls: &Nls, content: &str, what: &str) -> Result<()> {
+    let mut encoded = nls.encode(content);
+    encoded.extend_from_slice(nls.terminator());
+    if encoded.len() > u8::MAX as usize {
+        bail!("{} is too long: {:?}", what, content);
+    }
+    buf.push(encoded.len() as u8);
+    buf.extend_from_slice(&encoded);
+    Ok(())
+}
+
 #[derive(Debug, Clone)]
 pub struct Syscall {
     /// how many arguments the syscall takes from the stack
@@ -43,6 +264,7 @@ pub struct Syscall {
 #[allow(unused)]
 #[derive(Debug, Clone)]
 pub struct Scenario {
+    /// reference counted, clones of the scenario share the script bytes
     raw_data: Bytes,
     pub nls: Nls,
     pub sys_desc_offset: u32,
@@ -57,6 +279,11 @@ pub struct Scenario {
     game_title: String,
     pub syscall_count: u16,
     pub syscalls: HashMap<usize, Syscall>,
+    /// base function address -> patched function address, see `overlay`
+    overrides: HashMap<u32, u32>,
+    /// stub unknown syscalls instead of failing, see `new_tolerant`
+    tolerant: bool,
+    unresolved_syscalls: Vec<String>,
 }
 
 impl Scenario {
@@ -73,6 +300,9 @@ impl Scenario {
             game_title: String::new(),
             syscall_count: 0,
             syscalls: HashMap::new(),
+            overrides: HashMap::new(),
+            tolerant: false,
+            unresolved_syscalls: Vec::new(),
         };
 
         scenario.parser()?;
@@ -80,6 +310,52 @@ impl Scenario {
         Ok(scenario)
     }
 
+    /// load the script even if it imports syscalls the engine doesn't know.
+    /// the unknown syscalls are recorded and return nil when called, which is
+    /// enough to partially run a game for debugging.
+    pub fn new_tolerant(data: Bytes, nls: Option<Nls>) -> Result<Self> {
+        let mut scenario = Scenario::new(data, nls)?;
+        scenario.tolerant = true;
+
+        let mut ids = scenario.syscalls.keys().copied().collect::<Vec<_>>();
+        ids.sort_unstable();
+        for id in ids {
+            let name = &scenario.syscalls[&id].name;
+            if Command::from_syscall(name, Vec::new()).is_none() {
+                log::warn!("unresolved syscall: {}", name);
+                scenario.unresolved_syscalls.push(name.clone());
+            }
+        }
+
+        Ok(scenario)
+    }
+
+    /// the script with a patch script which overrides some of its functions, the ones
+    /// listed in the override table of the patch, see [`overlay`]
+    pub fn with_patch(&self, patch: Bytes) -> Result<Self> {
+        let patch = Scenario::new(patch, Some(self.nls.clone()))?;
+        let (merged, overrides) = overlay::merge(self, &patch)?;
+
+        let nls = Some(self.nls.clone());
+        let mut scenario = if self.tolerant {
+            Scenario::new_tolerant(merged.into(), nls)?
+        } else {
+            Scenario::new(merged.into(), nls)?
+        };
+        scenario.overrides = overrides;
+
+        Ok(scenario)
+    }
+
+    /// the script with the strings replaced by a text patch, see [`text_patch`]
+    pub fn with_text_patch(&self, patch: &text_patch::TextPatch) -> Result<Self> {
+        let data = patch.apply(self)?;
+        // the strings are replaced in place, nothing parsed from the script moves
+        let mut scenario = self.clone();
+        scenario.raw_data = data.into();
+        Ok(scenario)
+    }
+
     #[inline]
     pub fn raw(&self) -> &[u8] {
         &self.raw_data
@@ -87,73 +363,37 @@ impl Scenario {
 
     /// safely read a u8 from the buffer
     pub fn read_u8(&self, offset: usize) -> Result<u8> {
-        if offset >= self.raw().len() {
-            return Err(anyhow::anyhow!("offset out of bounds"));
-        }
-        Ok(self.raw()[offset])
+        read_u8(self.raw(), offset)
     }
 
     /// safely read a little-endian u16 from the buffer
     pub fn read_u16(&self, offset: usize) -> Result<u16> {
-        if offset + 1 >= self.raw().len() {
-            return Err(anyhow::anyhow!("offset out of bounds"));
-        }
-        Ok(u16::from_le_bytes([self.raw()[offset], self.raw()[offset + 1]]))
+        read_u16_le(self.raw(), offset)
     }
 
     /// safely read a little-endian u32 from the buffer
     pub fn read_u32(&self, offset: usize) -> Result<u32> {
-        if offset + 3 >= self.raw().len() {
-            return Err(anyhow::anyhow!("offset out of bounds"));
-        }
-        Ok(u32::from_le_bytes([
-            self.raw()[offset],
-            self.raw()[offset + 1],
-            self.raw()[offset + 2],
-            self.raw()[offset + 3],
-        ]))
+        read_u32_le(self.raw(), offset)
     }
 
-    /// safely read a little-endian i8 from the buffer
+    /// safely read an i8 from the buffer
     pub fn read_i8(&self, offset: usize) -> Result<i8> {
-        if offset >= self.raw().len() {
-            return Err(anyhow::anyhow!("offset out of bounds"));
-        }
-        Ok(self.raw()[offset] as i8)
+        read_i8(self.raw(), offset)
     }
 
     /// safely read a little-endian i16 from the buffer
     pub fn read_i16(&self, offset: usize) -> Result<i16> {
-        if offset + 1 >= self.raw().len() {
-            return Err(anyhow::anyhow!("offset out of bounds"));
-        }
-        Ok(i16::from_le_bytes([self.raw()[offset], self.raw()[offset + 1]]))
+        read_i16_le(self.raw(), offset)
     }
 
     /// safely read a little-endian i32 from the buffer
     pub fn read_i32(&self, offset: usize) -> Result<i32> {
-        if offset + 3 >= self.raw().len() {
-            return Err(anyhow::anyhow!("offset out of bounds"));
-        }
-        Ok(i32::from_le_bytes([
-            self.raw()[offset],
-            self.raw()[offset + 1],
-            self.raw()[offset + 2],
-            self.raw()[offset + 3],
-        ]))
+        read_i32_le(self.raw(), offset)
     }
 
     /// safely read a little-endian f32 from the buffer
     pub fn read_f32(&self, offset: usize) -> Result<f32> {
-        if offset + 3 >= self.raw().len() {
-            return Err(anyhow::anyhow!("offset out of bounds"));
-        }
-        Ok(f32::from_le_bytes([
-            self.raw()[offset],
-            self.raw()[offset + 1],
-            self.raw()[offset + 2],
-            self.raw()[offset + 3],
-        ]))
+        read_f32_le(self.raw(), offset)
     }
 
     /// safe read a c-style string from the buffer with string length
@@ -163,52 +403,22 @@ impl Scenario {
         if offset + len >= self.raw().len() {
             return Err(anyhow::anyhow!("offset out of bounds"));
         }
-        let mut string = Vec::new();
-        for i in 0..len {
-            if self.raw()[offset + i] == 0 {
-                break;
-            }
-            string.push(self.raw()[offset + i]);
-        }
-
-        if string.ends_with(&[0]) {
-            string.pop();
-        }
-        
-        let s = match self.nls {
-            Nls::ShiftJIS => {
-                let (s, _, e) = encoding_rs::SHIFT_JIS.decode(&string);
-                if e {
-                    log::error!("failed to decode string as ShiftJIS");
-                }
-                s
-            }
-            Nls::GBK => {
-                let (s, _, e) = encoding_rs::GBK.decode(&string);
-                if e {
-                    log::error!("failed to decode string as GBK");
-                }
-                s
-            }
-            Nls::UTF8 => {
-                let (s, _, e) = encoding_rs::UTF_8.decode(&string);
-                if e {
-                    log::error!("failed to decode string as UTF-8");
-                }
-                s
-            }
-        };
-
-        Ok(s.to_string())
+        Ok(self.nls.decode_cstr(&self.raw()[offset..offset + len]))
     }
 
     fn parser(&mut self) -> Result<()> {
         let mut off = 0usize;
         self.sys_desc_offset = self.read_u32(off)?;
+        if self.sys_desc_offset < 4 {
+            bail!("the sysdesc offset {:#x} is inside the header", self.sys_desc_offset);
+        }
 
         off = self.sys_desc_offset as usize;
         self.entry_point = self.read_u32(off)?;
         off += size_of::<u32>();
+        if !self.is_code_area(self.entry_point) {
+            bail!("the entry point {:#x} is outside of the code", self.entry_point);
+        }
 
         self.non_volatile_global_count = self.read_u16(off)?;
         off += size_of::<u16>();
@@ -223,12 +433,35 @@ impl Scenario {
         off += size_of::<u8>();
 
         self.game_title = self.read_cstring(off, title_len as usize)?;
-        off += title_len as usize;
 
-        self.syscall_count = self.read_u16(off)?;
+        let (syscalls, end) = self.read_imports()?;
+        self.syscall_count = syscalls.len() as u16;
+        self.syscalls = syscalls;
+        off = end;
+
+        self.custom_syscall_count = self.read_u16(off)?;
+        if self.custom_syscall_count > 0 {
+            log::warn!("custom syscall count: {}", self.custom_syscall_count);
+        }
+
+        Ok(())
+    }
+
+    /// offset of the import table in the raw data, right after the game title
+    fn imports_offset(&self) -> Result<usize> {
+        // entry point, global counts and game mode
+        let title_len_offset = self.sys_desc_offset as usize + 10;
+        Ok(title_len_offset + 1 + self.read_u8(title_len_offset)? as usize)
+    }
+
+    /// read the import table as it is in the raw data, returns the offset past its end
+    fn read_imports(&self) -> Result<(HashMap<usize, Syscall>, usize)> {
+        let mut off = self.imports_offset()?;
+        let count = self.read_u16(off)?;
         off += size_of::<u16>();
 
-        for i in 0..self.syscall_count {
+        let mut syscalls = HashMap::new();
+        for i in 0..count {
             let args = self.read_u8(off)?;
             off += size_of::<u8>();
 
@@ -238,15 +471,66 @@ impl Scenario {
             let name = self.read_cstring(off, name_len as usize)?;
             off += name_len as usize;
 
-            self.syscalls.insert(i as usize, Syscall { args, name });
+            syscalls.insert(i as usize, Syscall { args, name });
         }
 
-        self.custom_syscall_count = self.read_u16(off)?;
-        if self.custom_syscall_count > 0 {
-            log::warn!("custom syscall count: {}", self.custom_syscall_count);
+        Ok((syscalls, off))
+    }
+
+    /// the import table, for renaming syscalls or changing their argument counts
+    /// before writing the script back with `serialize`
+    pub fn imports_mut(&mut self) -> &mut HashMap<usize, Syscall> {
+        &mut self.syscalls
+    }
+
+    /// write the script back, with the header and the import table rebuilt from the
+    /// current fields. the code section is copied untouched, so all addresses stay valid.
+    /// strings are encoded in the scenario's NLS.
+    ///
+    /// changing how many arguments an import takes is allowed but warned about,
+    /// the existing call sites still push the original count.
+    pub fn serialize(&self) -> Result<Vec<u8>> {
+        let ids = 0..self.syscalls.len();
+        if let Some(id) = ids.clone().find(|id| !self.syscalls.contains_key(id)) {
+            bail!("the import table has a hole at syscall id {}", id);
         }
 
-        Ok(())
+        let (original, imports_end) = self.read_imports()?;
+        for id in ids.clone() {
+            let syscall = &self.syscalls[&id];
+            match original.get(&id) {
+                Some(orig) if orig.args != syscall.args => log::warn!(
+                    "syscall {} ({}) now takes {} arguments instead of {}, its call sites will unbalance the stack",
+                    id,
+                    syscall.name,
+                    syscall.args,
+                    orig.args
+                ),
+                _ => {}
+            }
+        }
+
+        let code_end = self.sys_desc_offset as usize;
+        let mut buf = Vec::with_capacity(self.raw().len());
+        buf.extend_from_slice(&self.raw()[..code_end]);
+
+        write_u32_le(&mut buf, self.entry_point);
+        write_u16_le(&mut buf, self.non_volatile_global_count);
+        write_u16_le(&mut buf, self.volatile_global_count);
+        write_u16_le(&mut buf, self.game_mode);
+        push_cstring(&mut buf, &self.nls, &self.game_title, "game title")?;
+
+        write_u16_le(&mut buf, self.syscalls.len() as u16);
+        for id in ids {
+            let syscall = &self.syscalls[&id];
+            buf.push(syscall.args);
+            push_cstring(&mut buf, &self.nls, &syscall.name, "syscall name")?;
+        }
+
+        // the custom syscall table and whatever follows it is kept as is
+        buf.extend_from_slice(&self.raw()[imports_end..]);
+
+        Ok(buf)
     }
 
     pub fn get_syscall_name(&self, id: u16) -> Option<&str> {
@@ -278,36 +562,63 @@ impl Scenario {
     }
 
     pub fn get_screen_size(&self) -> (u32, u32) {
-        match self.game_mode {
-            0 => (640, 480),
-            1 => (800, 600),
-            2 => (1024, 768),
-            3 => (1280, 960),
-            4 => (1600, 1200),
-            5 => (640, 480),
-            6 => (1024, 576),
-            7 => (1024, 640),
-            8 => (1280, 720),
-            9 => (1280, 800),
-            10 => (1440, 810),
-            11 => (1440, 900),
-            12 => (1680, 945),
-            13 => (1680, 1050),
-            14 => (1920, 1080),
-            15 => (1920, 1200),
-            _ => {
-                log::error!("unknown resolution: {}, use 640x480 as defualt", self.game_mode);
-                (640, 480)
-            }
-        }
+        screen_size_for_game_mode(self.game_mode)
     }
 
     pub fn get_game_mode(&self) -> u16 {
         self.game_mode
     }
 
+    pub fn scene_setup(&self) -> SceneSetup {
+        SceneSetup {
+            game_mode: self.game_mode,
+            screen_size: self.get_screen_size(),
+            non_volatile_global_count: self.non_volatile_global_count,
+            volatile_global_count: self.volatile_global_count,
+        }
+    }
+
+    /// prepare `global` for a fresh run of the script and return the setup of its game mode.
+    ///
+    /// the game mode only selects the resolution, every mode starts with all the
+    /// globals declared by the sysdesc set to nil.
+    pub fn init_scene(&self, global: &mut Global) -> SceneSetup {
+        let setup = self.scene_setup();
+        log::info!(
+            "game mode {}: {}x{}",
+            setup.game_mode,
+            setup.screen_size.0,
+            setup.screen_size.1
+        );
+
+        // the playtime isn't script state, restarting the script keeps it
+        let playtime = global.playtime();
+        *global = Global::new();
+        *global.playtime_mut() = playtime;
+        global.init_with(setup.non_volatile_global_count, setup.volatile_global_count);
+        setup
+    }
+
     pub fn get_entry_point(&self) -> u32 {
-        self.entry_point
+        self.resolve_function(self.entry_point)
+    }
+
+    /// redirect the address of an overridden function to its patched implementation
+    pub fn resolve_function(&self, addr: u32) -> u32 {
+        self.overrides.get(&addr).copied().unwrap_or(addr)
+    }
+
+    pub fn is_tolerant(&self) -> bool {
+        self.tolerant
+    }
+
+    /// syscalls imported by the script but unknown to the engine, only collected in tolerant mode
+    pub fn get_unresolved_syscalls(&self) -> &[String] {
+        &self.unresolved_syscalls
+    }
+
+    pub fn get_overrides(&self) -> &HashMap<u32, u32> {
+        &self.overrides
     }
 
     pub fn get_custom_syscall_count(&self) -> u16 {
@@ -318,6 +629,165 @@ impl Scenario {
     pub fn get_sys_desc_offset(&self) -> u32 {
         self.sys_desc_offset
     }
+
+    /// the bytecode, between the offset of the sysdesc and the sysdesc
+    pub fn code_section(&self) -> &[u8] {
+        &self.raw()[4..self.sys_desc_offset as usize]
+    }
+
+    /// the entry point as an offset into [`Self::code_section`]
+    pub fn entry_offset_in_code(&self) -> u32 {
+        self.entry_point - 4
+    }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::format::test_util::build_hcb;
+
+    #[test]
+    fn test_clone_shares_bytes() {
+        let code = [0x01, 0, 0, 0x0e, 3, b'h', b'i', 0, 0x04];
+        let scenario = Scenario::new(build_hcb(&code, 4, &[]), None).unwrap();
+        let cloned = scenario.clone();
+
+        assert_eq!(scenario.raw().as_ptr(), cloned.raw().as_ptr());
+        assert_eq!(scenario.read_cstring(9, 3).unwrap(), "hi");
+        assert_eq!(cloned.read_cstring(9, 3).unwrap(), "hi");
+        assert_eq!(scenario.read_u8(7).unwrap(), cloned.read_u8(7).unwrap());
+    }
+
+    #[test]
+    fn test_serialize_roundtrip() {
+        let code = [0x01, 0, 0, 0x03, 0, 0, 0x03, 1, 0, 0x04];
+        let data = build_hcb(&code, 4, &[(1, "ThreadWait"), (2, "SoundPlay")]);
+        let scenario = Scenario::new(data.clone(), None).unwrap();
+        assert_eq!(scenario.serialize().unwrap(), &data[..]);
+    }
+
+    #[test]
+    fn test_rename_import() {
+        let code = [0x01, 0, 0, 0x03, 0, 0, 0x03, 1, 0, 0x04];
+        let data = build_hcb(&code, 4, &[(1, "ThreadWait"), (2, "SoundPlay")]);
+        let mut scenario = Scenario::new(data.clone(), None).unwrap();
+        scenario.imports_mut().get_mut(&1).unwrap().name = "AudioPlay".to_string();
+
+        let serialized = scenario.serialize().unwrap();
+        let reparsed = Scenario::new(serialized.into(), None).unwrap();
+        assert_eq!(reparsed.get_syscall_name(0), Some("ThreadWait"));
+        assert_eq!(reparsed.get_syscall_name(1), Some("AudioPlay"));
+        assert_eq!(reparsed.get_syscall(1).unwrap().args, 2);
+        assert_eq!(reparsed.get_title(), "test");
+        assert_eq!(reparsed.get_sys_desc_offset(), scenario.get_sys_desc_offset());
+
+        let code_end = scenario.get_sys_desc_offset() as usize;
+        assert_eq!(reparsed.raw()[..code_end], data[..code_end]);
+
+        scenario.imports_mut().remove(&0);
+        assert!(scenario.serialize().is_err());
+    }
 
+    #[test]
+    fn test_code_section() {
+        let code = [0x01, 0, 0, 0x03, 0, 0, 0x04];
+        let scenario = Scenario::new(build_hcb(&code, 4, &[(1, "ThreadWait")]), None).unwrap();
+        let section = scenario.code_section();
+        assert_eq!(section.len(), scenario.get_sys_desc_offset() as usize - 4);
+        assert_eq!(&section[..code.len()], code);
+        assert_eq!(scenario.entry_offset_in_code(), 0);
+        assert!(scenario.is_code_area(4 + section.len() as u32 - 1));
+        assert!(!scenario.is_code_area(4 + section.len() as u32));
+    }
+
+    /// a script with another game mode and global counts, `build_hcb` always uses zeros
+    fn with_game_mode(game_mode: u16, non_volatile: u16, volatile: u16) -> Bytes {
+        let data = build_hcb(&[0x01, 0, 0, 0x04], 4, &[]);
+        let sys_desc = u32::from_le_bytes(data[..4].try_into().unwrap()) as usize;
+        let mut data = data.to_vec();
+        data[sys_desc + 4..sys_desc + 6].copy_from_slice(&non_volatile.to_le_bytes());
+        data[sys_desc + 6..sys_desc + 8].copy_from_slice(&volatile.to_le_bytes());
+        data[sys_desc + 8..sys_desc + 10].copy_from_slice(&game_mode.to_le_bytes());
+        data.into()
+    }
+
+    #[test]
+    fn test_game_mode_setup() {
+        let windowed = Scenario::new(with_game_mode(0, 2, 1), None).unwrap();
+        let widescreen = Scenario::new(with_game_mode(8, 0, 4), None).unwrap();
+        assert_eq!(windowed.get_game_mode(), 0);
+        assert_eq!(widescreen.get_game_mode(), 8);
+
+        let mut global = Global::new();
+        let setup = windowed.init_scene(&mut global);
+        assert_eq!(setup.screen_size, (640, 480));
+        assert!((0..3).all(|key| global.get(key).is_some_and(|v| v.is_nil())));
+        assert!(global.get(3).is_none());
+
+        // a new scene starts over, nothing is left from the previous script
+        global.set(1, variant::Variant::Int(1));
+        let setup = widescreen.init_scene(&mut global);
+        assert_eq!(setup.game_mode, 8);
+        assert_eq!(setup.screen_size, (1280, 720));
+        assert!((0..4).all(|key| global.get(key).is_some_and(|v| v.is_nil())));
+    }
+
+    #[test]
+    fn test_utf16() {
+        let text = "Aあ";
+        let le = [0x41, 0x00, 0x42, 0x30];
+        let be = [0x00, 0x41, 0x30, 0x42];
+        assert_eq!(Nls::UTF16LE.encode(text), le);
+        assert_eq!(Nls::UTF16BE.encode(text), be);
+        assert_eq!(Nls::UTF16LE.decode(&le), text);
+        assert_eq!(Nls::UTF16BE.decode(&be), text);
+
+        // the BOM is dropped, and wins over the configured byte order
+        let with_bom = [&[0xFF, 0xFE][..], &le].concat();
+        assert_eq!(Nls::UTF16LE.decode(&with_bom), text);
+        assert_eq!(Nls::UTF16BE.decode(&with_bom), text);
+
+        // the zero byte of `A` doesn't end the string, the aligned pair does
+        let cstr = [&le[..], &[0, 0, 0x43, 0x00]].concat();
+        assert_eq!(Nls::UTF16LE.cstr_content(&cstr), le);
+        assert_eq!(Nls::UTF16LE.decode_cstr(&cstr), text);
+        assert_eq!(Nls::UTF16LE.decode_cstr(&le), text);
+        assert_eq!(Nls::UTF8.decode_cstr(b"hi\0junk"), "hi");
+
+        let mut sysdesc = Vec::new();
+        push_cstring(&mut sysdesc, &Nls::UTF16LE, text, "title").unwrap();
+        assert_eq!(sysdesc, [6, 0x41, 0x00, 0x42, 0x30, 0, 0]);
+    }
+
+    #[test]
+    fn test_char_slice() {
+        for (nls, text) in [
+            (Nls::ShiftJIS, "名前はｱﾘｽ、A1"),
+            (Nls::GBK, "名字是爱丽丝,A1"),
+            (Nls::UTF8, "名前はアリス、A1"),
+            (Nls::UTF16LE, "名前は𠮷A1"),
+            (Nls::UTF16BE, "名前は𠮷A1"),
+        ] {
+            let chars: Vec<char> = text.chars().collect();
+            let encoded = nls.encode(text);
+            assert_eq!(nls.char_count(&encoded), chars.len(), "{:?}", nls);
+            // every odd and even start, the slices decode to the same characters
+            for start in 0..=chars.len() + 1 {
+                for len in [0, 1, 2, 3, 100] {
+                    let expected: String = chars.iter().skip(start).take(len).collect();
+                    let slice = nls.char_slice(&encoded, start, len);
+                    assert_eq!(nls.decode(slice), expected, "{:?} {} {}", nls, start, len);
+                }
+            }
+        }
+
+        // a four byte GB18030 character, and a lead byte cut short at the end
+        let encoded = Nls::GBK.encode("€x");
+        assert_eq!(Nls::GBK.char_count(&encoded), 2);
+        let euro = [0x81, 0x30, 0x81, 0x30];
+        assert_eq!(Nls::GBK.char_width(&euro), 4);
+        assert_eq!(Nls::ShiftJIS.char_count(&[0x41, 0x82]), 2);
+        assert_eq!(Nls::ShiftJIS.char_slice(&[0x41, 0x82], 1, 5), [0x82]);
+        assert_eq!(Nls::ShiftJIS.char_slice(&[], 0, 5), []);
+    }
+}
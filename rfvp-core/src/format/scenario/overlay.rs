@@ -0,0 +1,268 @@
+//! Patch overlay support
+//!
+//! Some fan patches ship a second HCB which only overrides a handful of
+//! functions of the original script instead of replacing the whole file.
+//! The code area of the patch is relocated right behind the code area of
+//! the base script, so both scripts share one address space and the VM keeps
+//! executing a single buffer:
+//!
+//! |------------------|
+//! | header           | <- points to the (moved) base sysdesc
+//! | base code        | <- 4 .. base sysdesc offset, untouched
+//! | patch code       | <- relocated by `base sysdesc offset - 4`
+//! | base sysdesc     |
+//! |------------------|
+//!
+//! Strings are decoded when they are pushed, so a `Variant` never refers back
+//! to the buffer and needs no tag. Calls into overridden base functions are
+//! redirected through the override map of the `Scenario`.
+//!
+//! The functions a patch overrides are listed by the patch itself, in a table
+//! behind the end of its sysdesc, after the custom syscall count. The engine
+//! reads nothing past that count, so a patch is still a valid script:
+//!
+//! | type              | meaning                                              |
+//! |-------------------|------------------------------------------------------|
+//! | u16               | the number of overrides                              |
+//! | (u32, u32) * n    | a base function, the patch function replacing it     |
+//!
+//! The patch is compiled on its own, so the targets of its calls and jumps
+//! are relocated, and the functions it starts as threads. Other immediates
+//! are left alone, even when their value happens to be a function address.
+
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, bail, Context, Result};
+
+use crate::format::{
+    bytes::{put_u16_le, put_u32_le, read_u16_le, read_u32_le, write_u32_le},
+    scenario::{
+        instructions::{InstructionIter, Opcode},
+        Scenario,
+    },
+};
+
+/// the patch script which belongs to a base script, e.g. `Snow.hcb` -> `Snow.patch.hcb`
+pub fn patch_path(base: &Path) -> PathBuf {
+    base.with_extension("patch.hcb")
+}
+
+/// the entry address of every function in the code area
+fn functions(scenario: &Scenario) -> Result<HashSet<u32>> {
+    let mut funcs = HashSet::new();
+    for inst in InstructionIter::new(scenario) {
+        let inst = inst?;
+        if let Opcode::InitStack = inst.opcode {
+            funcs.insert(inst.address);
+        }
+    }
+
+    Ok(funcs)
+}
+
+/// the override table of the patch, as pairs of base and patch function addresses
+fn read_overrides(patch: &Scenario) -> Result<Vec<(u32, u32)>> {
+    // behind the import table and the custom syscall count
+    let offset = patch.read_imports()?.1 + 2;
+    let raw = patch.raw();
+    if offset >= raw.len() {
+        bail!("the patch script has no override table");
+    }
+
+    let count = read_u16_le(raw, offset)? as usize;
+    (0..count)
+        .map(|i| {
+            let entry = offset + 2 + i * 8;
+            Ok((read_u32_le(raw, entry)?, read_u32_le(raw, entry + 4)?))
+        })
+        .collect::<Result<_>>()
+        .context("truncated override table")
+}
+
+/// relocate the patch into the base script, the overridden functions are the ones
+/// listed in the override table of the patch.
+/// returns the merged buffer and the override map in the merged address space.
+pub(super) fn merge(base: &Scenario, patch: &Scenario) -> Result<(Vec<u8>, HashMap<u32, u32>)> {
+    let base_code_end = base.get_sys_desc_offset() as usize;
+    let patch_code_end = patch.get_sys_desc_offset() as usize;
+    if base_code_end < 4 || patch_code_end < 4 {
+        bail!("invalid sysdesc offset");
+    }
+
+    // patch address + delta = merged address
+    let delta = (base_code_end - 4) as u32;
+    let base_funcs = functions(base)?;
+    let entries = functions(patch)?;
+    let syscall_ids = base
+        .get_all_syscalls()
+        .iter()
+        .map(|(id, syscall)| (syscall.name.as_str(), *id as u16))
+        .collect::<HashMap<_, _>>();
+
+    let mut code = patch.raw()[4..patch_code_end].to_vec();
+    let insts = InstructionIter::new(patch).collect::<Result<Vec<_>>>()?;
+    for (i, inst) in insts.iter().enumerate() {
+        let addr = inst.address as usize;
+        // offset of the first operand in `code`
+        let operand = addr - 4 + 1;
+        match inst.opcode {
+            Opcode::Call | Opcode::Jmp | Opcode::Jz => {
+                let target = patch.read_u32(addr + 1)? + delta;
+                put_u32_le(&mut code, operand, target);
+            }
+            Opcode::PushI32 => {
+                // the entry of a thread is the last argument of ThreadStart, pushed right
+                // before the syscall
+                let starts_thread = match insts.get(i + 1) {
+                    Some(next) if next.opcode == Opcode::Syscall => {
+                        let id = patch.read_u16(next.address as usize + 1)?;
+                        patch.get_syscall_name(id) == Some("ThreadStart")
+                    }
+                    _ => false,
+                };
+                let value = patch.read_u32(addr + 1)?;
+                if starts_thread && entries.contains(&value) {
+                    put_u32_le(&mut code, operand, value + delta);
+                }
+            }
+            Opcode::Syscall => {
+                let id = patch.read_u16(addr + 1)?;
+                let name = patch
+                    .get_syscall_name(id)
+                    .ok_or_else(|| anyhow!("patch syscall not found: {}", id))?;
+                let base_id = syscall_ids
+                    .get(name)
+                    .ok_or_else(|| anyhow!("syscall {} is missing in the base script", name))?;
+                put_u16_le(&mut code, operand, *base_id);
+            }
+            _ => {}
+        }
+    }
+
+    let mut overrides = HashMap::new();
+    for (b, p) in read_overrides(patch)? {
+        if !base_funcs.contains(&b) {
+            bail!(
+                "override target {:#x} is not a function of the base script",
+                b
+            );
+        }
+        if !entries.contains(&p) {
+            bail!("override {:#x} is not a function of the patch script", p);
+        }
+        overrides.insert(b, p + delta);
+    }
+
+    let mut merged = Vec::with_capacity(base.raw().len() + code.len());
+    write_u32_le(&mut merged, (base_code_end + code.len()) as u32);
+    merged.extend_from_slice(&base.raw()[4..base_code_end]);
+    merged.extend_from_slice(&code);
+    merged.extend_from_slice(&base.raw()[base_code_end..]);
+
+    Ok((merged, overrides))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::format::{bytes::write_u16_le, scenario::context::Context, test_util::build_hcb};
+    use bytes::Bytes;
+
+    fn base() -> Bytes {
+        let mut code = Vec::new();
+        // 0x04: main, calls 0x0e
+        code.extend_from_slice(&[0x01, 0, 0, 0x02]);
+        code.extend_from_slice(&0x0eu32.to_le_bytes());
+        code.extend_from_slice(&[0x14, 0x05]);
+        // 0x0e: returns 1
+        code.extend_from_slice(&[0x01, 0, 0, 0x0c, 1, 0x05]);
+        // 0x14: ThreadNext
+        code.extend_from_slice(&[0x01, 0, 0, 0x03, 0, 0, 0x04]);
+        build_hcb(
+            &code,
+            4,
+            &[(0, "ThreadNext"), (1, "Rand"), (2, "ThreadStart")],
+        )
+    }
+
+    /// the patch with its override table
+    fn patch(overrides: Option<&[(u32, u32)]>) -> Bytes {
+        let mut code = Vec::new();
+        // 0x04: starts 0x17 as thread 1, pushes 4 and returns it
+        code.extend_from_slice(&[0x01, 0, 0, 0x0c, 1, 0x0a]);
+        code.extend_from_slice(&0x17u32.to_le_bytes());
+        code.extend_from_slice(&[0x03, 0, 0, 0x0a]);
+        code.extend_from_slice(&4u32.to_le_bytes());
+        code.push(0x05);
+        // 0x17: returns 2
+        code.extend_from_slice(&[0x01, 0, 0, 0x0c, 2, 0x05]);
+
+        let mut data = build_hcb(&code, 4, &[(2, "ThreadStart")]).to_vec();
+        if let Some(overrides) = overrides {
+            write_u16_le(&mut data, overrides.len() as u16);
+            for &(base, patch) in overrides {
+                write_u32_le(&mut data, base);
+                write_u32_le(&mut data, patch);
+            }
+        }
+        data.into()
+    }
+
+    fn merged(overrides: &[(u32, u32)]) -> Result<Scenario> {
+        Scenario::new(base(), None)?.with_patch(patch(Some(overrides)))
+    }
+
+    #[test]
+    fn test_patch_overrides_function() {
+        let scenario = merged(&[(0x0e, 4)]).unwrap();
+        // the base code area ends at 0x1b, so the patch function lands there
+        assert_eq!(scenario.resolve_function(0x0e), 0x1b);
+        assert_eq!(scenario.resolve_function(0x14), 0x14);
+        // the entry point has the same signature, but isn't listed
+        assert_eq!(scenario.resolve_function(4), 4);
+        assert_eq!(scenario.get_entry_point(), 4);
+
+        let mut context = Context::new(scenario.get_entry_point());
+        context.dispatch_opcode(&scenario).unwrap();
+        context.dispatch_opcode(&scenario).unwrap();
+        assert_eq!(context.get_pc(), 0x1b);
+    }
+
+    #[test]
+    fn test_patch_relocation() {
+        let scenario = merged(&[(0x0e, 4)]).unwrap();
+        // the thread entry is relocated
+        assert_eq!(scenario.read_u8(0x20).unwrap(), 0x0a);
+        assert_eq!(scenario.read_u32(0x21).unwrap(), 0x2e);
+        // the syscall id of the patch is remapped to the one of the base
+        assert_eq!(scenario.read_u8(0x25).unwrap(), 0x03);
+        assert_eq!(scenario.read_u16(0x26).unwrap(), 2);
+        assert_eq!(scenario.get_syscall_name(2), Some("ThreadStart"));
+        // a plain immediate is left alone, even if it looks like a function address
+        assert_eq!(scenario.read_u8(0x28).unwrap(), 0x0a);
+        assert_eq!(scenario.read_u32(0x29).unwrap(), 4);
+    }
+
+    #[test]
+    fn test_patch_rejects_invalid_overrides() {
+        assert!(merged(&[(0x10, 4)]).is_err());
+        assert!(merged(&[(0x0e, 5)]).is_err());
+        let base = Scenario::new(base(), None).unwrap();
+        assert!(base.with_patch(patch(None)).is_err());
+        // a truncated table
+        let mut data = patch(Some(&[(0x0e, 4)])).to_vec();
+        data.pop();
+        assert!(base.with_patch(data.into()).is_err());
+    }
+
+    #[test]
+    fn test_patch_path() {
+        assert_eq!(
+            patch_path(Path::new("Snow.hcb")),
+            PathBuf::from("Snow.patch.hcb")
+        );
+    }
+}
@@ -0,0 +1,232 @@
+//! Reads what a launcher wants to show about a game without loading it.
+//!
+//! Only the sysdesc at the end of the HCB is read, the code and the archives are
+//! never touched, so probing a directory is cheap enough to do for a whole library.
+
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+
+use super::{overlay, screen_size_for_game_mode, Nls};
+use crate::format::bytes::read_u16_le;
+
+/// What [`probe_game`] found out about a game directory
+#[derive(Debug, Clone, PartialEq)]
+pub struct GameInfo {
+    pub hcb_path: PathBuf,
+    /// the title, transcoded from `nls`
+    pub title: String,
+    pub screen_width: u32,
+    pub screen_height: u32,
+    pub game_mode: u16,
+    pub syscall_count: u16,
+    /// the encoding guessed from the title
+    pub nls: Nls,
+    /// how sure the guess is, from 0 to 1
+    pub nls_confidence: f32,
+    /// whether there is a script patch next to the script, see [`overlay::patch_path`]
+    pub has_patch: bool,
+}
+
+/// the first `*.hcb` of the game directory, like the engine picks it, the script
+/// patches are skipped
+pub fn find_hcb(game_root: impl AsRef<Path>) -> Result<PathBuf> {
+    let pattern = game_root.as_ref().join("*.hcb");
+    let mut matches: Vec<_> = glob::glob(&pattern.to_string_lossy())?
+        .flatten()
+        .filter(|path| !path.to_string_lossy().ends_with(".patch.hcb"))
+        .collect();
+    matches.sort();
+
+    match matches.into_iter().next() {
+        Some(path) => Ok(path),
+        None => bail!("No hcb file found in {:?}", game_root.as_ref()),
+    }
+}
+
+pub fn probe_game(game_root: impl AsRef<Path>) -> Result<GameInfo> {
+    let game_root = game_root.as_ref();
+    let hcb_path = find_hcb(game_root)?;
+
+    let mut file = File::open(&hcb_path).with_context(|| format!("Opening {:?}", hcb_path))?;
+    let mut offset = [0u8; 4];
+    file.read_exact(&mut offset)
+        .context("Reading the sysdesc offset")?;
+    let sys_desc_offset = u32::from_le_bytes(offset);
+
+    file.seek(SeekFrom::Start(sys_desc_offset as u64))?;
+    let mut sys_desc = Vec::new();
+    file.read_to_end(&mut sys_desc)?;
+
+    let header = SysDescHeader::parse(&sys_desc)
+        .with_context(|| format!("Parsing the sysdesc of {:?}", hcb_path))?;
+    let (nls, nls_confidence) = detect_nls(header.title);
+    let (screen_width, screen_height) = screen_size_for_game_mode(header.game_mode);
+
+    Ok(GameInfo {
+        title: nls.decode(header.title),
+        screen_width,
+        screen_height,
+        game_mode: header.game_mode,
+        syscall_count: header.syscall_count,
+        nls,
+        nls_confidence,
+        has_patch: overlay::patch_path(&hcb_path).is_file(),
+        hcb_path,
+    })
+}
+
+struct SysDescHeader<'a> {
+    game_mode: u16,
+    title: &'a [u8],
+    syscall_count: u16,
+}
+
+impl<'a> SysDescHeader<'a> {
+    fn parse(data: &'a [u8]) -> Result<Self> {
+        let u16_at = |offset: usize| read_u16_le(data, offset).context("sysdesc is truncated");
+
+        // entry point, non-volatile and volatile global counts come first
+        let game_mode = u16_at(8)?;
+        let Some(&title_len) = data.get(10) else {
+            bail!("sysdesc is truncated");
+        };
+        let title_end = 11 + title_len as usize;
+        let Some(title) = data.get(11..title_end) else {
+            bail!("sysdesc is truncated");
+        };
+        // the length includes the null terminator
+        let title = title.split(|&b| b == 0).next().unwrap_or_default();
+        let syscall_count = u16_at(title_end)?;
+
+        Ok(Self {
+            game_mode,
+            title,
+            syscall_count,
+        })
+    }
+}
+
+fn is_kana(c: char) -> bool {
+    matches!(c, '\u{3040}'..='\u{30FF}' | '\u{FF66}'..='\u{FF9F}')
+}
+
+/// guesses the encoding of a script string, with a confidence from 0 to 1.
+///
+/// Non-ASCII text which is valid UTF-8 is almost never Shift-JIS or GBK. Between
+/// those two, kana only decode from Shift-JIS, while kanji-only text could be either.
+pub fn detect_nls(bytes: &[u8]) -> (Nls, f32) {
+    if bytes.is_ascii() {
+        // every encoding reads it the same, the original engine default it is
+        return (Nls::ShiftJIS, 0.5);
+    }
+    if std::str::from_utf8(bytes).is_ok() {
+        return (Nls::UTF8, 0.9);
+    }
+
+    let (sjis, _, sjis_errors) = encoding_rs::SHIFT_JIS.decode(bytes);
+    let (gbk, _, gbk_errors) = encoding_rs::GBK.decode(bytes);
+    match (sjis_errors, gbk_errors) {
+        (false, true) => (Nls::ShiftJIS, 0.9),
+        (true, false) => (Nls::GBK, 0.9),
+        (false, false) if sjis.chars().any(is_kana) && !gbk.chars().any(is_kana) => {
+            (Nls::ShiftJIS, 0.8)
+        }
+        (false, false) => (Nls::ShiftJIS, 0.5),
+        (true, true) => (Nls::ShiftJIS, 0.1),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::format::{scenario::Scenario, test_util::build_hcb};
+
+    fn temp_root(name: &str) -> PathBuf {
+        let root =
+            std::env::temp_dir().join(format!("rfvp_probe_test_{}_{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(&root).unwrap();
+        root
+    }
+
+    /// a script with another title, `build_hcb` always names it "test"
+    fn with_title(hcb: &[u8], title: &[u8]) -> Vec<u8> {
+        let title_offset = u32::from_le_bytes(hcb[..4].try_into().unwrap()) as usize + 10;
+        let mut result = hcb[..title_offset].to_vec();
+        result.push(title.len() as u8 + 1);
+        result.extend_from_slice(title);
+        result.push(0);
+        result.extend_from_slice(&hcb[title_offset + 1 + hcb[title_offset] as usize..]);
+        result
+    }
+
+    #[test]
+    fn test_probe() {
+        let root = temp_root("game");
+        let hcb = build_hcb(
+            &[0x01, 0x00, 0x00, 0x04],
+            4,
+            &[(1, "ThreadStart"), (0, "Rand")],
+        );
+        let title = Nls::ShiftJIS.encode("ゲームのタイトル");
+        std::fs::write(root.join("game.hcb"), with_title(&hcb, &title)).unwrap();
+
+        let info = probe_game(&root).unwrap();
+        assert_eq!(info.title, "ゲームのタイトル");
+        assert_eq!(info.nls, Nls::ShiftJIS);
+        assert!(info.nls_confidence >= 0.8);
+        assert_eq!(info.game_mode, 0);
+        assert_eq!((info.screen_width, info.screen_height), (640, 480));
+        assert_eq!(info.syscall_count, 2);
+        assert!(!info.has_patch);
+
+        std::fs::create_dir(root.join("patch")).unwrap();
+        assert!(!probe_game(&root).unwrap().has_patch);
+        std::fs::write(root.join("game.patch.hcb"), &hcb).unwrap();
+        assert!(probe_game(&root).unwrap().has_patch);
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_probe_snow() {
+        let root = temp_root("snow");
+        let hcb = std::fs::read(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../disassembler/testcase/Snow.hcb"
+        ))
+        .unwrap();
+        std::fs::write(root.join("Snow.hcb"), &hcb).unwrap();
+
+        // the sysdesc alone says what loading the whole script does
+        let info = probe_game(&root).unwrap();
+        let scenario = Scenario::new(hcb.into(), Some(info.nls)).unwrap();
+        assert_eq!(info.title, scenario.get_title());
+        assert_eq!(info.game_mode, scenario.get_game_mode());
+        assert_eq!(info.syscall_count, scenario.syscall_count);
+        assert_eq!(
+            (info.screen_width, info.screen_height),
+            scenario.get_screen_size()
+        );
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_probe_malformed() {
+        let root = temp_root("malformed");
+        assert!(probe_game(&root).is_err());
+
+        // the sysdesc offset points past the end of the file
+        std::fs::write(root.join("broken.hcb"), [0xFF, 0x00, 0x00, 0x00, 0x01]).unwrap();
+        let err = probe_game(&root).unwrap_err();
+        assert!(format!("{:#}", err).contains("truncated"));
+
+        assert!(probe_game(root.join("missing")).is_err());
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,212 @@
+//! Values in the layout of the original engine: a type tag and 32 bits.
+//!
+//! For the tools comparing the state of rfvp with a memory dump of the original
+//! engine. The tags are the types of [`matrix`](crate::vm::matrix). Ints and floats
+//! are their bits, a const string is the offset of its `push_string` operand (the
+//! length byte) in the code, a dynamic string the index of the string in the pool of
+//! the dump.
+//!
+//! rfvp pushes the literals as dynamic strings, the const strings of a dump are decoded
+//! to the [`Variant::String`] rfvp would hold.
+//!
+//! Tables are lossy: the original engine stores a handle to a table, the entries
+//! aren't part of the value. A table is encoded as handle 0 and a handle is decoded
+//! to an empty table. Stack frames only exist inside the VM and aren't converted.
+
+use anyhow::{anyhow, bail, Result};
+
+use crate::{
+    format::{
+        bytes,
+        scenario::{
+            variant::{Table, Variant},
+            Nls,
+        },
+    },
+    vm::matrix,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u32)]
+pub enum RawType {
+    Nil = matrix::TYPE_NIL as u32,
+    True = matrix::TYPE_TRUE as u32,
+    Int = matrix::TYPE_INT as u32,
+    Float = matrix::TYPE_FLOAT as u32,
+    String = matrix::TYPE_STRING as u32,
+    ConstString = matrix::TYPE_CONST_STRING as u32,
+    Table = matrix::TYPE_TABLE as u32,
+}
+
+impl TryFrom<u32> for RawType {
+    type Error = anyhow::Error;
+
+    fn try_from(tag: u32) -> Result<Self> {
+        Ok(match tag as usize {
+            matrix::TYPE_NIL => RawType::Nil,
+            matrix::TYPE_TRUE => RawType::True,
+            matrix::TYPE_INT => RawType::Int,
+            matrix::TYPE_FLOAT => RawType::Float,
+            matrix::TYPE_STRING => RawType::String,
+            matrix::TYPE_CONST_STRING => RawType::ConstString,
+            matrix::TYPE_TABLE => RawType::Table,
+            _ => bail!("unknown value type {}", tag),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RawVariant {
+    pub ty: RawType,
+    pub value: u32,
+}
+
+/// The dynamic strings of a dump, by index
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StringPool {
+    strings: Vec<String>,
+}
+
+impl StringPool {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, string: String) -> u32 {
+        self.strings.push(string);
+        (self.strings.len() - 1) as u32
+    }
+
+    pub fn get(&self, index: u32) -> Option<&str> {
+        self.strings.get(index as usize).map(String::as_str)
+    }
+}
+
+impl RawVariant {
+    pub fn new(ty: RawType, value: u32) -> Self {
+        Self { ty, value }
+    }
+
+    /// a dynamic string is added to `pool`
+    pub fn encode(value: &Variant, pool: &mut StringPool) -> Result<Self> {
+        match value {
+            Variant::String(s) => Ok(Self::new(RawType::String, pool.push(s.clone()))),
+            value => Self::try_from(value),
+        }
+    }
+
+    /// the const strings are read from `code`, in the encoding of the script
+    pub fn decode(self, code: &[u8], nls: &Nls, pool: &StringPool) -> Result<Variant> {
+        match self.ty {
+            RawType::String => pool
+                .get(self.value)
+                .map(|s| Variant::String(s.to_string()))
+                .ok_or_else(|| anyhow!("no dynamic string {} in the pool", self.value)),
+            RawType::ConstString => {
+                let offset = self.value as usize;
+                let len = bytes::read_u8(code, offset)? as usize;
+                let Some(string) = code.get(offset + 1..offset + 1 + len) else {
+                    bail!("const string at {:#x} out of bounds", offset);
+                };
+                // the length counts the terminator
+                Ok(Variant::String(nls.decode_cstr(string)))
+            }
+            _ => Variant::try_from(self),
+        }
+    }
+}
+
+/// the values which don't need the code or the string pool
+impl TryFrom<&Variant> for RawVariant {
+    type Error = anyhow::Error;
+
+    fn try_from(value: &Variant) -> Result<Self> {
+        Ok(match value {
+            Variant::Nil => Self::new(RawType::Nil, 0),
+            Variant::True => Self::new(RawType::True, 1),
+            Variant::Int(i) => Self::new(RawType::Int, *i as u32),
+            Variant::Float(f) => Self::new(RawType::Float, f.to_bits()),
+            Variant::ConstString(_, offset) => Self::new(RawType::ConstString, *offset),
+            Variant::Table(_) => Self::new(RawType::Table, 0),
+            Variant::String(_) => bail!("a dynamic string needs a pool, use RawVariant::encode"),
+            Variant::SavedStackInfo(_) => bail!("a stack frame has no raw value"),
+        })
+    }
+}
+
+/// the values which don't need the code or the string pool
+impl TryFrom<RawVariant> for Variant {
+    type Error = anyhow::Error;
+
+    fn try_from(raw: RawVariant) -> Result<Self> {
+        Ok(match raw.ty {
+            RawType::Nil => Variant::Nil,
+            RawType::True => Variant::True,
+            RawType::Int => Variant::Int(raw.value as i32),
+            RawType::Float => Variant::Float(f32::from_bits(raw.value)),
+            RawType::Table => Variant::Table(Table::new()),
+            RawType::String | RawType::ConstString => {
+                bail!("a string needs the code and the pool, use RawVariant::decode")
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        // push_string "ab", with the length counting the terminator
+        let code = [0x0e, 0x03, b'a', b'b', 0x00];
+        let mut pool = StringPool::new();
+        pool.push("unused".to_string());
+
+        let values = [
+            Variant::Nil,
+            Variant::True,
+            Variant::Int(-5),
+            Variant::Float(1.5),
+            Variant::String("dyn".to_string()),
+        ];
+        let raw = values
+            .iter()
+            .map(|value| RawVariant::encode(value, &mut pool).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            raw,
+            [
+                RawVariant::new(RawType::Nil, 0),
+                RawVariant::new(RawType::True, 1),
+                RawVariant::new(RawType::Int, 0xffff_fffb),
+                RawVariant::new(RawType::Float, 0x3fc0_0000),
+                RawVariant::new(RawType::String, 1),
+            ]
+        );
+        for (value, raw) in values.iter().zip(raw) {
+            let decoded = raw.decode(&code, &Nls::UTF8, &pool).unwrap();
+            assert_eq!(format!("{:?}", decoded), format!("{:?}", value));
+            assert_eq!(RawType::try_from(raw.ty as u32).unwrap(), raw.ty);
+        }
+
+        // a literal in a dump reads like the string push_string makes
+        let literal = RawVariant::new(RawType::ConstString, 1);
+        let decoded = literal.decode(&code, &Nls::UTF8, &pool).unwrap();
+        assert!(matches!(decoded, Variant::String(s) if s == "ab"));
+
+        // lossy
+        let mut table = Table::new();
+        table.push(Variant::Int(1));
+        let raw = RawVariant::encode(&Variant::Table(table), &mut pool).unwrap();
+        assert_eq!(raw, RawVariant::new(RawType::Table, 0));
+        let decoded = raw.decode(&code, &Nls::UTF8, &pool).unwrap();
+        assert!(matches!(decoded, Variant::Table(table) if table.is_empty()));
+
+        assert!(Variant::try_from(RawVariant::new(RawType::String, 0)).is_err());
+        assert!(RawVariant::try_from(&Variant::String("dyn".to_string())).is_err());
+        let out_of_bounds = RawVariant::new(RawType::ConstString, 3);
+        assert!(out_of_bounds.decode(&code, &Nls::UTF8, &pool).is_err());
+        assert!(RawType::try_from(7).is_err());
+    }
+}
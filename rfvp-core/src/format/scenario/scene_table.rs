@@ -0,0 +1,104 @@
+//! The scenes a developer can jump to, listed in a sidecar file next to the script.
+//!
+//! The file is YAML, a list of function addresses with a display name:
+//!
+//! ```yaml
+//! - address: 0x1a2b4
+//!   name: "Chapter 1: Prologue"
+//! - address: 0x2c0de
+//!   name: "Chapter 2"
+//! ```
+
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+
+use super::{instructions::Opcode, Scenario};
+
+/// The name of the sidecar file, in the game directory
+pub const SCENE_TABLE_FILE: &str = "scenes.yaml";
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct SceneEntry {
+    /// a function of the script, the scene starts by calling it from the main thread
+    pub address: u32,
+    pub name: String,
+}
+
+/// parse a scene table, every address has to be the start of a function of `scenario`
+pub fn parse_scene_table(text: &str, scenario: &Scenario) -> Result<Vec<SceneEntry>> {
+    let entries: Vec<SceneEntry> = serde_yaml::from_str(text)?;
+
+    for entry in &entries {
+        if entry.address < 4 || entry.address >= scenario.get_sys_desc_offset() {
+            bail!(
+                "scene {:?} at {:#x} is outside of the code",
+                entry.name,
+                entry.address
+            );
+        }
+        if scenario.read_u8(entry.address as usize)? != Opcode::InitStack as u8 {
+            bail!(
+                "scene {:?} at {:#x} isn't the start of a function",
+                entry.name,
+                entry.address
+            );
+        }
+    }
+
+    Ok(entries)
+}
+
+/// load the scene table of the game in `game_root`, an empty table if there is none
+pub fn load_scene_table(
+    game_root: impl AsRef<Path>,
+    scenario: &Scenario,
+) -> Result<Vec<SceneEntry>> {
+    let path = game_root.as_ref().join(SCENE_TABLE_FILE);
+    let text = match std::fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err).with_context(|| format!("Reading {:?}", path)),
+    };
+    parse_scene_table(&text, scenario).with_context(|| format!("Parsing {:?}", path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::format::test_util::build_hcb;
+
+    #[test]
+    fn test_parse_scene_table() {
+        // two functions, at 0x04 and 0x08
+        let code = [0x01, 0, 0, 0x04, 0x01, 0, 0, 0x04];
+        let scenario = Scenario::new(build_hcb(&code, 4, &[]), None).unwrap();
+
+        let table = parse_scene_table(
+            "- address: 0x8\n  name: \"Chapter 2\"\n- address: 4\n  name: Prologue\n",
+            &scenario,
+        )
+        .unwrap();
+        assert_eq!(
+            table,
+            vec![
+                SceneEntry {
+                    address: 8,
+                    name: "Chapter 2".to_string()
+                },
+                SceneEntry {
+                    address: 4,
+                    name: "Prologue".to_string()
+                },
+            ]
+        );
+
+        // in the middle of an instruction
+        let err = parse_scene_table("- {address: 5, name: broken}", &scenario).unwrap_err();
+        assert!(err.to_string().contains("start of a function"));
+        // past the code
+        assert!(parse_scene_table("- {address: 0x100, name: far}", &scenario).is_err());
+        assert!(parse_scene_table("- {name: no address}", &scenario).is_err());
+    }
+}
@@ -0,0 +1,186 @@
+//! In-place string patches, for translations which only change the text.
+//!
+//! Reassembling a script moves every instruction behind the first changed string,
+//! a text patch rewrites the `PushString` operands where they are instead. A
+//! replacement has to encode to at most the length of the original string, the
+//! rest of the operand is padded with nulls, so no address in the script changes.
+//!
+//! ```toml
+//! [[string]]
+//! address = 4660
+//! hash = 3735928559
+//! original = "おはよう"
+//! text = "Good morning"
+//! ```
+//!
+//! `address` is the file offset of the `PushString` instruction and `hash` the
+//! crc32 of the original encoded string, a patch made for another version of the
+//! script is refused instead of garbling it. `original` is only there for the
+//! translators.
+
+use std::collections::HashSet;
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    instructions::{InstructionIter, Opcode},
+    Scenario,
+};
+use crate::format::save::crc32::crc32;
+
+/// The name of the text patch, in the override directory of the game
+pub const TEXT_PATCH_FILE: &str = "text_patch.toml";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StringPatch {
+    pub address: u32,
+    pub hash: u32,
+    #[serde(default)]
+    pub original: String,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TextPatch {
+    #[serde(default, rename = "string")]
+    pub strings: Vec<StringPatch>,
+}
+
+/// the encoded string of a `PushString`, without the null terminator and the padding
+fn push_string_content(scenario: &Scenario, address: u32) -> &[u8] {
+    let start = address as usize + 2;
+    let len = scenario.raw()[address as usize + 1] as usize;
+    scenario
+        .nls
+        .cstr_content(&scenario.raw()[start..start + len])
+}
+
+impl TextPatch {
+    /// a patch listing every string of the script, replaced by itself
+    pub fn create(scenario: &Scenario) -> Result<Self> {
+        let mut strings = Vec::new();
+        for inst in InstructionIter::new(scenario) {
+            let inst = inst?;
+            if inst.opcode != Opcode::PushString {
+                continue;
+            }
+            let content = push_string_content(scenario, inst.address);
+            let original = scenario.nls.decode(content);
+            strings.push(StringPatch {
+                address: inst.address,
+                hash: crc32(content, 0),
+                text: original.clone(),
+                original,
+            });
+        }
+
+        Ok(Self { strings })
+    }
+
+    pub fn parse(content: &str) -> Result<Self> {
+        toml::from_str(content).context("Parsing the text patch")
+    }
+
+    pub fn to_toml(&self) -> Result<String> {
+        Ok(toml::to_string(self)?)
+    }
+
+    /// the script bytes with the strings replaced, fails if a string doesn't match
+    /// its hash or its replacement is too long
+    pub fn apply(&self, scenario: &Scenario) -> Result<Vec<u8>> {
+        let mut push_strings = HashSet::new();
+        for inst in InstructionIter::new(scenario) {
+            let inst = inst?;
+            if inst.opcode == Opcode::PushString {
+                push_strings.insert(inst.address);
+            }
+        }
+
+        let mut data = scenario.raw().to_vec();
+        for patch in &self.strings {
+            if !push_strings.contains(&patch.address) {
+                bail!("no push_string at {:#x}", patch.address);
+            }
+            let content = push_string_content(scenario, patch.address);
+            if crc32(content, 0) != patch.hash {
+                bail!(
+                    "the string at {:#x} doesn't match the patch, it is {:?}",
+                    patch.address,
+                    scenario.nls.decode(content)
+                );
+            }
+
+            let encoded = scenario.nls.encode(&patch.text);
+            let start = patch.address as usize + 2;
+            let len = data[patch.address as usize + 1] as usize;
+            // keep room for the null terminator
+            if encoded.len() + 1 > len {
+                bail!(
+                    "the replacement of the string at {:#x} takes {} bytes, only {} fit: {:?}",
+                    patch.address,
+                    encoded.len(),
+                    len - 1,
+                    patch.text
+                );
+            }
+            data[start..start + encoded.len()].copy_from_slice(&encoded);
+            data[start + encoded.len()..start + len].fill(0);
+        }
+
+        Ok(data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rfvp_test_support::{build_hcb, CodeBuilder};
+
+    use super::*;
+
+    fn scenario() -> Scenario {
+        let mut code = CodeBuilder::new();
+        code.init_stack(0, 0)
+            .push_string("hello world")
+            .push_string("bye")
+            .add()
+            .retv();
+        Scenario::new(build_hcb(code.code(), 4, &[]), None).unwrap()
+    }
+
+    #[test]
+    fn test_text_patch() {
+        let scenario = scenario();
+        let patch = TextPatch::create(&scenario).unwrap();
+        assert_eq!(patch.strings.len(), 2);
+        assert_eq!(patch.strings[0].original, "hello world");
+
+        // unchanged strings give back the same bytes
+        let mut patch = TextPatch::parse(&patch.to_toml().unwrap()).unwrap();
+        assert_eq!(patch.apply(&scenario).unwrap(), scenario.raw());
+
+        patch.strings[0].text = "hi".to_string();
+        let patched = Scenario::new(patch.apply(&scenario).unwrap().into(), None).unwrap();
+        assert_eq!(patched.raw().len(), scenario.raw().len());
+        let strings = TextPatch::create(&patched).unwrap().strings;
+        assert_eq!(strings[0].original, "hi");
+        assert_eq!(strings[0].address, patch.strings[0].address);
+        assert_eq!(strings[1].original, "bye");
+
+        patch.strings[1].text = "goodbye".to_string();
+        assert!(patch.apply(&scenario).is_err());
+    }
+
+    #[test]
+    fn test_hash_mismatch() {
+        let scenario = scenario();
+        let mut patch = TextPatch::create(&scenario).unwrap();
+        patch.strings[1].hash ^= 1;
+        assert!(patch.apply(&scenario).is_err());
+
+        // strings are only replaced at the start of a push_string
+        let mut patch = TextPatch::create(&scenario).unwrap();
+        patch.strings[0].address += 1;
+        assert!(patch.apply(&scenario).is_err());
+    }
+}
@@ -2,6 +2,11 @@ use serde::{Serialize, Deserialize};
 use twofloat::TwoFloat;
 use std::collections::HashMap;
 
+use crate::vm::{
+    matrix::{self, EqCell},
+    IntOverflow,
+};
+
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub(crate) struct SavedStackInfo {
@@ -44,6 +49,21 @@ impl Table {
     pub fn get(&self, key: u32) -> Option<&Variant> {
         self.table.get(&key)
     }
+
+    pub fn len(&self) -> usize {
+        self.table.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.table.is_empty()
+    }
+
+    /// entries in key order
+    pub fn iter(&self) -> impl Iterator<Item = (u32, &Variant)> {
+        let mut keys = self.table.keys().copied().collect::<Vec<_>>();
+        keys.sort_unstable();
+        keys.into_iter().map(move |key| (key, &self.table[&key]))
+    }
 }
 
 /// Represents a value that can be stored in the VM
@@ -115,6 +135,15 @@ impl Variant {
         }
     }
 
+    /// The key of a table read or write indexed by this value.
+    ///
+    /// Only ints are keys, their bits are the key: -1 is `0xFFFF_FFFF` everywhere, so the
+    /// tables of a save load the same on any platform. The original engine doesn't hash
+    /// the other values, a string key is as invalid as a float or nil one.
+    pub fn as_table_key(&self) -> Option<u32> {
+        self.as_int().map(|key| key as u32)
+    }
+
     pub fn as_float(&self) -> Option<f32> {
         match self {
             Variant::Float(f) => Some(*f),
@@ -158,15 +187,27 @@ impl Variant {
     }
 
     pub fn vadd(&mut self, other: &Variant) {
-        *self = vm_add(self.clone(), other.clone());
+        self.vadd_with(other, IntOverflow::default());
     }
 
     pub fn vsub(&mut self, other: &Variant) {
-        *self = vm_sub(self.clone(), other.clone());
+        self.vsub_with(other, IntOverflow::default());
     }
 
     pub fn vmul(&mut self, other: &Variant) {
-        *self = vm_mul(self.clone(), other.clone());
+        self.vmul_with(other, IntOverflow::default());
+    }
+
+    pub fn vadd_with(&mut self, other: &Variant, overflow: IntOverflow) {
+        *self = vm_add(self.clone(), other.clone(), overflow);
+    }
+
+    pub fn vsub_with(&mut self, other: &Variant, overflow: IntOverflow) {
+        *self = vm_sub(self.clone(), other.clone(), overflow);
+    }
+
+    pub fn vmul_with(&mut self, other: &Variant, overflow: IntOverflow) {
+        *self = vm_mul(self.clone(), other.clone(), overflow);
     }
 
     pub fn vdiv(&mut self, other: &Variant) {
@@ -185,92 +226,45 @@ impl Variant {
         }
     }
 
+    /// see [`matrix::AND_MATRIX`]
     pub fn and(&mut self, other: &Variant) {
-        let result = match (self.clone(), other) {
-            (Variant::Nil, Variant::Nil) => Variant::Nil,
-            (Variant::Nil, _) => Variant::Nil,
-            (_, Variant::Nil) => Variant::Nil,
-            _ => Variant::True,
-        };
-
-        *self = result;
+        *self = Variant::from_bool(matrix::and_cell(self, other));
     }
 
+    /// see [`matrix::OR_MATRIX`]
     pub fn or(&mut self, other: &Variant) {
-        let result = match (self.clone(), other) {
-            (Variant::Nil, Variant::Nil) => Variant::Nil,
-            _ => Variant::True,
-        };
-
-        *self = result;
+        *self = Variant::from_bool(matrix::or_cell(self, other));
     }
 
+    /// see [`matrix::EQ_MATRIX`]
     pub fn equal(&mut self, other: &Variant) {
-        let result = match (self.clone(), other) {
-            (Variant::Nil, Variant::Nil) => Variant::True,
-            (Variant::True, Variant::True) => Variant::True,
-            (Variant::Int(a), Variant::Int(b)) => {
-                if a == *b {
-                    Variant::True
-                } else {
-                    Variant::Nil
-                }
+        let equal = match matrix::eq_cell(self, other) {
+            EqCell::Never => false,
+            EqCell::Always => true,
+            EqCell::Number => match (&*self, other) {
+                (Variant::Int(a), Variant::Int(b)) => a == b,
+                _ => self.as_two_float() == other.as_two_float(),
             },
-            (Variant::Float(a), Variant::Float(b)) => {
-                let wrapped_a = TwoFloat::from(a);
-                let wrapped_b = TwoFloat::from(*b);
-
-                if wrapped_a == wrapped_b {
-                    Variant::True
-                } else {
-                    Variant::Nil
-                }
-            },
-            (Variant::Int(a), Variant::Float(b)) => {
-                let wrapped_a = TwoFloat::from(a);
-                let wrapped_b = TwoFloat::from(*b);
+            EqCell::String => self.as_string() == other.as_string(),
+        };
 
-                if wrapped_a == wrapped_b {
-                    Variant::True
-                } else {
-                    Variant::Nil
-                }
-            },
-            (Variant::Float(a), Variant::Int(b)) => {
-                let wrapped_a = TwoFloat::from(a);
-                let wrapped_b = TwoFloat::from(*b);
+        *self = Variant::from_bool(equal);
+    }
 
-                if wrapped_a == wrapped_b {
-                    Variant::True
-                } else {
-                    Variant::Nil
-                }
-            },
-            (Variant::String(a), Variant::String(b)) => {
-                if a == *b {
-                    Variant::True
-                } else {
-                    Variant::Nil
-                }
-            },
-            (Variant::String(a), Variant::ConstString(b, _)) => {
-                if a == *b {
-                    Variant::True
-                } else {
-                    Variant::Nil
-                }
-            },
-            (Variant::ConstString(a, _), Variant::String(b)) => {
-                if a == *b {
-                    Variant::True
-                } else {
-                    Variant::Nil
-                }
-            },
-            _ => Variant::Nil,
-        };
+    fn from_bool(value: bool) -> Variant {
+        if value {
+            Variant::True
+        } else {
+            Variant::Nil
+        }
+    }
 
-        *self = result;
+    fn as_two_float(&self) -> Option<TwoFloat> {
+        match self {
+            Variant::Int(i) => Some(TwoFloat::from(*i)),
+            Variant::Float(f) => Some(TwoFloat::from(*f)),
+            _ => None,
+        }
     }
 
     pub fn not_equal(&mut self, other: &Variant) {
@@ -416,7 +410,7 @@ impl Variant {
     }
 
     pub fn less(&mut self, other: &Variant) {
-        let _result = match (self.clone(), other) {
+        let result = match (self.clone(), other) {
             (Variant::Int(a), Variant::Int(b)) => {
                 if a < *b {
                     Variant::True
@@ -477,6 +471,8 @@ impl Variant {
             },
             _ => Variant::Nil,
         };
+
+        *self = result;
     }
 
     pub fn greater_equal(&mut self, other: &Variant) {
@@ -504,9 +500,32 @@ impl Variant {
     }
 }
 
-pub fn vm_add(a: Variant, b: Variant) -> Variant {
+/// integer arithmetic under the given overflow policy
+fn int_op(
+    a: i32,
+    b: i32,
+    overflow: IntOverflow,
+    checked: fn(i32, i32) -> Option<i32>,
+    wrapping: fn(i32, i32) -> i32,
+    saturating: fn(i32, i32) -> i32,
+) -> Variant {
+    match overflow {
+        IntOverflow::Wrap => Variant::Int(wrapping(a, b)),
+        IntOverflow::Saturate => Variant::Int(saturating(a, b)),
+        IntOverflow::Nil => checked(a, b).map_or(Variant::Nil, Variant::Int),
+    }
+}
+
+pub fn vm_add(a: Variant, b: Variant, overflow: IntOverflow) -> Variant {
     match (a, b) {
-        (Variant::Int(a), Variant::Int(b)) => Variant::Int(a + b),
+        (Variant::Int(a), Variant::Int(b)) => int_op(
+            a,
+            b,
+            overflow,
+            i32::checked_add,
+            i32::wrapping_add,
+            i32::saturating_add,
+        ),
         (Variant::Float(a), Variant::Float(b)) => {
             let wrapped_a = TwoFloat::from(a);
             let wrapped_b = TwoFloat::from(b);
@@ -532,9 +551,16 @@ pub fn vm_add(a: Variant, b: Variant) -> Variant {
     }
 }
 
-pub fn vm_sub(a: Variant, b: Variant) -> Variant {
+pub fn vm_sub(a: Variant, b: Variant, overflow: IntOverflow) -> Variant {
     match (a, b) {
-        (Variant::Int(a), Variant::Int(b)) => Variant::Int(a - b),
+        (Variant::Int(a), Variant::Int(b)) => int_op(
+            a,
+            b,
+            overflow,
+            i32::checked_sub,
+            i32::wrapping_sub,
+            i32::saturating_sub,
+        ),
         (Variant::Float(a), Variant::Float(b)) => {
             let wrapped_a = TwoFloat::from(a);
             let wrapped_b = TwoFloat::from(b);
@@ -557,12 +583,16 @@ pub fn vm_sub(a: Variant, b: Variant) -> Variant {
     }
 }
 
-pub fn vm_mul(a: Variant, b: Variant) -> Variant {
+pub fn vm_mul(a: Variant, b: Variant, overflow: IntOverflow) -> Variant {
     match (a, b) {
-        (Variant::Int(a), Variant::Int(b)) => {
-            let result = a * b;
-            Variant::Int(result)
-        },
+        (Variant::Int(a), Variant::Int(b)) => int_op(
+            a,
+            b,
+            overflow,
+            i32::checked_mul,
+            i32::wrapping_mul,
+            i32::saturating_mul,
+        ),
         (Variant::Float(a), Variant::Float(b)) => {
             let wrapped_a = TwoFloat::from(a);
             let wrapped_b = TwoFloat::from(b);
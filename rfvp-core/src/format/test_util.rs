@@ -2,6 +2,9 @@ use std::{fmt::Debug, io::Cursor};
 
 use binrw::{io::NoSeek, BinRead, BinWrite};
 
+// the synthetic scripts are shared with the benchmarks
+pub use rfvp_test_support::build_hcb;
+
 // NOTE: eh, okay, we assume little endian here
 // It's not like we support any other endianness anyway..
 // maybe we should explicitly mark everything with a `ReadEndian` and use `::read` instead?
@@ -59,24 +59,15 @@ impl VfsFile {
 
         let mut results = HashMap::new();
         let mut start = 0;
-        for (i, &b) in buffer.iter().enumerate() {
-            if b == 0 {
-                match nls {
-                    Nls::ShiftJIS => {
-                        let (s, _, _) = encoding_rs::SHIFT_JIS.decode(&buffer[start..i]);
-                        results.insert(start as u64, s.to_string());
-                    }
-                    Nls::GBK => {
-                        let (s, _, _) = encoding_rs::GBK.decode(&buffer[start..i]);
-                        results.insert(start as u64, s.to_string());
-                    }
-                    Nls::UTF8 => {
-                        let s = String::from_utf8_lossy(&buffer[start..i]);
-                        results.insert(start as u64, s.to_string());
-                    }
-                };
-                start = i + 1;
+        while start < buffer.len() {
+            let content = nls.cstr_content(&buffer[start..]);
+            let end = start + content.len();
+            // a name without its terminator is cut, left out
+            if end == buffer.len() {
+                break;
             }
+            results.insert(start as u64, nls.decode(content));
+            start = end + nls.terminator().len();
         }
 
         Ok(results)
@@ -150,6 +141,15 @@ impl VfsFile {
         Ok(())
     }
 
+    /// the size of a file, `None` if it isn't in the archive nor extracted next to it
+    pub fn file_size(&self, name: &str) -> Option<u64> {
+        let path = self.dir_path.join(&self.folder_name).join(name);
+        match std::fs::metadata(path) {
+            Ok(metadata) if metadata.is_file() => Some(metadata.len()),
+            _ => self.entries.get(name).map(|entry| entry.size),
+        }
+    }
+
     /// we assume that modern systems have enough memory to load the whole file into memory
     pub fn read_file(&self, name: &str) -> Result<Vec<u8>> {
         let path = self.dir_path.join(&self.folder_name).join(name);
@@ -241,6 +241,12 @@ impl Vfs {
         Ok(content)
     }
 
+    /// the size of a file in one of the archives, `path` is `archive/name`
+    pub fn archived_file_size(&self, path: &str) -> Option<u64> {
+        let (folder_name, name) = path.split_once('/')?;
+        self.files.get(folder_name)?.file_size(name)
+    }
+
     pub fn all_archives(&self) -> Vec<String> {
         self.files.keys().cloned().collect()
     }
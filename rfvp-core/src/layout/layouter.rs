@@ -10,7 +10,7 @@ use crate::{
     vm::command::types::MessageTextLayout,
 };
 
-use ab_glyph::{FontRef, Font, Glyph, point};
+use ab_glyph::{FontRef, Font, Glyph, ScaleFont, point};
 
 #[derive(Debug, Clone, Copy)]
 pub struct LayoutedChar {
@@ -20,6 +20,54 @@ pub struct LayoutedChar {
     pub size: GlyphSize,
     pub fade: f32,
     pub codepoint: char,
+    /// Set for chars inside an emphasis span (@( ... @)), which get a dot above them
+    pub emphasis: Option<EmphasisMark>,
+}
+
+impl LayoutedChar {
+    /// Center and radius of the emphasis dot, in the same space as `position`
+    pub fn emphasis_dot(&self) -> Option<(Vec2, f32)> {
+        self.emphasis.map(|mark| (self.position + mark.offset, mark.radius))
+    }
+}
+
+/// Emphasis dot diameter, relative to the line height of the marked char
+const EMPHASIS_DOT_SCALE: f32 = 0.2;
+/// Space between the top of the glyph and the emphasis dot
+const EMPHASIS_DOT_GAP: f32 = 2.0;
+
+/// An emphasis dot (傍点), drawn centered above the char
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EmphasisMark {
+    /// Offset of the dot center from the char position (on the baseline)
+    pub offset: Vec2,
+    pub radius: f32,
+}
+
+impl EmphasisMark {
+    fn new(size: &GlyphSize) -> Self {
+        Self {
+            offset: Vec2::ZERO, // set when the line is finalized
+            radius: size.line_height * EMPHASIS_DOT_SCALE / 2.0,
+        }
+    }
+
+    /// Vertical space the dot takes above the glyph
+    fn height(&self) -> f32 {
+        self.radius * 2.0 + EMPHASIS_DOT_GAP
+    }
+}
+
+/// Centers the emphasis dots of a line above their chars
+fn place_emphasis_marks(chars: &mut [LayoutedChar], line_ascent: f32) {
+    for c in chars {
+        if let Some(mark) = &mut c.emphasis {
+            mark.offset = vec2(
+                c.size.advance_width / 2.0,
+                -line_ascent - EMPHASIS_DOT_GAP - mark.radius,
+            );
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -47,6 +95,33 @@ pub struct LayouterState {
     pub fade: f32,
     /// Whether text should be displayed instantly, regardless of `text_draw_speed` and `fade`
     pub instant: bool,
+    /// Extra dwell time after a punctuation mark, before the next char is revealed
+    pub punctuation_pause: Ticks,
+}
+
+/// Chars which make the text reveal pause for `punctuation_pause`
+const PAUSE_PUNCTUATION: &[char] = &['、', '。', '，', '．', '!', '?', '！', '？'];
+
+/// Default of `punctuation_pause`, about two full-width chars at the default speed
+pub const DEFAULT_PUNCTUATION_PAUSE_MS: f32 = 150.0;
+
+impl LayouterState {
+    pub fn set_punctuation_pause(&mut self, ms: f32) {
+        self.punctuation_pause = Ticks::from_millis(ms);
+    }
+
+    /// Time between revealing this char and the next one
+    fn char_dwell(&self, c: char, advance_width: f32) -> Ticks {
+        if self.instant {
+            return Ticks::ZERO;
+        }
+
+        let mut dwell = Ticks::from_f32(self.text_draw_speed * advance_width);
+        if PAUSE_PUNCTUATION.contains(&c) {
+            dwell += self.punctuation_pause;
+        }
+        dwell
+    }
 }
 
 impl Default for LayouterState {
@@ -59,10 +134,22 @@ impl Default for LayouterState {
             text_draw_speed: 0.1,
             fade: 0.01,
             instant: false,
+            punctuation_pause: Ticks::from_millis(DEFAULT_PUNCTUATION_PAUSE_MS),
         }
     }
 }
 
+/// The range of the player's text scale setting, see [`LayoutParams::text_scale`]
+pub const MIN_TEXT_SCALE: f32 = 0.8;
+pub const MAX_TEXT_SCALE: f32 = 1.5;
+
+pub fn clamp_text_scale(scale: f32) -> f32 {
+    if scale.is_nan() {
+        return 1.0;
+    }
+    scale.clamp(MIN_TEXT_SCALE, MAX_TEXT_SCALE)
+}
+
 #[derive(Debug, Copy, Clone)]
 pub struct GlyphSize {
     pub scale: f32,
@@ -109,27 +196,45 @@ pub struct LayoutParams<'a> {
     pub default_state: LayouterState,
     pub has_character_name: bool,
     pub mode: LayoutingMode,
+    /// The player's text size multiplier, on top of the script font size.
+    ///
+    /// Glyphs, furigana and emphasis dots grow with it, the layout width doesn't: a
+    /// bigger text wraps into more lines instead of overflowing the box.
+    pub text_scale: f32,
 }
 
 impl<'a> LayoutParams<'a> {
+    fn text_scale(&self) -> f32 {
+        clamp_text_scale(self.text_scale)
+    }
+
+    fn line_height(&self, font_size: f32) -> f32 {
+        self.base_font_height * font_size * self.text_scale()
+    }
+
+    fn furigana_height(&self) -> f32 {
+        self.furigana_font_height * self.text_scale()
+    }
+
     fn glyph_size(&self, font_size: f32, codepoint: char) -> GlyphSize {
-        let line_height = self.base_font_height * font_size;
+        let line_height = self.line_height(font_size);
         let scale = line_height / self.base_font_height;
         let horizontal_scale = scale * self.font_horizontal_base_scale;
 
-        let glyph = self.font.glyph_id(codepoint).with_scale(scale);
-        let outlined_glyph = self.font.outline_glyph(glyph);
+        // the metrics of the glyph at the base size, scaled like it is drawn
+        let id = self.font.glyph_id(codepoint);
+        let advance_width =
+            self.font.as_scaled(self.base_font_height).h_advance(id) * horizontal_scale;
         let mut height = 0.0_f32;
         let mut width = 0.0_f32;
-        let mut advance_width = 0.0_f32;
-        if let Some(outlined_glyph) = outlined_glyph {
+        if let Some(outlined_glyph) = self
+            .font
+            .outline_glyph(id.with_scale(self.base_font_height))
+        {
             let rect = outlined_glyph.px_bounds();
-            height = rect.height();
-            width = rect.width();
+            height = rect.height() * scale;
+            width = rect.width() * horizontal_scale;
         }
-        // let height = glyph.actual_height as f32 * scale;
-        // let width = glyph.actual_width as f32 * horizontal_scale;
-        // let advance_width = glyph.advance_width as f32 * horizontal_scale;
 
         GlyphSize {
             scale,
@@ -151,6 +256,10 @@ struct Layouter<'a> {
     pending_chars: Vec<LayoutedChar>,
     position: Vec2,
     time: Ticks,
+    /// How many emphasis spans are open, they can nest
+    emphasis_depth: u32,
+    /// The top and bottom of each line in `chars`
+    line_bounds: Vec<(f32, f32)>,
 }
 
 impl<'a> Layouter<'a> {
@@ -159,10 +268,12 @@ impl<'a> Layouter<'a> {
         let _codepoint = c as u16;
 
         let size = self.params.glyph_size(self.state.font_size, c);
+        // the draw speed is in unscaled pixels, a bigger text isn't revealed slower
+        let text_scale = self.params.text_scale();
         let fade_time = if self.state.instant {
             0.0_f32
         } else {
-            self.state.text_draw_speed * size.width
+            self.state.text_draw_speed * size.width / text_scale
         };
 
         // TODO: handle special cases for brackets
@@ -175,15 +286,13 @@ impl<'a> Layouter<'a> {
             size,
             fade: fade_time,
             codepoint: c,
+            emphasis: (self.emphasis_depth > 0 && !c.is_whitespace())
+                .then(|| EmphasisMark::new(&size)),
         });
 
         self.position.x += size.advance_width;
 
-        if !self.state.instant {
-            self.time += Ticks::from_f32(self.state.text_draw_speed * size.advance_width);
-        }
-
-        // TODO: handle full stops (they add more delay)
+        self.time += self.state.char_dwell(c, size.advance_width / text_scale);
 
         // TODO: where are overflows handled? On the linefeed?
     }
@@ -198,9 +307,16 @@ impl<'a> Layouter<'a> {
             .map(|c| FloatOrd(c.size.line_height))
             .max()
             .map(|ord| ord.0)
-            .unwrap_or(self.params.base_font_height * self.state.font_size);
+            .unwrap_or(self.params.line_height(self.state.font_size));
 
-        let furigana_height = self.params.furigana_font_height; // TODO: there is an "always leave space for furigana" flag
+        let furigana_height = self.params.furigana_height(); // TODO: there is an "always leave space for furigana" flag
+
+        // emphasis dots go into the furigana space, the line only grows if they don't fit
+        let emphasis_height = chars
+            .iter()
+            .filter_map(|c| c.emphasis.map(|mark| FloatOrd(mark.height())))
+            .max()
+            .map_or(0.0, |ord| (ord.0 - furigana_height).max(0.0));
 
         // Find the total width of all chars in the line, or 0 if there are none
         let width = chars
@@ -250,47 +366,50 @@ impl<'a> Layouter<'a> {
         };
 
         // Append line to chars
-        self.chars.push(
-            chars
-                .iter()
-                .cloned()
-                .map(|mut c| {
-                    // align the text according to the layout params
-                    c.position.x += x_offset;
-
-                    // move the text to the beginning of the real line
-                    // x might be larger than we want if an overflow happened
-                    c.position.x -= x_pos;
-
-                    // move the glyph on its line y coordinate (previously it was zero)
-                    c.position.y += self.position.y;
-                    // make sure that the glyph is on the baseline (doing it here because font size might change on the line)
-                    c.position.y += line_ascent;
-                    // leave space for furigana
-                    // TODO: we, obviously, should not do this when there is no furigana
-                    c.position.y += furigana_height;
-
-                    // if we are overflowing - make it fit by squishing the text
-                    c.position.x *= fit_scale;
-                    c.size.scale_horizontal(fit_scale);
-
-                    // if needed - make the text fit by stretching it
-                    if should_stretch {
-                        // I don't get this formula...
-                        // also it seems to do something strange
-                        // TODO: figure this stuff out
-                        // c.position.x = (self.params.layout_width - c.size.width)
-                        //     * (self.position.x
-                        //         / (self.position.x + (width - (self.position.x + c.size.width))));
-                    }
-                    c
-                })
-                .collect(),
-        );
+        let mut line: Vec<LayoutedChar> = chars
+            .iter()
+            .cloned()
+            .map(|mut c| {
+                // align the text according to the layout params
+                c.position.x += x_offset;
+
+                // move the text to the beginning of the real line
+                // x might be larger than we want if an overflow happened
+                c.position.x -= x_pos;
+
+                // move the glyph on its line y coordinate (previously it was zero)
+                c.position.y += self.position.y;
+                // make sure that the glyph is on the baseline (doing it here because font size might change on the line)
+                c.position.y += line_ascent;
+                // leave space for furigana
+                // TODO: we, obviously, should not do this when there is no furigana
+                c.position.y += furigana_height;
+                c.position.y += emphasis_height;
+
+                // if we are overflowing - make it fit by squishing the text
+                c.position.x *= fit_scale;
+                c.size.scale_horizontal(fit_scale);
+
+                // if needed - make the text fit by stretching it
+                if should_stretch {
+                    // I don't get this formula...
+                    // also it seems to do something strange
+                    // TODO: figure this stuff out
+                    // c.position.x = (self.params.layout_width - c.size.width)
+                    //     * (self.position.x
+                    //         / (self.position.x + (width - (self.position.x + c.size.width))));
+                }
+                c
+            })
+            .collect();
+        place_emphasis_marks(&mut line, line_ascent);
+        self.chars.push(line);
 
         self.position.x = 0.0;
 
-        self.position.y += max_line_height + furigana_height + 4.0 /* TODO: this is one of the many obscure line height-type parameters */;
+        let top = self.position.y;
+        self.position.y += max_line_height + furigana_height + emphasis_height + 4.0 /* TODO: this is one of the many obscure line height-type parameters */;
+        self.line_bounds.push((top, self.position.y));
     }
 
     fn on_newline(&mut self, wrap: bool) {
@@ -300,19 +419,10 @@ impl<'a> Layouter<'a> {
         let mut x_pos = 0.0;
 
         if wrap {
-            // split into lines on overflows
-            // TODO: implement word wrapping?
-            for (i, c) in chars.iter().enumerate() {
-                // if the start of the character is outside of the layout width
-                if c.position.x - x_pos > self.params.layout_width
-                    // or if the end of the character is outside of the layout width * 1.05
-                    || c.position.x + c.size.width - x_pos > self.params.layout_width * 1.05
-                /* allow a bit of overflow, the chars will be rescaled */
-                {
-                    self.finalize_line(&chars[start..i], false, x_pos);
-                    x_pos = c.position.x;
-                    start = i;
-                }
+            for i in wrap_points(&chars, self.params.layout_width) {
+                self.finalize_line(&chars[start..i], false, x_pos);
+                x_pos = chars[i].position.x;
+                start = i;
             }
         }
 
@@ -321,11 +431,31 @@ impl<'a> Layouter<'a> {
         self.pending_chars.clear();
     }
 
-    fn finalize(mut self) -> Vec<Vec<LayoutedChar>> {
+    /// the chars and the top and bottom of each line
+    fn finalize(mut self) -> (Vec<Vec<LayoutedChar>>, Vec<(f32, f32)>) {
         // TODO: close furigana
         self.on_newline(true);
-        self.chars
+        (self.chars, self.line_bounds)
+    }
+}
+
+/// The indices of the chars starting a new line when `chars` are wrapped to `layout_width`
+fn wrap_points(chars: &[LayoutedChar], layout_width: f32) -> Vec<usize> {
+    // TODO: implement word wrapping?
+    let mut points = Vec::new();
+    let mut x_pos = 0.0;
+    for (i, c) in chars.iter().enumerate() {
+        // if the start of the character is outside of the layout width
+        if c.position.x - x_pos > layout_width
+            // or if the end of the character is outside of the layout width * 1.05
+            || c.position.x + c.size.width - x_pos > layout_width * 1.05
+        /* allow a bit of overflow, the chars will be rescaled */
+        {
+            points.push(i);
+            x_pos = c.position.x;
+        }
     }
+    points
 }
 
 pub enum BlockExitCondition {
@@ -360,6 +490,33 @@ impl Block {
     pub fn completed(&self, time: Ticks) -> bool {
         time >= self.end_time
     }
+
+    /// What an "Advance" input does to the block at `time`.
+    ///
+    /// With `click_completes_reveal`, a click during the reveal only completes it and a
+    /// second one moves on. Without it, a single click moves past a click-wait at once.
+    /// Signal waits are never clicked past, the click only completes their reveal.
+    pub fn advance_action(&self, time: Ticks, click_completes_reveal: bool) -> AdvanceAction {
+        let click_wait = matches!(self.exit_condition, BlockExitCondition::ClickWait);
+        if click_wait && (self.completed(time) || !click_completes_reveal) {
+            AdvanceAction::NextBlock
+        } else if !self.completed(time) {
+            AdvanceAction::CompleteReveal
+        } else {
+            AdvanceAction::None
+        }
+    }
+}
+
+/// The result of [`Block::advance_action`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AdvanceAction {
+    /// show the rest of the block at once
+    CompleteReveal,
+    /// leave the block, to the next one or the end of the message
+    NextBlock,
+    /// the block waits for a signal
+    None,
 }
 
 struct BlockBuilder {
@@ -445,13 +602,34 @@ impl ActionsBuilder {
     }
 }
 
+/// A line of [`LayoutedMessage::chars`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LayoutedLine {
+    /// The index of its first char
+    pub start: usize,
+    pub top: f32,
+    pub bottom: f32,
+}
+
 pub struct LayoutedMessage {
     pub character_name_chars: Option<Vec<LayoutedChar>>,
     pub chars: Vec<LayoutedChar>,
+    /// The lines of `chars`, a bigger text scale wraps into more of them
+    pub lines: Vec<LayoutedLine>,
     pub actions: Vec<Action>,
     pub blocks: Vec<Block>,
 }
 
+impl LayoutedMessage {
+    /// The height of the lines, without the character name
+    pub fn height(&self) -> f32 {
+        match (self.lines.first(), self.lines.last()) {
+            (Some(first), Some(last)) => last.bottom - first.top,
+            _ => 0.0,
+        }
+    }
+}
+
 pub fn layout_text(params: LayoutParams, text: &str) -> LayoutedMessage {
     let mut layouter = Layouter {
         parser: LayouterParser::new(text).peekable(),
@@ -461,6 +639,8 @@ pub fn layout_text(params: LayoutParams, text: &str) -> LayoutedMessage {
         pending_chars: Vec::new(),
         position: vec2(0.0, 0.0),
         time: Ticks::ZERO,
+        emphasis_depth: 0,
+        line_bounds: Vec::new(),
     };
 
     let mut block_builder = BlockBuilder::new();
@@ -540,6 +720,12 @@ pub fn layout_text(params: LayoutParams, text: &str) -> LayoutedMessage {
                         layouter.state.font_size = size;
                     }
                 }
+                ParsedCommand::FontSizeDelta(delta) => {
+                    if !character_name {
+                        layouter.state.font_size =
+                            (layouter.state.font_size + delta).clamp(0.1, 2.0);
+                    }
+                }
                 ParsedCommand::Signal => {
                     actions_builder.action(layouter.time, ActionType::SignalSection)
                 }
@@ -547,6 +733,13 @@ pub fn layout_text(params: LayoutParams, text: &str) -> LayoutedMessage {
                 ParsedCommand::InstantTextEnd => todo!(),
                 ParsedCommand::BoldTextStart => todo!(),
                 ParsedCommand::BoldTextEnd => todo!(),
+                ParsedCommand::EmphasisStart => layouter.emphasis_depth += 1,
+                ParsedCommand::EmphasisEnd => {
+                    if layouter.emphasis_depth == 0 {
+                        warn!("Unbalanced emphasis end in message");
+                    }
+                    layouter.emphasis_depth = layouter.emphasis_depth.saturating_sub(1);
+                }
             }
         }
     }
@@ -554,26 +747,207 @@ pub fn layout_text(params: LayoutParams, text: &str) -> LayoutedMessage {
     let blocks = block_builder.finalize(layouter.time);
     let actions = actions_builder.finalize();
 
-    let chars_by_line = layouter.finalize();
+    let (chars_by_line, line_bounds) = layouter.finalize();
+    let mut lines = chars_by_line.into_iter().zip(line_bounds);
 
-    let (character_name_chars, chars) = match layout_mode {
+    let character_name_chars = match layout_mode {
         // In message/log mode, the first line represents the character name (or is empty if not present).
-        LayoutingMode::MessageText | LayoutingMode::LogText => {
-            let mut iter = chars_by_line.into_iter();
-            // Get the first line; if it is empty, convert it to None
-            let character_name_chars = iter.next().filter(|v| !v.is_empty());
-            let chars = iter.flatten().collect();
-            (character_name_chars, chars)
-        }
+        // Get the first line; if it is empty, convert it to None
+        LayoutingMode::MessageText | LayoutingMode::LogText => lines
+            .next()
+            .map(|(chars, _)| chars)
+            .filter(|v| !v.is_empty()),
         // Otherwise, we just care about the main text
-        LayoutingMode::GenericText => (None, chars_by_line.into_iter().flatten().collect()),
+        LayoutingMode::GenericText => None,
     };
 
+    let mut chars = Vec::new();
+    let lines = lines
+        .map(|(line, (top, bottom))| {
+            let start = chars.len();
+            chars.extend(line);
+            LayoutedLine { start, top, bottom }
+        })
+        .collect();
+
     LayoutedMessage {
         character_name_chars,
         chars,
+        lines,
         actions,
         blocks,
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reveal_time(state: &LayouterState, text: &str) -> Ticks {
+        let mut time = Ticks::ZERO;
+        for c in text.chars() {
+            time += state.char_dwell(c, 20.0);
+        }
+        time
+    }
+
+    #[test]
+    fn test_punctuation_pause() {
+        let mut state = LayouterState::default();
+        assert_eq!(
+            reveal_time(&state, "あ、い。"),
+            reveal_time(&state, "ああああ")
+                + Ticks::from_millis(2.0 * DEFAULT_PUNCTUATION_PAUSE_MS)
+        );
+
+        state.set_punctuation_pause(0.0);
+        assert_eq!(
+            reveal_time(&state, "あ、い。"),
+            reveal_time(&state, "ああああ")
+        );
+
+        state.set_punctuation_pause(100.0);
+        let punctuated = reveal_time(&state, "あ、い。");
+        let plain = reveal_time(&state, "ああああ");
+        assert!(punctuated > plain);
+        assert_eq!(punctuated, plain + Ticks::from_millis(200.0));
+
+        state.instant = true;
+        assert_eq!(reveal_time(&state, "あ、い。"), Ticks::ZERO);
+    }
+
+    fn char_at(codepoint: char, x: f32, emphasis: bool) -> LayoutedChar {
+        let size = GlyphSize {
+            scale: 1.0,
+            horizontal_scale: 1.0,
+            advance_width: 50.0,
+            line_height: 50.0,
+            width: 46.0,
+            height: 46.0,
+        };
+        LayoutedChar {
+            time: Ticks::ZERO,
+            position: vec2(x, 100.0),
+            color: Vec3::ONE,
+            size,
+            fade: 0.0,
+            codepoint,
+            emphasis: emphasis.then(|| EmphasisMark::new(&size)),
+        }
+    }
+
+    #[test]
+    fn test_emphasis_dot_placement() {
+        let mut line = [
+            char_at('強', 0.0, true),
+            char_at('調', 50.0, true),
+            char_at('字', 100.0, false),
+        ];
+        place_emphasis_marks(&mut line, 40.0);
+
+        let dots: Vec<_> = line.iter().filter_map(|c| c.emphasis_dot()).collect();
+        assert_eq!(dots.len(), 2);
+        for ((center, radius), x) in dots.into_iter().zip([25.0, 75.0]) {
+            assert_eq!(radius, 5.0);
+            // centered over the char cell, clear of the glyph top at 100 - 40
+            assert_eq!(center, vec2(x, 100.0 - 40.0 - EMPHASIS_DOT_GAP - 5.0));
+        }
+        assert!(line[2].emphasis_dot().is_none());
+    }
+
+    /// a sentence repeated `count` times after a character name, laid out with a real font
+    fn layout_sentence(count: usize, text_scale: f32) -> LayoutedMessage {
+        let font = include_bytes!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/testcase/DejaVuSans.ttf"
+        ));
+        let params = LayoutParams {
+            font: FontRef::try_from_slice(font).unwrap(),
+            layout_width: 1500.0,
+            character_name_layout_width: 384.0,
+            base_font_height: 50.0,
+            furigana_font_height: 20.0,
+            font_horizontal_base_scale: 0.9697,
+            text_layout: MessageTextLayout::Left,
+            default_state: LayouterState::default(),
+            has_character_name: true,
+            mode: LayoutingMode::MessageText,
+            text_scale,
+        };
+        let text = "The quick brown fox jumps over the lazy dog. ".repeat(count);
+        layout_text(params, &format!("Name@r{}", text))
+    }
+
+    #[test]
+    fn test_advance_completes_reveal_first() {
+        let block = Block {
+            exit_condition: BlockExitCondition::ClickWait,
+            start_time: Ticks::ZERO,
+            end_time: Ticks::from_u32(100),
+        };
+        let printing = Ticks::from_u32(40);
+
+        // the first click completes the reveal, the block is then fully revealed
+        assert_eq!(
+            block.advance_action(printing, true),
+            AdvanceAction::CompleteReveal
+        );
+        assert!(block.completed(block.end_time));
+        assert_eq!(
+            block.advance_action(block.end_time, true),
+            AdvanceAction::NextBlock
+        );
+
+        // one click is enough when the reveal isn't completed on its own
+        assert_eq!(
+            block.advance_action(printing, false),
+            AdvanceAction::NextBlock
+        );
+
+        let signal = Block {
+            exit_condition: BlockExitCondition::Signal(0),
+            ..block
+        };
+        assert_eq!(
+            signal.advance_action(printing, false),
+            AdvanceAction::CompleteReveal
+        );
+        assert_eq!(
+            signal.advance_action(signal.end_time, true),
+            AdvanceAction::None
+        );
+    }
+
+    #[test]
+    fn test_text_scale_wrap() {
+        let normal = layout_sentence(6, 1.0);
+        let scaled = layout_sentence(6, 1.3);
+        // the same text wraps into more lines at the bigger scale, all still fitting the width
+        assert_eq!(normal.lines.len(), 4);
+        assert_eq!(scaled.lines.len(), 5);
+        for message in [&normal, &scaled] {
+            // the name goes to its own box, not to the first line
+            assert_eq!(message.lines[0].start, 0);
+            assert_eq!(message.chars.len(), 6 * 45);
+            for (i, line) in message.lines.iter().enumerate() {
+                let end = message
+                    .lines
+                    .get(i + 1)
+                    .map_or(message.chars.len(), |next| next.start);
+                let last = &message.chars[end - 1];
+                assert!(last.position.x + last.size.advance_width <= 1500.0 + 0.01);
+            }
+        }
+
+        // the glyphs and the line pitch grow with the scale, so does the message
+        let pitch = |message: &LayoutedMessage| message.lines[1].top - message.lines[0].top;
+        assert!(pitch(&scaled) > pitch(&normal) * 1.2);
+        assert!(scaled.height() > normal.height() * 1.2);
+        let glyph = |message: &LayoutedMessage| message.chars[message.lines[0].start].size;
+        assert!((glyph(&scaled).height / glyph(&normal).height - 1.3).abs() < 0.01);
+
+        assert_eq!(clamp_text_scale(3.0), MAX_TEXT_SCALE);
+        assert_eq!(clamp_text_scale(0.1), MIN_TEXT_SCALE);
+        assert_eq!(clamp_text_scale(f32::NAN), 1.0);
+    }
+}
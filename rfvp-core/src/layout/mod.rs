@@ -2,7 +2,8 @@ mod layouter;
 mod parser;
 
 pub use layouter::{
-    layout_text, Action, ActionType, Block, BlockExitCondition, LayoutParams, LayoutedChar,
-    LayoutedMessage, LayouterState, LayoutingMode,
+    clamp_text_scale, layout_text, Action, ActionType, AdvanceAction, Block, BlockExitCondition,
+    EmphasisMark, LayoutParams, LayoutedChar, LayoutedLine, LayoutedMessage, LayouterState,
+    LayoutingMode, MAX_TEXT_SCALE, MIN_TEXT_SCALE,
 };
 pub use parser::{LayouterParser, ParsedCommand};
@@ -1,4 +1,5 @@
 use glam::Vec3;
+use tracing::warn;
 
 use crate::time::Ticks;
 
@@ -40,6 +41,8 @@ pub enum ParsedCommand {
     Sync,
     /// @z
     FontSize(f32),
+    /// @z with a signed argument (`@z+20.`), relative to the current size
+    FontSizeDelta(f32),
     /// @|
     Signal,
     /// @[
@@ -50,6 +53,10 @@ pub enum ParsedCommand {
     BoldTextStart,
     /// @}
     BoldTextEnd,
+    /// @(
+    EmphasisStart,
+    /// @)
+    EmphasisEnd,
 }
 
 pub struct LayouterParser<'a> {
@@ -62,93 +69,132 @@ impl<'a> LayouterParser<'a> {
     }
 
     fn read_argument(&mut self) -> &'a str {
-        let end = self
-            .message
-            .find('.')
-            .expect("Could not find the end of the argument");
-        let argument = &self.message[..end];
-        self.message = &self.message[end + 1..];
-        argument
+        match self.message.find('.') {
+            Some(end) => {
+                let argument = &self.message[..end];
+                self.message = &self.message[end + 1..];
+                argument
+            }
+            None => {
+                // the argument runs to the end of the message
+                warn!("Unterminated layouter command argument: {:?}", self.message);
+                std::mem::take(&mut self.message)
+            }
+        }
     }
 
-    fn read_float_argument(&mut self, min: u32, max: u32, scale: f32) -> f32 {
-        let argument = self.read_argument();
-        let value = argument.parse::<u32>().expect("Could not parse argument");
+    fn read_float_argument(&mut self, min: u32, max: u32, scale: f32) -> Option<f32> {
+        let value = self.read_argument().parse::<u32>().ok()?;
         let value = value.clamp(min.min(max), max.max(min));
         // if min max are backwards - reverse the value
         let value = if min > max { max - value } else { value };
-        value as f32 / scale
+        Some(value as f32 / scale)
     }
 
-    fn read_color_argument(&mut self) -> Option<Vec3> {
+    fn read_font_size_argument(&mut self) -> Option<ParsedCommand> {
         let argument = self.read_argument();
-        if argument.is_empty() {
-            None
-        } else {
-            let mut chars = argument.chars();
-            let r = chars.next().unwrap().to_digit(10).unwrap() as f32 / 9.0;
-            let g = chars.next().unwrap().to_digit(10).unwrap() as f32 / 9.0;
-            let b = chars.next().unwrap().to_digit(10).unwrap() as f32 / 9.0;
-            assert!(chars.next().is_none());
-            Some(Vec3::new(r, g, b))
-        }
+        Some(match argument.as_bytes().first() {
+            Some(b'+' | b'-') => {
+                let delta = argument.parse::<i32>().ok()?;
+                ParsedCommand::FontSizeDelta(delta.clamp(-190, 190) as f32 / 100.0)
+            }
+            _ => {
+                let value = argument.parse::<u32>().ok()?;
+                ParsedCommand::FontSize(value.clamp(10, 200) as f32 / 100.0)
+            }
+        })
     }
-}
 
-impl Iterator for LayouterParser<'_> {
-    type Item = ParsedCommand;
-
-    fn next(&mut self) -> Option<Self::Item> {
-        // TODO: make this parsing fallible
-
-        if self.message.is_empty() {
-            return None;
+    /// `Some(None)` resets the color
+    fn read_color_argument(&mut self) -> Option<Option<Vec3>> {
+        let argument = self.read_argument();
+        if argument.is_empty() {
+            return Some(None);
         }
 
-        let mut chars = self.message.chars();
-        let first_char = chars.next().unwrap();
-
-        if first_char != '@' {
-            self.message = chars.as_str();
-            return Some(ParsedCommand::Char(first_char));
+        let digits = argument
+            .chars()
+            .map(|c| c.to_digit(10).map(|d| d as f32 / 9.0))
+            .collect::<Option<Vec<_>>>()?;
+        match digits[..] {
+            [r, g, b] => Some(Some(Vec3::new(r, g, b))),
+            _ => None,
         }
+    }
 
-        let second_char = chars.next().unwrap();
-        self.message = chars.as_str();
-
-        Some(match second_char {
+    fn read_command(&mut self, command: char) -> Option<ParsedCommand> {
+        Some(match command {
             '+' => ParsedCommand::EnableLipsync,
             '-' => ParsedCommand::DisableLipsync,
             'b' => ParsedCommand::Furigana(self.read_argument().to_owned()),
             '<' => ParsedCommand::FuriganaStart,
             '>' => ParsedCommand::FuriganaEnd,
-            'a' => ParsedCommand::SetFade(self.read_float_argument(0, u32::MAX, 1000.0)),
-            'c' => ParsedCommand::SetColor(self.read_color_argument()),
+            'a' => ParsedCommand::SetFade(self.read_float_argument(0, u32::MAX, 1000.0)?),
+            'c' => ParsedCommand::SetColor(self.read_color_argument()?),
             'e' => ParsedCommand::NoFinalClickWait,
             'k' => ParsedCommand::ClickWait,
-            'o' => ParsedCommand::VoiceVolume(self.read_float_argument(0, 100, 100.0)),
+            'o' => ParsedCommand::VoiceVolume(self.read_float_argument(0, 100, 100.0)?),
             'r' => ParsedCommand::Newline,
-            's' => ParsedCommand::TextSpeed(self.read_float_argument(100, 0, 40000.0)),
+            's' => ParsedCommand::TextSpeed(self.read_float_argument(100, 0, 40000.0)?),
             't' => ParsedCommand::SimultaneousStart,
             'v' => ParsedCommand::Voice(self.read_argument().to_owned()),
             'w' => ParsedCommand::Wait(Ticks::from_f32(self.read_float_argument(
                 0,
                 u32::MAX,
                 1000.0,
-            ))),
+            )?)),
             'y' => ParsedCommand::Sync,
-            'z' => ParsedCommand::FontSize(self.read_float_argument(10, 200, 100.0)),
+            'z' => self.read_font_size_argument()?,
             '|' => ParsedCommand::Signal,
             '[' => ParsedCommand::InstantTextStart,
             ']' => ParsedCommand::InstantTextEnd,
             '{' => ParsedCommand::BoldTextStart,
             '}' => ParsedCommand::BoldTextEnd,
-            'U' => todo!("@U layouter command parsing"),
-            _ => panic!("Unknown layouter command: {}", second_char),
+            '(' => ParsedCommand::EmphasisStart,
+            ')' => ParsedCommand::EmphasisEnd,
+            // TODO: @U
+            _ => return None,
         })
     }
 }
 
+impl Iterator for LayouterParser<'_> {
+    type Item = ParsedCommand;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.message.is_empty() {
+            return None;
+        }
+
+        let mut chars = self.message.chars();
+        let first_char = chars.next().unwrap();
+
+        if first_char != '@' {
+            self.message = chars.as_str();
+            return Some(ParsedCommand::Char(first_char));
+        }
+
+        let Some(second_char) = chars.next() else {
+            // a lone @ at the end of the message
+            self.message = "";
+            return Some(ParsedCommand::Char(first_char));
+        };
+        let after_at = &self.message[first_char.len_utf8()..];
+        self.message = chars.as_str();
+
+        match self.read_command(second_char) {
+            Some(command) => Some(command),
+            None => {
+                // an unknown or malformed command is shown as text
+                let end = after_at.len() - self.message.len();
+                warn!("Invalid layouter command: @{}", &after_at[..end]);
+                self.message = after_at;
+                Some(ParsedCommand::Char(first_char))
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use glam::vec3;
@@ -237,6 +283,70 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_emphasis() {
+        let message = "@(強@(調@)@z+20.文@)字";
+        let commands = parse(message);
+
+        // nesting is resolved by the layouter, the parser just reports the codes
+        assert_eq!(
+            commands,
+            vec![
+                ParsedCommand::EmphasisStart,
+                ParsedCommand::Char('強'),
+                ParsedCommand::EmphasisStart,
+                ParsedCommand::Char('調'),
+                ParsedCommand::EmphasisEnd,
+                ParsedCommand::FontSizeDelta(0.2),
+                ParsedCommand::Char('文'),
+                ParsedCommand::EmphasisEnd,
+                ParsedCommand::Char('字'),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unterminated() {
+        assert_eq!(
+            parse("@(AB@v00/voice"),
+            vec![
+                ParsedCommand::EmphasisStart,
+                ParsedCommand::Char('A'),
+                ParsedCommand::Char('B'),
+                ParsedCommand::Voice("00/voice".to_owned()),
+            ]
+        );
+        assert_eq!(
+            parse("A@z-30"),
+            vec![ParsedCommand::Char('A'), ParsedCommand::FontSizeDelta(-0.3)]
+        );
+        assert_eq!(
+            parse("100%@"),
+            vec![
+                ParsedCommand::Char('1'),
+                ParsedCommand::Char('0'),
+                ParsedCommand::Char('0'),
+                ParsedCommand::Char('%'),
+                ParsedCommand::Char('@'),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_invalid() {
+        let chars = |text: &str| text.chars().map(ParsedCommand::Char).collect::<Vec<_>>();
+        assert_eq!(parse("@q1"), chars("@q1"));
+        assert_eq!(parse("@Ux"), chars("@Ux"));
+        assert_eq!(parse("@zbig.A"), chars("@zbig.A"));
+        assert_eq!(parse("@c9x0."), chars("@c9x0."));
+        assert_eq!(parse("@c90.@z+."), chars("@c90.@z+."));
+
+        // the commands after the invalid one still parse
+        let mut commands = chars("@w?.");
+        commands.push(ParsedCommand::Newline);
+        assert_eq!(parse("@w?.@r"), commands);
+    }
+
     #[test]
     fn test_real1() {
         let message = "@r@v00/awase6042_o.@|@y｢｢@c900.@[謹啓､謹ﾝで申ｼ上げﾙ｡@k@v00/awase6043_o.どﾁﾗﾓ破ﾗﾚﾃｲﾅｲﾓﾉﾄ知ﾘ給ｴ@]@c.｣｣";
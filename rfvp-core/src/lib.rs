@@ -12,6 +12,13 @@ pub use rfvp_tasks::create_task_pools;
 
 pub mod format;
 pub mod layout;
+pub mod lip_sync;
+pub mod locale;
+pub mod logging;
+pub mod memory;
 pub mod rational;
+mod rng;
+pub mod screen_effect;
+pub mod shape;
 pub mod time;
 pub mod vm;
@@ -0,0 +1,206 @@
+//! Moving the mouths of the bustups with the voices.
+//!
+//! The audio thread measures the loudness of a sound with an [`RmsEnvelope`] and publishes
+//! it, a [`LipSync`] turns it into the mouth frame to draw. The louder the voice, the more
+//! open the mouth. Without a level, like for a line whose voice couldn't be loaded, the
+//! mouth cycles through the frames on a timer while the line is shown.
+
+use crate::time::Ticks;
+
+/// The length of the window the loudness is measured over
+pub const ENVELOPE_WINDOW_MS: f32 = 50.0;
+/// The frame of the fallback cycle changes this often
+const CYCLE_FRAME_MS: f32 = 100.0;
+
+/// The root mean square of the samples over a window, one level per window
+#[derive(Debug, Clone)]
+pub struct RmsEnvelope {
+    window: u32,
+    sum: f32,
+    count: u32,
+}
+
+impl RmsEnvelope {
+    /// measured over [`ENVELOPE_WINDOW_MS`] at `sample_rate`
+    pub fn new(sample_rate: u32) -> Self {
+        let window = (sample_rate as f32 * ENVELOPE_WINDOW_MS / 1000.0).round() as u32;
+        Self {
+            window: window.max(1),
+            sum: 0.0,
+            count: 0,
+        }
+    }
+
+    /// Adds a stereo sample, returns the level when a window is complete
+    pub fn push(&mut self, left: f32, right: f32) -> Option<f32> {
+        self.sum += (left * left + right * right) / 2.0;
+        self.count += 1;
+        if self.count < self.window {
+            return None;
+        }
+
+        let level = (self.sum / self.count as f32).sqrt();
+        self.sum = 0.0;
+        self.count = 0;
+        Some(level)
+    }
+}
+
+/// How the levels map to the mouth frames
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LipSyncConfig {
+    /// the mouth opens above this level
+    pub silence: f32,
+    /// the mouth is wide open above this level
+    pub open: f32,
+    /// a frame is entered this much above its threshold, and left this much below it, so
+    /// a level around a threshold doesn't flicker between two frames
+    pub hysteresis: f32,
+}
+
+impl Default for LipSyncConfig {
+    fn default() -> Self {
+        Self {
+            silence: 0.02,
+            open: 0.2,
+            hysteresis: 0.01,
+        }
+    }
+}
+
+/// The mouth frame of a bustup with `frames` mouths, the first one closed
+#[derive(Debug, Clone)]
+pub struct LipSync {
+    config: LipSyncConfig,
+    frames: usize,
+    current: usize,
+    cycle: Ticks,
+}
+
+impl LipSync {
+    pub fn new(config: LipSyncConfig, frames: usize) -> Self {
+        Self {
+            config,
+            frames,
+            current: 0,
+            cycle: Ticks::ZERO,
+        }
+    }
+
+    pub fn frame(&self) -> usize {
+        self.current
+    }
+
+    /// the level at which `frame` opens, evenly spaced from the silence to wide open
+    fn threshold(&self, frame: usize) -> f32 {
+        let LipSyncConfig { silence, open, .. } = self.config;
+        if self.frames <= 2 {
+            return silence;
+        }
+        silence + (open - silence) * (frame - 1) as f32 / (self.frames - 2) as f32
+    }
+
+    /// Follows a level of the voice, returns the frame to draw
+    pub fn update_level(&mut self, level: f32) -> usize {
+        let hysteresis = self.config.hysteresis;
+        while self.current + 1 < self.frames
+            && level >= self.threshold(self.current + 1) + hysteresis
+        {
+            self.current += 1;
+        }
+        while self.current > 0 && level < self.threshold(self.current) - hysteresis {
+            self.current -= 1;
+        }
+        self.current
+    }
+
+    /// Advances by `delta`. `level` is the level of the voice if it's measured, otherwise
+    /// the frames are cycled while `speaking`. Returns the frame to draw.
+    pub fn update(&mut self, delta: Ticks, level: Option<f32>, speaking: bool) -> usize {
+        if self.frames == 0 {
+            return 0;
+        }
+        if !speaking {
+            self.current = 0;
+            self.cycle = Ticks::ZERO;
+            return 0;
+        }
+        if let Some(level) = level {
+            return self.update_level(level);
+        }
+
+        // back and forth through the frames
+        self.cycle += delta;
+        let step = (self.cycle / Ticks::from_millis(CYCLE_FRAME_MS)) as usize;
+        let period = (self.frames - 1) * 2;
+        self.current = match period {
+            0 => 0,
+            _ => {
+                let step = step % period;
+                step.min(period - step)
+            }
+        };
+        self.current
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_envelope() {
+        // a square wave of 0.5 then silence, at 1000 samples per second: 50 per window
+        let mut envelope = RmsEnvelope::new(1000);
+        let levels: Vec<f32> = (0..200)
+            .map(|i| match i {
+                0..=99 if i % 2 == 0 => 0.5,
+                0..=99 => -0.5,
+                _ => 0.0,
+            })
+            .filter_map(|sample| envelope.push(sample, sample))
+            .collect();
+        assert_eq!(levels, [0.5, 0.5, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn test_levels_to_frames() {
+        let config = LipSyncConfig {
+            silence: 0.1,
+            open: 0.3,
+            hysteresis: 0.02,
+        };
+        // thresholds at 0.1, 0.2 and 0.3
+        let mut lip_sync = LipSync::new(config, 4);
+        let frames: Vec<usize> = [0.0, 0.05, 0.13, 0.25, 0.4, 0.25, 0.15, 0.0]
+            .into_iter()
+            .map(|level| lip_sync.update_level(level))
+            .collect();
+        assert_eq!(frames, [0, 0, 1, 2, 3, 2, 1, 0]);
+
+        // around a threshold, within the hysteresis, the frame stays
+        let mut lip_sync = LipSync::new(config, 4);
+        let frames: Vec<usize> = [0.11, 0.125, 0.115, 0.09, 0.085, 0.079, 0.11]
+            .into_iter()
+            .map(|level| lip_sync.update_level(level))
+            .collect();
+        assert_eq!(frames, [0, 1, 1, 1, 1, 0, 0]);
+
+        // a two frame mouth only opens and closes
+        let mut lip_sync = LipSync::new(config, 2);
+        assert_eq!(lip_sync.update_level(1.0), 1);
+        assert_eq!(lip_sync.update_level(0.0), 0);
+    }
+
+    #[test]
+    fn test_fallback_cycle() {
+        let mut lip_sync = LipSync::new(LipSyncConfig::default(), 3);
+        let frame = Ticks::from_millis(CYCLE_FRAME_MS);
+        let frames: Vec<usize> = (0..6).map(|_| lip_sync.update(frame, None, true)).collect();
+        assert_eq!(frames, [1, 2, 1, 0, 1, 2]);
+        // closed once the voice stops
+        assert_eq!(lip_sync.update(frame, None, false), 0);
+        // a level takes over the cycle
+        assert_eq!(lip_sync.update(frame, Some(1.0), true), 2);
+    }
+}
@@ -0,0 +1,356 @@
+//! The strings the engine shows by itself, like the notifications.
+//!
+//! Each language has a catalog mapping keys to texts, a TOML file whose tables nest
+//! the keys: `now_playing` in `[notification]` is `notification.now_playing`. The
+//! packs of en, ja and zh-CN are built in, and `locale/<code>.toml` in the override
+//! directory replaces some of their strings. A key missing from the selected language
+//! falls back to English, it's logged once.
+//!
+//! Placeholders are numbered, `{0}` is the first argument wherever it is in the text,
+//! as the word order differs between languages. `{{` and `}}` are literal braces.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::{Display, Write},
+    str::FromStr,
+    sync::{Mutex, RwLock},
+};
+
+use anyhow::{anyhow, bail, Context, Result};
+use once_cell::sync::Lazy;
+use tracing::warn;
+
+use crate::format::scenario::Nls;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Language {
+    English,
+    Japanese,
+    SimplifiedChinese,
+}
+
+impl Language {
+    pub const ALL: [Language; 3] = [
+        Language::English,
+        Language::Japanese,
+        Language::SimplifiedChinese,
+    ];
+
+    pub fn code(self) -> &'static str {
+        match self {
+            Language::English => "en",
+            Language::Japanese => "ja",
+            Language::SimplifiedChinese => "zh-CN",
+        }
+    }
+
+    /// the language of a game, guessed from the encoding of its script
+    pub fn from_nls(nls: &Nls) -> Self {
+        match nls {
+            Nls::ShiftJIS => Language::Japanese,
+            Nls::GBK => Language::SimplifiedChinese,
+            Nls::UTF8 | Nls::UTF16LE | Nls::UTF16BE => Language::English,
+        }
+    }
+
+    fn builtin_catalog(self) -> &'static str {
+        match self {
+            Language::English => include_str!("en.toml"),
+            Language::Japanese => include_str!("ja.toml"),
+            Language::SimplifiedChinese => include_str!("zh-CN.toml"),
+        }
+    }
+
+    /// the catalog overriding the built in one, in the override directory
+    pub fn override_path(self) -> String {
+        format!("locale/{}.toml", self.code())
+    }
+}
+
+impl FromStr for Language {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "en" => Ok(Language::English),
+            "ja" => Ok(Language::Japanese),
+            "zh" | "zh-cn" => Ok(Language::SimplifiedChinese),
+            _ => Err(anyhow!("unknown language, expected en, ja or zh-CN")),
+        }
+    }
+}
+
+/// The texts of a language, by key
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Catalog {
+    strings: HashMap<String, String>,
+}
+
+impl Catalog {
+    pub fn parse(content: &str) -> Result<Self> {
+        let table = content
+            .parse::<toml::Table>()
+            .context("Parsing the string catalog")?;
+        let mut strings = HashMap::new();
+        Self::flatten("", &table, &mut strings)?;
+        Ok(Self { strings })
+    }
+
+    fn flatten(
+        prefix: &str,
+        table: &toml::Table,
+        strings: &mut HashMap<String, String>,
+    ) -> Result<()> {
+        for (name, value) in table {
+            let key = if prefix.is_empty() {
+                name.clone()
+            } else {
+                format!("{}.{}", prefix, name)
+            };
+            match value {
+                toml::Value::String(text) => {
+                    strings.insert(key, text.clone());
+                }
+                toml::Value::Table(table) => Self::flatten(&key, table, strings)?,
+                _ => bail!("{} is not a string", key),
+            }
+        }
+        Ok(())
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.strings.get(key).map(String::as_str)
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.strings.keys().map(String::as_str)
+    }
+
+    /// replace the strings which are in `other` too
+    pub fn merge(&mut self, other: Catalog) {
+        self.strings.extend(other.strings);
+    }
+}
+
+/// Looks the keys up in the selected language, then in English
+pub struct Localizer {
+    language: Language,
+    strings: Catalog,
+    english: Catalog,
+    /// the keys already logged as missing
+    missing: Mutex<HashSet<String>>,
+}
+
+impl Localizer {
+    pub fn new(language: Language) -> Self {
+        let builtin = |language: Language| {
+            Catalog::parse(language.builtin_catalog()).expect("Invalid built in string catalog")
+        };
+        Self {
+            language,
+            strings: builtin(language),
+            english: builtin(Language::English),
+            missing: Mutex::new(HashSet::new()),
+        }
+    }
+
+    /// replace some of the strings of the selected language, with a catalog of the override directory
+    pub fn with_override(mut self, content: &str) -> Result<Self> {
+        self.strings.merge(Catalog::parse(content)?);
+        Ok(self)
+    }
+
+    pub fn language(&self) -> Language {
+        self.language
+    }
+
+    /// the text of `key`, the key itself if even English doesn't have it
+    pub fn get<'a>(&'a self, key: &'a str) -> &'a str {
+        if let Some(text) = self.strings.get(key) {
+            return text;
+        }
+
+        if self.missing.lock().unwrap().insert(key.to_string()) {
+            warn!(
+                "The string {:?} is missing from the {} catalog",
+                key,
+                self.language.code()
+            );
+        }
+        self.english.get(key).unwrap_or(key)
+    }
+
+    /// the text of `key`, with the placeholders replaced by `args`
+    pub fn tr(&self, key: &str, args: &[&dyn Display]) -> String {
+        format_placeholders(self.get(key), args)
+    }
+}
+
+/// replace `{0}`, `{1}`... with the arguments, a placeholder without an argument is kept as is
+pub fn format_placeholders(template: &str, args: &[&dyn Display]) -> String {
+    let mut result = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(pos) = rest.find(['{', '}']) {
+        result.push_str(&rest[..pos]);
+        rest = &rest[pos..];
+
+        if rest.starts_with("{{") || rest.starts_with("}}") {
+            result.push_str(&rest[..1]);
+            rest = &rest[2..];
+            continue;
+        }
+
+        let placeholder = rest
+            .strip_prefix('{')
+            .and_then(|inner| inner.split_once('}'))
+            .map(|(index, _)| index)
+            .filter(|index| !index.is_empty() && index.bytes().all(|b| b.is_ascii_digit()));
+        let arg = placeholder
+            .and_then(|index| index.parse::<usize>().ok())
+            .and_then(|index| args.get(index));
+        match (placeholder, arg) {
+            (Some(index), Some(arg)) => {
+                write!(result, "{}", arg).unwrap();
+                rest = &rest[index.len() + 2..];
+            }
+            _ => {
+                if placeholder.is_some() {
+                    warn!("Missing argument for the placeholder in {:?}", template);
+                }
+                result.push_str(&rest[..1]);
+                rest = &rest[1..];
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+static LOCALIZER: Lazy<RwLock<Localizer>> =
+    Lazy::new(|| RwLock::new(Localizer::new(Language::English)));
+
+/// select the language of the process, English until then
+pub fn set_localizer(localizer: Localizer) {
+    *LOCALIZER.write().unwrap() = localizer;
+}
+
+pub fn language() -> Language {
+    LOCALIZER.read().unwrap().language()
+}
+
+/// see [`tr!`](crate::tr)
+pub fn tr(key: &str, args: &[&dyn Display]) -> String {
+    LOCALIZER.read().unwrap().tr(key, args)
+}
+
+/// The text of a key in the language of the process, with the placeholders replaced
+///
+/// ```
+/// # use rfvp_core::tr;
+/// let name = "Main Theme";
+/// assert_eq!(tr!("notification.now_playing", name), "Now Playing: Main Theme");
+/// ```
+///
+/// The key has to be a literal, so the test of the catalogs can find it.
+#[macro_export]
+macro_rules! tr {
+    ($key:literal $(, $arg:expr)* $(,)?) => {
+        $crate::locale::tr($key, &[$(&$arg as &dyn ::std::fmt::Display),*])
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+
+    #[test]
+    fn test_fallback() {
+        let localizer = Localizer::new(Language::Japanese)
+            .with_override("[test]\nonly_here = \"ここだけ\"")
+            .unwrap();
+        assert_eq!(localizer.get("test.only_here"), "ここだけ");
+        assert_eq!(
+            localizer.tr("notification.now_playing", &[&"テーマ"]),
+            "再生中：テーマ"
+        );
+
+        let mut localizer = localizer;
+        localizer.strings = Catalog::default();
+        assert_eq!(
+            localizer.tr("notification.now_playing", &[&"テーマ"]),
+            "Now Playing: テーマ"
+        );
+        assert_eq!(localizer.get("test.nowhere"), "test.nowhere");
+    }
+
+    #[test]
+    fn test_placeholders() {
+        assert_eq!(format_placeholders("{1} <- {0}", &[&"a", &2]), "2 <- a");
+        assert_eq!(format_placeholders("{0}{0}", &[&"x"]), "xx");
+        assert_eq!(format_placeholders("{{0}} {0}", &[&1]), "{0} 1");
+        assert_eq!(format_placeholders("{2} {x} {", &[&1]), "{2} {x} {");
+        assert_eq!(format_placeholders("再生中：{0}", &[&"曲"]), "再生中：曲");
+    }
+
+    /// the keys passed to the `tr` macro in the sources of the workspace
+    fn used_keys(dir: &Path, keys: &mut Vec<String>) {
+        let needle = concat!("tr", "!(");
+        for entry in std::fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            let name = path.file_name().unwrap().to_string_lossy();
+            if path.is_dir() {
+                if !name.starts_with('.') && name != "target" {
+                    used_keys(&path, keys);
+                }
+                continue;
+            }
+            if !name.ends_with(".rs") {
+                continue;
+            }
+
+            let source = std::fs::read_to_string(&path).unwrap();
+            for (pos, _) in source.match_indices(needle) {
+                // `include_str!(` and the like
+                let before = source[..pos].chars().next_back();
+                if before.is_some_and(|c| c.is_alphanumeric() || c == '_') {
+                    continue;
+                }
+                let call = source[pos + needle.len()..].trim_start();
+                let key = call
+                    .strip_prefix('"')
+                    .and_then(|call| call.split_once('"'))
+                    .unwrap_or_else(|| panic!("{}: the key is not a literal", path.display()))
+                    .0;
+                keys.push(key.to_string());
+            }
+        }
+    }
+
+    #[test]
+    fn test_catalogs_complete() {
+        let english = Catalog::parse(Language::English.builtin_catalog()).unwrap();
+
+        let workspace = Path::new(env!("CARGO_MANIFEST_DIR")).parent().unwrap();
+        let mut keys = Vec::new();
+        used_keys(workspace, &mut keys);
+        assert!(keys.iter().any(|key| key == "notification.now_playing"));
+        for key in &keys {
+            assert!(english.get(key).is_some(), "{} is not in en.toml", key);
+        }
+
+        // the other languages only translate keys which exist
+        for language in Language::ALL {
+            let catalog = Catalog::parse(language.builtin_catalog()).unwrap();
+            for key in catalog.keys() {
+                assert!(
+                    english.get(key).is_some(),
+                    "{} of {} is not in en.toml",
+                    key,
+                    language.code()
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,97 @@
+use std::{fmt, str::FromStr};
+
+use anyhow::{anyhow, Result};
+use log::{Level, LevelFilter};
+
+/// The level of each module, in the syntax of `RUST_LOG`: `warn,rfvp_core::vm=debug`
+///
+/// A module applies to its submodules too, the longest matching module wins. A level
+/// without a module is the level of everything else.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogFilter {
+    default: LevelFilter,
+    /// longest modules first
+    modules: Vec<(String, LevelFilter)>,
+}
+
+impl Default for LogFilter {
+    fn default() -> Self {
+        Self {
+            default: LevelFilter::Info,
+            modules: Vec::new(),
+        }
+    }
+}
+
+impl LogFilter {
+    pub fn level_for(&self, target: &str) -> LevelFilter {
+        self.modules
+            .iter()
+            .find(|(module, _)| {
+                target
+                    .strip_prefix(module.as_str())
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with("::"))
+            })
+            .map_or(self.default, |&(_, level)| level)
+    }
+
+    pub fn enabled(&self, level: Level, target: &str) -> bool {
+        level <= self.level_for(target)
+    }
+
+    /// the most verbose level of any module
+    pub fn max_level(&self) -> LevelFilter {
+        self.modules
+            .iter()
+            .map(|&(_, level)| level)
+            .fold(self.default, Ord::max)
+    }
+
+    /// set the level of a module, and of its submodules without a level of their own
+    pub fn set_module(&mut self, module: &str, level: LevelFilter) {
+        match self.modules.iter_mut().find(|(m, _)| m == module) {
+            Some((_, l)) => *l = level,
+            None => {
+                self.modules.push((module.to_string(), level));
+                self.modules
+                    .sort_by(|(a, _), (b, _)| b.len().cmp(&a.len()).then(a.cmp(b)));
+            }
+        }
+    }
+}
+
+impl FromStr for LogFilter {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut filter = LogFilter::default();
+        for directive in s.split(',').map(str::trim).filter(|d| !d.is_empty()) {
+            let parse_level = |level: &str| {
+                level
+                    .parse::<LevelFilter>()
+                    .map_err(|_| anyhow!("unknown level {:?}", level))
+            };
+            match directive.split_once('=') {
+                Some((module, level)) => {
+                    let module = module.trim();
+                    if module.is_empty() {
+                        return Err(anyhow!("missing module before {:?}", directive));
+                    }
+                    filter.set_module(module, parse_level(level.trim())?);
+                }
+                None => filter.default = parse_level(directive)?,
+            }
+        }
+        Ok(filter)
+    }
+}
+
+impl fmt::Display for LogFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.default.as_str().to_ascii_lowercase())?;
+        for (module, level) in &self.modules {
+            write!(f, ",{}={}", module, level.as_str().to_ascii_lowercase())?;
+        }
+        Ok(())
+    }
+}
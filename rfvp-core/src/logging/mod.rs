@@ -0,0 +1,251 @@
+//! The logger of the engine.
+//!
+//! Every record goes through a [`LogFilter`] with a level per module, which can be changed
+//! while the game runs. The records passing it are printed to stderr, kept in a
+//! [`LogRing`] for the log panel of the debug UI and written to a [`RotatingFile`].
+//!
+//! The engine logs with both the `log` and the `tracing` macros. `tracing` is built with
+//! its `log` feature, so without a tracing subscriber its events end up here too.
+
+mod filter;
+mod ring;
+mod rotate;
+
+use std::{
+    io::Write,
+    path::PathBuf,
+    sync::{Mutex, MutexGuard, RwLock},
+};
+
+use anyhow::{anyhow, Context, Result};
+use chrono::Local;
+use log::{Log, Metadata, Record};
+use once_cell::sync::OnceCell;
+
+pub use filter::LogFilter;
+pub use ring::{LogEntry, LogRing};
+pub use rotate::RotatingFile;
+
+#[derive(Debug, Clone)]
+pub struct LogConfig {
+    /// the levels of the modules, like `warn,rfvp_core::vm=debug`
+    pub filter: String,
+    /// the number of entries kept for the debug UI
+    pub ring_capacity: usize,
+    pub file: Option<PathBuf>,
+    /// the size of a log file before it's rotated, in bytes
+    pub max_file_size: u64,
+    /// the number of rotated log files kept
+    pub kept_files: usize,
+    pub stderr: bool,
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        Self {
+            filter: "info".to_string(),
+            ring_capacity: 2000,
+            file: None,
+            max_file_size: 4 * 1024 * 1024,
+            kept_files: 3,
+            stderr: true,
+        }
+    }
+}
+
+pub struct Logger {
+    filter: RwLock<LogFilter>,
+    ring: Mutex<LogRing>,
+    file: Option<Mutex<RotatingFile>>,
+    stderr: bool,
+}
+
+impl Logger {
+    pub fn new(config: &LogConfig) -> Result<Self> {
+        let filter = config
+            .filter
+            .parse()
+            .with_context(|| format!("Parsing the log filter {:?}", config.filter))?;
+        let file = match &config.file {
+            Some(path) => Some(Mutex::new(
+                RotatingFile::open(path, config.max_file_size, config.kept_files)
+                    .with_context(|| format!("Opening the log file {}", path.display()))?,
+            )),
+            None => None,
+        };
+        Ok(Self {
+            filter: RwLock::new(filter),
+            ring: Mutex::new(LogRing::new(config.ring_capacity)),
+            file,
+            stderr: config.stderr,
+        })
+    }
+
+    pub fn filter(&self) -> LogFilter {
+        self.filter.read().unwrap().clone()
+    }
+
+    /// change the levels, for the records logged from now on
+    pub fn set_filter(&self, filter: LogFilter) {
+        log::set_max_level(filter.max_level());
+        *self.filter.write().unwrap() = filter;
+    }
+
+    pub fn ring(&self) -> MutexGuard<'_, LogRing> {
+        self.ring.lock().unwrap()
+    }
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.filter
+            .read()
+            .unwrap()
+            .enabled(metadata.level(), metadata.target())
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        let entry = LogEntry {
+            time: Local::now(),
+            level: record.level(),
+            target: record.target().to_string(),
+            message: record.args().to_string(),
+        };
+        if self.stderr || self.file.is_some() {
+            let line = format!(
+                "{} {:<5} {}: {}",
+                entry.time.format("%Y-%m-%d %H:%M:%S%.3f"),
+                entry.level,
+                entry.target,
+                entry.message
+            );
+            if self.stderr {
+                let _ = writeln!(std::io::stderr(), "{}", line);
+            }
+            if let Some(file) = &self.file {
+                // nowhere to report it, the stderr copy is all we have
+                let _ = file.lock().unwrap().write_line(&line);
+            }
+        }
+        self.ring().push(entry);
+    }
+
+    fn flush(&self) {
+        if let Some(file) = &self.file {
+            let _ = file.lock().unwrap().flush();
+        }
+    }
+}
+
+static LOGGER: OnceCell<Logger> = OnceCell::new();
+
+/// install the logger of the process, fails if there is already one
+pub fn init(config: &LogConfig) -> Result<()> {
+    let logger = Logger::new(config)?;
+    let max_level = logger.filter().max_level();
+    LOGGER
+        .set(logger)
+        .map_err(|_| anyhow!("The logger is already initialized"))?;
+    log::set_logger(LOGGER.get().unwrap())
+        .map_err(|_| anyhow!("Another logger is already installed"))?;
+    log::set_max_level(max_level);
+    Ok(())
+}
+
+/// the logger installed by [`init`]
+pub fn logger() -> Option<&'static Logger> {
+    LOGGER.get()
+}
+
+#[cfg(test)]
+mod tests {
+    use log::{Level, LevelFilter};
+
+    use super::*;
+
+    fn log(logger: &Logger, level: Level, target: &str, message: &str) {
+        logger.log(
+            &Record::builder()
+                .level(level)
+                .target(target)
+                .args(format_args!("{}", message))
+                .build(),
+        );
+    }
+
+    fn messages(ring: &LogRing) -> Vec<&str> {
+        ring.iter().map(|entry| entry.message.as_str()).collect()
+    }
+
+    #[test]
+    fn test_ring() {
+        let logger = Logger::new(&LogConfig {
+            filter: "trace".to_string(),
+            ring_capacity: 3,
+            stderr: false,
+            ..Default::default()
+        })
+        .unwrap();
+        for (index, level) in [Level::Info, Level::Warn, Level::Debug, Level::Error]
+            .into_iter()
+            .enumerate()
+        {
+            log(&logger, level, "rfvp::adv", &index.to_string());
+        }
+        log(&logger, Level::Warn, "rfvp_core::vm::context", "4");
+
+        let ring = logger.ring();
+        assert_eq!(ring.len(), 3);
+        assert_eq!(ring.dropped(), 2);
+        assert_eq!(messages(&ring), ["2", "3", "4"]);
+        let warnings = ring.filtered(LevelFilter::Warn, "").map(|e| &e.message);
+        assert_eq!(warnings.collect::<Vec<_>>(), ["3", "4"]);
+        let vm = ring.filtered(LevelFilter::Trace, "vm").map(|e| &e.message);
+        assert_eq!(vm.collect::<Vec<_>>(), ["4"]);
+    }
+
+    #[test]
+    fn test_filter_reconfiguration() {
+        let logger = Logger::new(&LogConfig {
+            filter: "warn,rfvp_core::vm=debug".to_string(),
+            stderr: false,
+            ..Default::default()
+        })
+        .unwrap();
+        let filter = logger.filter();
+        assert_eq!(
+            filter.level_for("rfvp_core::vm::context"),
+            LevelFilter::Debug
+        );
+        assert_eq!(filter.level_for("rfvp_core::vmx"), LevelFilter::Warn);
+        assert_eq!(filter.max_level(), LevelFilter::Debug);
+
+        log(&logger, Level::Debug, "rfvp_core::vm::context", "vm debug");
+        log(&logger, Level::Info, "rfvp::adv", "adv info");
+        assert_eq!(messages(&logger.ring()), ["vm debug"]);
+
+        // as the debug UI does
+        let mut filter: LogFilter = "error".parse().unwrap();
+        filter.set_module("rfvp::adv", LevelFilter::Info);
+        filter.set_module("rfvp", LevelFilter::Trace);
+        assert_eq!(filter.to_string(), "error,rfvp::adv=info,rfvp=trace");
+        assert_eq!(filter.to_string().parse::<LogFilter>().unwrap(), filter);
+        logger.set_filter(filter);
+
+        log(&logger, Level::Debug, "rfvp_core::vm::context", "vm debug");
+        log(&logger, Level::Info, "rfvp::adv::vm", "adv info");
+        log(&logger, Level::Debug, "rfvp::adv", "adv debug");
+        log(&logger, Level::Trace, "rfvp::layer", "layer trace");
+        assert_eq!(
+            messages(&logger.ring()),
+            ["vm debug", "adv info", "layer trace"]
+        );
+
+        assert!("rfvp=loud".parse::<LogFilter>().is_err());
+        assert!("=info".parse::<LogFilter>().is_err());
+    }
+}
@@ -0,0 +1,78 @@
+use std::collections::VecDeque;
+
+use chrono::{DateTime, Local};
+use log::{Level, LevelFilter};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogEntry {
+    pub time: DateTime<Local>,
+    pub level: Level,
+    pub target: String,
+    pub message: String,
+}
+
+/// The most recent log entries, for the log panel of the debug UI
+///
+/// Once full, a new entry drops the oldest one.
+#[derive(Debug, Clone)]
+pub struct LogRing {
+    entries: VecDeque<LogEntry>,
+    capacity: usize,
+    dropped: u64,
+}
+
+impl LogRing {
+    pub fn new(capacity: usize) -> Self {
+        assert!(capacity > 0, "the log ring can't be empty");
+        Self {
+            entries: VecDeque::with_capacity(capacity),
+            capacity,
+            dropped: 0,
+        }
+    }
+
+    pub fn push(&mut self, entry: LogEntry) {
+        if self.entries.len() == self.capacity {
+            self.entries.pop_front();
+            self.dropped += 1;
+        }
+        self.entries.push_back(entry);
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// the number of entries dropped to make room for newer ones
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    /// oldest first
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &LogEntry> {
+        self.entries.iter()
+    }
+
+    /// the entries at `level` or more severe, of targets containing `module`
+    pub fn filtered<'a>(
+        &'a self,
+        level: LevelFilter,
+        module: &'a str,
+    ) -> impl DoubleEndedIterator<Item = &'a LogEntry> {
+        self.entries
+            .iter()
+            .filter(move |entry| entry.level <= level && entry.target.contains(module))
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
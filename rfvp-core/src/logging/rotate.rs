@@ -0,0 +1,154 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+
+/// A log file, moved to `<name>.1` once it's too big, `<name>.1` to `<name>.2` and so on
+///
+/// The files are always UTF-8. On Windows they start with a BOM, or the editors read
+/// them in the ANSI code page and the Japanese text is garbled.
+pub struct RotatingFile {
+    path: PathBuf,
+    max_size: u64,
+    /// the number of rotated files kept next to the current one
+    keep: usize,
+    bom: bool,
+    file: File,
+    size: u64,
+}
+
+impl RotatingFile {
+    /// append to the file at `path`, creating it if needed
+    pub fn open(path: impl Into<PathBuf>, max_size: u64, keep: usize) -> io::Result<Self> {
+        Self::open_with_bom(path, max_size, keep, cfg!(windows))
+    }
+
+    fn open_with_bom(
+        path: impl Into<PathBuf>,
+        max_size: u64,
+        keep: usize,
+        bom: bool,
+    ) -> io::Result<Self> {
+        let path = path.into();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let size = file.metadata()?.len();
+        let mut result = Self {
+            path,
+            max_size,
+            keep,
+            bom,
+            file,
+            size,
+        };
+        if size == 0 {
+            result.write_bom()?;
+        }
+        Ok(result)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn rotated_path(&self, index: usize) -> PathBuf {
+        let mut name = self.path.clone().into_os_string();
+        name.push(format!(".{}", index));
+        name.into()
+    }
+
+    fn write_bom(&mut self) -> io::Result<()> {
+        if self.bom {
+            self.file.write_all(UTF8_BOM)?;
+            self.size += UTF8_BOM.len() as u64;
+        }
+        Ok(())
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        if self.keep == 0 {
+            self.file = File::create(&self.path)?;
+        } else {
+            // the oldest file is replaced by the rename
+            for index in (1..self.keep).rev() {
+                let from = self.rotated_path(index);
+                if from.exists() {
+                    fs::rename(&from, self.rotated_path(index + 1))?;
+                }
+            }
+            fs::rename(&self.path, self.rotated_path(1))?;
+            self.file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&self.path)?;
+        }
+        self.size = 0;
+        self.write_bom()
+    }
+
+    /// append a line, rotating first if the file would go over the size
+    ///
+    /// A single line bigger than the size is still written whole.
+    pub fn write_line(&mut self, line: &str) -> io::Result<()> {
+        let len = line.len() as u64 + 1;
+        let header = if self.bom { UTF8_BOM.len() as u64 } else { 0 };
+        if self.size > header && self.size + len > self.max_size {
+            self.rotate()?;
+        }
+        self.file.write_all(line.as_bytes())?;
+        self.file.write_all(b"\n")?;
+        self.size += len;
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rotation() {
+        let dir = std::env::temp_dir().join(format!("rfvp-log-rotate-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let path = dir.join("rfvp.log");
+
+        // 3 bytes of BOM, then room for two 5 byte lines per file
+        let mut file = RotatingFile::open_with_bom(&path, 13, 2, true).unwrap();
+        for line in ["aaaa", "bbbb", "cccc", "dddd", "eeee", "ffff", "gggg"] {
+            file.write_line(line).unwrap();
+        }
+        file.flush().unwrap();
+
+        let read = |path: PathBuf| fs::read(path).unwrap();
+        assert_eq!(read(path.clone()), b"\xEF\xBB\xBFgggg\n");
+        assert_eq!(read(file.rotated_path(1)), b"\xEF\xBB\xBFeeee\nffff\n");
+        assert_eq!(read(file.rotated_path(2)), b"\xEF\xBB\xBFcccc\ndddd\n");
+        // only two rotated files are kept
+        assert!(!file.rotated_path(3).exists());
+
+        // appending to an existing file doesn't write another BOM
+        drop(file);
+        let mut file = RotatingFile::open_with_bom(&path, 64, 2, true).unwrap();
+        file.write_line("日本").unwrap();
+        assert_eq!(read(path.clone()), "\u{feff}gggg\n日本\n".as_bytes());
+
+        // without rotated files, the file is started over
+        let mut file = RotatingFile::open_with_bom(dir.join("single.log"), 8, 0, false).unwrap();
+        for line in ["1111", "2222"] {
+            file.write_line(line).unwrap();
+        }
+        assert_eq!(read(dir.join("single.log")), b"2222\n");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
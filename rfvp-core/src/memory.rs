@@ -0,0 +1,315 @@
+//! A process-wide memory budget, for 32-bit builds and low-RAM devices.
+//!
+//! The subsystems holding large buffers report them with a [`MemoryTicket`], which
+//! is a couple of atomic additions: creating, resizing and dropping it keeps the
+//! counters up to date. Once per frame the host calls [`MemoryGovernor::rebalance`],
+//! which walks the [`DegradationStep`]s in order while the usage is above their
+//! threshold and asks the registered [`Reclaim`]ers to free memory at each.
+
+use std::{
+    fmt,
+    sync::{
+        atomic::{AtomicU64, AtomicU8, Ordering},
+        Mutex, Weak,
+    },
+};
+
+use once_cell::sync::Lazy;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MemoryKind {
+    /// decoded pictures, the CPU copy of the pixels
+    Pictures,
+    /// decoded bustup parts
+    Bustups,
+    /// audio files, loaded whole
+    Audio,
+    /// decoded video frames waiting to be shown
+    Video,
+}
+
+pub const MEMORY_KIND_COUNT: usize = 4;
+
+impl MemoryKind {
+    pub const ALL: [MemoryKind; MEMORY_KIND_COUNT] = [
+        MemoryKind::Pictures,
+        MemoryKind::Bustups,
+        MemoryKind::Audio,
+        MemoryKind::Video,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            MemoryKind::Pictures => "Pictures",
+            MemoryKind::Bustups => "Bustups",
+            MemoryKind::Audio => "Audio",
+            MemoryKind::Video => "Video",
+        }
+    }
+}
+
+/// What is given up to stay in the budget, in the order it's tried
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum DegradationStep {
+    /// drop the CPU pixels of what is uploaded to the GPU, it can be decoded again
+    DropCpuImages,
+}
+
+impl DegradationStep {
+    pub const ALL: [DegradationStep; 1] = [DegradationStep::DropCpuImages];
+
+    /// the usage above which the step is taken, in thousandths of the budget
+    pub fn threshold_permille(self) -> u64 {
+        match self {
+            DegradationStep::DropCpuImages => 700,
+        }
+    }
+
+    fn to_u8(step: Option<Self>) -> u8 {
+        match step {
+            None => 0,
+            Some(step) => step as u8 + 1,
+        }
+    }
+
+    fn from_u8(value: u8) -> Option<Self> {
+        value
+            .checked_sub(1)
+            .map(|index| DegradationStep::ALL[index as usize])
+    }
+}
+
+/// Something holding memory it can give back
+pub trait Reclaim: Send + Sync {
+    /// free what can be freed at this step, returns the bytes freed
+    fn reclaim(&self, step: DegradationStep) -> u64;
+}
+
+pub struct MemoryGovernor {
+    budget: AtomicU64,
+    usage: [AtomicU64; MEMORY_KIND_COUNT],
+    reclaimers: Mutex<Vec<Weak<dyn Reclaim>>>,
+    /// the furthest step taken by the last rebalance
+    step: AtomicU8,
+}
+
+impl MemoryGovernor {
+    pub fn new(budget: u64) -> Self {
+        Self {
+            budget: AtomicU64::new(budget),
+            usage: Default::default(),
+            reclaimers: Mutex::new(Vec::new()),
+            step: AtomicU8::new(0),
+        }
+    }
+
+    pub fn budget(&self) -> u64 {
+        self.budget.load(Ordering::Relaxed)
+    }
+
+    pub fn set_budget(&self, budget: u64) {
+        self.budget.store(budget, Ordering::Relaxed);
+    }
+
+    pub fn usage(&self, kind: MemoryKind) -> u64 {
+        self.usage[kind as usize].load(Ordering::Relaxed)
+    }
+
+    pub fn total_usage(&self) -> u64 {
+        self.usage
+            .iter()
+            .map(|usage| usage.load(Ordering::Relaxed))
+            .sum()
+    }
+
+    /// account for `bytes` of `kind` until the ticket is dropped
+    pub fn track(&'static self, kind: MemoryKind, bytes: u64) -> MemoryTicket {
+        self.usage[kind as usize].fetch_add(bytes, Ordering::Relaxed);
+        MemoryTicket {
+            governor: self,
+            kind,
+            bytes: AtomicU64::new(bytes),
+        }
+    }
+
+    /// the reclaimer is dropped from the list once it's gone
+    pub fn register(&self, reclaimer: Weak<dyn Reclaim>) {
+        self.reclaimers.lock().unwrap().push(reclaimer);
+    }
+
+    fn threshold(&self, step: DegradationStep) -> u64 {
+        self.budget() / 1000 * step.threshold_permille()
+    }
+
+    /// take the degradation steps needed to get back below their thresholds,
+    /// returns the furthest step taken
+    pub fn rebalance(&self) -> Option<DegradationStep> {
+        let mut reached = None;
+        for step in DegradationStep::ALL {
+            let usage = self.total_usage();
+            if usage <= self.threshold(step) {
+                break;
+            }
+            reached = Some(step);
+
+            let reclaimers = {
+                let mut reclaimers = self.reclaimers.lock().unwrap();
+                reclaimers.retain(|reclaimer| reclaimer.strong_count() > 0);
+                reclaimers
+                    .iter()
+                    .filter_map(Weak::upgrade)
+                    .collect::<Vec<_>>()
+            };
+            let freed = reclaimers
+                .iter()
+                .map(|reclaimer| reclaimer.reclaim(step))
+                .sum::<u64>();
+            if DegradationStep::to_u8(Some(step)) > self.step.load(Ordering::Relaxed) {
+                log::warn!(
+                    "Memory usage {} of {} bytes, {:?} freed {} bytes",
+                    usage,
+                    self.budget(),
+                    step,
+                    freed
+                );
+            }
+        }
+
+        self.step
+            .store(DegradationStep::to_u8(reached), Ordering::Relaxed);
+        reached
+    }
+
+    /// the furthest step taken by the last rebalance
+    pub fn step(&self) -> Option<DegradationStep> {
+        DegradationStep::from_u8(self.step.load(Ordering::Relaxed))
+    }
+}
+
+/// Accounts for a buffer in the [`MemoryGovernor`] while it lives
+pub struct MemoryTicket {
+    governor: &'static MemoryGovernor,
+    kind: MemoryKind,
+    bytes: AtomicU64,
+}
+
+impl MemoryTicket {
+    pub fn bytes(&self) -> u64 {
+        self.bytes.load(Ordering::Relaxed)
+    }
+
+    /// the buffer changed size, e.g. some of it was freed
+    pub fn set(&self, bytes: u64) {
+        let usage = &self.governor.usage[self.kind as usize];
+        let old = self.bytes.swap(bytes, Ordering::Relaxed);
+        if bytes > old {
+            usage.fetch_add(bytes - old, Ordering::Relaxed);
+        } else {
+            usage.fetch_sub(old - bytes, Ordering::Relaxed);
+        }
+    }
+}
+
+impl Drop for MemoryTicket {
+    fn drop(&mut self) {
+        self.set(0);
+    }
+}
+
+impl fmt::Debug for MemoryTicket {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MemoryTicket")
+            .field("kind", &self.kind)
+            .field("bytes", &self.bytes())
+            .finish()
+    }
+}
+
+/// the physical memory of the machine, if it can be found out
+fn system_memory() -> Option<u64> {
+    if cfg!(target_os = "linux") {
+        let meminfo = std::fs::read_to_string("/proc/meminfo").ok()?;
+        let line = meminfo.lines().find(|line| line.starts_with("MemTotal:"))?;
+        let kb = line.split_whitespace().nth(1)?.parse::<u64>().ok()?;
+        Some(kb * 1024)
+    } else {
+        None
+    }
+}
+
+/// half of the physical memory, 2GB if it's unknown, and at most 1.5GB in a 32-bit process
+pub fn default_budget() -> u64 {
+    const GB: u64 = 1024 * 1024 * 1024;
+    let budget = system_memory().map_or(2 * GB, |memory| memory / 2);
+    if cfg!(target_pointer_width = "32") {
+        budget.min(3 * GB / 2)
+    } else {
+        budget
+    }
+}
+
+static GOVERNOR: Lazy<MemoryGovernor> = Lazy::new(|| MemoryGovernor::new(default_budget()));
+
+/// the governor of the process
+pub fn governor() -> &'static MemoryGovernor {
+    &GOVERNOR
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+
+    /// pictures which can give back half of their memory at each step
+    struct Pictures {
+        ticket: MemoryTicket,
+        steps: Mutex<Vec<DegradationStep>>,
+    }
+
+    impl Reclaim for Pictures {
+        fn reclaim(&self, step: DegradationStep) -> u64 {
+            self.steps.lock().unwrap().push(step);
+            let freed = self.ticket.bytes() / 2;
+            self.ticket.set(self.ticket.bytes() - freed);
+            freed
+        }
+    }
+
+    #[test]
+    fn test_degradation_ladder() {
+        let governor: &'static MemoryGovernor = Box::leak(Box::new(MemoryGovernor::new(1000)));
+        let audio = governor.track(MemoryKind::Audio, 300);
+        let pictures = Arc::new(Pictures {
+            ticket: governor.track(MemoryKind::Pictures, 500),
+            steps: Mutex::new(Vec::new()),
+        });
+        governor.register(Arc::downgrade(&pictures) as Weak<dyn Reclaim>);
+        assert_eq!(governor.total_usage(), 800);
+
+        // 800 -> 550, below the threshold after the step
+        assert_eq!(governor.rebalance(), Some(DegradationStep::DropCpuImages));
+        assert_eq!(governor.usage(MemoryKind::Pictures), 250);
+        assert_eq!(governor.rebalance(), None);
+
+        // 1300 -> 1175, still above it: taken again by the next rebalance
+        audio.set(1050);
+        assert_eq!(governor.rebalance(), Some(DegradationStep::DropCpuImages));
+        assert_eq!(governor.rebalance(), Some(DegradationStep::DropCpuImages));
+        assert_eq!(
+            *pictures.steps.lock().unwrap(),
+            [DegradationStep::DropCpuImages; 3]
+        );
+        assert_eq!(governor.step(), Some(DegradationStep::DropCpuImages));
+
+        // still working, and back to normal once the memory is freed
+        drop(audio);
+        assert_eq!(governor.usage(MemoryKind::Audio), 0);
+        assert_eq!(governor.rebalance(), None);
+        assert_eq!(governor.step(), None);
+
+        drop(pictures);
+        assert_eq!(governor.total_usage(), 0);
+        assert_eq!(governor.rebalance(), None);
+    }
+}
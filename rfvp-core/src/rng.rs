@@ -0,0 +1,30 @@
+use serde::{Deserialize, Serialize};
+
+/// xorshift32, the effects don't need more and the seed must give the same run
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct Rng(u32);
+
+impl Rng {
+    pub fn new(seed: u32) -> Self {
+        // zero is a fixed point of xorshift
+        Self(if seed == 0 { 0x9e37_79b9 } else { seed })
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        let mut x = self.0;
+        x ^= x << 13;
+        x ^= x >> 17;
+        x ^= x << 5;
+        self.0 = x;
+        x
+    }
+
+    /// in `[0, 1)`
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u32() >> 8) as f32 / (1 << 24) as f32
+    }
+
+    pub fn range(&mut self, min: f32, max: f32) -> f32 {
+        min + (max - min) * self.next_f32()
+    }
+}
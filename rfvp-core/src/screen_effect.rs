@@ -0,0 +1,365 @@
+//! The full screen effects: a flash of color, a quake shaking the screen and a negative
+//! flicker.
+//!
+//! Each kind runs on its own, they stack when they overlap: a flash over a quake shakes
+//! and flashes. Starting a kind again replaces the running one. The arguments are:
+//!
+//! | effect   | index | meaning                                               |
+//! |----------|-------|-------------------------------------------------------|
+//! | flash    | 0     | the color, `0xRRGGBB`, nil for white                  |
+//! | flash    | 1     | the duration in milliseconds, nil for 100             |
+//! | quake    | 0     | the amplitude, in pixels                              |
+//! | quake    | 1     | the duration in milliseconds                          |
+//! | negative | 0     | the duration in milliseconds                          |
+//! | negative | 1     | the flicker period in milliseconds, nil for steady    |
+//!
+//! The quake jitter is seeded, the same seed and the same frames give the same shake.
+
+use anyhow::{bail, Result};
+use glam::{vec2, vec4, Vec2, Vec4};
+use serde::{Deserialize, Serialize};
+
+use crate::{rng::Rng, time::Ticks, vm::command::Args};
+
+/// the jitter of a quake moves this often, in ticks, whatever the frame rate
+const QUAKE_STEP: f32 = 2.0;
+const DEFAULT_FLASH_MS: i32 = 100;
+
+fn duration_arg(args: Args, n: usize, default: Option<i32>) -> Result<u32> {
+    let duration = match default {
+        Some(default) => args.int_or(n, default)?,
+        None => args.int(n)?,
+    };
+    if duration < 0 {
+        bail!("{}: negative duration {}", args.syscall(), duration);
+    }
+    Ok(duration as u32)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct FlashParams {
+    /// `0xRRGGBB`
+    pub color: u32,
+    pub duration_ms: u32,
+}
+
+impl FlashParams {
+    pub fn from_args(args: Args) -> Result<Self> {
+        Ok(Self {
+            color: args.int_or(0, 0xFFFFFF)? as u32 & 0xFFFFFF,
+            duration_ms: duration_arg(args, 1, Some(DEFAULT_FLASH_MS))?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct QuakeParams {
+    /// the largest offset, in pixels, at the start
+    pub amplitude: f32,
+    pub duration_ms: u32,
+}
+
+impl QuakeParams {
+    pub fn from_args(args: Args) -> Result<Self> {
+        Ok(Self {
+            amplitude: args.float(0)?.abs(),
+            duration_ms: duration_arg(args, 1, None)?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct NegativeParams {
+    pub duration_ms: u32,
+    /// the screen alternates between negative and normal every half period
+    pub flicker_ms: Option<u32>,
+}
+
+impl NegativeParams {
+    pub fn from_args(args: Args) -> Result<Self> {
+        let flicker_ms = args.opt_int(1)?.filter(|&flicker| flicker > 0);
+        Ok(Self {
+            duration_ms: duration_arg(args, 0, None)?,
+            flicker_ms: flicker_ms.map(|flicker| flicker as u32),
+        })
+    }
+}
+
+/// A running effect, saved with the time it has been running
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+struct Running<P> {
+    params: P,
+    /// in ticks, [`Ticks`] doesn't go into the snapshots
+    elapsed: f32,
+}
+
+impl<P> Running<P> {
+    fn new(params: P) -> Self {
+        Self {
+            params,
+            elapsed: 0.0,
+        }
+    }
+
+    /// from 0 to 1 over `duration_ms`
+    fn progress(&self, duration_ms: u32) -> f32 {
+        let duration = Ticks::from_millis(duration_ms as f32).as_f32();
+        if duration <= 0.0 {
+            return 1.0;
+        }
+        (self.elapsed / duration).clamp(0.0, 1.0)
+    }
+
+    /// Advances by `delta`, returns whether the effect is over
+    fn tick(&mut self, delta: Ticks, duration_ms: u32) -> bool {
+        self.elapsed += delta.as_f32();
+        self.progress(duration_ms) >= 1.0
+    }
+}
+
+/// The effects over the whole screen, ticked with the motions
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScreenEffects {
+    flash: Option<Running<FlashParams>>,
+    quake: Option<Running<QuakeParams>>,
+    negative: Option<Running<NegativeParams>>,
+    /// the direction of the current quake step, in `[-1, 1]`
+    jitter: [f32; 2],
+    rng: Rng,
+}
+
+impl Default for ScreenEffects {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+impl ScreenEffects {
+    pub fn new(seed: u32) -> Self {
+        Self {
+            flash: None,
+            quake: None,
+            negative: None,
+            jitter: [0.0; 2],
+            rng: Rng::new(seed),
+        }
+    }
+
+    pub fn flash(&mut self, params: FlashParams) {
+        self.flash = Some(Running::new(params));
+    }
+
+    pub fn quake(&mut self, params: QuakeParams) {
+        self.quake = Some(Running::new(params));
+        self.next_jitter();
+    }
+
+    pub fn negative(&mut self, params: NegativeParams) {
+        self.negative = Some(Running::new(params));
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.flash.is_some() || self.quake.is_some() || self.negative.is_some()
+    }
+
+    /// Ends everything at once, for the skip mode
+    pub fn skip(&mut self) {
+        self.flash = None;
+        self.quake = None;
+        self.negative = None;
+    }
+
+    fn next_jitter(&mut self) {
+        self.jitter = [self.rng.range(-1.0, 1.0), self.rng.range(-1.0, 1.0)];
+    }
+
+    pub fn update(&mut self, delta: Ticks) {
+        if let Some(flash) = &mut self.flash {
+            if flash.tick(delta, flash.params.duration_ms) {
+                self.flash = None;
+            }
+        }
+
+        if let Some(quake) = &mut self.quake {
+            let step = (quake.elapsed / QUAKE_STEP) as u32;
+            let done = quake.tick(delta, quake.params.duration_ms);
+            let steps = (quake.elapsed / QUAKE_STEP) as u32 - step;
+            if done {
+                self.quake = None;
+            } else {
+                // a long frame still draws as many numbers, the shake doesn't depend on
+                // the frame rate
+                for _ in 0..steps {
+                    self.next_jitter();
+                }
+            }
+        }
+
+        if let Some(negative) = &mut self.negative {
+            if negative.tick(delta, negative.params.duration_ms) {
+                self.negative = None;
+            }
+        }
+    }
+
+    /// The color to draw over the screen, its alpha fading out fast after the pulse
+    pub fn flash_color(&self) -> Option<Vec4> {
+        let flash = self.flash.as_ref()?;
+        let alpha = (1.0 - flash.progress(flash.params.duration_ms)).powi(2);
+        let channel = |shift: u32| ((flash.params.color >> shift) & 0xFF) as f32 / 255.0;
+        Some(vec4(channel(16), channel(8), channel(0), alpha))
+    }
+
+    /// How far the screen is moved, in pixels, shaking less and less
+    pub fn quake_offset(&self) -> Vec2 {
+        let Some(quake) = &self.quake else {
+            return Vec2::ZERO;
+        };
+        let amplitude = quake.params.amplitude * (1.0 - quake.progress(quake.params.duration_ms));
+        vec2(self.jitter[0], self.jitter[1]) * amplitude
+    }
+
+    /// Whether the screen is drawn inverted this frame
+    pub fn is_negative(&self) -> bool {
+        let Some(negative) = &self.negative else {
+            return false;
+        };
+        match negative.params.flicker_ms {
+            None => true,
+            Some(flicker) => {
+                let half = Ticks::from_millis(flicker as f32 / 2.0).as_f32();
+                ((negative.elapsed / half) as u32).is_multiple_of(2)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::format::scenario::variant::Variant;
+
+    const FRAME: Ticks = Ticks::from_u32(1);
+
+    #[test]
+    fn test_flash_envelope() {
+        let mut effects = ScreenEffects::default();
+        let args = [Variant::Int(0xFF8000), Variant::Int(100)];
+        effects.flash(FlashParams::from_args(Args::new("Flash", &args)).unwrap());
+
+        assert_eq!(
+            effects.flash_color(),
+            Some(vec4(1.0, 128.0 / 255.0, 0.0, 1.0))
+        );
+        effects.update(Ticks::from_millis(50.0));
+        let alpha = effects.flash_color().unwrap().w;
+        assert!((alpha - 0.25).abs() < 1e-4, "{}", alpha);
+        effects.update(Ticks::from_millis(50.0));
+        assert_eq!(effects.flash_color(), None);
+        assert!(!effects.is_active());
+
+        // white and 100ms by default
+        let args = [Variant::Nil];
+        let params = FlashParams::from_args(Args::new("Flash", &args)).unwrap();
+        assert_eq!(
+            params,
+            FlashParams {
+                color: 0xFFFFFF,
+                duration_ms: 100
+            }
+        );
+        let args = [Variant::Nil, Variant::Int(-1)];
+        assert!(FlashParams::from_args(Args::new("Flash", &args)).is_err());
+    }
+
+    /// the offsets of a quake of 10 pixels over 500ms, frame by frame
+    fn quake_offsets(seed: u32, frame: Ticks) -> Vec<Vec2> {
+        let mut effects = ScreenEffects::new(seed);
+        effects.quake(QuakeParams {
+            amplitude: 10.0,
+            duration_ms: 500,
+        });
+        let mut offsets = Vec::new();
+        while effects.is_active() {
+            offsets.push(effects.quake_offset());
+            effects.update(frame);
+        }
+        offsets
+    }
+
+    #[test]
+    fn test_quake_decay() {
+        let offsets = quake_offsets(42, FRAME);
+        assert_eq!(offsets.len(), 30);
+        // within the amplitude, which decays to nothing
+        for (frame, offset) in offsets.iter().enumerate() {
+            let amplitude = 10.0 * (1.0 - frame as f32 / 30.0);
+            assert!(offset.abs().max_element() <= amplitude + 1e-4);
+        }
+        assert!(offsets[0].length() > 1.0);
+
+        // the same seed shakes the same, another one doesn't
+        assert_eq!(offsets, quake_offsets(42, FRAME));
+        assert_ne!(offsets, quake_offsets(7, FRAME));
+
+        // the jitter moves with the time, not the frames: at 30fps the shake visits every
+        // other position of the one at 60fps
+        let half_rate = quake_offsets(42, Ticks::from_u32(2));
+        let same_direction = |a: Vec2, b: Vec2| a.normalize().dot(b.normalize()) > 0.999;
+        for (frame, offset) in half_rate.iter().enumerate() {
+            assert!(same_direction(*offset, offsets[frame * 2]));
+        }
+    }
+
+    #[test]
+    fn test_negative_flicker() {
+        let mut effects = ScreenEffects::default();
+        let args = [Variant::Int(200), Variant::Int(100)];
+        effects.negative(NegativeParams::from_args(Args::new("Negative", &args)).unwrap());
+        let mut frames = Vec::new();
+        for _ in 0..5 {
+            frames.push(effects.is_negative());
+            effects.update(Ticks::from_millis(50.0));
+        }
+        assert_eq!(frames, [true, false, true, false, false]);
+
+        let args = [Variant::Int(100)];
+        effects.negative(NegativeParams::from_args(Args::new("Negative", &args)).unwrap());
+        effects.update(Ticks::from_millis(60.0));
+        assert!(effects.is_negative());
+    }
+
+    #[test]
+    fn test_stacking_and_skip() {
+        let mut effects = ScreenEffects::new(1);
+        effects.quake(QuakeParams {
+            amplitude: 8.0,
+            duration_ms: 1000,
+        });
+        effects.update(Ticks::from_millis(100.0));
+        // a flash during the quake, both show
+        effects.flash(FlashParams {
+            color: 0xFFFFFF,
+            duration_ms: 200,
+        });
+        effects.update(FRAME);
+        assert!(effects.flash_color().is_some());
+        assert_ne!(effects.quake_offset(), Vec2::ZERO);
+
+        // a snapshot resumes with the time left and the same shake
+        let saved = serde_yaml::to_string(&effects).unwrap();
+        let mut restored: ScreenEffects = serde_yaml::from_str(&saved).unwrap();
+        assert_eq!(restored, effects);
+        for _ in 0..10 {
+            effects.update(FRAME);
+            restored.update(FRAME);
+            assert_eq!(restored.quake_offset(), effects.quake_offset());
+            assert_eq!(restored.flash_color(), effects.flash_color());
+        }
+
+        effects.skip();
+        assert!(!effects.is_active());
+        assert_eq!(effects.quake_offset(), Vec2::ZERO);
+        assert_eq!(effects.flash_color(), None);
+    }
+}
@@ -0,0 +1,212 @@
+//! The shapes drawn by the renderer instead of a picture: rounded rectangles and circles,
+//! for the buttons and the gauges of the UI.
+//!
+//! A shape is described by its signed distance to the edge, the renderer draws it with
+//! anti-aliased edges from it and the hit test uses the same one, so the corners cut off a
+//! rounded rectangle don't take the clicks. The arguments are:
+//!
+//! | index | meaning                                                             |
+//! |-------|---------------------------------------------------------------------|
+//! | 0     | the kind: 0 for a rounded rectangle, 1 for a circle                 |
+//! | 1     | the width                                                           |
+//! | 2     | the height                                                          |
+//! | 3     | the corner radius of a rounded rectangle, nil for square corners    |
+//! | 4     | the fill color, `0xAARRGGBB`                                        |
+//! | 5     | the width of the border, inside the edge, nil for none              |
+//! | 6     | the border color, `0xAARRGGBB`, nil for the fill color              |
+
+use anyhow::{bail, Result};
+use glam::{vec2, vec4, Vec2, Vec4};
+
+use crate::vm::command::Args;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ShapeKind {
+    /// the radius is clamped to half of the shorter side
+    RoundedRect { corner_radius: f32 },
+    /// the circle inscribed in the size, centered
+    Circle,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Shape {
+    pub kind: ShapeKind,
+    pub size: Vec2,
+    pub fill: Vec4,
+    pub border_width: f32,
+    pub border: Vec4,
+}
+
+/// `0xAARRGGBB` to a color
+pub fn argb_color(argb: u32) -> Vec4 {
+    let channel = |shift: u32| ((argb >> shift) & 0xFF) as f32 / 255.0;
+    vec4(channel(16), channel(8), channel(0), channel(24))
+}
+
+/// How much of a pixel at `distance` from the edge is covered, the edge blends over one
+/// pixel
+pub fn coverage(distance: f32) -> f32 {
+    (0.5 - distance).clamp(0.0, 1.0)
+}
+
+impl Shape {
+    pub fn from_args(args: Args) -> Result<Self> {
+        let kind = match args.int(0)? {
+            0 => ShapeKind::RoundedRect {
+                corner_radius: args.opt_float(3)?.unwrap_or(0.0).max(0.0),
+            },
+            1 => ShapeKind::Circle,
+            kind => bail!("{}: unknown shape kind {}", args.syscall(), kind),
+        };
+        let size = vec2(args.float(1)?, args.float(2)?);
+        if size.x <= 0.0 || size.y <= 0.0 {
+            bail!("{}: empty shape {}x{}", args.syscall(), size.x, size.y);
+        }
+        let fill = argb_color(args.int(4)? as u32);
+        let border_width = args.opt_float(5)?.unwrap_or(0.0).max(0.0);
+        let border = args
+            .opt_int(6)?
+            .map_or(fill, |border| argb_color(border as u32));
+
+        Ok(Self {
+            kind,
+            size,
+            fill,
+            border_width,
+            border,
+        })
+    }
+
+    /// the radius of the corners, 0 for square ones and half of the size for a circle
+    pub fn corner_radius(&self) -> f32 {
+        let half = self.size.min_element() / 2.0;
+        match self.kind {
+            ShapeKind::RoundedRect { corner_radius } => corner_radius.min(half),
+            ShapeKind::Circle => half,
+        }
+    }
+
+    /// The signed distance from `point` to the edge, negative inside. `point` is relative to
+    /// the top left corner of the shape.
+    pub fn distance(&self, point: Vec2) -> f32 {
+        let half = self.size / 2.0;
+        let p = point - half;
+        match self.kind {
+            ShapeKind::Circle => p.length() - half.min_element(),
+            ShapeKind::RoundedRect { .. } => {
+                let radius = self.corner_radius();
+                let q = p.abs() - half + radius;
+                q.max(Vec2::ZERO).length() + q.max_element().min(0.0) - radius
+            }
+        }
+    }
+
+    /// whether `point`, relative to the top left corner, is on the shape, its border included
+    pub fn contains(&self, point: Vec2) -> bool {
+        self.distance(point) <= 0.0
+    }
+
+    /// The color of the pixel centered on `point`, with its coverage in the alpha. Keep in
+    /// sync with shape.wgsl.
+    pub fn color_at(&self, point: Vec2) -> Vec4 {
+        let distance = self.distance(point);
+        let inner = coverage(distance + self.border_width);
+        let color = self.border.lerp(self.fill, inner);
+        color * vec4(1.0, 1.0, 1.0, coverage(distance))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::format::scenario::variant::Variant;
+
+    fn shape(args: &[Variant]) -> Shape {
+        Shape::from_args(Args::new("PrimSetShape", args)).unwrap()
+    }
+
+    #[test]
+    fn test_hit() {
+        let circle = shape(&[
+            Variant::Int(1),
+            Variant::Int(100),
+            Variant::Int(100),
+            Variant::Nil,
+            Variant::Int(0xFFFFFFFFu32 as i32),
+        ]);
+        assert!(circle.contains(vec2(50.0, 50.0)));
+        assert!(circle.contains(vec2(50.0, 1.0)));
+        assert!(circle.contains(vec2(80.0, 80.0)));
+        // inside the bounds, outside of the circle
+        assert!(!circle.contains(vec2(5.0, 5.0)));
+        assert!(!circle.contains(vec2(90.0, 10.0)));
+
+        let rect = shape(&[
+            Variant::Int(0),
+            Variant::Int(200),
+            Variant::Int(100),
+            Variant::Int(20),
+            Variant::Int(0x80FF0000u32 as i32),
+        ]);
+        // the corners are cut
+        assert!(!rect.contains(vec2(1.0, 1.0)));
+        assert!(!rect.contains(vec2(199.0, 99.0)));
+        assert!(rect.contains(vec2(20.0, 1.0)));
+        assert!(rect.contains(vec2(1.0, 50.0)));
+        assert!(rect.contains(vec2(10.0, 10.0)));
+        assert!(!rect.contains(vec2(201.0, 50.0)));
+
+        // square corners take the whole bounds
+        let square = shape(&[
+            Variant::Int(0),
+            Variant::Int(10),
+            Variant::Int(10),
+            Variant::Nil,
+            Variant::Int(0),
+        ]);
+        assert!(square.contains(vec2(0.0, 0.0)));
+        assert!(square.contains(vec2(10.0, 10.0)));
+    }
+
+    #[test]
+    fn test_edges() {
+        let circle = shape(&[
+            Variant::Int(1),
+            Variant::Int(100),
+            Variant::Int(100),
+            Variant::Nil,
+            Variant::Int(0xFF0000FFu32 as i32),
+            Variant::Int(4),
+            Variant::Int(0xFFFFFFFFu32 as i32),
+        ]);
+        assert_eq!(circle.fill, vec4(0.0, 0.0, 1.0, 1.0));
+        assert_eq!(circle.color_at(vec2(50.0, 50.0)), circle.fill);
+        // the border, then the edge half covered, then nothing
+        assert_eq!(circle.color_at(vec2(50.0, 2.0)), Vec4::ONE);
+        assert_eq!(circle.color_at(vec2(50.0, 0.0)).w, 0.5);
+        assert_eq!(circle.color_at(vec2(50.0, -1.0)).w, 0.0);
+        // the fill and the border blend at their edge too
+        let color = circle.color_at(vec2(50.0, 4.0));
+        assert_eq!(color, vec4(0.5, 0.5, 1.0, 1.0));
+    }
+
+    #[test]
+    fn test_invalid() {
+        let args = [Variant::Int(2), Variant::Int(10), Variant::Int(10)];
+        assert!(Shape::from_args(Args::new("PrimSetShape", &args)).is_err());
+        let args = [Variant::Int(0), Variant::Int(0), Variant::Int(10)];
+        assert!(Shape::from_args(Args::new("PrimSetShape", &args)).is_err());
+
+        // a radius larger than the shape makes a pill
+        let pill = shape(&[
+            Variant::Int(0),
+            Variant::Int(100),
+            Variant::Int(20),
+            Variant::Int(500),
+            Variant::Int(0),
+        ]);
+        assert_eq!(pill.corner_radius(), 10.0);
+        assert!(!pill.contains(vec2(1.0, 1.0)));
+        assert!(pill.contains(vec2(50.0, 1.0)));
+    }
+}
@@ -0,0 +1,154 @@
+use super::Ticks;
+
+/// Advances the message on its own in auto mode, after a dwell proportional to its length.
+///
+/// The timer is driven by the game clock, like the script waits: [`Self::start`] it when the
+/// message is waiting for a click, feed it the frame delta in [`Self::update`] and advance
+/// when it fires. Any input from the player should [`Self::cancel`] it.
+///
+/// A voiced message also waits for its voice, see [`Self::update_voiced`].
+#[derive(Debug, Clone)]
+pub struct AutoAdvance {
+    per_char: Ticks,
+    min_dwell: Ticks,
+    voice_padding: Ticks,
+    remaining: Option<Ticks>,
+    /// the padding left after the voice stopped
+    voice_remaining: Ticks,
+}
+
+impl AutoAdvance {
+    /// `ms_per_char` of reading time per revealed char, on top of a `min_dwell_ms` for every message
+    pub fn new(ms_per_char: f32, min_dwell_ms: f32) -> Self {
+        Self {
+            per_char: Ticks::from_millis(ms_per_char.max(0.0)),
+            min_dwell: Ticks::from_millis(min_dwell_ms.max(0.0)),
+            voice_padding: Ticks::from_millis(DEFAULT_VOICE_PADDING_MS),
+            remaining: None,
+            voice_remaining: Ticks::ZERO,
+        }
+    }
+
+    /// The pause between the end of the voice and the next message
+    pub fn set_voice_padding(&mut self, padding_ms: f32) {
+        self.voice_padding = Ticks::from_millis(padding_ms.max(0.0));
+    }
+
+    /// How long a message of `char_count` chars stays on screen before advancing
+    pub fn dwell(&self, char_count: usize) -> Ticks {
+        self.min_dwell + Ticks::from_f32(self.per_char.as_f32() * char_count as f32)
+    }
+
+    /// Starts the countdown for a message of `char_count` revealed chars, restarting a pending one
+    pub fn start(&mut self, char_count: usize) {
+        self.remaining = Some(self.dwell(char_count));
+        self.voice_remaining = Ticks::ZERO;
+    }
+
+    pub fn cancel(&mut self) {
+        self.remaining = None;
+    }
+
+    pub fn is_pending(&self) -> bool {
+        self.remaining.is_some()
+    }
+
+    /// Counts down by `delta`, returns `true` once when it's time to advance
+    pub fn update(&mut self, delta: Ticks) -> bool {
+        self.update_voiced(delta, false)
+    }
+
+    /// Like [`Self::update`], but doesn't fire while `voice_playing` and the padding after it.
+    /// A muted voice is never playing, the message goes by its length only.
+    pub fn update_voiced(&mut self, delta: Ticks, voice_playing: bool) -> bool {
+        let Some(remaining) = &mut self.remaining else {
+            return false;
+        };
+        *remaining -= delta;
+        if voice_playing {
+            self.voice_remaining = self.voice_padding;
+        } else {
+            self.voice_remaining -= delta;
+        }
+
+        if *remaining <= Ticks::ZERO && !voice_playing && self.voice_remaining <= Ticks::ZERO {
+            self.remaining = None;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// The default of [`AutoAdvance::set_voice_padding`]
+pub const DEFAULT_VOICE_PADDING_MS: f32 = 500.0;
+
+impl Default for AutoAdvance {
+    fn default() -> Self {
+        Self::new(50.0, 1000.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dwell() {
+        let mut auto = AutoAdvance::new(50.0, 1000.0);
+        assert_eq!(auto.dwell(20), Ticks::from_millis(2000.0));
+        assert_eq!(auto.dwell(0), Ticks::from_millis(1000.0));
+
+        auto.start(20);
+        assert!(!auto.update(Ticks::from_millis(1500.0)));
+        assert!(auto.update(Ticks::from_millis(500.0)));
+        // fires once
+        assert!(!auto.is_pending());
+        assert!(!auto.update(Ticks::from_millis(5000.0)));
+
+        auto.start(20);
+        auto.cancel();
+        assert!(!auto.update(Ticks::from_millis(5000.0)));
+    }
+
+    const FRAME_MS: u32 = 10;
+
+    /// the time in ms the message advances at, with a voice of `voice_ms` (`None` if muted)
+    fn advance_time(auto: &mut AutoAdvance, voice_ms: Option<u32>) -> u32 {
+        auto.start(20);
+        let mut now = 0;
+        loop {
+            now += FRAME_MS;
+            let voice_playing = voice_ms.is_some_and(|voice_ms| now <= voice_ms);
+            if auto.update_voiced(Ticks::from_millis(FRAME_MS as f32), voice_playing) {
+                return now;
+            }
+        }
+    }
+
+    #[test]
+    fn test_voiced() {
+        // a frame late at most, the ticks are floats
+        let assert_advances_at = |auto: &mut AutoAdvance, voice_ms, expected: u32| {
+            let now = advance_time(auto, voice_ms);
+            assert!(
+                (expected..=expected + FRAME_MS).contains(&now),
+                "advanced at {} instead of {}",
+                now,
+                expected
+            );
+        };
+        let mut auto = AutoAdvance::new(50.0, 1000.0);
+        auto.set_voice_padding(300.0);
+
+        // waits for the voice and the padding
+        assert_advances_at(&mut auto, Some(2500), 2800);
+        // a short voice doesn't cut the reading time
+        assert_advances_at(&mut auto, Some(500), 2000);
+        // a muted line goes by the text only
+        assert_advances_at(&mut auto, None, 2000);
+
+        auto.set_voice_padding(0.0);
+        assert_advances_at(&mut auto, Some(2500), 2500);
+    }
+}
@@ -0,0 +1,324 @@
+use std::time::Duration;
+
+use super::Ticks;
+
+/// The subsystems which follow their own child of the [`GameClock`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Subsystem {
+    Motion,
+    Video,
+    Pacing,
+}
+
+impl Subsystem {
+    pub const ALL: [Subsystem; 3] = [Subsystem::Motion, Subsystem::Video, Subsystem::Pacing];
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// The step of [`GameClock::set_fixed_timestep`] unless told otherwise, about a frame at 60 fps
+pub const DEFAULT_FIXED_STEP: Duration = Duration::from_millis(16);
+
+/// More steps in a frame are dropped, so a long stall doesn't make the next frames even slower
+const MAX_STEPS_PER_FRAME: u32 = 16;
+
+/// Splits the variable frame time into fixed steps, carrying the remainder to the next frame
+#[derive(Debug, Clone)]
+pub struct FixedTimestep {
+    step: Duration,
+    remainder: Duration,
+}
+
+impl FixedTimestep {
+    /// # Panics
+    ///
+    /// Panics if `step` is zero.
+    pub fn new(step: Duration) -> Self {
+        assert!(!step.is_zero(), "the fixed step can't be zero");
+        Self {
+            step,
+            remainder: Duration::ZERO,
+        }
+    }
+
+    pub fn step(&self) -> Duration {
+        self.step
+    }
+
+    /// the time not yet run, less than a step
+    pub fn remainder(&self) -> Duration {
+        self.remainder
+    }
+
+    /// Adds the time of a frame, returns how many steps to run
+    pub fn advance(&mut self, delta: Duration) -> u32 {
+        let total = self.remainder + delta;
+        let steps = (total.as_nanos() / self.step.as_nanos()) as u32;
+        self.remainder = total - self.step * steps;
+        if steps > MAX_STEPS_PER_FRAME {
+            tracing::debug!("Dropping {} fixed steps", steps - MAX_STEPS_PER_FRAME);
+            return MAX_STEPS_PER_FRAME;
+        }
+        steps
+    }
+}
+
+/// A child clock, advancing with the game clock unless paused on its own
+#[derive(Debug, Clone, Default)]
+pub struct SubClock {
+    paused: bool,
+    now: Duration,
+    delta: Duration,
+    fixed: Option<FixedTimestep>,
+    steps: u32,
+}
+
+impl SubClock {
+    pub fn now(&self) -> Duration {
+        self.now
+    }
+
+    pub fn delta_since_last_frame(&self) -> Duration {
+        self.delta
+    }
+
+    pub fn delta_ticks(&self) -> Ticks {
+        Ticks::from_duration(self.delta)
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    pub fn resume(&mut self) {
+        self.paused = false;
+    }
+
+    /// The time of the frame as steps of the same length: `steps` fixed steps with a fixed
+    /// timestep, otherwise a single step of the whole frame
+    pub fn sub_steps(&self) -> (u32, Ticks) {
+        match &self.fixed {
+            Some(fixed) => (self.steps, Ticks::from_duration(fixed.step())),
+            None => (1, self.delta_ticks()),
+        }
+    }
+
+    fn advance(&mut self, delta: Duration) {
+        let delta = if self.paused { Duration::ZERO } else { delta };
+        self.delta = match &mut self.fixed {
+            Some(fixed) => {
+                self.steps = fixed.advance(delta);
+                fixed.step() * self.steps
+            }
+            None => delta,
+        };
+        self.now += self.delta;
+    }
+}
+
+/// The virtual time of the game.
+///
+/// It's advanced once per frame by the real time elapsed, scaled by the time scale
+/// (skip mode) and stopped while paused (focus loss, system menu). Subsystems read
+/// their own child clock instead of the wall clock, so pausing or scaling applies
+/// to all of them at once and they stay in sync.
+///
+/// Changing the scale or pausing only affects the time advanced afterwards,
+/// the virtual time itself never jumps.
+#[derive(Debug, Clone)]
+pub struct GameClock {
+    scale: f64,
+    paused: bool,
+    now: Duration,
+    delta: Duration,
+    subclocks: [SubClock; Subsystem::ALL.len()],
+}
+
+impl Default for GameClock {
+    fn default() -> Self {
+        Self {
+            scale: 1.0,
+            paused: false,
+            now: Duration::ZERO,
+            delta: Duration::ZERO,
+            subclocks: Default::default(),
+        }
+    }
+}
+
+impl GameClock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Advance by the real time elapsed since the previous frame
+    pub fn advance(&mut self, real_delta: Duration) {
+        self.delta = if self.paused {
+            Duration::ZERO
+        } else if self.scale != 1.0 {
+            real_delta.mul_f64(self.scale)
+        } else {
+            // avoid rounding when at normal speed
+            real_delta
+        };
+        self.now += self.delta;
+
+        for subclock in &mut self.subclocks {
+            subclock.advance(self.delta);
+        }
+    }
+
+    pub fn now(&self) -> Duration {
+        self.now
+    }
+
+    pub fn delta_since_last_frame(&self) -> Duration {
+        self.delta
+    }
+
+    pub fn delta_ticks(&self) -> Ticks {
+        Ticks::from_duration(self.delta)
+    }
+
+    pub fn scale(&self) -> f64 {
+        self.scale
+    }
+
+    /// # Panics
+    ///
+    /// Panics if `scale` is negative or not finite.
+    pub fn set_scale(&mut self, scale: f64) {
+        assert!(scale.is_finite(), "tried to go infinitely fast");
+        assert!(scale.is_sign_positive(), "tried to go back in time");
+        self.scale = scale;
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    pub fn resume(&mut self) {
+        self.paused = false;
+    }
+
+    pub fn subsystem(&self, subsystem: Subsystem) -> &SubClock {
+        &self.subclocks[subsystem.index()]
+    }
+
+    pub fn subsystem_mut(&mut self, subsystem: Subsystem) -> &mut SubClock {
+        &mut self.subclocks[subsystem.index()]
+    }
+
+    /// Advance `subsystem` in fixed steps of `step`, or by the frame time with `None`.
+    ///
+    /// Its delta is then a whole number of steps, the rest of the frame time is carried
+    /// to the next frame, see [`SubClock::sub_steps`].
+    pub fn set_fixed_timestep(&mut self, subsystem: Subsystem, step: Option<Duration>) {
+        let subclock = self.subsystem_mut(subsystem);
+        subclock.fixed = step.map(FixedTimestep::new);
+        subclock.steps = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FRAME: Duration = Duration::from_millis(20);
+
+    #[test]
+    fn test_pause_resume_scale() {
+        let mut clock = GameClock::new();
+        clock.advance(FRAME);
+        assert_eq!(clock.now(), FRAME);
+
+        clock.pause();
+        clock.advance(FRAME);
+        assert_eq!(clock.delta_since_last_frame(), Duration::ZERO);
+        assert_eq!(clock.now(), FRAME);
+
+        clock.resume();
+        clock.set_scale(2.0);
+        clock.advance(FRAME);
+        assert_eq!(clock.delta_since_last_frame(), FRAME * 2);
+        assert_eq!(clock.now(), FRAME * 3);
+
+        clock.set_scale(0.5);
+        clock.advance(FRAME);
+        assert_eq!(clock.now(), FRAME * 3 + FRAME / 2);
+        assert_eq!(clock.delta_ticks(), Ticks::from_millis(10.0));
+    }
+
+    #[test]
+    fn test_subsystem_clocks() {
+        let mut clock = GameClock::new();
+        clock.subsystem_mut(Subsystem::Video).pause();
+        clock.advance(FRAME);
+
+        assert_eq!(clock.subsystem(Subsystem::Motion).now(), FRAME);
+        assert_eq!(clock.subsystem(Subsystem::Video).now(), Duration::ZERO);
+
+        // the global pause stops every child, resuming it leaves the individually paused ones alone
+        clock.pause();
+        clock.advance(FRAME);
+        clock.resume();
+        clock.advance(FRAME);
+        assert_eq!(clock.subsystem(Subsystem::Motion).now(), FRAME * 2);
+        assert_eq!(clock.subsystem(Subsystem::Pacing).now(), FRAME * 2);
+        assert_eq!(clock.subsystem(Subsystem::Video).now(), Duration::ZERO);
+
+        clock.subsystem_mut(Subsystem::Video).resume();
+        clock.advance(FRAME);
+        assert_eq!(clock.subsystem(Subsystem::Video).now(), FRAME);
+        assert_eq!(clock.subsystem(Subsystem::Video).delta_ticks(), Ticks::from_millis(20.0));
+    }
+
+    #[test]
+    fn test_fixed_timestep() {
+        let ms = Duration::from_millis;
+        let mut clock = GameClock::new();
+        clock.set_fixed_timestep(Subsystem::Motion, Some(DEFAULT_FIXED_STEP));
+
+        clock.advance(ms(50));
+        let motion = clock.subsystem(Subsystem::Motion);
+        assert_eq!(motion.sub_steps(), (3, Ticks::from_duration(ms(16))));
+        assert_eq!(motion.fixed.as_ref().unwrap().remainder(), ms(2));
+        assert_eq!(motion.now(), ms(48));
+        // the other subsystems still follow the frame time
+        assert_eq!(
+            clock.subsystem(Subsystem::Video).sub_steps(),
+            (1, Ticks::from_duration(ms(50)))
+        );
+
+        // the carried 2ms complete a step
+        clock.advance(ms(14));
+        assert_eq!(clock.subsystem(Subsystem::Motion).sub_steps().0, 1);
+        assert_eq!(clock.subsystem(Subsystem::Motion).now(), ms(64));
+        clock.advance(ms(5));
+        assert_eq!(clock.subsystem(Subsystem::Motion).sub_steps().0, 0);
+
+        // a stall doesn't run away
+        clock.advance(Duration::from_secs(10));
+        assert_eq!(
+            clock.subsystem(Subsystem::Motion).sub_steps().0,
+            MAX_STEPS_PER_FRAME
+        );
+
+        clock.set_fixed_timestep(Subsystem::Motion, None);
+        clock.advance(ms(50));
+        assert_eq!(
+            clock.subsystem(Subsystem::Motion).sub_steps(),
+            (1, Ticks::from_duration(ms(50)))
+        );
+    }
+}
@@ -0,0 +1,162 @@
+use std::time::Duration;
+
+use super::GameClock;
+
+/// The speeds offered by the debug time controls
+pub const SPEED_PRESETS: [f64; 5] = [0.1, 0.25, 0.5, 1.0, 2.0];
+const NORMAL_SPEED: usize = 3;
+
+/// The game time advanced by a single step, one frame at 60 fps
+pub const STEP_DURATION: Duration = Duration::from_nanos(1_000_000_000 / 60);
+
+/// Debug controls over the game time: slow motion and frame stepping.
+///
+/// They drive the [`GameClock`], so the motions, the text reveal and the videos all slow
+/// down or stop together. A frame of the game which advances the clock is a simulation
+/// step; while paused, frames only run the queued single steps, each advancing exactly
+/// [`STEP_DURATION`] whatever the wall clock did.
+#[derive(Debug, Clone)]
+pub struct TimeControls {
+    paused: bool,
+    preset: usize,
+    pending_steps: u32,
+    steps: u64,
+}
+
+impl Default for TimeControls {
+    fn default() -> Self {
+        Self {
+            paused: false,
+            preset: NORMAL_SPEED,
+            pending_steps: 0,
+            steps: 0,
+        }
+    }
+}
+
+impl TimeControls {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Pausing drops the steps which didn't run yet
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+        self.pending_steps = 0;
+    }
+
+    pub fn toggle_pause(&mut self) {
+        self.set_paused(!self.paused);
+    }
+
+    /// Pauses and runs `count` steps, one per frame
+    pub fn step(&mut self, count: u32) {
+        self.paused = true;
+        self.pending_steps += count;
+    }
+
+    pub fn pending_steps(&self) -> u32 {
+        self.pending_steps
+    }
+
+    /// The index in [`SPEED_PRESETS`]
+    pub fn preset(&self) -> usize {
+        self.preset
+    }
+
+    /// # Panics
+    ///
+    /// Panics if `preset` isn't an index of [`SPEED_PRESETS`].
+    pub fn set_preset(&mut self, preset: usize) {
+        assert!(preset < SPEED_PRESETS.len(), "no speed preset {}", preset);
+        self.preset = preset;
+    }
+
+    pub fn faster(&mut self) {
+        self.preset = (self.preset + 1).min(SPEED_PRESETS.len() - 1);
+    }
+
+    pub fn slower(&mut self) {
+        self.preset = self.preset.saturating_sub(1);
+    }
+
+    pub fn speed(&self) -> f64 {
+        SPEED_PRESETS[self.preset]
+    }
+
+    /// Whether the game runs at its normal speed
+    pub fn is_realtime(&self) -> bool {
+        !self.paused && self.preset == NORMAL_SPEED
+    }
+
+    /// The number of simulation steps run so far
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
+
+    /// Advances the clock for a frame which took `real_delta`, returns the number of
+    /// simulation steps run, which is at most one
+    pub fn advance(&mut self, clock: &mut GameClock, real_delta: Duration) -> u32 {
+        if !self.paused {
+            clock.set_scale(self.speed());
+            clock.advance(real_delta);
+        } else if self.pending_steps > 0 {
+            self.pending_steps -= 1;
+            clock.set_scale(1.0);
+            clock.advance(STEP_DURATION);
+        } else {
+            // no time passes, the deltas of the previous frame are cleared
+            clock.advance(Duration::ZERO);
+            return 0;
+        }
+
+        self.steps += 1;
+        1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FRAME: Duration = Duration::from_millis(20);
+
+    #[test]
+    fn test_time_controls() {
+        let mut clock = GameClock::new();
+        let mut controls = TimeControls::new();
+        assert_eq!(controls.advance(&mut clock, FRAME), 1);
+        assert_eq!(clock.now(), FRAME);
+
+        controls.slower();
+        controls.slower();
+        assert_eq!(controls.speed(), 0.25);
+        assert_eq!(controls.advance(&mut clock, FRAME), 1);
+        assert_eq!(clock.delta_since_last_frame(), FRAME / 4);
+
+        controls.toggle_pause();
+        assert_eq!(controls.advance(&mut clock, FRAME), 0);
+        assert_eq!(clock.delta_since_last_frame(), Duration::ZERO);
+
+        // one step per frame, however long the frames take
+        controls.step(2);
+        let now = clock.now();
+        assert_eq!(controls.advance(&mut clock, Duration::from_secs(1)), 1);
+        assert_eq!(controls.advance(&mut clock, Duration::ZERO), 1);
+        assert_eq!(controls.advance(&mut clock, FRAME), 0);
+        assert_eq!(clock.now(), now + STEP_DURATION * 2);
+        assert_eq!(controls.steps(), 4);
+
+        controls.toggle_pause();
+        for _ in 0..10 {
+            controls.faster();
+        }
+        assert_eq!(controls.speed(), 2.0);
+        assert_eq!(controls.advance(&mut clock, FRAME), 1);
+        assert_eq!(clock.delta_since_last_frame(), FRAME * 2);
+    }
+}
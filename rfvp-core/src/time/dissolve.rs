@@ -0,0 +1,77 @@
+use crate::format::pic::MaskImage;
+
+use super::Ticks;
+
+/// A dissolve through a grayscale mask: the dark pixels of the mask show the new picture
+/// first, the white ones last.
+///
+/// Driven by the game clock like the other waits, feed it the frame delta in
+/// [`Self::tick`]. `vague` is the width of the soft edge in mask levels, 0 cuts sharply.
+#[derive(Debug, Clone)]
+pub struct MaskDissolve {
+    duration: Ticks,
+    elapsed: Ticks,
+    vague: u8,
+}
+
+impl MaskDissolve {
+    pub fn new(duration: Ticks, vague: u8) -> Self {
+        Self {
+            duration,
+            elapsed: Ticks::ZERO,
+            vague,
+        }
+    }
+
+    /// Advances by `delta`, returns `true` once when the dissolve is over
+    pub fn tick(&mut self, delta: Ticks) -> bool {
+        if self.is_done() {
+            return false;
+        }
+        self.elapsed += delta;
+        self.is_done()
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.elapsed >= self.duration
+    }
+
+    /// from 0 to 1
+    pub fn progress(&self) -> f32 {
+        if self.duration <= Ticks::ZERO {
+            return 1.0;
+        }
+        (self.elapsed / self.duration).clamp(0.0, 1.0)
+    }
+
+    /// how much of the new picture shows through a mask pixel of `value`
+    pub fn alpha(&self, value: u8) -> u8 {
+        let vague = self.vague as f32;
+        // the threshold sweeps past the whitest pixel by the width of the edge, so the
+        // whole picture is shown at the end
+        let threshold = self.progress() * (256.0 + vague);
+        let alpha = if self.vague == 0 {
+            if (value as f32) < threshold {
+                1.0
+            } else {
+                0.0
+            }
+        } else {
+            ((threshold - value as f32) / vague).clamp(0.0, 1.0)
+        };
+        (alpha * 255.0).round() as u8
+    }
+
+    /// [`Self::alpha`] of a pixel of the mask, `None` outside of it
+    pub fn alpha_at(&self, mask: &MaskImage, x: u32, y: u32) -> Option<u8> {
+        mask.value_at(x, y).map(|value| self.alpha(value))
+    }
+
+    /// Scales the alpha of the RGBA pixels of the new picture, the size of the mask
+    pub fn apply(&self, mask: &MaskImage, rgba: &mut [u8]) {
+        for (pixel, &value) in rgba.chunks_exact_mut(4).zip(mask.pixels()) {
+            let alpha = self.alpha(value) as u32;
+            pixel[3] = ((pixel[3] as u32 * alpha + 127) / 255) as u8;
+        }
+    }
+}
@@ -1,3 +1,9 @@
+mod auto_advance;
+mod clock;
+mod controls;
+mod dissolve;
+mod playtime;
+mod presentation;
 mod tween;
 mod tweener;
 
@@ -10,8 +16,16 @@ use std::{
 use derive_more::{Add, AddAssign, Sub, SubAssign};
 use float_ord::FloatOrd;
 use tracing::warn;
+pub use auto_advance::{AutoAdvance, DEFAULT_VOICE_PADDING_MS};
+pub use clock::{FixedTimestep, GameClock, SubClock, Subsystem, DEFAULT_FIXED_STEP};
+pub use controls::{TimeControls, SPEED_PRESETS, STEP_DURATION};
+pub use dissolve::MaskDissolve;
+pub use playtime::{
+    Activity, Playtime, PlaytimeConfig, PlaytimeTracker, SessionStats, MAX_FRAME_PLAYTIME,
+};
+pub use presentation::{presented_frames, Completion, PresentedFrames};
 pub use tween::{Easing, Tween};
-pub use tweener::Tweener;
+pub use tweener::{MotionEnd, MotionWait, Tweener};
 
 
 /// A time value that can be used to store either a duration.
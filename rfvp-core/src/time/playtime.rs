@@ -0,0 +1,240 @@
+//! The time played, for the save screens and the content unlocked after some hours.
+//!
+//! It only runs while the player is playing: not while the window is unfocused or the
+//! game is paused, and in the system menus only if configured so. It follows the real
+//! time, skipping at a higher speed doesn't make it run faster.
+
+use std::{fmt, time::Duration};
+
+use serde::{Deserialize, Serialize};
+
+/// A longer frame is counted as this long: a stall, a suspended machine or the clock
+/// jumping forward isn't playtime
+pub const MAX_FRAME_PLAYTIME: Duration = Duration::from_millis(250);
+
+/// What the player is doing during a frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Activity {
+    Playing,
+    /// a system menu or the backlog is open
+    Menu,
+    Paused,
+    Unfocused,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PlaytimeConfig {
+    /// whether the time in the menus is played time
+    pub count_menus: bool,
+    /// the most a single frame adds
+    pub max_frame: Duration,
+}
+
+impl Default for PlaytimeConfig {
+    fn default() -> Self {
+        Self {
+            count_menus: true,
+            max_frame: MAX_FRAME_PLAYTIME,
+        }
+    }
+}
+
+/// The total time played, saved as milliseconds
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(from = "u64", into = "u64")]
+pub struct Playtime(Duration);
+
+impl Playtime {
+    pub const ZERO: Self = Self(Duration::ZERO);
+
+    pub fn from_secs(secs: u64) -> Self {
+        Self(Duration::from_secs(secs))
+    }
+
+    pub fn as_duration(self) -> Duration {
+        self.0
+    }
+
+    /// whole seconds, what the scripts see
+    pub fn as_secs(self) -> u64 {
+        self.0.as_secs()
+    }
+}
+
+impl From<u64> for Playtime {
+    fn from(millis: u64) -> Self {
+        Self(Duration::from_millis(millis))
+    }
+}
+
+impl From<Playtime> for u64 {
+    fn from(playtime: Playtime) -> Self {
+        playtime.0.as_millis() as u64
+    }
+}
+
+/// `h:mm:ss`, like the save screens show it
+impl fmt::Display for Playtime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let secs = self.as_secs();
+        write!(f, "{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+    }
+}
+
+/// Advances a [`Playtime`] by the time between the frames
+#[derive(Debug, Clone, Default)]
+pub struct PlaytimeTracker {
+    config: PlaytimeConfig,
+    /// the time of the previous frame
+    last: Option<Duration>,
+    /// played since the start of the process
+    session: Duration,
+}
+
+impl PlaytimeTracker {
+    pub fn new(config: PlaytimeConfig) -> Self {
+        Self {
+            config,
+            last: None,
+            session: Duration::ZERO,
+        }
+    }
+
+    pub fn config(&self) -> &PlaytimeConfig {
+        &self.config
+    }
+
+    /// the time played in this session, not saved
+    pub fn session(&self) -> Duration {
+        self.session
+    }
+
+    /// Called once per frame, `now` on a clock which should only go forward: going back
+    /// adds nothing, the next frame counts from there. Returns the time added.
+    pub fn update(
+        &mut self,
+        playtime: &mut Playtime,
+        now: Duration,
+        activity: Activity,
+    ) -> Duration {
+        let delta = match self.last.replace(now) {
+            Some(last) => now.saturating_sub(last),
+            None => Duration::ZERO,
+        };
+        let counts = match activity {
+            Activity::Playing => true,
+            Activity::Menu => self.config.count_menus,
+            Activity::Paused | Activity::Unfocused => false,
+        };
+        if !counts {
+            return Duration::ZERO;
+        }
+
+        let delta = delta.min(self.config.max_frame);
+        playtime.0 += delta;
+        self.session += delta;
+        delta
+    }
+}
+
+/// What happened in this session, for the debug overlay, not saved
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SessionStats {
+    pub lines_read: u32,
+    pub choices_made: u32,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FRAME: Duration = Duration::from_millis(20);
+
+    /// runs `frames` frames of [`FRAME`] from `now`, returns the time of the last one
+    fn run(
+        tracker: &mut PlaytimeTracker,
+        playtime: &mut Playtime,
+        now: Duration,
+        frames: u32,
+        activity: Activity,
+    ) -> Duration {
+        let mut now = now;
+        for _ in 0..frames {
+            now += FRAME;
+            tracker.update(playtime, now, activity);
+        }
+        now
+    }
+
+    #[test]
+    fn test_focus_and_pause() {
+        let mut tracker = PlaytimeTracker::new(PlaytimeConfig::default());
+        let mut playtime = Playtime::from_secs(3600);
+        // the first frame only sets the start
+        tracker.update(&mut playtime, Duration::ZERO, Activity::Playing);
+
+        let now = run(
+            &mut tracker,
+            &mut playtime,
+            Duration::ZERO,
+            50,
+            Activity::Playing,
+        );
+        assert_eq!(playtime.as_duration(), Duration::from_secs(3601));
+
+        let now = run(&mut tracker, &mut playtime, now, 100, Activity::Unfocused);
+        let now = run(&mut tracker, &mut playtime, now, 100, Activity::Paused);
+        assert_eq!(playtime.as_duration(), Duration::from_secs(3601));
+
+        // the frame after focus comes back only counts its own time
+        let now = run(&mut tracker, &mut playtime, now, 50, Activity::Playing);
+        assert_eq!(playtime.as_duration(), Duration::from_secs(3602));
+
+        run(&mut tracker, &mut playtime, now, 50, Activity::Menu);
+        assert_eq!(playtime.as_duration(), Duration::from_secs(3603));
+        assert_eq!(tracker.session(), Duration::from_secs(3));
+        assert_eq!(playtime.to_string(), "1:00:03");
+
+        let mut tracker = PlaytimeTracker::new(PlaytimeConfig {
+            count_menus: false,
+            ..Default::default()
+        });
+        let mut playtime = Playtime::ZERO;
+        run(
+            &mut tracker,
+            &mut playtime,
+            Duration::ZERO,
+            50,
+            Activity::Menu,
+        );
+        assert_eq!(playtime, Playtime::ZERO);
+    }
+
+    #[test]
+    fn test_clock_weirdness() {
+        let secs = Duration::from_secs;
+        let mut tracker = PlaytimeTracker::new(PlaytimeConfig::default());
+        let mut playtime = Playtime::ZERO;
+        tracker.update(&mut playtime, secs(100), Activity::Playing);
+
+        // back in time
+        assert_eq!(
+            tracker.update(&mut playtime, secs(90), Activity::Playing),
+            Duration::ZERO
+        );
+        assert_eq!(
+            tracker.update(&mut playtime, secs(90) + FRAME, Activity::Playing),
+            FRAME
+        );
+        // a jump forward is capped
+        assert_eq!(
+            tracker.update(&mut playtime, secs(3600), Activity::Playing),
+            MAX_FRAME_PLAYTIME
+        );
+        assert_eq!(playtime.as_duration(), FRAME + MAX_FRAME_PLAYTIME);
+
+        let saved = serde_yaml::to_string(&playtime).unwrap();
+        assert_eq!(saved.trim(), "270");
+        assert_eq!(serde_yaml::from_str::<Playtime>(&saved).unwrap(), playtime);
+    }
+}
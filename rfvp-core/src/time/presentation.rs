@@ -0,0 +1,100 @@
+//! Completion of what's shown on screen, exact to the frame.
+//!
+//! A movie or a transition finishing during an update has only prepared its last
+//! frame, the frame is seen once the render loop presents it. If the script moves on
+//! in the same update, it can replace the scene before that and the last frame is
+//! dropped. The render loop counts the frames it presents in [`PresentedFrames`], a
+//! [`Completion`] stays busy until a frame was presented after it completed.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// The number of frames presented so far, the generation of the next one
+#[derive(Debug, Default)]
+pub struct PresentedFrames(AtomicU64);
+
+impl PresentedFrames {
+    pub const fn new() -> Self {
+        Self(AtomicU64::new(0))
+    }
+
+    pub fn generation(&self) -> u64 {
+        self.0.load(Ordering::Acquire)
+    }
+
+    /// called by the render loop once a frame is on screen
+    pub fn presented(&self) {
+        self.0.fetch_add(1, Ordering::Release);
+    }
+}
+
+static PRESENTED_FRAMES: PresentedFrames = PresentedFrames::new();
+
+/// the frames presented by the window of the process
+pub fn presented_frames() -> &'static PresentedFrames {
+    &PRESENTED_FRAMES
+}
+
+/// Whether something shown on screen has finished, and was seen finishing
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Completion {
+    /// the generation of the frame showing the completed state
+    completed_at: Option<u64>,
+}
+
+impl Completion {
+    /// the state was updated to the end, completing again keeps the first frame
+    pub fn complete(&mut self, frames: &PresentedFrames) {
+        self.completed_at.get_or_insert(frames.generation());
+    }
+
+    /// whether the state reached the end, it may not be on screen yet
+    pub fn is_completed(&self) -> bool {
+        self.completed_at.is_some()
+    }
+
+    /// `true` until the frame showing the completed state was presented
+    pub fn is_busy(&self, frames: &PresentedFrames) -> bool {
+        match self.completed_at {
+            None => true,
+            Some(generation) => frames.generation() <= generation,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_three_frame_transition() {
+        const FRAMES: u32 = 3;
+        let frames = PresentedFrames::new();
+        let mut completion = Completion::default();
+        let mut progress = 0;
+        let mut busy_updates = 0;
+        let mut presented = Vec::new();
+
+        for _ in 0..10 {
+            // update: the transition advances, then the script polls it
+            if progress < FRAMES {
+                progress += 1;
+                if progress == FRAMES {
+                    completion.complete(&frames);
+                }
+            }
+            if !completion.is_busy(&frames) {
+                break;
+            }
+            busy_updates += 1;
+
+            // render
+            presented.push(progress);
+            frames.presented();
+        }
+
+        // busy while each frame of the transition is shown, the last one included
+        assert_eq!(busy_updates, FRAMES);
+        assert_eq!(presented, [1, 2, 3]);
+        assert!(completion.is_completed());
+    }
+}
@@ -1,4 +1,10 @@
-use std::collections::VecDeque;
+use std::{
+    collections::VecDeque,
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll, Waker},
+};
 
 use crate::time::{Ticks, Tween};
 
@@ -14,12 +20,52 @@ enum State {
     },
 }
 
+/// How an awaited motion ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MotionEnd {
+    /// Played to the end, or fast-forwarded to it.
+    Completed,
+    /// Interrupted by jumping to another value, see [`Tweener::fast_forward_to`].
+    Stopped,
+}
+
+#[derive(Default)]
+struct WaitState {
+    end: Option<MotionEnd>,
+    waker: Option<Waker>,
+}
+
+/// Resolves when all the motions queued on a [`Tweener`] have ended.
+///
+/// Created by [`Tweener::wait`]. The tweener wakes the task when it's updated
+/// past the end of the motion, so it can be awaited from any executor as long
+/// as the tweener keeps being updated.
+pub struct MotionWait {
+    state: Arc<Mutex<WaitState>>,
+}
+
+impl Future for MotionWait {
+    type Output = MotionEnd;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut state = self.state.lock().unwrap();
+        match state.end {
+            Some(end) => Poll::Ready(end),
+            None => {
+                state.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
 /// Holds a value and plays back tweens which smoothly
 /// adjust that value.
 pub struct Tweener {
     tween_queue: VecDeque<(Value, Tween)>,
     state: State,
     value: Value,
+    waiters: Vec<Arc<Mutex<WaitState>>>,
 }
 
 impl Tweener {
@@ -28,6 +74,7 @@ impl Tweener {
             tween_queue: VecDeque::new(),
             state: State::Idle,
             value,
+            waiters: Vec::new(),
         }
     }
 
@@ -51,6 +98,29 @@ impl Tweener {
         matches!(self.state, State::Idle)
     }
 
+    /// A future resolving once the tweener is idle, it's ready right away if it already is.
+    pub fn wait(&mut self) -> MotionWait {
+        let state = Arc::new(Mutex::new(WaitState::default()));
+        if self.is_idle() {
+            state.lock().unwrap().end = Some(MotionEnd::Completed);
+        } else {
+            // forget the futures which were dropped before the motion ended
+            self.waiters.retain(|waiter| Arc::strong_count(waiter) > 1);
+            self.waiters.push(state.clone());
+        }
+        MotionWait { state }
+    }
+
+    fn wake_waiters(&mut self, end: MotionEnd) {
+        for waiter in self.waiters.drain(..) {
+            let mut waiter = waiter.lock().unwrap();
+            waiter.end = Some(end);
+            if let Some(waker) = waiter.waker.take() {
+                waker.wake();
+            }
+        }
+    }
+
     /// Enqueues a new value to tween to.
     pub fn enqueue(&mut self, value: Value, tween: Tween) {
         match self.state {
@@ -100,6 +170,9 @@ impl Tweener {
                 self.value = values.1;
                 let remaining_time = *time - tween.duration;
                 self.next(remaining_time);
+                if self.is_idle() {
+                    self.wake_waiters(MotionEnd::Completed);
+                }
             } else {
                 self.value = Self::lerp(values.0, values.1, tween.value(*time));
             }
@@ -121,13 +194,17 @@ impl Tweener {
 
         self.state = State::Idle;
         self.value = value;
+        self.wake_waiters(MotionEnd::Completed);
     }
 
     /// Fast-forwards the tweener to the specified value.
+    ///
+    /// This stops the running motion, the futures waiting for it resolve with [`MotionEnd::Stopped`].
     pub fn fast_forward_to(&mut self, value: Value) {
         self.tween_queue.clear();
         self.state = State::Idle;
         self.value = value;
+        self.wake_waiters(MotionEnd::Stopped);
     }
 
     /// Enqueue a transition from the current value to the specified value, ignoring the previous queue (it's cleared).
@@ -136,3 +213,104 @@ impl Tweener {
         self.enqueue(value, tween);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::atomic::{AtomicUsize, Ordering},
+        task::Wake,
+    };
+
+    use super::*;
+
+    #[derive(Default)]
+    struct CountingWaker(AtomicUsize);
+
+    impl Wake for CountingWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn test_wait_resolves_at_the_end() {
+        let counter = Arc::new(CountingWaker::default());
+        let waker = Waker::from(counter.clone());
+        let mut cx = Context::from_waker(&waker);
+
+        let mut tweener = Tweener::new(0.0);
+        tweener.enqueue(100.0, Tween::linear(Ticks::from_u32(10)));
+        let mut wait = tweener.wait();
+
+        for _ in 0..9 {
+            assert_eq!(Pin::new(&mut wait).poll(&mut cx), Poll::Pending);
+            tweener.update(Ticks::from_u32(1));
+        }
+        assert_eq!(Pin::new(&mut wait).poll(&mut cx), Poll::Pending);
+        assert_eq!(counter.0.load(Ordering::SeqCst), 0);
+
+        tweener.update(Ticks::from_u32(1));
+        assert_eq!(counter.0.load(Ordering::SeqCst), 1);
+        assert_eq!(
+            Pin::new(&mut wait).poll(&mut cx),
+            Poll::Ready(MotionEnd::Completed)
+        );
+        assert_eq!(tweener.value(), 100.0);
+
+        // idle already
+        assert_eq!(
+            Pin::new(&mut tweener.wait()).poll(&mut cx),
+            Poll::Ready(MotionEnd::Completed)
+        );
+    }
+
+    /// values of a motion stepped with the frame times of an uneven frame rate
+    fn step_motion(from: f32, to: f32, duration: u32) -> Vec<f32> {
+        let mut tweener = Tweener::new(from);
+        tweener.enqueue(to, Tween::linear(Ticks::from_u32(duration)));
+        let mut values = vec![tweener.value()];
+        let mut frame = 0;
+        while !tweener.is_idle() {
+            let dt = if frame % 3 == 0 { 1.3 } else { 0.85 };
+            tweener.update(Ticks::from_f32(dt));
+            values.push(tweener.value());
+            frame += 1;
+        }
+        values
+    }
+
+    #[test]
+    fn test_sub_pixel_motion() {
+        // the x and y of a slow diagonal move, and a long pan: positions are never
+        // rounded to whole pixels, so no frame repeats a position or steps back
+        for (from, to) in [(0.0, 10.0), (0.0, 7.0), (1920.0, 0.0)] {
+            let values = step_motion(from, to, 100);
+            assert_eq!(*values.last().unwrap(), to);
+            let deltas: Vec<f32> = values.windows(2).map(|w| w[1] - w[0]).collect();
+            assert!(deltas
+                .iter()
+                .all(|d| *d != 0.0 && d.signum() == (to - from).signum()));
+        }
+    }
+
+    #[test]
+    fn test_wait_cancelled() {
+        let counter = Arc::new(CountingWaker::default());
+        let waker = Waker::from(counter.clone());
+        let mut cx = Context::from_waker(&waker);
+
+        let mut tweener = Tweener::new(0.0);
+        tweener.enqueue(100.0, Tween::linear(Ticks::from_u32(10)));
+        let mut wait = tweener.wait();
+        assert_eq!(Pin::new(&mut wait).poll(&mut cx), Poll::Pending);
+
+        // replacing the motion stops the awaited one
+        tweener.update(Ticks::from_u32(5));
+        tweener.enqueue_now(0.0, Tween::linear(Ticks::from_u32(10)));
+        assert_eq!(counter.0.load(Ordering::SeqCst), 1);
+        assert_eq!(
+            Pin::new(&mut wait).poll(&mut cx),
+            Poll::Ready(MotionEnd::Stopped)
+        );
+    }
+}
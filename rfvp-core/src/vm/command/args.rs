@@ -0,0 +1,213 @@
+//! Typed access to the arguments of a syscall.
+//!
+//! The failure messages name the syscall, the argument index, the expected
+//! type and a preview of the actual value, which makes porting scripts much
+//! less painful than a silent nil or a bare unwrap.
+
+use anyhow::{bail, Result};
+
+use crate::format::scenario::variant::{Table, Variant};
+
+/// longest string shown in an error message
+const PREVIEW_LEN: usize = 32;
+
+fn type_name(value: &Variant) -> &'static str {
+    match value {
+        Variant::Nil => "nil",
+        Variant::True => "true",
+        Variant::Int(_) => "int",
+        Variant::Float(_) => "float",
+        Variant::String(_) | Variant::ConstString(_, _) => "string",
+        Variant::Table(_) => "table",
+        Variant::SavedStackInfo(_) => "stack frame",
+    }
+}
+
+fn preview(value: &Variant) -> String {
+    match value {
+        Variant::Int(i) => i.to_string(),
+        Variant::Float(f) => f.to_string(),
+        Variant::String(s) | Variant::ConstString(s, _) => {
+            if s.chars().count() > PREVIEW_LEN {
+                format!("{:?}...", s.chars().take(PREVIEW_LEN).collect::<String>())
+            } else {
+                format!("{:?}", s)
+            }
+        }
+        other => type_name(other).to_string(),
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Args<'a> {
+    syscall: &'a str,
+    args: &'a [Variant],
+}
+
+impl<'a> Args<'a> {
+    pub fn new(syscall: &'a str, args: &'a [Variant]) -> Self {
+        Self { syscall, args }
+    }
+
+    pub fn syscall(&self) -> &'a str {
+        self.syscall
+    }
+
+    pub fn len(&self) -> usize {
+        self.args.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.args.is_empty()
+    }
+
+    /// the raw argument, nil if the script passed fewer arguments
+    pub fn get(&self, n: usize) -> &'a Variant {
+        static NIL: Variant = Variant::Nil;
+        self.args.get(n).unwrap_or(&NIL)
+    }
+
+    fn mismatch<T>(&self, n: usize, expected: &str) -> Result<T> {
+        let value = self.get(n);
+        bail!(
+            "{}: argument {} expected {}, got {} {}",
+            self.syscall,
+            n,
+            expected,
+            type_name(value),
+            preview(value)
+        )
+    }
+
+    pub fn int(&self, n: usize) -> Result<i32> {
+        match self.get(n) {
+            Variant::Int(i) => Ok(*i),
+            _ => self.mismatch(n, "int"),
+        }
+    }
+
+    /// nil is treated as an omitted argument
+    pub fn opt_int(&self, n: usize) -> Result<Option<i32>> {
+        match self.get(n) {
+            Variant::Nil => Ok(None),
+            _ => self.int(n).map(Some),
+        }
+    }
+
+    pub fn int_or(&self, n: usize, default: i32) -> Result<i32> {
+        Ok(self.opt_int(n)?.unwrap_or(default))
+    }
+
+    /// integers are accepted as well, scripts pass them for float parameters all the time
+    pub fn float(&self, n: usize) -> Result<f32> {
+        match self.get(n) {
+            Variant::Float(f) => Ok(*f),
+            Variant::Int(i) => Ok(*i as f32),
+            _ => self.mismatch(n, "float"),
+        }
+    }
+
+    pub fn opt_float(&self, n: usize) -> Result<Option<f32>> {
+        match self.get(n) {
+            Variant::Nil => Ok(None),
+            _ => self.float(n).map(Some),
+        }
+    }
+
+    /// strings are already decoded with the scenario NLS when they are pushed
+    pub fn str_lossy(&self, n: usize) -> Result<&'a str> {
+        match self.get(n) {
+            Variant::String(s) | Variant::ConstString(s, _) => Ok(s.as_str()),
+            _ => self.mismatch(n, "string"),
+        }
+    }
+
+    pub fn opt_str(&self, n: usize) -> Result<Option<&'a str>> {
+        match self.get(n) {
+            Variant::Nil => Ok(None),
+            _ => self.str_lossy(n).map(Some),
+        }
+    }
+
+    pub fn table(&self, n: usize) -> Result<&'a Table> {
+        match self.get(n) {
+            Variant::Table(t) => Ok(t),
+            _ => self.mismatch(n, "table"),
+        }
+    }
+
+    pub fn opt_table(&self, n: usize) -> Result<Option<&'a Table>> {
+        match self.get(n) {
+            Variant::Nil => Ok(None),
+            _ => self.table(n).map(Some),
+        }
+    }
+
+    /// nil is false, everything else is true
+    pub fn bool(&self, n: usize) -> bool {
+        self.get(n).canbe_true()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vm::command::Command;
+
+    fn args() -> Vec<Variant> {
+        vec![
+            Variant::Int(3),
+            Variant::Float(0.5),
+            Variant::String("bg/sky".to_string()),
+            Variant::Nil,
+            Variant::Table(Table::new()),
+        ]
+    }
+
+    #[test]
+    fn test_extractors() {
+        let values = args();
+        let args = Args::new("Test", &values);
+        assert_eq!(args.int(0).unwrap(), 3);
+        assert_eq!(args.float(0).unwrap(), 3.0);
+        assert_eq!(args.float(1).unwrap(), 0.5);
+        assert_eq!(args.str_lossy(2).unwrap(), "bg/sky");
+        assert!(args.table(4).is_ok());
+
+        assert_eq!(args.opt_int(3).unwrap(), None);
+        assert_eq!(args.int_or(3, 7).unwrap(), 7);
+        assert_eq!(args.int_or(9, 7).unwrap(), 7);
+        assert_eq!(args.opt_str(3).unwrap(), None);
+        assert!(args.opt_table(3).unwrap().is_none());
+        assert_eq!(args.opt_float(1).unwrap(), Some(0.5));
+
+        assert!(args.bool(0));
+        assert!(!args.bool(3));
+
+        assert!(args.int(1).is_err());
+        assert!(args.int_or(2, 0).is_err());
+        assert!(args.str_lossy(0).is_err());
+        assert!(args.table(2).is_err());
+    }
+
+    #[test]
+    fn test_error_message() {
+        let command = Command::GraphLoad {
+            args: vec![Variant::String("bg/sky".to_string()), Variant::Int(1)],
+        };
+        let args = command.args().unwrap();
+        let err = args.int(0).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "GraphLoad: argument 0 expected int, got string \"bg/sky\""
+        );
+
+        let long = Variant::String("a".repeat(40));
+        let values = [long];
+        let err = Args::new("TextPrint", &values).int(0).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!("TextPrint: argument 0 expected int, got string {:?}...", "a".repeat(32))
+        );
+    }
+}
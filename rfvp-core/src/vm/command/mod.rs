@@ -1,159 +1,208 @@
 //! Defines the commands that can be produced by the VM and executed by the engine.
 use crate::format::scenario::variant::Variant;
 
+pub mod args;
+pub mod strings;
 pub mod types;
 
-#[allow(non_camel_case_types, clippy::upper_case_acronyms)]
-#[derive(Debug)]
-pub enum Command {
-    AudioLoad {args: Vec<Variant>},
-    AudioPlay {args: Vec<Variant>},
-    AudioSilentOn {args: Vec<Variant>},
-    AudioState {args: Vec<Variant>},
-    AudioStop {args: Vec<Variant>},
-    AudioType {args: Vec<Variant>},
-    AudioVol {args: Vec<Variant>},
-    ColorSet {args: Vec<Variant>},
-    ControlMask {args: Vec<Variant>},
-    ControlPulse {args: Vec<Variant>},
-    CursorChange {args: Vec<Variant>},
-    CursorMove {args: Vec<Variant>},
-    CursorShow {args: Vec<Variant>},
-    Debmess {args: Vec<Variant>},
-    Dissolve {args: Vec<Variant>},
-    DissolveWait {args: Vec<Variant>},
-    ExitDialog {args: Vec<Variant>},
-    ExitMode {args: Vec<Variant>},
-    FlagGet {args: Vec<Variant>},
-    FlagSet {args: Vec<Variant>},
-    FloatToInt {args: Vec<Variant>},
-    GaijiLoad {args: Vec<Variant>},
-    GraphLoad {args: Vec<Variant>},
-    GraphRGB {args: Vec<Variant>},
-    IntToText {args: Vec<Variant>},
-    HistoryGet {args: Vec<Variant>},
-    HistorySet {args: Vec<Variant>},
-    InputFlash {args: Vec<Variant>},
-    InputGetCursIn {args: Vec<Variant>},
-    InputGetCursX {args: Vec<Variant>},
-    InputGetCursY {args: Vec<Variant>},
-    InputGetDown {args: Vec<Variant>},
-    InputGetEvent {args: Vec<Variant>},
-    InputGetRepeat {args: Vec<Variant>},
-    InputGetState {args: Vec<Variant>},
-    InputGetUp {args: Vec<Variant>},
-    InputGetWheel {args: Vec<Variant>},
-    InputSetClick {args: Vec<Variant>},
-    LipAnim {args: Vec<Variant>},
-    LipSync {args: Vec<Variant>},
-    Load {args: Vec<Variant>},
-    MenuMessSkip {args: Vec<Variant>},
-    MotionAlpha {args: Vec<Variant>},
-    MotionAlphaStop {args: Vec<Variant>},
-    MotionAlphaTest {args: Vec<Variant>},
-    MotionAnim {args: Vec<Variant>},
-    MotionAnimStop {args: Vec<Variant>},
-    MotionAnimTest {args: Vec<Variant>},
-    MotionMove {args: Vec<Variant>},
-    MotionMoveStop {args: Vec<Variant>},
-    MotionMoveTest {args: Vec<Variant>},
-    MotionMoveR {args: Vec<Variant>},
-    MotionMoveRStop {args: Vec<Variant>},
-    MotionMoveRTest {args: Vec<Variant>},
-    MotionMoveS2 {args: Vec<Variant>},
-    MotionMoveS2Stop {args: Vec<Variant>},
-    MotionMoveS2Test {args: Vec<Variant>},
-    MotionMoveZ {args: Vec<Variant>},
-    MotionMoveZStop {args: Vec<Variant>},
-    MotionMoveZTest {args: Vec<Variant>},
-    MotionPause {args: Vec<Variant>},
-    Movie {args: Vec<Variant>},
-    MovieState {args: Vec<Variant>},
-    MovieStop {args: Vec<Variant>},
-    PartsAssign {args: Vec<Variant>},
-    PartsLoad {args: Vec<Variant>},
-    PartsMotion {args: Vec<Variant>},
-    PartsMotionPause {args: Vec<Variant>},
-    PartsMotionStop {args: Vec<Variant>},
-    PartsMotionTest {args: Vec<Variant>},
-    PartsRGB {args: Vec<Variant>},
-    PartsSelect {args: Vec<Variant>},
-    PrimExitGroup {args: Vec<Variant>},
-    PrimGroupIn {args: Vec<Variant>},
-    PrimGroupMove {args: Vec<Variant>},
-    PrimGroupOut {args: Vec<Variant>},
-    PrimHit {args: Vec<Variant>},
-    PrimSetAlpha {args: Vec<Variant>},
-    PrimSetBlend {args: Vec<Variant>},
-    PrimSetDraw {args: Vec<Variant>},
-    PrimSetNull {args: Vec<Variant>},
-    PrimSetOP {args: Vec<Variant>},
-    PrimSetRS {args: Vec<Variant>},
-    PrimSetRS2 {args: Vec<Variant>},
-    PrimSetSnow {args: Vec<Variant>},
-    PrimSetSprt {args: Vec<Variant>},
-    PrimSetText {args: Vec<Variant>},
-    PrimSetTile {args: Vec<Variant>},
-    PrimSetUV {args: Vec<Variant>},
-    PrimSetWH {args: Vec<Variant>},
-    PrimSetXY {args: Vec<Variant>},
-    PrimSetZ {args: Vec<Variant>},
-    Rand {args: Vec<Variant>},
-    SaveCreate {args: Vec<Variant>},
-    SaveThumbSize {args: Vec<Variant>},
-    SaveData {args: Vec<Variant>},
-    SaveWrite {args: Vec<Variant>},
-    Snow {args: Vec<Variant>},
-    SnowStart {args: Vec<Variant>},
-    SnowStop {args: Vec<Variant>},
-    SoundLoad {args: Vec<Variant>},
-    SoundMasterVol {args: Vec<Variant>},
-    SoundPlay {args: Vec<Variant>},
-    SoundSilentOn {args: Vec<Variant>},
-    SoundStop {args: Vec<Variant>},
-    SoundType {args: Vec<Variant>},
-    SoundTypeVol {args: Vec<Variant>},
-    SoundVol {args: Vec<Variant>},
-    SysAtSkipName {args: Vec<Variant>},
-    SysProjFolder {args: Vec<Variant>},
-    TextBuff {args: Vec<Variant>},
-    TextClear {args: Vec<Variant>},
-    TextColor {args: Vec<Variant>},
-    TextFont {args: Vec<Variant>},
-    TextFontCount {args: Vec<Variant>},
-    TextFontGet {args: Vec<Variant>},
-    TextFontName {args: Vec<Variant>},
-    TextFontSet {args: Vec<Variant>},
-    TextFormat {args: Vec<Variant>},
-    TextFunction {args: Vec<Variant>},
-    TextOutSize {args: Vec<Variant>},
-    TextPause {args: Vec<Variant>},
-    TextPos {args: Vec<Variant>},
-    TextPrint {args: Vec<Variant>},
-    TextRepaint {args: Vec<Variant>},
-    TextShadowDist {args: Vec<Variant>},
-    TextSize {args: Vec<Variant>},
-    TextSkip {args: Vec<Variant>},
-    TextSpace {args: Vec<Variant>},
-    TextSpeed {args: Vec<Variant>},
-    TextSuspendChr {args: Vec<Variant>},
-    TextTest {args: Vec<Variant>},
-    ThreadExit {args: Vec<Variant>},
-    ThreadNext {args: Vec<Variant>},
-    ThreadRaise {args: Vec<Variant>},
-    ThreadSleep {args: Vec<Variant>},
-    ThreadStart {args: Vec<Variant>},
-    ThreadWait {args: Vec<Variant>},
-    TimerGet {args: Vec<Variant>},
-    TimerSet {args: Vec<Variant>},
-    TimerSuspend {args: Vec<Variant>},
-    TitleMenu {args: Vec<Variant>},
-    V3DMotion {args: Vec<Variant>},
-    V3DMotionPause {args: Vec<Variant>},
-    V3DMotionStop {args: Vec<Variant>},
-    V3DMotionTest {args: Vec<Variant>},
-    V3DSet {args: Vec<Variant>},
-    WindowMode {args: Vec<Variant>},
+pub use args::Args;
+
+/// Declares the commands of the syscalls the engine knows, each named after its syscall.
+/// The list is the single table of the names: the enum, the lookup by name and
+/// [`Command::name`] are all generated from it.
+macro_rules! commands {
+    ($($name:ident),* $(,)?) => {
+        #[allow(non_camel_case_types, clippy::upper_case_acronyms)]
+        #[derive(Debug)]
+        pub enum Command {
+            $($name { args: Vec<Variant> },)*
+
+            /// stub for a syscall the engine doesn't know, only produced in tolerant mode
+            /// and executed as a no-op returning nil
+            Unresolved { name: String },
+        }
+
+        impl Command {
+            /// map a syscall imported by the script to the command, `None` for unknown names
+            pub fn from_syscall(name: &str, args: Vec<Variant>) -> Option<Command> {
+                $(
+                    if name == stringify!($name) {
+                        return Some(Command::$name { args });
+                    }
+                )*
+                None
+            }
+
+            /// name of the syscall which produced the command
+            pub fn name(&self) -> &str {
+                match self {
+                    $(Command::$name { .. } => stringify!($name),)*
+                    Command::Unresolved { name } => name,
+                }
+            }
+
+            /// typed access to the arguments, `None` for the unresolved stub
+            pub fn args(&self) -> Option<Args<'_>> {
+                let args = match self {
+                    $(Command::$name { args } => args,)*
+                    Command::Unresolved { .. } => return None,
+                };
+
+                Some(Args::new(self.name(), args))
+            }
+        }
+    };
+}
+
+commands! {
+    AudioLoad,
+    AudioPlay,
+    AudioSilentOn,
+    AudioState,
+    AudioStop,
+    AudioType,
+    AudioVol,
+    ColorSet,
+    ControlMask,
+    ControlPulse,
+    CursorChange,
+    CursorMove,
+    CursorShow,
+    Debmess,
+    Dissolve,
+    DissolveWait,
+    ExitDialog,
+    ExitMode,
+    FlagGet,
+    FlagSet,
+    FloatToInt,
+    GaijiLoad,
+    GraphLoad,
+    GraphRGB,
+    IntToText,
+    HistoryGet,
+    HistorySet,
+    InputFlash,
+    InputGetCursIn,
+    InputGetCursX,
+    InputGetCursY,
+    InputGetDown,
+    InputGetEvent,
+    InputGetRepeat,
+    InputGetState,
+    InputGetUp,
+    InputGetWheel,
+    InputSetClick,
+    LipAnim,
+    LipSync,
+    Load,
+    MenuMessSkip,
+    MotionAlpha,
+    MotionAlphaStop,
+    MotionAlphaTest,
+    MotionAnim,
+    MotionAnimStop,
+    MotionAnimTest,
+    MotionMove,
+    MotionMoveStop,
+    MotionMoveTest,
+    MotionMoveR,
+    MotionMoveRStop,
+    MotionMoveRTest,
+    MotionMoveS2,
+    MotionMoveS2Stop,
+    MotionMoveS2Test,
+    MotionMoveZ,
+    MotionMoveZStop,
+    MotionMoveZTest,
+    MotionPause,
+    Movie,
+    MovieState,
+    MovieStop,
+    PartsAssign,
+    PartsLoad,
+    PartsMotion,
+    PartsMotionPause,
+    PartsMotionStop,
+    PartsMotionTest,
+    PartsRGB,
+    PartsSelect,
+    PrimExitGroup,
+    PrimGroupIn,
+    PrimGroupMove,
+    PrimGroupOut,
+    PrimHit,
+    PrimSetAlpha,
+    PrimSetBlend,
+    PrimSetDraw,
+    PrimSetNull,
+    PrimSetOP,
+    PrimSetRS,
+    PrimSetRS2,
+    PrimSetSnow,
+    PrimSetSprt,
+    PrimSetText,
+    PrimSetTile,
+    PrimSetUV,
+    PrimSetWH,
+    PrimSetXY,
+    PrimSetZ,
+    Rand,
+    SaveCreate,
+    SaveThumbSize,
+    SaveData,
+    SaveWrite,
+    Snow,
+    SnowStart,
+    SnowStop,
+    SoundLoad,
+    SoundMasterVol,
+    SoundPlay,
+    SoundSilentOn,
+    SoundStop,
+    SoundType,
+    SoundTypeVol,
+    SoundVol,
+    SysAtSkipName,
+    SysProjFolder,
+    TextBuff,
+    TextClear,
+    TextColor,
+    TextFont,
+    TextFontCount,
+    TextFontGet,
+    TextFontName,
+    TextFontSet,
+    TextFormat,
+    TextFunction,
+    TextOutSize,
+    TextPause,
+    TextPos,
+    TextPrint,
+    TextRepaint,
+    TextShadowDist,
+    TextSize,
+    TextSkip,
+    TextSpace,
+    TextSpeed,
+    TextSuspendChr,
+    TextTest,
+    ThreadExit,
+    ThreadNext,
+    ThreadRaise,
+    ThreadSleep,
+    ThreadStart,
+    ThreadWait,
+    TimerGet,
+    TimerSet,
+    TimerSuspend,
+    TitleMenu,
+    V3DMotion,
+    V3DMotionPause,
+    V3DMotionStop,
+    V3DMotionTest,
+    V3DSet,
+    WindowMode,
 }
 
 #[derive(Debug)]
@@ -169,4 +218,28 @@ pub enum CommandResult {
     None,
     /// Write back a value to R0 (aka the return value)
     WriteR0(Variant),
+    /// Call a routine of the script before resuming, as if the script called it right
+    /// after the syscall. What the routine returns is the return value of the syscall.
+    CallSubroutine { target: u32, args: Vec<Variant> },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_names() {
+        for name in ["AudioLoad", "IntToText", "InputSetClick", "WindowMode"] {
+            let command = Command::from_syscall(name, vec![Variant::Int(1)]).unwrap();
+            assert_eq!(command.name(), name);
+            assert_eq!(command.args().unwrap().syscall(), name);
+        }
+        assert!(Command::from_syscall("audioload", Vec::new()).is_none());
+
+        let unresolved = Command::Unresolved {
+            name: "Foo".to_string(),
+        };
+        assert_eq!(unresolved.name(), "Foo");
+        assert!(unresolved.args().is_none());
+    }
 }
@@ -0,0 +1,148 @@
+//! The string helpers of the scripts: the length and the substrings by character, and
+//! the formatting of numbers.
+//!
+//! The strings on the VM stack are decoded with the NLS of the script when they are
+//! pushed, so the characters counted here are the ones of the script: a double byte
+//! Shift-JIS or GBK character is one character, never cut in half. The same indices on
+//! the encoded bytes are [`Nls::char_slice`](crate::format::scenario::Nls::char_slice).
+//!
+//! The results are new strings, encoded with their null terminator when they are written
+//! back like any other string.
+
+use crate::{
+    format::scenario::{variant::Variant, MAX_PUSH_STRING_LEN},
+    vm::command::{Args, Command},
+};
+
+/// the number of characters of `s`
+pub fn char_length(s: &str) -> usize {
+    s.chars().count()
+}
+
+/// The `len` characters of `s` from the character `start`, to the end with `None`.
+/// Out of range indices give an empty string like in the original engine, not an error:
+/// a negative or too large start, or a length of 0 or less. A length past the end is
+/// clamped to it.
+pub fn substring(s: &str, start: i32, len: Option<i32>) -> String {
+    let (Ok(start), Ok(len)) = (
+        usize::try_from(start),
+        usize::try_from(len.unwrap_or(i32::MAX)),
+    ) else {
+        return String::new();
+    };
+    s.chars().skip(start).take(len).collect()
+}
+
+/// `value` in decimal, padded on the left with zeros to `width` characters. The minus sign
+/// comes before the zeros and counts in the width, like `printf("%05d")`.
+pub fn int_to_text(value: i32, width: usize) -> String {
+    // a string longer than that can't be pushed back anyway
+    let width = width.min(MAX_PUSH_STRING_LEN);
+    format!("{:0width$}", value, width = width)
+}
+
+/// `IntToText(value, width)`, width nil for no padding.
+///
+/// The sysdesc of the games declares it with 2 arguments, and the scripts build the
+/// asset paths with it: `"BGM2/" + IntToText(n, 3)`, `"voice/" + IntToText(n, 9)`. The
+/// file names are zero padded, so the padding is zeros.
+fn int_to_text_syscall(args: Args) -> Variant {
+    let text = args.int(0).and_then(|value| {
+        let width = args.int_or(1, 0)?.max(0) as usize;
+        Ok(int_to_text(value, width))
+    });
+    match text {
+        Ok(text) => Variant::String(text),
+        Err(e) => {
+            // a wrong argument in a script shouldn't stop it, the original engine doesn't
+            log::warn!("{:#}, returning an empty string", e);
+            Variant::String(String::new())
+        }
+    }
+}
+
+/// The result of a string helper syscall, `None` for the other commands. They only compute
+/// a value, the VM answers them without going through the engine.
+pub fn evaluate(command: &Command) -> Option<Variant> {
+    let args = command.args()?;
+    match command {
+        Command::IntToText { .. } => Some(int_to_text_syscall(args)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::format::scenario::Nls;
+
+    #[test]
+    fn test_char_length() {
+        assert_eq!(char_length(""), 0);
+        assert_eq!(char_length("Alice"), 5);
+        assert_eq!(char_length("名前はｱﾘｽ"), 6);
+        assert_eq!(char_length("爱丽丝A"), 4);
+
+        // as many as the encoded characters, not the bytes
+        for (nls, name) in [(Nls::ShiftJIS, "右代宮戦人"), (Nls::GBK, "右代宫战人")] {
+            let encoded = nls.encode(name);
+            assert_eq!(encoded.len(), 10);
+            assert_eq!(char_length(&nls.decode(&encoded)), 5);
+            assert_eq!(nls.char_count(&encoded), 5);
+        }
+    }
+
+    #[test]
+    fn test_substring() {
+        let sjis = "右代宮ｱﾘｽA";
+        assert_eq!(substring(sjis, 0, Some(3)), "右代宮");
+        // odd indices land on characters, not on the middle of a double byte one
+        assert_eq!(substring(sjis, 1, Some(3)), "代宮ｱ");
+        assert_eq!(substring(sjis, 3, None), "ｱﾘｽA");
+        assert_eq!(substring(sjis, 6, Some(1)), "A");
+        // past the end is clamped, then empty
+        assert_eq!(substring(sjis, 5, Some(100)), "ｽA");
+        assert_eq!(substring(sjis, 7, Some(1)), "");
+        assert_eq!(substring(sjis, 100, None), "");
+        // out of range indices are empty, not errors
+        assert_eq!(substring(sjis, -1, Some(2)), "");
+        assert_eq!(substring(sjis, 0, Some(0)), "");
+        assert_eq!(substring(sjis, 0, Some(-3)), "");
+        assert_eq!(substring("", 0, None), "");
+
+        // the same characters as the slice of the encoded bytes
+        let gbk = "二〇二四年三月";
+        let encoded = Nls::GBK.encode(gbk);
+        for start in 0..9 {
+            for len in 0..9 {
+                let slice = Nls::GBK.char_slice(&encoded, start, len);
+                assert_eq!(
+                    substring(gbk, start as i32, Some(len as i32)),
+                    Nls::GBK.decode(slice)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_int_to_text() {
+        assert_eq!(int_to_text(42, 0), "42");
+        assert_eq!(int_to_text(42, 4), "0042");
+        assert_eq!(int_to_text(-42, 5), "-0042");
+        assert_eq!(int_to_text(12345, 3), "12345");
+        assert_eq!(int_to_text(i32::MIN, 0), "-2147483648");
+        assert_eq!(int_to_text(7, 100_000).len(), MAX_PUSH_STRING_LEN);
+
+        let command = |args: Vec<Variant>| Command::IntToText { args };
+        let text = |command: Command| {
+            let value = evaluate(&command).unwrap();
+            value.as_string().cloned().unwrap()
+        };
+        assert_eq!(text(command(vec![Variant::Int(3), Variant::Int(3)])), "003");
+        assert_eq!(text(command(vec![Variant::Int(3), Variant::Nil])), "3");
+        // a malformed call gives an empty string instead of stopping the script
+        assert_eq!(text(command(vec![Variant::String("3".to_string())])), "");
+        assert_eq!(text(command(vec![Variant::Int(3), Variant::True])), "");
+        assert!(evaluate(&Command::Rand { args: vec![] }).is_none());
+    }
+}
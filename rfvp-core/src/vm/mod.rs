@@ -1,38 +1,127 @@
 pub mod command;
+pub mod matrix;
 
-use anyhow::Result;
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
 use tracing::{instrument, trace};
 
-use crate::vm::command::Command;
+use crate::vm::command::{Command, CommandResult};
 use std::cell::{RefCell, RefMut};
+use std::time::{Duration, Instant};
 
 use crate::{
     format::scenario::{
         context::{
-            Context, CONTEXT_STATUS_NONE, CONTEXT_STATUS_RUNNING, CONTEXT_STATUS_SLEEP,
-            CONTEXT_STATUS_WAIT,
+            Context, ContextSnapshot, CONTEXT_STATUS_NONE, CONTEXT_STATUS_RUNNING,
+            CONTEXT_STATUS_SLEEP, CONTEXT_STATUS_WAIT,
         },
+        global::Global,
+        instructions::{Opcode, OPCODE_COUNT},
+        variant::Variant,
         Scenario,
     },
-    vm::command::CommandResult,
+    vm::command::strings,
 };
 
+/// What integer arithmetic does when the result doesn't fit into an i32
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum IntOverflow {
+    /// wrap around in two's complement, like the original engine
+    #[default]
+    Wrap,
+    /// clamp to `i32::MIN`/`i32::MAX`
+    Saturate,
+    /// produce nil, to make overflows visible when debugging scripts
+    Nil,
+}
+
+/// Instructions run between two clock checks of [`Scripter::run_for_duration`]
+const DURATION_CHECK_BUDGET: u32 = 256;
+
+/// The wall time a thread may run for in a frame, it carries on in the next frame after that
+pub const THREAD_FRAME_BUDGET: Duration = Duration::from_millis(4);
+
+/// Why [`Scripter::run_for`] returned
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RunOutcome {
+    /// the thread gave up its time slice, e.g. waiting on a syscall
+    Yielded,
+    /// the thread isn't running anymore, or returned from its entry function
+    Halted,
+    /// the budget was used up while the thread could still run
+    BudgetExhausted,
+}
+
+/// What [`Scripter::run_until_yield`] did
+#[derive(Debug)]
+pub struct YieldReport {
+    pub outcome: RunOutcome,
+    /// the instructions executed, syscalls included
+    pub steps: u32,
+    /// the syscalls other than the thread controls, in order
+    pub commands: Vec<Command>,
+}
+
+/// The threads of the script, to resume it from a save
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VmSnapshot {
+    current_id: u32,
+    /// the threads which were started, by id
+    threads: Vec<(u32, ContextSnapshot)>,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct VmConfig {
+    pub int_overflow: IntOverflow,
+}
+
 pub struct Scripter {
     /// Vm execution context
     pub contexts: Vec<RefCell<Context>>,
+    config: VmConfig,
     current_id: u32,
     thread_break: bool,
+    /// executions per opcode, only allocated while profiling
+    opcode_histogram: Option<Box<[u64; OPCODE_COUNT]>>,
 }
 
 impl Scripter {
     pub fn new() -> Self {
+        Self::with_config(VmConfig::default())
+    }
+
+    pub fn with_config(config: VmConfig) -> Self {
+        let mut context = Context::new(0);
+        context.set_config(config);
         Self {
-            contexts: vec![RefCell::new(Context::new(0)); 32],
+            contexts: vec![RefCell::new(context); 32],
+            config,
             current_id: 0,
             thread_break: false,
+            opcode_histogram: None,
         }
     }
 
+    pub fn config(&self) -> VmConfig {
+        self.config
+    }
+
+    fn new_context(&self, addr: u32) -> Context {
+        let mut context = Context::new(addr);
+        context.set_config(self.config);
+        context
+    }
+
+    /// count the executed opcodes, enabling again resets the counts
+    pub fn enable_profiling(&mut self, enable: bool) {
+        self.opcode_histogram = enable.then(|| Box::new([0; OPCODE_COUNT]));
+    }
+
+    /// executions per opcode since profiling was enabled, indexed by the opcode value
+    pub fn opcode_histogram(&self) -> Option<&[u64; OPCODE_COUNT]> {
+        self.opcode_histogram.as_deref()
+    }
+
     pub fn get_current_id(&self) -> u32 {
         self.current_id
     }
@@ -49,17 +138,18 @@ impl Scripter {
         self.thread_break = should_break;
     }
 
+    /// starting the main thread (id 0) stops every other thread first
     pub fn thread_start(&mut self, id: u32, addr: u32) {
         if id == 0 {
-            for _i in 0..self.contexts.len() {
-                let mut context = Context::new(0);
+            for i in 0..self.contexts.len() {
+                let mut context = self.new_context(0);
                 context.set_status(CONTEXT_STATUS_NONE);
                 context.set_should_break(true);
-                self.contexts[id as usize] = RefCell::new(context);
+                self.contexts[i] = RefCell::new(context);
             }
         }
 
-        let mut context = Context::new(addr);
+        let mut context = self.new_context(addr);
         context.set_status(CONTEXT_STATUS_RUNNING);
         self.contexts[id as usize] = RefCell::new(context);
     }
@@ -125,7 +215,7 @@ impl Scripter {
 
         if id == 0 {
             for _i in 0..self.contexts.len() {
-                let mut ctx = Context::new(0);
+                let mut ctx = self.new_context(0);
                 ctx.set_status(CONTEXT_STATUS_NONE);
                 ctx.set_should_break(true);
                 self.contexts[id as usize] = RefCell::new(ctx);
@@ -133,7 +223,7 @@ impl Scripter {
 
             self.thread_break = true;
         } else {
-            let mut ctx = Context::new(0);
+            let mut ctx = self.new_context(0);
             ctx.set_status(CONTEXT_STATUS_NONE);
             ctx.set_should_break(true);
             self.contexts[id as usize] = RefCell::new(ctx);
@@ -144,8 +234,75 @@ impl Scripter {
         self.contexts[id as usize].borrow_mut()
     }
 
+    /// (re)start the script at `entry_point`, also used to jump to another scene.
+    /// only the threads are reset, the globals are kept.
     pub fn start_main(&mut self, entry_point: u32) {
         self.thread_start(0, entry_point);
+        self.current_id = 0;
+    }
+
+    /// The started threads, see [`Scripter::restore`]
+    pub fn snapshot(&self) -> VmSnapshot {
+        let threads = self
+            .contexts
+            .iter()
+            .enumerate()
+            .map(|(id, context)| (id as u32, context.borrow()))
+            .filter(|(_, context)| context.get_status() != CONTEXT_STATUS_NONE)
+            .map(|(id, context)| (id, context.snapshot()))
+            .collect();
+        VmSnapshot {
+            current_id: self.current_id,
+            threads,
+        }
+    }
+
+    /// Resumes the threads of a snapshot, the others are stopped. The globals are restored
+    /// on their own.
+    pub fn restore(&mut self, snapshot: &VmSnapshot) -> Result<()> {
+        let count = self.contexts.len() as u32;
+        if let Some(&(id, _)) = snapshot.threads.iter().find(|(id, _)| *id >= count) {
+            bail!("Invalid VM snapshot: thread {} of {}", id, count);
+        }
+
+        for context in &self.contexts {
+            let mut context = context.borrow_mut();
+            context.reset(0);
+            context.set_should_break(true);
+        }
+        for (id, thread) in &snapshot.threads {
+            self.get_thread(*id).restore(thread)?;
+        }
+        self.current_id = snapshot.current_id.min(count - 1);
+        self.thread_break = false;
+        Ok(())
+    }
+
+    /// Returns to the state of a new VM about to run the script from its entry point,
+    /// e.g. to start a new game from the title.
+    ///
+    /// The thread stacks are cleared in place instead of reallocated, `global` is reset to
+    /// nil and the opcode counts to zero. `scenario` may be another script than the one
+    /// which ran, the strings decoded from that one are dropped.
+    pub fn reset(&mut self, scenario: &Scenario, global: &mut Global) {
+        for context in &self.contexts {
+            let mut context = context.borrow_mut();
+            context.reset(0);
+            context.clear_const_strings();
+            context.set_should_break(true);
+        }
+        {
+            let mut main = self.get_thread(0);
+            main.reset(scenario.get_entry_point());
+            main.set_status(CONTEXT_STATUS_RUNNING);
+        }
+        self.current_id = 0;
+        self.thread_break = false;
+        if let Some(histogram) = &mut self.opcode_histogram {
+            histogram.fill(0);
+        }
+
+        scenario.init_scene(global);
     }
 
     // #[instrument(skip(self), level = "trace")]
@@ -169,18 +326,161 @@ impl Scripter {
 
         if status & CONTEXT_STATUS_RUNNING != 0 {
             self.get_thread(id).set_should_break(false);
-            while !self.get_thread(id).should_break() {
-                log::info!("tid: {}", id);
-                let result = self.get_thread(id).dispatch_opcode(&secnario);
-                if let Err(e) = result {
-                    panic!("Error while executing the script {:?}", e);
-                }
+            let outcome = self
+                .run_for_duration(secnario, id, THREAD_FRAME_BUDGET, Instant::now)
+                .unwrap_or_else(|e| panic!("Error while executing the script {:?}", e));
+            if outcome == RunOutcome::BudgetExhausted {
+                trace!("Thread {} ran out of its frame budget", id);
             }
         }
 
         None
     }
 
+    fn count_opcode(&mut self, scenario: &Scenario, id: u32) -> Result<()> {
+        if let Some(histogram) = &mut self.opcode_histogram {
+            let pc = self.contexts[id as usize].borrow().get_pc();
+            let opcode = scenario.read_u8(pc)? as usize;
+            if let Some(count) = histogram.get_mut(opcode) {
+                *count += 1;
+            }
+        }
+        Ok(())
+    }
+
+    /// execute a single instruction on the thread
+    #[inline]
+    pub fn step(&mut self, scenario: &Scenario, id: u32) -> Result<()> {
+        self.count_opcode(scenario, id)?;
+        self.get_thread(id).dispatch_opcode(scenario)
+    }
+
+    /// Runs the current thread until it yields or halts, for at most `max_budget`
+    /// instructions. For tests driving a script one event at a time, without an engine.
+    ///
+    /// The thread controls are applied, so `ThreadNext` or `ThreadWait` yield the thread.
+    /// The other syscalls are collected in the report and return nil, except for the string
+    /// helpers which return their result, see [`strings::evaluate`].
+    pub fn run_until_yield(&mut self, scenario: &Scenario, max_budget: u32) -> Result<YieldReport> {
+        self.run_until_yield_with(scenario, max_budget, |command| {
+            CommandResult::WriteR0(strings::evaluate(command).unwrap_or(Variant::Nil))
+        })
+    }
+
+    /// [`Scripter::run_until_yield`] answering the syscalls other than the thread controls
+    /// with `host`, its results are applied like the engine applies those of the commands
+    pub fn run_until_yield_with(
+        &mut self,
+        scenario: &Scenario,
+        max_budget: u32,
+        mut host: impl FnMut(&Command) -> CommandResult,
+    ) -> Result<YieldReport> {
+        let id = self.current_id;
+        self.get_thread(id).set_should_break(false);
+        let mut report = YieldReport {
+            outcome: RunOutcome::BudgetExhausted,
+            steps: 0,
+            commands: Vec::new(),
+        };
+        while report.steps < max_budget {
+            let pc = {
+                let thread = self.get_thread(id);
+                if thread.get_status() & CONTEXT_STATUS_RUNNING == 0 || thread.get_pc() == 0 {
+                    report.outcome = RunOutcome::Halted;
+                    return Ok(report);
+                }
+                if thread.should_break() {
+                    report.outcome = RunOutcome::Yielded;
+                    return Ok(report);
+                }
+                thread.get_pc()
+            };
+
+            if scenario.read_u8(pc)? == Opcode::Syscall as u8 {
+                self.count_opcode(scenario, id)?;
+                let command = self.get_thread(id).syscall(scenario)?;
+                if let Some(command) = self.apply_thread_control(scenario, command)? {
+                    let result = host(&command);
+                    self.apply_result(scenario, result)?;
+                    report.commands.push(command);
+                }
+            } else {
+                self.step(scenario, id)?;
+            }
+            report.steps += 1;
+        }
+        Ok(report)
+    }
+
+    /// Applies the result of the command of a syscall to the current thread, before it resumes
+    pub fn apply_result(&mut self, scenario: &Scenario, result: CommandResult) -> Result<()> {
+        let id = self.current_id;
+        self.get_thread(id).apply_result(scenario, result)
+    }
+
+    /// applies a thread control syscall, the other commands are given back
+    fn apply_thread_control(
+        &mut self,
+        scenario: &Scenario,
+        command: Command,
+    ) -> Result<Option<Command>> {
+        let Some(args) = command.args() else {
+            return Ok(Some(command));
+        };
+        match &command {
+            Command::ThreadNext { .. } => self.thread_next(),
+            Command::ThreadWait { .. } => self.thread_wait(args.int(0)? as u32),
+            Command::ThreadSleep { .. } => self.thread_sleep(args.int(0)? as u32),
+            Command::ThreadRaise { .. } => self.thread_raise(args.int(0)? as u32),
+            Command::ThreadStart { .. } => {
+                let addr = scenario.resolve_function(args.int(1)? as u32);
+                self.thread_start(args.int(0)? as u32, addr)
+            }
+            Command::ThreadExit { .. } => self.thread_exit(args.opt_int(0)?.map(|id| id as u32)),
+            _ => return Ok(Some(command)),
+        }
+        Ok(None)
+    }
+
+    /// Runs the thread `id` for at most `budget` instructions, stopping early when it
+    /// yields or halts.
+    pub fn run_for(&mut self, scenario: &Scenario, id: u32, budget: u32) -> Result<RunOutcome> {
+        for _ in 0..budget {
+            {
+                let thread = self.get_thread(id);
+                if thread.get_status() & CONTEXT_STATUS_RUNNING == 0 || thread.get_pc() == 0 {
+                    return Ok(RunOutcome::Halted);
+                }
+                if thread.should_break() {
+                    return Ok(RunOutcome::Yielded);
+                }
+            }
+            self.step(scenario, id)?;
+        }
+        Ok(RunOutcome::BudgetExhausted)
+    }
+
+    /// Runs the thread `id` for up to `max` of wall time, to keep a CPU-bound script from
+    /// stalling the frame. `now` reads the clock, [`Instant::now`] outside of tests.
+    ///
+    /// The clock is only checked between small instruction budgets, the run can overshoot
+    /// `max` by the time of one budget.
+    pub fn run_for_duration(
+        &mut self,
+        scenario: &Scenario,
+        id: u32,
+        max: Duration,
+        mut now: impl FnMut() -> Instant,
+    ) -> Result<RunOutcome> {
+        let start = now();
+        loop {
+            let outcome = self.run_for(scenario, id, DURATION_CHECK_BUDGET)?;
+            if outcome != RunOutcome::BudgetExhausted || now() - start >= max {
+                return Ok(outcome);
+            }
+        }
+    }
+
     /// Run the VM until a command is encountered
     #[inline]
     pub fn run(&mut self, secnario: &Scenario, frame_time: u64) -> Option<Command> {
@@ -196,3 +496,368 @@ impl Scripter {
         None
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::format::scenario::{instructions::Opcode, Nls};
+    use crate::format::test_util::build_hcb;
+
+    #[test]
+    fn test_opcode_histogram() {
+        // push_i32 1; push_i32 2; add; push_i32 3; add; ret
+        let code = [
+            0x01, 0, 0, 0x0a, 1, 0, 0, 0, 0x0a, 2, 0, 0, 0, 0x1a, 0x0a, 3, 0, 0, 0, 0x1a, 0x04,
+        ];
+        let scenario = Scenario::new(build_hcb(&code, 4, &[]), None).unwrap();
+
+        let mut scripter = Scripter::new();
+        assert!(scripter.opcode_histogram().is_none());
+        scripter.enable_profiling(true);
+        scripter.start_main(scenario.get_entry_point());
+        for _ in 0..7 {
+            scripter.step(&scenario, 0).unwrap();
+        }
+
+        let histogram = scripter.opcode_histogram().unwrap();
+        assert_eq!(histogram[Opcode::PushI32 as usize], 3);
+        assert_eq!(histogram[Opcode::Add as usize], 2);
+        assert_eq!(histogram[Opcode::InitStack as usize], 1);
+        assert_eq!(histogram[Opcode::Ret as usize], 1);
+        assert_eq!(histogram.iter().sum::<u64>(), 7);
+
+        scripter.enable_profiling(false);
+        assert!(scripter.opcode_histogram().is_none());
+    }
+
+    #[test]
+    fn test_synthetic_programs() {
+        let run = |hcb| {
+            let scenario = Scenario::new(hcb, None).unwrap();
+            rfvp_test_support::run_main!(Scripter::new(), &scenario)
+        };
+
+        assert_eq!(
+            run(rfvp_test_support::arithmetic_loop(100)).as_int(),
+            Some(100 * 99 / 2 - 300)
+        );
+        assert_eq!(
+            run(rfvp_test_support::string_concat_loop(200, "ab"))
+                .as_string()
+                .map(|s| s.len()),
+            Some(400)
+        );
+        assert_eq!(
+            run(rfvp_test_support::table_loop(50)).as_int(),
+            Some(50 * 49 / 2)
+        );
+        assert_eq!(run(rfvp_test_support::recursion(100)).as_int(), Some(100));
+    }
+
+    #[test]
+    fn test_reset() {
+        let scenario = Scenario::new(rfvp_test_support::table_loop(20), None).unwrap();
+        let mut scripter = Scripter::new();
+        scripter.enable_profiling(true);
+        scripter.start_main(scenario.get_entry_point());
+
+        let run = |scripter: &mut Scripter| {
+            assert_eq!(
+                scripter.run_for(&scenario, 0, 100_000).unwrap(),
+                RunOutcome::Halted
+            );
+            let value = scripter.get_thread(0).get_return_value().clone();
+            let executed = scripter.opcode_histogram().unwrap().iter().sum::<u64>();
+            (value.as_int(), executed)
+        };
+
+        let first = run(&mut scripter);
+        assert_eq!(first.0, Some(20 * 19 / 2));
+
+        let mut global = Global::new();
+        global.set(0x7e01, Variant::Int(1));
+        scripter.reset(&scenario, &mut global);
+        assert!(global.get(0x7e01).is_none());
+        assert_eq!(
+            scripter.get_thread(0).get_pc(),
+            scenario.get_entry_point() as usize
+        );
+        assert!(scripter.get_thread(0).get_return_value().is_nil());
+        assert_eq!(scripter.get_thread(1).get_status(), CONTEXT_STATUS_NONE);
+        assert_eq!(run(&mut scripter), first);
+    }
+
+    #[test]
+    fn test_reset_other_script() {
+        // the same code with another string at the same offset
+        let script = |text: &str| {
+            let mut code = rfvp_test_support::CodeBuilder::new();
+            code.init_stack(0, 0).push_string(text).retv();
+            Scenario::new(code.finish(4), Some(Nls::UTF8)).unwrap()
+        };
+        let first = script("ab");
+        let second = script("cd");
+
+        let mut scripter = Scripter::new();
+        let mut global = Global::new();
+        for (scenario, expected) in [(&first, "ab"), (&second, "cd")] {
+            scripter.reset(scenario, &mut global);
+            assert_eq!(
+                scripter.run_for(scenario, 0, 100_000).unwrap(),
+                RunOutcome::Halted
+            );
+            let value = scripter.get_thread(0).get_return_value().clone();
+            assert!(matches!(value, Variant::String(s) if s == expected));
+        }
+    }
+
+    #[test]
+    fn test_int_overflow() {
+        let mut code = rfvp_test_support::CodeBuilder::new();
+        code.init_stack(0, 0)
+            .push_i32(i32::MAX)
+            .push_i32(1)
+            .add()
+            .retv();
+        let scenario = Scenario::new(code.finish(4), None).unwrap();
+
+        let run = |int_overflow| {
+            let mut scripter = Scripter::with_config(VmConfig { int_overflow });
+            rfvp_test_support::run_main!(scripter, &scenario)
+        };
+
+        assert_eq!(run(IntOverflow::Wrap).as_int(), Some(i32::MIN));
+        assert_eq!(run(IntOverflow::Saturate).as_int(), Some(i32::MAX));
+        assert!(run(IntOverflow::Nil).is_nil());
+    }
+
+    #[test]
+    fn test_run_until_yield() {
+        use rfvp_test_support::{build_hcb, CodeBuilder};
+
+        const TEXT_PRINT: u16 = 0;
+        const THREAD_NEXT: u16 = 1;
+        let mut code = CodeBuilder::new();
+        code.init_stack(0, 0)
+            .push_i32(0)
+            .push_string("hello")
+            .syscall(TEXT_PRINT)
+            .syscall(THREAD_NEXT)
+            .push_i32(1)
+            .retv();
+        let hcb = build_hcb(code.code(), 4, &[(2, "TextPrint"), (0, "ThreadNext")]);
+        let scenario = Scenario::new(hcb, None).unwrap();
+
+        let mut scripter = Scripter::new();
+        scripter.start_main(scenario.get_entry_point());
+        let report = scripter.run_until_yield(&scenario, 100).unwrap();
+        assert_eq!(report.outcome, RunOutcome::Yielded);
+        assert_eq!(report.steps, 5);
+        assert_eq!(report.commands.len(), 1);
+        assert!(matches!(&report.commands[0], Command::TextPrint { args } if args.len() == 2));
+
+        let report = scripter.run_until_yield(&scenario, 1).unwrap();
+        assert_eq!(report.outcome, RunOutcome::BudgetExhausted);
+        assert_eq!(report.steps, 1);
+
+        let report = scripter.run_until_yield(&scenario, 100).unwrap();
+        assert_eq!(report.outcome, RunOutcome::Halted);
+        assert_eq!(report.steps, 1);
+        assert!(report.commands.is_empty());
+        assert_eq!(scripter.get_thread(0).get_return_value().as_int(), Some(1));
+    }
+
+    #[test]
+    fn test_host_calls_subroutine() {
+        use rfvp_test_support::{build_hcb, CodeBuilder};
+
+        let mut code = CodeBuilder::new();
+        // main: return Debmess(41)
+        code.init_stack(0, 0)
+            .push_i32(41)
+            .syscall(0)
+            .push_return()
+            .retv();
+        // the callback, returns its argument + 1
+        let callback = code.addr();
+        code.init_stack(1, 0)
+            .push_stack(-2)
+            .push_i32(1)
+            .add()
+            .retv();
+        let scenario = Scenario::new(build_hcb(code.code(), 4, &[(1, "Debmess")]), None).unwrap();
+
+        let mut scripter = Scripter::new();
+        scripter.start_main(scenario.get_entry_point());
+        let report = scripter
+            .run_until_yield_with(&scenario, 100, |command| CommandResult::CallSubroutine {
+                target: callback,
+                args: vec![command.args().unwrap().get(0).clone()],
+            })
+            .unwrap();
+        assert_eq!(report.outcome, RunOutcome::Halted);
+        assert_eq!(report.commands.len(), 1);
+        assert_eq!(scripter.get_thread(0).get_return_value().as_int(), Some(42));
+    }
+
+    #[test]
+    fn test_string_helpers() {
+        use rfvp_test_support::{build_hcb, CodeBuilder};
+
+        let mut code = CodeBuilder::new();
+        code.init_stack(0, 0)
+            .push_i32(7)
+            .push_i32(3)
+            .syscall(0)
+            .push_return()
+            .retv();
+        let hcb = build_hcb(code.code(), 4, &[(2, "IntToText")]);
+        let scenario = Scenario::new(hcb, None).unwrap();
+
+        // answered by the VM, still reported
+        let mut scripter = Scripter::new();
+        scripter.start_main(scenario.get_entry_point());
+        let report = scripter.run_until_yield(&scenario, 100).unwrap();
+        assert_eq!(report.outcome, RunOutcome::Halted);
+        assert!(matches!(&report.commands[..], [Command::IntToText { .. }]));
+        let value = scripter.get_thread(0).get_return_value().clone();
+        assert_eq!(value.as_string().map(String::as_str), Some("007"));
+
+        let mut scripter = Scripter::new();
+        scripter.start_main(scenario.get_entry_point());
+        let outcome = scripter.run_for(&scenario, 0, 100).unwrap();
+        assert_eq!(outcome, RunOutcome::Halted);
+        let value = scripter.get_thread(0).get_return_value().clone();
+        assert_eq!(value.as_string().map(String::as_str), Some("007"));
+    }
+
+    #[test]
+    fn test_comparison_branches() {
+        use rfvp_test_support::CodeBuilder;
+        type Emit = fn(&mut CodeBuilder) -> &mut CodeBuilder;
+
+        // returns 1 if the branch on `a op b` is taken, 0 otherwise
+        let branch = |a: i32, b: i32, op: Emit| {
+            let mut code = CodeBuilder::new();
+            code.init_stack(0, 0).push_i32(a).push_i32(b);
+            op(&mut code);
+            let not_taken = code.jz_forward();
+            code.push_i32(1).retv();
+            let else_addr = code.addr();
+            code.push_i32(0).retv();
+            code.patch(not_taken, else_addr);
+
+            let scenario = Scenario::new(code.finish(4), None).unwrap();
+            let taken = rfvp_test_support::run_main!(Scripter::new(), &scenario);
+            taken.as_int().unwrap()
+        };
+
+        let ops: [(&str, Emit, [i32; 3]); 6] = [
+            ("set_e", CodeBuilder::sete, [0, 1, 0]),
+            ("set_ne", CodeBuilder::setne, [1, 0, 1]),
+            ("set_g", CodeBuilder::setg, [0, 0, 1]),
+            ("set_le", CodeBuilder::setle, [1, 1, 0]),
+            ("set_l", CodeBuilder::setl, [1, 0, 0]),
+            ("set_ge", CodeBuilder::setge, [0, 1, 1]),
+        ];
+        for (name, op, expected) in ops {
+            // a < b, a == b, a > b
+            let taken = [branch(1, 2, op), branch(2, 2, op), branch(3, 2, op)];
+            assert_eq!(taken, expected, "{}", name);
+
+            // the builder and the opcode table agree on the byte
+            let mut code = CodeBuilder::new();
+            op(&mut code);
+            assert_eq!(Opcode::try_from(code.code()[0] as i32), Opcode::try_from(name));
+        }
+    }
+
+    #[test]
+    fn test_run_for_duration() {
+        // an endless counting loop
+        let mut code = rfvp_test_support::CodeBuilder::new();
+        code.init_stack(0, 1);
+        let top = code.addr();
+        code.push_stack(0)
+            .push_i32(1)
+            .add()
+            .pop_stack(0)
+            .jmp(top);
+        let scenario = Scenario::new(code.finish(4), None).unwrap();
+
+        let mut scripter = Scripter::new();
+        scripter.start_main(scenario.get_entry_point());
+        assert_eq!(
+            scripter.run_for(&scenario, 0, 100).unwrap(),
+            RunOutcome::BudgetExhausted
+        );
+
+        // every clock read is a millisecond later than the previous one
+        let start = Instant::now();
+        let mut reads = 0;
+        let clock = || {
+            reads += 1;
+            start + Duration::from_millis(reads - 1)
+        };
+        scripter.enable_profiling(true);
+        let outcome = scripter
+            .run_for_duration(&scenario, 0, Duration::from_millis(5), clock)
+            .unwrap();
+        assert_eq!(outcome, RunOutcome::BudgetExhausted);
+        // stopped at the first check past the limit
+        assert_eq!(reads, 6);
+        let executed: u64 = scripter.opcode_histogram().unwrap().iter().sum();
+        assert_eq!(executed, 5 * DURATION_CHECK_BUDGET as u64);
+
+        // a thread which returns stops the run right away
+        let scenario = Scenario::new(rfvp_test_support::recursion(10), None).unwrap();
+        scripter.start_main(scenario.get_entry_point());
+        let outcome = scripter
+            .run_for_duration(&scenario, 0, Duration::ZERO, || start)
+            .unwrap();
+        assert_eq!(outcome, RunOutcome::Halted);
+    }
+
+    #[test]
+    fn test_snapshot_resumes() {
+        let scenario = Scenario::new(rfvp_test_support::recursion(10), None).unwrap();
+        let mut scripter = Scripter::new();
+        scripter.start_main(scenario.get_entry_point());
+        // a few calls deep
+        scripter.run_for(&scenario, 0, 40).unwrap();
+        let yaml = serde_yaml::to_string(&scripter.snapshot()).unwrap();
+
+        let mut resumed = Scripter::new();
+        resumed.thread_start(5, 0x10);
+        resumed
+            .restore(&serde_yaml::from_str(&yaml).unwrap())
+            .unwrap();
+        assert_eq!(
+            resumed.get_thread(0).get_pc(),
+            scripter.get_thread(0).get_pc()
+        );
+        assert_eq!(resumed.get_thread(5).get_status(), CONTEXT_STATUS_NONE);
+
+        for scripter in [&mut scripter, &mut resumed] {
+            let outcome = scripter.run_for(&scenario, 0, 1000).unwrap();
+            assert_eq!(outcome, RunOutcome::Halted);
+            assert_eq!(scripter.get_thread(0).get_return_value().as_int(), Some(10));
+        }
+    }
+
+    #[test]
+    fn test_restart_main_resets_threads() {
+        let mut scripter = Scripter::new();
+        scripter.start_main(0x10);
+        scripter.thread_start(3, 0x20);
+        scripter.set_current_id(3);
+
+        scripter.start_main(0x30);
+        assert_eq!(scripter.get_current_id(), 0);
+        assert_eq!(scripter.get_thread(0).get_pc(), 0x30);
+        assert_eq!(scripter.get_thread(0).get_status(), CONTEXT_STATUS_RUNNING);
+        for id in 1..scripter.contexts.len() as u32 {
+            assert_eq!(scripter.get_thread(id).get_status(), CONTEXT_STATUS_NONE);
+        }
+    }
+}
@@ -0,0 +1,127 @@
+use glam::{vec2, Vec2};
+
+/// Keeps a resizable window at the aspect ratio of the script's screen.
+///
+/// Window managers don't let us constrain the ratio directly, so the size the
+/// user dragged to is snapped to the nearest size with the right ratio and
+/// requested back from the window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AspectLock {
+    screen_size: (u32, u32),
+}
+
+/// The screen of the game modes the scenario doesn't know, used for an empty screen size
+const FALLBACK_SCREEN_SIZE: (u32, u32) = (640, 480);
+
+impl AspectLock {
+    /// An empty `screen_size` has no ratio to keep, [`FALLBACK_SCREEN_SIZE`] is used instead
+    pub fn new(screen_size: (u32, u32)) -> Self {
+        if screen_size.0 == 0 || screen_size.1 == 0 {
+            tracing::warn!(
+                "Empty screen size {}x{}, keeping the window at {}x{} instead",
+                screen_size.0,
+                screen_size.1,
+                FALLBACK_SCREEN_SIZE.0,
+                FALLBACK_SCREEN_SIZE.1
+            );
+            return Self {
+                screen_size: FALLBACK_SCREEN_SIZE,
+            };
+        }
+        Self { screen_size }
+    }
+
+    pub fn screen_size(&self) -> (u32, u32) {
+        self.screen_size
+    }
+
+    /// The smallest window size allowed, half of the screen size
+    pub fn min_size(&self) -> (u32, u32) {
+        (self.screen_size.0 / 2, self.screen_size.1 / 2)
+    }
+
+    /// The aspect-correct size nearest to `requested`, coming from the `current` size.
+    ///
+    /// The axis which changed the most (relative to the screen size) is the one being
+    /// dragged, it's kept and the other one follows. The result is never smaller than
+    /// [`Self::min_size`].
+    pub fn snap(&self, current: (u32, u32), requested: (u32, u32)) -> (u32, u32) {
+        let (screen_w, screen_h) = (self.screen_size.0 as u64, self.screen_size.1 as u64);
+        let (min_w, min_h) = self.min_size();
+        let (w, h) = (requested.0.max(min_w) as u64, requested.1.max(min_h) as u64);
+
+        // compare the changes as fractions of the screen size, without dividing
+        let width_change = (w.abs_diff(current.0 as u64)) * screen_h;
+        let height_change = (h.abs_diff(current.1 as u64)) * screen_w;
+
+        let (w, h) = if width_change >= height_change {
+            (w, (w * screen_h + screen_w / 2) / screen_w)
+        } else {
+            ((h * screen_w + screen_h / 2) / screen_h, h)
+        };
+
+        (w as u32, h as u32)
+    }
+
+    /// Maps a cursor position in window pixels to script screen coordinates.
+    ///
+    /// The picture is letterboxed when the window isn't aspect-correct (fullscreen or
+    /// before the snapped size is applied), positions over the bars give `None`.
+    pub fn window_to_screen(&self, position: Vec2, window_size: (u32, u32)) -> Option<Vec2> {
+        if window_size.0 == 0 || window_size.1 == 0 {
+            return None;
+        }
+
+        let screen = vec2(self.screen_size.0 as f32, self.screen_size.1 as f32);
+        let window = vec2(window_size.0 as f32, window_size.1 as f32);
+
+        let scale = (window.x / screen.x).min(window.y / screen.y);
+        let offset = (window - screen * scale) / 2.0;
+        let mapped = (position - offset) / scale;
+
+        (mapped.x >= 0.0 && mapped.y >= 0.0 && mapped.x < screen.x && mapped.y < screen.y)
+            .then_some(mapped)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snap_off_ratio() {
+        let lock = AspectLock::new((1024, 576));
+
+        // dragging the right edge, the height follows
+        assert_eq!(lock.snap((1024, 576), (1600, 600)), (1600, 900));
+        // dragging the bottom edge, the width follows
+        assert_eq!(lock.snap((1024, 576), (1030, 900)), (1600, 900));
+        // already correct
+        assert_eq!(lock.snap((1024, 576), (2048, 1152)), (2048, 1152));
+        // clamped to the minimum
+        assert_eq!(lock.snap((1024, 576), (100, 50)), (512, 288));
+    }
+
+    #[test]
+    fn test_empty_screen() {
+        let lock = AspectLock::new((0, 600));
+        assert_eq!(lock.screen_size(), FALLBACK_SCREEN_SIZE);
+        assert_eq!(lock.snap((640, 480), (1280, 500)), (1280, 960));
+    }
+
+    #[test]
+    fn test_cursor_mapping() {
+        let lock = AspectLock::new((800, 600));
+
+        assert_eq!(
+            lock.window_to_screen(vec2(800.0, 600.0), (1600, 1200)),
+            Some(vec2(400.0, 300.0))
+        );
+        // pillarboxed by 200 pixels on each side
+        assert_eq!(
+            lock.window_to_screen(vec2(200.0, 0.0), (1200, 600)),
+            Some(vec2(0.0, 0.0))
+        );
+        assert_eq!(lock.window_to_screen(vec2(100.0, 300.0), (1200, 600)), None);
+    }
+}
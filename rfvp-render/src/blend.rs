@@ -0,0 +1,111 @@
+/// How a sprite is combined with what's already drawn under it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum BlendMode {
+    /// the sprite is drawn over, by its alpha
+    #[default]
+    Alpha,
+    /// the sprite lights up what's under it, for flares and glows
+    Additive,
+    /// the sprite darkens what's under it, a white sprite changes nothing
+    Multiply,
+}
+
+impl BlendMode {
+    pub const ALL: [BlendMode; 3] = [BlendMode::Alpha, BlendMode::Additive, BlendMode::Multiply];
+
+    pub fn blend_state(self) -> wgpu::BlendState {
+        // the alpha of the render target accumulates the same way for every mode, the
+        // groups are composited by it
+        let alpha = wgpu::BlendComponent {
+            src_factor: wgpu::BlendFactor::OneMinusDstAlpha,
+            dst_factor: wgpu::BlendFactor::One,
+            operation: wgpu::BlendOperation::Add,
+        };
+        let color = match self {
+            BlendMode::Alpha => wgpu::BlendComponent {
+                src_factor: wgpu::BlendFactor::SrcAlpha,
+                dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
+                operation: wgpu::BlendOperation::Add,
+            },
+            BlendMode::Additive => wgpu::BlendComponent {
+                src_factor: wgpu::BlendFactor::SrcAlpha,
+                dst_factor: wgpu::BlendFactor::One,
+                operation: wgpu::BlendOperation::Add,
+            },
+            // src * dst + dst * (1 - src alpha): the destination where the sprite is
+            // transparent, the product where it's opaque
+            BlendMode::Multiply => wgpu::BlendComponent {
+                src_factor: wgpu::BlendFactor::Dst,
+                dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
+                operation: wgpu::BlendOperation::Add,
+            },
+        };
+        wgpu::BlendState { color, alpha }
+    }
+}
+
+/// Consecutive draws sharing a blend mode, drawn with the same pipeline
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlendBatch<T> {
+    pub mode: BlendMode,
+    pub items: Vec<T>,
+}
+
+/// Groups the draws by blend mode, in order: blending doesn't commute, so only neighbours
+/// are put together and the result looks the same as drawing them one by one.
+pub fn batch_by_blend_mode<T>(
+    items: impl IntoIterator<Item = (BlendMode, T)>,
+) -> Vec<BlendBatch<T>> {
+    let mut batches: Vec<BlendBatch<T>> = Vec::new();
+    for (mode, item) in items {
+        match batches.last_mut() {
+            Some(batch) if batch.mode == mode => batch.items.push(item),
+            _ => batches.push(BlendBatch {
+                mode,
+                items: vec![item],
+            }),
+        }
+    }
+    batches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_batches() {
+        // a flare over the background, under the characters
+        let layers = [
+            (BlendMode::Alpha, 1),
+            (BlendMode::Alpha, 2),
+            (BlendMode::Additive, 3),
+            (BlendMode::Alpha, 4),
+            (BlendMode::Alpha, 5),
+        ];
+        let batches = batch_by_blend_mode(layers);
+        assert_eq!(
+            batches,
+            [
+                BlendBatch {
+                    mode: BlendMode::Alpha,
+                    items: vec![1, 2],
+                },
+                BlendBatch {
+                    mode: BlendMode::Additive,
+                    items: vec![3],
+                },
+                BlendBatch {
+                    mode: BlendMode::Alpha,
+                    items: vec![4, 5],
+                },
+            ]
+        );
+
+        assert!(batch_by_blend_mode(Vec::<(BlendMode, ())>::new()).is_empty());
+
+        let additive = BlendMode::Additive.blend_state();
+        assert_eq!(additive.color.dst_factor, wgpu::BlendFactor::One);
+        assert_eq!(additive.alpha, BlendMode::Alpha.blend_state().alpha);
+    }
+}
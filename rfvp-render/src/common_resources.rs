@@ -1,12 +1,13 @@
 use std::sync::RwLock;
 
 use glam::{Mat4, Vec2, Vec4};
-use rfvp_core::time::Ticks;
+use rfvp_core::{shape::Shape, time::Ticks};
 
 use crate::{
     pipelines::Pipelines,
     vertices::{PosColTexVertex, PosVertex, TextVertex, VertexSource},
-    BindGroupLayouts, SubmittingEncoder, TextureBindGroup, YuvTextureBindGroup,
+    BindGroupLayouts, BlendMode, DroppedFrames, Msaa, SubmittingEncoder, TextureBindGroup,
+    TextureLimit, YuvTextureBindGroup,
 };
 
 pub struct GpuCommonResources {
@@ -17,6 +18,12 @@ pub struct GpuCommonResources {
     pub render_buffer_size: RwLock<(u32, u32)>,
     pub pipelines: Pipelines,
     pub bind_group_layouts: BindGroupLayouts,
+    /// MSAA of the offscreen render targets, must match the one the pipelines were created with
+    pub msaa: Msaa,
+    /// the largest texture of the device, the pictures past it are tiled or scaled down
+    pub texture_limit: TextureLimit,
+    /// frames dropped by the video players, displayed by the FPS overlay
+    pub dropped_frames: DroppedFrames,
 }
 
 impl GpuCommonResources {
@@ -44,6 +51,19 @@ impl GpuCommonResources {
             .draw(render_pass, source, texture, transform);
     }
 
+    pub fn draw_sprite_blended<'a>(
+        &'a self,
+        render_pass: &mut wgpu::RenderPass<'a>,
+        source: VertexSource<'a, PosColTexVertex>,
+        texture: &'a TextureBindGroup,
+        transform: Mat4,
+        blend: BlendMode,
+    ) {
+        self.pipelines
+            .sprite
+            .draw_blended(render_pass, source, texture, transform, blend);
+    }
+
     pub fn draw_yuv_sprite<'a>(
         &'a self,
         render_pass: &mut wgpu::RenderPass<'a>,
@@ -56,6 +76,19 @@ impl GpuCommonResources {
             .draw(render_pass, source, texture, transform);
     }
 
+    /// [`Self::draw_sprite`] with the colors inverted
+    pub fn draw_negative<'a>(
+        &'a self,
+        render_pass: &mut wgpu::RenderPass<'a>,
+        source: VertexSource<'a, PosColTexVertex>,
+        texture: &'a TextureBindGroup,
+        transform: Mat4,
+    ) {
+        self.pipelines
+            .negative
+            .draw(render_pass, source, texture, transform);
+    }
+
     #[allow(unused)]
     pub fn draw_fill<'a>(
         &'a self,
@@ -69,6 +102,20 @@ impl GpuCommonResources {
             .draw(render_pass, source, transform, color);
     }
 
+    /// `source` is a quad over the shape, in its local space: the top left corner of the
+    /// shape is at the origin
+    pub fn draw_shape<'a>(
+        &'a self,
+        render_pass: &mut wgpu::RenderPass<'a>,
+        source: VertexSource<'a, PosVertex>,
+        transform: Mat4,
+        shape: &Shape,
+    ) {
+        self.pipelines
+            .shape
+            .draw(render_pass, source, transform, shape);
+    }
+
     pub fn draw_text<'a>(
         &'a self,
         render_pass: &mut wgpu::RenderPass<'a>,
@@ -0,0 +1,138 @@
+use std::{
+    collections::VecDeque,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+/// how many presented frames are averaged
+const WINDOW_SIZE: usize = 60;
+
+/// Count of the decoded frames which were discarded without being displayed
+///
+/// Clones share the count: the video players record into the one of the [`GpuCommonResources`],
+/// which the stats of the window display.
+///
+/// [`GpuCommonResources`]: crate::GpuCommonResources
+#[derive(Debug, Default, Clone)]
+pub struct DroppedFrames(Arc<AtomicU64>);
+
+impl DroppedFrames {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// (a video falling behind pops all due frames and keeps only the latest one)
+    pub fn record(&self, skipped: u32) {
+        self.0.fetch_add(skipped as u64, Ordering::Relaxed);
+    }
+
+    pub fn get(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Frame timing statistics of the renderer, updated once per presented frame
+#[derive(Debug, Default, Clone)]
+pub struct FrameStats {
+    present_deltas: VecDeque<Duration>,
+    dropped_frames: DroppedFrames,
+}
+
+impl FrameStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// stats showing the drops recorded on `dropped_frames` and its clones
+    pub fn with_dropped_frames(dropped_frames: DroppedFrames) -> Self {
+        Self {
+            present_deltas: VecDeque::new(),
+            dropped_frames,
+        }
+    }
+
+    /// record the time passed since the previous frame was presented
+    pub fn record_present(&mut self, delta: Duration) {
+        self.present_deltas.push_back(delta);
+        if self.present_deltas.len() > WINDOW_SIZE {
+            self.present_deltas.pop_front();
+        }
+    }
+
+    /// record the decoded frames which were discarded without being displayed
+    /// (a video falling behind pops all due frames and keeps only the latest one)
+    pub fn record_skipped(&mut self, skipped: u32) {
+        self.dropped_frames.record(skipped);
+    }
+
+    pub fn present_dt_avg(&self) -> Duration {
+        let sum: Duration = self.present_deltas.iter().sum();
+        sum.checked_div(self.present_deltas.len() as u32)
+            .unwrap_or(Duration::ZERO)
+    }
+
+    pub fn present_dt_us_avg(&self) -> u64 {
+        self.present_dt_avg().as_micros() as u64
+    }
+
+    pub fn render_fps(&self) -> f32 {
+        let avg = self.present_dt_avg();
+        if avg.is_zero() {
+            0.0
+        } else {
+            1.0 / avg.as_secs_f32()
+        }
+    }
+
+    pub fn dropped_frames(&self) -> u64 {
+        self.dropped_frames.get()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_averages() {
+        let mut stats = FrameStats::new();
+        assert_eq!(stats.render_fps(), 0.0);
+
+        for _ in 0..WINDOW_SIZE * 2 {
+            stats.record_present(Duration::from_millis(20));
+        }
+        assert_eq!(stats.present_dt_us_avg(), 20_000);
+        assert!((stats.render_fps() - 50.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_dropped_frames() {
+        let mut stats = FrameStats::new();
+        stats.record_skipped(0);
+        assert_eq!(stats.dropped_frames(), 0);
+
+        // a burst of three due frames, only the latest is displayed
+        stats.record_skipped(2);
+        stats.record_skipped(1);
+        assert_eq!(stats.dropped_frames(), 3);
+    }
+
+    #[test]
+    fn test_shared_dropped_frames() {
+        // the window displays these stats, a video player records into a clone of the count
+        let dropped_frames = DroppedFrames::new();
+        let stats = FrameStats::with_dropped_frames(dropped_frames.clone());
+        let player = dropped_frames.clone();
+
+        player.record(4);
+        player.record(1);
+        assert_eq!(stats.dropped_frames(), 5);
+        assert_eq!(dropped_frames.get(), 5);
+
+        // unrelated stats don't see them
+        assert_eq!(FrameStats::new().dropped_frames(), 0);
+    }
+}
@@ -1,16 +1,72 @@
-use std::borrow::Cow;
+use std::{
+    borrow::Cow,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+};
 
-use glam::{vec4, Vec2};
-use image::RgbaImage;
+use glam::{uvec2, vec4, UVec2, Vec2};
+use image::{
+    imageops::{self, FilterType},
+    RgbaImage,
+};
 use once_cell::sync::OnceCell;
 
 use crate::{
+    texture_tiles::{downscaled_size, tile_grid, OversizePolicy},
     vertices::{PosColTexVertex, VertexSource},
     GpuCommonResources, SpriteVertexBuffer, TextureBindGroup, SRGB_TEXTURE_FORMAT,
 };
 
+/// How a texture is filtered when it's scaled
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Sampling {
+    #[default]
+    Linear,
+    /// keeps pixel art crisp
+    Nearest,
+}
+
+impl Sampling {
+    /// Nearest when the texture is drawn at a whole multiple of its size, that's
+    /// how pixel art assets are laid out and filtering would only blur them
+    pub fn for_scale(texture_size: (u32, u32), draw_size: (u32, u32)) -> Self {
+        let (w, h) = texture_size;
+        let (dw, dh) = draw_size;
+        if w == 0 || h == 0 || dw % w != 0 || dh % h != 0 {
+            return Sampling::Linear;
+        }
+
+        let scale = dw / w;
+        if scale >= 2 && dh / h == scale {
+            Sampling::Nearest
+        } else {
+            Sampling::Linear
+        }
+    }
+
+    pub fn sampler_descriptor(self, label: Option<&str>) -> wgpu::SamplerDescriptor<'_> {
+        let mag_filter = match self {
+            Sampling::Linear => wgpu::FilterMode::Linear,
+            Sampling::Nearest => wgpu::FilterMode::Nearest,
+        };
+        wgpu::SamplerDescriptor {
+            label,
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter,
+            min_filter: wgpu::FilterMode::Nearest,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        }
+    }
+}
+
 pub struct LazyGpuImage {
-    image: RgbaImage,
+    /// the CPU copy of the pixels, dropped by `release_cpu_image` once uploaded
+    image: Mutex<Option<RgbaImage>>,
     origin: Vec2,
     label: Option<String>,
     gpu_image: OnceCell<GpuImage>,
@@ -19,7 +75,7 @@ pub struct LazyGpuImage {
 impl LazyGpuImage {
     pub fn new(image: RgbaImage, origin: Vec2, label: Option<&str>) -> Self {
         Self {
-            image,
+            image: Mutex::new(Some(image)),
             origin,
             label: label.map(|s| s.to_owned()),
             gpu_image: OnceCell::new(),
@@ -28,9 +84,34 @@ impl LazyGpuImage {
 
     pub fn gpu_image(&self, resources: &GpuCommonResources) -> &GpuImage {
         self.gpu_image.get_or_init(|| {
-            GpuImage::load(resources, &self.image, self.origin, self.label.as_deref())
+            let image = self.image.lock().unwrap();
+            let image = image
+                .as_ref()
+                .expect("the pixels are only released after the upload");
+            GpuImage::load(resources, image, self.origin, self.label.as_deref())
         })
     }
+
+    /// The size of the CPU copy of the pixels in bytes
+    pub fn cpu_image_size(&self) -> usize {
+        self.image
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map_or(0, |image| image.as_raw().len())
+    }
+
+    /// Drops the CPU copy of the pixels if they are on the GPU, returns the bytes freed
+    pub fn release_cpu_image(&self) -> usize {
+        if self.gpu_image.get().is_none() {
+            return 0;
+        }
+        self.image
+            .lock()
+            .unwrap()
+            .take()
+            .map_or(0, |image| image.as_raw().len())
+    }
 }
 
 pub struct LazyGpuTexture {
@@ -55,51 +136,162 @@ impl LazyGpuTexture {
 }
 
 /// Gpu picture, ready to be drawn
-/// Includes a texture, a sampler, a bind group, and a vertex buffer
+/// Includes a texture, a sampler, a bind group, and a vertex buffer per tile. A picture
+/// larger than the textures of the device is split into tiles or scaled down, see
+/// [`TextureLimit`](crate::TextureLimit), one which fits is a single tile.
 pub struct GpuImage {
+    tiles: Vec<GpuImageTile>,
+    /// the size of the picture, the tiles may be smaller or scaled down
+    size: UVec2,
+}
+
+/// A texture of a [`GpuImage`] and the quad it's drawn on
+pub struct GpuImageTile {
     pub texture: GpuTexture,
     pub vertex_buffer: SpriteVertexBuffer,
 }
 
+impl GpuImageTile {
+    pub fn bind_group(&self) -> &TextureBindGroup {
+        &self.texture.bind_group
+    }
+
+    /// The bind group with a sampler of `sampling`, see [`GpuTexture::bind_group_for`]
+    pub fn bind_group_for(
+        &self,
+        resources: &GpuCommonResources,
+        sampling: Sampling,
+    ) -> &TextureBindGroup {
+        self.texture.bind_group_for(resources, sampling)
+    }
+
+    pub fn vertex_source(&self) -> VertexSource<PosColTexVertex> {
+        self.vertex_buffer.vertex_source()
+    }
+}
+
+static DOWNSCALE_WARNED: AtomicBool = AtomicBool::new(false);
+
 impl GpuImage {
     pub fn load(
         resources: &GpuCommonResources,
         image: &RgbaImage,
         origin: Vec2,
         label: Option<&str>,
+    ) -> Self {
+        Self::load_with_sampling(resources, image, origin, label, Sampling::default())
+    }
+
+    /// every tile gets a sampler of `sampling`
+    pub fn load_with_sampling(
+        resources: &GpuCommonResources,
+        image: &RgbaImage,
+        origin: Vec2,
+        label: Option<&str>,
+        sampling: Sampling,
     ) -> Self {
         let label = label
             .map(|s| Cow::from(s.to_owned()))
             .unwrap_or_else(|| Cow::from("Unnamed GpuPicture"));
 
-        let texture = GpuTexture::load(resources, image, Some(&label));
+        let size = uvec2(image.width(), image.height());
+        let limit = resources.texture_limit;
+        // TODO: do we even want colored vertices?..
+        let color = vec4(1.0, 1.0, 1.0, 1.0);
+        let quad = |position: UVec2, size: UVec2| {
+            let start = position.as_vec2() - origin;
+            let end = start + size.as_vec2();
+            (start.x, start.y, end.x, end.y)
+        };
 
-        let origin_translate = -origin.extend(0.0);
+        if limit.fits(size) {
+            let texture = GpuTexture::load_with_sampling(resources, image, Some(&label), sampling);
+            let vertex_buffer = SpriteVertexBuffer::new(resources, quad(UVec2::ZERO, size), color);
+            return GpuImage {
+                tiles: vec![GpuImageTile {
+                    texture,
+                    vertex_buffer,
+                }],
+                size,
+            };
+        }
 
-        let vertex_buffer = SpriteVertexBuffer::new(
-            resources,
-            (
-                origin_translate.x,
-                origin_translate.y,
-                origin_translate.x + image.width() as f32,
-                origin_translate.y + image.height() as f32,
-            ),
-            // TODO: do we even want colored vertices?..
-            vec4(1.0, 1.0, 1.0, 1.0),
-        );
+        let tiles = match limit.oversize {
+            OversizePolicy::Tile => tile_grid(size, limit.max_dimension)
+                .into_iter()
+                .enumerate()
+                .map(|(index, tile)| {
+                    let (x, y) = tile.texture_position.into();
+                    let (width, height) = tile.texture_size.into();
+                    let pixels = imageops::crop_imm(image, x, y, width, height).to_image();
+                    let texture = GpuTexture::load_with_sampling(
+                        resources,
+                        &pixels,
+                        Some(&format!("{} Tile {}", label, index)),
+                        sampling,
+                    );
+                    let vertex_buffer = SpriteVertexBuffer::new_with_uv(
+                        resources,
+                        quad(tile.position, tile.size),
+                        tile.uv(),
+                        color,
+                    );
+                    GpuImageTile {
+                        texture,
+                        vertex_buffer,
+                    }
+                })
+                .collect(),
+            OversizePolicy::Downscale => {
+                let scaled = downscaled_size(size, limit.max_dimension);
+                if !DOWNSCALE_WARNED.swap(true, Ordering::Relaxed) {
+                    tracing::warn!(
+                        "{}: {}x{} is larger than the textures of the GPU, scaled down to {}x{}. \
+                         The pictures this large are scaled down from now on",
+                        label,
+                        size.x,
+                        size.y,
+                        scaled.x,
+                        scaled.y
+                    );
+                }
+                let pixels = imageops::resize(image, scaled.x, scaled.y, FilterType::Triangle);
+                let texture =
+                    GpuTexture::load_with_sampling(resources, &pixels, Some(&label), sampling);
+                // drawn at the size of the picture, the layout doesn't change
+                let vertex_buffer =
+                    SpriteVertexBuffer::new(resources, quad(UVec2::ZERO, size), color);
+                vec![GpuImageTile {
+                    texture,
+                    vertex_buffer,
+                }]
+            }
+        };
 
-        GpuImage {
-            texture,
-            vertex_buffer,
-        }
+        GpuImage { tiles, size }
     }
 
-    pub fn bind_group(&self) -> &TextureBindGroup {
-        &self.texture.bind_group
+    /// the tiles to draw, with the same transform
+    pub fn tiles(&self) -> &[GpuImageTile] {
+        &self.tiles
     }
 
-    pub fn vertex_source(&self) -> VertexSource<PosColTexVertex> {
-        self.vertex_buffer.vertex_source()
+    /// The sampling for the image drawn at `scale`, see [`Sampling::for_scale`]. A scale
+    /// which doesn't land on whole pixels is always filtered
+    pub fn sampling_at(&self, scale: Vec2) -> Sampling {
+        let draw_size = self.size.as_vec2() * scale.abs();
+        if (draw_size - draw_size.round()).abs().max_element() > 1e-3 {
+            return Sampling::Linear;
+        }
+        let draw_size = draw_size.round().as_uvec2();
+        Sampling::for_scale(self.size.into(), draw_size.into())
+    }
+
+    /// switch the sampler of every tile
+    pub fn set_sampling(&mut self, resources: &GpuCommonResources, sampling: Sampling) {
+        for tile in &mut self.tiles {
+            tile.texture.set_sampling(resources, sampling);
+        }
     }
 }
 
@@ -111,10 +303,23 @@ pub struct GpuTexture {
     pub bind_group: TextureBindGroup,
     pub width: u32,
     pub height: u32,
+    sampling: Sampling,
+    /// the sampler and bind group of the other sampling, made on the first draw with it
+    other_binding: OnceCell<(wgpu::Sampler, TextureBindGroup)>,
+    label: String,
 }
 
 impl GpuTexture {
     pub fn load(resources: &GpuCommonResources, image: &RgbaImage, label: Option<&str>) -> Self {
+        Self::load_with_sampling(resources, image, label, Sampling::default())
+    }
+
+    pub fn load_with_sampling(
+        resources: &GpuCommonResources,
+        image: &RgbaImage,
+        label: Option<&str>,
+        sampling: Sampling,
+    ) -> Self {
         let label = label
             .map(|s| Cow::from(s.to_owned()))
             .unwrap_or_else(|| Cow::from("Unnamed GpuTexture"));
@@ -162,16 +367,30 @@ impl GpuTexture {
             },
         );
 
-        let sampler = resources.device.create_sampler(&wgpu::SamplerDescriptor {
-            label: Some(&format!("{} Sampler", label)),
-            address_mode_u: wgpu::AddressMode::ClampToEdge,
-            address_mode_v: wgpu::AddressMode::ClampToEdge,
-            address_mode_w: wgpu::AddressMode::ClampToEdge,
-            mag_filter: wgpu::FilterMode::Linear,
-            min_filter: wgpu::FilterMode::Nearest,
-            mipmap_filter: wgpu::FilterMode::Nearest,
-            ..Default::default()
-        });
+        let (sampler, bind_group) = Self::create_binding(resources, &texture, &label, sampling);
+
+        Self {
+            texture,
+            sampler,
+            bind_group,
+            width: image.width(),
+            height: image.height(),
+            sampling,
+            other_binding: OnceCell::new(),
+            label: label.into_owned(),
+        }
+    }
+
+    fn create_binding(
+        resources: &GpuCommonResources,
+        texture: &wgpu::Texture,
+        label: &str,
+        sampling: Sampling,
+    ) -> (wgpu::Sampler, TextureBindGroup) {
+        let sampler_label = format!("{} Sampler", label);
+        let sampler = resources
+            .device
+            .create_sampler(&sampling.sampler_descriptor(Some(&sampler_label)));
 
         let texture_view = texture.create_view(&wgpu::TextureViewDescriptor::default());
 
@@ -182,16 +401,80 @@ impl GpuTexture {
             Some(&format!("{} BindGroup", label)),
         );
 
-        Self {
-            texture,
-            sampler,
-            bind_group,
-            width: image.width(),
-            height: image.height(),
+        (sampler, bind_group)
+    }
+
+    pub fn sampling(&self) -> Sampling {
+        self.sampling
+    }
+
+    /// switch the sampler, the texture data is kept
+    pub fn set_sampling(&mut self, resources: &GpuCommonResources, sampling: Sampling) {
+        if self.sampling == sampling {
+            return;
         }
+        let (sampler, bind_group) =
+            Self::create_binding(resources, &self.texture, &self.label, sampling);
+        self.sampler = sampler;
+        self.bind_group = bind_group;
+        self.sampling = sampling;
+        self.other_binding = OnceCell::new();
     }
 
     pub fn bind_group(&self) -> &TextureBindGroup {
         &self.bind_group
     }
+
+    /// The bind group with a sampler of `sampling`, for a texture drawn with either. The
+    /// texture data is shared
+    pub fn bind_group_for(
+        &self,
+        resources: &GpuCommonResources,
+        sampling: Sampling,
+    ) -> &TextureBindGroup {
+        if sampling == self.sampling {
+            return &self.bind_group;
+        }
+        &self
+            .other_binding
+            .get_or_init(|| Self::create_binding(resources, &self.texture, &self.label, sampling))
+            .1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sampler_selection() {
+        let desc = Sampling::Nearest.sampler_descriptor(None);
+        assert_eq!(desc.mag_filter, wgpu::FilterMode::Nearest);
+        assert_eq!(desc.min_filter, wgpu::FilterMode::Nearest);
+
+        let desc = Sampling::default().sampler_descriptor(None);
+        assert_eq!(desc.mag_filter, wgpu::FilterMode::Linear);
+    }
+
+    #[test]
+    fn test_for_scale() {
+        assert_eq!(Sampling::for_scale((32, 16), (96, 48)), Sampling::Nearest);
+        assert_eq!(Sampling::for_scale((32, 16), (32, 16)), Sampling::Linear);
+        assert_eq!(Sampling::for_scale((32, 16), (64, 48)), Sampling::Linear);
+        assert_eq!(Sampling::for_scale((32, 16), (100, 50)), Sampling::Linear);
+        assert_eq!(Sampling::for_scale((0, 16), (0, 32)), Sampling::Linear);
+    }
+
+    #[test]
+    fn test_sampling_at() {
+        let image = GpuImage {
+            tiles: Vec::new(),
+            size: uvec2(32, 16),
+        };
+        assert_eq!(image.sampling_at(Vec2::splat(3.0)), Sampling::Nearest);
+        // flipped, still whole pixels
+        assert_eq!(image.sampling_at(Vec2::new(-2.0, 2.0)), Sampling::Nearest);
+        assert_eq!(image.sampling_at(Vec2::ONE), Sampling::Linear);
+        assert_eq!(image.sampling_at(Vec2::splat(2.01)), Sampling::Linear);
+    }
 }
@@ -0,0 +1,142 @@
+use glam::{uvec2, Mat4, UVec2, Vec2, Vec4};
+
+/// Where a sprite is on screen, to test the cursor against its pixels.
+///
+/// The quad of the sprite spans `-origin` to `size - origin` in its local space, like a
+/// [`GpuImage`](crate::GpuImage), and `transform` is the model transform it's rendered
+/// with, the transforms of the parent groups included. The cursor is taken back into the
+/// texture through the inverse of the transform, so rotated and scaled sprites are hit
+/// where they are drawn.
+#[derive(Debug, Clone, Copy)]
+pub struct SpriteHitArea {
+    transform: Mat4,
+    origin: Vec2,
+    size: UVec2,
+}
+
+impl SpriteHitArea {
+    pub fn new(transform: Mat4, origin: Vec2, size: UVec2) -> Self {
+        Self {
+            transform,
+            origin,
+            size,
+        }
+    }
+
+    /// the texel under `point` of the virtual screen, `None` if it's outside of the sprite
+    pub fn texel_at(&self, point: Vec2) -> Option<UVec2> {
+        let texel = self.local_at(point)?.floor();
+        if texel.x < 0.0 || texel.y < 0.0 {
+            return None;
+        }
+        let texel = uvec2(texel.x as u32, texel.y as u32);
+        (texel.x < self.size.x && texel.y < self.size.y).then_some(texel)
+    }
+
+    /// `point` of the virtual screen taken back into the quad, relative to its top left
+    /// corner, without clamping to the size. `None` if the sprite is scaled down to nothing.
+    pub fn local_at(&self, point: Vec2) -> Option<Vec2> {
+        let t = &self.transform;
+        let local = if t.x_axis == Vec4::X && t.y_axis == Vec4::Y && t.z_axis == Vec4::Z {
+            // not rotated nor scaled, the common case
+            point - t.w_axis.truncate().truncate()
+        } else {
+            if t.determinant().abs() < f32::EPSILON {
+                // scaled down to nothing
+                return None;
+            }
+            t.inverse().transform_point3(point.extend(0.0)).truncate()
+        };
+        Some(local + self.origin)
+    }
+
+    /// whether `point` is over a pixel of the sprite which isn't fully transparent
+    pub fn hit(&self, point: Vec2, alpha: impl FnOnce(UVec2) -> Option<u8>) -> bool {
+        self.texel_at(point)
+            .and_then(alpha)
+            .is_some_and(|alpha| alpha > 0)
+    }
+
+    /// whether `point` is on a shape drawn over the quad, `inside` is given the position
+    /// relative to the top left corner, see [`Self::local_at`]
+    pub fn hit_local(&self, point: Vec2, inside: impl FnOnce(Vec2) -> bool) -> bool {
+        self.local_at(point).is_some_and(inside)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::FRAC_PI_2;
+
+    use glam::{vec2, vec3};
+
+    use super::*;
+
+    /// a 40x20 sprite with its origin at its center, drawn at (100, 100)
+    fn area(model: Mat4) -> SpriteHitArea {
+        let transform = Mat4::from_translation(vec3(100.0, 100.0, 0.0)) * model;
+        SpriteHitArea::new(transform, vec2(20.0, 10.0), uvec2(40, 20))
+    }
+
+    #[test]
+    fn test_translated() {
+        let area = area(Mat4::IDENTITY);
+        assert_eq!(area.texel_at(vec2(100.5, 100.5)), Some(uvec2(20, 10)));
+        assert_eq!(area.texel_at(vec2(80.0, 90.0)), Some(uvec2(0, 0)));
+        assert_eq!(area.texel_at(vec2(119.9, 109.9)), Some(uvec2(39, 19)));
+        assert_eq!(area.texel_at(vec2(120.0, 100.0)), None);
+        assert_eq!(area.texel_at(vec2(79.9, 100.0)), None);
+    }
+
+    #[test]
+    fn test_rotated() {
+        // standing up, 20 wide and 40 tall
+        let area = area(Mat4::from_rotation_z(FRAC_PI_2));
+        assert!(area.texel_at(vec2(100.0, 100.0)).is_some());
+        assert!(area.texel_at(vec2(91.0, 81.0)).is_some());
+        assert!(area.texel_at(vec2(109.0, 119.0)).is_some());
+        // where the unrotated sprite would be
+        assert_eq!(area.texel_at(vec2(115.0, 100.0)), None);
+        assert_eq!(area.texel_at(vec2(111.0, 100.0)), None);
+        assert_eq!(area.texel_at(vec2(100.0, 121.0)), None);
+        assert_eq!(area.texel_at(vec2(100.0, 79.0)), None);
+
+        // the top of the texture is on the right of the screen
+        assert_eq!(area.texel_at(vec2(109.5, 100.5)), Some(uvec2(20, 0)));
+    }
+
+    #[test]
+    fn test_scaled() {
+        let scaled = area(Mat4::from_scale(vec3(2.0, 2.0, 1.0)));
+        assert_eq!(scaled.texel_at(vec2(61.0, 81.0)), Some(uvec2(0, 0)));
+        assert_eq!(scaled.texel_at(vec2(139.0, 119.0)), Some(uvec2(39, 19)));
+        assert_eq!(scaled.texel_at(vec2(59.0, 100.0)), None);
+        assert_eq!(scaled.texel_at(vec2(141.0, 100.0)), None);
+        assert_eq!(scaled.texel_at(vec2(100.0, 121.0)), None);
+
+        let flat = area(Mat4::from_scale(vec3(0.0, 1.0, 1.0)));
+        assert_eq!(flat.texel_at(vec2(100.0, 100.0)), None);
+    }
+
+    #[test]
+    fn test_alpha() {
+        let area = area(Mat4::IDENTITY);
+        // only the left half is opaque
+        let alpha = |texel: UVec2| Some(if texel.x < 20 { 0xff } else { 0 });
+        assert!(area.hit(vec2(85.0, 100.0), alpha));
+        assert!(!area.hit(vec2(115.0, 100.0), alpha));
+        assert!(!area.hit(vec2(70.0, 100.0), alpha));
+        assert!(!area.hit(vec2(85.0, 100.0), |_| None));
+    }
+
+    #[test]
+    fn test_shape() {
+        // a circle of radius 10 in the middle of the sprite, rotated with it
+        let inside = |local: Vec2| local.distance(vec2(20.0, 10.0)) <= 10.0;
+        let area = area(Mat4::from_rotation_z(FRAC_PI_2));
+        assert!(area.hit_local(vec2(100.0, 100.0), inside));
+        assert!(area.hit_local(vec2(100.0, 109.0), inside));
+        assert!(!area.hit_local(vec2(108.0, 108.0), inside));
+        assert!(!area.hit_local(vec2(100.0, 115.0), inside));
+    }
+}
@@ -4,24 +4,40 @@ use std::ops::{Deref, DerefMut};
 
 use glam::Mat4;
 
+mod aspect_lock;
 mod bind_groups;
+mod blend;
 mod camera;
 mod common_resources;
+mod frame_stats;
 mod gpu_image;
+mod hit_test;
+mod msaa;
 mod new_render;
 mod pillarbox;
 mod pipelines;
 mod render_target;
+mod surface_format;
+mod surface_size;
+mod texture_tiles;
 mod vertex_buffer;
 pub mod vertices;
 
+pub use aspect_lock::AspectLock;
 pub use bind_groups::{BindGroupLayouts, TextureBindGroup, YuvTextureBindGroup};
+pub use blend::{batch_by_blend_mode, BlendBatch, BlendMode};
 pub use camera::{Camera, VIRTUAL_HEIGHT, VIRTUAL_WIDTH};
 pub use common_resources::GpuCommonResources;
-pub use gpu_image::{GpuImage, GpuTexture, LazyGpuImage, LazyGpuTexture};
+pub use frame_stats::{DroppedFrames, FrameStats};
+pub use gpu_image::{GpuImage, GpuImageTile, GpuTexture, LazyGpuImage, LazyGpuTexture, Sampling};
+pub use hit_test::SpriteHitArea;
+pub use msaa::Msaa;
 pub use pillarbox::Pillarbox;
 pub use pipelines::Pipelines;
 pub use render_target::RenderTarget;
+pub use surface_format::SurfaceFormat;
+pub use surface_size::{SurfaceResize, SurfaceSize};
+pub use texture_tiles::{downscaled_size, tile_grid, OversizePolicy, TextureLimit, Tile};
 pub use vertex_buffer::{IndexBuffer, PosVertexBuffer, SpriteVertexBuffer, Vertex, VertexBuffer};
 
 pub const SRGB_TEXTURE_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
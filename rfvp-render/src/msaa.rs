@@ -0,0 +1,143 @@
+/// Multi-sample anti-aliasing of the offscreen render targets.
+///
+/// Everything is drawn into a multisampled texture which is resolved into the
+/// render target texture at the end of the pass, the final pass to the screen
+/// only blits the resolved texture and stays single-sampled.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Msaa {
+    #[default]
+    Off,
+    X2,
+    X4,
+}
+
+impl Msaa {
+    pub fn from_sample_count(count: u32) -> Option<Self> {
+        match count {
+            0 | 1 => Some(Msaa::Off),
+            2 => Some(Msaa::X2),
+            4 => Some(Msaa::X4),
+            _ => None,
+        }
+    }
+
+    pub fn sample_count(self) -> u32 {
+        match self {
+            Msaa::Off => 1,
+            Msaa::X2 => 2,
+            Msaa::X4 => 4,
+        }
+    }
+
+    pub fn is_enabled(self) -> bool {
+        self != Msaa::Off
+    }
+
+    /// Step down until the sample count is supported, ending up with no MSAA at worst
+    pub fn fallback(self, is_supported: impl Fn(u32) -> bool) -> Self {
+        let mut msaa = self;
+        while msaa.is_enabled() && !is_supported(msaa.sample_count()) {
+            msaa = match msaa {
+                Msaa::X4 => Msaa::X2,
+                _ => Msaa::Off,
+            };
+        }
+        msaa
+    }
+
+    /// The features to request with the device, [`Self::for_adapter`] relies on them
+    pub fn device_features(adapter: &wgpu::Adapter) -> wgpu::Features {
+        adapter.features() & wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES
+    }
+
+    /// Without adapter specific format features the device only allows the sample counts
+    /// every WebGPU implementation supports
+    fn is_guaranteed(count: u32) -> bool {
+        count == 4
+    }
+
+    /// The best supported setting for rendering into `format` on a device of this adapter,
+    /// requested with [`Self::device_features`]
+    pub fn for_adapter(self, adapter: &wgpu::Adapter, format: wgpu::TextureFormat) -> Self {
+        let msaa = if Self::device_features(adapter).is_empty() {
+            self.fallback(Self::is_guaranteed)
+        } else {
+            let flags = adapter.get_texture_format_features(format).flags;
+            self.fallback(|count| {
+                flags.sample_count_supported(count)
+                    && flags.contains(wgpu::TextureFormatFeatureFlags::MULTISAMPLE_RESOLVE)
+            })
+        };
+        if msaa != self {
+            tracing::warn!("{:?} is not supported by the adapter, using {:?}", self, msaa);
+        }
+        msaa
+    }
+
+    /// Describes the multisampled texture which is resolved into a render target, `None` if MSAA is off
+    pub fn color_target_descriptor<'a>(
+        self,
+        label: Option<&'a str>,
+        size: (u32, u32),
+        format: wgpu::TextureFormat,
+        view_formats: &'a [wgpu::TextureFormat],
+    ) -> Option<wgpu::TextureDescriptor<'a>> {
+        if !self.is_enabled() {
+            return None;
+        }
+
+        Some(wgpu::TextureDescriptor {
+            label,
+            size: wgpu::Extent3d {
+                width: size.0,
+                height: size.1,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: self.sample_count(),
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            // only rendered to and resolved, never sampled
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            view_formats,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fallback() {
+        assert_eq!(Msaa::X4.fallback(|_| true), Msaa::X4);
+        assert_eq!(Msaa::X4.fallback(|count| count <= 2), Msaa::X2);
+        assert_eq!(Msaa::X4.fallback(|_| false), Msaa::Off);
+        assert_eq!(Msaa::Off.fallback(|_| false), Msaa::Off);
+        assert_eq!(Msaa::from_sample_count(8), None);
+
+        // only 4x or none without the adapter specific features
+        assert_eq!(Msaa::X4.fallback(Msaa::is_guaranteed), Msaa::X4);
+        assert_eq!(Msaa::X2.fallback(Msaa::is_guaranteed), Msaa::Off);
+    }
+
+    #[test]
+    fn test_color_target_4x() {
+        let desc = Msaa::X4
+            .color_target_descriptor(
+                None,
+                (1920, 1080),
+                wgpu::TextureFormat::Rgba8UnormSrgb,
+                &[wgpu::TextureFormat::Rgba8Unorm],
+            )
+            .unwrap();
+        assert_eq!(desc.sample_count, 4);
+        assert_eq!(desc.size.width, 1920);
+        assert_eq!(desc.size.height, 1080);
+        assert_eq!(desc.usage, wgpu::TextureUsages::RENDER_ATTACHMENT);
+
+        assert!(Msaa::Off
+            .color_target_descriptor(None, (1, 1), wgpu::TextureFormat::Rgba8UnormSrgb, &[])
+            .is_none());
+    }
+}
@@ -5,7 +5,7 @@ use glam::{Mat4, Vec4};
 use wgpu::include_wgsl;
 
 use crate::{
-    pipelines,
+    pipelines::{self, PipelineTarget},
     vertices::{PosVertex, VertexSource},
     BindGroupLayouts,
 };
@@ -24,6 +24,7 @@ impl FillPipeline {
         device: &wgpu::Device,
         _bind_group_layouts: &BindGroupLayouts,
         texture_format: wgpu::TextureFormat,
+        sample_count: u32,
     ) -> Self {
         let shader_module = device.create_shader_module(include_wgsl!("fill.wgsl"));
 
@@ -38,7 +39,10 @@ impl FillPipeline {
 
         Self(pipelines::make_pipeline(
             device,
-            texture_format,
+            PipelineTarget {
+                format: texture_format,
+                sample_count,
+            },
             shader_module,
             layout,
             PosVertex::desc(),
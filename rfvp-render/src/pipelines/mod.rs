@@ -1,21 +1,33 @@
 mod fill;
+mod negative;
+mod shape;
 mod sprite;
 mod text;
 mod text_outline;
 mod yuv_sprite;
 
 use fill::FillPipeline;
+use negative::NegativePipeline;
+use shape::ShapePipeline;
 use sprite::SpritePipeline;
 use text::TextPipeline;
 use text_outline::TextOutlinePipeline;
 use yuv_sprite::YuvSpritePipeline;
 
-use crate::{bind_groups::BindGroupLayouts, RAW_TEXTURE_FORMAT, SRGB_TEXTURE_FORMAT};
+use crate::{bind_groups::BindGroupLayouts, Msaa, RAW_TEXTURE_FORMAT, SRGB_TEXTURE_FORMAT};
+
+/// The texture a pipeline renders into
+#[derive(Debug, Clone, Copy)]
+struct PipelineTarget {
+    format: wgpu::TextureFormat,
+    /// see [`Msaa::sample_count`]
+    sample_count: u32,
+}
 
 // TODO: make a builder?
 fn make_pipeline(
     device: &wgpu::Device,
-    texture_format: wgpu::TextureFormat,
+    target: PipelineTarget,
     shader_module: wgpu::ShaderModule,
     layout: wgpu::PipelineLayout,
     vertex_buffer_layout: wgpu::VertexBufferLayout,
@@ -41,13 +53,16 @@ fn make_pipeline(
             conservative: false,
         },
         depth_stencil: None,
-        multisample: Default::default(),
+        multisample: wgpu::MultisampleState {
+            count: target.sample_count,
+            ..Default::default()
+        },
         fragment: Some(wgpu::FragmentState {
             module: &shader_module,
             entry_point: "fragment_main",
             compilation_options: Default::default(),
             targets: &[Some(wgpu::ColorTargetState {
-                format: texture_format,
+                format: target.format,
                 blend,
                 write_mask: wgpu::ColorWrites::ALL,
             })],
@@ -60,6 +75,8 @@ pub struct Pipelines {
     pub sprite: SpritePipeline,
     pub yuv_sprite: YuvSpritePipeline,
     pub fill: FillPipeline,
+    pub negative: NegativePipeline,
+    pub shape: ShapePipeline,
     pub text: TextPipeline,
     pub text_outline: TextOutlinePipeline,
     // those are pipelines using screen's texture format (not our preferred RGBA format)
@@ -73,16 +90,26 @@ impl Pipelines {
         device: &wgpu::Device,
         bind_group_layouts: &BindGroupLayouts,
         surface_texture_format: wgpu::TextureFormat,
+        msaa: Msaa,
     ) -> Pipelines {
+        // render targets are multisampled, the screen is not
+        let samples = msaa.sample_count();
         Pipelines {
-            sprite: SpritePipeline::new(device, bind_group_layouts, SRGB_TEXTURE_FORMAT),
-            yuv_sprite: YuvSpritePipeline::new(device, bind_group_layouts, RAW_TEXTURE_FORMAT),
-            fill: FillPipeline::new(device, bind_group_layouts, SRGB_TEXTURE_FORMAT),
-            text: TextPipeline::new(device, bind_group_layouts, SRGB_TEXTURE_FORMAT),
-            text_outline: TextOutlinePipeline::new(device, bind_group_layouts, SRGB_TEXTURE_FORMAT),
+            sprite: SpritePipeline::new(device, bind_group_layouts, SRGB_TEXTURE_FORMAT, samples),
+            yuv_sprite: YuvSpritePipeline::new(device, bind_group_layouts, RAW_TEXTURE_FORMAT, samples),
+            fill: FillPipeline::new(device, bind_group_layouts, SRGB_TEXTURE_FORMAT, samples),
+            negative: NegativePipeline::new(device, bind_group_layouts, SRGB_TEXTURE_FORMAT, samples),
+            shape: ShapePipeline::new(device, bind_group_layouts, SRGB_TEXTURE_FORMAT, samples),
+            text: TextPipeline::new(device, bind_group_layouts, SRGB_TEXTURE_FORMAT, samples),
+            text_outline: TextOutlinePipeline::new(
+                device,
+                bind_group_layouts,
+                SRGB_TEXTURE_FORMAT,
+                samples,
+            ),
 
-            sprite_screen: SpritePipeline::new(device, bind_group_layouts, surface_texture_format),
-            fill_screen: FillPipeline::new(device, bind_group_layouts, surface_texture_format),
+            sprite_screen: SpritePipeline::new(device, bind_group_layouts, surface_texture_format, 1),
+            fill_screen: FillPipeline::new(device, bind_group_layouts, surface_texture_format, 1),
         }
     }
 }
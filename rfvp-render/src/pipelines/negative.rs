@@ -0,0 +1,102 @@
+use std::mem;
+
+use bytemuck::{Pod, Zeroable};
+use glam::Mat4;
+use wgpu::include_wgsl;
+
+use crate::{
+    pipelines::{self, PipelineTarget},
+    vertices::{PosColTexVertex, VertexSource},
+    BindGroupLayouts, TextureBindGroup,
+};
+
+#[derive(Pod, Zeroable, Copy, Clone, Debug)]
+#[repr(C)]
+struct NegativeParams {
+    pub transform: Mat4,
+}
+
+/// Draws a texture like the sprite pipeline, with its colors inverted
+pub struct NegativePipeline(wgpu::RenderPipeline);
+
+impl NegativePipeline {
+    pub fn new(
+        device: &wgpu::Device,
+        bind_group_layouts: &BindGroupLayouts,
+        texture_format: wgpu::TextureFormat,
+        sample_count: u32,
+    ) -> Self {
+        let shader_module = device.create_shader_module(include_wgsl!("negative.wgsl"));
+
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("NegativePipeline Layout"),
+            bind_group_layouts: &[&bind_group_layouts.texture],
+            push_constant_ranges: &[wgpu::PushConstantRange {
+                stages: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                range: 0..mem::size_of::<NegativeParams>() as u32,
+            }],
+        });
+
+        Self(pipelines::make_pipeline(
+            device,
+            PipelineTarget {
+                format: texture_format,
+                sample_count,
+            },
+            shader_module,
+            layout,
+            PosColTexVertex::desc(),
+            Some(wgpu::BlendState::ALPHA_BLENDING),
+            "NegativePipeline",
+        ))
+    }
+
+    pub fn draw<'a>(
+        &'a self,
+        render_pass: &mut wgpu::RenderPass<'a>,
+        source: VertexSource<'a, PosColTexVertex>,
+        texture: &'a TextureBindGroup,
+        transform: Mat4,
+    ) {
+        render_pass.set_pipeline(&self.0);
+        render_pass.set_bind_group(0, &texture.0, &[]);
+        render_pass.set_push_constants(
+            wgpu::ShaderStages::VERTEX_FRAGMENT,
+            0,
+            bytemuck::cast_slice(&[NegativeParams { transform }]),
+        );
+        source.draw(render_pass);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    /// the conversions of negative.wgsl, channel by channel
+    fn linear_to_srgb(linear: f32) -> f32 {
+        if linear <= 0.0031308 {
+            linear * 12.92
+        } else {
+            1.055 * linear.powf(1.0 / 2.4) - 0.055
+        }
+    }
+
+    fn srgb_to_linear(srgb: f32) -> f32 {
+        if srgb <= 0.04045 {
+            srgb / 12.92
+        } else {
+            ((srgb + 0.055) / 1.055).powf(2.4)
+        }
+    }
+
+    #[test]
+    fn test_inversion() {
+        // through the sRGB texture, the shader and the sRGB render target, every value of a
+        // picture comes out as 255 - value
+        for value in 0..=255u8 {
+            let sampled = srgb_to_linear(value as f32 / 255.0);
+            let written = srgb_to_linear(1.0 - linear_to_srgb(sampled.clamp(0.0, 1.0)));
+            let stored = (linear_to_srgb(written) * 255.0).round() as u8;
+            assert_eq!(stored, 255 - value, "{}", value);
+        }
+    }
+}
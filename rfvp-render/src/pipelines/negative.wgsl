@@ -0,0 +1,53 @@
+struct VertexIn {
+    @location(0) position: vec3<f32>,
+    @location(1) color: vec4<f32>,
+    @location(2) texture_coordinate: vec2<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) color: vec4<f32>,
+    @location(1) texture_coordinate: vec2<f32>,
+}
+
+@group(0) @binding(0)
+var sprite_texture: texture_2d<f32>;
+@group(0) @binding(1)
+var sprite_sampler: sampler;
+
+struct SpriteParams {
+    transform: mat4x4<f32>,
+}
+
+var<push_constant> params: SpriteParams;
+
+// keep in sync with the mirror in negative.rs
+fn linear_to_srgb(linear: vec3<f32>) -> vec3<f32> {
+    let low = linear * 12.92;
+    let high = 1.055 * pow(linear, vec3<f32>(1.0 / 2.4)) - 0.055;
+    return select(high, low, linear <= vec3<f32>(0.0031308));
+}
+
+fn srgb_to_linear(srgb: vec3<f32>) -> vec3<f32> {
+    let low = srgb / 12.92;
+    let high = pow((srgb + 0.055) / 1.055, vec3<f32>(2.4));
+    return select(high, low, srgb <= vec3<f32>(0.04045));
+}
+
+@vertex
+fn vertex_main(input: VertexIn) -> VertexOutput {
+    var output: VertexOutput;
+    output.position = params.transform * vec4<f32>(input.position, 1.0);
+    output.color = input.color;
+    output.texture_coordinate = input.texture_coordinate;
+    return output;
+}
+
+@fragment
+fn fragment_main(input: VertexOutput) -> @location(0) vec4<f32> {
+    let color = textureSample(sprite_texture, sprite_sampler, input.texture_coordinate) * input.color;
+    // inverted like the pictures are stored, in sRGB, not in the linear space the texture is
+    // sampled in: otherwise the mid grays come out far too dark
+    let inverted = srgb_to_linear(1.0 - linear_to_srgb(clamp(color.rgb, vec3<f32>(0.0), vec3<f32>(1.0))));
+    return vec4<f32>(inverted, color.a);
+}
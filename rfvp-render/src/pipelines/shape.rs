@@ -0,0 +1,93 @@
+use std::mem;
+
+use bytemuck::{Pod, Zeroable};
+use glam::{vec4, Mat4, Vec2, Vec4};
+use rfvp_core::shape::{Shape, ShapeKind};
+use wgpu::include_wgsl;
+
+use crate::{
+    pipelines::{self, PipelineTarget},
+    vertices::{PosVertex, VertexSource},
+    BindGroupLayouts,
+};
+
+#[derive(Pod, Zeroable, Copy, Clone, Debug)]
+#[repr(C)]
+struct ShapeParams {
+    pub transform: Mat4,
+    pub fill: Vec4,
+    pub border: Vec4,
+    pub bounds: Vec4,
+    pub radius_border: Vec4,
+}
+
+impl ShapeParams {
+    fn new(shape: &Shape, transform: Mat4) -> Self {
+        let center = shape.size / 2.0;
+        // a circle is a rounded square with the radius of half its side
+        let half = match shape.kind {
+            ShapeKind::RoundedRect { .. } => center,
+            ShapeKind::Circle => Vec2::splat(center.min_element()),
+        };
+        Self {
+            transform,
+            fill: shape.fill,
+            border: shape.border,
+            bounds: vec4(center.x, center.y, half.x, half.y),
+            radius_border: vec4(shape.corner_radius(), shape.border_width, 0.0, 0.0),
+        }
+    }
+}
+
+/// Draws a [`Shape`] with anti-aliased edges over a quad covering it, in the local space
+/// of the shape
+pub struct ShapePipeline(wgpu::RenderPipeline);
+
+impl ShapePipeline {
+    pub fn new(
+        device: &wgpu::Device,
+        _bind_group_layouts: &BindGroupLayouts,
+        texture_format: wgpu::TextureFormat,
+        sample_count: u32,
+    ) -> Self {
+        let shader_module = device.create_shader_module(include_wgsl!("shape.wgsl"));
+
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("ShapePipeline Layout"),
+            bind_group_layouts: &[],
+            push_constant_ranges: &[wgpu::PushConstantRange {
+                stages: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                range: 0..mem::size_of::<ShapeParams>() as u32,
+            }],
+        });
+
+        Self(pipelines::make_pipeline(
+            device,
+            PipelineTarget {
+                format: texture_format,
+                sample_count,
+            },
+            shader_module,
+            layout,
+            PosVertex::desc(),
+            Some(wgpu::BlendState::ALPHA_BLENDING),
+            "ShapePipeline",
+        ))
+    }
+
+    pub fn draw<'a>(
+        &'a self,
+        render_pass: &mut wgpu::RenderPass<'a>,
+        source: VertexSource<'a, PosVertex>,
+        transform: Mat4,
+        shape: &Shape,
+    ) {
+        render_pass.set_pipeline(&self.0);
+        render_pass.set_push_constants(
+            wgpu::ShaderStages::VERTEX_FRAGMENT,
+            0,
+            bytemuck::cast_slice(&[ShapeParams::new(shape, transform)]),
+        );
+        source.draw(render_pass);
+    }
+}
@@ -0,0 +1,49 @@
+struct VertexIn {
+    @location(0) position: vec3<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) local_position: vec2<f32>,
+}
+
+struct ShapeParams {
+    transform: mat4x4<f32>,
+    fill: vec4<f32>,
+    border: vec4<f32>,
+    // the center and the half size of the rounded box
+    bounds: vec4<f32>,
+    // the corner radius and the border width
+    radius_border: vec4<f32>,
+}
+
+var<push_constant> params: ShapeParams;
+
+// keep in sync with Shape::distance and Shape::color_at in rfvp-core
+fn distance(point: vec2<f32>) -> f32 {
+    let radius = params.radius_border.x;
+    let q = abs(point - params.bounds.xy) - params.bounds.zw + radius;
+    return length(max(q, vec2<f32>(0.0))) + min(max(q.x, q.y), 0.0) - radius;
+}
+
+fn coverage(distance: f32) -> f32 {
+    return clamp(0.5 - distance, 0.0, 1.0);
+}
+
+@vertex
+fn vertex_main(input: VertexIn) -> VertexOutput {
+    var output: VertexOutput;
+    output.clip_position = params.transform * vec4<f32>(input.position, 1.0);
+    output.local_position = input.position.xy;
+    return output;
+}
+
+@fragment
+fn fragment_main(input: VertexOutput) -> @location(0) vec4<f32> {
+    let d = distance(input.local_position);
+    // in screen pixels, so the edge stays a pixel wide when the layer is scaled
+    let pixel = max(length(vec2<f32>(dpdx(d), dpdy(d))), 1e-4);
+    let inner = coverage((d + params.radius_border.y) / pixel);
+    let color = mix(params.border, params.fill, inner);
+    return vec4<f32>(color.rgb, color.a * coverage(d / pixel));
+}
@@ -5,9 +5,9 @@ use glam::Mat4;
 use wgpu::include_wgsl;
 
 use crate::{
-    pipelines,
+    pipelines::{self, PipelineTarget},
     vertices::{PosColTexVertex, VertexSource},
-    BindGroupLayouts, TextureBindGroup,
+    BindGroupLayouts, BlendMode, TextureBindGroup,
 };
 
 #[derive(Pod, Zeroable, Copy, Clone, Debug)]
@@ -16,45 +16,41 @@ struct SpriteParams {
     pub transform: Mat4,
 }
 
-pub struct SpritePipeline(wgpu::RenderPipeline);
+/// A pipeline per [`BlendMode`], in the order of [`BlendMode::ALL`]
+pub struct SpritePipeline([wgpu::RenderPipeline; 3]);
 
 impl SpritePipeline {
     pub fn new(
         device: &wgpu::Device,
         bind_group_layouts: &BindGroupLayouts,
         texture_format: wgpu::TextureFormat,
+        sample_count: u32,
     ) -> Self {
-        let shader_module = device.create_shader_module(include_wgsl!("sprite.wgsl"));
+        Self(BlendMode::ALL.map(|mode| {
+            let shader_module = device.create_shader_module(include_wgsl!("sprite.wgsl"));
 
-        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-            label: Some("SpritePipeline Layout"),
-            bind_group_layouts: &[&bind_group_layouts.texture],
-            push_constant_ranges: &[wgpu::PushConstantRange {
-                stages: wgpu::ShaderStages::VERTEX_FRAGMENT,
-                range: 0..(mem::size_of::<SpriteParams>() as u32),
-            }],
-        });
+            let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("SpritePipeline Layout"),
+                bind_group_layouts: &[&bind_group_layouts.texture],
+                push_constant_ranges: &[wgpu::PushConstantRange {
+                    stages: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                    range: 0..(mem::size_of::<SpriteParams>() as u32),
+                }],
+            });
 
-        Self(pipelines::make_pipeline(
-            device,
-            texture_format,
-            shader_module,
-            layout,
-            PosColTexVertex::desc(),
-            Some(wgpu::BlendState {
-                color: wgpu::BlendComponent {
-                    src_factor: wgpu::BlendFactor::SrcAlpha,
-                    dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
-                    operation: wgpu::BlendOperation::Add,
+            pipelines::make_pipeline(
+                device,
+                PipelineTarget {
+                    format: texture_format,
+                    sample_count,
                 },
-                alpha: wgpu::BlendComponent {
-                    src_factor: wgpu::BlendFactor::OneMinusDstAlpha,
-                    dst_factor: wgpu::BlendFactor::One,
-                    operation: wgpu::BlendOperation::Add,
-                },
-            }),
-            "SpritePipeline",
-        ))
+                shader_module,
+                layout,
+                PosColTexVertex::desc(),
+                Some(mode.blend_state()),
+                &format!("SpritePipeline {:?}", mode),
+            )
+        }))
     }
 
     pub fn draw<'a>(
@@ -64,7 +60,18 @@ impl SpritePipeline {
         texture: &'a TextureBindGroup,
         transform: Mat4,
     ) {
-        render_pass.set_pipeline(&self.0);
+        self.draw_blended(render_pass, source, texture, transform, BlendMode::Alpha);
+    }
+
+    pub fn draw_blended<'a>(
+        &'a self,
+        render_pass: &mut wgpu::RenderPass<'a>,
+        source: VertexSource<'a, PosColTexVertex>,
+        texture: &'a TextureBindGroup,
+        transform: Mat4,
+        blend: BlendMode,
+    ) {
+        render_pass.set_pipeline(&self.0[blend as usize]);
         render_pass.set_bind_group(0, &texture.0, &[]);
         render_pass.set_push_constants(
             wgpu::ShaderStages::VERTEX_FRAGMENT,
@@ -6,7 +6,7 @@ use rfvp_core::time::Ticks;
 use wgpu::include_wgsl;
 
 use crate::{
-    pipelines,
+    pipelines::{self, PipelineTarget},
     vertices::{TextVertex, VertexSource},
     BindGroupLayouts, TextureBindGroup,
 };
@@ -26,6 +26,7 @@ impl TextOutlinePipeline {
         device: &wgpu::Device,
         bind_group_layouts: &BindGroupLayouts,
         texture_format: wgpu::TextureFormat,
+        sample_count: u32,
     ) -> Self {
         let shader_module = device.create_shader_module(include_wgsl!("text_outline.wgsl"));
 
@@ -42,7 +43,10 @@ impl TextOutlinePipeline {
 
         Self(pipelines::make_pipeline(
             device,
-            texture_format,
+            PipelineTarget {
+                format: texture_format,
+                sample_count,
+            },
             shader_module,
             layout,
             desc,
@@ -5,7 +5,7 @@ use glam::Mat4;
 use wgpu::include_wgsl;
 
 use crate::{
-    pipelines,
+    pipelines::{self, PipelineTarget},
     vertices::{PosColTexVertex, VertexSource},
     BindGroupLayouts, YuvTextureBindGroup,
 };
@@ -23,6 +23,7 @@ impl YuvSpritePipeline {
         device: &wgpu::Device,
         bind_group_layouts: &BindGroupLayouts,
         texture_format: wgpu::TextureFormat,
+        sample_count: u32,
     ) -> Self {
         let shader_module = device.create_shader_module(include_wgsl!("yuv_sprite.wgsl"));
 
@@ -37,7 +38,10 @@ impl YuvSpritePipeline {
 
         Self(pipelines::make_pipeline(
             device,
-            texture_format,
+            PipelineTarget {
+                format: texture_format,
+                sample_count,
+            },
             shader_module,
             layout,
             PosColTexVertex::desc(),
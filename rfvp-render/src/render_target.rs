@@ -14,6 +14,8 @@ pub struct RenderTarget {
     texture: wgpu::Texture,
     srgb_view: wgpu::TextureView,
     raw_view: wgpu::TextureView,
+    /// multisampled views resolved into the views above, only present with MSAA on
+    msaa_views: Option<(wgpu::TextureView, wgpu::TextureView)>,
     sampler: wgpu::Sampler,
     bind_group: TextureBindGroup,
     vertices: SpriteVertexBuffer,
@@ -70,10 +72,12 @@ impl RenderTarget {
             Some(&format!("{} TextureBindGroup", label)),
         );
         let vertices = SpriteVertexBuffer::new_fullscreen(resources);
+        let msaa_views = Self::create_msaa_views(resources, size, &label);
         Self {
             texture,
             srgb_view,
             raw_view,
+            msaa_views,
             sampler,
             bind_group,
             vertices,
@@ -82,6 +86,12 @@ impl RenderTarget {
     }
 
     pub fn resize(&mut self, resources: &GpuCommonResources, size: (u32, u32)) {
+        if size.0 == 0 || size.1 == 0 {
+            // a tiny window can round the render buffer down to nothing, keep the old textures
+            tracing::warn!("{}: ignoring resize to {:?}", self.label, size);
+            return;
+        }
+
         self.texture = resources.device.create_texture(&wgpu::TextureDescriptor {
             label: Some(&format!("{} Texture", self.label)),
             size: wgpu::Extent3d {
@@ -94,12 +104,19 @@ impl RenderTarget {
             dimension: wgpu::TextureDimension::D2,
             format: Self::SRGB_FORMAT,
             usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
-            view_formats: &[],
+            view_formats: &[Self::RAW_FORMAT],
         });
         self.srgb_view = self.texture.create_view(&wgpu::TextureViewDescriptor {
-            label: Some(&format!("{} TextureView", self.label)),
+            label: Some(&format!("{} Srgb TextureView", self.label)),
+            format: Some(Self::SRGB_FORMAT),
+            ..Default::default()
+        });
+        self.raw_view = self.texture.create_view(&wgpu::TextureViewDescriptor {
+            label: Some(&format!("{} Raw TextureView", self.label)),
+            format: Some(Self::RAW_FORMAT),
             ..Default::default()
         });
+        self.msaa_views = Self::create_msaa_views(resources, size, &self.label);
         self.bind_group = TextureBindGroup::new(
             resources,
             &self.srgb_view,
@@ -108,6 +125,43 @@ impl RenderTarget {
         );
     }
 
+    fn create_msaa_views(
+        resources: &GpuCommonResources,
+        size: (u32, u32),
+        label: &str,
+    ) -> Option<(wgpu::TextureView, wgpu::TextureView)> {
+        let texture_label = format!("{} Multisampled Texture", label);
+        let desc = resources.msaa.color_target_descriptor(
+            Some(&texture_label),
+            size,
+            Self::SRGB_FORMAT,
+            &[Self::RAW_FORMAT],
+        )?;
+        let texture = resources.device.create_texture(&desc);
+        let srgb_view = texture.create_view(&wgpu::TextureViewDescriptor {
+            label: Some(&format!("{} Multisampled Srgb TextureView", label)),
+            format: Some(Self::SRGB_FORMAT),
+            ..Default::default()
+        });
+        let raw_view = texture.create_view(&wgpu::TextureViewDescriptor {
+            label: Some(&format!("{} Multisampled Raw TextureView", label)),
+            format: Some(Self::RAW_FORMAT),
+            ..Default::default()
+        });
+        Some((srgb_view, raw_view))
+    }
+
+    /// The view to render into and the view it is resolved to
+    fn attachment_views<'a>(
+        view: &'a wgpu::TextureView,
+        msaa_view: Option<&'a wgpu::TextureView>,
+    ) -> (&'a wgpu::TextureView, Option<&'a wgpu::TextureView>) {
+        match msaa_view {
+            Some(msaa_view) => (msaa_view, Some(view)),
+            None => (view, None),
+        }
+    }
+
     pub fn projection_matrix(&self) -> Mat4 {
         let mut projection = Mat4::IDENTITY;
         projection.x_axis.x = 2.0 / VIRTUAL_WIDTH;
@@ -127,11 +181,15 @@ impl RenderTarget {
         encoder: &'a mut wgpu::CommandEncoder,
         label: Option<&str>,
     ) -> wgpu::RenderPass<'a> {
+        let (view, resolve_target) = Self::attachment_views(
+            &self.srgb_view,
+            self.msaa_views.as_ref().map(|views| &views.0),
+        );
         encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
             label,
             color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                view: &self.srgb_view,
-                resolve_target: None,
+                view,
+                resolve_target,
                 ops: wgpu::Operations {
                     load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
                     store: wgpu::StoreOp::Store,
@@ -148,11 +206,15 @@ impl RenderTarget {
         encoder: &'a mut wgpu::CommandEncoder,
         label: Option<&str>,
     ) -> wgpu::RenderPass<'a> {
+        let (view, resolve_target) = Self::attachment_views(
+            &self.raw_view,
+            self.msaa_views.as_ref().map(|views| &views.1),
+        );
         encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
             label,
             color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                view: &self.raw_view,
-                resolve_target: None,
+                view,
+                resolve_target,
                 ops: wgpu::Operations {
                     load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
                     store: wgpu::StoreOp::Store,
@@ -0,0 +1,84 @@
+/// The format a surface is configured with, and the format it's rendered to with.
+///
+/// Everything is composed in sRGB textures, which are sampled as linear colors. The surface has
+/// to encode them back to sRGB when written to, or the output looks dark or washed out.
+/// A linear surface is rendered to through an sRGB view where the backend allows it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SurfaceFormat {
+    pub surface: wgpu::TextureFormat,
+    pub view: wgpu::TextureFormat,
+}
+
+impl SurfaceFormat {
+    /// Picks the first sRGB format of the surface capabilities, or the first format if none is.
+    ///
+    /// `srgb_views` tells whether the backend can view a linear surface as sRGB, WebGL can't.
+    /// Returns `None` if `formats` is empty, the surface is unsupported by the adapter.
+    pub fn select(formats: &[wgpu::TextureFormat], srgb_views: bool) -> Option<Self> {
+        if let Some(&format) = formats.iter().find(|format| format.is_srgb()) {
+            return Some(Self {
+                surface: format,
+                view: format,
+            });
+        }
+
+        let surface = *formats.first()?;
+        let view = if srgb_views {
+            surface.add_srgb_suffix()
+        } else {
+            surface
+        };
+        Some(Self { surface, view })
+    }
+
+    /// Whether the colors written to the surface are encoded to sRGB
+    pub fn is_gamma_correct(&self) -> bool {
+        self.view.is_srgb()
+    }
+
+    /// The `view_formats` of the surface configuration
+    pub fn view_formats(&self) -> Vec<wgpu::TextureFormat> {
+        if self.view == self.surface {
+            vec![]
+        } else {
+            vec![self.view]
+        }
+    }
+
+    /// The descriptor of the views to render to the surface textures with
+    pub fn view_descriptor(&self) -> wgpu::TextureViewDescriptor<'static> {
+        wgpu::TextureViewDescriptor {
+            format: Some(self.view),
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use wgpu::TextureFormat;
+
+    use super::*;
+
+    #[test]
+    fn test_select() {
+        let formats = [TextureFormat::Bgra8Unorm, TextureFormat::Bgra8UnormSrgb];
+        let format = SurfaceFormat::select(&formats, true).unwrap();
+        assert_eq!(format.surface, TextureFormat::Bgra8UnormSrgb);
+        assert_eq!(format.view, TextureFormat::Bgra8UnormSrgb);
+        assert!(format.view_formats().is_empty());
+
+        // only linear formats, viewed as sRGB
+        let formats = [TextureFormat::Rgba8Unorm, TextureFormat::Rgb10a2Unorm];
+        let format = SurfaceFormat::select(&formats, true).unwrap();
+        assert_eq!(format.surface, TextureFormat::Rgba8Unorm);
+        assert_eq!(format.view_formats(), vec![TextureFormat::Rgba8UnormSrgb]);
+        assert!(format.is_gamma_correct());
+
+        let format = SurfaceFormat::select(&formats, false).unwrap();
+        assert_eq!(format.view, TextureFormat::Rgba8Unorm);
+        assert!(!format.is_gamma_correct());
+
+        assert_eq!(SurfaceFormat::select(&[], true), None);
+    }
+}
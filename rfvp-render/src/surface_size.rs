@@ -0,0 +1,111 @@
+/// What the window has to do with the surface after a resize
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SurfaceResize {
+    /// same size as before, nothing to do
+    Unchanged,
+    /// the window went to 0x0 (minimized), rendering has to stop
+    Minimized,
+    /// reconfigure the surface with the new size
+    Resized((u32, u32)),
+    /// back from minimized, reconfigure the surface and redraw everything
+    /// (the swapchain may have been left unconfigured while hidden)
+    Restored((u32, u32)),
+}
+
+impl SurfaceResize {
+    /// the size to configure the surface with, if it needs reconfiguring
+    pub fn configure_size(self) -> Option<(u32, u32)> {
+        match self {
+            SurfaceResize::Resized(size) | SurfaceResize::Restored(size) => Some(size),
+            SurfaceResize::Unchanged | SurfaceResize::Minimized => None,
+        }
+    }
+}
+
+/// Tracks the size of the window surface.
+///
+/// A minimized window reports a zero size, a surface can't be configured with
+/// it and there is nothing to render to, so rendering is skipped until the
+/// window is restored.
+#[derive(Debug, Clone)]
+pub struct SurfaceSize {
+    size: (u32, u32),
+    minimized: bool,
+}
+
+impl SurfaceSize {
+    pub fn new(size: (u32, u32)) -> Self {
+        Self {
+            size,
+            minimized: size.0 == 0 || size.1 == 0,
+        }
+    }
+
+    pub fn resize(&mut self, new_size: (u32, u32)) -> SurfaceResize {
+        if new_size.0 == 0 || new_size.1 == 0 {
+            if self.minimized {
+                return SurfaceResize::Unchanged;
+            }
+            self.minimized = true;
+            return SurfaceResize::Minimized;
+        }
+
+        if self.minimized {
+            self.minimized = false;
+            self.size = new_size;
+            return SurfaceResize::Restored(new_size);
+        }
+
+        if new_size == self.size {
+            return SurfaceResize::Unchanged;
+        }
+
+        self.size = new_size;
+        SurfaceResize::Resized(new_size)
+    }
+
+    /// the last size which can be rendered to
+    pub fn size(&self) -> (u32, u32) {
+        self.size
+    }
+
+    pub fn is_minimized(&self) -> bool {
+        self.minimized
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_minimize_skips_render() {
+        let mut size = SurfaceSize::new((1280, 720));
+        assert!(!size.is_minimized());
+
+        assert_eq!(size.resize((0, 0)), SurfaceResize::Minimized);
+        assert!(size.is_minimized());
+        assert_eq!(size.resize((0, 720)), SurfaceResize::Unchanged);
+        assert_eq!(size.size(), (1280, 720));
+
+        // restoring to the same size still reconfigures the surface
+        assert_eq!(size.resize((1280, 720)), SurfaceResize::Restored((1280, 720)));
+        assert!(!size.is_minimized());
+        assert_eq!(size.resize((1280, 720)), SurfaceResize::Unchanged);
+    }
+
+    #[test]
+    fn test_configure_sequence() {
+        let mut size = SurfaceSize::new((1280, 720));
+        let mut configured = Vec::new();
+
+        // a scale factor change can report the new size before the Resized event repeats it
+        for new_size in [(0, 0), (0, 0), (1280, 720), (1280, 720), (1920, 1080), (1920, 1080)] {
+            if let Some(new_size) = size.resize(new_size).configure_size() {
+                configured.push(new_size);
+            }
+        }
+
+        assert_eq!(configured, [(1280, 720), (1920, 1080)]);
+    }
+}
@@ -0,0 +1,185 @@
+use glam::{uvec2, vec4, UVec2, Vec4};
+
+/// What's done with a picture larger than the textures the GPU can create
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum OversizePolicy {
+    /// split into tiles drawn side by side, keeps every pixel
+    #[default]
+    Tile,
+    /// scaled down to fit, one texture but blurrier
+    Downscale,
+}
+
+/// The largest texture of the device and what to do past it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TextureLimit {
+    pub max_dimension: u32,
+    pub oversize: OversizePolicy,
+}
+
+impl TextureLimit {
+    pub fn for_device(device: &wgpu::Device, oversize: OversizePolicy) -> Self {
+        Self {
+            max_dimension: device.limits().max_texture_dimension_2d,
+            oversize,
+        }
+    }
+
+    pub fn fits(&self, size: UVec2) -> bool {
+        size.x <= self.max_dimension && size.y <= self.max_dimension
+    }
+}
+
+/// A part of a picture uploaded as its own texture
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Tile {
+    /// the pixels of the picture the quad of the tile covers
+    pub position: UVec2,
+    pub size: UVec2,
+    /// the pixels uploaded: the ones of the quad and a texel of the neighbours around
+    /// them, so the filtering at the seams blends with the next tile like in one texture
+    pub texture_position: UVec2,
+    pub texture_size: UVec2,
+}
+
+impl Tile {
+    /// the texture coordinates of the quad, left, top, right and bottom
+    pub fn uv(&self) -> Vec4 {
+        let start = (self.position - self.texture_position).as_vec2() / self.texture_size.as_vec2();
+        let end = (self.position + self.size - self.texture_position).as_vec2()
+            / self.texture_size.as_vec2();
+        vec4(start.x, start.y, end.x, end.y)
+    }
+}
+
+/// the spans of the tiles along an axis: the start and the length of the quad, then of
+/// the texture
+fn spans(len: u32, max_dimension: u32) -> Vec<(u32, u32, u32, u32)> {
+    if len <= max_dimension {
+        return vec![(0, len, 0, len)];
+    }
+    // room for the texel of the neighbour on both sides
+    let step = max_dimension.max(3) - 2;
+    (0..len)
+        .step_by(step as usize)
+        .map(|start| {
+            let end = (start + step).min(len);
+            let texture_start = start.saturating_sub(1);
+            let texture_end = (end + 1).min(len);
+            (start, end - start, texture_start, texture_end - texture_start)
+        })
+        .collect()
+}
+
+/// Splits a picture of `size` into textures no larger than `max_dimension`, row by row.
+/// A picture which fits is a single tile.
+pub fn tile_grid(size: UVec2, max_dimension: u32) -> Vec<Tile> {
+    let columns = spans(size.x, max_dimension);
+    let rows = spans(size.y, max_dimension);
+    rows.iter()
+        .flat_map(|&(y, height, texture_y, texture_height)| {
+            columns
+                .iter()
+                .map(move |&(x, width, texture_x, texture_width)| Tile {
+                    position: uvec2(x, y),
+                    size: uvec2(width, height),
+                    texture_position: uvec2(texture_x, texture_y),
+                    texture_size: uvec2(texture_width, texture_height),
+                })
+        })
+        .collect()
+}
+
+/// The size of a picture scaled down to fit in `max_dimension`, keeping its aspect
+pub fn downscaled_size(size: UVec2, max_dimension: u32) -> UVec2 {
+    let largest = size.max_element();
+    if largest <= max_dimension {
+        return size;
+    }
+    let scale = max_dimension as f64 / largest as f64;
+    let scaled = |len: u32| ((len as f64 * scale).round() as u32).clamp(1, max_dimension);
+    uvec2(scaled(size.x), scaled(size.y))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tile_grid() {
+        let tiles = tile_grid(uvec2(4096, 128), 2048);
+        assert_eq!(tiles.len(), 3);
+        assert!(tiles.iter().all(|tile| tile.texture_size.max_element() <= 2048));
+        // the quads cover the picture once, without gaps
+        let mut x = 0;
+        for tile in &tiles {
+            assert_eq!(tile.position, uvec2(x, 0));
+            assert_eq!(tile.size.y, 128);
+            x += tile.size.x;
+        }
+        assert_eq!(x, 4096);
+
+        // the neighbours share the texels on both sides of a seam
+        for pair in tiles.windows(2) {
+            let seam = pair[1].position.x;
+            assert_eq!(pair[0].texture_position.x + pair[0].texture_size.x, seam + 1);
+            assert_eq!(pair[1].texture_position.x, seam - 1);
+        }
+
+        assert_eq!(
+            tile_grid(uvec2(1000, 500), 2048),
+            [Tile {
+                position: UVec2::ZERO,
+                size: uvec2(1000, 500),
+                texture_position: UVec2::ZERO,
+                texture_size: uvec2(1000, 500),
+            }]
+        );
+        assert_eq!(tile_grid(uvec2(3000, 3000), 2048).len(), 4);
+    }
+
+    /// filters a row of texels like the sampler, at `u` in texture coordinates
+    fn sample_linear(row: &[f32], u: f32) -> f32 {
+        let x = (u * row.len() as f32 - 0.5).clamp(0.0, row.len() as f32 - 1.0);
+        let left = x.floor() as usize;
+        let right = (left + 1).min(row.len() - 1);
+        let t = x - left as f32;
+        row[left] * (1.0 - t) + row[right] * t
+    }
+
+    #[test]
+    fn test_seams() {
+        // a row with a different value in every texel, a seam would show as a jump
+        let row: Vec<f32> = (0..4096).map(|x| (x * 7 % 256) as f32).collect();
+        let tiles = tile_grid(uvec2(4096, 128), 2048);
+
+        for tile in &tiles {
+            let start = tile.texture_position.x as usize;
+            let texture = &row[start..start + tile.texture_size.x as usize];
+            let uv = tile.uv();
+            // the pixels of the quad, including both edges, sample as the whole row does
+            for x in [0.0, 0.25, 0.5, tile.size.x as f32 - 0.5, tile.size.x as f32] {
+                let picture_x = tile.position.x as f32 + x;
+                let u = uv.x + (uv.z - uv.x) * x / tile.size.x as f32;
+                let expected = sample_linear(&row, picture_x / row.len() as f32);
+                let sampled = sample_linear(texture, u);
+                assert!(
+                    (sampled - expected).abs() < 1e-3,
+                    "{} at {}: {} != {}",
+                    tile.position.x,
+                    x,
+                    sampled,
+                    expected
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_downscaled_size() {
+        assert_eq!(downscaled_size(uvec2(4096, 128), 2048), uvec2(2048, 64));
+        assert_eq!(downscaled_size(uvec2(3000, 2000), 2048), uvec2(2048, 1365));
+        assert_eq!(downscaled_size(uvec2(1920, 1080), 2048), uvec2(1920, 1080));
+        assert_eq!(downscaled_size(uvec2(100_000, 1), 2048), uvec2(2048, 1));
+    }
+}
@@ -89,16 +89,20 @@ impl<T: Vertex> VertexBuffer<T> {
         }
     }
 
-    // pub fn vertex_source_slice(&self, range: std::ops::Range<u32>) -> VertexSource<T> {
-    //     assert!(range.end <= self.num_vertices.load(Ordering::SeqCst));
-    //
-    //     VertexSource::VertexBuffer {
-    //         vertex_buffer: &self.buffer,
-    //         vertices: range,
-    //         instances: 0..1,
-    //         phantom: std::marker::PhantomData,
-    //     }
-    // }
+    pub fn num_vertices(&self) -> u32 {
+        self.num_vertices.load(Ordering::SeqCst)
+    }
+
+    pub fn vertex_source_slice(&self, range: std::ops::Range<u32>) -> VertexSource<T> {
+        assert!(range.end <= self.num_vertices.load(Ordering::SeqCst));
+
+        VertexSource::VertexBuffer {
+            vertex_buffer: &self.buffer,
+            vertices: range,
+            instances: 0..1,
+            phantom: std::marker::PhantomData,
+        }
+    }
 }
 
 pub struct IndexBuffer {
@@ -138,31 +142,41 @@ impl SpriteVertexBuffer {
         resources: &GpuCommonResources,
         (l, t, r, b): (f32, f32, f32, f32),
         color: Vec4,
+    ) -> Self {
+        Self::new_with_uv(resources, (l, t, r, b), vec4(0.0, 0.0, 1.0, 1.0), color)
+    }
+
+    /// A quad showing the `uv` part of the texture, left, top, right and bottom
+    pub fn new_with_uv(
+        resources: &GpuCommonResources,
+        (l, t, r, b): (f32, f32, f32, f32),
+        uv: Vec4,
+        color: Vec4,
     ) -> Self {
         let vertices = [
             // 0
             PosColTexVertex {
                 position: vec3(l, b, 0.0),
                 color,
-                texture_coordinate: vec2(0.0, 1.0),
+                texture_coordinate: vec2(uv.x, uv.w),
             },
             // 1
             PosColTexVertex {
                 position: vec3(l, t, 0.0),
                 color,
-                texture_coordinate: vec2(0.0, 0.0),
+                texture_coordinate: vec2(uv.x, uv.y),
             },
             // 2
             PosColTexVertex {
                 position: vec3(r, b, 0.0),
                 color,
-                texture_coordinate: vec2(1.0, 1.0),
+                texture_coordinate: vec2(uv.z, uv.w),
             },
             // 3
             PosColTexVertex {
                 position: vec3(r, t, 0.0),
                 color,
-                texture_coordinate: vec2(1.0, 0.0),
+                texture_coordinate: vec2(uv.z, uv.y),
             },
         ];
 
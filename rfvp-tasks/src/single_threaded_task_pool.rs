@@ -71,6 +71,16 @@ impl TaskPool {
         1
     }
 
+    /// Return the number of worker threads which haven't exited yet, there are none
+    pub fn live_threads(&self) -> usize {
+        0
+    }
+
+    /// Nothing to stop, everything runs on the main thread
+    pub fn shutdown(&self, _timeout: std::time::Duration) -> Vec<String> {
+        Vec::new()
+    }
+
     /// Allows spawning non-`static futures on the thread pool. The function takes a callback,
     /// passing a scope object into it. The scope object provided to the callback can be used
     /// to spawn tasks. This function will await the completion of all tasks before returning.
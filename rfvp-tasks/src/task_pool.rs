@@ -5,6 +5,7 @@ use std::{
     panic::AssertUnwindSafe,
     sync::Arc,
     thread::{self, JoinHandle},
+    time::{Duration, Instant},
 };
 
 use async_task::FallibleTask;
@@ -189,6 +190,30 @@ impl TaskPool {
         self.threads.len()
     }
 
+    /// Return the number of worker threads which haven't exited yet
+    pub fn live_threads(&self) -> usize {
+        self.threads.iter().filter(|t| !t.is_finished()).count()
+    }
+
+    /// Signal the worker threads to stop and wait up to `timeout` for them to exit.
+    ///
+    /// Returns the names of the threads still running when the timeout expired.
+    /// Tasks spawned on the pool afterwards never run.
+    pub fn shutdown(&self, timeout: Duration) -> Vec<String> {
+        self.shutdown_tx.close();
+
+        let deadline = Instant::now() + timeout;
+        while self.live_threads() > 0 && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(1));
+        }
+
+        self.threads
+            .iter()
+            .filter(|t| !t.is_finished())
+            .map(|t| t.thread().name().unwrap_or("unnamed").to_string())
+            .collect()
+    }
+
     /// Allows spawning non-`'static` futures on the thread pool. The function takes a callback,
     /// passing a scope object into it. The scope object provided to the callback can be used
     /// to spawn tasks. This function will await the completion of all tasks before returning.
@@ -660,6 +685,23 @@ mod tests {
         assert_eq!(count.load(Ordering::Relaxed), 100);
     }
 
+    #[test]
+    fn test_shutdown() {
+        let pool = TaskPoolBuilder::new()
+            .num_threads(4)
+            .thread_name("Shutdown Test".to_string())
+            .build();
+        assert_eq!(pool.live_threads(), 4);
+
+        let outputs = pool.scope(|scope| scope.spawn(async { 1 }));
+        assert_eq!(outputs, [1]);
+
+        assert!(pool.shutdown(Duration::from_secs(3)).is_empty());
+        assert_eq!(pool.live_threads(), 0);
+        // a second shutdown (and the drop) are no-ops
+        assert!(pool.shutdown(Duration::ZERO).is_empty());
+    }
+
     #[test]
     fn test_thread_callbacks() {
         let counter = Arc::new(AtomicI32::new(0));
@@ -112,6 +112,22 @@ impl Deref for IoTaskPool {
     }
 }
 
+/// Stop the worker threads of the initialized global task pools, waiting up to `timeout` for each.
+///
+/// Returns the names of the threads which didn't stop in time.
+pub fn shutdown_global_task_pools(timeout: std::time::Duration) -> Vec<String> {
+    let pools = [
+        COMPUTE_TASK_POOL.get().map(|p| &p.0),
+        ASYNC_COMPUTE_TASK_POOL.get().map(|p| &p.0),
+        IO_TASK_POOL.get().map(|p| &p.0),
+    ];
+    pools
+        .into_iter()
+        .flatten()
+        .flat_map(|pool| pool.shutdown(timeout))
+        .collect()
+}
+
 /// A function used by `bevy_core` to tick the global tasks pools on the main thread.
 /// This will run a maximum of 100 local tasks per executor per call to this function.
 ///
@@ -0,0 +1,354 @@
+//! Synthetic HCB scripts for the tests, benchmarks and fuzzing harnesses.
+//!
+//! Real game scripts can't be shipped with the repository, so the VM is exercised
+//! with small programs assembled here byte by byte. The opcodes are the ones of the
+//! engine's [`Opcode`] table, the crate only produces bytes so any crate can use it
+//! as a dev-dependency, rfvp-core included. [`run_main!`] runs them to completion.
+
+use bytes::Bytes;
+use rfvp_core::format::scenario::instructions::Opcode;
+
+/// the code section starts right after the sysdesc offset
+pub const CODE_START: u32 = 4;
+
+/// build a minimal HCB from raw code, the code is placed at offset 4
+pub fn build_hcb(code: &[u8], entry: u32, syscalls: &[(u8, &str)]) -> Bytes {
+    let mut buf = Vec::new();
+    buf.extend_from_slice(&(CODE_START + code.len() as u32).to_le_bytes());
+    buf.extend_from_slice(code);
+    buf.extend_from_slice(&entry.to_le_bytes());
+    // non-volatile, volatile globals and game mode
+    buf.extend_from_slice(&[0, 0, 0, 0, 0, 0]);
+    buf.push(5);
+    buf.extend_from_slice(b"test\0");
+    buf.extend_from_slice(&(syscalls.len() as u16).to_le_bytes());
+    for (args, name) in syscalls {
+        buf.push(*args);
+        buf.push(name.len() as u8 + 1);
+        buf.extend_from_slice(name.as_bytes());
+        buf.push(0);
+    }
+    buf.extend_from_slice(&0u16.to_le_bytes());
+    buf.into()
+}
+
+/// A jump whose target isn't known yet, see [`CodeBuilder::patch`]
+#[must_use]
+pub struct Fixup(usize);
+
+/// Assembles code for [`build_hcb`], addresses are absolute
+#[derive(Default)]
+pub struct CodeBuilder {
+    code: Vec<u8>,
+}
+
+impl CodeBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// address of the next instruction
+    pub fn addr(&self) -> u32 {
+        CODE_START + self.code.len() as u32
+    }
+
+    fn op(&mut self, opcode: Opcode) -> &mut Self {
+        self.code.push(opcode as u8);
+        self
+    }
+
+    pub fn init_stack(&mut self, args: u8, locals: u8) -> &mut Self {
+        self.op(Opcode::InitStack);
+        self.code.extend_from_slice(&[args, locals]);
+        self
+    }
+
+    pub fn call(&mut self, addr: u32) -> &mut Self {
+        self.op(Opcode::Call);
+        self.code.extend_from_slice(&addr.to_le_bytes());
+        self
+    }
+
+    /// call a function which is emitted later
+    pub fn call_forward(&mut self) -> Fixup {
+        self.call(0);
+        Fixup(self.code.len() - 4)
+    }
+
+    /// `id` indexes the syscalls passed to [`build_hcb`]
+    pub fn syscall(&mut self, id: u16) -> &mut Self {
+        self.op(Opcode::Syscall);
+        self.code.extend_from_slice(&id.to_le_bytes());
+        self
+    }
+
+    pub fn ret(&mut self) -> &mut Self {
+        self.op(Opcode::Ret)
+    }
+
+    pub fn retv(&mut self) -> &mut Self {
+        self.op(Opcode::RetV)
+    }
+
+    pub fn jmp(&mut self, addr: u32) -> &mut Self {
+        self.op(Opcode::Jmp);
+        self.code.extend_from_slice(&addr.to_le_bytes());
+        self
+    }
+
+    /// jump if the top of the stack is nil (only nil is false, even 0 is true),
+    /// to an address set later
+    pub fn jz_forward(&mut self) -> Fixup {
+        self.op(Opcode::Jz);
+        self.code.extend_from_slice(&0u32.to_le_bytes());
+        Fixup(self.code.len() - 4)
+    }
+
+    /// point a forward jump or call at `addr`
+    pub fn patch(&mut self, fixup: Fixup, addr: u32) {
+        self.code[fixup.0..fixup.0 + 4].copy_from_slice(&addr.to_le_bytes());
+    }
+
+    pub fn push_i32(&mut self, value: i32) -> &mut Self {
+        self.op(Opcode::PushI32);
+        self.code.extend_from_slice(&value.to_le_bytes());
+        self
+    }
+
+    pub fn push_f32(&mut self, value: f32) -> &mut Self {
+        self.op(Opcode::PushF32);
+        self.code.extend_from_slice(&value.to_le_bytes());
+        self
+    }
+
+    /// # Panics
+    ///
+    /// Panics if the string doesn't fit into a single PushString.
+    pub fn push_string(&mut self, value: &str) -> &mut Self {
+        assert!(value.len() < 0xFF, "string too long for a PushString");
+        self.op(Opcode::PushString);
+        self.code.push(value.len() as u8 + 1);
+        self.code.extend_from_slice(value.as_bytes());
+        self.code.push(0);
+        self
+    }
+
+    pub fn push_stack(&mut self, offset: i8) -> &mut Self {
+        self.op(Opcode::PushStack);
+        self.code.extend_from_slice(&[offset as u8]);
+        self
+    }
+
+    pub fn push_local_table(&mut self, offset: i8) -> &mut Self {
+        self.op(Opcode::PushLocalTable);
+        self.code.extend_from_slice(&[offset as u8]);
+        self
+    }
+
+    pub fn push_return(&mut self) -> &mut Self {
+        self.op(Opcode::PushReturn)
+    }
+
+    pub fn pop_stack(&mut self, offset: i8) -> &mut Self {
+        self.op(Opcode::PopStack);
+        self.code.extend_from_slice(&[offset as u8]);
+        self
+    }
+
+    pub fn pop_global_table(&mut self, key: u16) -> &mut Self {
+        self.op(Opcode::PopGlobalTable);
+        self.code.extend_from_slice(&key.to_le_bytes());
+        self
+    }
+
+    pub fn pop_local_table(&mut self, offset: i8) -> &mut Self {
+        self.op(Opcode::PopLocalTable);
+        self.code.extend_from_slice(&[offset as u8]);
+        self
+    }
+
+    pub fn add(&mut self) -> &mut Self {
+        self.op(Opcode::Add)
+    }
+
+    pub fn sub(&mut self) -> &mut Self {
+        self.op(Opcode::Sub)
+    }
+
+    pub fn push_nil(&mut self) -> &mut Self {
+        self.op(Opcode::PushNil)
+    }
+
+    pub fn push_true(&mut self) -> &mut Self {
+        self.op(Opcode::PushTrue)
+    }
+
+    /// `a == b`, `b` is the top of the stack
+    pub fn sete(&mut self) -> &mut Self {
+        self.op(Opcode::SetE)
+    }
+
+    pub fn setne(&mut self) -> &mut Self {
+        self.op(Opcode::SetNE)
+    }
+
+    pub fn setg(&mut self) -> &mut Self {
+        self.op(Opcode::SetG)
+    }
+
+    /// `<=`, see [`Opcode`] for the order of the comparisons
+    pub fn setle(&mut self) -> &mut Self {
+        self.op(Opcode::SetLE)
+    }
+
+    pub fn setl(&mut self) -> &mut Self {
+        self.op(Opcode::SetL)
+    }
+
+    pub fn setge(&mut self) -> &mut Self {
+        self.op(Opcode::SetGE)
+    }
+
+    pub fn code(&self) -> &[u8] {
+        &self.code
+    }
+
+    pub fn finish(&self, entry: u32) -> Bytes {
+        build_hcb(&self.code, entry, &[])
+    }
+}
+
+/// Emits `for local0 in 0..count { body }` into a function with at least one local,
+/// the counter is kept in local 0
+fn counted_loop(code: &mut CodeBuilder, count: i32, body: impl FnOnce(&mut CodeBuilder)) {
+    code.push_i32(0).pop_stack(0);
+    let head = code.addr();
+    code.push_i32(count).push_stack(0).setg();
+    let exit = code.jz_forward();
+    body(code);
+    code.push_stack(0).push_i32(1).add().pop_stack(0);
+    code.jmp(head);
+    let end = code.addr();
+    code.patch(exit, end);
+}
+
+/// Runs the main thread of a `Scripter` over a `Scenario` until it returns from the
+/// entry point, evaluates to its return value.
+///
+/// A macro rather than a function: inside rfvp-core's own unit tests the `Scripter`
+/// is not the type this crate links against.
+#[macro_export]
+macro_rules! run_main {
+    ($scripter:expr, $scenario:expr) => {{
+        let scripter = &mut $scripter;
+        let scenario = $scenario;
+        scripter.start_main(scenario.get_entry_point());
+        while scripter.get_thread(0).get_pc() != 0 {
+            scripter.step(scenario, 0).unwrap();
+        }
+        let value = scripter.get_thread(0).get_return_value().clone();
+        value
+    }};
+}
+
+/// `acc = acc + i - 3` for every `i` in `0..count`, returns `acc`
+pub fn arithmetic_loop(count: i32) -> Bytes {
+    let mut code = CodeBuilder::new();
+    let entry = code.addr();
+    code.init_stack(0, 2);
+    code.push_i32(0).pop_stack(1);
+    counted_loop(&mut code, count, |code| {
+        code.push_stack(1)
+            .push_stack(0)
+            .add()
+            .push_i32(3)
+            .sub()
+            .pop_stack(1);
+    });
+    code.push_stack(1).retv();
+    code.finish(entry)
+}
+
+/// appends `piece` to a string `count` times, returns the string
+pub fn string_concat_loop(count: i32, piece: &str) -> Bytes {
+    let mut code = CodeBuilder::new();
+    let entry = code.addr();
+    code.init_stack(0, 2);
+    code.push_string("").pop_stack(1);
+    counted_loop(&mut code, count, |code| {
+        code.push_stack(1).push_string(piece).add().pop_stack(1);
+    });
+    code.push_stack(1).retv();
+    code.finish(entry)
+}
+
+/// fills a local table with `t[i] = i` for `i` in `0..count`, then sums it
+/// back with lookups, returns the sum
+pub fn table_loop(count: i32) -> Bytes {
+    let mut code = CodeBuilder::new();
+    let entry = code.addr();
+    // local 0: counter, local 1: table, local 2: sum
+    code.init_stack(0, 3);
+    counted_loop(&mut code, count, |code| {
+        code.push_stack(0).push_stack(0).pop_local_table(1);
+    });
+    code.push_i32(0).pop_stack(2);
+    counted_loop(&mut code, count, |code| {
+        code.push_stack(2)
+            .push_stack(0)
+            .push_local_table(1)
+            .add()
+            .pop_stack(2);
+    });
+    code.push_stack(2).retv();
+    code.finish(entry)
+}
+
+/// recurses `depth` calls deep and counts the frames on the way back, returns `depth`.
+///
+/// every frame takes two stack slots, the VM stack holds 256 of them.
+pub fn recursion(depth: i32) -> Bytes {
+    let mut code = CodeBuilder::new();
+    let entry = code.addr();
+    code.init_stack(0, 0).push_i32(depth);
+    let call = code.call_forward();
+    code.push_return().retv();
+
+    // f(n) = if n > 0 { f(n - 1) + 1 } else { 0 }
+    let f = code.addr();
+    code.init_stack(1, 0).push_stack(-2).push_i32(0).setg();
+    let base_case = code.jz_forward();
+    code.push_stack(-2).push_i32(1).sub().call(f);
+    code.push_return().push_i32(1).add().retv();
+    let base = code.addr();
+    code.push_i32(0).retv();
+
+    code.patch(call, f);
+    code.patch(base_case, base);
+    code.finish(entry)
+}
+
+/// `count` functions with a loop and a string literal each, every one calling the
+/// next, for the tools working on a whole script
+pub fn many_functions(count: usize) -> Bytes {
+    let mut code = CodeBuilder::new();
+    let entry = code.addr();
+    let mut call = None;
+    for index in 0..count {
+        let f = code.addr();
+        if let Some(call) = call.take() {
+            code.patch(call, f);
+        }
+        code.init_stack(0, 2);
+        code.push_string(&format!("function {}", index))
+            .pop_stack(1);
+        counted_loop(&mut code, 10, |code| {
+            code.push_stack(1).push_string("!").add().pop_stack(1);
+        });
+        if index + 1 < count {
+            call = Some(code.call_forward());
+        }
+        code.push_stack(1).retv();
+    }
+    code.finish(entry)
+}
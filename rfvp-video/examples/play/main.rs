@@ -7,9 +7,10 @@ use std::{
 
 use glam::Mat4;
 use rfvp_audio::AudioManager;
-use rfvp_core::time::Ticks;
+use rfvp_core::time::{presented_frames, Ticks};
 use rfvp_render::{
-    BindGroupLayouts, Camera, GpuCommonResources, Pipelines, RenderTarget, Renderable,
+    BindGroupLayouts, Camera, DroppedFrames, GpuCommonResources, Msaa, OversizePolicy, Pipelines,
+    RenderTarget, Renderable, SurfaceFormat, SurfaceSize, TextureLimit,
 };
 use rfvp_video::{mp4::Mp4, VideoPlayer};
 use winit::{
@@ -57,33 +58,39 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
         .expect("Failed to create device");
 
     let swapchain_capabilities = surface.get_capabilities(&adapter);
-    let swapchain_format = swapchain_capabilities.formats[0];
+    let srgb_views = adapter.get_info().backend != wgpu::Backend::Gl;
+    let surface_format = SurfaceFormat::select(&swapchain_capabilities.formats, srgb_views)
+        .expect("The surface is not supported by the adapter");
 
     let mut config = wgpu::SurfaceConfiguration {
         usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
-        format: swapchain_format,
+        format: surface_format.surface,
         width: size.width,
         height: size.height,
         present_mode: wgpu::PresentMode::Fifo,
         desired_maximum_frame_latency: 2,
         alpha_mode: swapchain_capabilities.alpha_modes[0],
-        view_formats: vec![],
+        view_formats: surface_format.view_formats(),
     };
 
     surface.configure(&device, &config);
 
     let bind_group_layouts = BindGroupLayouts::new(&device);
-    let pipelines = Pipelines::new(&device, &bind_group_layouts, swapchain_format);
+    let pipelines = Pipelines::new(&device, &bind_group_layouts, surface_format.view, Msaa::Off);
 
     let window_size = (window.inner_size().width, window.inner_size().height);
     let mut camera = Camera::new(window_size);
 
+    let texture_limit = TextureLimit::for_device(&device, OversizePolicy::Tile);
     let resources = Arc::new(GpuCommonResources {
         device,
         queue,
         render_buffer_size: RwLock::new(camera.render_buffer_size()),
         bind_group_layouts,
         pipelines,
+        msaa: Msaa::Off,
+        texture_limit,
+        dropped_frames: DroppedFrames::new(),
     });
 
     let audio_manager = AudioManager::new();
@@ -100,6 +107,7 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
     );
 
     let mut time = Instant::now();
+    let mut surface_size = SurfaceSize::new((size.width, size.height));
 
     // don't move it pls
     let window = &window;
@@ -125,11 +133,15 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
                     event: WindowEvent::Resized(size),
                     ..
                 } => {
-                    // Reconfigure the surface with the new size
-                    config.width = size.width;
-                    config.height = size.height;
-                    camera.resize((size.width, size.height));
-                    surface.configure(&resources.device, &config);
+                    // Reconfigure the surface with the new size, a minimized window can't be configured
+                    if let Some((width, height)) =
+                        surface_size.resize((size.width, size.height)).configure_size()
+                    {
+                        config.width = width;
+                        config.height = height;
+                        camera.resize((width, height));
+                        surface.configure(&resources.device, &config);
+                    }
                     // On macos the window needs to be redrawn manually after resizing
                     window.request_redraw();
                 }
@@ -147,12 +159,15 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
 
                     video_player.update(Ticks::from_duration(delta_time), &resources.queue);
 
+                    if surface_size.is_minimized() {
+                        window.request_redraw();
+                        return;
+                    }
+
                     let frame = surface
                         .get_current_texture()
                         .expect("Failed to acquire next swap chain texture");
-                    let view = frame
-                        .texture
-                        .create_view(&wgpu::TextureViewDescriptor::default());
+                    let view = frame.texture.create_view(&surface_format.view_descriptor());
 
                     let mut encoder = resources.start_encoder();
                     {
@@ -191,6 +206,7 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
                     drop(encoder);
 
                     frame.present();
+                    presented_frames().presented();
                     window.request_redraw();
                 }
                 Event::WindowEvent {
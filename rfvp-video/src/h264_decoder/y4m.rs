@@ -2,6 +2,7 @@ use std::{fmt, io, num, str};
 
 use futures_lite::{io::BufReader, AsyncBufReadExt, AsyncRead, AsyncReadExt};
 use num_integer::Integer;
+use rfvp_core::memory::{self, MemoryKind, MemoryTicket};
 
 const FILE_MAGICK: &[u8] = b"YUV4MPEG2 ";
 const FRAME_MAGICK: &[u8] = b"FRAME";
@@ -474,16 +475,21 @@ pub struct Frame {
     planes: [Vec<u8>; 3],
     raw_params: Option<Vec<u8>>,
     info: FrameSize,
+    /// the frames queued ahead of playback add up, they count against the memory budget
+    #[allow(unused)]
+    memory: MemoryTicket,
 }
 
 impl Frame {
     /// Create a new frame with optional parameters.
     /// No heap allocations are made.
     pub fn new(planes: [Vec<u8>; 3], raw_params: Option<Vec<u8>>, info: FrameSize) -> Frame {
+        let size = planes.iter().map(|plane| plane.len() as u64).sum();
         Frame {
             planes,
             raw_params,
             info,
+            memory: memory::governor().track(MemoryKind::Video, size),
         }
     }
 
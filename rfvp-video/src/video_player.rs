@@ -5,10 +5,10 @@ use glam::Mat4;
 use kira::track::TrackId;
 use rfvp_audio::{AudioData, AudioManager, AudioSettings};
 use rfvp_core::{
-    time::{Ticks, Tween},
+    time::{presented_frames, Completion, Ticks, Tween},
     vm::command::types::{Pan, Volume},
 };
-use rfvp_render::{GpuCommonResources, Renderable, SpriteVertexBuffer};
+use rfvp_render::{DroppedFrames, GpuCommonResources, Renderable, SpriteVertexBuffer};
 use tracing::{error, info, trace, warn};
 
 use crate::{
@@ -25,6 +25,10 @@ pub struct VideoPlayer {
     video_texture: YuvTexture,
     vertex_buffer: SpriteVertexBuffer,
     pending_frame: Option<(FrameTiming, Frame)>,
+    /// completed with the last frame, finished once it was presented
+    completion: Completion,
+    /// the count of the resources, shown by the FPS overlay
+    dropped_frames: DroppedFrames,
 }
 
 impl VideoPlayer {
@@ -64,6 +68,7 @@ impl VideoPlayer {
                     loop_start: None,
                     volume: Volume::default(),
                     pan: Pan::default(),
+                    start_position: 0,
                 },
             }))
         } else {
@@ -77,12 +82,19 @@ impl VideoPlayer {
 
         let vertex_buffer = SpriteVertexBuffer::new_fullscreen(resources);
 
+        let mut completion = Completion::default();
+        if pending_frame.is_none() {
+            completion.complete(presented_frames());
+        }
+
         Ok(VideoPlayer {
             timer,
             video_decoder,
             video_texture,
             vertex_buffer,
             pending_frame,
+            completion,
+            dropped_frames: resources.dropped_frames.clone(),
         })
     }
 
@@ -90,65 +102,79 @@ impl VideoPlayer {
         self.timer.update(delta_time);
         let current_time = self.timer.time();
 
-        let mut skipped_frames = 0;
-        // find the latest frame that is ready for display
-        // this might be the currently pending frame, or any of the frames after it (shouldn't happen often I think)
-        while let Some((timing, ref frame)) = self.pending_frame {
-            // if it's not time to display the frame yet - stop the loop
-            if timing.start_time > current_time {
-                // very noisy
-                // trace!(
-                //     "Not time to display frame #{}, time: {}",
-                //     timing.frame_number,
-                //     timing.start_time
-                // );
-                break;
-            }
-
-            // look at the frame after the pending one
-            let next_frame = match self.video_decoder.read_frame() {
+        let decoder = &mut self.video_decoder;
+        let due = take_due_frames(&mut self.pending_frame, current_time, || {
+            match decoder.read_frame() {
                 Ok(frame) => frame,
                 Err(err) => {
                     error!("Error reading frame: {}. Stopping playback", err);
                     None
                 }
-            };
-
-            // if the next frame is not ready for display yet...
-            if next_frame
-                .as_ref()
-                .map_or(true, |(timing, _)| timing.start_time > current_time)
-            {
-                if skipped_frames > 0 {
-                    warn!("Skipped {} frames", skipped_frames);
-                }
-
-                // then update the texture with the pending frame
-                trace!(
-                    "Displaying frame #{}, time: {}",
-                    timing.frame_number,
-                    timing.start_time
-                );
-                self.video_texture.write_data(frame, queue);
-                // the loop will not enter again, so the pending frame will now be displayed
-            } else {
-                skipped_frames += 1;
-                // if the next frame is also ready for display, then we should skip the pending frame
             }
+        });
 
-            if next_frame.is_none() {
-                info!("No more frames, stopping playback");
-            }
-
-            self.pending_frame = next_frame;
+        if due.skipped > 0 {
+            warn!("Skipped {} frames", due.skipped);
+            self.dropped_frames.record(due.skipped);
+        }
+        if let Some((timing, frame)) = &due.latest {
+            trace!(
+                "Displaying frame #{}, time: {}",
+                timing.frame_number,
+                timing.start_time
+            );
+            self.video_texture.write_data(frame, queue);
+        }
+        if due.ended {
+            info!("No more frames, stopping playback");
+            self.completion.complete(presented_frames());
         }
     }
 
+    /// whether the last frame was decoded and presented
     pub fn is_finished(&self) -> bool {
-        self.pending_frame.is_none()
+        !self.completion.is_busy(presented_frames())
     }
 }
 
+/// The frames a [`take_due_frames`] call went through
+struct DueFrames<F> {
+    /// the latest frame due, to be displayed
+    latest: Option<(FrameTiming, F)>,
+    /// the frames due before it, discarded without being displayed
+    skipped: u32,
+    /// there is no frame after the latest one
+    ended: bool,
+}
+
+/// Pops the frames due at `time`, from `pending` on, reading the next ones with `read_next`.
+///
+/// Only the latest due frame is kept, a video falling behind skips the others. `pending` is
+/// left at the first frame not due yet, `None` at the end of the video.
+fn take_due_frames<F>(
+    pending: &mut Option<(FrameTiming, F)>,
+    time: u64,
+    mut read_next: impl FnMut() -> Option<(FrameTiming, F)>,
+) -> DueFrames<F> {
+    let mut due = DueFrames {
+        latest: None,
+        skipped: 0,
+        ended: false,
+    };
+    while pending
+        .as_ref()
+        .is_some_and(|(timing, _)| timing.start_time <= time)
+    {
+        let next = read_next();
+        due.ended = next.is_none();
+        let frame = std::mem::replace(pending, next);
+        if due.latest.replace(frame.unwrap()).is_some() {
+            due.skipped += 1;
+        }
+    }
+    due
+}
+
 // or should it just provide a renderable texture?
 // depends on how will the generic layer rendering will be implemented...
 impl Renderable for VideoPlayer {
@@ -170,3 +196,42 @@ impl Renderable for VideoPlayer {
 
     fn resize(&mut self, _resources: &GpuCommonResources) {}
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn timing(frame_number: u32, start_time: u64) -> FrameTiming {
+        FrameTiming {
+            frame_number,
+            start_time,
+            duration: 10,
+        }
+    }
+
+    #[test]
+    fn test_take_due_frames() {
+        // a frame every 10 ticks, the frame is its number
+        let mut stream = (1..5).map(|n| (timing(n, n as u64 * 10), n));
+        let mut pending = Some((timing(0, 0), 0));
+
+        // fell behind to 35: frames 0 to 3 are due, only 3 is displayed
+        let due = take_due_frames(&mut pending, 35, || stream.next());
+        assert_eq!(due.latest.map(|(_, frame)| frame), Some(3));
+        assert_eq!(due.skipped, 3);
+        assert!(!due.ended);
+        assert_eq!(pending.as_ref().map(|(_, frame)| *frame), Some(4));
+
+        // nothing is due yet
+        let due = take_due_frames(&mut pending, 39, || stream.next());
+        assert!(due.latest.is_none());
+        assert_eq!(due.skipped, 0);
+
+        // the last frame, on time
+        let due = take_due_frames(&mut pending, 40, || stream.next());
+        assert_eq!(due.latest.map(|(_, frame)| frame), Some(4));
+        assert_eq!(due.skipped, 0);
+        assert!(due.ended);
+        assert!(pending.is_none());
+    }
+}
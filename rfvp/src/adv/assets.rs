@@ -1,43 +1,131 @@
 use std::sync::Arc;
 use std::path::{Path, PathBuf};
-use anyhow::Result;
+use ab_glyph::FontRef;
+use anyhow::{Context, Result};
 use futures::try_join;
-use rfvp_core::format::scenario::Scenario;
+use rfvp_core::{
+    format::{
+        font::FontLocator,
+        scenario::{
+            overlay, probe,
+            text_patch::{TextPatch, TEXT_PATCH_FILE},
+            Scenario,
+        },
+    },
+    locale::{Language, Localizer},
+};
+use tracing::{debug, info};
 
 use crate::asset::AnyAssetServer;
 
+/// The message font file names, by preference: the one of the original engine, then
+/// the CJK fonts of the Linux distributions
+const MESSAGE_FONTS: &[&str] = &[
+    "msgothic.ttc",
+    "NotoSansCJK-Regular.ttc",
+    "NotoSansCJKjp-Regular.otf",
+];
+
+#[derive(Clone)]
+pub struct AdvFonts {
+    pub medium_font: FontRef<'static>,
+}
+
+impl AdvFonts {
+    /// Finds the message font in the game's `font` directory or the ones of the OS, see
+    /// [`FontLocator`]. The font is used for the whole run, its data is never freed.
+    pub fn load(game_root: &Path) -> Result<Self> {
+        let (location, data) = FontLocator::new(game_root).load(MESSAGE_FONTS, None)?;
+        info!("Using the message font {:?}", location);
+
+        let data: &'static [u8] = Box::leak(Box::new(data));
+        let medium_font = FontRef::try_from_slice(data)
+            .with_context(|| format!("Parsing the message font {:?}", location))?;
+        Ok(Self { medium_font })
+    }
+}
+
 // TODO: this can be done with a macro
 #[derive(Clone)]
 pub struct AdvAssets {
     pub scenario: Arc<Scenario>,
+    pub fonts: AdvFonts,
 }
 
 
 impl AdvAssets {
     pub async fn load(asset_server: &AnyAssetServer, root: impl AsRef<Path>) -> Result<Self> {
-        let hcb = Self::find_hcb(root)?;
+        let root = root.as_ref();
+        let hcb_path = Self::find_hcb(root)?;
         // assume hcb is a valid path
-        let hcb = hcb.to_string_lossy();
+        let hcb = hcb_path.to_string_lossy();
         let result = try_join!(
             asset_server.load(hcb),
         )?;
 
-        Ok(Self {
-            scenario: result.0,
-        })
-    }
+        let mut scenario = result.0;
+        if let Some(patch) = Self::load_patch(asset_server, &hcb_path).await? {
+            scenario = Arc::new(scenario.with_patch(patch.into())?);
+            info!(
+                "Applying the patch script, {} overridden functions",
+                scenario.get_overrides().len()
+            );
+        }
+        if let Some(patch) = Self::load_text_patch(asset_server).await? {
+            info!("Applying the text patch, {} strings", patch.strings.len());
+            scenario = Arc::new(scenario.with_text_patch(&patch)?);
+        }
 
-    pub fn find_hcb(game_path: impl AsRef<Path>) -> Result<PathBuf> {
-        let mut path = game_path.as_ref().to_path_buf();
-        path.push("*.hcb");
+        let fonts = AdvFonts::load(root)?;
 
-        let macthes: Vec<_> = glob::glob(&path.to_string_lossy())?.flatten().collect();
+        Ok(Self { scenario, fonts })
+    }
 
-        if macthes.is_empty() {
-            anyhow::bail!("No hcb file found in the game directory");
+    /// the patch script next to the script, if there is one, see [`overlay`]
+    async fn load_patch(asset_server: &AnyAssetServer, hcb: &Path) -> Result<Option<Vec<u8>>> {
+        let path = overlay::patch_path(hcb);
+        match asset_server.read_file(&path.to_string_lossy()).await {
+            Ok(data) => Ok(Some(data)),
+            Err(err) => {
+                debug!("No patch script: {:#}", err);
+                Ok(None)
+            }
         }
+    }
 
-        Ok(macthes[0].to_path_buf())
+    /// the text patch of the override directory, if there is one
+    async fn load_text_patch(asset_server: &AnyAssetServer) -> Result<Option<TextPatch>> {
+        let data = match asset_server.read_file(TEXT_PATCH_FILE).await {
+            Ok(data) => data,
+            Err(err) => {
+                debug!("No text patch: {:#}", err);
+                return Ok(None);
+            }
+        };
+        let patch = TextPatch::parse(&String::from_utf8(data)?)?;
+        Ok(Some(patch))
+    }
+
+    /// the built in strings of `language`, with the catalog of the override directory on top
+    pub async fn load_localizer(
+        asset_server: &AnyAssetServer,
+        language: Language,
+    ) -> Result<Localizer> {
+        let localizer = Localizer::new(language);
+        let path = language.override_path();
+        let data = match asset_server.read_file(&path).await {
+            Ok(data) => data,
+            Err(err) => {
+                debug!("No string catalog override: {:#}", err);
+                return Ok(localizer);
+            }
+        };
+        info!("Overriding the engine strings with {}", path);
+        localizer.with_override(&String::from_utf8(data)?)
+    }
+
+    pub fn find_hcb(game_path: impl AsRef<Path>) -> Result<PathBuf> {
+        probe::find_hcb(game_path)
     }
 }
 
@@ -1,3 +1,5 @@
+use rfvp_core::format::save::autosave::AutosaveTrigger;
+
 use super::prelude::*;
 
 impl StartableCommand for command::runtime::AUTOSAVE {
@@ -10,9 +12,12 @@ impl StartableCommand for command::runtime::AUTOSAVE {
         _context: &UpdateContext,
         _scenario: &Arc<Scenario>,
         _vm_state: &VmState,
-        _adv_state: &mut AdvState,
+        adv_state: &mut AdvState,
     ) -> CommandStartResult {
-        warn!("TODO: AUTOSAVE: {:?}", self);
+        // taken at the next frame boundary, once the script yields
+        if let Some(autosave) = &mut adv_state.autosave {
+            autosave.scheduler.request(AutosaveTrigger::SceneTransition);
+        }
         self.token.finish().into()
     }
 }
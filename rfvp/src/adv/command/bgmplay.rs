@@ -1,6 +1,6 @@
 use std::ops::Not;
 
-use rfvp_core::{format::scenario::info::BgmInfoItem, time::Tween};
+use rfvp_core::{format::scenario::info::BgmInfoItem, time::Tween, tr};
 
 use super::prelude::*;
 use crate::adv::vm_state::audio::BgmState;
@@ -34,12 +34,18 @@ impl StartableCommand for command::runtime::BGMPLAY {
 
         adv_state.bgm_player.play(
             audio,
+            self.bgm_data_id,
             display_name.as_str(),
             !self.no_repeat,
             self.volume,
             Tween::linear(self.fade_in_time),
         );
 
+        let notifications = adv_state.root_layer_group.notification_layer_mut();
+        if notifications.now_playing() && !display_name.is_empty() {
+            notifications.notify(tr!("notification.now_playing", display_name));
+        }
+
         self.token.finish().into()
     }
 }
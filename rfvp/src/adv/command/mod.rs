@@ -5,7 +5,7 @@ mod prelude {
 
     pub use rfvp_core::{
         format::scenario::Scenario,
-        time::Ticks,
+        time::{Subsystem, Ticks},
         vm::{
             command,
             command::{
@@ -24,6 +24,11 @@ impl StartableCommand for command::runtime::MOVIEWAIT {
         match adv_state.get_layer(vm_state, self.layer_id) {
             Some(UserLayer::MovieLayer(_)) => {
                 assert_eq!(self.target_status, 2, "MOVIEWAIT: unknown target status");
+                // no toasts over a movie
+                adv_state
+                    .root_layer_group
+                    .notification_layer_mut()
+                    .set_suppressed(true);
                 Yield(
                     MOVIEWAIT {
                         token: Some(self.token),
@@ -55,6 +60,10 @@ impl UpdatableCommand for MOVIEWAIT {
         };
         let finished = layer.is_finished();
         if finished {
+            adv_state
+                .root_layer_group
+                .notification_layer_mut()
+                .set_suppressed(false);
             Some(self.token.take().unwrap().finish())
         } else {
             None
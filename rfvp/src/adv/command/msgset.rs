@@ -1,10 +1,14 @@
 use std::fmt::{Debug, Formatter};
 
+use rfvp_core::format::save::text_history::HistoryLine;
+
 use super::prelude::*;
 
 pub struct MSGSET {
     #[allow(unused)]
     token: Option<command::token::MSGSET>,
+    /// recorded in the history once the message is fully displayed
+    line: Option<HistoryLine>,
 }
 
 impl StartableCommand for command::runtime::MSGSET {
@@ -26,14 +30,18 @@ impl StartableCommand for command::runtime::MSGSET {
             .message_layer_mut()
             .set_message(context, &self.text);
 
+        let line = HistoryLine::from_message(&self.text);
         if self.auto_wait {
             Yield(
                 MSGSET {
                     token: Some(self.token),
+                    line: Some(line),
                 }
                 .into(),
             )
         } else {
+            // nothing waits for the message here, it's recorded as soon as it's shown
+            adv_state.record_line(line);
             self.token.finish().into()
         }
     }
@@ -51,6 +59,9 @@ impl UpdatableCommand for MSGSET {
         _is_fast_forwarding: bool,
     ) -> Option<CommandResult> {
         if adv_state.root_layer_group.message_layer().is_finished() {
+            if let Some(line) = self.line.take() {
+                adv_state.record_line(line);
+            }
             Some(self.token.take().unwrap().finish())
         } else {
             None
@@ -4,7 +4,7 @@ use super::prelude::*;
 
 impl StartableCommand for command::runtime::SEPAN {
     fn apply_state(&self, state: &mut VmState) {
-        if let Some(state) = state.audio.se[self.se_slot as usize].as_mut() {
+        if let Some(Some(state)) = state.audio.se_mut(self.se_slot) {
             state.pan = self.pan;
         }
     }
@@ -3,11 +3,15 @@ use std::ops::Not;
 use rfvp_core::time::Tween;
 
 use super::prelude::*;
-use crate::adv::vm_state::audio::SeState;
+use crate::{adv::vm_state::audio::SeState, audio::SeChannelInfo};
 
 impl StartableCommand for command::runtime::SEPLAY {
     fn apply_state(&self, state: &mut VmState) {
-        state.audio.se[self.se_slot as usize] = self.no_repeat.not().then_some(SeState {
+        // SEs on a slot picked by the engine (negative slot) are not restarted on load
+        let Some(slot) = state.audio.se_mut(self.se_slot) else {
+            return;
+        };
+        *slot = self.no_repeat.not().then_some(SeState {
             se_id: self.se_data_id,
             volume: self.volume,
             pan: self.pan,
@@ -36,8 +40,11 @@ impl StartableCommand for command::runtime::SEPLAY {
 
         adv_state.se_player.play(
             self.se_slot,
+            SeChannelInfo {
+                se_id: self.se_data_id,
+                repeat: !self.no_repeat,
+            },
             audio,
-            !self.no_repeat,
             self.volume,
             self.pan,
             Tween::linear(self.fade_in_time),
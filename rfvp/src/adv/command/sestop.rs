@@ -4,7 +4,9 @@ use super::prelude::*;
 
 impl StartableCommand for command::runtime::SESTOP {
     fn apply_state(&self, state: &mut VmState) {
-        state.audio.se[self.se_slot as usize] = None;
+        if let Some(state) = state.audio.se_mut(self.se_slot) {
+            *state = None;
+        }
     }
 
     fn start(
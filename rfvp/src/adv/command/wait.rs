@@ -42,7 +42,7 @@ impl UpdatableCommand for WAIT {
         _adv_state: &mut AdvState,
         is_fast_forwarding: bool,
     ) -> Option<CommandResult> {
-        self.waiting_left -= context.time_delta_ticks();
+        self.waiting_left -= context.subsystem_delta_ticks(Subsystem::Pacing);
         // TODO: short circuit the wait for now
         if self.waiting_left <= Ticks::ZERO || is_fast_forwarding {
             debug!("WAIT: done");
@@ -2,24 +2,53 @@ pub mod assets;
 mod command;
 mod vm_state;
 
-use std::{borrow::Cow, sync::Arc};
+use std::{
+    borrow::Cow,
+    cell::{Cell, RefCell},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
 
+use anyhow::{bail, Result};
 pub use command::{CommandStartResult, ExecutingCommand, StartableCommand, UpdatableCommand};
 use egui::Window;
-use glam::Mat4;
+use glam::{Mat4, Vec2};
 use itertools::Itertools;
-use rfvp_audio::AudioManager;
+use rfvp_audio::{AudioManager, DeviceLost};
 use rfvp_core::{
-    format::scenario::{instruction_elements::CodeAddress, Scenario},
+    format::{
+        save::{
+            audio_snapshot::AudioManagerSnapshotV1,
+            autosave::{
+                parse_save_state, save_state_snapshot, AutosaveConfig, AutosaveEntry,
+                AutosaveScheduler, AutosaveStore, AutosaveTrigger,
+            },
+            text_history::{HistoryLine, TextHistory},
+            voice_config::{voice_character_id, VoiceConfig},
+        },
+        scenario::{
+            global::GLOBAL, instruction_elements::CodeAddress, scene_table::SceneEntry,
+            variant::Variant, Scenario,
+        },
+    },
+    screen_effect::ScreenEffects,
     vm::{
         command::{
-            types::{LayerId, VLayerId, VLayerIdRepr, PLANES_COUNT},
-            CommandResult,
+            types::{LayerId, VLayerId, VLayerIdRepr, LAYERS_COUNT, PLANES_COUNT},
+            Args, Command, CommandResult,
         },
-        Scripter,
+        Scripter, VmSnapshot,
     },
+    time::{
+        Activity, AutoAdvance, PlaytimeConfig, PlaytimeTracker, SessionStats, Subsystem, Tween,
+    },
+    tr,
 };
 use rfvp_render::{GpuCommonResources, Renderable};
+use rfvp_tasks::IoTaskPool;
 use smallvec::{smallvec, SmallVec};
 use tracing::{debug, warn};
 use vm_state::layers::ITER_VLAYER_SMALL_VECTOR_SIZE;
@@ -27,10 +56,12 @@ pub use vm_state::{layers::LayerSelection, VmState};
 
 use crate::{
     adv::assets::AdvAssets,
-    audio::{BgmPlayer, SePlayer},
+    asset::AnyAssetServer,
+    audio::{BgmPlayer, SePlayer, VoicePlayer},
     input::{actions::AdvMessageAction, ActionState},
     layer::{
-        AnyLayer, AnyLayerMut, LayerGroup, MessageLayer, RootLayerGroup, ScreenLayer, UserLayer,
+        AnyLayer, AnyLayerMut, LayerGroup, MessageLayer, NotificationAnchor, NotificationTimings,
+        RootLayerGroup, ScreenLayer, Speech, UserLayer,
     },
     render::overlay::{OverlayCollector, OverlayVisitable},
     update::{Updatable, UpdateContext},
@@ -43,6 +74,20 @@ pub struct Adv {
     adv_state: AdvState,
     action_state: ActionState<AdvMessageAction>,
     current_command: Option<ExecutingCommand>,
+    /// scenes of the developer jump menu, empty when jumping is disabled
+    scene_table: Vec<SceneEntry>,
+    /// the scene picked in the menu, waiting for a confirmation
+    scene_jump_selection: Cell<Option<usize>>,
+    /// the confirmed jump, done on the next update
+    scene_jump_request: Cell<Option<u32>>,
+    /// the autosave picked in the load screen, loaded on the next update
+    autosave_load_request: RefCell<Option<AutosaveEntry>>,
+    /// the auto mode timer, `None` when the player advances by hand
+    auto_advance: Option<AutoAdvance>,
+    /// the backlog is shown, opened with the wheel
+    history_open: Cell<bool>,
+    /// advances the playtime in the globals
+    playtime: PlaytimeTracker,
 }
 
 impl Adv {
@@ -54,9 +99,13 @@ impl Adv {
         random_seed: u32,
     ) -> Self {
         let scenario = assets.scenario.clone();
+        scenario.init_scene(&mut GLOBAL.lock().unwrap());
         let scripter = Scripter::new();
         let vm_state = VmState::new();
-        let adv_state = AdvState::new(resources, audio_manager, assets);
+        let mut adv_state = AdvState::new(resources, audio_manager, assets);
+        // the quakes shake the same on every run of the same seed
+        *adv_state.root_layer_group.screen_layer_mut().effects_mut() =
+            ScreenEffects::new(random_seed);
 
         Self {
             scenario,
@@ -65,6 +114,195 @@ impl Adv {
             adv_state,
             action_state: ActionState::new(),
             current_command: None,
+            scene_table: Vec::new(),
+            scene_jump_selection: Cell::new(None),
+            scene_jump_request: Cell::new(None),
+            autosave_load_request: RefCell::new(None),
+            auto_advance: None,
+            history_open: Cell::new(false),
+            playtime: PlaytimeTracker::default(),
+        }
+    }
+
+    /// Shows the scene jump menu in the debug overlay, for QA and translators
+    pub fn enable_scene_jump(&mut self, scene_table: Vec<SceneEntry>) {
+        self.scene_table = scene_table;
+    }
+
+    /// Turns the auto mode on with the given timing, or off with `None`
+    pub fn set_auto_advance(&mut self, mut auto_advance: Option<AutoAdvance>) {
+        if let Some(auto_advance) = &mut auto_advance {
+            auto_advance.set_voice_padding(self.adv_state.voice_config.auto_advance_padding_ms);
+        }
+        self.auto_advance = auto_advance;
+    }
+
+    /// The muted characters and the pause of the auto mode after a voice
+    pub fn set_voice_config(&mut self, config: VoiceConfig) {
+        if let Some(auto_advance) = &mut self.auto_advance {
+            auto_advance.set_voice_padding(config.auto_advance_padding_ms);
+        }
+        self.adv_state.voice_config = config;
+    }
+
+    pub fn is_auto_advance(&self) -> bool {
+        self.auto_advance.is_some()
+    }
+
+    /// The auto mode key: turns it on with the default timing, or off
+    pub fn toggle_auto_advance(&mut self) {
+        let auto_advance = match self.auto_advance {
+            Some(_) => None,
+            None => Some(AutoAdvance::default()),
+        };
+        debug!("Auto mode: {}", auto_advance.is_some());
+        self.set_auto_advance(auto_advance);
+    }
+
+    /// Autosaves into the rotating autosave slots of `store`
+    pub fn enable_autosave(&mut self, store: AutosaveStore, config: AutosaveConfig) -> Result<()> {
+        let next_sequence = store.next_sequence()?;
+        self.adv_state.autosave = Some(Autosave {
+            scheduler: AutosaveScheduler::new(config),
+            store,
+            next_sequence,
+            entries: RefCell::new(Ok(Vec::new())),
+            entries_stale: Arc::new(AtomicBool::new(true)),
+        });
+        Ok(())
+    }
+
+    /// The player's text size multiplier, see [`MessageLayer::set_text_scale`]
+    pub fn set_text_scale(&mut self, scale: f32) {
+        self.adv_state
+            .root_layer_group
+            .message_layer_mut()
+            .set_text_scale(scale);
+    }
+
+    /// Whether a click on a line being revealed only completes it, see
+    /// [`MessageLayer::set_click_completes_reveal`]
+    pub fn set_click_completes_reveal(&mut self, click_completes_reveal: bool) {
+        self.adv_state
+            .root_layer_group
+            .message_layer_mut()
+            .set_click_completes_reveal(click_completes_reveal);
+    }
+
+    /// The screen corner of the notifications, like the "now playing" toast
+    pub fn set_notification_anchor(&mut self, anchor: NotificationAnchor) {
+        self.adv_state
+            .root_layer_group
+            .notification_layer_mut()
+            .set_anchor(anchor);
+    }
+
+    /// How long the notifications fade in, stay and fade out
+    pub fn set_notification_timings(&mut self, timings: NotificationTimings) {
+        self.adv_state
+            .root_layer_group
+            .notification_layer_mut()
+            .set_timings(timings);
+    }
+
+    /// Tells the player the audio device went away, and whether the sound moved to another one
+    pub fn notify_device_lost(&mut self, lost: &DeviceLost) {
+        let text = if lost.recovered {
+            tr!("notification.audio_device_changed")
+        } else {
+            tr!("notification.audio_device_lost")
+        };
+        self.adv_state
+            .root_layer_group
+            .notification_layer_mut()
+            .notify(text);
+    }
+
+    /// Whether BGMPLAY shows the "now playing" toast
+    pub fn set_now_playing_toast(&mut self, enabled: bool) {
+        self.adv_state
+            .root_layer_group
+            .notification_layer_mut()
+            .set_now_playing(enabled);
+    }
+
+    /// Whether the time in the backlog counts as playtime, see [`PlaytimeConfig`]
+    pub fn set_playtime_config(&mut self, config: PlaytimeConfig) {
+        self.playtime = PlaytimeTracker::new(config);
+    }
+
+    /// Advances the playtime, called every frame even while the game stands still.
+    ///
+    /// `now` is the real time since the start, the playtime doesn't run while the window
+    /// is unfocused or the game is paused.
+    pub fn track_playtime(&mut self, now: Duration, focused: bool, paused: bool) {
+        let activity = if !focused {
+            Activity::Unfocused
+        } else if paused {
+            Activity::Paused
+        } else if self.history_open.get() {
+            Activity::Menu
+        } else {
+            Activity::Playing
+        };
+        self.playtime
+            .update(GLOBAL.lock().unwrap().playtime_mut(), now, activity);
+    }
+
+    /// Restarts the script at the function `addr`, like a load does.
+    ///
+    /// The running command, the message and the sounds are dropped, the globals are
+    /// kept: the state after a jump may not be what the scene expects.
+    pub fn jump_to_scene(&mut self, addr: u32) {
+        debug!("Jumping to the scene at {:08x}", addr);
+        self.current_command = None;
+        self.adv_state.reset_scene();
+        self.scripter.start_main(addr);
+    }
+
+    /// Resumes the game from an autosave: the globals, the script threads, the sounds and
+    /// the backlog are replaced.
+    ///
+    /// The autosaves from before the threads were saved restart the script at its entry point.
+    pub fn load_autosave(
+        &mut self,
+        entry: &AutosaveEntry,
+        asset_server: &AnyAssetServer,
+    ) -> Result<()> {
+        let Some(autosave) = &self.adv_state.autosave else {
+            bail!("Autosave is disabled");
+        };
+        let state = parse_save_state(&autosave.store.read(entry)?)?;
+        debug!("Loading autosave {}", entry.sequence);
+
+        self.current_command = None;
+        self.adv_state.reset_scene();
+        match &state.vm_v1 {
+            Some(vm) => self.scripter.restore(vm)?,
+            None => self.scripter.start_main(self.scenario.get_entry_point()),
+        }
+        *GLOBAL.lock().unwrap() = state.globals;
+        self.adv_state
+            .apply_audio_snapshot_v1(&state.audio_v1, &self.scenario, asset_server);
+        self.adv_state.text_history = state.history_v1;
+        Ok(())
+    }
+
+    /// Takes a requested autosave, called before the script runs on: the last command is
+    /// finished and its result applied, a load resumes the threads right after it
+    fn poll_autosave(&mut self) {
+        let settled = self.current_command.is_none();
+        let trigger = self
+            .adv_state
+            .autosave
+            .as_mut()
+            .and_then(|autosave| autosave.scheduler.poll(Instant::now(), settled));
+        if let Some(trigger) = trigger {
+            let vm = self.scripter.snapshot();
+            let audio = self.adv_state.capture_audio_snapshot_v1();
+            if let Some(autosave) = &mut self.adv_state.autosave {
+                autosave.save(trigger, &vm, &audio, &self.adv_state.text_history);
+            }
         }
     }
 
@@ -76,17 +314,72 @@ impl Adv {
 
 impl Updatable for Adv {
     fn update(&mut self, context: &UpdateContext) {
+        if let Some(addr) = self.scene_jump_request.take() {
+            self.jump_to_scene(addr);
+        }
+        if let Some(entry) = self.autosave_load_request.take() {
+            if let Err(err) = self.load_autosave(&entry, context.asset_server) {
+                warn!("Could not load autosave {}: {:#}", entry.sequence, err);
+            }
+        }
+
+        if let Some(lost) = self.adv_state.audio_manager.poll_device() {
+            if lost.recovered {
+                self.adv_state
+                    .reopen_audio(&self.scenario, context.asset_server);
+            }
+        }
+
         self.action_state.update(context.raw_input_state);
 
+        if self.action_state.is_just_pressed(AdvMessageAction::Backlog) {
+            self.history_open.set(true);
+        }
+        // the line and the auto mode wait behind the backlog, the reveal goes on when it's closed
+        let message_layer = self.adv_state.root_layer_group.message_layer_mut();
+        match (self.history_open.get(), message_layer.is_suspended()) {
+            (true, false) => message_layer.suspend(),
+            (false, true) => message_layer.resume(),
+            _ => {}
+        }
+        if self
+            .action_state
+            .is_just_pressed(AdvMessageAction::ToggleAuto)
+        {
+            self.toggle_auto_advance();
+        }
+
         let fast_forward_button_held = self
             .action_state
             .is_pressed(AdvMessageAction::HoldFastForward);
 
         if self.action_state.is_just_pressed(AdvMessageAction::Advance) {
-            self.adv_state
+            if let Some(auto_advance) = &mut self.auto_advance {
+                auto_advance.cancel();
+            }
+            let advanced = self
+                .adv_state
                 .root_layer_group
                 .message_layer_mut()
                 .advance();
+            if let Some(autosave) = self.adv_state.autosave.as_mut().filter(|_| advanced) {
+                autosave.scheduler.line_advanced();
+            }
+        }
+
+        if let Some(auto_advance) = &mut self.auto_advance {
+            let voice_playing = self.adv_state.voice_player.is_playing();
+            let message_layer = self.adv_state.root_layer_group.message_layer_mut();
+            match message_layer.click_wait_chars() {
+                Some(chars) if !auto_advance.is_pending() => auto_advance.start(chars),
+                Some(_) => {
+                    let delta = context.subsystem_delta_ticks(Subsystem::Pacing);
+                    if auto_advance.update_voiced(delta, voice_playing) {
+                        message_layer.advance();
+                    }
+                }
+                None => auto_advance.cancel(),
+            }
         }
 
         if fast_forward_button_held || self.fast_forward_to_bp.is_some() {
@@ -94,9 +387,14 @@ impl Updatable for Adv {
                 .root_layer_group
                 .message_layer_mut()
                 .fast_forward();
+            // the skip mode doesn't sit through the flashes and the quakes
+            self.adv_state
+                .root_layer_group
+                .screen_layer_mut()
+                .effects_mut()
+                .skip();
         }
 
-        let mut result = CommandResult::None;
         loop {
             // check the fast forward breakpoint; delete if hit
             if self
@@ -122,23 +420,42 @@ impl Updatable for Adv {
                     None => break,
                     Some(result) => {
                         self.current_command = None;
+                        self.scripter
+                            .apply_result(self.scenario.as_ref(), result)
+                            .expect("applying the command result failed");
+                        self.poll_autosave();
                         self.scripter
                             .run(
                                 self.scenario.as_ref(),
-                                context.time_delta().as_millis() as u64,
+                                context.subsystem_delta(Subsystem::Pacing).as_millis() as u64,
                             )
                             .expect("scripter run failed")
                     }
                 }
             } else {
+                self.poll_autosave();
                 self.scripter
                     .run(
                         self.scenario.as_ref(),
-                        context.time_delta().as_millis() as u64,
+                        context.subsystem_delta(Subsystem::Pacing).as_millis() as u64,
                     )
                     .expect("scripter run failed")
             };
 
+            // answered from the layers right away, nothing to wait for
+            if let Command::PrimHit { args } = &runtime_command {
+                let hit = self.adv_state.prim_hit(
+                    &self.vm_state,
+                    Args::new("PrimHit", args),
+                    context.raw_input_state.cursor_screen_position,
+                );
+                let value = if hit { Variant::True } else { Variant::Nil };
+                self.scripter
+                    .apply_result(self.scenario.as_ref(), CommandResult::WriteR0(value))
+                    .expect("applying the command result failed");
+                continue;
+            }
+
             runtime_command.apply_state(&mut self.vm_state);
 
             match runtime_command.start(
@@ -147,7 +464,10 @@ impl Updatable for Adv {
                 &self.vm_state,
                 &mut self.adv_state,
             ) {
-                CommandStartResult::Continue(r) => result = r,
+                CommandStartResult::Continue(result) => self
+                    .scripter
+                    .apply_result(self.scenario.as_ref(), result)
+                    .expect("applying the command result failed"),
                 CommandStartResult::Yield(executing_command) => {
                     self.current_command = Some(executing_command);
                 }
@@ -158,6 +478,8 @@ impl Updatable for Adv {
         }
 
         self.adv_state.update(context);
+        self.adv_state
+            .update_voice(&self.vm_state, context.asset_server);
     }
 }
 
@@ -203,6 +525,33 @@ impl OverlayVisitable for Adv {
                     .root_layer_group
                     .message_layer()
                     .visit_overlay(collector);
+                self.adv_state.se_player.visit_overlay(collector);
+                collector.overlay(
+                    "Session",
+                    |_ctx, top_left| {
+                        let playtime = GLOBAL.lock().unwrap().playtime();
+                        let stats = &self.adv_state.session_stats;
+                        top_left.label(format!(
+                            "Playtime: {} (session {}s), lines read: {}, choices: {}",
+                            playtime,
+                            self.playtime.session().as_secs(),
+                            stats.lines_read,
+                            stats.choices_made
+                        ));
+                    },
+                    false,
+                );
+                collector.overlay(
+                    "Text History",
+                    |ctx, _top_left| {
+                        let mut open = self.history_open.get();
+                        Window::new("Text History")
+                            .open(&mut open)
+                            .show(ctx, |ui| self.history_ui(ui));
+                        self.history_open.set(open);
+                    },
+                    true,
+                );
                 collector.overlay(
                     "User Layers",
                     |ctx, _top_left| {
@@ -229,17 +578,171 @@ impl OverlayVisitable for Adv {
                     },
                     false,
                 );
+                if self.adv_state.autosave.is_some() {
+                    collector.overlay(
+                        "Autosaves",
+                        |ctx, _top_left| {
+                            Window::new("Autosaves").show(ctx, |ui| {
+                                self.autosave_ui(ui);
+                            });
+                        },
+                        false,
+                    );
+                }
+                if !self.scene_table.is_empty() {
+                    collector.overlay(
+                        "Scene Jump",
+                        |ctx, _top_left| {
+                            Window::new("Scene Jump").show(ctx, |ui| {
+                                self.scene_jump_ui(ui);
+                            });
+                        },
+                        false,
+                    );
+                }
             },
             true,
         );
     }
 }
 
+impl Adv {
+    fn history_ui(&self, ui: &mut egui::Ui) {
+        egui::ScrollArea::vertical()
+            .max_height(500.0)
+            .stick_to_bottom(true)
+            .show(ui, |ui| {
+                for line in self.adv_state.text_history.iter_recent(usize::MAX) {
+                    if let Some(speaker) = &line.speaker {
+                        ui.strong(speaker);
+                    }
+                    ui.label(&line.text);
+                    ui.separator();
+                }
+            });
+    }
+
+    /// the load screen of the autosaves, newest first
+    fn autosave_ui(&self, ui: &mut egui::Ui) {
+        let Some(autosave) = &self.adv_state.autosave else {
+            return;
+        };
+        // listed again only once a slot was written, not on every frame
+        if autosave.entries_stale.swap(false, Ordering::Relaxed) {
+            *autosave.entries.borrow_mut() =
+                autosave.store.list().map_err(|err| format!("{:#}", err));
+        }
+        let entries = autosave.entries.borrow();
+        let entries = match &*entries {
+            Ok(entries) => entries,
+            Err(err) => {
+                ui.label(format!("Could not list the autosaves: {}", err));
+                return;
+            }
+        };
+        if entries.is_empty() {
+            ui.label("No autosaves yet");
+        }
+        for entry in entries {
+            ui.horizontal(|ui| {
+                ui.monospace(format!("#{:<4} {}", entry.sequence, entry.playtime));
+                if ui.button("Load").clicked() {
+                    self.autosave_load_request.replace(Some(entry.clone()));
+                }
+            });
+        }
+    }
+
+    fn scene_jump_ui(&self, ui: &mut egui::Ui) {
+        match self.scene_jump_selection.get() {
+            None => {
+                for (i, scene) in self.scene_table.iter().enumerate() {
+                    if ui
+                        .button(format!("{:08x} {}", scene.address, scene.name))
+                        .clicked()
+                    {
+                        self.scene_jump_selection.set(Some(i));
+                    }
+                }
+            }
+            Some(i) => {
+                let scene = &self.scene_table[i];
+                ui.label(format!(
+                    "Jump to {}? The game state may be inconsistent after the jump.",
+                    scene.name
+                ));
+                ui.horizontal(|ui| {
+                    if ui.button("Jump").clicked() {
+                        self.scene_jump_request.set(Some(scene.address));
+                        self.scene_jump_selection.set(None);
+                    }
+                    if ui.button("Cancel").clicked() {
+                        self.scene_jump_selection.set(None);
+                    }
+                });
+            }
+        }
+    }
+}
+
+/// The autosave state of a running game, see [`Adv::enable_autosave`]
+pub struct Autosave {
+    pub scheduler: AutosaveScheduler,
+    store: AutosaveStore,
+    next_sequence: u64,
+    /// the listing of the load screen, or why it failed
+    entries: RefCell<Result<Vec<AutosaveEntry>, String>>,
+    /// set once a slot is written, the load screen lists the slots again
+    entries_stale: Arc<AtomicBool>,
+}
+
+impl Autosave {
+    /// Writes the globals, the script threads, the audio and the backlog to the next autosave
+    /// slot, on the IO pool so the frame doesn't wait
+    fn save(
+        &mut self,
+        trigger: AutosaveTrigger,
+        vm: &VmSnapshot,
+        audio: &AudioManagerSnapshotV1,
+        history: &TextHistory,
+    ) {
+        let snapshot = match save_state_snapshot(&GLOBAL.lock().unwrap(), vm, audio, history) {
+            Ok(snapshot) => snapshot,
+            Err(err) => {
+                warn!("Could not take the autosave snapshot: {:#}", err);
+                return;
+            }
+        };
+        let sequence = self.next_sequence;
+        self.next_sequence += 1;
+        debug!("Autosave {} ({:?})", sequence, trigger);
+
+        let store = self.store.clone();
+        let entries_stale = self.entries_stale.clone();
+        IoTaskPool::get()
+            .spawn(async move {
+                match store.write(sequence, &snapshot) {
+                    Ok(_) => entries_stale.store(true, Ordering::Relaxed),
+                    Err(err) => warn!("Autosave failed: {:#}", err),
+                }
+            })
+            .detach();
+    }
+}
+
 pub struct AdvState {
     pub root_layer_group: RootLayerGroup,
     pub audio_manager: Arc<AudioManager>,
     pub bgm_player: BgmPlayer,
     pub se_player: SePlayer,
+    pub voice_player: VoicePlayer,
+    pub autosave: Option<Autosave>,
+    pub text_history: TextHistory,
+    pub session_stats: SessionStats,
+    pub voice_config: VoiceConfig,
+    /// the voice of the line couldn't be loaded, the mouths move on a timer until the line
+    /// is revealed
+    voice_missing: bool,
 }
 
 impl AdvState {
@@ -256,7 +759,132 @@ impl AdvState {
             ),
             audio_manager: audio_manager.clone(),
             bgm_player: BgmPlayer::new(audio_manager.clone()),
-            se_player: SePlayer::new(audio_manager),
+            se_player: SePlayer::new(audio_manager.clone()),
+            voice_player: VoicePlayer::new(audio_manager),
+            autosave: None,
+            text_history: TextHistory::default(),
+            session_stats: SessionStats::default(),
+            voice_config: VoiceConfig::default(),
+            voice_missing: false,
+        }
+    }
+
+    /// a line was fully displayed
+    pub fn record_line(&mut self, line: HistoryLine) {
+        self.text_history.push_line(line);
+        self.session_stats.lines_read += 1;
+    }
+
+    /// drops what belongs to the current scene, before jumping to another one
+    pub fn reset_scene(&mut self) {
+        self.root_layer_group.message_layer_mut().close();
+        self.se_player.stop_all(Tween::MS_15);
+        self.voice_player.stop(Tween::MS_15);
+        self.voice_missing = false;
+        if self.bgm_player.is_playing() {
+            self.bgm_player.stop(Tween::MS_15);
+        }
+        // a dropped MOVIEWAIT would keep the notifications held back
+        self.root_layer_group
+            .notification_layer_mut()
+            .set_suppressed(false);
+    }
+
+    /// what the BGM and SE players are playing, for the save state
+    pub fn capture_audio_snapshot_v1(&self) -> AudioManagerSnapshotV1 {
+        AudioManagerSnapshotV1 {
+            bgm: self.bgm_player.capture_snapshot_v1(),
+            se: self.se_player.capture_snapshot_v1(),
+        }
+    }
+
+    /// replaces what plays with the sounds of a save state, resumed where they were
+    pub fn apply_audio_snapshot_v1(
+        &mut self,
+        snapshot: &AudioManagerSnapshotV1,
+        scenario: &Scenario,
+        asset_server: &AnyAssetServer,
+    ) {
+        self.se_player.stop_all(Tween::MS_15);
+        match snapshot.bgm.filter(|bgm| bgm.playing) {
+            Some(bgm) => {
+                let path = scenario.info_tables().bgm_info(bgm.asset_id).path();
+                match asset_server.load_sync(&path) {
+                    Ok(audio) => self.bgm_player.apply_snapshot_v1(audio, &bgm),
+                    Err(err) => warn!("Could not resume BGM {}: {:#}", bgm.asset_id, err),
+                }
+            }
+            None if self.bgm_player.is_playing() => self.bgm_player.stop(Tween::MS_15),
+            None => {}
+        }
+
+        for (slot, se) in snapshot.se.iter().enumerate() {
+            let Some(se) = se.filter(|se| se.playing) else {
+                continue;
+            };
+            let path = scenario.info_tables().se_info(se.asset_id).path();
+            match asset_server.load_sync(&path) {
+                Ok(audio) => self.se_player.apply_snapshot_v1(slot, audio, &se),
+                Err(err) => warn!("Could not resume SE {}: {:#}", se.asset_id, err),
+            }
+        }
+    }
+
+    /// Plays again what played when the audio device was lost, on the one opened instead
+    pub fn reopen_audio(&mut self, scenario: &Scenario, asset_server: &AnyAssetServer) {
+        let snapshot = self.capture_audio_snapshot_v1();
+        self.bgm_player.reopen();
+        self.se_player.reopen();
+        self.voice_player.reopen();
+        self.apply_audio_snapshot_v1(&snapshot, scenario, asset_server);
+    }
+
+    /// Plays the voice the message reached. The bustups of the current plane move their mouths
+    /// with it while the message has the lip sync on, or on a timer while the line is
+    /// revealed if the voice couldn't be loaded.
+    pub fn update_voice(&mut self, vm_state: &VmState, asset_server: &AnyAssetServer) {
+        if let Some(voice) = self.root_layer_group.message_layer_mut().take_voice() {
+            self.voice_missing = false;
+            let muted = voice_character_id(&voice).is_some_and(|id| self.voice_config.is_muted(id));
+            if muted {
+                // skipped like a finished voice, the line before it doesn't go on either
+                debug!("Voice {} is muted", voice);
+                self.voice_player.stop(Tween::MS_15);
+            } else {
+                match asset_server.load_sync(&voice) {
+                    Ok(audio) => self.voice_player.play(audio),
+                    Err(err) => {
+                        warn!("Could not play voice {}: {:#}", voice, err);
+                        self.voice_missing = true;
+                    }
+                }
+            }
+        }
+
+        let message_layer = self.root_layer_group.message_layer();
+        if message_layer.is_fully_revealed() {
+            self.voice_missing = false;
+        }
+        let lip_sync = message_layer.lip_sync();
+        let plane = self
+            .root_layer_group
+            .screen_layer_mut()
+            .page_layer_mut()
+            .plane_mut(vm_state.layers.current_plane);
+        let ids = plane.get_layer_ids().collect_vec();
+        for id in ids {
+            if let Some(UserLayer::BustupLayer(bustup)) = plane.get_layer_mut(id) {
+                let speech = if !lip_sync {
+                    Speech::Silent
+                } else if let Some(envelope) = self.voice_player.envelope() {
+                    Speech::Voice(envelope)
+                } else if self.voice_missing {
+                    Speech::Timed
+                } else {
+                    Speech::Silent
+                };
+                bustup.set_speech(speech);
+            }
         }
     }
 
@@ -274,6 +902,31 @@ impl AdvState {
             .plane_mut(vm_state.layers.current_plane)
     }
 
+    /// `PrimHit(id, ..)`: whether the cursor is over an opaque pixel of the layer `id` of the
+    /// current plane, and not under another layer. `cursor` is in game screen coordinates,
+    /// `None` outside of the picture.
+    pub fn prim_hit(&self, vm_state: &VmState, args: Args, cursor: Option<Vec2>) -> bool {
+        let id = match args.int(0) {
+            Ok(id) if (0..LAYERS_COUNT as i32).contains(&id) => LayerId::new(id as u32),
+            Ok(id) => {
+                warn!("PrimHit: layer id {} out of range", id);
+                return false;
+            }
+            Err(err) => {
+                warn!("{:#}", err);
+                return false;
+            }
+        };
+        let Some(cursor) = cursor else {
+            return false;
+        };
+
+        let transform = self.root_layer_group.plane_transform();
+        self.current_plane_layer_group(vm_state)
+            .hit_test(transform, cursor)
+            == Some(id)
+    }
+
     pub fn get_layer(&self, vm_state: &VmState, id: LayerId) -> Option<&UserLayer> {
         let layer = self.current_plane_layer_group(vm_state).get_layer(id);
         if let Some(layer) = layer {
@@ -29,4 +29,11 @@ impl AudioState {
             se: [None; SE_SLOT_COUNT],
         }
     }
+
+    /// the state of an SE slot, `None` for slots picked by the engine (negative) or out of range
+    pub fn se_mut(&mut self, slot: i32) -> Option<&mut Option<SeState>> {
+        usize::try_from(slot)
+            .ok()
+            .and_then(|slot| self.se.get_mut(slot))
+    }
 }
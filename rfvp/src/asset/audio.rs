@@ -1,5 +1,5 @@
 use anyhow::{Context, Result};
-use rfvp_core::format::audio::{read_audio, AudioFile};
+use rfvp_core::format::audio::{read_audio, read_wav, AudioFile, WavFile};
 
 use crate::asset::Asset;
 
@@ -8,3 +8,9 @@ impl Asset for AudioFile {
         read_audio(&data).context("Parsing audio file")
     }
 }
+
+impl Asset for WavFile {
+    fn load_from_bytes(data: Vec<u8>) -> Result<Self> {
+        read_wav(&data).context("Parsing WAV file")
+    }
+}
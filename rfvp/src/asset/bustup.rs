@@ -1,6 +1,7 @@
 use anyhow::{Context, Result};
 use bevy_utils::HashMap;
 use glam::{vec2, Vec2};
+use rfvp_core::memory::{self, MemoryKind, MemoryTicket};
 use rfvp_render::{GpuCommonResources, GpuImage, LazyGpuImage};
 
 use crate::asset::Asset;
@@ -14,9 +15,29 @@ struct BustupExpression {
 pub struct Bustup {
     base_picture: LazyGpuImage,
     emotions: HashMap<String, BustupExpression>,
+    memory: MemoryTicket,
 }
 
 impl Bustup {
+    fn images(&self) -> impl Iterator<Item = &LazyGpuImage> {
+        std::iter::once(&self.base_picture).chain(self.emotions.values().flat_map(|emotion| {
+            emotion
+                .face_picture
+                .iter()
+                .chain(emotion.mouth_pictures.iter())
+        }))
+    }
+
+    /// Drops the CPU copy of the parts on the GPU, returns the bytes freed
+    pub fn release_cpu_images(&self) -> u64 {
+        let freed = self
+            .images()
+            .map(|image| image.release_cpu_image() as u64)
+            .sum::<u64>();
+        self.memory.set(self.memory.bytes() - freed);
+        freed
+    }
+
     pub fn base_gpu_image(&self, resources: &GpuCommonResources) -> &GpuImage {
         self.base_picture.gpu_image(resources)
     }
@@ -35,6 +56,13 @@ impl Bustup {
             .map(|pic| pic.gpu_image(resources))
     }
 
+    /// the number of mouths of an emotion, the first one closed
+    pub fn mouth_count(&self, emotion: &str) -> usize {
+        self.emotions
+            .get(emotion)
+            .map_or(0, |emotion| emotion.mouth_pictures.len())
+    }
+
     pub fn mouth_gpu_image(
         &self,
         resources: &GpuCommonResources,
@@ -65,7 +93,7 @@ impl Asset for Bustup {
 
         let origin = vec2(bustup.origin.0 as f32, bustup.origin.1 as f32);
 
-        Ok(Self {
+        let bustup = Self {
             base_picture: LazyGpuImage::new(bustup.base_image, origin, Some("Bustup Base")),
             emotions: bustup
                 .expressions
@@ -113,6 +141,14 @@ impl Asset for Bustup {
                     )
                 })
                 .collect(),
-        })
+            memory: memory::governor().track(MemoryKind::Bustups, 0),
+        };
+        let size = bustup
+            .images()
+            .map(|image| image.cpu_image_size() as u64)
+            .sum();
+        bustup.memory.set(size);
+
+        Ok(bustup)
     }
 }
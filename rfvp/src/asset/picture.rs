@@ -1,6 +1,9 @@
 use anyhow::Result;
-use glam::vec2;
-use rfvp_core::format::pic::NvsgTexture;
+use glam::{vec2, UVec2};
+use rfvp_core::{
+    format::pic::{GraphInfo, NvsgTexture},
+    memory::{self, MemoryKind, MemoryTicket},
+};
 use rfvp_render::{GpuCommonResources, GpuImage, LazyGpuImage};
 
 use crate::asset::Asset;
@@ -9,12 +12,31 @@ use crate::asset::Asset;
 pub struct Picture {
     picture: LazyGpuImage,
     nvsg_texture: NvsgTexture,
+    memory: MemoryTicket,
 }
 
 impl Picture {
     pub fn gpu_image(&self, resources: &GpuCommonResources) -> &GpuImage {
         self.picture.gpu_image(resources)
     }
+
+    /// The size and offset of the picture, for the debug tools
+    pub fn info(&self) -> GraphInfo {
+        self.nvsg_texture.info()
+    }
+
+    /// The alpha of a pixel, for hit testing. Read from the encoded pixels, which are kept
+    /// when the decoded ones are released.
+    pub fn alpha_at(&self, texel: UVec2) -> Option<u8> {
+        self.nvsg_texture.alpha_at(0, texel.x, texel.y)
+    }
+
+    /// Drops the CPU copy of the pixels if they are on the GPU, returns the bytes freed
+    pub fn release_cpu_image(&self) -> u64 {
+        let freed = self.picture.release_cpu_image() as u64;
+        self.memory.set(self.memory.bytes() - freed);
+        freed
+    }
 }
 
 impl Asset for Picture {
@@ -33,9 +55,25 @@ impl Asset for Picture {
             None,
         );
 
+        let memory = memory::governor().track(
+            MemoryKind::Pictures,
+            (picture.cpu_image_size() + container.data_size()) as u64,
+        );
+
         Ok(Self {
             picture,
             nvsg_texture: container,
+            memory,
         })
     }
+
+    fn summary(&self) -> String {
+        let info = self.info();
+        format!(
+            "{}x{}{}",
+            info.width,
+            info.height,
+            if info.ready { "" } else { " (not ready)" }
+        )
+    }
 }
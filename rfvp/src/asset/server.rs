@@ -17,10 +17,23 @@ use rfvp_core::format::vfs::Vfs;
 use rfvp_tasks::{AsyncComputeTaskPool, IoTaskPool};
 use tracing::debug;
 
-use rfvp_core::format::scenario::Nls;
+use rfvp_core::{
+    format::scenario::Nls,
+    memory::{DegradationStep, Reclaim},
+};
+
+use crate::{
+    asset::{bustup::Bustup, picture::Picture},
+    render::overlay::OverlayCollector,
+};
 
 pub trait Asset: Send + Sync + Sized + 'static {
     fn load_from_bytes(data: Vec<u8>) -> Result<Self>;
+
+    /// what the debug overlays show about the asset, after its path
+    fn summary(&self) -> String {
+        String::new()
+    }
 }
 
 struct AssetMap<T: Asset>(HashMap<String, Weak<T>>);
@@ -87,6 +100,28 @@ impl<Io: AssetIo> AssetServer<Io> {
         Ok(asset)
     }
 
+    /// Read a file which isn't an asset, bypassing the cache
+    pub async fn read_file(&self, path: &str) -> Result<Vec<u8>> {
+        self.io
+            .read_file(path)
+            .await
+            .with_context(|| format!("Reading file {:?}", path))
+    }
+
+    /// The assets of a type which are still in use, sorted by path
+    pub fn loaded<T: Asset>(&self) -> Vec<(String, Arc<T>)> {
+        let loaded_assets = self.loaded_assets.read().unwrap();
+        let Some(loaded) = loaded_assets.get::<AssetMap<T>>() else {
+            return Vec::new();
+        };
+        let mut assets = loaded
+            .iter()
+            .filter_map(|(path, asset)| Some((path.clone(), asset.upgrade()?)))
+            .collect::<Vec<_>>();
+        assets.sort_by(|(a, _), (b, _)| a.cmp(b));
+        assets
+    }
+
     /// Load an asset synchronously. This is useful for assets not requiring much CPU time to load.
     /// Though it might cause lockups if the loading is not blazing fast (tm).
     ///
@@ -98,6 +133,24 @@ impl<Io: AssetIo> AssetServer<Io> {
 
 pub type AnyAssetServer = AssetServer<AnyAssetIo>;
 
+impl<Io: AssetIo> AssetServer<Io> {
+    /// Lists the loaded assets of a type in the "Loaded `name`" debug overlay, with their
+    /// [`Asset::summary`]
+    pub fn visit_loaded_overlay<T: Asset>(&self, collector: &mut OverlayCollector, name: &str) {
+        collector.overlay(
+            &format!("Loaded {}", name),
+            |_ctx, top_left| {
+                let assets = self.loaded::<T>();
+                top_left.label(format!("{}: {}", name, assets.len()));
+                for (path, asset) in assets {
+                    top_left.label(format!("{}: {}", path, asset.summary()));
+                }
+            },
+            false,
+        );
+    }
+}
+
 impl AnyAssetServer {
     #[allow(unused)]
     pub fn new_dir(root_path: PathBuf) -> Self {
@@ -111,6 +164,27 @@ impl AnyAssetServer {
     }
 }
 
+/// The pictures and bustups give back their CPU pixels once they are on the GPU
+impl<Io: AssetIo + Send + Sync> Reclaim for AssetServer<Io> {
+    fn reclaim(&self, step: DegradationStep) -> u64 {
+        match step {
+            DegradationStep::DropCpuImages => {
+                let pictures = self
+                    .loaded::<Picture>()
+                    .iter()
+                    .map(|(_, picture)| picture.release_cpu_image())
+                    .sum::<u64>();
+                let bustups = self
+                    .loaded::<Bustup>()
+                    .iter()
+                    .map(|(_, bustup)| bustup.release_cpu_images())
+                    .sum::<u64>();
+                pictures + bustups
+            }
+        }
+    }
+}
+
 #[async_trait]
 pub trait AssetIo {
     async fn read_file(&self, path: &str) -> Result<Vec<u8>>;
@@ -283,3 +357,56 @@ impl AssetIo for LayeredAssetIo {
         ))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use rfvp_tasks::TaskPool;
+
+    use super::*;
+
+    /// every path reads as its own name
+    struct NameIo;
+
+    #[async_trait]
+    impl AssetIo for NameIo {
+        async fn read_file(&self, path: &str) -> Result<Vec<u8>> {
+            Ok(path.as_bytes().to_vec())
+        }
+    }
+
+    struct Text(String);
+
+    impl Asset for Text {
+        fn load_from_bytes(data: Vec<u8>) -> Result<Self> {
+            Ok(Text(String::from_utf8(data)?))
+        }
+
+        fn summary(&self) -> String {
+            self.0.clone()
+        }
+    }
+
+    #[test]
+    fn test_loaded_assets() {
+        AsyncComputeTaskPool::init(TaskPool::new);
+        let server = AssetServer::new(NameIo);
+        let loaded = || {
+            server
+                .loaded::<Text>()
+                .into_iter()
+                .map(|(path, text)| format!("{}={}", path, text.summary()))
+                .collect::<Vec<_>>()
+        };
+
+        let b = server.load_sync::<Text, _>("b").unwrap();
+        let a = server.load_sync::<Text, _>("a").unwrap();
+        assert!(Arc::ptr_eq(&a, &server.load_sync("a").unwrap()));
+        assert_eq!(loaded(), ["a=a", "b=b"]);
+
+        // gone from the list once nothing uses it
+        drop(a);
+        assert_eq!(loaded(), ["b=b"]);
+        drop(b);
+        assert!(loaded().is_empty());
+    }
+}
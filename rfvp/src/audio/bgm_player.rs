@@ -1,29 +1,26 @@
 use std::sync::Arc;
 
 use kira::track::{TrackBuilder, TrackHandle, TrackId, TrackRoutes};
-use rfvp_audio::{AudioData, AudioFile, AudioHandle, AudioManager, AudioSettings};
+use rfvp_audio::{AudioFile, AudioManager};
 use rfvp_core::{
+    format::save::audio_snapshot::AudioSlotSnapshotV1,
     time::Tween,
     vm::command::types::{Pan, Volume},
 };
 use tracing::warn;
 
+use super::playing_sound::PlayingSound;
+
 pub struct BgmPlayer {
     audio_manager: Arc<AudioManager>,
     bgm_track: TrackHandle,
     // TODO: async track loading?
-    current_bgm: Option<AudioHandle>,
+    current_bgm: Option<PlayingSound>,
 }
 
 impl BgmPlayer {
     pub fn new(audio_manager: Arc<AudioManager>) -> Self {
-        let mut manager = audio_manager.kira_manager().lock().unwrap();
-
-        let bgm_track = manager
-            .add_sub_track(TrackBuilder::new().routes(TrackRoutes::parent(TrackId::Main)))
-            .expect("Failed to create bgm track");
-
-        drop(manager);
+        let bgm_track = Self::add_track(&audio_manager);
 
         Self {
             audio_manager,
@@ -32,46 +29,85 @@ impl BgmPlayer {
         }
     }
 
+    fn add_track(audio_manager: &AudioManager) -> TrackHandle {
+        audio_manager
+            .kira_manager()
+            .lock()
+            .unwrap()
+            .add_sub_track(TrackBuilder::new().routes(TrackRoutes::parent(TrackId::Main)))
+            .expect("Failed to create bgm track")
+    }
+
+    /// After the audio device was opened again, like [`SePlayer::reopen`]
+    ///
+    /// [`SePlayer::reopen`]: super::se_player::SePlayer::reopen
+    pub fn reopen(&mut self) {
+        self.current_bgm = None;
+        self.bgm_track = Self::add_track(&self.audio_manager);
+    }
+
     pub fn play(
         &mut self,
         bgm: Arc<AudioFile>,
+        bgm_id: i32,
         _display_name: &str,
         repeat: bool,
         volume: Volume,
         fade_in: Tween,
     ) {
-        let loop_start = repeat.then_some(bgm.info().loop_start);
-        let kira_data = AudioData::from_audio_file(
+        let state = AudioSlotSnapshotV1 {
+            asset_id: bgm_id,
+            playing: true,
+            volume: volume.0,
+            pan: Pan::default().0,
+            loop_start: repeat.then_some(bgm.info().loop_start),
+            position_ms: 0,
+        };
+        self.start(bgm, state, fade_in);
+    }
+
+    fn start(&mut self, bgm: Arc<AudioFile>, state: AudioSlotSnapshotV1, fade_in: Tween) {
+        let sound = PlayingSound::start(
+            &self.audio_manager,
+            self.bgm_track.id(),
             bgm,
-            AudioSettings {
-                track: self.bgm_track.id(),
-                fade_in,
-                loop_start,
-                volume,
-                pan: Pan::default(),
-            },
+            state,
+            fade_in,
         );
 
-        let handle = self.audio_manager.play(kira_data);
-
-        if let Some(mut old_handle) = self.current_bgm.take() {
-            old_handle.stop(Tween::MS_15).unwrap();
+        if let Some(mut old_bgm) = self.current_bgm.take() {
+            old_bgm.handle.stop(Tween::MS_15).unwrap();
         }
 
-        self.current_bgm = Some(handle);
+        self.current_bgm = sound;
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.current_bgm.is_some()
+    }
+
+    /// the BGM being played, `None` if there is none
+    pub fn capture_snapshot_v1(&self) -> Option<AudioSlotSnapshotV1> {
+        self.current_bgm.as_ref().map(PlayingSound::snapshot)
+    }
+
+    /// resume a BGM of [`Self::capture_snapshot_v1`] where it was, `bgm` is the file of its id
+    pub fn apply_snapshot_v1(&mut self, bgm: Arc<AudioFile>, snapshot: &AudioSlotSnapshotV1) {
+        self.start(bgm, *snapshot, Tween::MS_15);
     }
 
     pub fn set_volume(&mut self, volume: Volume, tween: Tween) {
-        if let Some(handle) = self.current_bgm.as_mut() {
-            handle.set_volume(volume, tween).unwrap();
+        if let Some(bgm) = self.current_bgm.as_mut() {
+            bgm.handle.set_volume(volume, tween).unwrap();
+            bgm.state.volume = volume.0;
         } else {
             warn!("Tried to set volume of BGM, but no BGM is currently playing");
         }
     }
 
     pub fn stop(&mut self, fade_out: Tween) {
-        if let Some(mut handle) = self.current_bgm.take() {
-            handle.stop(fade_out).unwrap();
+        if let Some(mut bgm) = self.current_bgm.take() {
+            bgm.handle.stop(fade_out).unwrap();
         } else {
             warn!("Tried to stop BGM, but no BGM is currently playing");
         }
@@ -1,5 +1,10 @@
 mod bgm_player;
+mod playing_sound;
+mod se_channels;
 mod se_player;
+mod voice_player;
 
 pub use bgm_player::BgmPlayer;
+pub use se_channels::SeChannelInfo;
 pub use se_player::{SePlayer, SE_SLOT_COUNT};
+pub use voice_player::VoicePlayer;
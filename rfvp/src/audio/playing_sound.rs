@@ -0,0 +1,65 @@
+use std::sync::Arc;
+
+use kira::track::TrackId;
+use rfvp_audio::{AudioData, AudioFile, AudioHandle, AudioManager, AudioSettings};
+use rfvp_core::{
+    format::save::audio_snapshot::AudioSlotSnapshotV1,
+    time::Tween,
+    vm::command::types::{AudioWaitStatus, Pan, Volume},
+};
+
+/// A sound started by a player, with what's needed to start it again when a save is loaded
+pub struct PlayingSound {
+    pub handle: AudioHandle,
+    /// the state it was started with, the position is only filled in by [`Self::snapshot`]
+    pub state: AudioSlotSnapshotV1,
+}
+
+impl PlayingSound {
+    /// start `audio` on `track`, from the sample of `state.position_ms`
+    pub fn start(
+        audio_manager: &AudioManager,
+        track: TrackId,
+        audio: Arc<AudioFile>,
+        state: AudioSlotSnapshotV1,
+        fade_in: Tween,
+    ) -> Option<Self> {
+        let start_position = state.resume_sample(audio.info())?;
+        let kira_data = AudioData::from_audio_file(
+            audio,
+            AudioSettings {
+                track,
+                fade_in,
+                loop_start: state.loop_start,
+                volume: Volume(state.volume),
+                pan: Pan(state.pan),
+                start_position,
+            },
+        );
+
+        Some(Self {
+            handle: audio_manager.play(kira_data),
+            state: AudioSlotSnapshotV1 {
+                position_ms: 0,
+                ..state
+            },
+        })
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.handle
+            .get_wait_status()
+            .contains(AudioWaitStatus::STOPPED)
+    }
+
+    pub fn snapshot(&self) -> AudioSlotSnapshotV1 {
+        AudioSlotSnapshotV1 {
+            playing: self
+                .handle
+                .get_wait_status()
+                .contains(AudioWaitStatus::PLAYING),
+            position_ms: (self.handle.position().as_seconds() * 1000.0).round() as u32,
+            ..self.state
+        }
+    }
+}
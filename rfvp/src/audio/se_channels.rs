@@ -0,0 +1,217 @@
+/// How many SEs can play at once when the script lets the engine pick the channel
+pub const MAX_SE_VOICES: usize = 8;
+
+/// What is playing on an SE channel
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SeChannelInfo {
+    pub se_id: i32,
+    pub repeat: bool,
+}
+
+struct Voice<H> {
+    handle: H,
+    info: SeChannelInfo,
+    /// when it was started, used to find the oldest one to steal
+    sequence: u64,
+    /// picked by the engine, only these are stolen
+    auto: bool,
+}
+
+/// The result of [`SeChannels::place`]
+pub struct SePlacement<H> {
+    pub channel: usize,
+    /// whether the engine picked the channel
+    pub auto: bool,
+    /// the sound which has to be stopped to make room: the previous sound of the
+    /// channel, or the oldest voice when stealing
+    pub evicted: Option<H>,
+}
+
+/// Keeps track of what plays on each SE channel.
+///
+/// Scripts address channels by number, playing on a channel replaces whatever
+/// played there. A negative channel lets the engine pick a free one, these never
+/// go over [`MAX_SE_VOICES`] at once: the oldest of them is stolen instead, so
+/// spamming the same SE doesn't stack up dozens of instances. The sounds of the
+/// channels addressed by the script are never stolen.
+///
+/// Generic over the handle so the policy can be tested without an audio device.
+pub struct SeChannels<H> {
+    voices: Vec<Option<Voice<H>>>,
+    max_voices: usize,
+    next_sequence: u64,
+}
+
+impl<H> SeChannels<H> {
+    pub fn new(channel_count: usize, max_voices: usize) -> Self {
+        Self {
+            voices: (0..channel_count).map(|_| None).collect(),
+            max_voices,
+            next_sequence: 0,
+        }
+    }
+
+    pub fn channel_count(&self) -> usize {
+        self.voices.len()
+    }
+
+    fn channel(&self, channel: i32) -> Option<usize> {
+        usize::try_from(channel)
+            .ok()
+            .filter(|&channel| channel < self.voices.len())
+    }
+
+    fn auto_voices(&self) -> impl Iterator<Item = (usize, &Voice<H>)> {
+        self.voices
+            .iter()
+            .enumerate()
+            .filter_map(|(i, v)| v.as_ref().filter(|v| v.auto).map(|v| (i, v)))
+    }
+
+    /// Decides where a new sound goes, `channel` is the script's channel argument.
+    /// `None` if the channel doesn't exist, or if there is no free channel and no
+    /// engine-picked sound to steal.
+    pub fn place(&mut self, channel: i32) -> Option<SePlacement<H>> {
+        let auto = channel < 0;
+        let channel = if auto {
+            let free = self.voices.iter().position(|v| v.is_none());
+            match free {
+                Some(free) if self.auto_voices().count() < self.max_voices => free,
+                _ => self
+                    .auto_voices()
+                    .min_by_key(|&(_, v)| v.sequence)
+                    .map(|(i, _)| i)?,
+            }
+        } else {
+            self.channel(channel)?
+        };
+
+        let evicted = self.voices[channel].take().map(|v| v.handle);
+
+        Some(SePlacement {
+            channel,
+            auto,
+            evicted,
+        })
+    }
+
+    /// Records the sound started on a channel returned by [`Self::place`]
+    pub fn insert(&mut self, placement: &SePlacement<H>, info: SeChannelInfo, handle: H) {
+        self.voices[placement.channel] = Some(Voice {
+            handle,
+            info,
+            sequence: self.next_sequence,
+            auto: placement.auto,
+        });
+        self.next_sequence += 1;
+    }
+
+    pub fn get_mut(&mut self, channel: i32) -> Option<&mut H> {
+        let channel = self.channel(channel)?;
+        self.voices[channel].as_mut().map(|v| &mut v.handle)
+    }
+
+    pub fn get(&self, channel: i32) -> Option<&H> {
+        let channel = self.channel(channel)?;
+        self.voices[channel].as_ref().map(|v| &v.handle)
+    }
+
+    /// Removes the sound from the channel, to be stopped by the caller
+    pub fn take(&mut self, channel: i32) -> Option<H> {
+        let channel = self.channel(channel)?;
+        self.voices[channel].take().map(|v| v.handle)
+    }
+
+    /// Frees the channels whose sound has finished on its own
+    pub fn retain(&mut self, mut is_playing: impl FnMut(&H) -> bool) {
+        for voice in &mut self.voices {
+            if voice.as_ref().is_some_and(|v| !is_playing(&v.handle)) {
+                *voice = None;
+            }
+        }
+    }
+
+    /// What plays on each channel, for the debug overlay
+    pub fn info(&self) -> impl Iterator<Item = (usize, SeChannelInfo)> + '_ {
+        self.voices
+            .iter()
+            .enumerate()
+            .filter_map(|(i, v)| v.as_ref().map(|v| (i, v.info)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// stands in for an audio handle, one per generated click
+    #[derive(Debug, PartialEq)]
+    struct Click(u32);
+
+    fn play(channels: &mut SeChannels<Click>, channel: i32, click: u32) -> (usize, Option<Click>) {
+        let placement = channels.place(channel).unwrap();
+        channels.insert(
+            &placement,
+            SeChannelInfo {
+                se_id: click as i32,
+                repeat: false,
+            },
+            Click(click),
+        );
+        (placement.channel, placement.evicted)
+    }
+
+    #[test]
+    fn test_steals_oldest() {
+        let mut channels = SeChannels::new(32, 4);
+        for click in 0..4 {
+            assert_eq!(play(&mut channels, -1, click), (click as usize, None));
+        }
+
+        // over the cap, the first click is the oldest
+        assert_eq!(play(&mut channels, -1, 4), (0, Some(Click(0))));
+        assert_eq!(play(&mut channels, -1, 5), (1, Some(Click(1))));
+
+        // a finished sound frees its channel without stealing
+        channels.retain(|click| click.0 != 3);
+        assert_eq!(play(&mut channels, -1, 6), (3, None));
+        assert_eq!(channels.info().count(), 4);
+    }
+
+    #[test]
+    fn test_channel_stop() {
+        let mut channels = SeChannels::new(8, 8);
+        assert_eq!(play(&mut channels, 5, 0), (5, None));
+        // playing on the same channel replaces the sound
+        assert_eq!(play(&mut channels, 5, 1), (5, Some(Click(0))));
+
+        assert_eq!(channels.take(5), Some(Click(1)));
+        assert_eq!(channels.take(5), None);
+        assert!(channels.get(5).is_none());
+
+        assert!(channels.place(8).is_none());
+        assert!(channels.take(100).is_none());
+    }
+
+    #[test]
+    fn test_steals_only_engine_picked() {
+        let mut channels = SeChannels::new(6, 2);
+        // the script's sounds are the oldest ones
+        assert_eq!(play(&mut channels, 0, 0), (0, None));
+        assert_eq!(play(&mut channels, 1, 1), (1, None));
+
+        assert_eq!(play(&mut channels, -1, 2), (2, None));
+        assert_eq!(play(&mut channels, -1, 3), (3, None));
+        // over the cap, the oldest engine-picked sound goes
+        assert_eq!(play(&mut channels, -1, 4), (2, Some(Click(2))));
+        assert_eq!(channels.get(0), Some(&Click(0)));
+        assert_eq!(channels.get(1), Some(&Click(1)));
+
+        // every channel is busy, the script's sounds still aren't stolen
+        let mut channels = SeChannels::new(2, 2);
+        play(&mut channels, 0, 0);
+        play(&mut channels, 1, 1);
+        assert!(channels.place(-1).is_none());
+        assert_eq!(channels.info().count(), 2);
+    }
+}
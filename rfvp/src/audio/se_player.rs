@@ -1,77 +1,138 @@
 use std::sync::Arc;
 
 use kira::track::{TrackBuilder, TrackHandle, TrackId, TrackRoutes};
-use rfvp_audio::{AudioData, AudioFile, AudioHandle, AudioManager, AudioSettings};
+use rfvp_audio::{AudioFile, AudioManager};
 use rfvp_core::{
+    format::save::audio_snapshot::AudioSlotSnapshotV1,
     time::Tween,
     vm::command::types::{AudioWaitStatus, Pan, Volume},
 };
 use tracing::warn;
 
+use super::{
+    playing_sound::PlayingSound,
+    se_channels::{SeChannelInfo, SeChannels, MAX_SE_VOICES},
+};
+use crate::render::overlay::{OverlayCollector, OverlayVisitable};
+
 pub const SE_SLOT_COUNT: usize = 32;
 
 pub struct SePlayer {
     audio_manager: Arc<AudioManager>,
     se_tracks: [TrackHandle; SE_SLOT_COUNT],
-    se_slots: [Option<AudioHandle>; SE_SLOT_COUNT],
+    se_slots: SeChannels<PlayingSound>,
 }
 
 impl SePlayer {
     pub fn new(audio_manager: Arc<AudioManager>) -> Self {
+        let se_tracks = Self::add_tracks(&audio_manager);
+
+        Self {
+            audio_manager,
+            se_tracks,
+            se_slots: SeChannels::new(SE_SLOT_COUNT, MAX_SE_VOICES),
+        }
+    }
+
+    fn add_tracks(audio_manager: &AudioManager) -> [TrackHandle; SE_SLOT_COUNT] {
         let mut manager = audio_manager.kira_manager().lock().unwrap();
 
-        let se_tracks = [(); SE_SLOT_COUNT].map(|_| {
+        [(); SE_SLOT_COUNT].map(|_| {
             manager
                 .add_sub_track(TrackBuilder::new().routes(TrackRoutes::parent(TrackId::Main)))
                 .expect("Failed to create se track")
-        });
-
-        drop(manager);
+        })
+    }
 
-        Self {
-            audio_manager,
-            se_tracks,
-            se_slots: [(); SE_SLOT_COUNT].map(|_| None),
-        }
+    /// After the audio device was opened again: the tracks are created on the new one, and
+    /// the sounds of the old one are dropped without stopping them, nothing listens anymore
+    pub fn reopen(&mut self) {
+        self.se_slots.retain(|_| false);
+        self.se_tracks = Self::add_tracks(&self.audio_manager);
     }
 
+    /// play on the given slot, replacing what played there.
+    /// a negative slot picks a free one, stealing the oldest sound if too many are playing
     pub fn play(
         &mut self,
         slot: i32,
+        info: SeChannelInfo,
         se: Arc<AudioFile>,
-        repeat: bool,
         volume: Volume,
         pan: Pan,
         fade_in: Tween,
     ) {
-        let slot = slot as usize;
+        let state = AudioSlotSnapshotV1 {
+            asset_id: info.se_id,
+            playing: true,
+            volume: volume.0,
+            pan: pan.0,
+            loop_start: info.repeat.then_some(se.info().loop_start),
+            position_ms: 0,
+        };
+        self.start(slot, info, se, state, fade_in);
+    }
+
+    fn start(
+        &mut self,
+        slot: i32,
+        info: SeChannelInfo,
+        se: Arc<AudioFile>,
+        state: AudioSlotSnapshotV1,
+        fade_in: Tween,
+    ) {
+        self.se_slots.retain(|sound| !sound.is_stopped());
 
-        let loop_start = repeat.then_some(se.info().loop_start);
-        let kira_data = AudioData::from_audio_file(
+        let Some(mut placement) = self.se_slots.place(slot) else {
+            warn!(
+                "Tried to play a SE on slot {}, which does not exist or is busy",
+                slot
+            );
+            return;
+        };
+
+        let sound = PlayingSound::start(
+            &self.audio_manager,
+            self.se_tracks[placement.channel].id(),
             se,
-            AudioSettings {
-                track: self.se_tracks[slot].id(),
-                fade_in,
-                loop_start,
-                volume,
-                pan,
-            },
+            state,
+            fade_in,
         );
 
-        let handle = self.audio_manager.play(kira_data);
+        if let Some(mut old_sound) = placement.evicted.take() {
+            old_sound.handle.stop(Tween::MS_15).unwrap();
+        }
 
-        if let Some(mut old_handle) = self.se_slots[slot].take() {
-            old_handle.stop(Tween::MS_15).unwrap();
+        if let Some(sound) = sound {
+            self.se_slots.insert(&placement, info, sound);
         }
+    }
 
-        self.se_slots[slot] = Some(handle);
+    /// what plays on each slot, indexed by slot
+    pub fn capture_snapshot_v1(&self) -> Vec<Option<AudioSlotSnapshotV1>> {
+        (0..SE_SLOT_COUNT as i32)
+            .map(|slot| self.se_slots.get(slot).map(PlayingSound::snapshot))
+            .collect()
     }
 
-    pub fn set_volume(&mut self, slot: i32, volume: Volume, tween: Tween) {
-        let slot = slot as usize;
+    /// resume a SE of [`Self::capture_snapshot_v1`] on its slot, `se` is the file of its id
+    pub fn apply_snapshot_v1(
+        &mut self,
+        slot: usize,
+        se: Arc<AudioFile>,
+        snapshot: &AudioSlotSnapshotV1,
+    ) {
+        let info = SeChannelInfo {
+            se_id: snapshot.asset_id,
+            repeat: snapshot.loop_start.is_some(),
+        };
+        self.start(slot as i32, info, se, *snapshot, Tween::MS_15);
+    }
 
-        if let Some(handle) = self.se_slots[slot].as_mut() {
-            handle.set_volume(volume, tween).unwrap();
+    pub fn set_volume(&mut self, slot: i32, volume: Volume, tween: Tween) {
+        if let Some(sound) = self.se_slots.get_mut(slot) {
+            sound.handle.set_volume(volume, tween).unwrap();
+            sound.state.volume = volume.0;
         } else {
             warn!(
                 "Tried to set volume of se slot {}, but there was no se playing",
@@ -81,10 +142,9 @@ impl SePlayer {
     }
 
     pub fn set_panning(&mut self, slot: i32, pan: Pan, tween: Tween) {
-        let slot = slot as usize;
-
-        if let Some(handle) = self.se_slots[slot].as_mut() {
-            handle.set_panning(pan, tween).unwrap();
+        if let Some(sound) = self.se_slots.get_mut(slot) {
+            sound.handle.set_panning(pan, tween).unwrap();
+            sound.state.pan = pan.0;
         } else {
             warn!(
                 "Tried to set pan of se slot {}, but there was no se playing",
@@ -94,30 +154,50 @@ impl SePlayer {
     }
 
     pub fn stop(&mut self, slot: i32, fade_out: Tween) {
-        let slot = slot as usize;
-
-        if let Some(mut se) = self.se_slots[slot].take() {
-            se.stop(fade_out).unwrap();
+        if let Some(mut se) = self.se_slots.take(slot) {
+            se.handle.stop(fade_out).unwrap();
         } else {
             warn!("Tried to stop a SE that was not playing");
         }
     }
 
     pub fn stop_all(&mut self, fade_out: Tween) {
-        for slot in 0..SE_SLOT_COUNT {
-            if self.se_slots[slot].is_some() {
-                self.stop(slot as i32, fade_out);
+        for slot in 0..SE_SLOT_COUNT as i32 {
+            if let Some(mut se) = self.se_slots.take(slot) {
+                se.handle.stop(fade_out).unwrap();
             }
         }
     }
 
     pub fn get_wait_status(&self, slot: i32) -> AudioWaitStatus {
-        let slot = slot as usize;
-
-        if let Some(handle) = self.se_slots[slot].as_ref() {
-            handle.get_wait_status()
+        if let Some(sound) = self.se_slots.get(slot) {
+            sound.handle.get_wait_status()
         } else {
             AudioWaitStatus::STOPPED
         }
     }
 }
+
+impl OverlayVisitable for SePlayer {
+    fn visit_overlay(&self, collector: &mut OverlayCollector) {
+        collector.overlay(
+            "SE Channels",
+            |_ctx, top_left| {
+                let channels = self
+                    .se_slots
+                    .info()
+                    .map(|(slot, info)| {
+                        format!(
+                            "{}: {}{}",
+                            slot,
+                            info.se_id,
+                            if info.repeat { " (loop)" } else { "" }
+                        )
+                    })
+                    .collect::<Vec<_>>();
+                top_left.label(format!("SE: [{}]", channels.join(", ")));
+            },
+            false,
+        );
+    }
+}
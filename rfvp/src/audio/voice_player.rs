@@ -0,0 +1,92 @@
+use std::sync::Arc;
+
+use kira::track::{TrackBuilder, TrackHandle, TrackId, TrackRoutes};
+use rfvp_audio::{AudioEnvelope, AudioFile, AudioManager};
+use rfvp_core::{
+    format::save::audio_snapshot::AudioSlotSnapshotV1,
+    time::Tween,
+    vm::command::types::{Pan, Volume},
+};
+
+use super::playing_sound::PlayingSound;
+
+/// Plays the voice of the message, one at a time
+pub struct VoicePlayer {
+    audio_manager: Arc<AudioManager>,
+    voice_track: TrackHandle,
+    current_voice: Option<PlayingSound>,
+}
+
+impl VoicePlayer {
+    pub fn new(audio_manager: Arc<AudioManager>) -> Self {
+        let voice_track = Self::add_track(&audio_manager);
+
+        Self {
+            audio_manager,
+            voice_track,
+            current_voice: None,
+        }
+    }
+
+    fn add_track(audio_manager: &AudioManager) -> TrackHandle {
+        audio_manager
+            .kira_manager()
+            .lock()
+            .unwrap()
+            .add_sub_track(TrackBuilder::new().routes(TrackRoutes::parent(TrackId::Main)))
+            .expect("Failed to create voice track")
+    }
+
+    /// After the audio device was opened again, like [`SePlayer::reopen`]. The voice isn't
+    /// played again, the line it belongs to is already being read.
+    ///
+    /// [`SePlayer::reopen`]: super::se_player::SePlayer::reopen
+    pub fn reopen(&mut self) {
+        self.current_voice = None;
+        self.voice_track = Self::add_track(&self.audio_manager);
+    }
+
+    /// plays `voice`, cutting the one still playing
+    pub fn play(&mut self, voice: Arc<AudioFile>) {
+        let state = AudioSlotSnapshotV1 {
+            // the voices are named by the message, they aren't in the save state
+            asset_id: 0,
+            playing: true,
+            volume: Volume::default().0,
+            pan: Pan::default().0,
+            loop_start: None,
+            position_ms: 0,
+        };
+        let sound = PlayingSound::start(
+            &self.audio_manager,
+            self.voice_track.id(),
+            voice,
+            state,
+            Tween::IMMEDIATE,
+        );
+
+        self.stop(Tween::MS_15);
+        self.current_voice = sound;
+    }
+
+    /// whether a voice is still playing, the auto mode waits for it
+    pub fn is_playing(&self) -> bool {
+        self.current_voice
+            .as_ref()
+            .is_some_and(|voice| !voice.is_stopped())
+    }
+
+    /// the loudness of the voice, for the lip sync. `None` when no voice plays.
+    pub fn envelope(&self) -> Option<AudioEnvelope> {
+        self.current_voice
+            .as_ref()
+            .filter(|voice| !voice.is_stopped())
+            .map(|voice| voice.handle.envelope())
+    }
+
+    pub fn stop(&mut self, fade_out: Tween) {
+        if let Some(mut voice) = self.current_voice.take() {
+            voice.handle.stop(fade_out).unwrap();
+        }
+    }
+}
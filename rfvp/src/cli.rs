@@ -2,6 +2,9 @@ use std::path::PathBuf;
 
 use clap::Parser;
 use clap_num::maybe_hex;
+use rfvp_core::locale::Language;
+
+use crate::layer::NotificationAnchor;
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
@@ -13,4 +16,130 @@ pub struct Cli {
     /// Consult the README for more information.
     #[clap(short, long)]
     pub assets_dir: Option<PathBuf>,
+
+    /// MSAA sample count of the offscreen render targets (1, 2 or 4)
+    ///
+    /// Falls back to a lower count if the GPU doesn't support it.
+    #[clap(long, default_value_t = 1)]
+    pub msaa: u32,
+
+    /// Scale down the pictures too large for the GPU instead of tiling them
+    ///
+    /// Tiling keeps every pixel, scaling down draws them with a single texture.
+    #[clap(long)]
+    pub downscale_oversized_textures: bool,
+
+    /// Allow resizing the window
+    ///
+    /// The window keeps the aspect ratio of the game screen while being resized.
+    #[clap(long)]
+    pub resizable: bool,
+
+    /// Show the scene jump menu in the debug overlay
+    ///
+    /// The scenes are listed in a `scenes.yaml` next to the script. Always enabled in debug builds.
+    #[clap(long)]
+    pub scene_jump: bool,
+
+    /// Scale the message text, from 0.8 to 1.5
+    ///
+    /// Bigger text wraps into more lines, the message box grows to fit them and scrolls past a limit.
+    #[clap(long, default_value_t = 1.0)]
+    pub text_scale: f32,
+
+    /// Start in the auto mode, the lines advance on their own once there was time to read them
+    ///
+    /// A voiced line also waits for its voice. The A key turns the auto mode on and off.
+    #[clap(long)]
+    pub auto: bool,
+
+    /// Advance with a single click, even while the line is still being revealed
+    ///
+    /// By default the first click shows the rest of the line and the second one moves on.
+    #[clap(long)]
+    pub single_click_advance: bool,
+
+    /// The language of the engine's own text, like the notifications (en, ja or zh-CN)
+    ///
+    /// Defaults to the language of the game, guessed from the encoding of its script.
+    #[clap(long)]
+    pub language: Option<Language>,
+
+    /// The screen corner of the notifications, like the "now playing" toast
+    #[clap(long, value_enum, default_value_t = NotificationAnchor::TopRight)]
+    pub notification_corner: NotificationAnchor,
+
+    /// Show the title of the BGM in a toast when a track starts
+    #[clap(long)]
+    pub now_playing_toast: bool,
+
+    /// How long the notifications fade in, in milliseconds
+    #[clap(long, default_value_t = 250.0)]
+    pub notification_fade_in_ms: f32,
+
+    /// How long the notifications stay fully shown, in milliseconds
+    #[clap(long, default_value_t = 2500.0)]
+    pub notification_hold_ms: f32,
+
+    /// How long the notifications fade out, in milliseconds
+    #[clap(long, default_value_t = 500.0)]
+    pub notification_fade_out_ms: f32,
+
+    /// Autosave into this directory
+    ///
+    /// The script's autosave points are used, and a few rotating slots are kept. The voice options,
+    /// like the muted characters, are read from the `voice.toml` in it.
+    #[clap(long)]
+    pub autosave_dir: Option<PathBuf>,
+
+    /// Also autosave after this many advanced lines, 0 to only use the script's points
+    #[clap(long, default_value_t = 100)]
+    pub autosave_lines: u32,
+
+    /// Stop the playtime while the backlog is open
+    ///
+    /// The playtime never runs while the window is unfocused or the game is paused.
+    #[clap(long)]
+    pub playtime_skip_menus: bool,
+
+    /// Run the motions in fixed 16ms steps instead of once per frame
+    ///
+    /// The motions play the same at any frame rate, the rest of a frame's time is carried to the next one.
+    #[clap(long)]
+    pub fixed_motion_step: bool,
+
+    /// The log level of each module, like `warn,rfvp_core::vm=debug`
+    ///
+    /// Defaults to the `RUST_LOG` environment variable, or `info`. Can be changed in the log panel of the debug overlay.
+    #[clap(long)]
+    pub log_filter: Option<String>,
+
+    /// Also write the log to this file
+    ///
+    /// The file is rotated at 4 MB, three older files are kept next to it.
+    #[clap(long)]
+    pub log_file: Option<PathBuf>,
+
+    /// Enable the slow motion and frame stepping controls
+    ///
+    /// F5 pauses, F6 steps a single frame, F7 and F8 change the speed. Always enabled in debug builds.
+    #[clap(long)]
+    pub debug_time: bool,
+
+    /// Check the script for broken calls, jumps and stack imbalances on load
+    ///
+    /// The findings are located in the source with the `map.json` of the assembler, if present.
+    #[clap(long)]
+    pub validate_script: bool,
+
+    /// The memory the engine tries to stay under, in MB
+    ///
+    /// Past 70% of it the decoded pixels already on the GPU are dropped.
+    /// Defaults to half of the physical memory.
+    #[clap(long)]
+    pub memory_budget: Option<u64>,
+
+    /// Refuse to start if the script validation finds a problem, unreachable code is only logged
+    #[clap(long, requires = "validate_script")]
+    pub strict: bool,
 }
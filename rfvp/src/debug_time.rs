@@ -0,0 +1,158 @@
+use std::{cell::Cell, time::Duration};
+
+use egui::{DragValue, Window};
+use rfvp_audio::AudioManager;
+use rfvp_core::time::{GameClock, TimeControls, SPEED_PRESETS};
+
+use crate::{
+    input::{actions::DebugTimeAction, ActionState, RawInputState},
+    render::overlay::{OverlayCollector, OverlayVisitable},
+};
+
+/// A change asked for in the debug UI, applied on the next frame
+#[derive(Debug, Clone, Copy)]
+enum TimeRequest {
+    TogglePause,
+    Step(u32),
+    Preset(usize),
+}
+
+/// Slow motion and frame stepping, to debug animations.
+///
+/// Controlled with F5 (pause), F6 (step), F7 and F8 (slower, faster) or in the debug UI.
+/// Only the game time is affected, the input and the debug UI keep running in real time.
+/// The audio is paused instead of playing slowly.
+pub struct DebugTime {
+    controls: TimeControls,
+    action_state: ActionState<DebugTimeAction>,
+    audio_paused: bool,
+    request: Cell<Option<TimeRequest>>,
+    run_frames: Cell<u32>,
+}
+
+impl DebugTime {
+    pub fn new() -> Self {
+        Self {
+            controls: TimeControls::new(),
+            action_state: ActionState::new(),
+            audio_paused: false,
+            request: Cell::new(None),
+            run_frames: Cell::new(10),
+        }
+    }
+
+    /// Applies the hotkeys and the debug UI, then advances the clock for this frame.
+    ///
+    /// Returns the number of simulation steps to run, at most one.
+    pub fn advance(
+        &mut self,
+        input: &RawInputState,
+        clock: &mut GameClock,
+        real_delta: Duration,
+        audio_manager: &AudioManager,
+    ) -> u32 {
+        self.action_state.update(input);
+        if self
+            .action_state
+            .is_just_pressed(DebugTimeAction::TogglePause)
+        {
+            self.controls.toggle_pause();
+        }
+        if self.action_state.is_just_pressed(DebugTimeAction::Step) {
+            self.controls.step(1);
+        }
+        if self.action_state.is_just_pressed(DebugTimeAction::Slower) {
+            self.controls.slower();
+        }
+        if self.action_state.is_just_pressed(DebugTimeAction::Faster) {
+            self.controls.faster();
+        }
+        match self.request.take() {
+            Some(TimeRequest::TogglePause) => self.controls.toggle_pause(),
+            Some(TimeRequest::Step(count)) => self.controls.step(count),
+            Some(TimeRequest::Preset(preset)) => self.controls.set_preset(preset),
+            None => {}
+        }
+
+        let steps = self.controls.advance(clock, real_delta);
+
+        let audio_paused = !self.controls.is_realtime();
+        if audio_paused != self.audio_paused {
+            if audio_paused {
+                audio_manager.pause();
+            } else {
+                audio_manager.resume();
+            }
+            self.audio_paused = audio_paused;
+        }
+
+        steps
+    }
+
+    fn time_controls_ui(&self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            let label = if self.controls.is_paused() {
+                "Resume"
+            } else {
+                "Pause"
+            };
+            if ui.button(label).clicked() {
+                self.request.set(Some(TimeRequest::TogglePause));
+            }
+            if ui.button("Step").clicked() {
+                self.request.set(Some(TimeRequest::Step(1)));
+            }
+        });
+        ui.horizontal(|ui| {
+            for (preset, speed) in SPEED_PRESETS.iter().enumerate() {
+                if ui
+                    .selectable_label(self.controls.preset() == preset, format!("{}x", speed))
+                    .clicked()
+                {
+                    self.request.set(Some(TimeRequest::Preset(preset)));
+                }
+            }
+        });
+        ui.horizontal(|ui| {
+            let mut run_frames = self.run_frames.get();
+            ui.add(DragValue::new(&mut run_frames).clamp_range(1..=10000));
+            self.run_frames.set(run_frames);
+            if ui.button("Run frames").clicked() {
+                self.request.set(Some(TimeRequest::Step(run_frames)));
+            }
+        });
+    }
+}
+
+impl OverlayVisitable for DebugTime {
+    fn visit_overlay(&self, collector: &mut OverlayCollector) {
+        collector.overlay(
+            "Time Scale",
+            |_ctx, top_left| {
+                let state = if self.controls.pending_steps() > 0 {
+                    format!(" (stepping, {} left)", self.controls.pending_steps())
+                } else if self.controls.is_paused() {
+                    " (paused)".to_string()
+                } else {
+                    String::new()
+                };
+                top_left.label(format!(
+                    "Time: {}x, step {}{}",
+                    self.controls.speed(),
+                    self.controls.steps(),
+                    state
+                ));
+            },
+            true,
+        );
+        collector.overlay(
+            "Time Controls",
+            |ctx, _top_left| {
+                Window::new("Time Controls").show(ctx, |ui| {
+                    self.time_controls_ui(ui);
+                });
+            },
+            false,
+        );
+    }
+}
@@ -1,30 +1,30 @@
-use std::{collections::VecDeque, time::Duration};
+use rfvp_render::{DroppedFrames, FrameStats};
 
 use crate::{
     render::overlay::{OverlayCollector, OverlayVisitable},
     update::{Updatable, UpdateContext},
 };
 
-const WINDOW_SIZE: usize = 60;
-
 pub struct FpsCounter {
-    values: VecDeque<Duration>,
+    stats: FrameStats,
 }
 
 impl FpsCounter {
-    pub fn new() -> Self {
+    /// `dropped_frames` is the count of the [`rfvp_render::GpuCommonResources`], which the videos record into
+    pub fn new(dropped_frames: DroppedFrames) -> Self {
         Self {
-            values: VecDeque::new(),
+            stats: FrameStats::with_dropped_frames(dropped_frames),
         }
     }
+
+    pub fn stats(&self) -> &FrameStats {
+        &self.stats
+    }
 }
 
 impl Updatable for FpsCounter {
     fn update(&mut self, context: &UpdateContext) {
-        self.values.push_back(context.time_delta());
-        if self.values.len() > WINDOW_SIZE {
-            self.values.pop_front();
-        }
+        self.stats.record_present(context.time_delta());
     }
 }
 
@@ -33,13 +33,12 @@ impl OverlayVisitable for FpsCounter {
         collector.overlay(
             "FPS",
             |_ctx, top_left| {
-                let sum: Duration = self.values.iter().cloned().sum();
-                let avg = sum
-                    .checked_div(self.values.len() as u32)
-                    .unwrap_or(Duration::ZERO);
-                let fps = 1.0 / avg.as_secs_f32();
-
-                top_left.label(format!("FPS: {:.2}", fps));
+                top_left.label(format!(
+                    "FPS: {:.2} ({} us, {} dropped)",
+                    self.stats.render_fps(),
+                    self.stats.present_dt_us_avg(),
+                    self.stats.dropped_frames()
+                ));
             },
             true,
         )
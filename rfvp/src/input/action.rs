@@ -34,6 +34,11 @@ impl ActionData {
         self.amount = amount;
     }
 
+    /// An auto-repeat press, triggers the action again while it's held
+    fn repeat(&mut self) {
+        self.state = ButtonState::JustPressed;
+    }
+
     fn release(&mut self) {
         self.state.release();
         self.amount = 0.0;
@@ -73,9 +78,12 @@ where
         self.action_data.values_mut().for_each(|d| d.tick());
 
         let pressed = self.action_map.which_pressed(raw_input_state);
-        for ((_action, pressed), data) in pressed.into_iter().zip(self.action_data.values_mut()) {
+        for ((action, pressed), data) in pressed.into_iter().zip(self.action_data.values_mut()) {
             if let Some(amount) = pressed {
                 data.press(amount);
+                if self.action_map.is_repeated(action, raw_input_state) {
+                    data.repeat();
+                }
             } else {
                 data.release();
             }
@@ -148,4 +156,11 @@ where
                 .next()
         })
     }
+
+    /// Whether one of the inputs of the action got an auto-repeat press
+    pub fn is_repeated(&self, action: A, input_state: &RawInputState) -> bool {
+        self.action_map[action]
+            .iter()
+            .any(|input| input_state.is_repeated(input))
+    }
 }
@@ -14,6 +14,8 @@ pub enum AdvMessageAction {
     HoldFastForward,
     Backlog,
     Rollback,
+    /// turns the auto mode on or off
+    ToggleAuto,
 }
 
 impl Action for AdvMessageAction {
@@ -30,8 +32,9 @@ impl Action for AdvMessageAction {
                 AdvMessageAction::HoldFastForward => {
                     [KeyCode::ControlLeft.into()].into_iter().collect()
                 }
-                AdvMessageAction::Backlog => [].into_iter().collect(),
+                AdvMessageAction::Backlog => [MouseButton::WheelUp.into()].into_iter().collect(),
                 AdvMessageAction::Rollback => [].into_iter().collect(),
+                AdvMessageAction::ToggleAuto => [KeyCode::KeyA.into()].into_iter().collect(),
             }
         }
 
@@ -58,3 +61,27 @@ impl Action for OverlayManagerAction {
         ActionMap::new(enum_map! { v => map(v) })
     }
 }
+
+/// Debug time controls, see [`crate::debug_time::DebugTime`]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Enum)]
+pub enum DebugTimeAction {
+    TogglePause,
+    Step,
+    Slower,
+    Faster,
+}
+
+impl Action for DebugTimeAction {
+    fn default_action_map() -> ActionMap<Self> {
+        fn map(v: DebugTimeAction) -> InputSet {
+            match v {
+                DebugTimeAction::TogglePause => [KeyCode::F5.into()].into_iter().collect(),
+                DebugTimeAction::Step => [KeyCode::F6.into()].into_iter().collect(),
+                DebugTimeAction::Slower => [KeyCode::F7.into()].into_iter().collect(),
+                DebugTimeAction::Faster => [KeyCode::F8.into()].into_iter().collect(),
+            }
+        }
+
+        ActionMap::new(enum_map! { v => map(v) })
+    }
+}
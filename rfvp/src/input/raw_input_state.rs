@@ -20,12 +20,17 @@ pub struct RawInputState {
     pub keyboard: PetitSet<KeyCode, 16>,
     /// Mouse buttons state, simple state of each button
     pub mouse_buttons: EnumMap<MouseButton, bool>,
+    /// Cursor position in window pixels
     pub mouse_position: Vec2,
+    /// Cursor position in game screen coordinates, `None` when it's outside of the picture
+    pub cursor_screen_position: Option<Vec2>,
     pub mouse_scroll_amount: f32,
+    /// Keys which got an auto-repeat press since the last update, only for keys allowed to repeat
+    pub repeated: PetitSet<KeyCode, 16>,
+    /// Keys whose auto-repeat presses are let through, see [`Self::set_allow_repeat`]
+    repeat_allowed: PetitSet<KeyCode, 16>,
     #[allow(unused)] // TODO: implement gamepad input
     gamepad: (),
-    // TODO: mouse position?
-    // How do we even handle mouse position?
 }
 
 impl RawInputState {
@@ -34,11 +39,50 @@ impl RawInputState {
             keyboard: PetitSet::new(),
             mouse_buttons: enum_map! { _ => false },
             mouse_position: vec2(0.0, 0.0),
+            cursor_screen_position: None,
             mouse_scroll_amount: 0.0,
+            repeated: PetitSet::new(),
+            repeat_allowed: PetitSet::new(),
             gamepad: (),
         }
     }
 
+    /// Whether holding the key re-triggers the actions bound to it.
+    ///
+    /// Auto-repeat presses are dropped by default, so holding Enter advances once
+    /// instead of skipping through the scene.
+    pub fn set_allow_repeat(&mut self, key_code: KeyCode, allow: bool) {
+        if allow {
+            self.repeat_allowed.insert(key_code);
+        } else {
+            self.repeat_allowed.remove(&key_code);
+        }
+    }
+
+    /// Whether the input got an auto-repeat press since the last update
+    pub fn is_repeated(&self, input: &UserInput) -> bool {
+        match input {
+            UserInput::Keyboard(key_code) => self.repeated.contains(key_code),
+            UserInput::MouseButton(_) | UserInput::GamepadButton(_) => false,
+        }
+    }
+
+    fn on_key(&mut self, key_code: KeyCode, state: ElementState, repeat: bool) {
+        match state {
+            ElementState::Pressed if repeat => {
+                if self.repeat_allowed.contains(&key_code) {
+                    self.repeated.insert(key_code);
+                }
+            }
+            ElementState::Pressed => {
+                self.keyboard.insert(key_code);
+            }
+            ElementState::Released => {
+                self.keyboard.remove(&key_code);
+            }
+        }
+    }
+
     /// Returns the current state of the given button, and optionally the value (useful for axis)
     pub fn is_pressed(&self, input: &UserInput) -> Option<f32> {
         match input {
@@ -54,14 +98,7 @@ impl RawInputState {
         match event {
             WindowEvent::KeyboardInput { event, .. } => {
                 if let PhysicalKey::Code(keycode) = event.physical_key {
-                    match event.state {
-                        ElementState::Pressed => {
-                            self.keyboard.insert(keycode);
-                        }
-                        ElementState::Released => {
-                            self.keyboard.remove(&keycode);
-                        }
-                    }
+                    self.on_key(keycode, event.state, event.repeat);
                 }
             }
             WindowEvent::CursorMoved { position, .. } => {
@@ -99,6 +136,7 @@ impl RawInputState {
     pub fn update(&mut self) {
         // NOTE: this should be done __after__ everything has handled the events
         self.mouse_scroll_amount = 0.0;
+        self.repeated = PetitSet::new();
         self.mouse_buttons[MouseButton::WheelUp] = false;
         self.mouse_buttons[MouseButton::WheelDown] = false;
     }
@@ -155,3 +193,37 @@ fn convert_winit_mouse_button(winit: winit::event::MouseButton) -> Option<MouseB
         winit::event::MouseButton::Other(_) => None,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::{actions::AdvMessageAction, ActionState};
+
+    #[test]
+    fn test_repeat_filtered() {
+        let mut raw = RawInputState::new();
+        let mut actions = ActionState::<AdvMessageAction>::new();
+
+        raw.on_key(KeyCode::Enter, ElementState::Pressed, false);
+        actions.update(&raw);
+        raw.update();
+        assert!(actions.is_just_pressed(AdvMessageAction::Advance));
+
+        // holding Enter, the repeat is dropped
+        raw.on_key(KeyCode::Enter, ElementState::Pressed, true);
+        actions.update(&raw);
+        raw.update();
+        assert!(!actions.is_just_pressed(AdvMessageAction::Advance));
+        assert!(actions.is_pressed(AdvMessageAction::Advance));
+
+        // opted into repeat, every repeat advances again
+        raw.set_allow_repeat(KeyCode::Enter, true);
+        raw.on_key(KeyCode::Enter, ElementState::Pressed, true);
+        actions.update(&raw);
+        raw.update();
+        assert!(actions.is_just_pressed(AdvMessageAction::Advance));
+
+        actions.update(&raw);
+        assert!(!actions.is_just_pressed(AdvMessageAction::Advance));
+    }
+}
@@ -1,6 +1,8 @@
 use std::{fmt::Debug, sync::Arc};
 
 use glam::Mat4;
+use rfvp_audio::AudioEnvelope;
+use rfvp_core::lip_sync::{LipSync, LipSyncConfig};
 use rfvp_render::{GpuCommonResources, GpuImage, Renderable};
 
 use crate::{
@@ -9,11 +11,24 @@ use crate::{
     update::{Updatable, UpdateContext},
 };
 
+/// What the mouth of a bustup follows
+pub enum Speech {
+    /// the mouth stays closed
+    Silent,
+    /// the mouth opens with the loudness of the voice
+    Voice(AudioEnvelope),
+    /// the mouth cycles through its frames on a timer, for a line whose voice has no level
+    Timed,
+}
+
 pub struct BustupLayer {
     bustup: Arc<Bustup>,
     bustup_name: Option<String>,
     emotion: String,
 
+    lip_sync: LipSync,
+    speech: Speech,
+
     properties: LayerProperties,
 }
 
@@ -27,13 +42,25 @@ impl BustupLayer {
         // ensure the picture is loaded to gpu
         bustup.base_gpu_image(resources);
 
+        let mouths = bustup.mouth_count(emotion);
         Self {
             bustup,
             bustup_name,
             emotion: emotion.to_owned(),
+            lip_sync: LipSync::new(LipSyncConfig::default(), mouths),
+            speech: Speech::Silent,
             properties: LayerProperties::new(),
         }
     }
+
+    pub fn set_speech(&mut self, speech: Speech) {
+        self.speech = speech;
+    }
+
+    pub fn set_lip_sync_config(&mut self, config: LipSyncConfig) {
+        let mouths = self.bustup.mouth_count(&self.emotion);
+        self.lip_sync = LipSync::new(config, mouths);
+    }
 }
 
 impl Renderable for BustupLayer {
@@ -46,15 +73,19 @@ impl Renderable for BustupLayer {
     ) {
         let transform = self.properties.compute_transform(transform);
         let total_transform = projection * transform;
+        let scale = self.properties.scale();
 
         let mut draw_image = |image: &'enc GpuImage| {
             // TODO: there should be a generic function to render a layer (from texture?)
-            resources.draw_sprite(
-                render_pass,
-                image.vertex_source(),
-                image.bind_group(),
-                total_transform,
-            );
+            let sampling = image.sampling_at(scale);
+            for tile in image.tiles() {
+                resources.draw_sprite(
+                    render_pass,
+                    tile.vertex_source(),
+                    tile.bind_group_for(resources, sampling),
+                    total_transform,
+                );
+            }
         };
 
         let base_gpu_image = self.bustup.base_gpu_image(resources);
@@ -64,7 +95,15 @@ impl Renderable for BustupLayer {
             draw_image(emotion_gpu_image);
         }
 
-        if let Some(mouth_gpu_image) = self.bustup.mouth_gpu_image(resources, &self.emotion, 0.0) {
+        let mouths = self.bustup.mouth_count(&self.emotion);
+        let mouth_intensity = match mouths {
+            0 | 1 => 0.0,
+            _ => self.lip_sync.frame() as f32 / (mouths - 1) as f32,
+        };
+        if let Some(mouth_gpu_image) =
+            self.bustup
+                .mouth_gpu_image(resources, &self.emotion, mouth_intensity)
+        {
             draw_image(mouth_gpu_image);
         }
     }
@@ -77,6 +116,14 @@ impl Renderable for BustupLayer {
 impl Updatable for BustupLayer {
     fn update(&mut self, ctx: &UpdateContext) {
         self.properties.update(ctx);
+
+        let (level, speaking) = match &self.speech {
+            Speech::Silent => (None, false),
+            Speech::Voice(voice) => (Some(voice.level()), voice.is_playing()),
+            Speech::Timed => (None, true),
+        };
+        self.lip_sync
+            .update(ctx.time_delta_ticks(), level, speaking);
     }
 }
 
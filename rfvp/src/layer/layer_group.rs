@@ -1,8 +1,8 @@
 use bevy_utils::hashbrown::HashMap;
-use glam::Mat4;
+use glam::{Mat4, Vec2};
 use itertools::Itertools;
 use rfvp_core::vm::command::types::LayerId;
-use rfvp_render::{GpuCommonResources, RenderTarget, Renderable};
+use rfvp_render::{batch_by_blend_mode, GpuCommonResources, RenderTarget, Renderable};
 
 use crate::{
     adv::LayerSelection,
@@ -71,6 +71,23 @@ impl LayerGroup {
             .filter(move |&(&id, _)| selection.contains(id))
             .map(|(_, v)| v)
     }
+
+    /// The topmost picture layer with an opaque pixel under `point` of the virtual screen, or
+    /// shape layer with `point` on its shape
+    pub fn hit_test(&self, transform: Mat4, point: Vec2) -> Option<LayerId> {
+        let transform = self.properties.compute_transform(transform);
+        self.layers
+            .iter()
+            // TODO: use render order property, like render
+            .sorted_by_key(|&(id, _)| *id)
+            .rev()
+            .find(|(_, layer)| match layer {
+                UserLayer::PictureLayer(layer) => layer.hit_test(transform, point),
+                UserLayer::ShapeLayer(layer) => layer.hit_test(transform, point),
+                _ => false,
+            })
+            .map(|(&id, _)| id)
+    }
 }
 
 impl Updatable for LayerGroup {
@@ -103,14 +120,18 @@ impl Renderable for LayerGroup {
                     // TODO: use render order property
                     *id
                 })
-                .collect::<Vec<_>>();
+                .map(|(id, l)| (l.properties().blend_mode(), (id, l)));
 
             let transform = self.properties.compute_transform(transform);
             let projection = self.render_target.projection_matrix();
 
-            for (id, l) in ordered_layers {
-                render_pass.push_debug_group(&format!("Layer {:?}", id));
-                l.render(resources, &mut render_pass, transform, projection);
+            for batch in batch_by_blend_mode(ordered_layers) {
+                render_pass.push_debug_group(&format!("{:?} blending", batch.mode));
+                for (id, l) in batch.items {
+                    render_pass.push_debug_group(&format!("Layer {:?}", id));
+                    l.render(resources, &mut render_pass, transform, projection);
+                    render_pass.pop_debug_group();
+                }
                 render_pass.pop_debug_group();
             }
         }
@@ -1,9 +1,10 @@
 use std::sync::Arc;
 
-use glam::{vec2, Mat4, Vec2};
+use glam::{vec2, vec3, Mat4, Vec2};
 use rfvp_core::{
     layout::{
-        Action, ActionType, Block, BlockExitCondition, LayoutedChar, LayoutedMessage, LayoutingMode,
+        Action, ActionType, AdvanceAction, Block, BlockExitCondition, LayoutedChar,
+        LayoutedMessage, LayouterState, LayoutingMode,
     },
     time::Ticks,
     vm::command::types::MessageTextLayout,
@@ -17,6 +18,14 @@ use crate::{
     update::{Updatable, UpdateContext},
 };
 
+/// Glyph drawn for emphasis dots (傍点)
+const EMPHASIS_DOT_CODEPOINT: char = '●';
+
+/// The height of the text area of the message box, see [`MessageMetrics`]
+pub(super) const MESSAGE_HEIGHT: f32 = 360.0;
+/// The box grows with a taller text up to this, the text scrolls past it
+const MAX_MESSAGE_HEIGHT: f32 = 720.0;
+
 /// Calculated global metrics for a message. Used to adjust the sizes of individual parts of
 /// the message box, such that it fits the character name and the entire height of the message
 #[derive(Copy, Clone)]
@@ -36,6 +45,23 @@ pub struct Message {
     received_signals: u32,
     completed_blocks: u32,
     metrics: MessageMetrics,
+    /// the voice file of a `@v` reached by the reveal, until the layer plays it
+    voice: Option<String>,
+    /// whether the bustups move their mouths with the voice
+    lip_sync: bool,
+    /// reveal times of the message text chars, without the character name
+    char_times: Vec<Ticks>,
+    /// the character name is drawn from the first vertices, it never scrolls
+    name_vertices: u32,
+    lines: Vec<MessageLine>,
+}
+
+/// A line of the message text, relative to the first one
+struct MessageLine {
+    first_char: usize,
+    first_vertex: u32,
+    top: f32,
+    bottom: f32,
 }
 
 pub enum MessageStatus {
@@ -51,10 +77,57 @@ impl Message {
         font_atlas: Arc<FontAtlas>,
         base_position: Vec2,
         show_character_name: bool,
+        text_scale: f32,
+        message: &str,
+    ) -> Self {
+        Self::with_mode(
+            context,
+            font_atlas,
+            base_position,
+            show_character_name,
+            text_scale,
+            LayoutingMode::MessageText,
+            message,
+        )
+    }
+
+    /// A single block of text shown at once, outside of the message box
+    pub fn new_generic(
+        context: &UpdateContext,
+        font_atlas: Arc<FontAtlas>,
+        base_position: Vec2,
+        text: &str,
+    ) -> Self {
+        Self::with_mode(
+            context,
+            font_atlas,
+            base_position,
+            false,
+            1.0,
+            LayoutingMode::GenericText,
+            text,
+        )
+    }
+
+    fn with_mode(
+        context: &UpdateContext,
+        font_atlas: Arc<FontAtlas>,
+        base_position: Vec2,
+        show_character_name: bool,
+        text_scale: f32,
+        mode: LayoutingMode,
         message: &str,
     ) -> Self {
         // let mut font_atlas_guard = font_atlas.lock().unwrap();
 
+        let default_state = match mode {
+            LayoutingMode::GenericText => LayouterState {
+                instant: true,
+                ..Default::default()
+            },
+            _ => Default::default(),
+        };
+
         let layout_params = rfvp_core::layout::LayoutParams {
             font: font_atlas.get_font(),
             layout_width: 1500.0,
@@ -63,17 +136,21 @@ impl Message {
             furigana_font_height: 20.0,
             font_horizontal_base_scale: 0.9697,
             text_layout: MessageTextLayout::Left,
-            default_state: Default::default(),
+            default_state,
             has_character_name: true,
-            mode: LayoutingMode::MessageText,
+            mode,
+            text_scale,
         };
 
+        let layouted = rfvp_core::layout::layout_text(layout_params, message);
+        let height = layouted.height();
         let LayoutedMessage {
             mut character_name_chars,
             chars,
+            lines,
             mut actions,
             mut blocks,
-        } = rfvp_core::layout::layout_text(layout_params, message);
+        } = layouted;
 
         if !show_character_name {
             character_name_chars = None;
@@ -107,13 +184,16 @@ impl Message {
                 0.0
             },
 
-            // TODO: calculate message height
-            height: 360.0,
+            // the box grows with a taller text, up to a limit
+            height: height.clamp(MESSAGE_HEIGHT, MAX_MESSAGE_HEIGHT),
         };
 
         let character_name_x_offset =
             (metrics.character_name_width - character_name_actual_width) / 2.0;
 
+        let char_times = chars.iter().map(|c| c.time).collect();
+        let name_len = character_name_chars.as_ref().map_or(0, Vec::len);
+
         // perform layout post-processing on the character name; chain to form an iterator
         // over all chars
         let all_chars_iter = character_name_chars
@@ -136,7 +216,11 @@ impl Message {
 
         let mut used_codepoints = Vec::new();
         let mut vertices = Vec::new();
+        // the first vertex of each char, an emphasis dot adds to the six of its char
+        let mut char_vertices = Vec::new();
         for char in all_chars_iter {
+            char_vertices.push(vertices.len() as u32);
+
             // TODO: support for BOLD font
             let glyph_info = font_atlas
                 .get_font()
@@ -198,6 +282,48 @@ impl Message {
                 v!((0.0, 1.0), (0.0, 1.0)),
                 v!((1.0, 0.0), (1.0, 0.0)),
             ]);
+
+            // the emphasis dot is a filled circle glyph, revealed together with its char
+            if let Some((center, radius)) = char.emphasis_dot() {
+                let AtlasImage {
+                    position: tex_position,
+                    size: _,
+                } = font_atlas.get_glyph(context.gpu_resources, EMPHASIS_DOT_CODEPOINT);
+                used_codepoints.push(EMPHASIS_DOT_CODEPOINT);
+
+                let dot_info = font_atlas
+                    .get_font()
+                    .get_glyph_for_character(EMPHASIS_DOT_CODEPOINT)
+                    .get_info();
+                let tex_size = dot_info.actual_size();
+                let tex_size = vec2(tex_size.0 as f32, tex_size.1 as f32) / atlas_size;
+                let tex_position = tex_position / atlas_size;
+
+                let position = base_position + center - Vec2::splat(radius);
+                let size = Vec2::splat(radius * 2.0);
+
+                // v! would pick up the char's quad, macro locals resolve where it was defined
+                macro_rules! dot_v {
+                    (($x:expr, $y:expr), ($tex_x:expr, $tex_y:expr)) => {
+                        TextVertex {
+                            position: position + vec2($x, $y) * size,
+                            tex_position: tex_position + vec2($tex_x, $tex_y) * tex_size,
+                            color,
+                            time,
+                            fade,
+                        }
+                    };
+                }
+
+                vertices.extend([
+                    dot_v!((0.0, 0.0), (0.0, 0.0)),
+                    dot_v!((1.0, 0.0), (1.0, 0.0)),
+                    dot_v!((0.0, 1.0), (0.0, 1.0)),
+                    dot_v!((1.0, 1.0), (1.0, 1.0)),
+                    dot_v!((0.0, 1.0), (0.0, 1.0)),
+                    dot_v!((1.0, 0.0), (1.0, 0.0)),
+                ]);
+            }
         }
 
         let vertex_buffer = VertexBuffer::new(
@@ -206,6 +332,23 @@ impl Message {
             Some("Message VertexBuffer"),
         );
 
+        let first_vertex = |char: usize| {
+            char_vertices
+                .get(char)
+                .copied()
+                .unwrap_or(vertices.len() as u32)
+        };
+        let text_top = lines.first().map_or(0.0, |line| line.top);
+        let lines = lines
+            .iter()
+            .map(|line| MessageLine {
+                first_char: line.start,
+                first_vertex: first_vertex(name_len + line.start),
+                top: line.top - text_top,
+                bottom: line.bottom - text_top,
+            })
+            .collect();
+
         Self {
             time: Ticks::ZERO,
             font_atlas,
@@ -217,6 +360,11 @@ impl Message {
             received_signals: 0,
             completed_blocks: 0,
             metrics,
+            voice: None,
+            lip_sync: true,
+            char_times,
+            name_vertices: first_vertex(name_len),
+            lines,
         }
     }
 
@@ -243,6 +391,18 @@ impl Message {
         }
     }
 
+    /// How many chars of the current block are revealed, the auto mode reading time is based on it
+    pub fn revealed_block_chars(&self) -> usize {
+        let Some(block) = self.current_block() else {
+            return 0;
+        };
+        let end_time = self.time.min(block.end_time);
+        self.char_times
+            .iter()
+            .filter(|&&time| time >= block.start_time && time < end_time)
+            .count()
+    }
+
     fn current_block(&self) -> Option<&Block> {
         self.blocks.last()
     }
@@ -262,17 +422,36 @@ impl Message {
         }
     }
 
-    pub fn advance(&mut self) {
+    /// Whether the current block is shown whole, a message without blocks left is
+    pub fn is_fully_revealed(&self) -> bool {
+        self.current_block()
+            .map_or(true, |block| block.completed(self.time))
+    }
+
+    /// skip to the end of the current block
+    pub fn complete_current_reveal(&mut self) {
         if let Some(block) = self.current_block() {
-            if block.completed(self.time)
-                && matches!(block.exit_condition, BlockExitCondition::ClickWait)
-            {
+            self.time = self.time.max(block.end_time);
+        }
+    }
+
+    /// Handles an "Advance" input, see [`Block::advance_action`].
+    /// Returns whether it moved past a click-wait.
+    pub fn advance(&mut self, click_completes_reveal: bool) -> bool {
+        let Some(block) = self.current_block() else {
+            return false;
+        };
+        match block.advance_action(self.time, click_completes_reveal) {
+            AdvanceAction::CompleteReveal => {
+                self.complete_current_reveal();
+                false
+            }
+            AdvanceAction::NextBlock => {
+                self.complete_current_reveal();
                 self.next_block();
-            } else {
-                // skip to the end of the current block
-                // NOTE: we may want to have a separate control for that
-                self.time = block.end_time;
+                true
             }
+            AdvanceAction::None => false,
         }
     }
 
@@ -303,16 +482,25 @@ impl Message {
             }
             let action = self.actions.pop().unwrap();
             match action.action_type {
-                ActionType::SetLipSync(state) => warn!("Ignoring SetLipSync action: {:?}", state),
+                ActionType::SetLipSync(state) => self.lip_sync = state,
                 ActionType::VoiceVolume(volume) => {
                     warn!("Ignoring voice volume change: {}", volume)
                 }
-                ActionType::Voice(filename) => warn!("Ignoring voice action: {}", filename),
+                ActionType::Voice(filename) => self.voice = Some(filename),
                 ActionType::SignalSection => self.sent_signals += 1,
             }
         }
     }
 
+    /// the voice to play, once
+    pub fn take_voice(&mut self) -> Option<String> {
+        self.voice.take()
+    }
+
+    pub fn lip_sync(&self) -> bool {
+        self.lip_sync
+    }
+
     pub fn completed_blocks(&self) -> u32 {
         self.completed_blocks
     }
@@ -332,13 +520,40 @@ impl Message {
     pub fn metrics(&self) -> MessageMetrics {
         self.metrics
     }
+
+    /// The first vertex of the text to draw and how far up it is scrolled, a text taller than
+    /// [`MAX_MESSAGE_HEIGHT`] scrolls to keep the line being revealed in the box
+    fn scroll(&self) -> (u32, f32) {
+        let revealed = self
+            .char_times
+            .iter()
+            .filter(|&&time| time <= self.time)
+            .count();
+        let Some(current) = self
+            .lines
+            .iter()
+            .rev()
+            .find(|line| line.first_char < revealed)
+        else {
+            return (self.name_vertices, 0.0);
+        };
+
+        let scroll = (current.bottom - MAX_MESSAGE_HEIGHT).max(0.0);
+        // the lines scrolled past the top of the box are not drawn
+        let first_vertex = self
+            .lines
+            .iter()
+            .find(|line| line.top >= scroll)
+            .map_or(self.name_vertices, |line| line.first_vertex);
+        (first_vertex, scroll)
+    }
 }
 
 impl Updatable for Message {
     fn update(&mut self, context: &UpdateContext) {
         if let Some(block) = self.current_block() {
             if !block.completed(self.time) {
-                self.time += context.time_delta_ticks();
+                self.time += context.clock_delta_ticks();
             } else {
                 match block.exit_condition {
                     BlockExitCondition::None => self.next_block(),
@@ -351,40 +566,85 @@ impl Updatable for Message {
     }
 }
 
-impl Renderable for Message {
-    fn render<'enc>(
+impl Message {
+    /// Draws the whole text at `opacity`, for an instant text made by [`Self::new_generic`].
+    ///
+    /// The text shader fades each char in over one tick from its reveal time, all chars of
+    /// an instant text are revealed at zero, so drawing at a time between 0 and 1 fades it.
+    pub fn render_faded<'enc>(
         &'enc self,
         resources: &'enc GpuCommonResources,
         render_pass: &mut wgpu::RenderPass<'enc>,
         transform: Mat4,
         projection: Mat4,
+        opacity: f32,
     ) {
-        const OUTLINE_DISTANCE: f32 = 3.5;
+        self.render_at(
+            resources,
+            render_pass,
+            projection * transform,
+            Ticks::from_f32(opacity.clamp(0.0, 1.0)),
+        );
+    }
 
-        let total_transform = projection * transform;
+    fn render_at<'enc>(
+        &'enc self,
+        resources: &'enc GpuCommonResources,
+        render_pass: &mut wgpu::RenderPass<'enc>,
+        total_transform: Mat4,
+        time: Ticks,
+    ) {
+        const OUTLINE_DISTANCE: f32 = 3.5;
 
         let atlas_size = self.font_atlas.texture_size();
         let scaled_distance = OUTLINE_DISTANCE / vec2(atlas_size.0 as f32, atlas_size.1 as f32);
 
-        render_pass.push_debug_group("Message");
-        resources.draw_text_outline(
-            render_pass,
-            self.vertex_buffer.vertex_source(),
-            self.font_atlas.texture_bind_group(),
-            total_transform,
-            self.time,
-            scaled_distance,
-        );
+        let (first_vertex, scroll) = self.scroll();
+        let text_transform = total_transform * Mat4::from_translation(vec3(0.0, -scroll, 0.0));
+        let parts = [
+            (0..self.name_vertices, total_transform),
+            (
+                first_vertex..self.vertex_buffer.num_vertices(),
+                text_transform,
+            ),
+        ];
 
-        resources.draw_text(
-            render_pass,
-            self.vertex_buffer.vertex_source(),
-            self.font_atlas.texture_bind_group(),
-            total_transform,
-            self.time,
-        );
+        render_pass.push_debug_group("Message");
+        for (vertices, transform) in parts {
+            if vertices.is_empty() {
+                continue;
+            }
+            resources.draw_text_outline(
+                render_pass,
+                self.vertex_buffer.vertex_source_slice(vertices.clone()),
+                self.font_atlas.texture_bind_group(),
+                transform,
+                time,
+                scaled_distance,
+            );
+
+            resources.draw_text(
+                render_pass,
+                self.vertex_buffer.vertex_source_slice(vertices),
+                self.font_atlas.texture_bind_group(),
+                transform,
+                time,
+            );
+        }
         render_pass.pop_debug_group();
     }
+}
+
+impl Renderable for Message {
+    fn render<'enc>(
+        &'enc self,
+        resources: &'enc GpuCommonResources,
+        render_pass: &mut wgpu::RenderPass<'enc>,
+        transform: Mat4,
+        projection: Mat4,
+    ) {
+        self.render_at(resources, render_pass, projection * transform, self.time);
+    }
 
     fn resize(&mut self, _resources: &GpuCommonResources) {}
 }
@@ -264,5 +264,6 @@ impl Messagebox {
 
     pub fn set_metrics(&mut self, metrics: MessageMetrics) {
         self.metrics = metrics;
+        self.dynamic_height = metrics.height;
     }
 }
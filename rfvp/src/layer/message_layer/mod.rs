@@ -1,13 +1,14 @@
-mod font_atlas;
-mod message;
+pub(super) mod font_atlas;
+pub(super) mod message;
 mod messagebox;
 
 use std::sync::Arc;
 
-use glam::{vec2, Mat4};
-use message::{Message, MessageStatus};
+use glam::{vec2, vec3, Mat4};
+use message::{Message, MessageStatus, MESSAGE_HEIGHT};
 pub use messagebox::MessageboxTextures;
 use rfvp_core::{
+    layout::{clamp_text_scale, MAX_TEXT_SCALE, MIN_TEXT_SCALE},
     time::Ticks,
     vm::command::types::{MessageboxStyle, MessageboxType},
 };
@@ -29,6 +30,12 @@ pub struct MessageLayer {
     font_atlas: Arc<FontAtlas>,
     message: Option<Message>,
     messagebox: Messagebox,
+    /// the reveal is frozen while a menu is over the message, see [`Self::suspend`]
+    suspended: bool,
+    /// the player's text size setting, applied from the next message on
+    text_scale: f32,
+    /// a click during the reveal only completes it, see [`Self::set_click_completes_reveal`]
+    click_completes_reveal: bool,
 }
 
 impl MessageLayer {
@@ -43,6 +50,9 @@ impl MessageLayer {
             font_atlas: Arc::new(FontAtlas::new(resources, fonts.medium_font)),
             message: None,
             messagebox: Messagebox::new(textures, resources),
+            suspended: false,
+            text_scale: 1.0,
+            click_completes_reveal: true,
         }
     }
 
@@ -72,6 +82,7 @@ impl MessageLayer {
             self.font_atlas.clone(),
             base_position,
             show_character_name,
+            self.text_scale,
             text,
         );
 
@@ -79,18 +90,79 @@ impl MessageLayer {
         self.message = Some(message);
     }
 
+    /// The message font, shared with the other text shown by the engine
+    pub fn font_atlas(&self) -> Arc<FontAtlas> {
+        self.font_atlas.clone()
+    }
+
+    /// Sets the text size multiplier, clamped to [`MIN_TEXT_SCALE`]..=[`MAX_TEXT_SCALE`].
+    ///
+    /// The text wraps into more lines, the message box grows to fit them up to a limit
+    /// and the text scrolls past it.
+    pub fn set_text_scale(&mut self, scale: f32) {
+        self.text_scale = clamp_text_scale(scale);
+    }
+
+    pub fn text_scale(&self) -> f32 {
+        self.text_scale
+    }
+
+    /// With `true`, the default, the first click on a line being revealed shows the rest of
+    /// it and the second one moves on. With `false`, a single click moves on.
+    pub fn set_click_completes_reveal(&mut self, click_completes_reveal: bool) {
+        self.click_completes_reveal = click_completes_reveal;
+    }
+
+    /// The voice of a `@v` the reveal reached since the last call, to be played
+    pub fn take_voice(&mut self) -> Option<String> {
+        self.message.as_mut().and_then(Message::take_voice)
+    }
+
+    /// whether the bustups follow the voice, the message can turn it off for a part of it
+    pub fn lip_sync(&self) -> bool {
+        self.message.as_ref().is_some_and(Message::lip_sync)
+    }
+
     pub fn close(&mut self) {
         self.message = None;
         self.messagebox.set_visible(false);
     }
 
+    /// a suspended message is never finished, so the script doesn't move on behind a menu
     pub fn is_finished(&self) -> bool {
         self.message
             .as_ref()
-            .map(|m| m.is_complete())
+            .map(|m| !self.suspended && m.is_complete())
             .unwrap_or(true)
     }
 
+    /// Freezes the reveal of the message while a choice or the system menu is shown.
+    ///
+    /// The message keeps its progress: it doesn't tick and ignores advance and
+    /// fast-forward until [`Self::resume`], then continues from the same character.
+    /// A message set while suspended starts frozen too.
+    pub fn suspend(&mut self) {
+        self.suspended = true;
+    }
+
+    pub fn resume(&mut self) {
+        self.suspended = false;
+    }
+
+    pub fn is_suspended(&self) -> bool {
+        self.suspended
+    }
+
+    /// The revealed char count of the message when it waits for a click, for the auto mode
+    pub fn click_wait_chars(&self) -> Option<usize> {
+        if self.suspended {
+            return None;
+        }
+        let message = self.message.as_ref()?;
+        matches!(message.status(), MessageStatus::ClickWaiting)
+            .then(|| message.revealed_block_chars())
+    }
+
     pub fn is_section_finished(&self, section_num: u32) -> bool {
         self.message
             .as_ref()
@@ -104,13 +176,37 @@ impl MessageLayer {
         }
     }
 
-    pub fn advance(&mut self) {
+    /// Returns whether the message moved past a click-wait, a click which only completed
+    /// the reveal doesn't count as an advanced line
+    pub fn advance(&mut self) -> bool {
+        if self.suspended {
+            return false;
+        }
+        self.message
+            .as_mut()
+            .is_some_and(|m| m.advance(self.click_completes_reveal))
+    }
+
+    /// Shows the rest of the line being revealed, without moving on
+    pub fn complete_current_reveal(&mut self) {
+        if self.suspended {
+            return;
+        }
         if let Some(m) = self.message.as_mut() {
-            m.advance()
+            m.complete_current_reveal()
         }
     }
 
+    /// Whether the line is shown whole, for the host to decide what a click does.
+    /// `true` without a message.
+    pub fn is_fully_revealed(&self) -> bool {
+        self.message.as_ref().map_or(true, |m| m.is_fully_revealed())
+    }
+
     pub fn fast_forward(&mut self) {
+        if self.suspended {
+            return;
+        }
         if let Some(m) = self.message.as_mut() {
             m.fast_forward()
         }
@@ -129,6 +225,12 @@ impl Renderable for MessageLayer {
         self.messagebox
             .render(resources, render_pass, transform, projection);
         if let Some(message) = &self.message {
+            // the box grows upwards, so does the text in it
+            let growth = match self.style.messagebox_type {
+                MessageboxType::Novel => 0.0,
+                _ => message.metrics().height - MESSAGE_HEIGHT,
+            };
+            let transform = transform * Mat4::from_translation(vec3(0.0, -growth, 0.0));
             message.render(resources, render_pass, transform, projection);
         }
     }
@@ -141,6 +243,9 @@ impl Renderable for MessageLayer {
 impl Updatable for MessageLayer {
     fn update(&mut self, ctx: &UpdateContext) {
         self.messagebox.update(ctx);
+        if self.suspended {
+            return;
+        }
         if let Some(message) = &mut self.message {
             message.update(ctx);
         }
@@ -183,8 +288,9 @@ impl OverlayVisitable for MessageLayer {
                             .unwrap_or(Ticks::ZERO);
 
                         top_left.label(format!(
-                            "MessageLayer: {} B={} So={} Si={} T={:06.1} AF={:04.1}%",
+                            "MessageLayer: {}{} B={} So={} Si={} T={:06.1} AF={:04.1}%",
                             status,
+                            if self.suspended { " (suspended)" } else { "" },
                             blocks,
                             signalled_out,
                             signalled_in,
@@ -2,40 +2,44 @@ mod bustup_layer;
 mod layer_group;
 mod message_layer;
 mod movie_layer;
+mod notification_layer;
 mod null_layer;
 mod page_layer;
 mod picture_layer;
 mod root_layer_group;
 mod screen_layer;
+mod shape_layer;
 mod tile_layer;
 mod wobbler;
 
 use std::f32::consts::PI;
 
-pub use bustup_layer::BustupLayer;
+pub use bustup_layer::{BustupLayer, Speech};
 use derivative::Derivative;
 use derive_more::From;
 use enum_dispatch::enum_dispatch;
 use enum_map::{enum_map, EnumMap};
-use glam::{vec3, Mat4};
+use glam::{vec2, vec3, Mat4, Vec2};
 pub use layer_group::LayerGroup;
 pub use message_layer::{MessageLayer, MessageboxTextures};
 pub use movie_layer::MovieLayer;
+pub use notification_layer::{NotificationAnchor, NotificationLayer, NotificationTimings};
 pub use null_layer::NullLayer;
 pub use page_layer::PageLayer;
 pub use picture_layer::PictureLayer;
 pub use root_layer_group::RootLayerGroup;
 pub use screen_layer::ScreenLayer;
+pub use shape_layer::ShapeLayer;
 use rfvp_audio::AudioManager;
 use rfvp_core::{
     format::scenario::{
         info::{BustupInfoItem, MovieInfoItem, PictureInfoItem},
         Scenario,
     },
-    time::{Ticks, Tweener},
+    time::{MotionWait, Subsystem, Ticks, Tweener},
     vm::command::types::{LayerProperty, LayerType},
 };
-use rfvp_render::{GpuCommonResources, Renderable};
+use rfvp_render::{BlendMode, GpuCommonResources, Renderable};
 pub use tile_layer::TileLayer;
 use tracing::{debug, warn};
 
@@ -59,6 +63,7 @@ pub struct LayerProperties {
     wobbler_rotation: Wobbler,
     wobbler_scale_x: Wobbler,
     wobbler_scale_y: Wobbler,
+    blend_mode: BlendMode,
 }
 
 impl LayerProperties {
@@ -71,6 +76,7 @@ impl LayerProperties {
             wobbler_rotation: Wobbler::new(),
             wobbler_scale_x: Wobbler::new(),
             wobbler_scale_y: Wobbler::new(),
+            blend_mode: BlendMode::Alpha,
         }
     }
 
@@ -86,10 +92,49 @@ impl LayerProperties {
         &mut self.properties[property]
     }
 
+    /// Resolves when the motion of the property ends, for the host side to await instead of polling
+    #[allow(unused)]
+    pub fn wait_motion(&mut self, property: LayerProperty) -> MotionWait {
+        self.properties[property].wait()
+    }
+
     pub fn init(&mut self) {
         for (prop, val) in initial_values() {
             self.properties[prop].fast_forward_to(val as f32);
         }
+        self.blend_mode = BlendMode::Alpha;
+    }
+
+    pub fn blend_mode(&self) -> BlendMode {
+        self.blend_mode
+    }
+
+    /// How the layer is drawn over the ones under it, not a tweened property
+    pub fn set_blend_mode(&mut self, blend_mode: BlendMode) {
+        self.blend_mode = blend_mode;
+    }
+
+    /// The scale the layer is drawn at, 1.0 being the size of its picture
+    pub fn scale(&self) -> Vec2 {
+        let get = |property| self.get_property_value(property);
+        let wobble =
+            |wobbler: &Wobbler, amplitude, bias| wobbler.value() * get(amplitude) + get(bias);
+        vec2(
+            get(LayerProperty::ScaleX) / 1000.0 * get(LayerProperty::ScaleX2) / 1000.0
+                * wobble(
+                    &self.wobbler_scale_x,
+                    LayerProperty::WobbleScaleXAmplitude,
+                    LayerProperty::WobbleScaleXBias,
+                )
+                / 1000.0,
+            get(LayerProperty::ScaleY) / 1000.0 * get(LayerProperty::ScaleY2) / 1000.0
+                * wobble(
+                    &self.wobbler_scale_y,
+                    LayerProperty::WobbleScaleYAmplitude,
+                    LayerProperty::WobbleScaleYBias,
+                )
+                / 1000.0,
+        )
     }
 
     pub fn compute_transform(&self, base_transform: Mat4) -> Mat4 {
@@ -116,15 +161,7 @@ impl LayerProperties {
         let transforms = [
             // apply scale
             Mat4::from_translation(-get!(ScaleOriginX, ScaleOriginY, Zero)),
-            Mat4::from_scale(vec3(
-                get!(ScaleX) / 1000.0 * get!(ScaleX2) / 1000.0
-                    * wobble!(wobbler_scale_x, WobbleScaleXAmplitude, WobbleScaleXBias)
-                    / 1000.0,
-                get!(ScaleY) / 1000.0 * get!(ScaleY2) / 1000.0
-                    * wobble!(wobbler_scale_y, WobbleScaleYAmplitude, WobbleScaleYBias)
-                    / 1000.0,
-                1.0,
-            )),
+            Mat4::from_scale(self.scale().extend(1.0)),
             Mat4::from_translation(get!(ScaleOriginX, ScaleOriginY, Zero)),
             // apply rotation
             Mat4::from_translation(-get!(RotationOriginX, RotationOriginY, Zero)),
@@ -159,8 +196,16 @@ impl LayerProperties {
 
 impl Updatable for LayerProperties {
     fn update(&mut self, context: &UpdateContext) {
-        let dt = context.time_delta_ticks();
+        // a single step of the frame time, unless the motions run at a fixed timestep
+        let (steps, dt) = context.subsystem_sub_steps(Subsystem::Motion);
+        for _ in 0..steps {
+            self.step(dt);
+        }
+    }
+}
 
+impl LayerProperties {
+    fn step(&mut self, dt: Ticks) {
         for property in self.properties.values_mut() {
             property.update(dt);
         }
@@ -200,17 +245,30 @@ pub struct LayerPropertiesSnapshot {
     // The game can actually only set integer values
     // hence the the use of i32 instead of f32
     properties: EnumMap<LayerProperty, i32>,
+    blend_mode: BlendMode,
 }
 
 impl LayerPropertiesSnapshot {
     pub fn new() -> Self {
         Self {
             properties: initial_values(),
+            blend_mode: BlendMode::Alpha,
         }
     }
 
     pub fn init(&mut self) {
         self.properties = initial_values();
+        self.blend_mode = BlendMode::Alpha;
+    }
+
+    #[allow(unused)]
+    pub fn blend_mode(&self) -> BlendMode {
+        self.blend_mode
+    }
+
+    #[allow(unused)]
+    pub fn set_blend_mode(&mut self, blend_mode: BlendMode) {
+        self.blend_mode = blend_mode;
     }
 
     #[allow(unused)]
@@ -244,6 +302,8 @@ pub enum UserLayer {
     TileLayer,
     #[derivative(Debug = "transparent")]
     MovieLayer,
+    #[derivative(Debug = "transparent")]
+    ShapeLayer,
 }
 
 impl UserLayer {
@@ -338,6 +398,7 @@ impl Renderable for UserLayer {
             UserLayer::BustupLayer(l) => l.render(resources, render_pass, transform, projection),
             UserLayer::TileLayer(l) => l.render(resources, render_pass, transform, projection),
             UserLayer::MovieLayer(l) => l.render(resources, render_pass, transform, projection),
+            UserLayer::ShapeLayer(l) => l.render(resources, render_pass, transform, projection),
         }
     }
 
@@ -348,6 +409,7 @@ impl Renderable for UserLayer {
             UserLayer::BustupLayer(l) => l.resize(resources),
             UserLayer::TileLayer(l) => l.resize(resources),
             UserLayer::MovieLayer(l) => l.resize(resources),
+            UserLayer::ShapeLayer(l) => l.resize(resources),
         }
     }
 }
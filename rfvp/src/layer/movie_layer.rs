@@ -2,6 +2,7 @@ use std::{fmt::Debug, sync::Arc};
 
 use glam::Mat4;
 use rfvp_audio::AudioManager;
+use rfvp_core::time::Subsystem;
 use rfvp_render::{GpuCommonResources, RenderTarget, Renderable};
 use rfvp_video::VideoPlayer;
 
@@ -85,7 +86,7 @@ impl Renderable for MovieLayer {
 impl Updatable for MovieLayer {
     fn update(&mut self, ctx: &UpdateContext) {
         self.video_player
-            .update(ctx.time_delta_ticks(), &ctx.gpu_resources.queue);
+            .update(ctx.subsystem_delta_ticks(Subsystem::Video), &ctx.gpu_resources.queue);
     }
 }
 
@@ -0,0 +1,168 @@
+mod queue;
+
+use std::sync::Arc;
+
+use glam::{vec2, vec4, Mat4, Vec2};
+use rfvp_core::time::Subsystem;
+use rfvp_render::{GpuCommonResources, PosVertexBuffer, Renderable, VIRTUAL_HEIGHT, VIRTUAL_WIDTH};
+
+use queue::NotificationQueue;
+pub use queue::NotificationTimings;
+
+use crate::{
+    layer::message_layer::{font_atlas::FontAtlas, message::Message},
+    update::{Updatable, UpdateContext},
+};
+
+/// Size of the toast panel, in virtual pixels
+const TOAST_SIZE: Vec2 = vec2(640.0, 72.0);
+/// Distance between the toast and the screen edges
+const TOAST_MARGIN: f32 = 32.0;
+/// Distance between the panel edge and the text
+const TOAST_PADDING: Vec2 = vec2(20.0, 12.0);
+/// Opacity of the panel behind the text when fully shown
+const PANEL_ALPHA: f32 = 0.6;
+
+/// The corner of the screen the toasts are shown in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum NotificationAnchor {
+    TopLeft,
+    #[default]
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+impl NotificationAnchor {
+    /// The top left corner of the toast, the origin is the screen center
+    fn toast_position(self) -> Vec2 {
+        let half_screen = vec2(VIRTUAL_WIDTH, VIRTUAL_HEIGHT) / 2.0;
+        let left = -half_screen.x + TOAST_MARGIN;
+        let right = half_screen.x - TOAST_MARGIN - TOAST_SIZE.x;
+        let top = -half_screen.y + TOAST_MARGIN;
+        let bottom = half_screen.y - TOAST_MARGIN - TOAST_SIZE.y;
+
+        match self {
+            NotificationAnchor::TopLeft => vec2(left, top),
+            NotificationAnchor::TopRight => vec2(right, top),
+            NotificationAnchor::BottomLeft => vec2(left, bottom),
+            NotificationAnchor::BottomRight => vec2(right, bottom),
+        }
+    }
+}
+
+struct Toast {
+    panel: PosVertexBuffer,
+    text: Message,
+}
+
+/// Transient notices over the game: the "now playing" BGM title, and other engine notices.
+///
+/// Notifications are shown one at a time in a corner, with a fade in and out, and wait
+/// while suppressed, e.g. during a movie.
+pub struct NotificationLayer {
+    font_atlas: Arc<FontAtlas>,
+    queue: NotificationQueue,
+    anchor: NotificationAnchor,
+    now_playing: bool,
+    suppressed: bool,
+    toast: Option<Toast>,
+}
+
+impl NotificationLayer {
+    pub fn new(font_atlas: Arc<FontAtlas>) -> Self {
+        Self {
+            font_atlas,
+            queue: NotificationQueue::new(NotificationTimings::default()),
+            anchor: NotificationAnchor::default(),
+            now_playing: false,
+            suppressed: false,
+            toast: None,
+        }
+    }
+
+    pub fn notify(&mut self, text: impl Into<String>) {
+        self.queue.push(text.into());
+    }
+
+    /// Moves the toasts to another corner, from the next one on
+    pub fn set_anchor(&mut self, anchor: NotificationAnchor) {
+        self.anchor = anchor;
+    }
+
+    pub fn set_timings(&mut self, timings: NotificationTimings) {
+        self.queue.set_timings(timings);
+    }
+
+    /// Whether BGMPLAY shows the title of the track, off by default
+    pub fn set_now_playing(&mut self, now_playing: bool) {
+        self.now_playing = now_playing;
+    }
+
+    pub fn now_playing(&self) -> bool {
+        self.now_playing
+    }
+
+    /// Holds the notifications back, they are shown once unsuppressed
+    pub fn set_suppressed(&mut self, suppressed: bool) {
+        self.suppressed = suppressed;
+    }
+}
+
+impl Updatable for NotificationLayer {
+    fn update(&mut self, context: &UpdateContext) {
+        let delta = context.subsystem_delta_ticks(Subsystem::Pacing);
+        if !self.queue.update(delta, self.suppressed) {
+            return;
+        }
+
+        self.toast = self.queue.current().map(|text| {
+            let position = self.anchor.toast_position();
+            let end = position + TOAST_SIZE;
+            Toast {
+                panel: PosVertexBuffer::new(
+                    context.gpu_resources,
+                    (position.x, position.y, end.x, end.y),
+                ),
+                text: Message::new_generic(
+                    context,
+                    self.font_atlas.clone(),
+                    position + TOAST_PADDING,
+                    text,
+                ),
+            }
+        });
+    }
+}
+
+impl Renderable for NotificationLayer {
+    fn render<'enc>(
+        &'enc self,
+        resources: &'enc GpuCommonResources,
+        render_pass: &mut wgpu::RenderPass<'enc>,
+        transform: Mat4,
+        projection: Mat4,
+    ) {
+        let Some(toast) = &self.toast else {
+            return;
+        };
+        if self.suppressed {
+            return;
+        }
+        let opacity = self.queue.opacity();
+
+        resources.draw_fill(
+            render_pass,
+            toast.panel.vertex_source(),
+            projection * transform,
+            vec4(0.0, 0.0, 0.0, PANEL_ALPHA * opacity),
+        );
+        toast
+            .text
+            .render_faded(resources, render_pass, transform, projection, opacity);
+    }
+
+    fn resize(&mut self, _resources: &GpuCommonResources) {
+        // no internal buffers to resize
+    }
+}
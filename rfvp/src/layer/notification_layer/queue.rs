@@ -0,0 +1,193 @@
+use std::collections::VecDeque;
+
+use rfvp_core::time::Ticks;
+use tracing::warn;
+
+/// Notifications waiting behind the shown one, older ones are dropped past this
+const MAX_PENDING: usize = 8;
+
+/// How long a notification fades in, stays and fades out
+#[derive(Debug, Clone, Copy)]
+pub struct NotificationTimings {
+    pub fade_in: Ticks,
+    pub hold: Ticks,
+    pub fade_out: Ticks,
+}
+
+impl NotificationTimings {
+    pub fn from_millis(fade_in: f32, hold: f32, fade_out: f32) -> Self {
+        Self {
+            fade_in: Ticks::from_millis(fade_in.max(0.0)),
+            hold: Ticks::from_millis(hold.max(0.0)),
+            fade_out: Ticks::from_millis(fade_out.max(0.0)),
+        }
+    }
+
+    fn total(&self) -> Ticks {
+        self.fade_in + self.hold + self.fade_out
+    }
+}
+
+impl Default for NotificationTimings {
+    fn default() -> Self {
+        Self::from_millis(250.0, 2500.0, 500.0)
+    }
+}
+
+struct Shown {
+    text: String,
+    elapsed: Ticks,
+}
+
+/// Shows notifications one at a time, in the order they arrive.
+///
+/// Kept apart from the rendering so the timing can be tested without a GPU.
+pub struct NotificationQueue {
+    timings: NotificationTimings,
+    pending: VecDeque<String>,
+    shown: Option<Shown>,
+}
+
+impl NotificationQueue {
+    pub fn new(timings: NotificationTimings) -> Self {
+        Self {
+            timings,
+            pending: VecDeque::new(),
+            shown: None,
+        }
+    }
+
+    /// Also applies to the notification being shown
+    pub fn set_timings(&mut self, timings: NotificationTimings) {
+        self.timings = timings;
+    }
+
+    pub fn push(&mut self, text: String) {
+        if self.pending.len() >= MAX_PENDING {
+            let dropped = self.pending.pop_front();
+            warn!("Too many notifications, dropping {:?}", dropped);
+        }
+        self.pending.push_back(text);
+    }
+
+    /// Advances the shown notification by `delta`, starting the next one when it's over.
+    ///
+    /// Nothing moves while `suppressed`, the notifications wait for it to be lifted.
+    /// Returns whether the shown notification changed.
+    pub fn update(&mut self, delta: Ticks, suppressed: bool) -> bool {
+        if suppressed {
+            return false;
+        }
+
+        let mut changed = false;
+        if let Some(shown) = &mut self.shown {
+            shown.elapsed += delta;
+            if shown.elapsed >= self.timings.total() {
+                self.shown = None;
+                changed = true;
+            }
+        }
+        if self.shown.is_none() {
+            if let Some(text) = self.pending.pop_front() {
+                self.shown = Some(Shown {
+                    text,
+                    elapsed: Ticks::ZERO,
+                });
+                changed = true;
+            }
+        }
+        changed
+    }
+
+    pub fn current(&self) -> Option<&str> {
+        self.shown.as_ref().map(|shown| shown.text.as_str())
+    }
+
+    /// The opacity of the shown notification, from 0 to 1
+    pub fn opacity(&self) -> f32 {
+        let Some(shown) = &self.shown else {
+            return 0.0;
+        };
+        let NotificationTimings {
+            fade_in,
+            hold,
+            fade_out,
+        } = self.timings;
+
+        let ramp = |elapsed: Ticks, duration: Ticks| {
+            if duration <= Ticks::ZERO {
+                1.0
+            } else {
+                (elapsed / duration).clamp(0.0, 1.0)
+            }
+        };
+
+        if shown.elapsed < fade_in {
+            ramp(shown.elapsed, fade_in)
+        } else if shown.elapsed < fade_in + hold {
+            1.0
+        } else {
+            1.0 - ramp(shown.elapsed - fade_in - hold, fade_out)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn timings() -> NotificationTimings {
+        NotificationTimings {
+            fade_in: Ticks::from_u32(10),
+            hold: Ticks::from_u32(20),
+            fade_out: Ticks::from_u32(10),
+        }
+    }
+
+    #[test]
+    fn test_fade_timing() {
+        let mut queue = NotificationQueue::new(timings());
+        assert!(!queue.update(Ticks::from_u32(1), false));
+
+        queue.push("Now Playing: Track 1".to_string());
+        assert!(queue.update(Ticks::from_u32(1), false));
+        assert_eq!(queue.current(), Some("Now Playing: Track 1"));
+        assert_eq!(queue.opacity(), 0.0);
+
+        queue.update(Ticks::from_u32(5), false);
+        assert_eq!(queue.opacity(), 0.5);
+        queue.update(Ticks::from_u32(10), false);
+        assert_eq!(queue.opacity(), 1.0);
+        queue.update(Ticks::from_u32(20), false);
+        assert_eq!(queue.opacity(), 0.5);
+
+        assert!(queue.update(Ticks::from_u32(5), false));
+        assert_eq!(queue.current(), None);
+        assert_eq!(queue.opacity(), 0.0);
+    }
+
+    #[test]
+    fn test_queueing_and_suppression() {
+        let mut queue = NotificationQueue::new(timings());
+        queue.push("first".to_string());
+        queue.push("second".to_string());
+
+        // nothing shows during a movie
+        assert!(!queue.update(Ticks::from_u32(100), true));
+        assert_eq!(queue.current(), None);
+
+        queue.update(Ticks::ZERO, false);
+        assert_eq!(queue.current(), Some("first"));
+        queue.update(Ticks::from_u32(39), false);
+        assert_eq!(queue.current(), Some("first"));
+        // the next one starts as soon as the first is gone
+        assert!(queue.update(Ticks::from_u32(1), false));
+        assert_eq!(queue.current(), Some("second"));
+        assert_eq!(queue.pending.len(), 0);
+
+        for i in 0..MAX_PENDING + 2 {
+            queue.push(i.to_string());
+        }
+        assert_eq!(queue.pending.len(), MAX_PENDING);
+    }
+}
@@ -1,7 +1,7 @@
 use std::{fmt::Debug, sync::Arc};
 
-use glam::Mat4;
-use rfvp_render::{GpuCommonResources, Renderable};
+use glam::{uvec2, vec2, Mat4, Vec2};
+use rfvp_render::{GpuCommonResources, Renderable, SpriteHitArea};
 
 use crate::{
     asset::picture::Picture,
@@ -31,6 +31,18 @@ impl PictureLayer {
             props: LayerProperties::new(),
         }
     }
+
+    /// Whether `point` of the virtual screen is over an opaque pixel of the picture, as drawn
+    /// under `transform`, the transform of the parent groups.
+    pub fn hit_test(&self, transform: Mat4, point: Vec2) -> bool {
+        let info = self.picture.info();
+        SpriteHitArea::new(
+            self.props.compute_transform(transform),
+            vec2(info.offset_x as f32, info.offset_y as f32),
+            uvec2(info.width as u32, info.height as u32),
+        )
+        .hit(point, |texel| self.picture.alpha_at(texel))
+    }
 }
 
 impl Renderable for PictureLayer {
@@ -44,12 +56,16 @@ impl Renderable for PictureLayer {
         let total_transform = projection * self.props.compute_transform(transform);
         // TODO: there should be a generic function to render a layer (from texture?)
         let gpu_image = self.picture.gpu_image(resources);
-        resources.draw_sprite(
-            render_pass,
-            gpu_image.vertex_source(),
-            gpu_image.bind_group(),
-            total_transform,
-        );
+        let sampling = gpu_image.sampling_at(self.props.scale());
+        for tile in gpu_image.tiles() {
+            resources.draw_sprite_blended(
+                render_pass,
+                tile.vertex_source(),
+                tile.bind_group_for(resources, sampling),
+                total_transform,
+                self.props.blend_mode(),
+            );
+        }
     }
 
     fn resize(&mut self, _resources: &GpuCommonResources) {
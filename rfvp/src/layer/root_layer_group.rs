@@ -2,13 +2,16 @@ use glam::Mat4;
 use rfvp_render::{GpuCommonResources, RenderTarget, Renderable};
 
 use crate::{
-    layer::{screen_layer::ScreenLayer, Layer, LayerProperties, MessageLayer},
+    layer::{
+        screen_layer::ScreenLayer, Layer, LayerProperties, MessageLayer, NotificationLayer,
+    },
     update::{Updatable, UpdateContext},
 };
 
 pub struct RootLayerGroup {
     screen_layer: ScreenLayer,
     message_layer: MessageLayer,
+    notification_layer: NotificationLayer,
     render_target: RenderTarget,
     properties: LayerProperties,
 }
@@ -25,9 +28,12 @@ impl RootLayerGroup {
             Some("LayerGroup RenderTarget"),
         );
 
+        let notification_layer = NotificationLayer::new(message_layer.font_atlas());
+
         Self {
             screen_layer,
             message_layer,
+            notification_layer,
             render_target,
             properties: LayerProperties::new(),
         }
@@ -48,6 +54,16 @@ impl RootLayerGroup {
     pub fn message_layer_mut(&mut self) -> &mut MessageLayer {
         &mut self.message_layer
     }
+
+    pub fn notification_layer_mut(&mut self) -> &mut NotificationLayer {
+        &mut self.notification_layer
+    }
+
+    /// The transform the planes of the page are drawn with, to hit test their layers
+    pub fn plane_transform(&self) -> Mat4 {
+        self.screen_layer
+            .plane_transform(self.properties.compute_transform(Mat4::IDENTITY))
+    }
 }
 
 impl Updatable for RootLayerGroup {
@@ -55,6 +71,7 @@ impl Updatable for RootLayerGroup {
         self.properties.update(context);
         self.screen_layer.update(context);
         self.message_layer.update(context);
+        self.notification_layer.update(context);
     }
 }
 
@@ -84,6 +101,11 @@ impl Renderable for RootLayerGroup {
             self.message_layer
                 .render(resources, &mut render_pass, transform, projection);
             render_pass.pop_debug_group();
+
+            render_pass.push_debug_group("NotificationLayer");
+            self.notification_layer
+                .render(resources, &mut render_pass, transform, projection);
+            render_pass.pop_debug_group();
         }
 
         render_pass.push_debug_group("RootLayerGroup Render");
@@ -1,5 +1,6 @@
 use glam::Mat4;
-use rfvp_render::{GpuCommonResources, RenderTarget, Renderable};
+use rfvp_core::{screen_effect::ScreenEffects, time::Subsystem};
+use rfvp_render::{GpuCommonResources, PosVertexBuffer, RenderTarget, Renderable};
 
 use crate::{
     layer::{page_layer::PageLayer, Layer, LayerProperties},
@@ -10,6 +11,8 @@ pub struct ScreenLayer {
     page_layer: PageLayer,
     properties: LayerProperties,
     render_target: RenderTarget,
+    effects: ScreenEffects,
+    flash_vertex_buffer: PosVertexBuffer,
     // TODO: a TransitionLayer (two kinds??) should be here
 }
 
@@ -23,6 +26,8 @@ impl ScreenLayer {
                 resources.current_render_buffer_size(),
                 Some("ScreenLayer RenderTarget"),
             ),
+            effects: ScreenEffects::default(),
+            flash_vertex_buffer: PosVertexBuffer::new_fullscreen(resources),
         }
     }
 
@@ -33,12 +38,30 @@ impl ScreenLayer {
     pub fn page_layer_mut(&mut self) -> &mut PageLayer {
         &mut self.page_layer
     }
+
+    /// the flash, quake and negative over the whole screen
+    pub fn effects(&self) -> &ScreenEffects {
+        &self.effects
+    }
+
+    pub fn effects_mut(&mut self) -> &mut ScreenEffects {
+        &mut self.effects
+    }
+
+    /// The transform the planes are drawn with under `transform`, shaken by the quake
+    pub fn plane_transform(&self, transform: Mat4) -> Mat4 {
+        let quake = Mat4::from_translation(self.effects.quake_offset().extend(0.0));
+        let transform = quake * self.properties.compute_transform(transform);
+        self.page_layer.properties().compute_transform(transform)
+    }
 }
 
 impl Updatable for ScreenLayer {
     fn update(&mut self, context: &UpdateContext) {
         self.page_layer.update(context);
         self.properties.update(context);
+        self.effects
+            .update(context.subsystem_delta_ticks(Subsystem::Motion));
     }
 }
 
@@ -56,7 +79,9 @@ impl Renderable for ScreenLayer {
                 .render_target
                 .begin_srgb_render_pass(&mut encoder, Some("PageLayer RenderPass"));
 
-            let transform = self.properties.compute_transform(transform);
+            // the quake shakes the pages, the screen quad stays put
+            let quake = Mat4::from_translation(self.effects.quake_offset().extend(0.0));
+            let transform = quake * self.properties.compute_transform(transform);
             let projection = self.render_target.projection_matrix();
 
             self.page_layer
@@ -65,12 +90,29 @@ impl Renderable for ScreenLayer {
 
         render_pass.push_debug_group("ScreenLayer Render");
         // TODO use layer pseudo-pipeline
-        resources.draw_sprite(
-            render_pass,
-            self.render_target.vertex_source(),
-            self.render_target.bind_group(),
-            projection,
-        );
+        if self.effects.is_negative() {
+            resources.draw_negative(
+                render_pass,
+                self.render_target.vertex_source(),
+                self.render_target.bind_group(),
+                projection,
+            );
+        } else {
+            resources.draw_sprite(
+                render_pass,
+                self.render_target.vertex_source(),
+                self.render_target.bind_group(),
+                projection,
+            );
+        }
+        if let Some(color) = self.effects.flash_color() {
+            resources.draw_fill(
+                render_pass,
+                self.flash_vertex_buffer.vertex_source(),
+                projection,
+                color,
+            );
+        }
         render_pass.pop_debug_group();
     }
 
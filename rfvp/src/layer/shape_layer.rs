@@ -0,0 +1,106 @@
+use std::fmt::Debug;
+
+use glam::{Mat4, Vec2};
+use rfvp_core::shape::{Shape, ShapeKind};
+use rfvp_render::{GpuCommonResources, PosVertexBuffer, Renderable, SpriteHitArea};
+
+use crate::{
+    layer::{Layer, LayerProperties},
+    update::{Updatable, UpdateContext},
+};
+
+/// the quad goes this far past the shape, for the anti-aliased edge
+const EDGE_MARGIN: f32 = 1.0;
+
+/// A rounded rectangle or a circle drawn by the renderer, its top left corner at the
+/// position of the layer
+pub struct ShapeLayer {
+    shape: Shape,
+    vertex_buffer: PosVertexBuffer,
+
+    props: LayerProperties,
+}
+
+impl ShapeLayer {
+    pub fn new(resources: &GpuCommonResources, shape: Shape) -> Self {
+        let rect = (
+            -EDGE_MARGIN,
+            -EDGE_MARGIN,
+            shape.size.x + EDGE_MARGIN,
+            shape.size.y + EDGE_MARGIN,
+        );
+
+        Self {
+            shape,
+            vertex_buffer: PosVertexBuffer::new(resources, rect),
+            props: LayerProperties::new(),
+        }
+    }
+
+    pub fn shape(&self) -> &Shape {
+        &self.shape
+    }
+
+    /// Whether `point` of the virtual screen is on the shape, as drawn under `transform`.
+    /// The corners cut off a rounded rectangle or a circle don't hit.
+    pub fn hit_test(&self, transform: Mat4, point: Vec2) -> bool {
+        SpriteHitArea::new(
+            self.props.compute_transform(transform),
+            Vec2::ZERO,
+            self.shape.size.as_uvec2(),
+        )
+        .hit_local(point, |local| self.shape.contains(local))
+    }
+}
+
+impl Renderable for ShapeLayer {
+    fn render<'enc>(
+        &'enc self,
+        resources: &'enc GpuCommonResources,
+        render_pass: &mut wgpu::RenderPass<'enc>,
+        transform: Mat4,
+        projection: Mat4,
+    ) {
+        let total_transform = projection * self.props.compute_transform(transform);
+
+        resources.draw_shape(
+            render_pass,
+            self.vertex_buffer.vertex_source(),
+            total_transform,
+            &self.shape,
+        );
+    }
+
+    fn resize(&mut self, _resources: &GpuCommonResources) {
+        // no internal buffers to resize
+    }
+}
+
+impl Updatable for ShapeLayer {
+    fn update(&mut self, ctx: &UpdateContext) {
+        self.props.update(ctx);
+    }
+}
+
+impl Debug for ShapeLayer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let kind = match self.shape.kind {
+            ShapeKind::RoundedRect { .. } => "RoundedRect",
+            ShapeKind::Circle => "Circle",
+        };
+        let size = self.shape.size;
+        let shape = format!("{} {}x{}", kind, size.x, size.y);
+
+        f.debug_tuple("ShapeLayer").field(&shape).finish()
+    }
+}
+
+impl Layer for ShapeLayer {
+    fn properties(&self) -> &LayerProperties {
+        &self.props
+    }
+
+    fn properties_mut(&mut self) -> &mut LayerProperties {
+        &mut self.props
+    }
+}
@@ -0,0 +1,138 @@
+use std::cell::{Cell, RefCell};
+
+use egui::{ComboBox, Grid, RichText, ScrollArea, TextEdit, Window};
+use log::{Level, LevelFilter};
+use rfvp_core::logging::{self, LogFilter};
+
+use crate::render::overlay::{OverlayCollector, OverlayVisitable};
+
+const LEVELS: [LevelFilter; 5] = [
+    LevelFilter::Error,
+    LevelFilter::Warn,
+    LevelFilter::Info,
+    LevelFilter::Debug,
+    LevelFilter::Trace,
+];
+
+fn level_color(level: Level) -> egui::Color32 {
+    match level {
+        Level::Error => egui::Color32::RED,
+        Level::Warn => egui::Color32::from_rgb(0xc0, 0x80, 0x00),
+        Level::Info => egui::Color32::from_gray(40),
+        Level::Debug | Level::Trace => egui::Color32::from_gray(110),
+    }
+}
+
+/// The recent log entries, and the levels of the modules
+pub struct LogPanel {
+    /// the least severe level of the entries shown
+    level: Cell<LevelFilter>,
+    module: RefCell<String>,
+    filter_text: RefCell<String>,
+    filter_error: RefCell<Option<String>>,
+}
+
+impl LogPanel {
+    pub fn new() -> Self {
+        let filter_text = logging::logger()
+            .map(|logger| logger.filter().to_string())
+            .unwrap_or_default();
+        Self {
+            level: Cell::new(LevelFilter::Trace),
+            module: RefCell::new(String::new()),
+            filter_text: RefCell::new(filter_text),
+            filter_error: RefCell::new(None),
+        }
+    }
+
+    fn filter_ui(&self, ui: &mut egui::Ui, logger: &logging::Logger) {
+        ui.horizontal(|ui| {
+            ui.label("Levels:");
+            ui.add(TextEdit::singleline(&mut *self.filter_text.borrow_mut()).desired_width(300.0));
+            if ui.button("Apply").clicked() {
+                let result = self.filter_text.borrow().parse::<LogFilter>();
+                match result {
+                    Ok(filter) => {
+                        *self.filter_text.borrow_mut() = filter.to_string();
+                        logger.set_filter(filter);
+                        *self.filter_error.borrow_mut() = None;
+                    }
+                    Err(err) => *self.filter_error.borrow_mut() = Some(format!("{:#}", err)),
+                }
+            }
+        });
+        if let Some(error) = &*self.filter_error.borrow() {
+            ui.colored_label(egui::Color32::RED, error);
+        }
+    }
+
+    fn entries_ui(&self, ui: &mut egui::Ui, logger: &logging::Logger) {
+        ui.horizontal(|ui| {
+            let mut level = self.level.get();
+            ComboBox::from_label("Show")
+                .selected_text(level.as_str())
+                .show_ui(ui, |ui| {
+                    for option in LEVELS {
+                        ui.selectable_value(&mut level, option, option.as_str());
+                    }
+                });
+            self.level.set(level);
+            ui.label("Module:");
+            ui.add(TextEdit::singleline(&mut *self.module.borrow_mut()).desired_width(200.0));
+        });
+
+        // copied out, egui may log while drawing and the logger would wait on the ring
+        let (entries, dropped, capacity) = {
+            let ring = logger.ring();
+            let module = self.module.borrow();
+            let entries = ring
+                .filtered(self.level.get(), &module)
+                .cloned()
+                .collect::<Vec<_>>();
+            (entries, ring.dropped(), ring.capacity())
+        };
+        if dropped > 0 {
+            ui.label(format!(
+                "{} older entries dropped, the last {} are kept",
+                dropped, capacity
+            ));
+        }
+        ScrollArea::vertical()
+            .max_height(400.0)
+            .stick_to_bottom(true)
+            .show(ui, |ui| {
+                Grid::new("log_entries").striped(true).show(ui, |ui| {
+                    for entry in &entries {
+                        ui.monospace(entry.time.format("%H:%M:%S%.3f").to_string());
+                        ui.label(
+                            RichText::new(entry.level.as_str())
+                                .monospace()
+                                .color(level_color(entry.level)),
+                        );
+                        ui.monospace(&entry.target);
+                        ui.monospace(&entry.message);
+                        ui.end_row();
+                    }
+                });
+            });
+    }
+}
+
+impl OverlayVisitable for LogPanel {
+    fn visit_overlay(&self, collector: &mut OverlayCollector) {
+        collector.overlay(
+            "Log",
+            |ctx, _top_left| {
+                let Some(logger) = logging::logger() else {
+                    return;
+                };
+                Window::new("Log").default_width(800.0).show(ctx, |ui| {
+                    self.filter_ui(ui, logger);
+                    ui.separator();
+                    self.entries_ui(ui, logger);
+                });
+            },
+            false,
+        );
+    }
+}
@@ -10,9 +10,12 @@ mod asset;
 mod adv;
 mod audio;
 mod cli;
+mod debug_time;
 mod fps_counter;
 mod input;
 mod layer;
+mod log_panel;
+mod memory_usage;
 mod render;
 mod time;
 mod update;
@@ -0,0 +1,33 @@
+use rfvp_core::memory::{MemoryGovernor, MemoryKind};
+
+use crate::render::overlay::{OverlayCollector, OverlayVisitable};
+
+const MB: f64 = 1024.0 * 1024.0;
+
+impl OverlayVisitable for MemoryGovernor {
+    fn visit_overlay(&self, collector: &mut OverlayCollector) {
+        collector.overlay(
+            "Memory",
+            |_ctx, top_left| {
+                let step = match self.step() {
+                    Some(step) => format!(", {:?}", step),
+                    None => String::new(),
+                };
+                top_left.label(format!(
+                    "Memory: {:.1} of {:.1} MB{}",
+                    self.total_usage() as f64 / MB,
+                    self.budget() as f64 / MB,
+                    step
+                ));
+                for kind in MemoryKind::ALL {
+                    top_left.label(format!(
+                        "  {}: {:.1} MB",
+                        kind.name(),
+                        self.usage(kind) as f64 / MB
+                    ));
+                }
+            },
+            false,
+        );
+    }
+}
@@ -1,13 +1,14 @@
 use std::{sync::Arc, time::Duration};
 
 use enum_dispatch::enum_dispatch;
-use rfvp_core::time::Ticks;
+use rfvp_core::time::{GameClock, Subsystem, Ticks};
 use rfvp_render::GpuCommonResources;
 
 use crate::{asset::AnyAssetServer, input::RawInputState, layer::UserLayer, time::Time};
 
 pub struct UpdateContext<'a> {
     pub time: &'a Time,
+    pub clock: &'a GameClock,
     pub gpu_resources: &'a Arc<GpuCommonResources>,
     pub asset_server: &'a Arc<AnyAssetServer>,
     pub raw_input_state: &'a RawInputState,
@@ -20,6 +21,20 @@ impl<'a> UpdateContext<'a> {
     pub fn time_delta_ticks(&self) -> Ticks {
         Ticks::from_seconds(self.time.delta_seconds())
     }
+    /// virtual time advanced since the last frame, stops while the game is paused
+    pub fn clock_delta_ticks(&self) -> Ticks {
+        self.clock.delta_ticks()
+    }
+    pub fn subsystem_delta(&self, subsystem: Subsystem) -> Duration {
+        self.clock.subsystem(subsystem).delta_since_last_frame()
+    }
+    pub fn subsystem_delta_ticks(&self, subsystem: Subsystem) -> Ticks {
+        self.clock.subsystem(subsystem).delta_ticks()
+    }
+    /// see [`SubClock::sub_steps`](rfvp_core::time::SubClock::sub_steps)
+    pub fn subsystem_sub_steps(&self, subsystem: Subsystem) -> (u32, Ticks) {
+        self.clock.subsystem(subsystem).sub_steps()
+    }
 }
 
 #[enum_dispatch]
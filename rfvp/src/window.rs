@@ -1,13 +1,35 @@
 use std::{
     path::Path,
-    sync::{Arc, RwLock},
+    sync::{mpsc, Arc, RwLock, Weak},
+    time::Duration,
 };
 
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use glam::Mat4;
-use rfvp_audio::AudioManager;
+use rfvp_audio::{AudioManager, DeviceLost};
+use rfvp_core::{
+    format::{
+        save::{
+            autosave::{AutosaveConfig, AutosaveStore},
+            voice_config::VoiceConfig,
+        },
+        scenario::{
+            lint::{lint_scenario, load_source_map},
+            scene_table::load_scene_table,
+            Scenario,
+        },
+    },
+    locale::{self, Language},
+    logging::{self, LogConfig},
+    memory::{self, Reclaim},
+    time::{
+        presented_frames, AutoAdvance, GameClock, PlaytimeConfig, Subsystem, DEFAULT_FIXED_STEP,
+    },
+};
 use rfvp_render::{
-    BindGroupLayouts, Camera, GpuCommonResources, Pillarbox, Pipelines, RenderTarget, Renderable,
+    AspectLock, BindGroupLayouts, Camera, DroppedFrames, GpuCommonResources, Msaa, OversizePolicy,
+    Pillarbox, Pipelines, RenderTarget, Renderable, SurfaceFormat, SurfaceResize, SurfaceSize,
+    TextureLimit, SRGB_TEXTURE_FORMAT,
 };
 use tracing::{debug, info, warn};
 #[cfg(target_arch = "wasm32")]
@@ -22,28 +44,51 @@ use winit::{
 
 use crate::{
     adv::{self, assets::AdvAssets, Adv},
-    asset::{locate_assets, AnyAssetIo, AnyAssetServer, AssetServer},
+    asset::{locate_assets, picture::Picture, AnyAssetIo, AnyAssetServer, AssetServer},
     cli::Cli,
+    debug_time::DebugTime,
     fps_counter::FpsCounter,
     input::RawInputState,
+    layer::NotificationTimings,
+    log_panel::LogPanel,
     render::overlay::{OverlayManager, OverlayVisitable},
     time::Time,
     update::{Updatable, UpdateContext},
 };
 
+/// how long the audio fades out when the game is closed
+const SHUTDOWN_AUDIO_FADE: Duration = Duration::from_millis(150);
+/// how long each task pool gets to stop its worker threads
+const SHUTDOWN_JOIN_TIMEOUT: Duration = Duration::from_secs(1);
+/// the process is killed if the shutdown hangs for longer than this
+const FORCE_QUIT_AFTER: Duration = Duration::from_secs(3);
+
 struct State<'window> {
     surface: wgpu::Surface<'window>,
     surface_config: wgpu::SurfaceConfiguration,
-    window_size: (u32, u32),
+    surface_format: SurfaceFormat,
+    window_size: SurfaceSize,
+    /// maps the cursor to the game screen, and keeps the window at its ratio if resizable
+    aspect_lock: AspectLock,
+    resizable: bool,
     resources: Arc<GpuCommonResources>,
     camera: Camera,
     time: Time,
+    clock: GameClock,
+    /// the playtime stops while the window isn't focused
+    focused: bool,
+    /// slow motion and frame stepping, only in debug builds or with `--debug-time`
+    debug_time: Option<DebugTime>,
+    log_panel: LogPanel,
     render_target: RenderTarget,
     pillarbox: Pillarbox,
     asset_server: Arc<AnyAssetServer>,
     input: RawInputState,
     overlay_manager: OverlayManager,
     fps_counter: FpsCounter,
+    audio_manager: Arc<AudioManager>,
+    /// the losses of the audio device reported by the audio manager
+    device_lost: mpsc::Receiver<DeviceLost>,
     adv: Adv,
 }
 
@@ -52,7 +97,7 @@ impl<'state> State<'state> {
         window: &'state Window,
         adv_assets: AdvAssets,
         asset_server: Arc<AssetServer<AnyAssetIo>>,
-        _cli: &Cli,
+        cli: &Cli,
     ) -> Result<Self> {
         let window_size = window.inner_size();
         let window_size = (window_size.width, window_size.height);
@@ -83,7 +128,8 @@ impl<'state> State<'state> {
             .request_device(
                 &wgpu::DeviceDescriptor {
                     label: None,
-                    required_features: wgpu::Features::PUSH_CONSTANTS,
+                    required_features: wgpu::Features::PUSH_CONSTANTS
+                        | Msaa::device_features(&adapter),
                     // WebGL doesn't support all of wgpu's features, so if
                     // we're building for the web we'll have to disable some.
                     required_limits: wgpu::Limits {
@@ -99,35 +145,63 @@ impl<'state> State<'state> {
             .await
             .context("Failed to create wgpu device")?;
 
-        // TODO: make a better selection?
         // TODO: rn we don't really support switching this
-        // it may be worth to add one more pass to convert from internal (Rgba8) to the preferred output format
-        // or support having everything in the preferred format? (sounds hard)
-        let surface_texture_format = surface.get_capabilities(&adapter).formats[0];
+        let srgb_views = adapter.get_info().backend != wgpu::Backend::Gl;
+        let surface_format =
+            SurfaceFormat::select(&surface.get_capabilities(&adapter).formats, srgb_views)
+                .context("The surface is not supported by the adapter")?;
+        if !surface_format.is_gamma_correct() {
+            warn!(
+                "No sRGB output format available ({:?}), colors will look off",
+                surface_format.surface
+            );
+        }
+        let surface_texture_format = surface_format.view;
 
         let config = wgpu::SurfaceConfiguration {
             usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
-            format: surface_texture_format,
+            format: surface_format.surface,
             width: window_size.0,
             height: window_size.1,
             present_mode: wgpu::PresentMode::Fifo,
             desired_maximum_frame_latency: 2,
             alpha_mode: wgpu::CompositeAlphaMode::Auto,
-            view_formats: vec![],
+            view_formats: surface_format.view_formats(),
         };
         surface.configure(&device, &config);
 
+        let msaa = Msaa::from_sample_count(cli.msaa)
+            .unwrap_or_else(|| {
+                warn!("Unsupported MSAA sample count {}, disabling MSAA", cli.msaa);
+                Msaa::Off
+            })
+            .for_adapter(&adapter, SRGB_TEXTURE_FORMAT);
+
         let bind_group_layouts = BindGroupLayouts::new(&device);
-        let pipelines = Pipelines::new(&device, &bind_group_layouts, surface_texture_format);
+        let pipelines = Pipelines::new(&device, &bind_group_layouts, surface_texture_format, msaa);
 
         let camera = Camera::new(window_size);
 
+        let oversize = if cli.downscale_oversized_textures {
+            OversizePolicy::Downscale
+        } else {
+            OversizePolicy::Tile
+        };
+        let texture_limit = TextureLimit::for_device(&device, oversize);
+        info!(
+            "Textures up to {0}x{0}, larger pictures: {1:?}",
+            texture_limit.max_dimension, texture_limit.oversize
+        );
+
         let resources = Arc::new(GpuCommonResources {
             device,
             queue,
             render_buffer_size: RwLock::new(camera.render_buffer_size()),
             bind_group_layouts,
             pipelines,
+            msaa,
+            texture_limit,
+            dropped_frames: DroppedFrames::new(),
         });
 
         let overlay = OverlayManager::new(&resources, surface_texture_format);
@@ -140,86 +214,244 @@ impl<'state> State<'state> {
 
         let pillarbox = Pillarbox::new(&resources);
 
+        let mut clock = GameClock::new();
+        if cli.fixed_motion_step {
+            clock.set_fixed_timestep(Subsystem::Motion, Some(DEFAULT_FIXED_STEP));
+        }
+
+        let aspect_lock = AspectLock::new(adv_assets.scenario.get_screen_size());
+
         let audio_manager = Arc::new(AudioManager::new());
+        let (device_lost_sender, device_lost) = mpsc::channel();
+        audio_manager.set_on_device_lost(move |lost: &DeviceLost| {
+            // told to the player by the next update
+            let _ = device_lost_sender.send(lost.clone());
+        });
+
+        let scenario = adv_assets.scenario.clone();
+        let mut adv = Adv::new(&resources, audio_manager.clone(), adv_assets, 0, 42);
+        adv.set_text_scale(cli.text_scale);
+        adv.set_click_completes_reveal(!cli.single_click_advance);
+        if cli.auto {
+            adv.set_auto_advance(Some(AutoAdvance::default()));
+        }
+        adv.set_notification_anchor(cli.notification_corner);
+        adv.set_notification_timings(NotificationTimings::from_millis(
+            cli.notification_fade_in_ms,
+            cli.notification_hold_ms,
+            cli.notification_fade_out_ms,
+        ));
+        adv.set_now_playing_toast(cli.now_playing_toast);
+        adv.set_playtime_config(PlaytimeConfig {
+            count_menus: !cli.playtime_skip_menus,
+            ..Default::default()
+        });
+        if cfg!(debug_assertions) || cli.scene_jump {
+            let game_root = cli.assets_dir.as_deref().unwrap_or(Path::new("."));
+            match load_scene_table(game_root, &scenario) {
+                Ok(scene_table) => adv.enable_scene_jump(scene_table),
+                Err(err) => warn!("Scene jump disabled: {:#}", err),
+            }
+        }
+        if let Some(autosave_dir) = &cli.autosave_dir {
+            let config = AutosaveConfig {
+                every_lines: (cli.autosave_lines > 0).then_some(cli.autosave_lines),
+                ..Default::default()
+            };
+            let result = AutosaveStore::open(autosave_dir, config.slot_count)
+                .and_then(|store| adv.enable_autosave(store, config));
+            if let Err(err) = result {
+                warn!("Autosave disabled: {:#}", err);
+            }
+            match VoiceConfig::load(autosave_dir) {
+                Ok(voice_config) => adv.set_voice_config(voice_config),
+                Err(err) => warn!("Using the default voice options: {:#}", err),
+            }
+        }
 
-        let mut adv = Adv::new(&resources, audio_manager, adv_assets, 0, 42);
+        let fps_counter = FpsCounter::new(resources.dropped_frames.clone());
 
         Ok(Self {
             surface,
             surface_config: config,
-            window_size,
+            surface_format,
+            window_size: SurfaceSize::new(window_size),
+            aspect_lock,
+            resizable: cli.resizable,
             resources,
             camera,
             time: Time::default(),
+            clock,
+            focused: true,
+            debug_time: (cfg!(debug_assertions) || cli.debug_time).then(DebugTime::new),
+            log_panel: LogPanel::new(),
             render_target,
             pillarbox,
             asset_server,
             input: RawInputState::new(),
             overlay_manager: overlay,
-            fps_counter: FpsCounter::new(),
+            fps_counter,
+            audio_manager,
+            device_lost,
             adv,
         })
     }
 
+    /// Stop everything in order before the window goes away: fade the audio out
+    /// and stop the worker threads. The GPU resources are dropped with the state,
+    /// before the window.
+    fn shutdown(&mut self) {
+        info!("Shutting down");
+
+        std::thread::Builder::new()
+            .name("Shutdown watchdog".to_string())
+            .spawn(|| {
+                std::thread::sleep(FORCE_QUIT_AFTER);
+                warn!("Shutdown takes longer than {:?}, quitting", FORCE_QUIT_AFTER);
+                std::process::exit(1);
+            })
+            .expect("Failed to spawn the shutdown watchdog");
+
+        self.audio_manager.fade_out(SHUTDOWN_AUDIO_FADE);
+        std::thread::sleep(SHUTDOWN_AUDIO_FADE);
+
+        for thread in rfvp_tasks::shutdown_global_task_pools(SHUTDOWN_JOIN_TIMEOUT) {
+            warn!("Thread {:?} did not stop in time", thread);
+        }
+    }
+
     fn reconfigure_surface(&mut self) {
         self.surface
             .configure(&self.resources.device, &self.surface_config);
     }
 
-    pub fn resize(&mut self, new_size: (u32, u32)) {
-        if new_size.0 > 0 && new_size.1 > 0 {
-            self.window_size = new_size;
-            self.surface_config.width = new_size.0;
-            self.surface_config.height = new_size.1;
-            self.surface
-                .configure(&self.resources.device, &self.surface_config);
-
-            self.camera.resize(new_size);
-            self.render_target
-                .resize(&self.resources, self.camera.render_buffer_size());
-
-            debug!(
-                "Window resized to {:?}, new render buffer size is {:?}",
-                new_size,
-                self.camera.render_buffer_size()
-            );
+    /// returns whether a full redraw is needed.
+    /// the VM and audio keep running while the window is minimized, only rendering stops
+    pub fn resize(&mut self, new_size: (u32, u32)) -> bool {
+        let new_size = match self.window_size.resize(new_size) {
+            SurfaceResize::Unchanged => return false,
+            SurfaceResize::Minimized => {
+                debug!("Window minimized, rendering is paused");
+                return false;
+            }
+            SurfaceResize::Resized(new_size) => new_size,
+            SurfaceResize::Restored(new_size) => {
+                debug!("Window restored to {:?}", new_size);
+                new_size
+            }
+        };
+
+        self.surface_config.width = new_size.0;
+        self.surface_config.height = new_size.1;
+        self.surface
+            .configure(&self.resources.device, &self.surface_config);
+
+        self.camera.resize(new_size);
+        self.render_target
+            .resize(&self.resources, self.camera.render_buffer_size());
+
+        debug!(
+            "Window resized to {:?}, new render buffer size is {:?}",
+            new_size,
+            self.camera.render_buffer_size()
+        );
+
+        *self.resources.render_buffer_size.write().unwrap() = self.camera.render_buffer_size();
 
-            *self.resources.render_buffer_size.write().unwrap() = self.camera.render_buffer_size();
+        self.pillarbox.resize(&self.resources);
+        self.adv.resize(&self.resources);
+        self.update_cursor_mapping();
 
-            self.pillarbox.resize(&self.resources);
-            self.adv.resize(&self.resources);
+        true
+    }
+
+    /// The aspect-correct size to request instead of `requested`, if it's off-ratio
+    /// and the window can be resized
+    fn snap_window_size(&self, requested: (u32, u32)) -> Option<(u32, u32)> {
+        if !self.resizable || self.window_size.is_minimized() {
+            return None;
+        }
+        if requested.0 == 0 || requested.1 == 0 {
+            return None;
         }
+
+        let snapped = self.aspect_lock.snap(self.window_size.size(), requested);
+        (snapped != requested).then_some(snapped)
+    }
+
+    fn update_cursor_mapping(&mut self) {
+        self.input.cursor_screen_position = self
+            .aspect_lock
+            .window_to_screen(self.input.mouse_position, self.window_size.size());
     }
 
-    #[allow(unused_variables)]
     fn input(&mut self, event: &WindowEvent) -> bool {
         self.input.on_winit_event(event);
+        match event {
+            WindowEvent::CursorMoved { .. } => self.update_cursor_mapping(),
+            WindowEvent::Focused(focused) => self.focused = *focused,
+            _ => {}
+        }
         false
     }
 
     fn update(&mut self) {
         self.time.update();
+        let steps = match &mut self.debug_time {
+            Some(debug_time) => debug_time.advance(
+                &self.input,
+                &mut self.clock,
+                self.time.raw_delta(),
+                &self.audio_manager,
+            ),
+            None => {
+                self.clock.advance(self.time.raw_delta());
+                1
+            }
+        };
+
+        self.adv.track_playtime(
+            self.time.raw_elapsed(),
+            self.focused,
+            self.clock.is_paused(),
+        );
+
+        memory::governor().rebalance();
 
         let mut input = self.input.clone();
 
         self.overlay_manager
-            .start_update(&self.time, &input, self.window_size);
+            .start_update(&self.time, &input, self.window_size.size());
         self.overlay_manager.visit_overlays(|collector| {
             self.fps_counter.visit_overlay(collector);
             input.visit_overlay(collector);
             self.adv.visit_overlay(collector);
+            self.asset_server
+                .visit_loaded_overlay::<Picture>(collector, "Pictures");
+            memory::governor().visit_overlay(collector);
+            if let Some(debug_time) = &self.debug_time {
+                debug_time.visit_overlay(collector);
+            }
+            self.log_panel.visit_overlay(collector);
         });
         self.overlay_manager
             .finish_update(&self.resources, &mut input);
 
         let update_context = UpdateContext {
             time: &self.time,
+            clock: &self.clock,
             gpu_resources: &self.resources,
             asset_server: &self.asset_server,
             raw_input_state: &input,
         };
 
-        self.adv.update(&update_context);
+        // the game stands still between the single steps
+        if steps > 0 {
+            self.adv.update(&update_context);
+        }
+        for lost in self.device_lost.try_iter() {
+            self.adv.notify_device_lost(&lost);
+        }
         self.fps_counter.update(&update_context);
 
         // NOTE: it's important that the input is updated after everything else, as it clears some state after it should have been handled
@@ -227,6 +459,12 @@ impl<'state> State<'state> {
     }
 
     fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
+        // there is no surface to present to while minimized, don't keep the script waiting for it
+        if self.window_size.is_minimized() {
+            presented_frames().presented();
+            return Ok(());
+        }
+
         // render everything to the render target
         {
             let mut encoder = self.resources.start_encoder();
@@ -245,7 +483,7 @@ impl<'state> State<'state> {
         let output = self.surface.get_current_texture()?;
         let view = output
             .texture
-            .create_view(&wgpu::TextureViewDescriptor::default());
+            .create_view(&self.surface_format.view_descriptor());
 
         {
             let mut encoder = self.resources.start_encoder();
@@ -282,11 +520,40 @@ impl<'state> State<'state> {
         }
 
         output.present();
+        presented_frames().presented();
 
         Ok(())
     }
 }
 
+/// log the lint findings of the script, the problems are fatal if `strict`
+fn validate_script(scenario: &Scenario, game_root: &Path, strict: bool) -> Result<()> {
+    let source_map = load_source_map(game_root)?;
+    let findings = lint_scenario(scenario);
+    for finding in &findings {
+        let message = match source_map
+            .as_ref()
+            .and_then(|map| map.locate(finding.address))
+        {
+            Some(location) => format!("{} ({}:{})", finding, location.file, location.line),
+            None => finding.to_string(),
+        };
+        if finding.kind.is_fatal() {
+            warn!("{}", message);
+        } else {
+            info!("{}", message);
+        }
+    }
+    let problems = findings
+        .iter()
+        .filter(|finding| finding.kind.is_fatal())
+        .count();
+    if strict && problems > 0 {
+        bail!("{} problems found in the script", problems);
+    }
+    Ok(())
+}
+
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen(start))]
 pub async fn run(cli: Cli) {
     cfg_if::cfg_if! {
@@ -294,7 +561,16 @@ pub async fn run(cli: Cli) {
             std::panic::set_hook(Box::new(console_error_panic_hook::hook));
             console_log::init_with_level(log::Level::Warn).expect("Couldn't initialize logger");
         } else {
-            tracing_subscriber::fmt::init();
+            let config = LogConfig {
+                filter: cli
+                    .log_filter
+                    .clone()
+                    .or_else(|| std::env::var("RUST_LOG").ok())
+                    .unwrap_or_else(|| "info".to_string()),
+                file: cli.log_file.clone(),
+                ..Default::default()
+            };
+            logging::init(&config).expect("Couldn't initialize logger");
         }
     }
 
@@ -306,7 +582,13 @@ pub async fn run(cli: Cli) {
 
     debug!("Asset IO: {:#?}", asset_io);
 
+    if let Some(budget) = cli.memory_budget {
+        memory::governor().set_budget(budget * 1024 * 1024);
+    }
+
     let asset_server = Arc::new(AnyAssetServer::new(asset_io.into()));
+    let reclaimer: Weak<dyn Reclaim> = Arc::downgrade(&asset_server);
+    memory::governor().register(reclaimer);
 
     let adv_assets = pollster::block_on(AdvAssets::load(
         &asset_server,
@@ -314,11 +596,34 @@ pub async fn run(cli: Cli) {
     ))
     .expect("Loading assets failed");
 
+    let language = cli
+        .language
+        .unwrap_or_else(|| Language::from_nls(&adv_assets.scenario.nls));
+    let localizer = pollster::block_on(AdvAssets::load_localizer(&asset_server, language))
+        .expect("Loading the engine strings failed");
+    locale::set_localizer(localizer);
+
+    if cli.validate_script {
+        validate_script(
+            &adv_assets.scenario,
+            cli.assets_dir.as_deref().unwrap_or(Path::new(".")),
+            cli.strict,
+        )
+        .expect("Script validation failed");
+    }
+
     let (width, height) = adv_assets.scenario.get_screen_size();
 
     let event_loop = EventLoop::new().unwrap();
-    let window = WindowBuilder::new()
+    let mut window_builder = WindowBuilder::new()
         .with_inner_size(LogicalSize::new(width, height))
+        .with_resizable(cli.resizable);
+    if cli.resizable {
+        let (min_width, min_height) = AspectLock::new((width, height)).min_size();
+        window_builder =
+            window_builder.with_min_inner_size(LogicalSize::new(min_width, min_height));
+    }
+    let window = window_builder
         .with_maximized(false)
         .with_position(LogicalPosition::new(width, 0))
         .with_title(adv_assets.scenario.get_title())
@@ -372,7 +677,10 @@ pub async fn run(cli: Cli) {
                                         ..
                                     },
                                 ..
-                            } => target.exit(),
+                            } => {
+                                state.shutdown();
+                                target.exit();
+                            }
                             WindowEvent::KeyboardInput {
                                 event:
                                     KeyEvent {
@@ -401,11 +709,35 @@ pub async fn run(cli: Cli) {
                                 if let Some(new_size) =
                                     window.request_inner_size(PhysicalSize::new(1920, 1080))
                                 {
-                                    state.resize(new_size.into());
+                                    if state.resize(new_size.into()) {
+                                        window.request_redraw();
+                                    }
                                 }
                             }
                             WindowEvent::Resized(physical_size) => {
-                                state.resize((*physical_size).into());
+                                let mut new_size = (*physical_size).into();
+                                // fullscreen and maximized windows can't choose their size, they are pillarboxed instead
+                                if window.fullscreen().is_none() && !window.is_maximized() {
+                                    if let Some((width, height)) = state.snap_window_size(new_size)
+                                    {
+                                        // if the request is applied asynchronously, another Resized event follows
+                                        if let Some(applied) = window
+                                            .request_inner_size(PhysicalSize::new(width, height))
+                                        {
+                                            new_size = applied.into();
+                                        }
+                                    }
+                                }
+                                if state.resize(new_size) {
+                                    window.request_redraw();
+                                }
+                            }
+                            WindowEvent::ScaleFactorChanged { .. } => {
+                                // the size may already have changed before the Resized event arrives,
+                                // it can be zero as well if this happens while minimized
+                                if state.resize(window.inner_size().into()) {
+                                    window.request_redraw();
+                                }
                             }
                             WindowEvent::RedrawRequested => {
                                 state.update();